@@ -0,0 +1,14 @@
+CREATE TABLE default_versions (
+  crate_id INTEGER PRIMARY KEY NOT NULL REFERENCES crates ON DELETE CASCADE,
+  version_id INTEGER NOT NULL REFERENCES versions ON DELETE CASCADE
+);
+
+CREATE INDEX index_default_versions_version_id ON default_versions (version_id);
+
+-- Best-effort backfill: the highest non-yanked version of each crate, falling
+-- back to the highest version if all of them are yanked. Prerelease handling is
+-- refined by the `sync_default_versions` background job on its first run.
+INSERT INTO default_versions (crate_id, version_id)
+SELECT DISTINCT ON (crate_id) crate_id, id
+FROM versions
+ORDER BY crate_id, yanked, to_semver_no_prerelease(num) DESC NULLS LAST, id DESC;
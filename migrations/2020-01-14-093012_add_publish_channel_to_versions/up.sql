@@ -0,0 +1,7 @@
+-- 0 = registry (`cargo publish`), 1 = trusted publishing, 2 = admin import.
+-- NULL means that the channel is unknown.
+ALTER TABLE versions ADD COLUMN publish_channel INTEGER;
+
+-- Best-effort backfill: versions with a recorded publisher were uploaded via
+-- `cargo publish`. Older versions are left as unknown.
+UPDATE versions SET publish_channel = 0 WHERE published_by IS NOT NULL;
@@ -0,0 +1,8 @@
+CREATE TABLE crate_policies (
+  crate_id INTEGER PRIMARY KEY NOT NULL REFERENCES crates ON DELETE CASCADE,
+  version_id INTEGER NOT NULL REFERENCES versions ON DELETE CASCADE,
+  replaced_by VARCHAR,
+  deprecation_message VARCHAR
+);
+
+CREATE INDEX index_crate_policies_version_id ON crate_policies (version_id);
@@ -0,0 +1 @@
+ALTER TABLE readme_renderings DROP COLUMN textsearchable_index_col;
@@ -0,0 +1,10 @@
+-- Populated by the `render_and_upload_readme` background job, so READMEs that
+-- were rendered before this migration aren't searchable until the next release
+-- of their crate.
+ALTER TABLE readme_renderings
+  ADD COLUMN textsearchable_index_col TSVECTOR NOT NULL DEFAULT ''::tsvector;
+
+-- The column only holds empty vectors yet, so building the index is quick.
+-- migration-safety: allow index-not-concurrent
+CREATE INDEX index_readme_renderings_textsearchable_index_col
+  ON readme_renderings USING gin (textsearchable_index_col);
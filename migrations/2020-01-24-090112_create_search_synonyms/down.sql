@@ -0,0 +1,2 @@
+DROP AGGREGATE tsquery_or_agg(tsquery);
+DROP TABLE search_synonyms;
@@ -0,0 +1,11 @@
+CREATE TABLE search_synonyms (
+    term TEXT PRIMARY KEY,
+    synonyms TEXT[] NOT NULL
+);
+
+-- Combines the tsqueries of all rows with `||`, so that a search matches
+-- if any of its synonym-expanded variants matches.
+CREATE AGGREGATE tsquery_or_agg(tsquery) (
+    SFUNC = tsquery_or,
+    STYPE = tsquery
+);
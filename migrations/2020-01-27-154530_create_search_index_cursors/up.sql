@@ -0,0 +1,4 @@
+CREATE TABLE search_index_cursors (
+    backend TEXT PRIMARY KEY,
+    synced_until TIMESTAMP NOT NULL
+);
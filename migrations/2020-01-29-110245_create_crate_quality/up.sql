@@ -0,0 +1,15 @@
+CREATE TABLE crate_quality (
+    crate_id INTEGER PRIMARY KEY REFERENCES crates (id) ON DELETE CASCADE,
+    has_documentation BOOLEAN NOT NULL DEFAULT FALSE,
+    readme_length INTEGER NOT NULL DEFAULT 0,
+    -- Whether the most recently published version contains a `tests`
+    -- directory. Unknown for crates that weren't published since this column
+    -- was added.
+    has_tests BOOLEAN,
+    releases_last_year INTEGER NOT NULL DEFAULT 0,
+    yanked_ratio REAL NOT NULL DEFAULT 0,
+    score REAL NOT NULL DEFAULT 0,
+    computed_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX crate_quality_score ON crate_quality (score);
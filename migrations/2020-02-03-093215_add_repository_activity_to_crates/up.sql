@@ -0,0 +1,38 @@
+ALTER TABLE crates
+    ADD COLUMN repository_last_commit_at TIMESTAMP,
+    ADD COLUMN repository_checked_at TIMESTAMP;
+
+-- The column is empty and `crates` is small, so building the index is quick.
+-- migration-safety: allow index-not-concurrent
+CREATE INDEX index_crates_repository_last_commit_at ON crates (repository_last_commit_at);
+
+-- Recording repository activity shouldn't count as an update of the crate
+CREATE FUNCTION set_crates_updated_at() RETURNS trigger AS $$
+DECLARE
+    new_downloads integer;
+    new_repository_last_commit_at timestamp;
+    new_repository_checked_at timestamp;
+BEGIN
+    new_downloads := NEW.downloads;
+    new_repository_last_commit_at := NEW.repository_last_commit_at;
+    new_repository_checked_at := NEW.repository_checked_at;
+    OLD.downloads := NEW.downloads;
+    OLD.repository_last_commit_at := NEW.repository_last_commit_at;
+    OLD.repository_checked_at := NEW.repository_checked_at;
+    IF (
+        NEW IS DISTINCT FROM OLD AND
+        NEW.updated_at IS NOT DISTINCT FROM OLD.updated_at
+    ) THEN
+        NEW.updated_at = CURRENT_TIMESTAMP;
+    END IF;
+    NEW.downloads := new_downloads;
+    NEW.repository_last_commit_at := new_repository_last_commit_at;
+    NEW.repository_checked_at := new_repository_checked_at;
+    RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER trigger_crates_set_updated_at ON crates;
+CREATE TRIGGER trigger_crates_set_updated_at BEFORE UPDATE
+ON crates
+FOR EACH ROW EXECUTE PROCEDURE set_crates_updated_at();
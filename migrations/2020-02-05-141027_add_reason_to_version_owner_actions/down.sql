@@ -0,0 +1,2 @@
+DROP INDEX index_version_owner_actions_time;
+ALTER TABLE version_owner_actions DROP COLUMN reason;
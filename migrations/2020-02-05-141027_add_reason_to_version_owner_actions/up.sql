@@ -0,0 +1,5 @@
+ALTER TABLE version_owner_actions ADD COLUMN reason VARCHAR;
+-- `version_owner_actions` only goes back to early 2019, so building the index
+-- is quick.
+-- migration-safety: allow index-not-concurrent
+CREATE INDEX index_version_owner_actions_time ON version_owner_actions (time);
@@ -0,0 +1 @@
+DROP TABLE yank_events;
@@ -0,0 +1,17 @@
+CREATE TABLE yank_events (
+    id SERIAL PRIMARY KEY,
+    version_id INTEGER NOT NULL REFERENCES versions (id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES users (id),
+    api_token_id INTEGER REFERENCES api_tokens (id),
+    yanked BOOLEAN NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT now()
+);
+
+CREATE INDEX index_yank_events_version_id_created_at ON yank_events (version_id, created_at);
+
+-- Seed the history with the yanks and unyanks recorded so far
+INSERT INTO yank_events (version_id, user_id, api_token_id, yanked, created_at)
+SELECT version_id, user_id, api_token_id, action = 1, time
+FROM version_owner_actions
+WHERE action IN (1, 2)
+ORDER BY id;
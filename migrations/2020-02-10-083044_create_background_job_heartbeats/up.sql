@@ -0,0 +1,8 @@
+CREATE TABLE background_job_heartbeats (
+    id SERIAL PRIMARY KEY,
+    job_type TEXT NOT NULL,
+    worker TEXT NOT NULL,
+    started_at TIMESTAMP NOT NULL DEFAULT now(),
+    heartbeat_at TIMESTAMP NOT NULL DEFAULT now(),
+    cancel_requested_at TIMESTAMP
+);
@@ -0,0 +1,2 @@
+-- Jobs enqueued before payloads were versioned use the first version
+ALTER TABLE background_jobs ADD COLUMN payload_version INTEGER NOT NULL DEFAULT 1;
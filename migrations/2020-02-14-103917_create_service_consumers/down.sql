@@ -0,0 +1,2 @@
+DROP TABLE service_consumer_usage;
+DROP TABLE service_consumers;
@@ -0,0 +1,17 @@
+CREATE TABLE service_consumers (
+  id SERIAL PRIMARY KEY,
+  name VARCHAR NOT NULL UNIQUE,
+  contact_email VARCHAR NOT NULL,
+  api_key VARCHAR NOT NULL UNIQUE DEFAULT random_string(32),
+  requests_per_hour INTEGER NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  last_used_at TIMESTAMP,
+  revoked BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE TABLE service_consumer_usage (
+  service_consumer_id INTEGER NOT NULL REFERENCES service_consumers ON DELETE CASCADE,
+  hour TIMESTAMP NOT NULL,
+  requests INTEGER NOT NULL DEFAULT 0,
+  PRIMARY KEY (service_consumer_id, hour)
+);
@@ -0,0 +1,7 @@
+-- The sitemap files currently in the object storage, which are listed by
+-- `/sitemap.xml`
+CREATE TABLE sitemaps (
+  file_name VARCHAR PRIMARY KEY,
+  url_count INTEGER NOT NULL,
+  generated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
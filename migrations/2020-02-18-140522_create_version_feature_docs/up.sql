@@ -0,0 +1,8 @@
+-- The descriptions of features, taken from the `## ` comments above them in
+-- the `[features]` table of the published `Cargo.toml`
+CREATE TABLE version_feature_docs (
+  version_id INTEGER NOT NULL REFERENCES versions (id) ON DELETE CASCADE,
+  feature VARCHAR NOT NULL,
+  description TEXT NOT NULL,
+  PRIMARY KEY (version_id, feature)
+);
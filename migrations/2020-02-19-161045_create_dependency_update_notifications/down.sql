@@ -0,0 +1,2 @@
+DROP TABLE dependency_update_notifications;
+DROP TABLE dependency_update_subscriptions;
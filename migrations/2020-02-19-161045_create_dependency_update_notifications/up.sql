@@ -0,0 +1,24 @@
+-- Owners who want to be notified about semver-incompatible releases of the
+-- dependencies of their crates
+CREATE TABLE dependency_update_subscriptions (
+  crate_id INTEGER NOT NULL REFERENCES crates (id) ON DELETE CASCADE,
+  user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (crate_id, user_id)
+);
+
+-- Notifications waiting for the next daily email, and recently sent ones,
+-- which prevent a release from being reported twice
+CREATE TABLE dependency_update_notifications (
+  id SERIAL PRIMARY KEY,
+  user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  crate_id INTEGER NOT NULL REFERENCES crates (id) ON DELETE CASCADE,
+  dependency_version_id INTEGER NOT NULL REFERENCES versions (id) ON DELETE CASCADE,
+  requirement VARCHAR NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  sent_at TIMESTAMP,
+  UNIQUE (user_id, crate_id, dependency_version_id)
+);
+
+CREATE INDEX dependency_update_notifications_unsent
+  ON dependency_update_notifications (user_id) WHERE sent_at IS NULL;
@@ -0,0 +1,6 @@
+-- The SHA-256 checksum of each crate's index file as of its last push
+CREATE TABLE index_checksums (
+  crate_id INTEGER PRIMARY KEY REFERENCES crates (id) ON DELETE CASCADE,
+  checksum VARCHAR NOT NULL,
+  updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
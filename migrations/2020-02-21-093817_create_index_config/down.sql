@@ -0,0 +1,2 @@
+DROP TABLE index_settings;
+DROP TABLE index_download_endpoints;
@@ -0,0 +1,12 @@
+-- Additional download endpoints advertised in the `config.json` of the
+-- index, in addition to the `dl` URL of the API
+CREATE TABLE index_download_endpoints (
+  url VARCHAR PRIMARY KEY,
+  priority INTEGER NOT NULL DEFAULT 0
+);
+
+-- Other values of the `config.json` of the index, like `checksum-url`
+CREATE TABLE index_settings (
+  name VARCHAR PRIMARY KEY,
+  value VARCHAR NOT NULL
+);
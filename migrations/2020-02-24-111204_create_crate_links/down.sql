@@ -0,0 +1,2 @@
+DROP TABLE crate_link_events;
+DROP TABLE crate_links;
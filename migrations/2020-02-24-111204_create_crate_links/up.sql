@@ -0,0 +1,19 @@
+-- Extra links of a crate which aren't part of its manifest, set by its owners
+CREATE TABLE crate_links (
+  crate_id INTEGER NOT NULL REFERENCES crates (id) ON DELETE CASCADE,
+  kind VARCHAR NOT NULL,
+  url VARCHAR NOT NULL,
+  PRIMARY KEY (crate_id, kind)
+);
+
+-- Every change of a link, `url` is NULL if the link was removed
+CREATE TABLE crate_link_events (
+  id SERIAL PRIMARY KEY,
+  crate_id INTEGER NOT NULL REFERENCES crates (id) ON DELETE CASCADE,
+  user_id INTEGER NOT NULL REFERENCES users (id),
+  kind VARCHAR NOT NULL,
+  url VARCHAR,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX crate_link_events_crate_id ON crate_link_events (crate_id);
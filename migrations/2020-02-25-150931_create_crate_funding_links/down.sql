@@ -0,0 +1 @@
+DROP TABLE crate_funding_links;
@@ -0,0 +1,7 @@
+-- Funding links found in the most recently published version of a crate
+CREATE TABLE crate_funding_links (
+  crate_id INTEGER NOT NULL REFERENCES crates (id) ON DELETE CASCADE,
+  platform VARCHAR NOT NULL,
+  url VARCHAR NOT NULL,
+  PRIMARY KEY (crate_id, url)
+);
@@ -0,0 +1,6 @@
+-- Precomputed payloads of `GET /api/v1/crates/:crate_id/full`
+CREATE TABLE crate_pages (
+  crate_id INTEGER NOT NULL PRIMARY KEY REFERENCES crates (id) ON DELETE CASCADE,
+  payload JSONB NOT NULL,
+  generated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1 @@
+DROP TABLE squatting_reports;
@@ -0,0 +1,19 @@
+-- Reports of crate names which look squatted. The `check_squatting_reports`
+-- job sets `status` to `candidate` or `dismissed` and records the
+-- heuristics it evaluated, admins set `outcome` when they resolve a report.
+CREATE TABLE squatting_reports (
+  id SERIAL PRIMARY KEY,
+  crate_id INTEGER NOT NULL REFERENCES crates (id) ON DELETE CASCADE,
+  reporter_id INTEGER NOT NULL REFERENCES users (id),
+  reason VARCHAR NOT NULL,
+  status VARCHAR NOT NULL DEFAULT 'pending',
+  heuristics JSONB,
+  checked_at TIMESTAMP,
+  outcome VARCHAR,
+  resolved_at TIMESTAMP,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  UNIQUE (crate_id, reporter_id)
+);
+
+CREATE INDEX squatting_reports_status ON squatting_reports (status);
+CREATE INDEX squatting_reports_reporter_id ON squatting_reports (reporter_id, created_at);
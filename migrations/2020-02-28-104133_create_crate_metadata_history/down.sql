@@ -0,0 +1 @@
+DROP TABLE crate_metadata_history;
@@ -0,0 +1,13 @@
+-- Every change of the crate-level metadata, with the values of the field
+-- before and after the change as JSON
+CREATE TABLE crate_metadata_history (
+  id SERIAL PRIMARY KEY,
+  crate_id INTEGER NOT NULL REFERENCES crates (id) ON DELETE CASCADE,
+  user_id INTEGER NOT NULL REFERENCES users (id),
+  field VARCHAR NOT NULL,
+  old_value JSONB NOT NULL,
+  new_value JSONB NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX crate_metadata_history_crate_id ON crate_metadata_history (crate_id);
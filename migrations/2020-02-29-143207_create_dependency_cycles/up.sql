@@ -0,0 +1,15 @@
+-- Dependency cycles found by the `detect_dependency_cycles` job. `crates`
+-- lists the crates of the cycle starting with the smallest name, and `kinds`
+-- the kind of the dependency from each crate on the next one. `crate_id` and
+-- `version_id` are the publish which introduced the cycle.
+CREATE TABLE dependency_cycles (
+  id SERIAL PRIMARY KEY,
+  crate_id INTEGER NOT NULL REFERENCES crates (id) ON DELETE CASCADE,
+  version_id INTEGER NOT NULL REFERENCES versions (id) ON DELETE CASCADE,
+  crates TEXT[] NOT NULL,
+  kinds TEXT[] NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  UNIQUE (crates)
+);
+
+CREATE INDEX dependency_cycles_crate_id ON dependency_cycles (crate_id);
@@ -0,0 +1 @@
+DROP TABLE outreach_emails;
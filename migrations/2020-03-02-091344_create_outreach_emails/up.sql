@@ -0,0 +1,11 @@
+-- Emails sent to crate owners with `crates-io-admin notify`. Every user gets
+-- at most one email per campaign.
+CREATE TABLE outreach_emails (
+  id SERIAL PRIMARY KEY,
+  campaign VARCHAR NOT NULL,
+  user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  email VARCHAR NOT NULL,
+  crates TEXT[] NOT NULL,
+  sent_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  UNIQUE (campaign, user_id)
+);
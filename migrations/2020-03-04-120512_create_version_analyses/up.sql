@@ -0,0 +1,7 @@
+-- The reports of the analyzers run over the crate file of a version.
+CREATE TABLE version_analyses (
+  version_id INTEGER NOT NULL REFERENCES versions (id) ON DELETE CASCADE,
+  analyzer VARCHAR NOT NULL,
+  result JSONB NOT NULL,
+  PRIMARY KEY (version_id, analyzer)
+);
@@ -0,0 +1,13 @@
+-- Publishes with `?queued=true`, whose crate file is uploaded and added to the
+-- index by a background job. Until then, the crate file is staged in the
+-- storage at `staged_path`.
+CREATE TABLE publish_jobs (
+  id SERIAL PRIMARY KEY,
+  version_id INTEGER NOT NULL UNIQUE REFERENCES versions (id) ON DELETE CASCADE,
+  user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  state VARCHAR NOT NULL DEFAULT 'queued',
+  error VARCHAR,
+  staged_path VARCHAR,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,11 @@
+-- Statistics on the release cadence of crates, recomputed nightly by the
+-- `compute_release_stats` job.
+CREATE TABLE crate_release_stats (
+  crate_id INTEGER PRIMARY KEY REFERENCES crates (id) ON DELETE CASCADE,
+  releases INTEGER NOT NULL,
+  avg_days_between_releases REAL,
+  longest_gap_days REAL,
+  releases_per_year REAL NOT NULL,
+  yank_rate REAL NOT NULL,
+  computed_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
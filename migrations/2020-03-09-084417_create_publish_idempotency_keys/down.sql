@@ -0,0 +1 @@
+DROP TABLE publish_idempotency_keys;
@@ -0,0 +1,14 @@
+-- Responses to publishes with an `Idempotency-Key` header, which retries of
+-- the same publish get again instead of an error. The key is claimed at the
+-- start of the publish, and the checksum and the response are recorded at its
+-- end, in the same transaction.
+CREATE TABLE publish_idempotency_keys (
+  user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  key VARCHAR NOT NULL,
+  crate_name VARCHAR NOT NULL,
+  version VARCHAR NOT NULL,
+  checksum VARCHAR,
+  response JSONB,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (user_id, key)
+);
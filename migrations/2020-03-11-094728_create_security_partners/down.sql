@@ -0,0 +1,5 @@
+DROP TABLE partner_audit_log;
+DROP TABLE version_quarantines;
+DROP TABLE version_verdicts;
+DROP TABLE version_checksums;
+DROP TABLE security_partners;
@@ -0,0 +1,51 @@
+-- Security vendors which submit verdicts about published versions through
+-- the partner API, authenticated by `api_key` in the `X-Partner-Key` header.
+CREATE TABLE security_partners (
+  id SERIAL PRIMARY KEY,
+  name VARCHAR NOT NULL UNIQUE,
+  contact_email VARCHAR NOT NULL,
+  api_key VARCHAR NOT NULL UNIQUE DEFAULT random_string(32),
+  verdicts_per_day INTEGER NOT NULL,
+  auto_quarantine BOOLEAN NOT NULL DEFAULT FALSE,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  revoked BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+-- The hex encoded SHA-256 checksum of the crate file of each version, as
+-- recorded when it was published.
+CREATE TABLE version_checksums (
+  version_id INTEGER PRIMARY KEY REFERENCES versions (id) ON DELETE CASCADE,
+  checksum VARCHAR NOT NULL
+);
+
+CREATE TABLE version_verdicts (
+  id SERIAL PRIMARY KEY,
+  partner_id INTEGER NOT NULL REFERENCES security_partners (id) ON DELETE CASCADE,
+  version_id INTEGER NOT NULL REFERENCES versions (id) ON DELETE CASCADE,
+  checksum VARCHAR NOT NULL,
+  verdict VARCHAR NOT NULL,
+  details VARCHAR,
+  status VARCHAR NOT NULL,
+  reviewed_at TIMESTAMP,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX version_verdicts_partner_id_created_at ON version_verdicts (partner_id, created_at);
+CREATE INDEX version_verdicts_status ON version_verdicts (status);
+
+-- Versions which can't be downloaded until a verdict about them is reviewed.
+CREATE TABLE version_quarantines (
+  version_id INTEGER PRIMARY KEY REFERENCES versions (id) ON DELETE CASCADE,
+  verdict_id INTEGER REFERENCES version_verdicts (id) ON DELETE SET NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Every request to the partner API and every review of a verdict.
+CREATE TABLE partner_audit_log (
+  id SERIAL PRIMARY KEY,
+  partner_id INTEGER NOT NULL REFERENCES security_partners (id) ON DELETE CASCADE,
+  action VARCHAR NOT NULL,
+  version_id INTEGER REFERENCES versions (id) ON DELETE SET NULL,
+  details VARCHAR NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,4 @@
+DROP TABLE signed_tree_heads;
+DROP TRIGGER trigger_ensure_transparency_log_is_append_only ON transparency_log_entries;
+DROP FUNCTION ensure_transparency_log_is_append_only();
+DROP TABLE transparency_log_entries;
@@ -0,0 +1,41 @@
+-- An append-only log of the crate file of every published version. Entries
+-- are added when a version is published and get their position in the
+-- Merkle tree, `leaf_index`, when the next tree head is signed.
+CREATE TABLE transparency_log_entries (
+  id SERIAL PRIMARY KEY,
+  leaf_index INTEGER UNIQUE,
+  crate_name VARCHAR NOT NULL,
+  num VARCHAR NOT NULL,
+  checksum VARCHAR NOT NULL,
+  published_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Once an entry is part of the tree, changing or removing it would change
+-- the root hashes of all tree heads signed since.
+CREATE FUNCTION ensure_transparency_log_is_append_only() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        RAISE EXCEPTION 'transparency log entries cannot be deleted';
+    END IF;
+    IF OLD.leaf_index IS NOT NULL
+        OR (NEW.crate_name, NEW.num, NEW.checksum, NEW.published_at)
+            IS DISTINCT FROM (OLD.crate_name, OLD.num, OLD.checksum, OLD.published_at) THEN
+        RAISE EXCEPTION 'transparency log entries cannot be changed';
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_ensure_transparency_log_is_append_only
+BEFORE UPDATE OR DELETE ON transparency_log_entries
+FOR EACH ROW EXECUTE PROCEDURE ensure_transparency_log_is_append_only();
+
+-- The signed root hashes of the tree of the first `tree_size` entries.
+CREATE TABLE signed_tree_heads (
+  id SERIAL PRIMARY KEY,
+  tree_size INTEGER NOT NULL,
+  root_hash VARCHAR NOT NULL,
+  signature VARCHAR NOT NULL,
+  public_key VARCHAR NOT NULL,
+  signed_at TIMESTAMP NOT NULL
+);
@@ -0,0 +1,9 @@
+-- Sigstore bundles signing the crate files of versions, as uploaded by their
+-- owners. `signer` is the identity of the signing certificate.
+CREATE TABLE version_signatures (
+  version_id INTEGER PRIMARY KEY REFERENCES versions (id) ON DELETE CASCADE,
+  bundle VARCHAR NOT NULL,
+  signer VARCHAR,
+  uploaded_by INTEGER NOT NULL REFERENCES users (id),
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,2 @@
+-- 0 = gzip, 1 = zstd. All versions published so far are gzip compressed.
+ALTER TABLE versions ADD COLUMN compression INTEGER NOT NULL DEFAULT 0;
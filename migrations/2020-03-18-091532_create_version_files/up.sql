@@ -0,0 +1,8 @@
+-- The files in the crate file of a version, as they were when it was published.
+CREATE TABLE version_files (
+  version_id INTEGER NOT NULL REFERENCES versions (id) ON DELETE CASCADE,
+  path VARCHAR NOT NULL,
+  size BIGINT NOT NULL,
+  sha256 VARCHAR NOT NULL,
+  PRIMARY KEY (version_id, path)
+);
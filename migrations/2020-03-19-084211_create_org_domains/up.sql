@@ -0,0 +1,12 @@
+-- Domains which GitHub organizations proved to control with a DNS TXT record.
+CREATE TABLE org_domains (
+  id SERIAL PRIMARY KEY,
+  org VARCHAR NOT NULL,
+  domain VARCHAR NOT NULL,
+  challenge VARCHAR NOT NULL,
+  requested_by INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  created_at TIMESTAMP NOT NULL DEFAULT now(),
+  checked_at TIMESTAMP,
+  verified_at TIMESTAMP,
+  UNIQUE (org, domain)
+);
@@ -0,0 +1,2 @@
+DROP TABLE version_scan_holds;
+DROP TABLE version_scan_results;
@@ -0,0 +1,17 @@
+-- What the `executables` analyzer found in the crate file of a version.
+CREATE TABLE version_scan_results (
+  version_id INTEGER NOT NULL REFERENCES versions (id) ON DELETE CASCADE,
+  path VARCHAR NOT NULL,
+  kind VARCHAR NOT NULL,
+  detail VARCHAR NOT NULL,
+  PRIMARY KEY (version_id, path)
+);
+
+-- Versions with findings which are kept out of the index until an admin
+-- reviewed them, with the index entry and queued publish to continue with.
+CREATE TABLE version_scan_holds (
+  version_id INTEGER PRIMARY KEY REFERENCES versions (id) ON DELETE CASCADE,
+  index_entry JSONB NOT NULL,
+  publish_job_id INTEGER REFERENCES publish_jobs (id) ON DELETE SET NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT now()
+);
@@ -0,0 +1 @@
+DROP TABLE ecosystem_stats;
@@ -0,0 +1,14 @@
+-- Daily totals of the whole registry, rolled up by the
+-- `compute_ecosystem_stats` job and served by `GET /stats/ecosystem`.
+--
+-- Users don't record when they signed up, so their total is only known for
+-- the days the job ran on.
+CREATE TABLE ecosystem_stats (
+  date DATE PRIMARY KEY,
+  total_crates INTEGER NOT NULL,
+  new_crates INTEGER NOT NULL,
+  new_versions INTEGER NOT NULL,
+  downloads BIGINT NOT NULL,
+  total_users INTEGER,
+  computed_at TIMESTAMP NOT NULL DEFAULT now()
+);
@@ -0,0 +1,2 @@
+ALTER TABLE versions DROP COLUMN license_ids;
+ALTER TABLE versions DROP COLUMN license_expression;
@@ -0,0 +1,9 @@
+-- The license of a version as a normalized SPDX expression, and the licenses
+-- it mentions. Versions published before are normalized by
+-- `crates-io-admin normalize-licenses`.
+ALTER TABLE versions ADD COLUMN license_expression VARCHAR;
+ALTER TABLE versions ADD COLUMN license_ids TEXT[];
+
+-- The column is empty, so building the index only takes a moment.
+-- migration-safety: allow index-not-concurrent
+CREATE INDEX versions_license_ids ON versions USING GIN (license_ids);
@@ -0,0 +1,10 @@
+-- The result of the last check that a version can be installed through the
+-- public index and download URLs, recorded by the `verify_installability`
+-- job a few minutes after it was published.
+CREATE TABLE version_install_checks (
+  version_id INTEGER PRIMARY KEY REFERENCES versions (id) ON DELETE CASCADE,
+  passed BOOLEAN NOT NULL,
+  error VARCHAR,
+  attempts INTEGER NOT NULL DEFAULT 1,
+  checked_at TIMESTAMP NOT NULL DEFAULT now()
+);
@@ -0,0 +1,15 @@
+-- Days on which a crate was downloaded far more often than during the weeks
+-- before, detected by the `detect_download_anomalies` job. `baseline` is the
+-- average of the daily downloads before, and `score` how many deviations
+-- above it the downloads of the day are.
+CREATE TABLE download_anomalies (
+  crate_id INTEGER NOT NULL REFERENCES crates (id) ON DELETE CASCADE,
+  date DATE NOT NULL,
+  downloads BIGINT NOT NULL,
+  baseline DOUBLE PRECISION NOT NULL,
+  score DOUBLE PRECISION NOT NULL,
+  detected_at TIMESTAMP NOT NULL DEFAULT now(),
+  PRIMARY KEY (crate_id, date)
+);
+
+CREATE INDEX download_anomalies_date ON download_anomalies (date);
@@ -0,0 +1 @@
+DROP TABLE deprecated_endpoint_usage;
@@ -0,0 +1,13 @@
+-- How often deprecated endpoints were requested on each day, by the service
+-- consumer which made the requests, or by their `User-Agent` for requests
+-- without a service key. Counted by the `DeprecationNotices` middleware.
+CREATE TABLE deprecated_endpoint_usage (
+  endpoint VARCHAR NOT NULL,
+  consumer VARCHAR NOT NULL,
+  date DATE NOT NULL DEFAULT CURRENT_DATE,
+  requests INTEGER NOT NULL DEFAULT 1,
+  last_used_at TIMESTAMP NOT NULL DEFAULT now(),
+  PRIMARY KEY (endpoint, consumer, date)
+);
+
+CREATE INDEX deprecated_endpoint_usage_date ON deprecated_endpoint_usage (date);
@@ -0,0 +1,11 @@
+-- Upload size limits of crates set by admins through the API, which take
+-- precedence over `crates.max_upload_size` and the global limit until they
+-- expire.
+CREATE TABLE crate_upload_limits (
+  crate_id INTEGER PRIMARY KEY REFERENCES crates (id) ON DELETE CASCADE,
+  max_upload_size INTEGER NOT NULL,
+  reason VARCHAR NOT NULL,
+  expires_at TIMESTAMP,
+  created_by INTEGER NOT NULL REFERENCES users (id),
+  created_at TIMESTAMP NOT NULL DEFAULT now()
+);
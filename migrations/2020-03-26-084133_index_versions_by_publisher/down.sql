@@ -0,0 +1 @@
+DROP INDEX versions_published_by_created_at;
@@ -0,0 +1,12 @@
+-- Lists the versions a user published, most recent first, for
+-- `GET /users/:user_id/publishes`.
+--
+-- Building it scans all of `versions`, and diesel can't build it
+-- concurrently since it runs migrations in a transaction. In production, it's
+-- created by hand before deploying this migration:
+--
+--   CREATE INDEX CONCURRENTLY IF NOT EXISTS versions_published_by_created_at
+--     ON versions (published_by, created_at);
+--
+-- migration-safety: allow index-not-concurrent
+CREATE INDEX IF NOT EXISTS versions_published_by_created_at ON versions (published_by, created_at);
@@ -0,0 +1,3 @@
+-- The headings of the rendered README, as a list of `{level, slug, title}`
+-- objects. `NULL` for READMEs rendered before headings were extracted.
+ALTER TABLE readme_renderings ADD COLUMN toc JSONB;
@@ -0,0 +1,7 @@
+-- Environment variables set with `crates-io-admin set-config`, which take
+-- precedence over the process environment and the configuration profiles
+CREATE TABLE config_overrides (
+  name VARCHAR PRIMARY KEY,
+  value VARCHAR NOT NULL,
+  updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,12 @@
+-- How long it took for each version to reach the index and the CDN in front
+-- of it, served by `GET /api/private/metrics/index-sync`
+CREATE TABLE index_syncs (
+  version_id INTEGER PRIMARY KEY REFERENCES versions (id) ON DELETE CASCADE,
+  queued_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  indexed_at TIMESTAMP,
+  visible_at TIMESTAMP,
+  index_failures INTEGER NOT NULL DEFAULT 0,
+  visibility_failures INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE INDEX index_syncs_pending ON index_syncs (queued_at) WHERE visible_at IS NULL;
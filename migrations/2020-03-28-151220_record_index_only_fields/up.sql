@@ -0,0 +1,5 @@
+-- The fields of index entries which weren't stored anywhere but the index,
+-- so that `reconcile_index` can rebuild index files from the database. They
+-- are unknown for the versions published before.
+ALTER TABLE versions ADD COLUMN links VARCHAR;
+ALTER TABLE dependencies ADD COLUMN explicit_name VARCHAR;
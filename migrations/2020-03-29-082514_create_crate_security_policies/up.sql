@@ -0,0 +1,9 @@
+-- The security policy file found in the crate file of the most recently
+-- published version of each crate, and where it can be read
+CREATE TABLE crate_security_policies (
+  crate_id INTEGER PRIMARY KEY REFERENCES crates (id) ON DELETE CASCADE,
+  version_id INTEGER NOT NULL REFERENCES versions (id) ON DELETE CASCADE,
+  path VARCHAR NOT NULL,
+  url VARCHAR NOT NULL,
+  updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
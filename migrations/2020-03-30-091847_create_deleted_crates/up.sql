@@ -0,0 +1,11 @@
+-- Tombstones of crates and versions removed from the registry, which are
+-- listed publicly so that mirrors can remove them as well
+CREATE TABLE deleted_crates (
+  id SERIAL PRIMARY KEY,
+  name VARCHAR NOT NULL,
+  version VARCHAR,
+  reason VARCHAR NOT NULL CHECK (reason IN ('malware', 'legal', 'policy', 'owner_request', 'other')),
+  deleted_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX deleted_crates_deleted_at ON deleted_crates (deleted_at);
@@ -0,0 +1,9 @@
+-- The squashes of the history of the git index by the `squash_index` job
+CREATE TABLE index_squashes (
+  id SERIAL PRIMARY KEY,
+  previous_head VARCHAR NOT NULL,
+  squashed_commit VARCHAR NOT NULL,
+  snapshot_branch VARCHAR NOT NULL,
+  commit_count INTEGER NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,8 @@
+-- When the GitHub profile of each user was last refreshed by the
+-- `refresh_user_profiles` job, and the ETag of the response, which makes the
+-- next request conditional
+CREATE TABLE user_profile_syncs (
+  user_id INTEGER PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+  etag VARCHAR,
+  checked_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
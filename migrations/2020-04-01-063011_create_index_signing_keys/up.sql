@@ -0,0 +1,7 @@
+-- The keys the commits to the index were signed with, and since when, which
+-- is published as the `allowed_signers` file of the index
+CREATE TABLE index_signing_keys (
+  fingerprint VARCHAR PRIMARY KEY,
+  public_key VARCHAR NOT NULL,
+  first_used_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
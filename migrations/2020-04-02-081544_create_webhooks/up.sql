@@ -0,0 +1,26 @@
+-- Endpoints of service consumers which are notified of yanked, unyanked and
+-- deleted versions, and the notifications sent to them
+CREATE TABLE webhooks (
+  id SERIAL PRIMARY KEY,
+  service_consumer_id INTEGER NOT NULL REFERENCES service_consumers ON DELETE CASCADE,
+  url VARCHAR NOT NULL,
+  secret VARCHAR NOT NULL DEFAULT random_string(32),
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  UNIQUE (service_consumer_id, url)
+);
+
+CREATE TABLE webhook_deliveries (
+  id SERIAL PRIMARY KEY,
+  webhook_id INTEGER NOT NULL REFERENCES webhooks ON DELETE CASCADE,
+  event VARCHAR NOT NULL CHECK (event IN ('yank', 'unyank', 'delete')),
+  payload JSONB NOT NULL,
+  attempts INTEGER NOT NULL DEFAULT 0,
+  last_status INTEGER,
+  last_error VARCHAR,
+  next_attempt_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  delivered_at TIMESTAMP,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX webhook_deliveries_pending ON webhook_deliveries (next_attempt_at)
+  WHERE delivered_at IS NULL;
@@ -0,0 +1,15 @@
+-- How far the dependencies of the default version of each crate are behind
+-- their latest releases, recomputed weekly
+CREATE TABLE crate_dependency_freshness (
+    crate_id INTEGER PRIMARY KEY REFERENCES crates (id) ON DELETE CASCADE,
+    version_id INTEGER NOT NULL REFERENCES versions (id) ON DELETE CASCADE,
+    dependencies INTEGER NOT NULL,
+    outdated_dependencies INTEGER NOT NULL,
+    -- The number of semver incompatible releases the dependencies are behind
+    -- in total
+    releases_behind INTEGER NOT NULL,
+    score REAL NOT NULL,
+    computed_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX crate_dependency_freshness_score ON crate_dependency_freshness (score);
@@ -0,0 +1,9 @@
+-- Duplicate user accounts merged by admins, with what was moved so that a
+-- merge can be reversed
+CREATE TABLE user_merges (
+  id SERIAL PRIMARY KEY,
+  from_user_id INTEGER NOT NULL REFERENCES users ON DELETE CASCADE,
+  into_user_id INTEGER NOT NULL REFERENCES users ON DELETE CASCADE,
+  report JSONB NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
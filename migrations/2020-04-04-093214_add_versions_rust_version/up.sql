@@ -0,0 +1,3 @@
+-- The `package.rust-version` of the manifest, which is written to the index
+-- with `INDEX_V3_FIELDS`. Unknown for the versions published before.
+ALTER TABLE versions ADD COLUMN rust_version VARCHAR;
@@ -0,0 +1,2 @@
+DROP TABLE version_content_matches;
+DROP TABLE version_fingerprints;
@@ -0,0 +1,17 @@
+-- The hashes of the normalized source files of a version, as reported by the
+-- `fingerprint` analyzer, to find crates which copy a popular one.
+CREATE TABLE version_fingerprints (
+  version_id INTEGER PRIMARY KEY REFERENCES versions (id) ON DELETE CASCADE,
+  crate_id INTEGER NOT NULL REFERENCES crates (id) ON DELETE CASCADE,
+  hashes TEXT[] NOT NULL
+);
+CREATE INDEX version_fingerprints_hashes ON version_fingerprints USING GIN (hashes);
+
+-- New crates whose sources are nearly identical to the ones of a version of
+-- a popular crate.
+CREATE TABLE version_content_matches (
+  version_id INTEGER PRIMARY KEY REFERENCES versions (id) ON DELETE CASCADE,
+  matched_version_id INTEGER NOT NULL REFERENCES versions (id) ON DELETE CASCADE,
+  similarity REAL NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT now()
+);
@@ -0,0 +1,6 @@
+-- The targets declared in `package.metadata.crates-io.targets`, for the
+-- `target` filter of the crate search.
+ALTER TABLE versions ADD COLUMN targets TEXT[] NOT NULL DEFAULT '{}';
+-- The column is empty, so building the index only takes a moment.
+-- migration-safety: allow index-not-concurrent
+CREATE INDEX versions_targets ON versions USING GIN (targets);
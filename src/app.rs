@@ -1,5 +1,11 @@
 //! Application-wide components in a struct accessible from each request
 
+use crate::download_cache::DownloadCache;
+use crate::publish_rate_limit::RateLimiter;
+use crate::response_cache::{InMemoryStore, ResponseCache};
+use crate::search_backend::SearchBackend;
+use crate::util::TtlCache;
+use crate::views::EncodableSearchFacets;
 use crate::{db, Config, Env};
 use std::{path::PathBuf, sync::Arc, time::Duration};
 
@@ -38,6 +44,22 @@ pub struct App {
     /// this is either None (in which case any attempt to create an outgoing connection
     /// will panic) or a `Client` configured with a per-test replay proxy.
     pub(crate) http_client: Option<Client>,
+
+    /// The backend for the full text part of crate searches
+    pub search_backend: Arc<dyn SearchBackend>,
+
+    /// Recently computed facet counts of crate searches, keyed by the search
+    /// parameters
+    pub(crate) search_facets: TtlCache<EncodableSearchFacets>,
+
+    /// Limits how many new crates users can publish
+    pub publish_rate_limiter: Arc<dyn RateLimiter>,
+
+    /// Cached responses of the summary and crate endpoints
+    pub(crate) response_cache: ResponseCache,
+
+    /// The versions recently requested through the download endpoint
+    pub(crate) download_cache: DownloadCache,
 }
 
 impl App {
@@ -133,6 +155,14 @@ impl App {
             git_repo_checkout: config.git_repo_checkout.clone(),
             config: config.clone(),
             http_client,
+            search_backend: config.search.build(),
+            search_facets: TtlCache::new(Duration::from_secs(60), 1000),
+            publish_rate_limiter: config.publish_rate_limiter.build(config.publish_rate_limit),
+            response_cache: ResponseCache::new(
+                config.response_cache,
+                Arc::new(InMemoryStore::new(10_000)),
+            ),
+            download_cache: DownloadCache::new(50_000),
         }
     }
 
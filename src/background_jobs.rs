@@ -7,8 +7,17 @@ use swirl::PerformError;
 
 use crate::db::{DieselPool, DieselPooledConn};
 use crate::git::Repository;
+use crate::search_backend::SearchBackend;
 use crate::uploaders::Uploader;
 
+pub use self::heartbeat::{Heartbeat, HEARTBEAT_INTERVAL};
+pub use self::job_tracker::JobTracker;
+pub use self::payload::EnqueueVersioned;
+
+mod heartbeat;
+mod job_tracker;
+pub mod payload;
+
 impl<'a> swirl::db::BorrowedConnection<'a> for DieselPool {
     type Connection = DieselPooledConn<'a>;
 }
@@ -28,6 +37,9 @@ pub struct Environment {
     pub connection_pool: AssertUnwindSafe<DieselPool>,
     pub uploader: Uploader,
     http_client: AssertUnwindSafe<Client>,
+    search_backend: AssertUnwindSafe<Arc<dyn SearchBackend>>,
+    og_image_renderer: Option<String>,
+    job_tracker: Arc<JobTracker>,
 }
 
 // FIXME: AssertUnwindSafe should be `Clone`, this can be replaced with
@@ -39,6 +51,9 @@ impl Clone for Environment {
             connection_pool: AssertUnwindSafe(self.connection_pool.0.clone()),
             uploader: self.uploader.clone(),
             http_client: AssertUnwindSafe(self.http_client.0.clone()),
+            search_backend: AssertUnwindSafe(self.search_backend.0.clone()),
+            og_image_renderer: self.og_image_renderer.clone(),
+            job_tracker: self.job_tracker.clone(),
         }
     }
 }
@@ -49,12 +64,17 @@ impl Environment {
         connection_pool: DieselPool,
         uploader: Uploader,
         http_client: Client,
+        search_backend: Arc<dyn SearchBackend>,
+        og_image_renderer: Option<String>,
     ) -> Self {
         Self::new_shared(
             Arc::new(Mutex::new(index)),
             connection_pool,
             uploader,
             http_client,
+            search_backend,
+            og_image_renderer,
+            Arc::new(JobTracker::default()),
         )
     }
 
@@ -63,12 +83,18 @@ impl Environment {
         connection_pool: DieselPool,
         uploader: Uploader,
         http_client: Client,
+        search_backend: Arc<dyn SearchBackend>,
+        og_image_renderer: Option<String>,
+        job_tracker: Arc<JobTracker>,
     ) -> Self {
         Self {
             index,
             connection_pool: AssertUnwindSafe(connection_pool),
             uploader,
             http_client: AssertUnwindSafe(http_client),
+            search_backend: AssertUnwindSafe(search_backend),
+            og_image_renderer,
+            job_tracker,
         }
     }
 
@@ -86,4 +112,25 @@ impl Environment {
     pub(crate) fn http_client(&self) -> &Client {
         &self.http_client
     }
+
+    pub(crate) fn search_backend(&self) -> &dyn SearchBackend {
+        &**self.search_backend
+    }
+
+    /// The command rendering preview images, see `Config::og_image_renderer`.
+    pub(crate) fn og_image_renderer(&self) -> Option<&str> {
+        self.og_image_renderer.as_ref().map(String::as_str)
+    }
+
+    /// Registers a running job of the given type with the `monitor` and the
+    /// `JobTracker` of the worker, until the returned heartbeat is dropped.
+    ///
+    /// This does not return if the worker is shutting down.
+    pub(crate) fn heartbeat(&self, job_type: &str) -> Result<Heartbeat, PerformError> {
+        Heartbeat::start(
+            self.connection_pool.0.clone(),
+            self.job_tracker.clone(),
+            job_type,
+        )
+    }
 }
@@ -0,0 +1,151 @@
+//! Heartbeats of running background jobs
+//!
+//! Jobs which can run for a long time register themselves in the
+//! `background_job_heartbeats` table while they are running, and a thread
+//! keeps the registration alive. The `monitor` binary uses these rows to
+//! detect jobs which have been running far longer than expected, as well as
+//! workers which died without cleaning up.
+
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use diesel::dsl::now;
+use diesel::prelude::*;
+use swirl::PerformError;
+
+use super::JobTracker;
+use crate::db::DieselPool;
+use crate::schema::background_job_heartbeats;
+
+/// How often a running job updates its heartbeat.
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The registration of a running job, which is removed once this is dropped.
+#[allow(missing_debug_implementations)]
+pub struct Heartbeat {
+    stop: Sender<()>,
+    tracker: Arc<JobTracker>,
+}
+
+impl Heartbeat {
+    pub(super) fn start(
+        pool: DieselPool,
+        tracker: Arc<JobTracker>,
+        job_type: &str,
+    ) -> Result<Self, PerformError> {
+        tracker.job_started()?;
+        let id = match register(&pool, job_type) {
+            Ok(id) => id,
+            Err(error) => {
+                tracker.job_finished();
+                return Err(error);
+            }
+        };
+
+        let (stop, stopped) = mpsc::channel();
+        let job_type = job_type.to_string();
+        thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(HEARTBEAT_INTERVAL) {
+                if let Err(error) = beat(&pool, id, &job_type) {
+                    eprintln!("Updating the heartbeat of `{}` failed: {}", job_type, error);
+                }
+            }
+
+            // The thread isn't joined, so that dropping a heartbeat while
+            // still holding the test connection can't deadlock.
+            let removed = pool.get().map_err(|e| e.to_string()).and_then(|conn| {
+                diesel::delete(background_job_heartbeats::table.find(id))
+                    .execute(&*conn)
+                    .map_err(|e| e.to_string())
+            });
+            if let Err(error) = removed {
+                eprintln!("Removing the heartbeat of `{}` failed: {}", job_type, error);
+            }
+        });
+
+        Ok(Self { stop, tracker })
+    }
+}
+
+impl Drop for Heartbeat {
+    fn drop(&mut self) {
+        let _ = self.stop.send(());
+        self.tracker.job_finished();
+    }
+}
+
+fn register(pool: &DieselPool, job_type: &str) -> Result<i32, PerformError> {
+    let conn = pool.get()?;
+    let id = diesel::insert_into(background_job_heartbeats::table)
+        .values((
+            background_job_heartbeats::job_type.eq(job_type),
+            background_job_heartbeats::worker.eq(worker_name()),
+        ))
+        .returning(background_job_heartbeats::id)
+        .get_result(&*conn)?;
+    Ok(id)
+}
+
+/// Updates the heartbeat of a job, and exits the worker if the `monitor`
+/// requested the job to be cancelled.
+///
+/// A stuck job can't be interrupted from the outside, and its row in
+/// `background_jobs` stays locked as long as the worker is alive. Exiting
+/// releases the lock, so that the job is retried once the worker restarts.
+fn beat(pool: &DieselPool, id: i32, job_type: &str) -> Result<(), PerformError> {
+    let conn = pool.get()?;
+    let cancel_requested_at = diesel::update(background_job_heartbeats::table.find(id))
+        .set(background_job_heartbeats::heartbeat_at.eq(now))
+        .returning(background_job_heartbeats::cancel_requested_at)
+        .get_result::<Option<chrono::NaiveDateTime>>(&*conn)
+        .optional()?
+        .flatten();
+
+    if let Some(requested_at) = cancel_requested_at {
+        eprintln!(
+            "`{}` was cancelled at {} after running for too long, exiting the worker",
+            job_type, requested_at
+        );
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn worker_name() -> String {
+    let host = dotenv::var("DYNO").unwrap_or_else(|_| "worker".into());
+    format!("{}:{}", host, std::process::id())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::pg_connection;
+    use parking_lot::ReentrantMutex;
+    use std::sync::Arc;
+
+    #[test]
+    fn running_jobs_are_registered() {
+        let conn = pg_connection();
+        let pool = DieselPool::Test(Arc::new(ReentrantMutex::new(conn)));
+
+        let tracker = Arc::new(JobTracker::default());
+        let heartbeat = Heartbeat::start(pool.clone(), tracker.clone(), "some_job").unwrap();
+        assert_eq!(tracker.running(), 1);
+        let conn = pool.get().unwrap();
+        let (job_type, worker) = background_job_heartbeats::table
+            .select((
+                background_job_heartbeats::job_type,
+                background_job_heartbeats::worker,
+            ))
+            .first::<(String, String)>(&*conn)
+            .unwrap();
+        assert_eq!(job_type, "some_job");
+        assert!(worker.ends_with(&format!(":{}", std::process::id())));
+
+        drop(conn);
+        drop(heartbeat);
+        assert_eq!(tracker.running(), 0);
+    }
+}
@@ -0,0 +1,128 @@
+//! Graceful shutdown of background workers
+//!
+//! Every job registers with the `JobTracker` of its worker while it runs (see
+//! `Environment::heartbeat`). Once a shutdown was requested no further jobs
+//! are started, and the worker waits for the running ones to finish before
+//! exiting.
+//!
+//! Exiting releases the locks of all jobs the worker still holds, so jobs
+//! which didn't finish in time are picked up again by another worker, without
+//! counting as a failed attempt.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use parking_lot::{Condvar, Mutex};
+use swirl::PerformError;
+
+#[derive(Debug, Default)]
+pub struct JobTracker {
+    shutting_down: AtomicBool,
+    running: Mutex<usize>,
+    finished: Condvar,
+}
+
+impl JobTracker {
+    /// Stops starting new jobs.
+    pub fn shut_down(&self) {
+        self.shutting_down.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::SeqCst)
+    }
+
+    /// The number of jobs currently running.
+    pub fn running(&self) -> usize {
+        *self.running.lock()
+    }
+
+    /// Waits until all running jobs finished or the timeout elapsed, and
+    /// returns the number of jobs still running.
+    pub fn wait_for_running_jobs(&self, timeout: Duration) -> usize {
+        let deadline = Instant::now() + timeout;
+        let mut running = self.running.lock();
+        while *running > 0 {
+            if self.finished.wait_until(&mut running, deadline).timed_out() {
+                break;
+            }
+        }
+        *running
+    }
+
+    /// Registers a job which is about to start.
+    ///
+    /// A job claimed by the worker after a shutdown was requested never
+    /// starts. Its thread is parked until the worker exits, which releases
+    /// the lock of the job again.
+    pub(super) fn job_started(&self) -> Result<(), PerformError> {
+        if self.is_shutting_down() {
+            loop {
+                thread::park();
+            }
+        }
+        *self.running.lock() += 1;
+        Ok(())
+    }
+
+    pub(super) fn job_finished(&self) {
+        let mut running = self.running.lock();
+        *running = running.saturating_sub(1);
+        self.finished.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn waits_for_running_jobs() {
+        let tracker = Arc::new(JobTracker::default());
+        tracker.job_started().unwrap();
+        tracker.job_started().unwrap();
+        assert_eq!(tracker.running(), 2);
+
+        tracker.shut_down();
+        assert!(tracker.is_shutting_down());
+
+        let finishing = {
+            let tracker = tracker.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(50));
+                tracker.job_finished();
+                tracker.job_finished();
+            })
+        };
+        assert_eq!(tracker.wait_for_running_jobs(Duration::from_secs(10)), 0);
+        finishing.join().unwrap();
+    }
+
+    #[test]
+    fn gives_up_after_the_timeout() {
+        let tracker = JobTracker::default();
+        tracker.job_started().unwrap();
+        tracker.shut_down();
+
+        let started = Instant::now();
+        assert_eq!(tracker.wait_for_running_jobs(Duration::from_millis(50)), 1);
+        assert!(started.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn jobs_are_not_started_after_a_shutdown() {
+        let tracker = Arc::new(JobTracker::default());
+        tracker.shut_down();
+
+        let claimed = {
+            let tracker = tracker.clone();
+            thread::spawn(move || tracker.job_started())
+        };
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(tracker.running(), 0);
+        // The parked thread is left behind, just like in a worker that exits
+        drop(claimed);
+    }
+}
@@ -0,0 +1,190 @@
+//! Versioning of background job payloads
+//!
+//! A job is stored as the serialized arguments of its function, so changing
+//! those arguments would break jobs which were enqueued before a deploy.
+//! Jobs are therefore enqueued with the current payload version of their
+//! type, and the worker migrates the payloads of older jobs before running
+//! them.
+//!
+//! To change the arguments of a job, append a migration to its entry in
+//! `MIGRATIONS` which converts a payload of the previous version, and add a
+//! fixture of the previous version to `src/tests/job-payloads`.
+
+use diesel::prelude::*;
+use serde_json::Value;
+use swirl::{EnqueueError, Job, PerformError};
+
+use crate::schema::background_jobs;
+
+/// Converts a payload to the next version of its job type.
+pub type Migration = fn(&mut Value) -> Result<(), PerformError>;
+
+/// The payload migrations of each job type, oldest first.
+const MIGRATIONS: &[(&str, &[Migration])] = &[("render_and_upload_readme", &[add_readme_vcs_ref])];
+
+/// READMEs rendered by jobs enqueued before the commit of a crate file was
+/// known link to the default branch of the repository.
+fn add_readme_vcs_ref(data: &mut Value) -> Result<(), PerformError> {
+    let payload = data.as_object_mut().ok_or("not an object")?;
+    payload.insert("vcs_ref".into(), Value::Null);
+    Ok(())
+}
+
+/// The payload version of jobs of the given type which are enqueued now.
+pub fn current_version(job_type: &str) -> i32 {
+    current_version_in(MIGRATIONS, job_type)
+}
+
+/// Converts the payload of a job enqueued with an older version to the
+/// current version of its job type.
+pub fn migrate(job_type: &str, version: i32, data: Value) -> Result<Value, PerformError> {
+    migrate_in(MIGRATIONS, job_type, version, data)
+}
+
+/// Enqueues a job along with the current payload version of its type.
+///
+/// This should be used instead of `swirl::Job::enqueue`, which doesn't record
+/// the version.
+pub trait EnqueueVersioned: Job {
+    fn enqueue_versioned(self, conn: &PgConnection) -> Result<(), EnqueueError>;
+}
+
+impl<J: Job> EnqueueVersioned for J {
+    fn enqueue_versioned(self, conn: &PgConnection) -> Result<(), EnqueueError> {
+        let data = serde_json::to_value(self)?;
+        diesel::insert_into(background_jobs::table)
+            .values((
+                background_jobs::job_type.eq(J::JOB_TYPE),
+                background_jobs::data.eq(data),
+                background_jobs::payload_version.eq(current_version(J::JOB_TYPE)),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+}
+
+/// Migrates the payloads of all queued jobs which were enqueued with an
+/// older version, and returns the number of migrated jobs.
+///
+/// Jobs which are currently running are skipped. A job whose payload can't
+/// be migrated is left alone, and fails once it runs.
+pub fn migrate_queued_jobs(conn: &PgConnection) -> QueryResult<usize> {
+    let mut migrated = 0;
+    for &(job_type, migrations) in MIGRATIONS {
+        let current = migrations.len() as i32 + 1;
+        conn.transaction::<_, diesel::result::Error, _>(|| {
+            let outdated = background_jobs::table
+                .filter(background_jobs::job_type.eq(job_type))
+                .filter(background_jobs::payload_version.lt(current))
+                .select((
+                    background_jobs::id,
+                    background_jobs::payload_version,
+                    background_jobs::data,
+                ))
+                .for_update()
+                .skip_locked()
+                .load::<(i64, i32, Value)>(conn)?;
+
+            for (id, version, data) in outdated {
+                match migrate(job_type, version, data) {
+                    Ok(data) => {
+                        diesel::update(background_jobs::table.find(id))
+                            .set((
+                                background_jobs::data.eq(data),
+                                background_jobs::payload_version.eq(current),
+                            ))
+                            .execute(conn)?;
+                        migrated += 1;
+                    }
+                    Err(error) => eprintln!(
+                        "Migrating the payload of job {} (`{}` version {}) failed: {}",
+                        id, job_type, version, error
+                    ),
+                }
+            }
+            Ok(())
+        })?;
+    }
+    Ok(migrated)
+}
+
+fn migrations_of<'a>(migrations: &[(&str, &'a [Migration])], job_type: &str) -> &'a [Migration] {
+    migrations
+        .iter()
+        .find(|(name, _)| *name == job_type)
+        .map(|(_, migrations)| *migrations)
+        .unwrap_or(&[])
+}
+
+fn current_version_in(migrations: &[(&str, &[Migration])], job_type: &str) -> i32 {
+    migrations_of(migrations, job_type).len() as i32 + 1
+}
+
+fn migrate_in(
+    migrations: &[(&str, &[Migration])],
+    job_type: &str,
+    version: i32,
+    mut data: Value,
+) -> Result<Value, PerformError> {
+    let current = current_version_in(migrations, job_type);
+    if version < 1 || version > current {
+        return Err(format!(
+            "unknown payload version {} of `{}`, the current version is {}",
+            version, job_type, current
+        )
+        .into());
+    }
+
+    let pending = &migrations_of(migrations, job_type)[version as usize - 1..];
+    for migration in pending {
+        migration(&mut data)?;
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rename_name(data: &mut Value) -> Result<(), PerformError> {
+        let payload = data.as_object_mut().ok_or("not an object")?;
+        let name = payload.remove("name").ok_or("missing `name`")?;
+        payload.insert("crate_name".into(), name);
+        Ok(())
+    }
+
+    fn add_force(data: &mut Value) -> Result<(), PerformError> {
+        data["force"] = json!(false);
+        Ok(())
+    }
+
+    const TEST_MIGRATIONS: &[(&str, &[Migration])] = &[("render", &[rename_name, add_force])];
+
+    #[test]
+    fn versions_are_counted_per_job_type() {
+        assert_eq!(current_version_in(TEST_MIGRATIONS, "render"), 3);
+        assert_eq!(current_version_in(TEST_MIGRATIONS, "other"), 1);
+    }
+
+    #[test]
+    fn pending_migrations_are_applied_in_order() {
+        let v1 = json!({ "name": "foo" });
+        let v2 = json!({ "crate_name": "foo" });
+        let v3 = json!({ "crate_name": "foo", "force": false });
+
+        assert_eq!(migrate_in(TEST_MIGRATIONS, "render", 1, v1).unwrap(), v3);
+        assert_eq!(migrate_in(TEST_MIGRATIONS, "render", 2, v2).unwrap(), v3);
+        assert_eq!(
+            migrate_in(TEST_MIGRATIONS, "render", 3, v3.clone()).unwrap(),
+            v3
+        );
+    }
+
+    #[test]
+    fn unknown_versions_are_rejected() {
+        assert!(migrate_in(TEST_MIGRATIONS, "render", 0, json!({})).is_err());
+        assert!(migrate_in(TEST_MIGRATIONS, "render", 4, json!({})).is_err());
+        assert!(migrate_in(TEST_MIGRATIONS, "other", 2, json!({})).is_err());
+    }
+}
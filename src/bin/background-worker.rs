@@ -7,15 +7,21 @@
 //! the worker thread), we will rebuild the runner and try again up to 5 times.
 //! After the 5th occurrance, we will panic.
 //!
+//! On SIGTERM or SIGINT no further jobs are started, and running jobs get
+//! `WORKER_SHUTDOWN_TIMEOUT` seconds (30 by default) to finish. Jobs which
+//! are still running after that are retried by the next worker.
+//!
 //! Usage:
 //!      cargo run --bin background-worker
 
 #![warn(clippy::all, rust_2018_idioms)]
 
-use cargo_registry::git::{Repository, RepositoryConfig};
+use cargo_registry::git::{self, Repository, RepositoryConfig};
+use cargo_registry::models::{IndexSettings, IndexSigningKey};
 use cargo_registry::{background_jobs::*, db};
 use diesel::r2d2;
 use reqwest::blocking::Client;
+use std::process;
 use std::sync::{Arc, Mutex};
 use std::thread::sleep;
 use std::time::Duration;
@@ -23,13 +29,31 @@ use std::time::Duration;
 fn main() {
     println!("Booting runner");
 
-    let config = cargo_registry::Config::default();
+    let config = cargo_registry::Config::load().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1)
+    });
 
     let job_start_timeout = dotenv::var("BACKGROUND_JOB_TIMEOUT")
         .unwrap_or_else(|_| "30".into())
         .parse()
         .expect("Invalid value for `BACKGROUND_JOB_TIMEOUT`");
 
+    let shutdown_timeout = dotenv::var("WORKER_SHUTDOWN_TIMEOUT")
+        .unwrap_or_else(|_| "30".into())
+        .parse()
+        .expect("Invalid value for `WORKER_SHUTDOWN_TIMEOUT`");
+
+    let job_tracker = Arc::new(JobTracker::default());
+    {
+        let job_tracker = job_tracker.clone();
+        ctrlc::set_handler(move || {
+            println!("Shutting down, no further jobs will be started");
+            job_tracker.shut_down();
+        })
+        .expect("Failed to install the shutdown handler");
+    }
+
     println!("Cloning index");
 
     let repository_config = RepositoryConfig::from_environment();
@@ -38,6 +62,8 @@ fn main() {
     ));
     println!("Index cloned");
 
+    let search_backend = config.search.build();
+
     let build_runner = || {
         // 2x the thread pool size -- not all our jobs need a DB connection,
         // but we want to always be able to run our jobs in parallel, rather
@@ -52,6 +78,9 @@ fn main() {
             db_pool.clone(),
             config.uploader.clone(),
             Client::new(),
+            search_backend.clone(),
+            config.og_image_renderer.clone(),
+            job_tracker.clone(),
         );
         swirl::Runner::builder(db_pool, environment)
             .thread_count(2)
@@ -60,12 +89,58 @@ fn main() {
     };
     let mut runner = build_runner();
 
+    // Payloads are migrated outside of the runner, since jobs enqueued by
+    // older versions of the app can show up at any time during a deploy
+    let migration_pool = db::diesel_pool(
+        &config.db_url,
+        config.env,
+        r2d2::Pool::builder().max_size(1),
+    );
+
+    if let Some(signing_key) = &repository_config.signing_key {
+        let conn = migration_pool
+            .get()
+            .expect("Failed to connect to the database");
+        IndexSigningKey::record(&conn, signing_key).expect("Failed to record the signing key");
+        println!("Signing index commits with {}", signing_key.fingerprint());
+    }
+
+    // The settings are checked before the job runs, which rewrites the
+    // `config.json` file of the index if the configuration changed
+    if let Err(e) = IndexSettings::from_environment(&config.uploader) {
+        eprintln!("{}", e);
+        process::exit(1)
+    }
+    {
+        let conn = migration_pool
+            .get()
+            .expect("Failed to connect to the database");
+        git::update_index_config()
+            .enqueue_versioned(&conn)
+            .expect("Failed to enqueue the index config update");
+    }
+
     println!("Runner booted, running jobs");
 
     let mut failure_count = 0;
 
-    loop {
+    while !job_tracker.is_shutting_down() {
+        let migrated = migration_pool
+            .get()
+            .map_err(|e| e.to_string())
+            .and_then(|conn| payload::migrate_queued_jobs(&conn).map_err(|e| e.to_string()));
+        match migrated {
+            Ok(0) => {}
+            Ok(count) => println!("Migrated the payloads of {} jobs", count),
+            Err(e) => eprintln!("Error migrating job payloads: {}", e),
+        }
+
         if let Err(e) = runner.run_all_pending_jobs() {
+            // Jobs claimed during the shutdown never start, which can make
+            // the runner time out
+            if job_tracker.is_shutting_down() {
+                break;
+            }
             failure_count += 1;
             if failure_count < 5 {
                 eprintln!(
@@ -79,4 +154,14 @@ fn main() {
         }
         sleep(Duration::from_secs(1));
     }
+
+    let running = job_tracker.wait_for_running_jobs(Duration::from_secs(shutdown_timeout));
+    if running > 0 {
+        eprintln!(
+            "{} jobs did not finish within {} seconds, they will be retried",
+            running, shutdown_timeout
+        );
+        process::exit(1);
+    }
+    println!("All running jobs finished, exiting");
 }
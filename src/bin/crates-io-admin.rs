@@ -0,0 +1,802 @@
+// Tools for admins.
+//
+// `notify` emails the owners of the crates matching a filter, see the
+// `outreach` module. Templates are read from `admin-emails/<name>.txt` and
+// saved segments from `admin-emails/segments.toml`.
+//
+// `reextract-metadata` downloads the crate files of the given versions and
+// inspects them again with the current code, to correct values which were
+// stored by a buggy version of it. With `--analyzers` it also runs the given
+// analyzers of the `tarball` module, to backfill their reports.
+//
+// `scan-queue` and `scan-review` work through the versions which are kept out
+// of the index because of what the `executables` analyzer found in them, or
+// because they're new crates copying the sources of a popular one, see
+// `HOLD_FLAGGED_VERSIONS`.
+//
+// `normalize-licenses` stores the normalized SPDX expression of the versions
+// published before publishing started to normalize them.
+//
+// `download-anomalies` lists the download spikes recorded by the
+// `detect_download_anomalies` job.
+//
+// `deprecated-endpoints` lists who still uses the endpoints in the
+// `deprecations` module.
+//
+// `set-config` and `unset-config` change the overrides of environment
+// variables stored in the database, see the `config` module.
+//
+// `merge-users` merges a duplicate user account into another one, and records
+// what it moved in `user_merges`, so that it can be reversed by hand.
+//
+// `import-registry` imports crates from another registry running this
+// codebase, see the `import_registry` module.
+//
+// `export-registry` exports crates into a registry seed, which cargo can use
+// offline and `import-registry` can load, see the `export_registry` module.
+
+#![warn(clippy::all, rust_2018_idioms)]
+
+#[macro_use]
+extern crate serde;
+
+mod export_registry;
+mod import_registry;
+
+use crate::import_registry::OnConflict;
+use cargo_registry::{
+    background_jobs::EnqueueVersioned,
+    config::check_overridable,
+    db,
+    deprecations::DEPRECATED_ENDPOINTS,
+    email, git, lower,
+    models::{
+        ConfigOverride, ContentMatch, Crate, CratePolicy, CrateQuality, DeprecatedEndpointUsage,
+        DownloadAnomaly, FundingLink, IndexSync, MergeReport, PublishJob, ScanHold, User,
+        UserMerge, Version, VersionAnalysis, VersionFeatureDoc, VersionFile, VersionFingerprint,
+        VersionScanResult,
+    },
+    outreach::{recipients, CrateFilter, OutreachEmail, Template},
+    schema::{crate_policies, crate_quality, crates, users, versions},
+    spdx::LicenseExpr,
+    tarball, uploaders, Config,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use diesel::prelude::*;
+use docopt::Docopt;
+use reqwest::blocking::Client;
+
+const USAGE: &str = "
+Usage: crates-io-admin notify --filter <filter> --template <name> [options]
+       crates-io-admin reextract-metadata --versions <ids> [--analyzers <names>] [--apply]
+       crates-io-admin scan-queue
+       crates-io-admin scan-review <crate> <version> <decision>
+       crates-io-admin normalize-licenses
+       crates-io-admin download-anomalies [--days <n>]
+       crates-io-admin deprecated-endpoints [--days <n>]
+       crates-io-admin set-config <name> <value>
+       crates-io-admin unset-config <name>
+       crates-io-admin merge-users <from> <into> [--dry-run]
+       crates-io-admin import-registry --from <index> --crates-dir <dir> --owners <file> [--crates <names>] [--on-conflict <mode>]
+       crates-io-admin export-registry --crates <names> [--include-deps] [--output <file>]
+       crates-io-admin --help
+
+Emails the owners of the crates matching <filter>, which is either a filter
+like \"depends_on = 'time' and downloads >= 1000\" or `@<segment>`. Every
+owner gets one email listing their matching crates, and owners who were
+already emailed as part of the campaign are skipped.
+
+`reextract-metadata` inspects the crate files of the versions with the given
+IDs, either a list like `12,15` or a range like `100-250`, and reports where
+what is extracted from them differs from what was stored when they were
+published. The reports of the given analyzers are printed, or stored with
+`--apply`.
+
+`scan-queue` lists the versions held back from the index with what was found
+in them, and `scan-review` decides about one of them. <decision> is either
+`release`, which adds the version to the index, or `reject`, which yanks it
+without ever adding it.
+
+`normalize-licenses` normalizes the license expressions of the versions which
+were published before they were normalized at publish time.
+
+`download-anomalies` lists the days within the last <n> days on which crates
+were downloaded far more often than usual, highest score first.
+
+`deprecated-endpoints` lists the deprecated endpoints with the consumers which
+requested them within the last <n> days, most requests first.
+
+`set-config` overrides the environment variable <name> of the server and the
+background worker with <value>, and `unset-config` removes the override. They
+take effect when the processes are restarted next, and secrets can't be set
+this way.
+
+`merge-users` moves the crates, follows, email address and audit entries of
+the user <from> to the user <into> and revokes the API tokens of <from>. Users
+are given by their GitHub login, or by their ID like `#123`. With `--dry-run`
+it only reports what would be moved.
+
+`import-registry` imports the crates of another registry, all of them or the
+given ones. Crates which don't exist on this registry yet are created with the
+owners listed for them in the owners file.
+
+`export-registry` exports all versions of the given crates into a registry
+seed.
+
+Options:
+    -h, --help           Show this message.
+    --filter <filter>    The crates whose owners are emailed.
+    --template <name>    The template in `admin-emails/<name>.txt`.
+    --campaign <name>    The campaign the emails are recorded for, the name
+                         of the template by default.
+    --dry-run            Only list the recipients and the first email, or
+                         what would be merged.
+    --per-minute <n>     How many emails are sent per minute [default: 30].
+    --versions <ids>     The IDs of the versions to inspect.
+    --analyzers <names>  The analyzers to run, a list like `size,license` or
+                         `all`.
+    --apply              Correct the stored values instead of only reporting
+                         the differences.
+    --days <n>           How many days back anomalies and uses of deprecated
+                         endpoints are listed [default: 7].
+    --from <index>       URL of the other registry's git index, or the path
+                         of a checkout of it or of an exported registry seed.
+    --crates-dir <dir>   Directory containing the other registry's crate
+                         files, laid out as `crates/<name>/<name>-<version>.crate`
+                         or as `<name>-<version>.crate`.
+    --owners <file>      TOML file mapping crate names to the GitHub logins of
+                         their owners on this registry.
+    --crates <names>     Comma separated list of the crates to import or
+                         export.
+    --on-conflict <mode>  What to do with versions that already exist on this
+                         registry, either `abort` or `skip` [default: abort].
+    --include-deps       Also export all crates the given crates (transitively)
+                         depend on. Dev-dependencies are not included.
+    --output <file>      Where to write the seed [default: registry-seed.tar.gz].
+";
+
+#[derive(Deserialize)]
+struct Args {
+    cmd_notify: bool,
+    cmd_reextract_metadata: bool,
+    cmd_scan_queue: bool,
+    cmd_scan_review: bool,
+    cmd_normalize_licenses: bool,
+    cmd_download_anomalies: bool,
+    cmd_deprecated_endpoints: bool,
+    cmd_set_config: bool,
+    cmd_unset_config: bool,
+    cmd_merge_users: bool,
+    cmd_import_registry: bool,
+    cmd_export_registry: bool,
+    arg_crate: String,
+    arg_version: String,
+    arg_decision: String,
+    arg_name: String,
+    arg_value: String,
+    arg_from: String,
+    arg_into: String,
+    flag_filter: String,
+    flag_template: String,
+    flag_campaign: Option<String>,
+    flag_dry_run: bool,
+    flag_per_minute: u64,
+    flag_versions: String,
+    flag_analyzers: Option<String>,
+    flag_apply: bool,
+    flag_days: i64,
+    flag_from: String,
+    flag_crates_dir: PathBuf,
+    flag_owners: PathBuf,
+    flag_crates: Option<String>,
+    flag_on_conflict: OnConflict,
+    flag_include_deps: bool,
+    flag_output: String,
+}
+
+#[derive(Deserialize)]
+struct Segments {
+    segments: HashMap<String, String>,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Args = Docopt::new(USAGE)
+        .and_then(|d| d.deserialize())
+        .unwrap_or_else(|e| e.exit());
+
+    if args.cmd_notify {
+        notify(&args)
+    } else if args.cmd_reextract_metadata {
+        reextract_metadata(&args)
+    } else if args.cmd_scan_queue {
+        scan_queue()
+    } else if args.cmd_scan_review {
+        scan_review(&args)
+    } else if args.cmd_normalize_licenses {
+        normalize_licenses()
+    } else if args.cmd_download_anomalies {
+        download_anomalies(&args)
+    } else if args.cmd_deprecated_endpoints {
+        deprecated_endpoints(&args)
+    } else if args.cmd_set_config {
+        set_config(&args)
+    } else if args.cmd_unset_config {
+        unset_config(&args)
+    } else if args.cmd_merge_users {
+        merge_users(&args)
+    } else if args.cmd_import_registry {
+        import_registry(&args)
+    } else if args.cmd_export_registry {
+        export_registry::run(
+            &crate_names(&args),
+            args.flag_include_deps,
+            &args.flag_output,
+        )
+    } else {
+        Ok(())
+    }
+}
+
+fn notify(args: &Args) -> Result<(), Box<dyn Error>> {
+    let filter = if args.flag_filter.starts_with('@') {
+        let name = &args.flag_filter[1..];
+        let segments: Segments =
+            toml::from_str(&fs::read_to_string("admin-emails/segments.toml")?)?;
+        segments
+            .segments
+            .get(name)
+            .ok_or_else(|| format!("unknown segment `{}`", name))?
+            .clone()
+    } else {
+        args.flag_filter.clone()
+    };
+    let filter = CrateFilter::parse(&filter)?;
+    let template = fs::read_to_string(format!("admin-emails/{}.txt", args.flag_template))?;
+    let template = Template::parse(&template)?;
+    let campaign = args
+        .flag_campaign
+        .clone()
+        .unwrap_or_else(|| args.flag_template.clone());
+    let delay = Duration::from_millis(60_000 / args.flag_per_minute.max(1));
+
+    let conn = db::connect_now()?;
+    let crate_ids = filter.matching_crates(&conn)?;
+    let already_sent = OutreachEmail::already_sent(&conn, &campaign)?;
+    let recipients = recipients(&conn, &crate_ids)?
+        .into_iter()
+        .filter(|recipient| !already_sent.contains(&recipient.user_id))
+        .collect::<Vec<_>>();
+    println!(
+        "{} crates match, {} owners to email, {} already emailed for `{}`",
+        crate_ids.len(),
+        recipients.len(),
+        already_sent.len(),
+        campaign
+    );
+
+    if args.flag_dry_run {
+        for recipient in &recipients {
+            println!("{}", recipient);
+        }
+        if let Some(recipient) = recipients.first() {
+            println!(
+                "\nSubject: {}\n\n{}",
+                template.subject,
+                template.render(recipient)
+            );
+        }
+        return Ok(());
+    }
+
+    let mut failed = 0;
+    for (i, recipient) in recipients.iter().enumerate() {
+        if i > 0 {
+            thread::sleep(delay);
+        }
+        let body = template.render(recipient);
+        match email::send_outreach_email(&recipient.email, &template.subject, &body) {
+            Ok(()) => {
+                OutreachEmail::record(&conn, &campaign, recipient)?;
+                println!("Sent to {}", recipient);
+            }
+            Err(e) => {
+                failed += 1;
+                println!("Sending to {} failed: {}", recipient, e);
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(format!("{} emails couldn't be sent", failed).into());
+    }
+    Ok(())
+}
+
+fn reextract_metadata(args: &Args) -> Result<(), Box<dyn Error>> {
+    let config = Config::load()?;
+    let client = Client::new();
+    let conn = db::connect_now()?;
+
+    let query = versions::table.order(versions::id).into_boxed();
+    let query = match args.flag_versions.find('-') {
+        Some(i) => {
+            let first = args.flag_versions[..i].trim().parse::<i32>()?;
+            let last = args.flag_versions[i + 1..].trim().parse::<i32>()?;
+            query.filter(versions::id.between(first, last))
+        }
+        None => {
+            let ids = args
+                .flag_versions
+                .split(',')
+                .map(|id| id.trim().parse::<i32>())
+                .collect::<Result<Vec<_>, _>>()?;
+            query.filter(versions::id.eq_any(ids))
+        }
+    };
+    let versions = query.load::<Version>(&conn)?;
+    let analyzer_names = match args.flag_analyzers.as_deref() {
+        None => vec![],
+        Some("all") => tarball::ANALYZERS.to_vec(),
+        Some(names) => names.split(',').map(str::trim).collect(),
+    };
+    // Fail early on unknown names, instead of after the first download
+    tarball::analyzers(&analyzer_names)?;
+
+    let mut differing = 0;
+    for version in versions {
+        let krate = Crate::all()
+            .filter(crates::id.eq(version.crate_id))
+            .first::<Crate>(&conn)?;
+        let prefix = format!("[{}-{}]", krate.name, version.num);
+
+        let location = config
+            .uploader
+            .crate_location(&krate.name, &version.num.to_string());
+        let body = client.get(&location).send()?.error_for_status()?.bytes()?;
+        let mut analyzers = tarball::analyzers(&analyzer_names)?;
+        let contents = match uploaders::inspect_crate_file(
+            &krate,
+            &version.num,
+            &body,
+            config.max_unpack_size,
+            &mut analyzers,
+        ) {
+            Ok(contents) => contents,
+            Err(e) => {
+                println!("{} can't be inspected: {}", prefix, e);
+                continue;
+            }
+        };
+
+        let features =
+            serde_json::from_value::<HashMap<String, Vec<String>>>(version.features.clone())?;
+        let feature_docs = contents
+            .feature_docs
+            .into_iter()
+            .filter(|(feature, _)| features.contains_key(feature))
+            .collect::<BTreeMap<_, _>>();
+        let stored_feature_docs = VersionFeatureDoc::by_version(&conn, version.id)?
+            .into_iter()
+            .collect::<BTreeMap<_, _>>();
+        let crate_size = body.len() as i32;
+        let checksum = hex::encode(openssl::sha::sha256(&body));
+        let compression = contents.compression;
+        let targets = contents.targets;
+        let funding_links = contents.funding_links;
+        let has_tests = contents.has_tests;
+        let stored_checksum = Version::checksum(version.id, &conn)?;
+        let mut files = contents.files;
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        let mut stored_files = VersionFile::by_version(&conn, version.id)?;
+        stored_files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut differences = Vec::new();
+        if version.crate_size != Some(crate_size) {
+            differences.push(format!(
+                "crate_size: {:?} -> {}",
+                version.crate_size, crate_size
+            ));
+        }
+        if stored_checksum.as_ref() != Some(&checksum) {
+            differences.push(format!("checksum: {:?} -> {}", stored_checksum, checksum));
+        }
+        if version.compression != compression {
+            differences.push(format!(
+                "compression: {:?} -> {:?}",
+                version.compression, compression
+            ));
+        }
+        if version.targets != targets {
+            differences.push(format!("targets: {:?} -> {:?}", version.targets, targets));
+        }
+        if feature_docs != stored_feature_docs {
+            differences.push(format!(
+                "feature docs: {:?} -> {:?}",
+                stored_feature_docs.keys().collect::<Vec<_>>(),
+                feature_docs.keys().collect::<Vec<_>>()
+            ));
+        }
+        if files != stored_files {
+            differences.push(format!("files: {} -> {}", stored_files.len(), files.len()));
+        }
+
+        // The policy, the funding links and whether the crate has tests are
+        // taken from the most recently published version of a crate
+        let latest_version_id = versions::table
+            .filter(versions::crate_id.eq(krate.id))
+            .order(versions::created_at.desc())
+            .select(versions::id)
+            .first::<i32>(&conn)?;
+        let is_latest = latest_version_id == version.id;
+        let policy = contents
+            .policy_file
+            .map(|file| CratePolicy::new(krate.id, version.id, file));
+        if is_latest {
+            let stored_policy = crate_policies::table
+                .find(krate.id)
+                .first::<CratePolicy>(&conn)
+                .optional()?;
+            if policy != stored_policy {
+                differences.push(format!("policy: {:?} -> {:?}", stored_policy, policy));
+            }
+            let stored_funding_links = FundingLink::by_crate(&conn, krate.id)?;
+            if funding_links != stored_funding_links {
+                differences.push(format!(
+                    "funding links: {:?} -> {:?}",
+                    stored_funding_links, funding_links
+                ));
+            }
+            let stored_has_tests = crate_quality::table
+                .find(krate.id)
+                .select(crate_quality::has_tests)
+                .first::<Option<bool>>(&conn)
+                .optional()?
+                .and_then(|tests| tests);
+            if stored_has_tests != Some(has_tests) {
+                differences.push(format!(
+                    "has_tests: {:?} -> {}",
+                    stored_has_tests, has_tests
+                ));
+            }
+        }
+
+        for difference in &differences {
+            println!("{} {}", prefix, difference);
+        }
+        for (analyzer, result) in &contents.analyses {
+            println!("{} {}: {}", prefix, analyzer, result);
+        }
+        if args.flag_apply && !contents.analyses.is_empty() {
+            VersionScanResult::save(&conn, version.id, &contents.analyses)?;
+            VersionFingerprint::save(&conn, version.crate_id, version.id, &contents.analyses)?;
+            VersionAnalysis::save(&conn, version.id, contents.analyses)?;
+        }
+        if differences.is_empty() {
+            continue;
+        }
+        differing += 1;
+
+        if args.flag_apply {
+            conn.transaction::<_, diesel::result::Error, _>(|| {
+                diesel::update(&version)
+                    .set(versions::crate_size.eq(crate_size))
+                    .execute(&conn)?;
+                Version::record_checksum(version.id, &checksum, &conn)?;
+                Version::record_compression(version.id, compression, &conn)?;
+                Version::record_targets(version.id, &targets, &conn)?;
+                VersionFeatureDoc::replace(&conn, version.id, &features, feature_docs)?;
+                VersionFile::save(&conn, version.id, &files)?;
+                if is_latest {
+                    CratePolicy::replace(&conn, krate.id, policy)?;
+                    FundingLink::update_crate(&conn, krate.id, &funding_links)?;
+                    CrateQuality::record_tests(&conn, krate.id, has_tests)?;
+                }
+                Ok(())
+            })?;
+            println!("{} corrected", prefix);
+        }
+    }
+
+    println!(
+        "{} versions differ{}",
+        differing,
+        if args.flag_apply {
+            " and were corrected"
+        } else {
+            ""
+        }
+    );
+    Ok(())
+}
+
+fn scan_queue() -> Result<(), Box<dyn Error>> {
+    let conn = db::connect_now()?;
+    for (hold, krate, version) in ScanHold::pending(&conn)? {
+        println!("{} {} (held since {})", krate, version, hold.created_at);
+        for finding in VersionScanResult::by_version(&conn, hold.version_id)? {
+            println!(
+                "    {}: {} ({})",
+                finding.path, finding.kind, finding.detail
+            );
+        }
+        if let Some((content_match, name, num)) = ContentMatch::by_version(&conn, hold.version_id)?
+        {
+            println!(
+                "    sources {:.0}% identical to {} {}",
+                content_match.similarity * 100.0,
+                name,
+                num
+            );
+        }
+    }
+    Ok(())
+}
+
+fn scan_review(args: &Args) -> Result<(), Box<dyn Error>> {
+    let decision = args.arg_decision.as_str();
+    if decision != "release" && decision != "reject" {
+        return Err(format!("unknown decision `{}`", decision).into());
+    }
+    let conn = db::connect_now()?;
+    let krate = Crate::by_name(&args.arg_crate).first::<Crate>(&conn)?;
+    let version_id = versions::table
+        .filter(versions::crate_id.eq(krate.id))
+        .filter(versions::num.eq(&args.arg_version))
+        .select(versions::id)
+        .first::<i32>(&conn)?;
+
+    conn.transaction::<_, Box<dyn Error>, _>(|| {
+        let hold = ScanHold::release(&conn, version_id)?
+            .ok_or_else(|| format!("{} {} isn't held", krate.name, args.arg_version))?;
+        if decision == "release" {
+            let entry = serde_json::from_value::<git::Crate>(hold.index_entry)?;
+            match hold.publish_job_id {
+                Some(publish_job_id) => {
+                    git::add_queued_crate(publish_job_id, entry).enqueue_versioned(&conn)?
+                }
+                None => git::add_crate(entry).enqueue_versioned(&conn)?,
+            }
+            IndexSync::queued(&conn, version_id)?;
+            println!(
+                "Released {} {} into the index",
+                krate.name, args.arg_version
+            );
+        } else {
+            diesel::update(versions::table.find(version_id))
+                .set(versions::yanked.eq(true))
+                .execute(&conn)?;
+            if let Some(publish_job_id) = hold.publish_job_id {
+                PublishJob::fail(&conn, publish_job_id, "rejected after a review")?;
+            }
+            println!("Rejected and yanked {} {}", krate.name, args.arg_version);
+        }
+        Ok(())
+    })
+}
+
+fn download_anomalies(args: &Args) -> Result<(), Box<dyn Error>> {
+    let conn = db::connect_now()?;
+    let since = chrono::Utc::today().naive_utc() - chrono::Duration::days(args.flag_days);
+    for (anomaly, krate) in DownloadAnomaly::since(&conn, since)? {
+        println!(
+            "{} {}: {} downloads, {:.0} per day before (score {:.1})",
+            anomaly.date, krate, anomaly.downloads, anomaly.baseline, anomaly.score
+        );
+    }
+    Ok(())
+}
+
+fn deprecated_endpoints(args: &Args) -> Result<(), Box<dyn Error>> {
+    let conn = db::connect_now()?;
+    let since = chrono::Utc::today().naive_utc() - chrono::Duration::days(args.flag_days);
+    let usage = DeprecatedEndpointUsage::since(&conn, since)?;
+    for endpoint in DEPRECATED_ENDPOINTS {
+        println!(
+            "{} {} (deprecated on {}, sunset {})",
+            endpoint.method,
+            endpoint.path,
+            endpoint.deprecated_on,
+            endpoint.sunset.unwrap_or("not planned yet")
+        );
+        let consumers = usage.iter().filter(|u| u.endpoint == endpoint.path);
+        for usage in consumers {
+            println!(
+                "    {} requests by `{}`, last on {}",
+                usage.requests, usage.consumer, usage.last_used_at
+            );
+        }
+    }
+    Ok(())
+}
+
+fn set_config(args: &Args) -> Result<(), Box<dyn Error>> {
+    check_overridable(&args.arg_name)?;
+    let conn = db::connect_now()?;
+    ConfigOverride::set(&conn, &args.arg_name, &args.arg_value)?;
+    println!(
+        "{} is set to `{}` once the server and the background worker restart",
+        args.arg_name, args.arg_value
+    );
+    Ok(())
+}
+
+fn unset_config(args: &Args) -> Result<(), Box<dyn Error>> {
+    let conn = db::connect_now()?;
+    if !ConfigOverride::remove(&conn, &args.arg_name)? {
+        return Err(format!("{} isn't overridden", args.arg_name).into());
+    }
+    println!(
+        "{} is no longer overridden once the server and the background worker restart",
+        args.arg_name
+    );
+    Ok(())
+}
+
+fn merge_users(args: &Args) -> Result<(), Box<dyn Error>> {
+    let conn = db::connect_now()?;
+    let from = find_user(&conn, &args.arg_from)?;
+    let into = find_user(&conn, &args.arg_into)?;
+    if from.id == into.id {
+        return Err("can't merge a user into itself".into());
+    }
+    if from.gh_id != into.gh_id {
+        println!(
+            "Note: the accounts belong to different GitHub users ({} and {})",
+            from.gh_id, into.gh_id
+        );
+    }
+
+    let merged = conn.transaction(|| {
+        let (merge, report) = UserMerge::merge(&conn, &from, &into)?;
+        print_merge_report(&conn, &from, &into, &report)?;
+        if args.flag_dry_run {
+            return Err(diesel::result::Error::RollbackTransaction);
+        }
+        Ok(merge)
+    });
+    match merged {
+        Ok(merge) => println!("Recorded as merge {}", merge.id),
+        Err(diesel::result::Error::RollbackTransaction) => println!("Dry run, nothing was changed"),
+        Err(e) => return Err(e.into()),
+    }
+    Ok(())
+}
+
+/// Finds a user by GitHub login, or by ID like `#123`.
+fn import_registry(args: &Args) -> Result<(), Box<dyn Error>> {
+    import_registry::run(
+        &args.flag_from,
+        &args.flag_crates_dir,
+        &args.flag_owners,
+        &crate_names(args),
+        args.flag_on_conflict,
+    )
+}
+
+/// The crate names given with `--crates`.
+fn crate_names(args: &Args) -> Vec<String> {
+    args.flag_crates
+        .iter()
+        .flat_map(|names| names.split(','))
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+fn find_user(conn: &PgConnection, user: &str) -> Result<User, Box<dyn Error>> {
+    if user.starts_with('#') {
+        let id = user[1..].parse::<i32>()?;
+        return Ok(users::table.find(id).first(conn)?);
+    }
+    let mut matching = users::table
+        .filter(lower(users::gh_login).eq(user.to_lowercase()))
+        .load::<User>(conn)?;
+    match matching.len() {
+        0 => Err(format!("no user `{}`", user).into()),
+        1 => Ok(matching.remove(0)),
+        _ => {
+            let ids = matching
+                .iter()
+                .map(|user| format!("#{}", user.id))
+                .collect::<Vec<_>>();
+            Err(format!("`{}` is ambiguous, use one of {}", user, ids.join(", ")).into())
+        }
+    }
+}
+
+fn print_merge_report(
+    conn: &PgConnection,
+    from: &User,
+    into: &User,
+    report: &MergeReport,
+) -> QueryResult<()> {
+    let crate_names = |ids: &[i32]| {
+        crates::table
+            .filter(crates::id.eq_any(ids))
+            .order(crates::name)
+            .select(crates::name)
+            .load::<String>(conn)
+            .map(|names| names.join(", "))
+    };
+    println!(
+        "Merging {} (#{}) into {} (#{}):",
+        from.gh_login, from.id, into.gh_login, into.id
+    );
+    println!(
+        "  moved the ownership of {} crates: {}",
+        report.moved_ownerships.len(),
+        crate_names(&report.moved_ownerships)?
+    );
+    println!(
+        "  removed the ownership of {} crates owned by both: {}",
+        report.removed_ownerships.len(),
+        crate_names(&report.removed_ownerships)?
+    );
+    println!(
+        "  moved {} follows, removed {} follows of crates followed by both",
+        report.moved_follows.len(),
+        report.removed_follows.len()
+    );
+    println!("  revoked {} API tokens", report.revoked_tokens.len());
+    match report.moved_email {
+        Some(_) => println!("  moved the email address"),
+        None => println!("  kept the email address of {}", into.gh_login),
+    }
+    println!(
+        "  moved {} published versions, {} owner actions, {} yank events, \
+         {} metadata changes and {} link changes",
+        report.published_versions.len(),
+        report.version_owner_actions.len(),
+        report.yank_events.len(),
+        report.crate_metadata_history.len(),
+        report.crate_link_events.len()
+    );
+    Ok(())
+}
+
+fn normalize_licenses() -> Result<(), Box<dyn Error>> {
+    let conn = db::connect_now()?;
+    let mut normalized = 0;
+    let mut last_id = 0;
+    loop {
+        let batch = versions::table
+            .filter(versions::id.gt(last_id))
+            .filter(versions::license.is_not_null())
+            .filter(versions::license_expression.is_null())
+            .select((versions::id, versions::license))
+            .order(versions::id)
+            .limit(1000)
+            .load::<(i32, Option<String>)>(&conn)?;
+        last_id = match batch.last() {
+            Some(&(id, _)) => id,
+            None => break,
+        };
+
+        for (id, license) in batch {
+            let license = license.unwrap_or_default();
+            // Versions published with an invalid expression stay as they are
+            let expr = match LicenseExpr::parse(&license) {
+                Ok(expr) => expr,
+                Err(e) => {
+                    println!("version {}: `{}` can't be normalized: {}", id, license, e);
+                    continue;
+                }
+            };
+            diesel::update(versions::table.find(id))
+                .set((
+                    versions::license_expression.eq(expr.to_string()),
+                    versions::license_ids.eq(expr.licenses()),
+                ))
+                .execute(&conn)?;
+            normalized += 1;
+        }
+    }
+    println!("normalized the licenses of {} versions", normalized);
+    Ok(())
+}
@@ -2,12 +2,23 @@
 //
 // Please be super sure you want to do this before running this.
 //
+// The deletion is listed by `GET /api/v1/deleted_crates` with the reason,
+// one of `malware`, `legal`, `policy`, `owner_request` or `other`.
+// Service consumers with a webhook are notified once the deletion is
+// committed.
+//
 // Usage:
-//      cargo run --bin delete-crate crate-name
+//      cargo run --bin delete-crate crate-name reason
 
 #![warn(clippy::all, rust_2018_idioms)]
 
-use cargo_registry::{db, models::Crate, schema::crates};
+use cargo_registry::{
+    background_jobs::EnqueueVersioned,
+    db,
+    models::{deleted_crate, webhook, Crate, DeletedCrate},
+    schema::crates,
+    tasks,
+};
 use std::{
     env,
     io::{self, prelude::*},
@@ -32,6 +43,16 @@ fn delete(conn: &PgConnection) {
         }
         Some(s) => s,
     };
+    let reason = match env::args().nth(2) {
+        Some(ref s) if deleted_crate::REASONS.contains(&s.as_str()) => s.clone(),
+        _ => {
+            println!(
+                "needs a reason argument, one of {}",
+                deleted_crate::REASONS.join(", ")
+            );
+            return;
+        }
+    };
 
     let krate = Crate::by_name(&name).first::<Crate>(conn).unwrap();
     print!(
@@ -50,6 +71,14 @@ fn delete(conn: &PgConnection) {
         .execute(conn)
         .unwrap();
     println!("  {} deleted", n);
+    tasks::remove_from_search_index(krate.id)
+        .enqueue_versioned(conn)
+        .unwrap();
+    let deleted = DeletedCrate::record(conn, &krate.name, None, &reason).unwrap();
+    if webhook::notify_deletion(conn, &deleted).unwrap() > 0 {
+        println!("notifying the webhooks of service consumers");
+        tasks::deliver_webhooks().enqueue_versioned(conn).unwrap();
+    }
 
     print!("commit? [y/N]: ");
     io::stdout().flush().unwrap();
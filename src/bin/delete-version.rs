@@ -2,15 +2,22 @@
 //
 // Please be super sure you want to do this before running this.
 //
+// The deletion is listed by `GET /api/v1/deleted_crates` with the reason,
+// one of `malware`, `legal`, `policy`, `owner_request` or `other`.
+// Service consumers with a webhook are notified once the deletion is
+// committed.
+//
 // Usage:
-//      cargo run --bin delete-version crate-name version-number
+//      cargo run --bin delete-version crate-name version-number reason
 
 #![warn(clippy::all, rust_2018_idioms)]
 
 use cargo_registry::{
+    background_jobs::EnqueueVersioned,
     db,
-    models::{Crate, Version},
+    models::{default_versions, deleted_crate, webhook, Crate, DeletedCrate, Version},
     schema::versions,
+    tasks,
 };
 use std::{
     env,
@@ -43,6 +50,16 @@ fn delete(conn: &PgConnection) {
         }
         Some(s) => s,
     };
+    let reason = match env::args().nth(3) {
+        Some(ref s) if deleted_crate::REASONS.contains(&s.as_str()) => s.clone(),
+        _ => {
+            println!(
+                "needs a reason argument, one of {}",
+                deleted_crate::REASONS.join(", ")
+            );
+            return;
+        }
+    };
 
     let krate = Crate::by_name(&name).first::<Crate>(conn).unwrap();
     let v = Version::belonging_to(&krate)
@@ -64,6 +81,17 @@ fn delete(conn: &PgConnection) {
     diesel::delete(versions::table.find(&v.id))
         .execute(conn)
         .unwrap();
+    let deleted = DeletedCrate::record(conn, &krate.name, Some(&v.num), &reason).unwrap();
+    if webhook::notify_deletion(conn, &deleted).unwrap() > 0 {
+        println!("notifying the webhooks of service consumers");
+        tasks::deliver_webhooks().enqueue_versioned(conn).unwrap();
+    }
+
+    println!("updating the default version of {}", name);
+    match default_versions::update_default_version(krate.id, conn) {
+        Err(diesel::result::Error::NotFound) => println!("  no versions left"),
+        result => result.unwrap(),
+    }
 
     print!("commit? [y/N]: ");
     io::stdout().flush().unwrap();
@@ -0,0 +1,54 @@
+// Lists the most recently found dependency cycles through dev- or
+// build-dependencies, and the publishes which introduced them.
+
+#![warn(clippy::all, rust_2018_idioms)]
+
+#[macro_use]
+extern crate serde;
+
+use cargo_registry::{db, models::DependencyCycle};
+use std::error::Error;
+
+use docopt::Docopt;
+
+const USAGE: &str = "
+Usage: dependency-cycles [options]
+       dependency-cycles --help
+
+Lists the most recently found dependency cycles, newest first.
+
+Options:
+    -h, --help     Show this message.
+    --limit <n>    How many cycles to list [default: 100].
+";
+
+#[derive(Deserialize)]
+struct Args {
+    flag_limit: i64,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Args = Docopt::new(USAGE)
+        .and_then(|d| d.deserialize())
+        .unwrap_or_else(|e| e.exit());
+    let conn = db::connect_now()?;
+
+    for (cycle, krate, version) in DependencyCycle::recent(&conn, args.flag_limit)? {
+        let path = cycle
+            .crates
+            .iter()
+            .zip(&cycle.kinds)
+            .map(|(name, kind)| format!("{} -({})->", name, kind))
+            .collect::<Vec<_>>()
+            .join(" ");
+        println!(
+            "{} {}@{}: {} {}",
+            cycle.created_at.format("%Y-%m-%d"),
+            krate,
+            version,
+            path,
+            cycle.crates[0]
+        );
+    }
+    Ok(())
+}
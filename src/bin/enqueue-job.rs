@@ -1,7 +1,7 @@
 #![deny(clippy::all)]
 
-use cargo_registry::{db, env, tasks, util::Error};
-use swirl::Job;
+use cargo_registry::background_jobs::EnqueueVersioned;
+use cargo_registry::{crate_page, db, env, git, og_image, sitemap, tasks, util::Error};
 
 fn main() -> Result<(), Error> {
     let conn = db::connect_now()?;
@@ -11,13 +11,66 @@ fn main() -> Result<(), Error> {
     println!("Enqueueing background job: {}", job);
 
     match &*job {
-        "update_downloads" => Ok(tasks::update_downloads().enqueue(&conn)?),
+        "update_downloads" => Ok(tasks::update_downloads().enqueue_versioned(&conn)?),
+        "sync_default_versions" => Ok(tasks::sync_default_versions().enqueue_versioned(&conn)?),
+        "sync_search_index" => Ok(tasks::sync_search_index().enqueue_versioned(&conn)?),
+        "compute_crate_quality" => Ok(tasks::compute_crate_quality().enqueue_versioned(&conn)?),
+        "compute_dependency_freshness" => {
+            Ok(tasks::compute_dependency_freshness().enqueue_versioned(&conn)?)
+        }
+        "compute_release_stats" => Ok(tasks::compute_release_stats().enqueue_versioned(&conn)?),
+        "compute_ecosystem_stats" => Ok(tasks::compute_ecosystem_stats().enqueue_versioned(&conn)?),
+        "detect_download_anomalies" => {
+            Ok(tasks::detect_download_anomalies().enqueue_versioned(&conn)?)
+        }
+        "sync_repository_activity" => {
+            Ok(tasks::sync_repository_activity().enqueue_versioned(&conn)?)
+        }
+        "notify_dependency_updates" => {
+            Ok(tasks::notify_dependency_updates().enqueue_versioned(&conn)?)
+        }
+        "check_squatting_reports" => Ok(tasks::check_squatting_reports().enqueue_versioned(&conn)?),
+        "prune_audit_tables" => Ok(tasks::prune_audit_tables().enqueue_versioned(&conn)?),
+        "prune_idempotency_keys" => Ok(tasks::prune_idempotency_keys().enqueue_versioned(&conn)?),
+        "sign_transparency_log" => Ok(tasks::sign_transparency_log().enqueue_versioned(&conn)?),
+        "verify_installability" => Ok(tasks::verify_installability().enqueue_versioned(&conn)?),
+        "reconcile_index" => Ok(tasks::reconcile_index().enqueue_versioned(&conn)?),
+        "refresh_user_profiles" => Ok(tasks::refresh_user_profiles().enqueue_versioned(&conn)?),
+        "deliver_webhooks" => Ok(tasks::deliver_webhooks().enqueue_versioned(&conn)?),
+        "squash_index" => Ok(tasks::squash_index().enqueue_versioned(&conn)?),
+        "update_index_config" => Ok(git::update_index_config().enqueue_versioned(&conn)?),
+        "generate_sitemaps" => Ok(sitemap::generate_sitemaps().enqueue_versioned(&conn)?),
+        "refresh_og_images" => Ok(og_image::refresh_og_images().enqueue_versioned(&conn)?),
+        "render_og_image" => {
+            let crate_name = args
+                .next()
+                .ok_or_else(|| String::from("Usage: enqueue-job render_og_image <crate>"))?;
+            Ok(og_image::render_og_image(crate_name).enqueue_versioned(&conn)?)
+        }
+        "sync_index" => {
+            let crate_name = args
+                .next()
+                .ok_or_else(|| String::from("Usage: enqueue-job sync_index <crate>"))?;
+            Ok(tasks::sync_index(crate_name).enqueue_versioned(&conn)?)
+        }
+        "render_crate_page" => {
+            let crate_name = args
+                .next()
+                .ok_or_else(|| String::from("Usage: enqueue-job render_crate_page <crate>"))?;
+            Ok(crate_page::render_crate_page(crate_name).enqueue_versioned(&conn)?)
+        }
+        "detect_dependency_cycles" => {
+            let version_id = args.next().and_then(|id| id.parse().ok()).ok_or_else(|| {
+                String::from("Usage: enqueue-job detect_dependency_cycles <version_id>")
+            })?;
+            Ok(tasks::detect_dependency_cycles(version_id).enqueue_versioned(&conn)?)
+        }
         "dump_db" => {
             let database_url = args.next().unwrap_or_else(|| env("READ_ONLY_REPLICA_URL"));
             let target_name = args
                 .next()
                 .unwrap_or_else(|| String::from("db-dump.tar.gz"));
-            Ok(tasks::dump_db(database_url, target_name).enqueue(&conn)?)
+            Ok(tasks::dump_db(database_url, target_name).enqueue_versioned(&conn)?)
         }
         other => Err(Error::from(format!("Unrecognized job type `{}`", other))),
     }
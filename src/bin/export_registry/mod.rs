@@ -0,0 +1,105 @@
+//! Exports crates into a self-contained registry seed for air-gapped
+//! environments, see the `export-registry` subcommand.
+//!
+//! The seed is a `.tar.gz` file laid out as a cargo local registry: the index
+//! entries of all versions in `index/` and the crate files next to it. Once
+//! unpacked, cargo can use it offline via
+//!
+//!      [source.crates-io]
+//!      replace-with = "seed"
+//!
+//!      [source.seed]
+//!      local-registry = "path/to/seed"
+//!
+//! and `crates-io-admin import-registry --from path/to/seed/index
+//! --crates-dir path/to/seed` can load it into another registry instance.
+
+use cargo_registry::{
+    git::{self, Repository, RepositoryConfig},
+    models::DependencyKind,
+    Config,
+};
+use std::{collections::BTreeMap, error::Error, fs::File, io::Write, path::Path};
+
+use flate2::{write::GzEncoder, Compression};
+use reqwest::blocking::Client;
+
+/// Exports all versions of the given crates, and with `include_deps` of the
+/// crates they (transitively) depend on, into a seed written to `output`.
+pub fn run(crates: &[String], include_deps: bool, output: &str) -> Result<(), Box<dyn Error>> {
+    let config = Config::load()?;
+    let client = Client::new();
+
+    println!("Cloning the index");
+    let repo = Repository::open(&RepositoryConfig::from_environment())?;
+
+    let mut pending = crates.to_vec();
+    let mut crates: BTreeMap<String, Vec<git::Crate>> = BTreeMap::new();
+    while let Some(name) = pending.pop() {
+        if crates.contains_key(&name.to_lowercase()) {
+            continue;
+        }
+
+        let versions = repo.crate_versions(&name)?;
+        if include_deps {
+            for dep in versions.iter().flat_map(|version| &version.deps) {
+                if let Some(DependencyKind::Dev) = dep.kind {
+                    continue;
+                }
+                pending.push(dep.package.as_ref().unwrap_or(&dep.name).clone());
+            }
+        }
+        crates.insert(name.to_lowercase(), versions);
+    }
+
+    println!("Exporting {} crates to {}", crates.len(), output);
+    let output = File::create(output)?;
+    let mut archive = tar::Builder::new(GzEncoder::new(output, Compression::default()));
+    for (name, versions) in &crates {
+        let mut index_file = Vec::new();
+        for version in versions {
+            println!("[{}-{}] Downloading", version.name, version.vers);
+            let crate_file = download(&config, &client, version)?;
+            let path = format!("{}-{}.crate", version.name, version.vers);
+            append(&mut archive, &path, &crate_file)?;
+
+            serde_json::to_writer(&mut index_file, version)?;
+            index_file.push(b'\n');
+        }
+        let path = Path::new("index").join(repo.relative_index_file(name));
+        append(&mut archive, &path, &index_file)?;
+    }
+    archive.into_inner()?.finish()?;
+
+    println!("Done");
+    Ok(())
+}
+
+/// Downloads a crate file and verifies it against the checksum in the index.
+fn download(
+    config: &Config,
+    client: &Client,
+    krate: &git::Crate,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let location = config.uploader.crate_location(&krate.name, &krate.vers);
+    let body = client.get(&location).send()?.error_for_status()?.bytes()?;
+    if hex::encode(openssl::sha::sha256(&body)) != krate.cksum {
+        return Err(format!(
+            "[{}-{}] crate file doesn't match the checksum in the index",
+            krate.name, krate.vers
+        )
+        .into());
+    }
+    Ok(body.to_vec())
+}
+
+fn append<W: Write, P: AsRef<Path>>(
+    archive: &mut tar::Builder<W>,
+    path: P,
+    data: &[u8],
+) -> std::io::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    archive.append_data(&mut header, path, data)
+}
@@ -0,0 +1,553 @@
+//! Imports crates from another registry running this codebase, see the
+//! `import-registry` subcommand.
+//!
+//! The versions to import are read from the other registry's git index, and
+//! the `.crate` files from a copy of its storage (e.g. a synced S3 bucket).
+//! Imported versions are verified against the checksums in the index, added to
+//! the local index, and recorded with the `admin-import` publish channel.
+//!
+//! The other registry's owners can't be read from its index, so the owners of
+//! newly created crates are taken from a TOML file instead:
+//!
+//!      # Owners of all crates that aren't listed below
+//!      default = ["alice"]
+//!
+//!      [crates]
+//!      internal-utils = ["bob", "carol"]
+//!
+//! The first listed owner is recorded as the publisher of the imported
+//! versions. All versions are recorded in a single transaction, so nothing is
+//! recorded if any of them fails. Their crate files are only uploaded, and the
+//! jobs adding them to the index only enqueued, once that transaction is
+//! committed. A version whose upload fails then stays out of the index and is
+//! reported, its crate file has to be uploaded and `add_crate` enqueued by
+//! hand.
+
+use cargo_registry::{
+    background_jobs::EnqueueVersioned,
+    db, git,
+    models::{
+        default_versions::update_default_version, Category, CompressionFormat, Crate, CrateOwner,
+        CratePolicy, CrateQuality, CrateUploadLimit, FundingLink, IndexSync, Keyword, NewCrate,
+        NewVersion, OwnerKind, PublishChannel, TransparencyLogEntry, User, Version,
+        VersionAnalysis, VersionFeatureDoc, VersionFile, VersionScanResult,
+    },
+    render, sbom,
+    schema::{crate_owners, dependencies, users, versions},
+    uploaders,
+    util::{
+        errors::{cargo_err, AppResult},
+        Maximums,
+    },
+    Config,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fs,
+    io::{Cursor, Read},
+    path::{Path, PathBuf},
+};
+
+use diesel::{dsl::exists, prelude::*};
+use flate2::read::GzDecoder;
+use reqwest::blocking::Client;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OnConflict {
+    Abort,
+    Skip,
+}
+
+#[derive(Deserialize)]
+struct OwnersFile {
+    #[serde(default)]
+    default: Vec<String>,
+    #[serde(default)]
+    crates: HashMap<String, Vec<String>>,
+}
+
+impl OwnersFile {
+    fn logins(&self, crate_name: &str) -> &[String] {
+        self.crates.get(crate_name).unwrap_or(&self.default)
+    }
+}
+
+/// The parts of a (normalized) `Cargo.toml` that aren't part of the index.
+#[derive(Deserialize)]
+struct Manifest {
+    package: Package,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Package {
+    description: Option<String>,
+    homepage: Option<String>,
+    documentation: Option<String>,
+    repository: Option<String>,
+    license: Option<String>,
+    license_file: Option<String>,
+    readme: Option<String>,
+    #[serde(default)]
+    authors: Vec<String>,
+    #[serde(default)]
+    keywords: Vec<String>,
+    #[serde(default)]
+    categories: Vec<String>,
+}
+
+struct Importer {
+    config: Config,
+    client: Client,
+    crates_dir: PathBuf,
+    owners: OwnersFile,
+    on_conflict: OnConflict,
+}
+
+/// A version which was recorded, but whose crate file isn't uploaded yet.
+struct Imported {
+    prefix: String,
+    version_id: i32,
+    crate_file: PathBuf,
+    readme: Option<Readme>,
+    index_entry: git::Crate,
+}
+
+struct Readme {
+    text: String,
+    file: String,
+    repository: Option<String>,
+    vcs_commit: Option<String>,
+}
+
+/// Imports the given crates, or all crates of the index if none are given.
+pub fn run(
+    from: &str,
+    crates_dir: &Path,
+    owners_file: &Path,
+    only: &[String],
+    on_conflict: OnConflict,
+) -> Result<(), Box<dyn Error>> {
+    let owners: OwnersFile = toml::from_str(&fs::read_to_string(owners_file)?)?;
+
+    let checkout = tempfile::tempdir()?;
+    let index_path = if Path::new(from).is_dir() {
+        Path::new(from)
+    } else {
+        println!("Cloning {}", from);
+        git2::Repository::clone(from, checkout.path())?;
+        checkout.path()
+    };
+
+    let index = read_index(index_path, only).map_err(|e| e.to_string())?;
+    println!("Importing {} crates", index.len());
+
+    let importer = Importer {
+        config: Config::load()?,
+        client: Client::new(),
+        crates_dir: crates_dir.to_path_buf(),
+        owners,
+        on_conflict,
+    };
+
+    let conn = db::connect_now()?;
+    let (imported, skipped) = conn
+        .transaction(|| importer.import(&conn, index))
+        .map_err(|e| format!("Import failed, nothing was imported: {}", e))?;
+    println!("Recorded {} versions, skipped {}", imported.len(), skipped);
+
+    let mut failed = Vec::new();
+    for version in &imported {
+        match importer.publish(&conn, version) {
+            Ok(()) => println!("{} Imported", version.prefix),
+            Err(e) => {
+                println!("{} Upload failed: {}", version.prefix, e);
+                failed.push(version.prefix.as_str());
+            }
+        }
+    }
+    if !failed.is_empty() {
+        return Err(format!(
+            "{} versions were recorded, but aren't in the index: {}",
+            failed.len(),
+            failed.join(", ")
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Reads the index entries of all crates in a checkout of an index, or only
+/// of the given crates if any are given.
+fn read_index(path: &Path, only: &[String]) -> AppResult<BTreeMap<String, Vec<git::Crate>>> {
+    let only = only
+        .iter()
+        .map(|name| name.to_lowercase())
+        .collect::<Vec<_>>();
+    let mut index = BTreeMap::new();
+    let mut dirs = vec![path.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            if file_name.starts_with('.') || (dir == path && file_name == "config.json") {
+                continue;
+            }
+            if entry.file_type()?.is_dir() {
+                dirs.push(entry.path());
+                continue;
+            }
+            if !only.is_empty() && !only.contains(&file_name) {
+                continue;
+            }
+
+            let versions = fs::read_to_string(entry.path())?
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(serde_json::from_str)
+                .collect::<Result<Vec<git::Crate>, _>>()?;
+            index.insert(file_name, versions);
+        }
+    }
+
+    if let Some(missing) = only.iter().find(|name| !index.contains_key(*name)) {
+        return Err(cargo_err(&format_args!("`{}` isn't in the index", missing)));
+    }
+    Ok(index)
+}
+
+impl Importer {
+    /// Records all versions and returns the recorded ones and how many were
+    /// skipped.
+    fn import(
+        &self,
+        conn: &PgConnection,
+        index: BTreeMap<String, Vec<git::Crate>>,
+    ) -> AppResult<(Vec<Imported>, usize)> {
+        // Dependencies reference crates rather than versions, so all crates
+        // have to exist before the first version can be imported.
+        for versions in index.values() {
+            if let Some(first) = versions.first() {
+                self.ensure_crate(conn, &first.name)?;
+            }
+        }
+
+        let (mut imported, mut skipped) = (Vec::new(), 0);
+        for entry in index.into_iter().flat_map(|(_, versions)| versions) {
+            match self.import_version(conn, entry)? {
+                Some(version) => imported.push(version),
+                None => skipped += 1,
+            }
+        }
+        Ok((imported, skipped))
+    }
+
+    /// Looks up the local users for the owners of a crate in the owners file.
+    fn owners(&self, conn: &PgConnection, crate_name: &str) -> AppResult<Vec<User>> {
+        let logins = self.owners.logins(crate_name);
+        if logins.is_empty() {
+            return Err(cargo_err(&format_args!(
+                "the owners file doesn't list any owners for `{}`",
+                crate_name
+            )));
+        }
+
+        logins
+            .iter()
+            .map(|login| {
+                users::table
+                    .filter(users::gh_login.eq(login))
+                    .first::<User>(conn)
+                    .optional()?
+                    .ok_or_else(|| cargo_err(&format_args!("no user with the login `{}`", login)))
+            })
+            .collect()
+    }
+
+    /// Creates a crate with the owners from the owners file, unless a crate
+    /// with that name already exists on this registry.
+    fn ensure_crate(&self, conn: &PgConnection, crate_name: &str) -> AppResult<()> {
+        let existing = Crate::by_exact_name(crate_name)
+            .first::<Crate>(conn)
+            .optional()?;
+        if existing.is_some() {
+            return Ok(());
+        }
+
+        let owners = self.owners(conn, crate_name)?;
+        let krate = NewCrate {
+            name: crate_name,
+            description: None,
+            homepage: None,
+            documentation: None,
+            readme: None,
+            repository: None,
+            max_upload_size: None,
+        }
+        .create_or_update(conn, owners[0].id, None)?;
+
+        for owner in &owners[1..] {
+            diesel::insert_into(crate_owners::table)
+                .values(&CrateOwner {
+                    crate_id: krate.id,
+                    owner_id: owner.id,
+                    created_by: owners[0].id,
+                    owner_kind: OwnerKind::User as i32,
+                    email_notifications: true,
+                })
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+        }
+        Ok(())
+    }
+
+    /// Records a single version, returning `None` if it was skipped.
+    fn import_version(
+        &self,
+        conn: &PgConnection,
+        entry: git::Crate,
+    ) -> AppResult<Option<Imported>> {
+        let krate = Crate::by_exact_name(&entry.name).first::<Crate>(conn)?;
+        let vers = semver::Version::parse(&entry.vers)
+            .map_err(|e| cargo_err(&format_args!("[{}-{}] {}", entry.name, entry.vers, e)))?;
+
+        let already_imported = versions::table
+            .filter(versions::crate_id.eq(krate.id))
+            .filter(versions::num.eq(&entry.vers));
+        if diesel::select(exists(already_imported)).get_result(conn)? {
+            if self.on_conflict == OnConflict::Skip {
+                println!("[{}-{}] Already exists, skipping", entry.name, entry.vers);
+                return Ok(None);
+            }
+            return Err(cargo_err(&format_args!(
+                "[{}-{}] already exists on this registry, use `--on-conflict skip` \
+                 to skip existing versions",
+                entry.name, entry.vers
+            )));
+        }
+
+        let file_name = format!("{}-{}.crate", entry.name, entry.vers);
+        let storage_path = self.crates_dir.join("crates").join(&entry.name);
+        let crate_file = if storage_path.is_dir() {
+            storage_path.join(&file_name)
+        } else {
+            self.crates_dir.join(&file_name)
+        };
+        let tarball = fs::read(&crate_file)?;
+        if hex::encode(openssl::sha::sha256(&tarball)) != entry.cksum {
+            return Err(cargo_err(&format_args!(
+                "[{}-{}] crate file doesn't match the checksum in the index",
+                entry.name, entry.vers
+            )));
+        }
+
+        let prefix = format!("{}-{}", entry.name, entry.vers);
+        let manifest = read_file(&tarball, &format!("{}/Cargo.toml", prefix))?
+            .ok_or_else(|| cargo_err(&format_args!("[{}] crate file has no Cargo.toml", prefix)))?;
+        let package = toml::from_str::<Manifest>(&manifest)
+            .map_err(|e| cargo_err(&format_args!("[{}] invalid Cargo.toml: {}", prefix, e)))?
+            .package;
+
+        let owners = self.owners(conn, &entry.name)?;
+        let publisher = &owners[0];
+        let krate = NewCrate {
+            name: &krate.name,
+            description: package.description.as_deref(),
+            homepage: package.homepage.as_deref(),
+            documentation: package.documentation.as_deref(),
+            readme: None,
+            repository: package.repository.as_deref(),
+            max_upload_size: None,
+        }
+        .create_or_update(conn, publisher.id, None)?;
+
+        let version = NewVersion::new(
+            krate.id,
+            &vers,
+            &entry.features,
+            package.license.clone(),
+            package.license_file.as_deref(),
+            tarball.len() as i32,
+            publisher.id,
+        )?
+        .with_publish_channel(PublishChannel::AdminImport)
+        .save(
+            conn,
+            &package.authors,
+            &publisher.verified_email(conn)?.unwrap_or_default(),
+        )?;
+
+        Version::record_links(version.id, entry.links.as_deref(), conn)?;
+        if entry.yanked == Some(true) {
+            diesel::update(versions::table.find(version.id))
+                .set(versions::yanked.eq(true))
+                .execute(conn)?;
+        }
+
+        let new_dependencies = entry
+            .deps
+            .iter()
+            .map(|dep| {
+                let name = dep.package.as_ref().unwrap_or(&dep.name);
+                let dep_crate = Crate::by_exact_name(name)
+                    .first::<Crate>(conn)
+                    .optional()?
+                    .ok_or_else(|| {
+                        cargo_err(&format_args!(
+                            "[{}] depends on `{}`, which doesn't exist on this registry",
+                            prefix, name
+                        ))
+                    })?;
+                Ok((
+                    dependencies::version_id.eq(version.id),
+                    dependencies::crate_id.eq(dep_crate.id),
+                    dependencies::req.eq(&dep.req),
+                    dep.kind.map(|k| dependencies::kind.eq(k as i32)),
+                    dependencies::optional.eq(dep.optional),
+                    dependencies::default_features.eq(dep.default_features),
+                    dependencies::features.eq(&dep.features),
+                    dependencies::target.eq(dep.target.as_deref()),
+                    dependencies::explicit_name.eq(dep.package.as_ref().map(|_| dep.name.as_str())),
+                ))
+            })
+            .collect::<AppResult<Vec<_>>>()?;
+        diesel::insert_into(dependencies::table)
+            .values(&new_dependencies)
+            .execute(conn)?;
+
+        let keywords = package.keywords.iter().map(|s| &**s).collect::<Vec<_>>();
+        Keyword::update_crate(conn, &krate, &keywords)?;
+        let categories = package.categories.iter().map(|s| &**s).collect::<Vec<_>>();
+        let invalid_categories = Category::update_crate(conn, &krate, &categories)?;
+        if !invalid_categories.is_empty() {
+            println!(
+                "[{}] Ignoring unknown categories: {}",
+                prefix,
+                invalid_categories.join(", ")
+            );
+        }
+
+        let readme = match package.readme {
+            Some(readme_file) => match read_file(&tarball, &format!("{}/{}", prefix, readme_file))?
+            {
+                Some(text) => {
+                    let vcs_info_path = format!("{}/{}", prefix, uploaders::VCS_INFO_FILE);
+                    let vcs_commit = read_file(&tarball, &vcs_info_path)?
+                        .and_then(|vcs_info| uploaders::vcs_commit(vcs_info.as_bytes()));
+                    Some(Readme {
+                        text,
+                        file: readme_file,
+                        repository: package.repository,
+                        vcs_commit,
+                    })
+                }
+                None => None,
+            },
+            None => None,
+        };
+
+        let max_upload_size =
+            CrateUploadLimit::active_for(conn, krate.id)?.or(krate.max_upload_size);
+        let maximums = Maximums::new(
+            max_upload_size,
+            self.config.max_upload_size,
+            self.config.max_unpack_size,
+        )
+        .with_compression_ratios(
+            self.config.max_compression_ratio,
+            self.config.max_file_compression_ratio,
+        )
+        .with_entry_limits(self.config.max_file_count, self.config.max_path_length);
+        let uploaded = uploaders::verify_crate_file(
+            &krate,
+            &vers,
+            &tarball,
+            maximums,
+            self.config.accept_zstd_crates,
+        )?;
+        CratePolicy::update_crate(conn, krate.id, version.id, uploaded.policy_file)?;
+        CrateQuality::record_tests(conn, krate.id, uploaded.has_tests)?;
+        FundingLink::update_crate(conn, krate.id, &uploaded.funding_links)?;
+        VersionFeatureDoc::save(conn, version.id, &entry.features, uploaded.feature_docs)?;
+        // Imported versions are already in the index of the other registry,
+        // so they aren't held even if something was found
+        VersionScanResult::save(conn, version.id, &uploaded.analyses)?;
+        VersionAnalysis::save(conn, version.id, uploaded.analyses)?;
+        VersionFile::save(conn, version.id, &uploaded.files)?;
+        Version::record_checksum(version.id, &entry.cksum, conn)?;
+        Version::record_compression(version.id, uploaded.compression, conn)?;
+        Version::record_rust_version(version.id, entry.rust_version.as_deref(), conn)?;
+        TransparencyLogEntry::append(conn, &krate.name, &version.num, &entry.cksum)?;
+        update_default_version(krate.id, conn)?;
+
+        // The index entry is copied as is, so that dependency renames and
+        // the yanked state are preserved. Only the compression is taken from
+        // the crate file, since older registries don't record it.
+        let index_entry = git::Crate {
+            compression: Some(uploaded.compression).filter(|&c| c != CompressionFormat::Gzip),
+            ..entry
+        };
+        Ok(Some(Imported {
+            prefix: format!("[{}]", prefix),
+            version_id: version.id,
+            crate_file,
+            readme,
+            index_entry,
+        }))
+    }
+
+    /// Uploads the crate file of a recorded version, and only then enqueues
+    /// the jobs which add it to the index and render its readme.
+    fn publish(&self, conn: &PgConnection, imported: &Imported) -> Result<(), Box<dyn Error>> {
+        let entry = &imported.index_entry;
+        let tarball = fs::read(&imported.crate_file)?;
+        let content_length = tarball.len() as u64;
+        self.config
+            .uploader
+            .store_crate_file(
+                &self.client,
+                &entry.name,
+                &entry.vers,
+                Cursor::new(tarball),
+                content_length,
+            )
+            .map_err(|e| e.to_string())?;
+
+        if let Some(readme) = &imported.readme {
+            render::render_and_upload_readme(
+                imported.version_id,
+                readme.text.clone(),
+                readme.file.clone(),
+                readme.repository.clone(),
+                readme.vcs_commit.clone(),
+            )
+            .enqueue_versioned(conn)?;
+        }
+        sbom::generate_sbom(imported.version_id).enqueue_versioned(conn)?;
+        conn.transaction::<_, Box<dyn Error>, _>(|| {
+            git::add_crate(entry.clone()).enqueue_versioned(conn)?;
+            IndexSync::queued(conn, imported.version_id)?;
+            Ok(())
+        })
+    }
+}
+
+/// Reads a UTF-8 encoded file from a crate file.
+fn read_file(tarball: &[u8], path: &str) -> AppResult<Option<String>> {
+    let decoder: Box<dyn Read + '_> = match uploaders::detect_compression(tarball) {
+        CompressionFormat::Gzip => Box::new(GzDecoder::new(tarball)),
+        CompressionFormat::Zstd => Box::new(zstd::Decoder::new(tarball)?),
+    };
+    let mut archive = tar::Archive::new(decoder);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.path()?.to_str() == Some(path) {
+            let mut contents = String::new();
+            entry.read_to_string(&mut contents)?;
+            return Ok(Some(contents));
+        }
+    }
+    Ok(None)
+}
@@ -0,0 +1,84 @@
+// Manages the `config.json` file of the index, which tells cargo where to
+// download crate files from.
+//
+// Admins can replace the download endpoints and the checksum URL template
+// with `PUT /api/private/admin/index-config` as well.
+//
+// Every change enqueues a job which writes the new file to the index. The
+// `dl`, `api` and `auth-required` fields follow the server configuration
+// instead, see `IndexSettings`, and are rewritten whenever the background
+// worker boots.
+
+#![warn(clippy::all, rust_2018_idioms)]
+
+#[macro_use]
+extern crate serde;
+
+use cargo_registry::{
+    background_jobs::EnqueueVersioned,
+    db, git,
+    models::{IndexConfig, IndexSettings},
+    Config,
+};
+use std::error::Error;
+
+use docopt::Docopt;
+
+const USAGE: &str = "
+Usage: index-config show
+       index-config add-download-endpoint [options] <url>
+       index-config remove-download-endpoint <url>
+       index-config set-checksum-url <template>
+       index-config unset-checksum-url
+       index-config --help
+
+Shows and changes the download endpoints and the checksum URL template
+advertised in the `config.json` file of the index.
+
+Download endpoints may contain the `{crate}` and `{version}` markers, the
+checksum URL template has to contain both of them.
+
+Options:
+    -h, --help           Show this message.
+    --priority PRIORITY  Endpoints with a higher priority are tried first [default: 0].
+";
+
+#[derive(Deserialize)]
+struct Args {
+    cmd_show: bool,
+    cmd_add_download_endpoint: bool,
+    cmd_remove_download_endpoint: bool,
+    cmd_set_checksum_url: bool,
+    cmd_unset_checksum_url: bool,
+    arg_url: String,
+    arg_template: String,
+    flag_priority: i32,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Args = Docopt::new(USAGE)
+        .and_then(|d| d.deserialize())
+        .unwrap_or_else(|e| e.exit());
+    let conn = db::connect_now()?;
+
+    if args.cmd_add_download_endpoint {
+        IndexConfig::add_download_endpoint(&conn, &args.arg_url, args.flag_priority)?;
+    } else if args.cmd_remove_download_endpoint {
+        if !IndexConfig::remove_download_endpoint(&conn, &args.arg_url)? {
+            return Err(format!("no download endpoint `{}`", args.arg_url).into());
+        }
+    } else if args.cmd_set_checksum_url {
+        IndexConfig::set_checksum_url(&conn, Some(&args.arg_template))?;
+    } else if args.cmd_unset_checksum_url {
+        IndexConfig::set_checksum_url(&conn, None)?;
+    }
+
+    let settings = IndexSettings::from_environment(&Config::default().uploader)?;
+    print!("{}", IndexConfig::load(&conn, &settings)?.to_json()?);
+
+    if !args.cmd_show {
+        git::update_index_config().enqueue_versioned(&conn)?;
+        println!("Enqueued a job to update the index");
+    }
+    Ok(())
+}
@@ -0,0 +1,177 @@
+// Runs the pending database migrations, and checks them before they're
+// deployed, see the `migration_safety` module.
+//
+// In production, migrations with statements which lock busy tables for long
+// are refused, and each migration only waits `LOCK_TIMEOUT` for its locks.
+//
+// `check` applies the pending migrations to a database restored from a
+// database dump, in the database at `SHADOW_DATABASE_URL`, and reports how
+// long their statements took and their locks were held. It fails if a
+// statement or a lock blocking writes took longer than allowed, or if a
+// migration would be refused in production.
+
+#![warn(clippy::all, rust_2018_idioms)]
+
+#[macro_use]
+extern crate serde;
+
+use cargo_registry::{
+    db,
+    migration_safety::{Migration, Report},
+    tasks::dump_db::run_psql,
+};
+use std::error::Error;
+use std::path::Path;
+use std::time::Duration;
+
+use diesel::connection::SimpleConnection;
+use diesel::prelude::*;
+use docopt::Docopt;
+
+const USAGE: &str = "
+Usage: migrate [options]
+       migrate check [options] <dump-dir>
+       migrate --help
+
+Runs the pending migrations of the `migrations` directory.
+
+`check` restores the database dump in <dump-dir> into the database at
+SHADOW_DATABASE_URL, destroying all of its data, and applies the pending
+migrations to it.
+
+Options:
+    -h, --help                Show this message.
+    --max-statement-ms MS     Statements may take this long [default: 1000].
+    --max-lock-ms MS          Locks blocking writes may be held this long [default: 1000].
+    --json                    Print the reports of `check` as JSON.
+";
+
+/// How long a migration waits for a lock in production before it fails.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Deserialize)]
+struct Args {
+    cmd_check: bool,
+    arg_dump_dir: String,
+    flag_max_statement_ms: u64,
+    flag_max_lock_ms: u64,
+    flag_json: bool,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Args = Docopt::new(USAGE)
+        .and_then(|d| d.deserialize())
+        .unwrap_or_else(|e| e.exit());
+    let migrations = Migration::load_all(Path::new("migrations"))?;
+
+    if args.cmd_check {
+        return check(&args, migrations);
+    }
+
+    let production = dotenv::var("HEROKU").is_ok();
+    let conn = db::connect_now()?;
+    let pending = Migration::pending(&conn, migrations)?;
+    let mut refused = false;
+    for migration in &pending {
+        for violation in migration.lint() {
+            println!(
+                "{} {}: {}\n    {}",
+                migration.name, violation.rule, violation.reason, violation.statement
+            );
+            refused = true;
+        }
+    }
+    if refused && production {
+        return Err("refusing to run the migrations, \
+                    see the `migration_safety` module for how to allow these statements"
+            .into());
+    }
+
+    let lock_timeout = if production { Some(LOCK_TIMEOUT) } else { None };
+    for migration in &pending {
+        println!("Running migration {}", migration.name);
+        migration.run(&conn, lock_timeout)?;
+    }
+    Ok(())
+}
+
+fn check(args: &Args, migrations: Vec<Migration>) -> Result<(), Box<dyn Error>> {
+    let shadow_url = dotenv::var("SHADOW_DATABASE_URL")
+        .map_err(|_| "SHADOW_DATABASE_URL must be set to check migrations")?;
+    if dotenv::var("DATABASE_URL").ok().as_ref() == Some(&shadow_url) {
+        return Err("SHADOW_DATABASE_URL must not be the database of the app".into());
+    }
+
+    let dump_dir = Path::new(&args.arg_dump_dir);
+    let conn = PgConnection::establish(&shadow_url)?;
+    conn.batch_execute("DROP SCHEMA public CASCADE; CREATE SCHEMA public;")?;
+    run_psql(&dump_dir.join("schema.sql"), &shadow_url)?;
+    run_psql(&dump_dir.join("import.sql"), &shadow_url)?;
+
+    let mut reports = Vec::new();
+    for migration in Migration::pending(&conn, migrations)? {
+        reports.push(migration.apply_measured(&conn)?);
+    }
+
+    let mut failed = false;
+    for report in &reports {
+        let problems = problems(report, args);
+        if !args.flag_json {
+            print_report(report, &problems);
+        }
+        failed |= !problems.is_empty();
+    }
+    if args.flag_json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+    }
+    if failed {
+        return Err("the pending migrations aren't safe to run in production".into());
+    }
+    Ok(())
+}
+
+/// Why a migration shouldn't be run in production as it is.
+fn problems(report: &Report, args: &Args) -> Vec<String> {
+    let slow_statements = report
+        .statements
+        .iter()
+        .filter(|statement| statement.duration_ms > args.flag_max_statement_ms)
+        .map(|statement| format!("took {}ms: {}", statement.duration_ms, statement.statement));
+    let long_locks = report
+        .locks
+        .iter()
+        .filter(|lock| lock.blocks_writes() && lock.held_ms > args.flag_max_lock_ms)
+        .map(|lock| {
+            format!(
+                "blocked writes to `{}` for {}ms with a {}",
+                lock.relation, lock.held_ms, lock.mode
+            )
+        });
+    let violations = report
+        .violations
+        .iter()
+        .map(|violation| format!("{}: {}", violation.rule, violation.reason));
+    slow_statements
+        .chain(long_locks)
+        .chain(violations)
+        .collect()
+}
+
+fn print_report(report: &Report, problems: &[String]) {
+    println!("{} took {}ms", report.migration, report.duration_ms);
+    for statement in &report.statements {
+        println!(
+            "    {:>6}ms  {}",
+            statement.duration_ms, statement.statement
+        );
+    }
+    for lock in &report.locks {
+        println!(
+            "    held {} on `{}` for {}ms",
+            lock.mode, lock.relation, lock.held_ms
+        );
+    }
+    for problem in problems {
+        println!("  ! {}", problem);
+    }
+}
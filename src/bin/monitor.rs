@@ -15,7 +15,9 @@ fn main() -> Result<(), Error> {
     let conn = db::connect_now()?;
 
     check_stalled_background_jobs(&conn)?;
+    check_stuck_background_jobs(&conn)?;
     check_spam_attack(&conn)?;
+    check_failed_install_checks(&conn)?;
     Ok(())
 }
 
@@ -55,6 +57,109 @@ fn check_stalled_background_jobs(conn: &PgConnection) -> Result<(), Error> {
     Ok(())
 }
 
+/// Checks the heartbeats of running jobs for jobs which have been running for
+/// longer than expected.
+///
+/// - `MAX_JOB_DURATIONS`: Per job type overrides of `MAX_JOB_TIME` in minutes,
+///   e.g. `dump_db=120,update_downloads=30`.
+/// - `STUCK_JOB_POLICY`: Either `alert` (the default) to only page whoever is
+///   on call, or `retry` to additionally make the worker running a stuck job
+///   exit, so that the job is retried once the worker restarts.
+fn check_stuck_background_jobs(conn: &PgConnection) -> Result<(), Error> {
+    use cargo_registry::background_jobs::HEARTBEAT_INTERVAL;
+    use chrono::{Duration, NaiveDateTime, Utc};
+    use diesel::dsl::now;
+
+    const EVENT_KEY: &str = "stuck_background_jobs";
+
+    println!("Checking for stuck background jobs");
+
+    let default_max_duration = dotenv::var("MAX_JOB_TIME")
+        .map(|s| s.parse::<i64>().unwrap())
+        .unwrap_or(15);
+    let max_durations = dotenv::var("MAX_JOB_DURATIONS").unwrap_or_default();
+    let max_durations = max_durations
+        .split(',')
+        .filter_map(|entry| {
+            let mut parts = entry.splitn(2, '=');
+            let job_type = parts.next()?.trim();
+            let minutes = parts.next()?.trim().parse::<i64>().ok()?;
+            Some((job_type, minutes))
+        })
+        .collect::<Vec<_>>();
+    let retry = match dotenv::var("STUCK_JOB_POLICY").as_ref().map(|s| s.as_str()) {
+        Err(_) | Ok("alert") => false,
+        Ok("retry") => true,
+        Ok(other) => return Err(format!("Unknown STUCK_JOB_POLICY `{}`", other).into()),
+    };
+
+    // Workers which died without removing their heartbeats have released the
+    // locks of their jobs, so the jobs will be retried anyway.
+    let now_utc = Utc::now().naive_utc();
+    let orphaned_before = now_utc - Duration::from_std(HEARTBEAT_INTERVAL * 10).unwrap();
+    let orphaned = diesel::delete(
+        background_job_heartbeats::table
+            .filter(background_job_heartbeats::heartbeat_at.lt(orphaned_before)),
+    )
+    .execute(conn)?;
+    if orphaned > 0 {
+        println!("Removed {} heartbeats of dead workers", orphaned);
+    }
+
+    let running = background_job_heartbeats::table
+        .select((
+            background_job_heartbeats::id,
+            background_job_heartbeats::job_type,
+            background_job_heartbeats::worker,
+            background_job_heartbeats::started_at,
+        ))
+        .load::<(i32, String, String, NaiveDateTime)>(conn)?;
+
+    let mut stuck = Vec::new();
+    for (id, job_type, worker, started_at) in running {
+        let max_duration = max_durations
+            .iter()
+            .find(|(name, _)| *name == job_type)
+            .map_or(default_max_duration, |&(_, minutes)| minutes);
+        if started_at < now_utc - Duration::minutes(max_duration) {
+            println!(
+                "`{}` on {} has been running since {}",
+                job_type, worker, started_at
+            );
+            stuck.push(id);
+        }
+    }
+
+    if retry && !stuck.is_empty() {
+        diesel::update(
+            background_job_heartbeats::table
+                .filter(background_job_heartbeats::id.eq_any(&stuck))
+                .filter(background_job_heartbeats::cancel_requested_at.is_null()),
+        )
+        .set(background_job_heartbeats::cancel_requested_at.eq(now.nullable()))
+        .execute(conn)?;
+    }
+
+    let event = if stuck.is_empty() {
+        on_call::Event::Resolve {
+            incident_key: EVENT_KEY.into(),
+            description: Some("No stuck background jobs".into()),
+        }
+    } else {
+        on_call::Event::Trigger {
+            incident_key: Some(EVENT_KEY.into()),
+            description: format!(
+                "{} jobs have been running for longer than expected{}",
+                stuck.len(),
+                if retry { ", retrying them" } else { "" }
+            ),
+        }
+    };
+
+    log_and_trigger_event(event)?;
+    Ok(())
+}
+
 fn check_spam_attack(conn: &PgConnection) -> Result<(), Error> {
     use cargo_registry::models::krate::canon_crate_name;
     use diesel::dsl::*;
@@ -116,6 +221,37 @@ fn check_spam_attack(conn: &PgConnection) -> Result<(), Error> {
     Ok(())
 }
 
+/// Checks whether versions failed the check of the `verify_installability`
+/// job, which means that cargo can't install them through the CDN.
+fn check_failed_install_checks(conn: &PgConnection) -> Result<(), Error> {
+    use cargo_registry::models::VersionInstallCheck;
+
+    const EVENT_KEY: &str = "install_checks";
+
+    println!("Checking for versions which can't be installed");
+
+    let failing = VersionInstallCheck::failing(conn)?;
+    let event = match failing.first() {
+        Some((check, name, num)) => on_call::Event::Trigger {
+            incident_key: Some(EVENT_KEY.into()),
+            description: format!(
+                "{} versions can't be installed, e.g. {}#{}: {}",
+                failing.len(),
+                name,
+                num,
+                check.error.as_deref().unwrap_or("unknown error")
+            ),
+        },
+        None => on_call::Event::Resolve {
+            incident_key: EVENT_KEY.into(),
+            description: Some("All checked versions can be installed".into()),
+        },
+    };
+
+    log_and_trigger_event(event)?;
+    Ok(())
+}
+
 fn log_and_trigger_event(event: on_call::Event) -> Result<(), Error> {
     match event {
         on_call::Event::Trigger {
@@ -13,7 +13,7 @@ use cargo_registry::{
     models::Version,
     render::readme_to_html,
     schema::{crates, readme_renderings, versions},
-    Config,
+    uploaders, Config,
 };
 use std::{io::Read, path::Path, thread};
 
@@ -191,7 +191,21 @@ fn get_readme(
         return None;
     }
 
-    let reader = GzDecoder::new(response);
+    // The crate file is searched twice, since the commit it was packaged
+    // from can be recorded anywhere in it
+    let body = match response.bytes() {
+        Ok(body) => body,
+        Err(err) => {
+            println!(
+                "[{}-{}] Unable to read crate: {}",
+                krate_name, version.num, err
+            );
+            return None;
+        }
+    };
+    let vcs_commit = find_vcs_commit(&body, version, krate_name);
+
+    let reader = GzDecoder::new(&body[..]);
     let mut archive = Archive::new(reader);
     let mut entries = archive.entries().unwrap_or_else(|_| {
         panic!(
@@ -224,6 +238,7 @@ fn get_readme(
                 .as_ref()
                 .map_or("README.md", |e| &**e),
             manifest.package.repository.as_deref(),
+            vcs_commit.as_deref(),
         )
     };
     return Some(rendered);
@@ -241,6 +256,20 @@ fn get_readme(
     }
 }
 
+/// The commit recorded in the `.cargo_vcs_info.json` of a crate file, if any.
+fn find_vcs_commit(crate_file: &[u8], version: &Version, krate_name: &str) -> Option<String> {
+    let path = Path::new(&format!("{}-{}", krate_name, version.num)).join(uploaders::VCS_INFO_FILE);
+    let mut archive = Archive::new(GzDecoder::new(crate_file));
+    let mut entry = archive
+        .entries()
+        .ok()?
+        .filter_map(Result::ok)
+        .find(|entry| entry.path().map_or(false, |p| p == path))?;
+    let mut contents = Vec::new();
+    entry.read_to_end(&mut contents).ok()?;
+    uploaders::vcs_commit(&contents)
+}
+
 /// Search an entry by its path in a Tar archive.
 fn find_file_by_path<R: Read>(
     entries: &mut tar::Entries<'_, R>,
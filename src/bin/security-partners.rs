@@ -0,0 +1,148 @@
+// Manages the security partners which submit verdicts about versions, and
+// works through the review queue of their verdicts.
+//
+// `add` prints the API key of the new partner, which has to be sent in the
+// `X-Partner-Key` header of its requests.
+
+#![warn(clippy::all, rust_2018_idioms)]
+
+#[macro_use]
+extern crate serde;
+
+use cargo_registry::{
+    db,
+    models::{
+        security_partner::{self, CONFIRMED, DISMISSED},
+        Crate, SecurityPartner, VersionVerdict,
+    },
+    schema::{security_partners, versions},
+};
+use std::error::Error;
+
+use chrono::Utc;
+use diesel::prelude::*;
+use docopt::Docopt;
+
+const USAGE: &str = "
+Usage: security-partners add [options] <name> <contact-email>
+       security-partners revoke <name>
+       security-partners audit <name>
+       security-partners queue
+       security-partners review [--quarantine] <verdict-id> <decision>
+       security-partners release <crate> <version>
+       security-partners --help
+
+Registers and revokes security partners, and shows their audit logs.
+
+`queue` lists the verdicts waiting for a review, and `review` records the
+decision about one of them. <decision> is either `confirmed` or `dismissed`.
+Confirmed verdicts can quarantine their version, which keeps it from being
+downloaded until it's released again. Dismissing a verdict releases the
+quarantine it caused.
+
+Options:
+    -h, --help                Show this message.
+    --verdicts-per-day LIMIT  The quota of the partner [default: 1000].
+    --auto-quarantine         Quarantine versions on malicious verdicts of
+                              the partner before they're reviewed.
+    --quarantine              Quarantine the version of a confirmed verdict.
+";
+
+#[derive(Deserialize)]
+struct Args {
+    cmd_add: bool,
+    cmd_revoke: bool,
+    cmd_audit: bool,
+    cmd_queue: bool,
+    cmd_review: bool,
+    cmd_release: bool,
+    arg_name: String,
+    arg_contact_email: String,
+    arg_verdict_id: Option<i32>,
+    arg_decision: String,
+    arg_crate: String,
+    arg_version: String,
+    flag_verdicts_per_day: i32,
+    flag_auto_quarantine: bool,
+    flag_quarantine: bool,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Args = Docopt::new(USAGE)
+        .and_then(|d| d.deserialize())
+        .unwrap_or_else(|e| e.exit());
+    let conn = db::connect_now()?;
+
+    if args.cmd_add {
+        let partner = SecurityPartner::create(
+            &conn,
+            &args.arg_name,
+            &args.arg_contact_email,
+            args.flag_verdicts_per_day,
+            args.flag_auto_quarantine,
+        )?;
+        println!(
+            "Registered `{}` with a quota of {} verdicts per day, its API key is {}",
+            partner.name, partner.verdicts_per_day, partner.api_key
+        );
+    } else if args.cmd_revoke {
+        let revoked = diesel::update(
+            security_partners::table.filter(security_partners::name.eq(&args.arg_name)),
+        )
+        .set(security_partners::revoked.eq(true))
+        .execute(&conn)?;
+        if revoked == 0 {
+            return Err(format!("no security partner named `{}`", args.arg_name).into());
+        }
+        println!("Revoked the API key of `{}`", args.arg_name);
+    } else if args.cmd_audit {
+        let partner = security_partners::table
+            .filter(security_partners::name.eq(&args.arg_name))
+            .first::<SecurityPartner>(&conn)?;
+        for (action, details, time) in partner.audit_log(&conn, 100)? {
+            println!("{}  {:<18} {}", time, action, details);
+        }
+    } else if args.cmd_queue {
+        for (verdict, partner, krate, version) in VersionVerdict::pending(&conn)? {
+            println!(
+                "#{} {} {} is {} according to {} ({})",
+                verdict.id, krate, version, verdict.verdict, partner, verdict.created_at
+            );
+            if let Some(details) = verdict.details {
+                println!("    {}", details);
+            }
+        }
+    } else if args.cmd_review {
+        let decision = args.arg_decision.as_str();
+        if decision != CONFIRMED && decision != DISMISSED {
+            return Err(format!("unknown decision `{}`", decision).into());
+        }
+        if args.flag_quarantine && decision != CONFIRMED {
+            return Err("only confirmed verdicts can quarantine a version".into());
+        }
+        let id = args.arg_verdict_id.ok_or("missing verdict ID")?;
+
+        conn.transaction::<_, Box<dyn Error>, _>(|| {
+            let verdict = VersionVerdict::review(&conn, id, decision, Utc::now().naive_utc())?
+                .ok_or_else(|| format!("verdict {} isn't waiting for a review", id))?;
+            if args.flag_quarantine {
+                security_partner::quarantine(&conn, verdict.version_id, Some(verdict.id))?;
+                println!("Quarantined the version of verdict {}", verdict.id);
+            }
+            println!("Verdict {} was {}", verdict.id, decision);
+            Ok(())
+        })?;
+    } else if args.cmd_release {
+        let krate = Crate::by_name(&args.arg_crate).first::<Crate>(&conn)?;
+        let version_id = versions::table
+            .filter(versions::crate_id.eq(krate.id))
+            .filter(versions::num.eq(&args.arg_version))
+            .select(versions::id)
+            .first::<i32>(&conn)?;
+        if !security_partner::release(&conn, version_id)? {
+            return Err(format!("{} {} isn't quarantined", krate.name, args.arg_version).into());
+        }
+        println!("Released {} {}", krate.name, args.arg_version);
+    }
+    Ok(())
+}
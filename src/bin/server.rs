@@ -24,7 +24,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize logging
     env_logger::init();
 
-    let config = cargo_registry::Config::default();
+    let config = cargo_registry::Config::load()?;
     let client = Client::new();
 
     let app = App::new(&config, Some(client));
@@ -35,6 +35,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let categories_toml = include_str!("../boot/categories.toml");
     boot::categories::sync(categories_toml).unwrap();
 
+    let synonyms_toml = include_str!("../boot/synonyms.toml");
+    boot::synonyms::sync(synonyms_toml).unwrap();
+
     let heroku = dotenv::var("HEROKU").is_ok();
     let fastboot = dotenv::var("USE_FASTBOOT").is_ok();
 
@@ -0,0 +1,142 @@
+// Manages the service consumers which are allowed to read from the API at a
+// high volume.
+//
+// `add` prints the API key of the new consumer, which has to be sent in the
+// `X-Service-Key` header of its requests. `add-webhook` prints the secret the
+// notifications sent to the webhook are signed with.
+
+#![warn(clippy::all, rust_2018_idioms)]
+
+#[macro_use]
+extern crate serde;
+
+use cargo_registry::{
+    db,
+    models::{ServiceConsumer, Webhook, WebhookDelivery},
+    schema::{service_consumers, webhook_deliveries, webhooks},
+};
+use std::error::Error;
+
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use docopt::Docopt;
+
+const USAGE: &str = "
+Usage: service-consumers add [options] <name> <contact-email>
+       service-consumers revoke <name>
+       service-consumers usage <name>
+       service-consumers add-webhook <name> <url>
+       service-consumers remove-webhook <name> <url>
+       service-consumers deliveries <name>
+       service-consumers --help
+
+Registers and revokes service consumers, and shows their usage within the
+last 24 hours. Webhooks are notified when versions are yanked, unyanked or
+deleted, and `deliveries` shows the last notifications sent to them.
+
+Options:
+    -h, --help                 Show this message.
+    --requests-per-hour LIMIT  The quota of the consumer [default: 10000].
+";
+
+#[derive(Deserialize)]
+struct Args {
+    cmd_add: bool,
+    cmd_revoke: bool,
+    cmd_usage: bool,
+    cmd_add_webhook: bool,
+    cmd_remove_webhook: bool,
+    cmd_deliveries: bool,
+    arg_name: String,
+    arg_contact_email: String,
+    arg_url: String,
+    flag_requests_per_hour: i32,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Args = Docopt::new(USAGE)
+        .and_then(|d| d.deserialize())
+        .unwrap_or_else(|e| e.exit());
+    let conn = db::connect_now()?;
+
+    if args.cmd_add {
+        let consumer = ServiceConsumer::create(
+            &conn,
+            &args.arg_name,
+            &args.arg_contact_email,
+            args.flag_requests_per_hour,
+        )?;
+        println!(
+            "Registered `{}` with a quota of {} requests per hour, its API key is {}",
+            consumer.name, consumer.requests_per_hour, consumer.api_key
+        );
+    } else if args.cmd_revoke {
+        let revoked = diesel::update(
+            service_consumers::table.filter(service_consumers::name.eq(&args.arg_name)),
+        )
+        .set(service_consumers::revoked.eq(true))
+        .execute(&conn)?;
+        if revoked == 0 {
+            return Err(format!("no service consumer named `{}`", args.arg_name).into());
+        }
+        println!("Revoked the API key of `{}`", args.arg_name);
+    } else if args.cmd_usage {
+        let consumer = find(&conn, &args.arg_name)?;
+        println!(
+            "`{}` (contact: {}, quota: {} requests per hour)",
+            consumer.name, consumer.contact_email, consumer.requests_per_hour
+        );
+        let since = Utc::now().naive_utc() - Duration::hours(24);
+        for (hour, requests) in consumer.usage_since(&conn, since)? {
+            println!("{}  {:>8}", hour, requests);
+        }
+    } else if args.cmd_add_webhook {
+        let consumer = find(&conn, &args.arg_name)?;
+        let url = args.arg_url.parse::<url::Url>()?;
+        if url.scheme() != "https" {
+            return Err("webhooks must use https".into());
+        }
+        let webhook = Webhook::create(&conn, &consumer, url.as_str())?;
+        println!(
+            "Notifying `{}` at {}, the secret the notifications are signed with is {}",
+            consumer.name, webhook.url, webhook.secret
+        );
+    } else if args.cmd_remove_webhook {
+        let consumer = find(&conn, &args.arg_name)?;
+        let removed = diesel::delete(
+            Webhook::belonging_to(&consumer).filter(webhooks::url.eq(&args.arg_url)),
+        )
+        .execute(&conn)?;
+        if removed == 0 {
+            return Err(format!("`{}` has no webhook {}", consumer.name, args.arg_url).into());
+        }
+        println!("Stopped notifying `{}` at {}", consumer.name, args.arg_url);
+    } else if args.cmd_deliveries {
+        let consumer = find(&conn, &args.arg_name)?;
+        let deliveries = webhook_deliveries::table
+            .inner_join(webhooks::table)
+            .filter(webhooks::service_consumer_id.eq(consumer.id))
+            .order(webhook_deliveries::id.desc())
+            .limit(50)
+            .select((webhook_deliveries::all_columns, webhooks::url))
+            .load::<(WebhookDelivery, String)>(&conn)?;
+        for (delivery, url) in deliveries {
+            let status = match (delivery.delivered_at, &delivery.last_error) {
+                (Some(delivered_at), _) => format!("delivered at {}", delivered_at),
+                (None, Some(error)) => format!("{} failed attempts: {}", delivery.attempts, error),
+                (None, None) => "pending".into(),
+            };
+            println!(
+                "{:>8}  {}  {:<6}  {}  {}",
+                delivery.id, delivery.created_at, delivery.event, url, status
+            );
+        }
+    }
+    Ok(())
+}
+
+fn find(conn: &PgConnection, name: &str) -> QueryResult<ServiceConsumer> {
+    service_consumers::table
+        .filter(service_consumers::name.eq(name))
+        .first(conn)
+}
@@ -0,0 +1,110 @@
+// Lists the crates which were reported as squatted and look unused, and
+// records the decisions made about them.
+//
+// `stats` compares the heuristics of resolved reports with their outcomes,
+// e.g. a heuristic that often matches crates which are kept is a poor
+// indicator of squatting.
+
+#![warn(clippy::all, rust_2018_idioms)]
+
+#[macro_use]
+extern crate serde;
+
+use cargo_registry::{
+    db,
+    models::{squatting_report::OUTCOMES, Crate, SquattingReport},
+};
+use std::collections::BTreeMap;
+use std::error::Error;
+
+use chrono::Utc;
+use diesel::prelude::*;
+use docopt::Docopt;
+
+const USAGE: &str = "
+Usage: squatting-reports list
+       squatting-reports resolve <crate> <outcome>
+       squatting-reports stats
+       squatting-reports --help
+
+Manages the crates which were reported as squatted. <outcome> is one of
+`released`, `transferred` or `kept`.
+
+Options:
+    -h, --help  Show this message.
+";
+
+#[derive(Deserialize)]
+struct Args {
+    cmd_list: bool,
+    cmd_resolve: bool,
+    cmd_stats: bool,
+    arg_crate: String,
+    arg_outcome: String,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Args = Docopt::new(USAGE)
+        .and_then(|d| d.deserialize())
+        .unwrap_or_else(|e| e.exit());
+    let conn = db::connect_now()?;
+
+    if args.cmd_list {
+        for (name, reasons) in SquattingReport::candidates(&conn)? {
+            println!("{} ({} reports)", name, reasons.len());
+            for reason in reasons {
+                println!("    {}", reason);
+            }
+        }
+    } else if args.cmd_resolve {
+        if !OUTCOMES.contains(&args.arg_outcome.as_str()) {
+            return Err(format!("unknown outcome `{}`", args.arg_outcome).into());
+        }
+        let krate = Crate::by_name(&args.arg_crate).first::<Crate>(&conn)?;
+        let resolved =
+            SquattingReport::resolve(&conn, krate.id, &args.arg_outcome, Utc::now().naive_utc())?;
+        if resolved == 0 {
+            return Err(format!("`{}` has no open reports", krate.name).into());
+        }
+        println!(
+            "Resolved {} reports of `{}` as {}",
+            resolved, krate.name, args.arg_outcome
+        );
+    } else if args.cmd_stats {
+        // Per outcome, the number of reports and how many of them matched
+        // each heuristic
+        let mut stats = BTreeMap::<String, [usize; 6]>::new();
+        for (heuristics, outcome) in SquattingReport::outcomes(&conn)? {
+            let counts = stats.entry(outcome).or_default();
+            let matched = [
+                true,
+                heuristics.no_code,
+                heuristics.no_downloads,
+                heuristics.single_version,
+                heuristics.inactive,
+                heuristics.placeholder,
+            ];
+            for (count, matched) in counts.iter_mut().zip(matched.iter()) {
+                *count += *matched as usize;
+            }
+        }
+
+        println!(
+            "{:<12} {:>8} {:>8} {:>13} {:>15} {:>9} {:>12}",
+            "outcome",
+            "reports",
+            "no_code",
+            "no_downloads",
+            "single_version",
+            "inactive",
+            "placeholder"
+        );
+        for (outcome, c) in stats {
+            println!(
+                "{:<12} {:>8} {:>8} {:>13} {:>15} {:>9} {:>12}",
+                outcome, c[0], c[1], c[2], c[3], c[4], c[5]
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,183 @@
+// Verifies that the git index agrees with the database.
+//
+// The git index is read from a checkout, or cloned from `GIT_REPO_URL`.
+// Versions kept out of the index by `HOLD_FLAGGED_VERSIONS` aren't expected
+// in it. The index is only published as a git repository, so there's no
+// sparse index to verify.
+//
+// The mismatches are printed as a JSON report:
+//
+// - `checksum`: an index entry has another checksum than the database.
+// - `missing_version`: a version of the database isn't in the index.
+// - `unknown_version`: the index has a version the database doesn't know.
+// - `stale_yank`: an index entry isn't yanked, or not unyanked, like the
+//   version in the database.
+
+#![warn(clippy::all, rust_2018_idioms)]
+
+#[macro_use]
+extern crate serde;
+
+use cargo_registry::{
+    db,
+    git::{self, relative_index_path},
+    index_verification::{compare, parse_entries, DatabaseVersion, Mismatch},
+    schema::{crates, version_checksums, version_scan_holds, versions},
+};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    process::exit,
+};
+
+use diesel::prelude::*;
+use docopt::Docopt;
+
+const USAGE: &str = "
+Usage: verify-index [options] [<crate>...]
+       verify-index --help
+
+Compares the git index with the database, for the given crates or all of
+them. Exits with 1 if there are mismatches.
+
+Options:
+    -h, --help          Show this message.
+    --git INDEX         URL of the git index, or the path of a checkout of it,
+                        `GIT_REPO_URL` by default.
+";
+
+#[derive(Deserialize)]
+struct Args {
+    flag_git: Option<String>,
+    arg_crate: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct Report {
+    crates: usize,
+    versions: usize,
+    mismatches: Vec<Mismatch>,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Args = Docopt::new(USAGE)
+        .and_then(|d| d.deserialize())
+        .unwrap_or_else(|e| e.exit());
+    let git_index = args
+        .flag_git
+        .or_else(|| dotenv::var("GIT_REPO_URL").ok())
+        .ok_or("--git or GIT_REPO_URL must be set")?;
+
+    let checkout = tempfile::tempdir()?;
+    let git_path = if Path::new(&git_index).is_dir() {
+        PathBuf::from(&git_index)
+    } else {
+        eprintln!("Cloning {}", git_index);
+        git2::Repository::clone(&git_index, checkout.path())?;
+        checkout.path().to_path_buf()
+    };
+
+    let only = args
+        .arg_crate
+        .iter()
+        .map(|name| name.to_lowercase())
+        .collect::<HashSet<_>>();
+    let conn = db::connect_now()?;
+    let mut database = load_database(&conn)?;
+    database.retain(|name, _| only.is_empty() || only.contains(name));
+    let mut names = database.keys().cloned().collect::<BTreeSet<_>>();
+    names.extend(
+        git_crate_names(&git_path)?
+            .into_iter()
+            .filter(|name| only.is_empty() || only.contains(name)),
+    );
+
+    let mut report = Report {
+        crates: names.len(),
+        versions: 0,
+        mismatches: Vec::new(),
+    };
+    for (i, name) in names.iter().enumerate() {
+        if i % 1000 == 0 {
+            eprintln!("Verified {} of {} crates", i, names.len());
+        }
+        let expected = database.remove(name).unwrap_or_default();
+        report.versions += expected.len();
+        let entries = read_git(&git_path, name)?;
+        report.mismatches.extend(compare(name, &expected, &entries));
+    }
+
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    if !report.mismatches.is_empty() {
+        exit(1);
+    }
+    Ok(())
+}
+
+/// The versions in the database which should be in the index, by lower case
+/// crate name.
+fn load_database(conn: &PgConnection) -> QueryResult<BTreeMap<String, Vec<DatabaseVersion>>> {
+    let held = version_scan_holds::table
+        .select(version_scan_holds::version_id)
+        .load::<i32>(conn)?
+        .into_iter()
+        .collect::<HashSet<_>>();
+    let rows = versions::table
+        .inner_join(crates::table)
+        .left_join(version_checksums::table)
+        .select((
+            crates::name,
+            versions::id,
+            versions::num,
+            version_checksums::checksum.nullable(),
+            versions::yanked,
+        ))
+        .load::<(String, i32, String, Option<String>, bool)>(conn)?;
+
+    let mut database = BTreeMap::<_, Vec<_>>::new();
+    for (name, version_id, num, checksum, yanked) in rows {
+        if held.contains(&version_id) {
+            continue;
+        }
+        database
+            .entry(name.to_lowercase())
+            .or_default()
+            .push(DatabaseVersion {
+                num,
+                checksum,
+                yanked,
+            });
+    }
+    Ok(database)
+}
+
+/// The names of all crates with a file in a checkout of the git index.
+fn git_crate_names(path: &Path) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut names = Vec::new();
+    let mut dirs = vec![path.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            if file_name.starts_with('.') || (dir == path && file_name == "config.json") {
+                continue;
+            }
+            if entry.file_type()?.is_dir() {
+                dirs.push(entry.path());
+            } else {
+                names.push(file_name);
+            }
+        }
+    }
+    Ok(names)
+}
+
+fn read_git(path: &Path, name: &str) -> Result<Vec<git::Crate>, Box<dyn Error>> {
+    let file = path.join(relative_index_path(name));
+    if !file.is_file() {
+        return Ok(Vec::new());
+    }
+    Ok(parse_entries(&fs::read_to_string(file)?)?)
+}
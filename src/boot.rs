@@ -1 +1,2 @@
 pub mod categories;
+pub mod synonyms;
@@ -0,0 +1,88 @@
+// Sync the search synonym dictionary from `src/boot/synonyms.toml`.
+// Runs when the server is started.
+
+use crate::models::search_synonym::{SearchSynonym, MAX_SYNONYMS, MAX_TERM_WORDS};
+use crate::schema::search_synonyms;
+use crate::{db, util::Error};
+
+use std::collections::BTreeMap;
+
+use diesel::prelude::*;
+
+fn synonyms_from_toml(toml_str: &str) -> Result<Vec<SearchSynonym>, Error> {
+    let dictionary: BTreeMap<String, Vec<String>> = toml::from_str(toml_str)
+        .map_err(|e| format!("could not parse the synonyms TOML: {}", e))?;
+
+    let mut result = Vec::new();
+    for (term, synonyms) in dictionary {
+        let words = term.split_whitespace().collect::<Vec<_>>();
+        if words.is_empty() || words.join(" ") != term || term.to_lowercase() != term {
+            return Err(format!(
+                "synonym term `{}` must be lowercase words separated by single spaces",
+                term
+            )
+            .into());
+        }
+        if words.len() > MAX_TERM_WORDS {
+            return Err(format!(
+                "synonym term `{}` has more than {} words",
+                term, MAX_TERM_WORDS
+            )
+            .into());
+        }
+        if synonyms.is_empty() || synonyms.len() > MAX_SYNONYMS {
+            return Err(format!(
+                "synonym term `{}` must have between 1 and {} synonyms",
+                term, MAX_SYNONYMS
+            )
+            .into());
+        }
+        if synonyms.iter().any(|synonym| synonym.trim().is_empty()) {
+            return Err(format!("synonym term `{}` has an empty synonym", term).into());
+        }
+
+        result.push(SearchSynonym { term, synonyms });
+    }
+
+    Ok(result)
+}
+
+pub fn sync(toml_str: &str) -> Result<(), Error> {
+    let conn = db::connect_now().unwrap();
+    sync_with_connection(toml_str, &conn)
+}
+
+pub fn sync_with_connection(toml_str: &str, conn: &PgConnection) -> Result<(), Error> {
+    let synonyms = synonyms_from_toml(toml_str)?;
+
+    conn.transaction(|| {
+        diesel::delete(search_synonyms::table).execute(conn)?;
+        if !synonyms.is_empty() {
+            diesel::insert_into(search_synonyms::table)
+                .values(&synonyms)
+                .execute(conn)?;
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::synonyms_from_toml;
+
+    #[test]
+    fn bundled_synonyms_are_valid() {
+        synonyms_from_toml(include_str!("synonyms.toml")).unwrap();
+    }
+
+    #[test]
+    fn invalid_synonyms_are_rejected() {
+        let err = |toml| synonyms_from_toml(toml).unwrap_err().to_string();
+
+        assert!(err(r#""HTTP" = ["hyper"]"#).contains("lowercase"));
+        assert!(err(r#""http  client" = ["hyper"]"#).contains("single spaces"));
+        assert!(err(r#""a b c d" = ["x"]"#).contains("more than 3 words"));
+        assert!(err(r#""http" = []"#).contains("between 1 and 8"));
+        assert!(err(r#""http" = [" "]"#).contains("empty synonym"));
+    }
+}
@@ -1,6 +1,16 @@
-use crate::publish_rate_limit::PublishRateLimit;
-use crate::{env, uploaders::Uploader, Env, Replica};
+use crate::middleware::cors::CorsConfig;
+use crate::models::dependency::DependencyCheck;
+use crate::publish_rate_limit::{PublishRateLimit, RateLimiterConfig};
+use crate::response_cache::ResponseCacheConfig;
+use crate::sigstore::TrustRoot;
+use crate::storage::StorageConfig;
+use crate::{db, env, search_backend::SearchConfig, uploaders::Uploader, Env, Replica};
 use std::path::PathBuf;
+use std::time::Duration;
+
+mod layers;
+
+pub use self::layers::{check_overridable, ConfigLayers, ConfigSource, ConfigVariable};
 
 #[derive(Clone, Debug)]
 pub struct Config {
@@ -14,10 +24,93 @@ pub struct Config {
     pub env: Env,
     pub max_upload_size: u64,
     pub max_unpack_size: u64,
+    pub max_compression_ratio: u64,
+    pub max_file_compression_ratio: u64,
+    pub max_file_count: u64,
+    pub max_path_length: u64,
     pub mirror: Replica,
     pub api_protocol: String,
     pub publish_rate_limit: PublishRateLimit,
+    pub publish_rate_limiter: RateLimiterConfig,
     pub blocked_traffic: Vec<(String, Vec<String>)>,
+    pub og_image_renderer: Option<String>,
+    pub search: SearchConfig,
+    pub yank_cooldown: Duration,
+    pub cors: CorsConfig,
+    pub response_cache: ResponseCacheConfig,
+    pub dependency_check: DependencyCheck,
+    pub accept_zstd_crates: bool,
+    pub hold_flagged_versions: bool,
+    pub show_download_anomalies_to_owners: bool,
+    pub admin_github_ids: Vec<i32>,
+    pub metrics_authorization_token: Option<String>,
+    pub sigstore_trust_root: Option<TrustRoot>,
+    /// Where the environment variables were loaded from, empty unless the
+    /// config was created with `Config::load`.
+    pub layers: ConfigLayers,
+}
+
+impl Config {
+    /// Loads the profiles and the overrides from the database into the
+    /// environment, see the `layers` module, and then reads and validates the
+    /// config like `Config::default`.
+    pub fn load() -> Result<Config, String> {
+        let mut layers = ConfigLayers::load()?;
+        let conn =
+            db::connect_now().map_err(|e| format!("connecting to the database failed: {}", e))?;
+        layers.apply_overrides(&conn)?;
+        let config = Config {
+            layers,
+            ..Config::default()
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks the values which would otherwise only cause errors once they
+    /// are used, listing all problems at once.
+    pub fn validate(&self) -> Result<(), String> {
+        let mut problems = Vec::new();
+        if self.session_key.len() < 32 {
+            problems.push("SESSION_KEY must be at least 32 bytes long".to_string());
+        }
+        if self.replica_db_url.as_ref() == Some(&self.db_url) {
+            problems.push("READ_ONLY_REPLICA_URL must not be the DATABASE_URL".to_string());
+        }
+        if self.api_protocol != "https" && self.api_protocol != "http" {
+            problems.push(format!("`{}` isn't an API protocol", self.api_protocol));
+        }
+        for (var, value) in &[
+            ("MAX_COMPRESSION_RATIO", self.max_compression_ratio),
+            (
+                "MAX_FILE_COMPRESSION_RATIO",
+                self.max_file_compression_ratio,
+            ),
+            ("MAX_FILE_COUNT", self.max_file_count),
+            ("MAX_PATH_LENGTH", self.max_path_length),
+        ] {
+            if *value == 0 {
+                problems.push(format!("{} must be greater than 0", var));
+            }
+        }
+        if self.admin_github_ids.iter().any(|&id| id <= 0) {
+            problems.push("GH_ADMIN_USER_IDS must only contain GitHub user IDs".to_string());
+        }
+        if self.env == Env::Production
+            && (self.gh_client_id.is_empty() || self.gh_client_secret.is_empty())
+        {
+            problems.push("GH_CLIENT_ID and GH_CLIENT_SECRET must be set in production".into());
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "invalid configuration:\n- {}",
+                problems.join("\n- ")
+            ))
+        }
+    }
 }
 
 impl Default for Config {
@@ -33,6 +126,8 @@ impl Default for Config {
     /// - `GIT_REPO_CHECKOUT`: The directory where the registry index was cloned.
     /// - `MIRROR`: Is this instance of cargo_registry a mirror of crates.io.
     /// - `HEROKU`: Is this instance of cargo_registry currently running on Heroku.
+    /// - `STORAGE_BACKEND`: Where crate files and other uploads are stored, see
+    ///    `StorageConfig::from_environment` for the related variables.
     /// - `S3_BUCKET`: The S3 bucket used to store crate files. If not present during development,
     ///    cargo_registry will fall back to a local uploader.
     /// - `S3_REGION`: The region in which the bucket was created. Optional if US standard.
@@ -45,6 +140,43 @@ impl Default for Config {
     /// - `READ_ONLY_REPLICA_URL`: The URL of an optional postgres read-only replica database.
     /// - `BLOCKED_TRAFFIC`: A list of headers and environment variables to use for blocking
     ///.  traffic. See the `block_traffic` module for more documentation.
+    /// - `OG_IMAGE_RENDERER`: An optional SVG to PNG converter command. If set, Open Graph
+    ///    preview images are rendered for crates when they are published.
+    /// - `SEARCH_BACKEND`: The backend for full text searches, see
+    ///    `SearchConfig::from_environment` for the related variables.
+    /// - `YANK_COOLDOWN_SECONDS`: How long a version has to stay yanked or unyanked before it can
+    ///    be changed back, 60 seconds by default.
+    /// - `CORS_PUBLIC_*` and `CORS_PRIVATE_*`: The CORS policies of the API, see the `cors`
+    ///    middleware for the related variables.
+    /// - `PUBLISH_RATE_LIMIT_BACKEND`: Where the publish rate limit is tracked, see
+    ///    `RateLimiterConfig::from_environment` for the related variables.
+    /// - `RESPONSE_CACHE_FRESH_SECONDS` and `RESPONSE_CACHE_STALE_SECONDS`: How long cached
+    ///    responses of hot read endpoints are served, see the `response_cache` module.
+    /// - `PUBLISH_DEPENDENCY_CHECK`: Whether publishing warns about (`warn`) or rejects (`deny`)
+    ///    dependencies which no version that isn't yanked matches. Off by default.
+    /// - `MAX_COMPRESSION_RATIO` and `MAX_FILE_COMPRESSION_RATIO`: How many times larger than
+    ///    the crate file its contents, and a single file than its compressed data, may be. 50 and
+    ///    200 by default, and only checked once more than 1 MiB were decompressed.
+    /// - `MAX_FILE_COUNT` and `MAX_PATH_LENGTH`: How many files and directories a crate file may
+    ///    contain, and how many bytes long their paths within the package may be. 50,000 and 512
+    ///    by default.
+    /// - `ACCEPT_ZSTD_CRATES`: Whether crate files compressed with zstd are accepted at publish
+    ///    time, in addition to gzip compressed ones.
+    /// - `HOLD_FLAGGED_VERSIONS`: Whether versions containing executables, precompiled libraries
+    ///    or high-entropy blobs, and new crates copying the sources of a popular one, are kept out
+    ///    of the index until an admin reviewed them.
+    /// - `SHOW_DOWNLOAD_ANOMALIES_TO_OWNERS`: Whether owners can see the download anomalies of
+    ///    their crates, which are otherwise only listed to admins.
+    /// - `GH_ADMIN_USER_IDS`: The comma separated GitHub user IDs of the users who can use the
+    ///    admin API, like setting the upload size limit of a crate.
+    /// - `METRICS_AUTHORIZATION_TOKEN`: The bearer token Prometheus scrapes the metrics under
+    ///    `/api/private/metrics` with. The metrics aren't served if it isn't set.
+    /// - `SIGSTORE_FULCIO_CERTIFICATES` and `SIGSTORE_REKOR_KEYS`: The PEM encoded certificates
+    ///    of Fulcio and public keys of Rekor which sigstore bundles of versions are verified
+    ///    against. Bundles aren't accepted unless both are set.
+    ///
+    /// `CONFIG_PROFILE` selects the profile in `config/profiles` which `Config::load` reads
+    /// variables that aren't set from, see the `layers` module.
     fn default() -> Config {
         let checkout = PathBuf::from(env("GIT_REPO_CHECKOUT"));
         let api_protocol = String::from("https");
@@ -59,71 +191,20 @@ impl Default for Config {
         } else {
             Env::Development
         };
-        let uploader = match (cargo_env, mirror) {
-            (Env::Production, Replica::Primary) => {
-                // `env` panics if these vars are not set, and in production for a primary instance,
-                // that's what we want since we don't want to be able to start the server if the
-                // server doesn't know where to upload crates.
-                Uploader::S3 {
-                    bucket: s3::Bucket::new(
-                        env("S3_BUCKET"),
-                        dotenv::var("S3_REGION").ok(),
-                        env("S3_ACCESS_KEY"),
-                        env("S3_SECRET_KEY"),
-                        &api_protocol,
-                    ),
-                    cdn: dotenv::var("S3_CDN").ok(),
-                }
-            }
-            (Env::Production, Replica::ReadOnlyMirror) => {
-                // Read-only mirrors don't need access key or secret key since by definition,
-                // they'll only need to read from a bucket, not upload.
-                //
-                // Read-only mirrors might have access key or secret key, so use them if those
-                // environment variables are set.
-                //
-                // Read-only mirrors definitely need bucket though, so that they know where
-                // to serve crate files from.
-                Uploader::S3 {
-                    bucket: s3::Bucket::new(
-                        env("S3_BUCKET"),
-                        dotenv::var("S3_REGION").ok(),
-                        dotenv::var("S3_ACCESS_KEY").unwrap_or_default(),
-                        dotenv::var("S3_SECRET_KEY").unwrap_or_default(),
-                        &api_protocol,
-                    ),
-                    cdn: dotenv::var("S3_CDN").ok(),
-                }
-            }
-            // In Development mode, either running as a primary instance or a read-only mirror
-            _ => {
-                if dotenv::var("S3_BUCKET").is_ok() {
-                    // If we've set the `S3_BUCKET` variable to any value, use all of the values
-                    // for the related S3 environment variables and configure the app to upload to
-                    // and read from S3 like production does. All values except for bucket are
-                    // optional, like production read-only mirrors.
-                    println!("Using S3 uploader");
-                    Uploader::S3 {
-                        bucket: s3::Bucket::new(
-                            env("S3_BUCKET"),
-                            dotenv::var("S3_REGION").ok(),
-                            dotenv::var("S3_ACCESS_KEY").unwrap_or_default(),
-                            dotenv::var("S3_SECRET_KEY").unwrap_or_default(),
-                            &api_protocol,
-                        ),
-                        cdn: dotenv::var("S3_CDN").ok(),
-                    }
-                } else {
-                    // If we don't set the `S3_BUCKET` variable, we'll use a development-only
-                    // uploader that makes it possible to run and publish to a locally-running
-                    // crates.io instance without needing to set up an account and a bucket in S3.
-                    println!(
-                        "Using local uploader, crate files will be in the local_uploads directory"
-                    );
-                    Uploader::Local
+        let storage = StorageConfig::from_environment(&api_protocol, cargo_env, mirror);
+        if cargo_env == Env::Development {
+            match storage {
+                StorageConfig::S3 { .. } => println!("Using S3 uploader"),
+                StorageConfig::Local { ref directory } => println!(
+                    "Using local uploader, crate files will be in the {} directory",
+                    directory.display()
+                ),
+                StorageConfig::Memory => {
+                    println!("Using in-memory uploader, crate files are lost on restart")
                 }
             }
-        };
+        }
+        let uploader = Uploader::new(storage.build());
         Config {
             uploader,
             session_key: env("SESSION_KEY"),
@@ -135,14 +216,58 @@ impl Default for Config {
             env: cargo_env,
             max_upload_size: 10 * 1024 * 1024, // 10 MB default file upload size limit
             max_unpack_size: 512 * 1024 * 1024, // 512 MB max when decompressed
+            max_compression_ratio: numeric_var("MAX_COMPRESSION_RATIO", 50),
+            max_file_compression_ratio: numeric_var("MAX_FILE_COMPRESSION_RATIO", 200),
+            max_file_count: numeric_var("MAX_FILE_COUNT", 50_000),
+            max_path_length: numeric_var("MAX_PATH_LENGTH", 512),
             mirror,
             api_protocol,
             publish_rate_limit: Default::default(),
+            publish_rate_limiter: RateLimiterConfig::from_environment(),
             blocked_traffic: blocked_traffic(),
+            og_image_renderer: dotenv::var("OG_IMAGE_RENDERER").ok(),
+            search: SearchConfig::from_environment(),
+            yank_cooldown: Duration::from_secs(
+                dotenv::var("YANK_COOLDOWN_SECONDS")
+                    .map(|s| s.parse().expect("YANK_COOLDOWN_SECONDS must be a number"))
+                    .unwrap_or(60),
+            ),
+            cors: CorsConfig::from_environment(),
+            response_cache: ResponseCacheConfig::from_environment(),
+            dependency_check: DependencyCheck::from_environment(),
+            accept_zstd_crates: dotenv::var("ACCEPT_ZSTD_CRATES").is_ok(),
+            hold_flagged_versions: dotenv::var("HOLD_FLAGGED_VERSIONS").is_ok(),
+            show_download_anomalies_to_owners: dotenv::var("SHOW_DOWNLOAD_ANOMALIES_TO_OWNERS")
+                .is_ok(),
+            admin_github_ids: admin_github_ids(),
+            metrics_authorization_token: dotenv::var("METRICS_AUTHORIZATION_TOKEN").ok(),
+            sigstore_trust_root: TrustRoot::from_environment(),
+            layers: ConfigLayers::default(),
         }
     }
 }
 
+fn numeric_var(var: &str, default: u64) -> u64 {
+    dotenv::var(var)
+        .map(|s| {
+            s.parse()
+                .unwrap_or_else(|_| panic!("{} must be a number", var))
+        })
+        .unwrap_or(default)
+}
+
+fn admin_github_ids() -> Vec<i32> {
+    dotenv::var("GH_ADMIN_USER_IDS")
+        .unwrap_or_default()
+        .split_terminator(',')
+        .map(|id| {
+            id.trim()
+                .parse()
+                .expect("GH_ADMIN_USER_IDS must be a comma separated list of GitHub user IDs")
+        })
+        .collect()
+}
+
 fn blocked_traffic() -> Vec<(String, Vec<String>)> {
     let pattern_list = dotenv::var("BLOCKED_TRAFFIC").unwrap_or_default();
     parse_traffic_patterns(&pattern_list)
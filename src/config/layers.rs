@@ -0,0 +1,358 @@
+//! Layered loading of the environment variables `Config::default` reads
+//!
+//! Staging and production run the same binary, and only differ in their
+//! environment. Instead of setting every variable on every deployment, the
+//! shared values live in `config/profiles/base.env` and the values of each
+//! deployment in `config/profiles/<CONFIG_PROFILE>.env`. Variables are taken
+//! from, in order of precedence:
+//!
+//! 1. the `config_overrides` table, set with `crates-io-admin set-config`,
+//!    which changes the configuration without a deploy,
+//! 2. the process environment, including the `.env` file,
+//! 3. the profile named by `CONFIG_PROFILE`,
+//! 4. the base profile, which is only loaded together with a named profile.
+//!
+//! The source of each variable is remembered, so that admins can see the
+//! effective configuration at `GET /api/private/admin/config`.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use diesel::PgConnection;
+
+use crate::models::ConfigOverride;
+
+/// The directory the profiles are read from.
+const PROFILE_DIR: &str = "config/profiles";
+
+/// The variables the application reads, apart from the `CORS_` ones whose
+/// names are built from the policy, and the ones only used by tests.
+pub const VARIABLES: &[&str] = &[
+    "ACCEPT_ZSTD_CRATES",
+    "AUDIT_RETENTION_DAYS",
+    "BACKGROUND_JOB_TIMEOUT",
+    "BLOCKED_TRAFFIC",
+    "CONFIG_PROFILE",
+    "DATABASE_URL",
+    "DB_HELPER_THREADS",
+    "DB_MIN_IDLE",
+    "DB_POOL_SIZE",
+    "DB_TIMEOUT",
+    "DNS_OVER_HTTPS_URL",
+    "DOMAIN_NAME",
+    "DOWNLOAD_ANOMALY_MIN_DOWNLOADS",
+    "DOWNLOAD_ANOMALY_MIN_SCORE",
+    "GH_ADMIN_USER_IDS",
+    "GH_CLIENT_ID",
+    "GH_CLIENT_SECRET",
+    "GITHUB_API_TOKEN",
+    "GITLAB_API_TOKEN",
+    "GIT_CLONE_DEPTH",
+    "GIT_HTTP_PWD",
+    "GIT_HTTP_USER",
+    "GIT_REPO_CHECKOUT",
+    "GIT_REPO_URL",
+    "GIT_SPARSE_CHECKOUT",
+    "GIT_SSH_KEY",
+    "GIT_SSH_REPO_URL",
+    "HEROKU",
+    "HOLD_FLAGGED_VERSIONS",
+    "IMAGE_PROXY_URL",
+    "INDEX_API_URL",
+    "INDEX_AUTH_REQUIRED",
+    "INDEX_DL_URL",
+    "INDEX_SIGNING_KEY",
+    "INDEX_SQUASH_THRESHOLD",
+    "INDEX_STORAGE_ENDPOINT_PRIORITY",
+    "INDEX_V3_FIELDS",
+    "INSTALL_CHECK_DELAY",
+    "LOCAL_UPLOADS_DIR",
+    "MAILGUN_SMTP_LOGIN",
+    "MAILGUN_SMTP_PASSWORD",
+    "MAILGUN_SMTP_SERVER",
+    "MAX_COMPRESSION_RATIO",
+    "MAX_FILE_COMPRESSION_RATIO",
+    "MAX_FILE_COUNT",
+    "MAX_JOB_DURATIONS",
+    "MAX_JOB_TIME",
+    "MAX_PATH_LENGTH",
+    "MEILISEARCH_API_KEY",
+    "MEILISEARCH_INDEX",
+    "MEILISEARCH_URL",
+    "METRICS_AUTHORIZATION_TOKEN",
+    "MIRROR",
+    "OG_IMAGE_RENDERER",
+    "PAGERDUTY_API_TOKEN",
+    "PAGERDUTY_INTEGRATION_KEY",
+    "PORT",
+    "PRUNE_BATCH_SIZE",
+    "PUBLIC_INDEX_URL",
+    "PUBLISH_DEPENDENCY_CHECK",
+    "PUBLISH_RATE_LIMIT_BACKEND",
+    "READ_ONLY_MODE",
+    "READ_ONLY_REPLICA_URL",
+    "REDIS_URL",
+    "RESPONSE_CACHE_FRESH_SECONDS",
+    "RESPONSE_CACHE_STALE_SECONDS",
+    "S3_ACCESS_KEY",
+    "S3_BUCKET",
+    "S3_CDN",
+    "S3_REGION",
+    "S3_SECRET_KEY",
+    "SEARCH_BACKEND",
+    "SERVER_THREADS",
+    "SESSION_KEY",
+    "SHADOW_DATABASE_URL",
+    "SHOW_DOWNLOAD_ANOMALIES_TO_OWNERS",
+    "SIGSTORE_FULCIO_CERTIFICATES",
+    "SIGSTORE_REKOR_KEYS",
+    "SPAM_AUTHOR_PATTERNS",
+    "SPAM_CRATE_NAMES",
+    "SQUATTING_REPORTS_EMAIL",
+    "STORAGE_BACKEND",
+    "STUCK_JOB_POLICY",
+    "TRANSPARENCY_LOG_KEY",
+    "USE_FASTBOOT",
+    "USE_HYPER",
+    "WORKER_SHUTDOWN_TIMEOUT",
+    "YANK_COOLDOWN_SECONDS",
+];
+
+/// Variables holding credentials, whose values are never shown, and which
+/// can't be set in the database. The URLs of databases and Redis contain
+/// passwords.
+const SECRETS: &[&str] = &[
+    "DATABASE_URL",
+    "GH_CLIENT_SECRET",
+    "GITHUB_API_TOKEN",
+    "GITLAB_API_TOKEN",
+    "GIT_HTTP_PWD",
+    "GIT_SSH_KEY",
+    "INDEX_SIGNING_KEY",
+    "MAILGUN_SMTP_PASSWORD",
+    "MEILISEARCH_API_KEY",
+    "METRICS_AUTHORIZATION_TOKEN",
+    "PAGERDUTY_API_TOKEN",
+    "PAGERDUTY_INTEGRATION_KEY",
+    "READ_ONLY_REPLICA_URL",
+    "REDIS_URL",
+    "S3_ACCESS_KEY",
+    "S3_SECRET_KEY",
+    "SESSION_KEY",
+    "SHADOW_DATABASE_URL",
+    "TRANSPARENCY_LOG_KEY",
+];
+
+/// Where the value of a variable came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigSource {
+    Base,
+    Profile,
+    Environment,
+    Database,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigVariable {
+    pub value: String,
+    pub source: ConfigSource,
+}
+
+/// The layers the configuration was loaded from.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConfigLayers {
+    /// The value of `CONFIG_PROFILE`, if a profile was loaded.
+    pub profile: Option<String>,
+    /// The known variables which are set, by name.
+    pub variables: BTreeMap<String, ConfigVariable>,
+}
+
+impl ConfigLayers {
+    /// Loads the profiles into the process environment, where variables
+    /// which are already set are left alone.
+    pub fn load() -> Result<Self, String> {
+        dotenv::dotenv().ok();
+        let mut layers = Self {
+            profile: dotenv::var("CONFIG_PROFILE").ok(),
+            variables: BTreeMap::new(),
+        };
+        for (name, value) in std::env::vars() {
+            layers.insert(name, value, ConfigSource::Environment);
+        }
+
+        if let Some(profile) = layers.profile.clone() {
+            if profile.is_empty()
+                || !profile
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-')
+            {
+                return Err(format!("`{}` isn't a valid CONFIG_PROFILE", profile));
+            }
+            let path = Path::new(PROFILE_DIR).join(format!("{}.env", profile));
+            if !path.exists() {
+                return Err(format!("the profile {} doesn't exist", path.display()));
+            }
+            layers.load_file(&path, ConfigSource::Profile)?;
+            let base = Path::new(PROFILE_DIR).join("base.env");
+            if base.exists() {
+                layers.load_file(&base, ConfigSource::Base)?;
+            }
+        }
+        Ok(layers)
+    }
+
+    fn load_file(&mut self, path: &Path, source: ConfigSource) -> Result<(), String> {
+        let invalid = |e: dotenv::Error| format!("{} is invalid: {}", path.display(), e);
+        for item in dotenv::from_path_iter(path).map_err(invalid)? {
+            let (name, value) = item.map_err(invalid)?;
+            if std::env::var_os(&name).is_none() {
+                std::env::set_var(&name, &value);
+                self.insert(name, value, source);
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets the variables of the `config_overrides` table in the process
+    /// environment, replacing the values from the other layers.
+    pub fn apply_overrides(&mut self, conn: &PgConnection) -> Result<(), String> {
+        let overrides = ConfigOverride::all(conn)
+            .map_err(|e| format!("loading the config overrides failed: {}", e))?;
+        for ConfigOverride { name, value, .. } in overrides {
+            check_overridable(&name)?;
+            std::env::set_var(&name, &value);
+            self.insert(name, value, ConfigSource::Database);
+        }
+        Ok(())
+    }
+
+    fn insert(&mut self, name: String, value: String, source: ConfigSource) {
+        if is_known(&name) {
+            self.variables
+                .insert(name, ConfigVariable { value, source });
+        }
+    }
+
+    /// The variables with the values of secrets redacted, for showing them
+    /// to admins.
+    pub fn redacted(&self) -> impl Iterator<Item = (&str, &str, ConfigSource)> {
+        self.variables.iter().map(|(name, variable)| {
+            let value = if is_secret(name) {
+                "[redacted]"
+            } else {
+                &variable.value
+            };
+            (name.as_str(), value, variable.source)
+        })
+    }
+}
+
+fn is_known(name: &str) -> bool {
+    VARIABLES.contains(&name) || name.starts_with("CORS_")
+}
+
+fn is_secret(name: &str) -> bool {
+    SECRETS.contains(&name)
+}
+
+/// Checks that a variable may be set in the `config_overrides` table.
+/// Secrets belong in the environment of the deployment, and the profile and
+/// the database can't be chosen by the database itself.
+pub fn check_overridable(name: &str) -> Result<(), String> {
+    if !is_known(name) {
+        Err(format!("`{}` isn't a variable crates.io reads", name))
+    } else if is_secret(name) || name == "CONFIG_PROFILE" {
+        Err(format!("`{}` can't be set in the database", name))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secrets_are_redacted() {
+        let mut layers = ConfigLayers::default();
+        layers.insert(
+            "SESSION_KEY".into(),
+            "hunter2".into(),
+            ConfigSource::Profile,
+        );
+        layers.insert("MAX_FILE_COUNT".into(), "10".into(), ConfigSource::Database);
+        layers.insert("PATH".into(), "/usr/bin".into(), ConfigSource::Environment);
+        assert_eq!(
+            layers.redacted().collect::<Vec<_>>(),
+            vec![
+                ("MAX_FILE_COUNT", "10", ConfigSource::Database),
+                ("SESSION_KEY", "[redacted]", ConfigSource::Profile),
+            ]
+        );
+    }
+
+    #[test]
+    fn only_known_variables_which_arent_secret_can_be_overridden() {
+        assert_eq!(check_overridable("MAX_FILE_COUNT"), Ok(()));
+        assert_eq!(check_overridable("CORS_PUBLIC_ORIGINS"), Ok(()));
+        assert!(check_overridable("PATH").is_err());
+        assert!(check_overridable("S3_SECRET_KEY").is_err());
+        assert!(check_overridable("CONFIG_PROFILE").is_err());
+    }
+
+    /// Variables which are read, but set by Heroku rather than by us.
+    const PLATFORM_VARIABLES: &[&str] = &["DYNO", "HEROKU_SLUG_COMMIT"];
+
+    /// The variable names passed to `dotenv::var` and its wrappers in a
+    /// source file.
+    fn read_variables(source: &str) -> Vec<String> {
+        let calls = &["dotenv::var(\"", "env(\"", "numeric_var(\"", "seconds(\""];
+        let mut names = Vec::new();
+        for call in calls {
+            for (start, _) in source.match_indices(call) {
+                let rest = &source[start + call.len()..];
+                if let Some(end) = rest.find('"') {
+                    names.push(rest[..end].to_string());
+                }
+            }
+        }
+        names
+    }
+
+    /// Collects the variables read in a directory, apart from the
+    /// integration tests.
+    fn collect_read_variables(dir: &Path, names: &mut Vec<(String, String)>) {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                if !path.ends_with("tests") {
+                    collect_read_variables(&path, names);
+                }
+            } else if path.extension().map_or(false, |ext| ext == "rs") {
+                let source = std::fs::read_to_string(&path).unwrap();
+                for name in read_variables(&source) {
+                    names.push((path.display().to_string(), name));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn every_variable_read_is_known() {
+        let mut names = Vec::new();
+        let src = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        collect_read_variables(&src, &mut names);
+        assert!(!names.is_empty());
+
+        let unknown = names
+            .into_iter()
+            .filter(|(_, name)| {
+                !is_known(name)
+                    && !name.starts_with("TEST_")
+                    && !PLATFORM_VARIABLES.contains(&name.as_str())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(unknown, Vec::<(String, String)>::new());
+    }
+}
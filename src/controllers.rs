@@ -89,12 +89,21 @@ mod prelude {
 pub mod helpers;
 mod util;
 
+pub mod admin;
 pub mod category;
 pub mod crate_owner_invitation;
+pub mod index_config;
+pub mod index_signing;
 pub mod keyword;
 pub mod krate;
+pub mod metrics;
+pub mod org;
+pub mod partner;
 pub mod site_metadata;
+pub mod sitemap;
+pub mod stats;
 pub mod team;
 pub mod token;
+pub mod transparency_log;
 pub mod user;
 pub mod version;
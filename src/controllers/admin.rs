@@ -0,0 +1,164 @@
+//! The API for admins, who are configured with `GH_ADMIN_USER_IDS`
+//!
+//! These endpoints let whoever is on call handle support requests which would
+//! otherwise need a change to the database or a deploy.
+
+use chrono::NaiveDateTime;
+
+use super::frontend_prelude::*;
+
+use crate::background_jobs::EnqueueVersioned;
+use crate::config::ConfigSource;
+use crate::git;
+use crate::models::{Crate, CrateUploadLimit, IndexConfig, IndexSettings, User};
+use crate::util::errors::{internal, NotFound, Unauthorized};
+use crate::views::EncodableCrateUploadLimit;
+
+/// The user making the request, if they are an admin.
+fn authenticate_admin(req: &dyn Request, conn: &PgConnection) -> AppResult<User> {
+    let user = req.authenticate(conn)?.find_user(conn)?;
+    if !req.app().config.admin_github_ids.contains(&user.gh_id) {
+        return Err(Box::new(Unauthorized));
+    }
+    Ok(user)
+}
+
+#[derive(Deserialize)]
+struct NewUploadLimit {
+    max_upload_size: i32,
+    reason: String,
+    /// When the limit stops applying, or `None` to keep it until it's
+    /// removed.
+    expires_at: Option<NaiveDateTime>,
+}
+
+/// Handles the `PUT /api/private/admin/crates/:crate_id/upload-limit` route.
+pub fn set_upload_limit(req: &mut dyn Request) -> AppResult<Response> {
+    let conn = req.db_conn()?;
+    let admin = authenticate_admin(req, &conn)?;
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let new_limit = serde_json::from_str::<NewUploadLimit>(&body)
+        .map_err(|e| bad_request(&format_args!("invalid upload limit: {}", e)))?;
+    if new_limit.max_upload_size <= 0 {
+        return Err(bad_request(
+            "`max_upload_size` must be a positive number of bytes",
+        ));
+    }
+    if new_limit.reason.trim().is_empty() {
+        return Err(bad_request("a `reason` for the upload limit is required"));
+    }
+
+    let crate_name = &req.params()["crate_id"];
+    let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
+    let limit = CrateUploadLimit::set(
+        &conn,
+        krate.id,
+        new_limit.max_upload_size,
+        new_limit.reason.trim(),
+        new_limit.expires_at,
+        admin.id,
+    )?;
+    req.log_metadata("admin", admin.gh_login.clone());
+
+    #[derive(Serialize)]
+    struct R {
+        upload_limit: EncodableCrateUploadLimit,
+    }
+    Ok(req.json(&R {
+        upload_limit: limit.encodable(&krate.name),
+    }))
+}
+
+/// Handles the `DELETE /api/private/admin/crates/:crate_id/upload-limit`
+/// route.
+pub fn remove_upload_limit(req: &mut dyn Request) -> AppResult<Response> {
+    let conn = req.db_conn()?;
+    let admin = authenticate_admin(req, &conn)?;
+    let crate_name = &req.params()["crate_id"];
+    let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
+    if !CrateUploadLimit::remove(&conn, krate.id)? {
+        return Err(Box::new(NotFound));
+    }
+    req.log_metadata("admin", admin.gh_login.clone());
+    ok_true()
+}
+
+/// Handles the `GET /api/private/admin/config` route, which lists the
+/// variables the running server was configured with and where they came
+/// from, without the values of secrets.
+pub fn config(req: &mut dyn Request) -> AppResult<Response> {
+    let conn = req.db_conn()?;
+    authenticate_admin(req, &conn)?;
+
+    #[derive(Serialize)]
+    struct Variable<'a> {
+        name: &'a str,
+        value: &'a str,
+        source: ConfigSource,
+    }
+    #[derive(Serialize)]
+    struct R<'a> {
+        profile: Option<&'a str>,
+        variables: Vec<Variable<'a>>,
+    }
+    let layers = &req.app().config.layers;
+    let variables = layers
+        .redacted()
+        .map(|(name, value, source)| Variable {
+            name,
+            value,
+            source,
+        })
+        .collect();
+    Ok(req.json(&R {
+        profile: layers.profile.as_deref(),
+        variables,
+    }))
+}
+
+#[derive(Deserialize)]
+struct NewIndexConfig {
+    dl_endpoints: Vec<NewDownloadEndpoint>,
+    checksum_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct NewDownloadEndpoint {
+    url: String,
+    priority: i32,
+}
+
+/// Handles the `PUT /api/private/admin/index-config` route, which replaces
+/// the download endpoints and the checksum URL template of the index, like
+/// the `index-config` binary, and responds with the new `config.json`.
+pub fn set_index_config(req: &mut dyn Request) -> AppResult<Response> {
+    let conn = req.db_conn()?;
+    let admin = authenticate_admin(req, &conn)?;
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let new_config = serde_json::from_str::<NewIndexConfig>(&body)
+        .map_err(|e| bad_request(&format_args!("invalid index config: {}", e)))?;
+    let settings =
+        IndexSettings::from_environment(&req.app().config.uploader).map_err(|e| internal(&e))?;
+
+    let config = conn.transaction::<_, Box<dyn AppError>, _>(|| {
+        for endpoint in IndexConfig::load(&conn, &settings)?.dl_endpoints {
+            IndexConfig::remove_download_endpoint(&conn, &endpoint.url)?;
+        }
+        for endpoint in &new_config.dl_endpoints {
+            IndexConfig::add_download_endpoint(&conn, &endpoint.url, endpoint.priority)
+                .map_err(|e| bad_request(&e))?;
+        }
+        IndexConfig::set_checksum_url(&conn, new_config.checksum_url.as_deref())
+            .map_err(|e| bad_request(&e))?;
+        git::update_index_config()
+            .enqueue_versioned(&conn)
+            .map_err(|e| AppError::from_std_error(e))?;
+        Ok(IndexConfig::load(&conn, &settings)?)
+    })?;
+    req.log_metadata("admin", admin.gh_login.clone());
+    Ok(req.json(&config))
+}
@@ -1,8 +1,10 @@
 use super::frontend_prelude::*;
 
+use crate::background_jobs::EnqueueVersioned;
 use crate::models::{CrateOwner, CrateOwnerInvitation, OwnerKind};
-use crate::schema::{crate_owner_invitations, crate_owners};
+use crate::schema::{crate_owner_invitations, crate_owners, crates};
 use crate::views::{EncodableCrateOwnerInvitation, InvitationResponse};
+use crate::{crate_page, og_image};
 
 /// Handles the `GET /me/crate_owner_invitations` route.
 pub fn list(req: &mut dyn Request) -> AppResult<Response> {
@@ -99,6 +101,16 @@ fn accept_invite(
         delete(crate_owner_invitations::table.find((user_id, crate_invite.crate_id)))
             .execute(conn)?;
 
+        let crate_name = crates::table
+            .find(crate_invite.crate_id)
+            .select(crates::name)
+            .first::<String>(conn)?;
+        og_image::enqueue_render(conn, &req.app().config, &crate_name)
+            .map_err(|e| AppError::from_std_error(e))?;
+        crate_page::render_crate_page(crate_name)
+            .enqueue_versioned(conn)
+            .map_err(|e| AppError::from_std_error(e))?;
+
         #[derive(Serialize)]
         struct R {
             crate_owner_invitation: InvitationResponse,
@@ -0,0 +1,31 @@
+//! The `config.json` file of the index, as the `update_index_config` job
+//! would write it with the current configuration.
+
+use super::prelude::*;
+
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use crate::models::{IndexConfig, IndexSettings};
+use crate::util::errors::internal;
+
+/// Handles the `GET /index-config` route.
+pub fn show(req: &mut dyn Request) -> AppResult<Response> {
+    let settings =
+        IndexSettings::from_environment(&req.app().config.uploader).map_err(|e| internal(&e))?;
+    let conn = req.db_read_only()?;
+    let body = IndexConfig::load(&conn, &settings)?
+        .to_json()
+        .map_err(|e| internal(&e))?;
+
+    let mut headers = HashMap::new();
+    headers.insert(
+        "Content-Type".to_string(),
+        vec!["application/json; charset=utf-8".to_string()],
+    );
+    Ok(Response {
+        status: (200, "OK"),
+        headers,
+        body: Box::new(Cursor::new(body.into_bytes())),
+    })
+}
@@ -0,0 +1,53 @@
+//! The keys the commits to the index are signed with, see the
+//! `index_signing` module for how to verify them.
+
+use super::prelude::*;
+
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use crate::models::IndexSigningKey;
+use crate::views::EncodableIndexSigningKey;
+
+/// Handles the `GET /index-signing-keys` route.
+pub fn keys(req: &mut dyn Request) -> AppResult<Response> {
+    let conn = req.db_read_only()?;
+    let keys = IndexSigningKey::all(&conn)?;
+    let allowed_signers = IndexSigningKey::allowed_signers(&keys);
+    let keys = keys
+        .into_iter()
+        .map(|key| EncodableIndexSigningKey {
+            fingerprint: key.fingerprint,
+            public_key: key.public_key,
+            first_used_at: key.first_used_at,
+        })
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        keys: Vec<EncodableIndexSigningKey>,
+        allowed_signers: String,
+    }
+    Ok(req.json(&R {
+        keys,
+        allowed_signers,
+    }))
+}
+
+/// Handles the `GET /index-signing-keys/allowed_signers` route, serving the
+/// file for `gpg.ssh.allowedSignersFile`.
+pub fn allowed_signers(req: &mut dyn Request) -> AppResult<Response> {
+    let conn = req.db_read_only()?;
+    let body = IndexSigningKey::allowed_signers(&IndexSigningKey::all(&conn)?);
+
+    let mut headers = HashMap::new();
+    headers.insert(
+        "Content-Type".to_string(),
+        vec!["text/plain; charset=utf-8".to_string()],
+    );
+    Ok(Response {
+        status: (200, "OK"),
+        headers,
+        body: Box::new(Cursor::new(body.into_bytes())),
+    })
+}
@@ -1,6 +1,13 @@
+pub mod badges;
+pub mod custom_links;
+pub mod deleted;
+pub mod dependency_cycles;
+pub mod dependency_updates;
 pub mod downloads;
 pub mod follow;
+pub mod history;
 pub mod metadata;
 pub mod owners;
 pub mod publish;
 pub mod search;
+pub mod squatting;
@@ -0,0 +1,174 @@
+//! Endpoints returning shields.io endpoint badge JSON
+//!
+//! See <https://shields.io/endpoint> for the format. These endpoints only
+//! load the few columns they need and are cached aggressively, so that badge
+//! traffic doesn't have to go through the full crate endpoint.
+
+use crate::controllers::frontend_prelude::*;
+
+use crate::models::Crate;
+use crate::schema::{crates, default_versions, versions};
+
+/// How long clients and CDNs may cache badge responses, in seconds.
+const MAX_AGE: u32 = 60 * 60;
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ShieldsBadge {
+    schema_version: u8,
+    label: &'static str,
+    message: String,
+    color: &'static str,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    is_error: bool,
+}
+
+impl ShieldsBadge {
+    fn new(label: &'static str, message: String, color: &'static str) -> Self {
+        Self {
+            schema_version: 1,
+            label,
+            message,
+            color,
+            is_error: false,
+        }
+    }
+
+    fn error(label: &'static str, message: &str) -> Self {
+        Self {
+            is_error: true,
+            ..Self::new(label, message.into(), "lightgrey")
+        }
+    }
+}
+
+/// The columns needed to render any of the badges.
+#[derive(Queryable)]
+struct BadgeData {
+    downloads: i32,
+    version: String,
+    yanked: bool,
+    license: Option<String>,
+    rust_version: Option<String>,
+}
+
+fn load(req: &dyn Request) -> AppResult<Option<BadgeData>> {
+    let crate_name = &req.params()["crate_id"];
+    let conn = req.db_read_only()?;
+    let data = crates::table
+        .inner_join(default_versions::table.inner_join(versions::table))
+        .filter(Crate::with_name(crate_name))
+        .select((
+            crates::downloads,
+            versions::num,
+            versions::yanked,
+            versions::license,
+            versions::rust_version,
+        ))
+        .first(&*conn)
+        .optional()?;
+    Ok(data)
+}
+
+fn badge_response(req: &dyn Request, badge: &ShieldsBadge) -> Response {
+    let mut response = req.json(badge);
+    response.headers.insert(
+        "Cache-Control".into(),
+        vec![format!("public, max-age={}", MAX_AGE)],
+    );
+    response
+}
+
+/// Handles the `GET /crates/:crate_id/badges/downloads` route.
+pub fn downloads(req: &mut dyn Request) -> AppResult<Response> {
+    let badge = match load(req)? {
+        Some(data) => ShieldsBadge::new("downloads", humanize(data.downloads), "blue"),
+        None => ShieldsBadge::error("downloads", "crate not found"),
+    };
+    Ok(badge_response(req, &badge))
+}
+
+/// Handles the `GET /crates/:crate_id/badges/version` route.
+pub fn version(req: &mut dyn Request) -> AppResult<Response> {
+    let badge = match load(req)? {
+        Some(data) => {
+            let color = if data.yanked {
+                "red"
+            } else if data.version.contains('-') {
+                "orange"
+            } else {
+                "green"
+            };
+            ShieldsBadge::new("crates.io", format!("v{}", data.version), color)
+        }
+        None => ShieldsBadge::error("crates.io", "crate not found"),
+    };
+    Ok(badge_response(req, &badge))
+}
+
+/// Handles the `GET /crates/:crate_id/badges/license` route.
+pub fn license(req: &mut dyn Request) -> AppResult<Response> {
+    let badge = match load(req)? {
+        Some(BadgeData {
+            license: Some(license),
+            ..
+        }) => ShieldsBadge::new("license", license, "blue"),
+        Some(_) => ShieldsBadge::new("license", "unknown".into(), "lightgrey"),
+        None => ShieldsBadge::error("license", "crate not found"),
+    };
+    Ok(badge_response(req, &badge))
+}
+
+/// Handles the `GET /crates/:crate_id/badges/msrv` route.
+///
+/// Reports the `package.rust-version` of the default version, or unknown if
+/// it didn't declare one.
+pub fn msrv(req: &mut dyn Request) -> AppResult<Response> {
+    let badge = match load(req)? {
+        Some(BadgeData {
+            rust_version: Some(rust_version),
+            ..
+        }) => ShieldsBadge::new("msrv", rust_version, "blue"),
+        Some(_) => ShieldsBadge::new("msrv", "unknown".into(), "lightgrey"),
+        None => ShieldsBadge::error("msrv", "crate not found"),
+    };
+    Ok(badge_response(req, &badge))
+}
+
+/// Formats a download count like `950`, `12k` or `3.4M`.
+fn humanize(downloads: i32) -> String {
+    let downloads = f64::from(downloads);
+    if downloads >= 1_000_000.0 {
+        format_with_suffix(downloads / 1_000_000.0, "M")
+    } else if downloads >= 1_000.0 {
+        format_with_suffix(downloads / 1_000.0, "k")
+    } else {
+        downloads.to_string()
+    }
+}
+
+/// Uses one decimal place for values below 10, e.g. `3.4M` but `34M`.
+fn format_with_suffix(value: f64, suffix: &str) -> String {
+    if value < 10.0 {
+        let rounded = (value * 10.0).floor() / 10.0;
+        format!("{}{}", rounded, suffix)
+    } else {
+        format!("{}{}", value.floor(), suffix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::humanize;
+
+    #[test]
+    fn humanize_downloads() {
+        assert_eq!(humanize(0), "0");
+        assert_eq!(humanize(999), "999");
+        assert_eq!(humanize(1_000), "1k");
+        assert_eq!(humanize(1_450), "1.4k");
+        assert_eq!(humanize(12_999), "12k");
+        assert_eq!(humanize(3_456_789), "3.4M");
+        assert_eq!(humanize(34_567_890), "34M");
+    }
+}
@@ -0,0 +1,66 @@
+//! Endpoints for the funding, chat and security policy links owners can add
+//! to a crate
+
+use crate::background_jobs::EnqueueVersioned;
+use crate::controllers::frontend_prelude::*;
+use crate::{crate_page, og_image};
+
+use crate::models::{
+    Crate, CrateLinkEvent, CrateLinks, CrateMetadata, CrateMetadataChange, Rights,
+};
+use crate::views::{EncodableCrateLinkEvent, EncodableCustomLinks};
+
+/// Handles the `PUT /crates/:crate_id/custom_links` route.
+///
+/// The body contains all links of the crate, links which are missing or
+/// blank are removed.
+pub fn update(req: &mut dyn Request) -> AppResult<Response> {
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let links: EncodableCustomLinks =
+        serde_json::from_str(&body).map_err(|e| bad_request(&format_args!("{}", e)))?;
+
+    let conn = req.db_conn()?;
+    let user = req.authenticate(&conn)?.find_user(&conn)?;
+    let crate_name = &req.params()["crate_id"];
+    let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
+    let owners = krate.owners(&conn)?;
+    if user.rights(req.app(), &owners)? < Rights::Publish {
+        return Err(cargo_err("only owners of a crate can change its links"));
+    }
+
+    let previous = CrateMetadata::load(&conn, krate.id)?;
+    let custom_links = CrateLinks::update(&conn, krate.id, user.id, &links)?;
+    CrateMetadataChange::record(&conn, krate.id, user.id, &previous)?;
+    req.app().response_cache.invalidate_crate(&krate.name);
+    og_image::enqueue_render(&conn, &req.app().config, &krate.name)
+        .map_err(|e| AppError::from_std_error(e))?;
+    crate_page::render_crate_page(krate.name)
+        .enqueue_versioned(&conn)
+        .map_err(|e| AppError::from_std_error(e))?;
+
+    #[derive(Serialize)]
+    struct R {
+        custom_links: EncodableCustomLinks,
+    }
+    Ok(req.json(&R { custom_links }))
+}
+
+/// Handles the `GET /crates/:crate_id/custom_links/history` route.
+pub fn history(req: &mut dyn Request) -> AppResult<Response> {
+    let conn = req.db_read_only()?;
+    let crate_name = &req.params()["crate_id"];
+    let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
+    let events = CrateLinkEvent::by_crate(&conn, krate.id)?
+        .into_iter()
+        .map(|(event, user)| event.encodable(user))
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        custom_link_events: Vec<EncodableCrateLinkEvent>,
+    }
+    Ok(req.json(&R {
+        custom_link_events: events,
+    }))
+}
@@ -0,0 +1,66 @@
+//! The public list of crates and versions removed from the registry
+
+use chrono::DateTime;
+
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::helpers::Paginate;
+use crate::models::DeletedCrate;
+use crate::schema::deleted_crates;
+use crate::views::EncodableDeletedCrate;
+
+/// Handles the `GET /deleted_crates` route.
+///
+/// Lists deleted crates and versions, oldest first, optionally only those
+/// deleted since the RFC 3339 timestamp in the `since` query parameter, so
+/// that mirrors can remove them as well. Deletions take effect immediately,
+/// so nothing is ever listed as pending.
+pub fn index(req: &mut dyn Request) -> AppResult<Response> {
+    let params = req.query();
+    let since = match params.get("since") {
+        Some(since) => Some(
+            DateTime::parse_from_rfc3339(since)
+                .map_err(|_| {
+                    bad_request(&format_args!(
+                        "invalid `since` value: `{}`, expected an RFC 3339 timestamp",
+                        since
+                    ))
+                })?
+                .naive_utc(),
+        ),
+        None => None,
+    };
+
+    let conn = req.db_read_only()?;
+    let mut query = deleted_crates::table
+        .order((deleted_crates::deleted_at, deleted_crates::id))
+        .into_boxed();
+    if let Some(since) = since {
+        query = query.filter(deleted_crates::deleted_at.ge(since));
+    }
+    let data = query.paginate(&params)?.load::<DeletedCrate>(&*conn)?;
+    let total = data.total();
+    let next_page = data.next_page_params().map(|p| req.query_with_params(p));
+    let prev_page = data.prev_page_params().map(|p| req.query_with_params(p));
+    let deleted_crates = data.into_iter().map(DeletedCrate::encodable).collect();
+
+    #[derive(Serialize)]
+    struct R {
+        deleted_crates: Vec<EncodableDeletedCrate>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        total: Option<i64>,
+        next_page: Option<String>,
+        prev_page: Option<String>,
+    }
+
+    Ok(req.json(&R {
+        deleted_crates,
+        meta: Meta {
+            total,
+            next_page,
+            prev_page,
+        },
+    }))
+}
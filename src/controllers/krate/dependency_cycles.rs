@@ -0,0 +1,34 @@
+//! Endpoint for the dependency cycles introduced by publishes of a crate
+
+use crate::controllers::frontend_prelude::*;
+
+use crate::models::{Crate, DependencyCycle, Rights};
+use crate::views::EncodableDependencyCycle;
+
+/// Handles the `GET /crates/:crate_id/dependency_cycles` route.
+///
+/// Lists the cycles through dev- or build-dependencies which publishes of
+/// the crate introduced, newest first. Only owners can see them.
+pub fn list(req: &mut dyn Request) -> AppResult<Response> {
+    let conn = req.db_read_only()?;
+    let user = req.authenticate(&conn)?.find_user(&conn)?;
+    let crate_name = &req.params()["crate_id"];
+    let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
+    let owners = krate.owners(&conn)?;
+    if user.rights(req.app(), &owners)? < Rights::Publish {
+        return Err(cargo_err(
+            "only owners of a crate can see its dependency cycles",
+        ));
+    }
+
+    let dependency_cycles = DependencyCycle::by_crate(&conn, krate.id)?
+        .into_iter()
+        .map(|(cycle, version)| cycle.encodable(version))
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        dependency_cycles: Vec<EncodableDependencyCycle>,
+    }
+    Ok(req.json(&R { dependency_cycles }))
+}
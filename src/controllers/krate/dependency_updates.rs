@@ -0,0 +1,75 @@
+//! Endpoints for opting into daily emails about semver-incompatible releases
+//! of the dependencies of a crate
+
+use diesel::dsl::exists;
+
+use crate::controllers::frontend_prelude::*;
+use crate::db::DieselPooledConn;
+use crate::models::{Crate, OwnerKind};
+use crate::schema::*;
+
+/// Returns the `(crate_id, user_id)` of the subscription, if the user owns
+/// the crate directly. Members of owning teams can't subscribe, since the
+/// notifications are only sent to individual owners.
+fn subscription_target(req: &dyn Request, conn: &DieselPooledConn<'_>) -> AppResult<(i32, i32)> {
+    let user_id = req.authenticate(conn)?.user_id();
+    let crate_name = &req.params()["crate_id"];
+    let crate_id = Crate::by_name(crate_name)
+        .select(crates::id)
+        .first(&**conn)?;
+    let is_owner = diesel::select(exists(
+        crate_owners::table
+            .filter(crate_owners::crate_id.eq(crate_id))
+            .filter(crate_owners::owner_id.eq(user_id))
+            .filter(crate_owners::owner_kind.eq(OwnerKind::User as i32))
+            .filter(crate_owners::deleted.eq(false)),
+    ))
+    .get_result::<bool>(&**conn)?;
+    if !is_owner {
+        return Err(cargo_err(
+            "only owners of a crate can subscribe to updates of its dependencies",
+        ));
+    }
+    Ok((crate_id, user_id))
+}
+
+/// Handles the `PUT /crates/:crate_id/dependency_updates` route.
+pub fn subscribe(req: &mut dyn Request) -> AppResult<Response> {
+    let conn = req.db_conn()?;
+    let (crate_id, user_id) = subscription_target(req, &conn)?;
+    diesel::insert_into(dependency_update_subscriptions::table)
+        .values((
+            dependency_update_subscriptions::crate_id.eq(crate_id),
+            dependency_update_subscriptions::user_id.eq(user_id),
+        ))
+        .on_conflict_do_nothing()
+        .execute(&*conn)?;
+
+    ok_true()
+}
+
+/// Handles the `DELETE /crates/:crate_id/dependency_updates` route.
+pub fn unsubscribe(req: &mut dyn Request) -> AppResult<Response> {
+    let conn = req.db_conn()?;
+    let (crate_id, user_id) = subscription_target(req, &conn)?;
+    diesel::delete(dependency_update_subscriptions::table.find((crate_id, user_id)))
+        .execute(&*conn)?;
+
+    ok_true()
+}
+
+/// Handles the `GET /crates/:crate_id/dependency_updates` route.
+pub fn subscribed(req: &mut dyn Request) -> AppResult<Response> {
+    let conn = req.db_conn()?;
+    let (crate_id, user_id) = subscription_target(req, &conn)?;
+    let subscribed = diesel::select(exists(
+        dependency_update_subscriptions::table.find((crate_id, user_id)),
+    ))
+    .get_result(&*conn)?;
+
+    #[derive(Serialize)]
+    struct R {
+        subscribed: bool,
+    }
+    Ok(req.json(&R { subscribed }))
+}
@@ -4,66 +4,227 @@
 //! download counts are located in `krate::downloads`.
 
 use std::cmp;
+use std::collections::HashMap;
+
+use chrono::{Duration, NaiveDate, Utc};
 
 use crate::controllers::frontend_prelude::*;
 
-use crate::models::{Crate, CrateVersions, Version, VersionDownload};
+use crate::models::{Crate, CrateVersions, DownloadAnomaly, Rights, Version, VersionDownload};
 use crate::schema::version_downloads;
-use crate::views::EncodableVersionDownload;
+use crate::util::errors::NotFound;
+use crate::views::{EncodableDownloadAnomaly, EncodableVersionDownload};
 
 use crate::models::krate::to_char;
 
+/// The maximum number of versions that can be compared with `per_version`.
+const MAX_COMPARED_VERSIONS: usize = 10;
+
 /// Handles the `GET /crates/:crate_id/downloads` route.
+///
+/// With `per_version=true&versions=1.0.0,2.0.0`, the daily downloads of the
+/// given versions over the last 90 days are returned instead, as series
+/// aligned to the same dates.
 pub fn downloads(req: &mut dyn Request) -> AppResult<Response> {
-    use diesel::dsl::*;
-    use diesel::sql_types::BigInt;
-
     let crate_name = &req.params()["crate_id"];
     let conn = req.db_read_only()?;
     let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
 
-    let mut versions = krate.all_versions().load::<Version>(&*conn)?;
-    versions.sort_by(|a, b| b.num.cmp(&a.num));
-    let (latest_five, rest) = versions.split_at(cmp::min(5, versions.len()));
+    let query = req.query();
+    if query.get("per_version").map(String::as_str) == Some("true") {
+        let nums = query
+            .get("versions")
+            .map(|versions| parse_version_list(versions))
+            .unwrap_or_default();
+        if nums.is_empty() {
+            return Err(bad_request(
+                "`versions` is required with `per_version=true`",
+            ));
+        }
+        if nums.len() > MAX_COMPARED_VERSIONS {
+            return Err(bad_request(&format_args!(
+                "at most {} versions can be compared",
+                MAX_COMPARED_VERSIONS
+            )));
+        }
+        return Ok(req.json(&VersionComparison::load(&conn, &krate, &nums)?));
+    }
 
-    let downloads = VersionDownload::belonging_to(latest_five)
-        .filter(version_downloads::date.gt(date(now - 90.days())))
-        .order(version_downloads::date.asc())
-        .load(&*conn)?
-        .into_iter()
-        .map(VersionDownload::encodable)
-        .collect::<Vec<_>>();
-
-    let sum_downloads = sql::<BigInt>("SUM(version_downloads.downloads)");
-    let extra = VersionDownload::belonging_to(rest)
-        .select((
-            to_char(version_downloads::date, "YYYY-MM-DD"),
-            sum_downloads,
-        ))
-        .filter(version_downloads::date.gt(date(now - 90.days())))
-        .group_by(version_downloads::date)
-        .order(version_downloads::date.asc())
-        .load::<ExtraDownload>(&*conn)?;
-
-    #[derive(Serialize, Queryable)]
-    struct ExtraDownload {
-        date: String,
-        downloads: i64,
+    Ok(req.json(&CrateDownloads::load(&conn, &krate)?))
+}
+
+/// Handles the `GET /crates/:crate_id/download_anomalies` route.
+///
+/// Lists the days on which the crate was downloaded far more often than
+/// usual, see the `detect_download_anomalies` job. Only owners can see them,
+/// and only if `SHOW_DOWNLOAD_ANOMALIES_TO_OWNERS` is set.
+pub fn anomalies(req: &mut dyn Request) -> AppResult<Response> {
+    if !req.app().config.show_download_anomalies_to_owners {
+        return Err(Box::new(NotFound));
+    }
+    let conn = req.db_read_only()?;
+    let user = req.authenticate(&conn)?.find_user(&conn)?;
+    let crate_name = &req.params()["crate_id"];
+    let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
+    let owners = krate.owners(&conn)?;
+    if user.rights(req.app(), &owners)? < Rights::Publish {
+        return Err(cargo_err(
+            "only owners of a crate can see its download anomalies",
+        ));
     }
+
+    let anomalies = DownloadAnomaly::by_crate(&conn, krate.id)?
+        .into_iter()
+        .map(DownloadAnomaly::encodable)
+        .collect();
+
     #[derive(Serialize)]
     struct R {
-        version_downloads: Vec<EncodableVersionDownload>,
-        meta: Meta,
+        anomalies: Vec<EncodableDownloadAnomaly>,
     }
-    #[derive(Serialize)]
-    struct Meta {
-        extra_downloads: Vec<ExtraDownload>,
+    Ok(req.json(&R { anomalies }))
+}
+
+/// Splits a comma separated list of versions, ignoring blanks and
+/// duplicates.
+fn parse_version_list(versions: &str) -> Vec<&str> {
+    let mut nums = Vec::new();
+    for num in versions.split(',').map(str::trim) {
+        if !num.is_empty() && !nums.contains(&num) {
+            nums.push(num);
+        }
+    }
+    nums
+}
+
+/// The payload of the `GET /crates/:crate_id/downloads?per_version=true`
+/// route. `downloads` of every series has one entry per date.
+#[derive(Serialize)]
+struct VersionComparison {
+    dates: Vec<String>,
+    version_series: Vec<VersionSeries>,
+}
+
+#[derive(Serialize)]
+struct VersionSeries {
+    version: String,
+    downloads: Vec<i32>,
+}
+
+impl VersionComparison {
+    fn load(conn: &PgConnection, krate: &Crate, nums: &[&str]) -> AppResult<Self> {
+        use crate::schema::versions;
+
+        let versions = krate
+            .all_versions()
+            .filter(versions::num.eq_any(nums))
+            .select((versions::id, versions::num))
+            .load::<(i32, String)>(conn)?;
+        let ids = nums
+            .iter()
+            .map(|num| {
+                versions
+                    .iter()
+                    .find(|(_, version)| version == num)
+                    .map(|(id, _)| *id)
+                    .ok_or_else(|| {
+                        bad_request(&format_args!(
+                            "crate `{}` does not have a version `{}`",
+                            krate.name, num
+                        ))
+                    })
+            })
+            .collect::<AppResult<Vec<_>>>()?;
+
+        let today = Utc::today().naive_utc();
+        let first_date = today - Duration::days(89);
+        let rows = version_downloads::table
+            .filter(version_downloads::version_id.eq_any(&ids))
+            .filter(version_downloads::date.ge(first_date))
+            .select((
+                version_downloads::version_id,
+                version_downloads::date,
+                version_downloads::downloads,
+            ))
+            .load::<(i32, NaiveDate, i32)>(conn)?;
+
+        let dates = (0..90)
+            .map(|days| first_date + Duration::days(days))
+            .collect::<Vec<_>>();
+        let mut downloads = HashMap::new();
+        for (version_id, date, count) in rows {
+            downloads.insert((version_id, date), count);
+        }
+
+        Ok(Self {
+            version_series: nums
+                .iter()
+                .zip(ids)
+                .map(|(num, id)| VersionSeries {
+                    version: num.to_string(),
+                    downloads: dates
+                        .iter()
+                        .map(|date| downloads.get(&(id, *date)).copied().unwrap_or(0))
+                        .collect(),
+                })
+                .collect(),
+            dates: dates.iter().map(|date| date.to_string()).collect(),
+        })
+    }
+}
+
+/// The payload of the `GET /crates/:crate_id/downloads` route, which is also
+/// part of the precomputed crate page.
+#[derive(Serialize)]
+pub(crate) struct CrateDownloads {
+    version_downloads: Vec<EncodableVersionDownload>,
+    meta: Meta,
+}
+
+#[derive(Serialize)]
+struct Meta {
+    extra_downloads: Vec<ExtraDownload>,
+}
+
+#[derive(Serialize, Queryable)]
+struct ExtraDownload {
+    date: String,
+    downloads: i64,
+}
+
+impl CrateDownloads {
+    pub(crate) fn load(conn: &PgConnection, krate: &Crate) -> QueryResult<Self> {
+        use diesel::dsl::*;
+        use diesel::sql_types::BigInt;
+
+        let mut versions = krate.all_versions().load::<Version>(conn)?;
+        versions.sort_by(|a, b| b.num.cmp(&a.num));
+        let (latest_five, rest) = versions.split_at(cmp::min(5, versions.len()));
+
+        let downloads = VersionDownload::belonging_to(latest_five)
+            .filter(version_downloads::date.gt(date(now - 90.days())))
+            .order(version_downloads::date.asc())
+            .load(conn)?
+            .into_iter()
+            .map(VersionDownload::encodable)
+            .collect::<Vec<_>>();
+
+        let sum_downloads = sql::<BigInt>("SUM(version_downloads.downloads)");
+        let extra = VersionDownload::belonging_to(rest)
+            .select((
+                to_char(version_downloads::date, "YYYY-MM-DD"),
+                sum_downloads,
+            ))
+            .filter(version_downloads::date.gt(date(now - 90.days())))
+            .group_by(version_downloads::date)
+            .order(version_downloads::date.asc())
+            .load::<ExtraDownload>(conn)?;
+
+        Ok(Self {
+            version_downloads: downloads,
+            meta: Meta {
+                extra_downloads: extra,
+            },
+        })
     }
-    let meta = Meta {
-        extra_downloads: extra,
-    };
-    Ok(req.json(&R {
-        version_downloads: downloads,
-        meta,
-    }))
 }
@@ -0,0 +1,32 @@
+//! Endpoint for the history of the metadata of a crate
+
+use crate::controllers::frontend_prelude::*;
+
+use crate::models::{Crate, CrateMetadataChange, Rights};
+use crate::views::EncodableMetadataChange;
+
+/// Handles the `GET /crates/:crate_id/history` route.
+///
+/// Lists every change of the description, categories, keywords, links and
+/// deprecation of a crate, and who made it. Only owners can see it.
+pub fn history(req: &mut dyn Request) -> AppResult<Response> {
+    let conn = req.db_read_only()?;
+    let user = req.authenticate(&conn)?.find_user(&conn)?;
+    let crate_name = &req.params()["crate_id"];
+    let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
+    let owners = krate.owners(&conn)?;
+    if user.rights(req.app(), &owners)? < Rights::Publish {
+        return Err(cargo_err("only owners of a crate can see its history"));
+    }
+
+    let history = CrateMetadataChange::by_crate(&conn, krate.id)?
+        .into_iter()
+        .map(|(change, user)| change.encodable(user))
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        history: Vec<EncodableMetadataChange>,
+    }
+    Ok(req.json(&R { history }))
+}
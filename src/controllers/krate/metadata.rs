@@ -4,31 +4,60 @@
 //! index or cached metadata which was extracted (client side) from the
 //! `Cargo.toml` file.
 
+use crate::background_jobs::EnqueueVersioned;
 use crate::controllers::frontend_prelude::*;
+use crate::crate_page;
+use crate::response_cache::{crate_key, SUMMARY_KEY};
+use crate::util::raw_json_response;
 
 use crate::models::{
-    Category, Crate, CrateCategory, CrateKeyword, CrateVersions, Keyword, RecentCrateDownloads,
-    User, Version, VersionOwnerAction,
+    Category, Crate, CrateCategory, CrateKeyword, CrateLinks, CratePolicy, CrateQuality,
+    CrateSecurityPolicy, CrateVersions, DependencyFreshness, Keyword, OrgDomain,
+    RecentCrateDownloads, ReleaseStats, User, Version, VersionOwnerAction,
 };
 use crate::schema::*;
 use crate::views::{
-    EncodableCategory, EncodableCrate, EncodableDependency, EncodableKeyword, EncodableVersion,
+    EncodableCategory, EncodableCrate, EncodableCratePolicy, EncodableCrateQuality,
+    EncodableDependency, EncodableDependencyFreshness, EncodableFundingLink, EncodableKeyword,
+    EncodableReleaseStats, EncodableVersion,
 };
 
 use crate::models::krate::ALL_COLUMNS;
 
+use chrono::NaiveDateTime;
+use diesel::dsl::max;
+use std::collections::BTreeMap;
+
+/// The maximum number of crates per `GET /index-checksums` request.
+const MAX_INDEX_CHECKSUMS: usize = 100;
+
 /// Handles the `GET /summary` route.
 pub fn summary(req: &mut dyn Request) -> AppResult<Response> {
+    let conn = req.db_read_only()?;
+    let last_update = crates::table
+        .select(max(crates::updated_at))
+        .first::<Option<NaiveDateTime>>(&*conn)?;
+
+    let body = req.app().response_cache.fetch(
+        req.app(),
+        &*conn,
+        SUMMARY_KEY.into(),
+        format!("{:?}", last_update),
+        render_summary,
+    )?;
+    Ok(raw_json_response(body.to_string()))
+}
+
+fn render_summary(conn: &PgConnection) -> AppResult<String> {
     use crate::schema::crates::dsl::*;
 
-    let conn = req.db_read_only()?;
-    let num_crates = crates.count().get_result(&*conn)?;
+    let num_crates = crates.count().get_result(conn)?;
     let num_downloads = metadata::table
         .select(metadata::total_downloads)
-        .get_result(&*conn)?;
+        .get_result(conn)?;
 
     let encode_crates = |krates: Vec<Crate>| -> AppResult<Vec<_>> {
-        let versions = krates.versions().load::<Version>(&*conn)?;
+        let versions = krates.versions().load::<Version>(conn)?;
         versions
             .grouped_by(&krates)
             .into_iter()
@@ -44,35 +73,35 @@ pub fn summary(req: &mut dyn Request) -> AppResult<Response> {
         .order(created_at.desc())
         .select(ALL_COLUMNS)
         .limit(10)
-        .load(&*conn)?;
+        .load(conn)?;
     let just_updated = crates
         .filter(updated_at.ne(created_at))
         .order(updated_at.desc())
         .select(ALL_COLUMNS)
         .limit(10)
-        .load(&*conn)?;
+        .load(conn)?;
     let most_downloaded = crates
         .order(downloads.desc())
         .select(ALL_COLUMNS)
         .limit(10)
-        .load(&*conn)?;
+        .load(conn)?;
 
     let most_recently_downloaded = crates
         .inner_join(recent_crate_downloads::table)
         .order(recent_crate_downloads::downloads.desc())
         .select(ALL_COLUMNS)
         .limit(10)
-        .load(&*conn)?;
+        .load(conn)?;
 
     let popular_keywords = keywords::table
         .order(keywords::crates_cnt.desc())
         .limit(10)
-        .load(&*conn)?
+        .load(conn)?
         .into_iter()
         .map(Keyword::encodable)
         .collect();
 
-    let popular_categories = Category::toplevel(&conn, "crates", 10, 0)?
+    let popular_categories = Category::toplevel(conn, "crates", 10, 0)?
         .into_iter()
         .map(Category::encodable)
         .collect();
@@ -88,7 +117,7 @@ pub fn summary(req: &mut dyn Request) -> AppResult<Response> {
         popular_keywords: Vec<EncodableKeyword>,
         popular_categories: Vec<EncodableCategory>,
     }
-    Ok(req.json(&R {
+    Ok(serde_json::to_string(&R {
         num_downloads,
         num_crates,
         new_crates: encode_crates(new_crates)?,
@@ -97,64 +126,125 @@ pub fn summary(req: &mut dyn Request) -> AppResult<Response> {
         just_updated: encode_crates(just_updated)?,
         popular_keywords,
         popular_categories,
-    }))
+    })?)
 }
 
 /// Handles the `GET /crates/:crate_id` route.
 pub fn show(req: &mut dyn Request) -> AppResult<Response> {
     let name = &req.params()["crate_id"];
     let conn = req.db_read_only()?;
-    let krate = Crate::by_name(name).first::<Crate>(&*conn)?;
+    let (name, cache_version) = crate_cache_version(&*conn, name)?;
 
-    let mut versions_and_publishers = krate
-        .all_versions()
-        .left_outer_join(users::table)
-        .select((versions::all_columns, users::all_columns.nullable()))
-        .load::<(Version, Option<User>)>(&*conn)?;
-    versions_and_publishers.sort_by(|a, b| b.0.num.cmp(&a.0.num));
-    let versions = versions_and_publishers
-        .iter()
-        .map(|(v, _)| v)
-        .cloned()
-        .collect::<Vec<_>>();
-    let versions_publishers_and_audit_actions = versions_and_publishers
-        .into_iter()
-        .zip(VersionOwnerAction::for_versions(&conn, &versions)?.into_iter())
-        .map(|((v, pb), aas)| (v, pb, aas))
-        .collect::<Vec<_>>();
-    let ids = versions_publishers_and_audit_actions
-        .iter()
-        .map(|v| v.0.id)
-        .collect();
+    let key = crate_key(&name, "show");
+    let body =
+        req.app()
+            .response_cache
+            .fetch(req.app(), &*conn, key, cache_version, move |conn| {
+                let krate = Crate::by_name(&name).first::<Crate>(conn)?;
+                Ok(serde_json::to_string(&CrateDetails::load(conn, &krate)?)?)
+            })?;
+    Ok(raw_json_response(body.to_string()))
+}
 
-    let kws = CrateKeyword::belonging_to(&krate)
-        .inner_join(keywords::table)
-        .select(keywords::all_columns)
-        .load(&*conn)?;
-    let cats = CrateCategory::belonging_to(&krate)
-        .inner_join(categories::table)
-        .select(categories::all_columns)
-        .load(&*conn)?;
-    let recent_downloads = RecentCrateDownloads::belonging_to(&krate)
-        .select(recent_crate_downloads::downloads)
-        .get_result(&*conn)
-        .optional()?;
+/// The name of a crate and the version of its cached responses. Yanking a
+/// version only touches the `updated_at` of the version, so the latest one of
+/// its versions is part of it.
+fn crate_cache_version(conn: &PgConnection, name: &str) -> AppResult<(String, String)> {
+    let (id, name, updated_at) = Crate::by_name(name)
+        .select((crates::id, crates::name, crates::updated_at))
+        .first::<(i32, String, NaiveDateTime)>(conn)?;
+    let versions_updated_at = versions::table
+        .filter(versions::crate_id.eq(id))
+        .select(max(versions::updated_at))
+        .first::<Option<NaiveDateTime>>(conn)?;
+    Ok((name, format!("{}/{:?}", updated_at, versions_updated_at)))
+}
 
-    let badges = badges::table
-        .filter(badges::crate_id.eq(krate.id))
-        .load(&*conn)?;
-    let top_versions = krate.top_versions(&conn)?;
+/// Handles the `GET /crates/:crate_id/full` route.
+///
+/// Serves everything the crate page needs with a single request, see the
+/// `crate_page` module.
+pub fn full(req: &mut dyn Request) -> AppResult<Response> {
+    let name = &req.params()["crate_id"];
+    let conn = req.db_conn()?;
+    let krate = Crate::by_name(name).first::<Crate>(&*conn)?;
 
-    #[derive(Serialize)]
-    struct R {
-        #[serde(rename = "crate")]
-        krate: EncodableCrate,
-        versions: Vec<EncodableVersion>,
-        keywords: Vec<EncodableKeyword>,
-        categories: Vec<EncodableCategory>,
+    if let Some(payload) = crate_page::cached(&conn, krate.id)? {
+        return Ok(req.json(&payload));
     }
-    Ok(req.json(&R {
-        krate: krate.clone().encodable(
+
+    let payload = crate_page::payload(&conn, &krate).map_err(|e| AppError::from_std_error(e))?;
+    // Storing the payload is left to the job, so that this request doesn't
+    // write to the database. It fails in read only mode, which is fine.
+    if let Err(e) = crate_page::render_crate_page(krate.name.clone()).enqueue_versioned(&conn) {
+        eprintln!("Couldn't enqueue rendering the crate page: {}", e);
+    }
+    Ok(req.json(&payload))
+}
+
+/// The payload of the `GET /crates/:crate_id` route, which is also part of
+/// the precomputed crate page.
+#[derive(Serialize)]
+pub(crate) struct CrateDetails {
+    #[serde(rename = "crate")]
+    krate: EncodableCrate,
+    versions: Vec<EncodableVersion>,
+    keywords: Vec<EncodableKeyword>,
+    categories: Vec<EncodableCategory>,
+    quality: Option<EncodableCrateQuality>,
+    dependency_freshness: Option<EncodableDependencyFreshness>,
+}
+
+impl CrateDetails {
+    pub(crate) fn load(conn: &PgConnection, krate: &Crate) -> QueryResult<Self> {
+        let mut versions_and_publishers = krate
+            .all_versions()
+            .left_outer_join(users::table)
+            .select((versions::all_columns, users::all_columns.nullable()))
+            .load::<(Version, Option<User>)>(conn)?;
+        versions_and_publishers.sort_by(|a, b| b.0.num.cmp(&a.0.num));
+        let versions = versions_and_publishers
+            .iter()
+            .map(|(v, _)| v)
+            .cloned()
+            .collect::<Vec<_>>();
+        let versions_publishers_and_audit_actions = versions_and_publishers
+            .into_iter()
+            .zip(VersionOwnerAction::for_versions(conn, &versions)?.into_iter())
+            .map(|((v, pb), aas)| (v, pb, aas))
+            .collect::<Vec<_>>();
+        let ids = versions_publishers_and_audit_actions
+            .iter()
+            .map(|v| v.0.id)
+            .collect();
+
+        let kws = CrateKeyword::belonging_to(krate)
+            .inner_join(keywords::table)
+            .select(keywords::all_columns)
+            .load(conn)?;
+        let cats = CrateCategory::belonging_to(krate)
+            .inner_join(categories::table)
+            .select(categories::all_columns)
+            .load(conn)?;
+        let recent_downloads = RecentCrateDownloads::belonging_to(krate)
+            .select(recent_crate_downloads::downloads)
+            .get_result(conn)
+            .optional()?;
+
+        let badges = badges::table
+            .filter(badges::crate_id.eq(krate.id))
+            .load(conn)?;
+        let top_versions = krate.top_versions(conn)?;
+        let quality = crate_quality::table
+            .find(krate.id)
+            .first::<CrateQuality>(conn)
+            .optional()?;
+        let dependency_freshness = crate_dependency_freshness::table
+            .find(krate.id)
+            .first::<DependencyFreshness>(conn)
+            .optional()?;
+
+        let mut encodable_crate = krate.clone().encodable(
             &top_versions,
             Some(ids),
             Some(&kws),
@@ -162,14 +252,27 @@ pub fn show(req: &mut dyn Request) -> AppResult<Response> {
             Some(badges),
             false,
             recent_downloads,
-        ),
-        versions: versions_publishers_and_audit_actions
-            .into_iter()
-            .map(|(v, pb, aas)| v.encodable(&krate.name, pb, aas))
-            .collect(),
-        keywords: kws.into_iter().map(Keyword::encodable).collect(),
-        categories: cats.into_iter().map(Category::encodable).collect(),
-    }))
+        );
+        let custom_links = CrateLinks::load(conn, krate.id)?;
+        encodable_crate.security_policy_url = match &custom_links.security_policy {
+            Some(url) => Some(url.clone()),
+            None => CrateSecurityPolicy::url(conn, krate.id)?,
+        };
+        encodable_crate.custom_links = Some(custom_links);
+        encodable_crate.verified_publisher = OrgDomain::verified_publisher(conn, krate)?;
+
+        Ok(Self {
+            krate: encodable_crate,
+            versions: versions_publishers_and_audit_actions
+                .into_iter()
+                .map(|(v, pb, aas)| v.encodable(&krate.name, pb, aas))
+                .collect(),
+            keywords: kws.into_iter().map(Keyword::encodable).collect(),
+            categories: cats.into_iter().map(Category::encodable).collect(),
+            quality: quality.map(CrateQuality::encodable),
+            dependency_freshness: dependency_freshness.map(DependencyFreshness::encodable),
+        })
+    }
 }
 
 /// Handles the `GET /crates/:crate_id/:version/readme` route.
@@ -194,18 +297,186 @@ pub fn readme(req: &mut dyn Request) -> AppResult<Response> {
     }
 }
 
+/// Handles the `GET /crates/:crate_id/:version/sbom` route.
+///
+/// Redirects to the CycloneDX SBOM of the version, see the `sbom` module.
+pub fn sbom(req: &mut dyn Request) -> AppResult<Response> {
+    let crate_name = &req.params()["crate_id"];
+    let version = &req.params()["version"];
+
+    let redirect_url = req.app().config.uploader.sbom_location(crate_name, version);
+
+    if req.wants_json() {
+        #[derive(Serialize)]
+        struct R {
+            url: String,
+        }
+        Ok(req.json(&R { url: redirect_url }))
+    } else {
+        Ok(req.redirect(redirect_url))
+    }
+}
+
+/// Handles the `GET /crates/:crate_id/og_image` route.
+///
+/// Redirects to the Open Graph preview image of the crate, which gives
+/// crate pages a stable image URL independent of the storage location.
+pub fn og_image(req: &mut dyn Request) -> AppResult<Response> {
+    let crate_name = &req.params()["crate_id"];
+
+    let redirect_url = req.app().config.uploader.og_image_location(crate_name);
+
+    if req.wants_json() {
+        #[derive(Serialize)]
+        struct R {
+            url: String,
+        }
+        Ok(req.json(&R { url: redirect_url }))
+    } else {
+        Ok(req.redirect(redirect_url))
+    }
+}
+
+/// Handles the `GET /crates/:crate_id/policy` route.
+///
+/// Returns the policy from the `.crates-io.toml` file of the most recently
+/// published version, or `null` if that version didn't contain one.
+pub fn policy(req: &mut dyn Request) -> AppResult<Response> {
+    let crate_name = &req.params()["crate_id"];
+    let conn = req.db_read_only()?;
+    let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
+    let policy = crate_policies::table
+        .inner_join(versions::table)
+        .filter(crate_policies::crate_id.eq(krate.id))
+        .select((crate_policies::all_columns, versions::num))
+        .first::<(CratePolicy, String)>(&*conn)
+        .optional()?;
+
+    #[derive(Serialize)]
+    struct R {
+        policy: Option<EncodableCratePolicy>,
+    }
+    Ok(req.json(&R {
+        policy: policy.map(|(policy, num)| policy.encodable(num)),
+    }))
+}
+
+/// Handles the `GET /crates/:crate_id/funding` route.
+///
+/// The links are taken from the `[package.metadata.funding]` table of the
+/// manifest and the `.github/FUNDING.yml` file of the most recently
+/// published version.
+pub fn funding(req: &mut dyn Request) -> AppResult<Response> {
+    let crate_name = &req.params()["crate_id"];
+    let conn = req.db_read_only()?;
+    let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
+    let links = FundingLink::by_crate(&conn, krate.id)?
+        .into_iter()
+        .map(FundingLink::encodable)
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        funding: Vec<EncodableFundingLink>,
+    }
+    Ok(req.json(&R { funding: links }))
+}
+
+/// Handles the `GET /crates/:crate_id/release_stats` route.
+///
+/// The statistics are recomputed nightly, so they are `null` for crates
+/// published since the last run.
+pub fn release_stats(req: &mut dyn Request) -> AppResult<Response> {
+    let crate_name = &req.params()["crate_id"];
+    let conn = req.db_read_only()?;
+    let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
+    let release_stats = crate_release_stats::table
+        .find(krate.id)
+        .first::<ReleaseStats>(&*conn)
+        .optional()?
+        .map(ReleaseStats::encodable);
+
+    #[derive(Serialize)]
+    struct R {
+        release_stats: Option<EncodableReleaseStats>,
+    }
+    Ok(req.json(&R { release_stats }))
+}
+
+/// Handles the `GET /index-checksums?crates=a,b,c` route.
+///
+/// Returns the SHA-256 checksum of the index file of each requested crate as
+/// of its last push, or `null` if the crate has never been pushed. Mirrors
+/// and caching proxies can compare these to their copies instead of checking
+/// every index file.
+pub fn index_checksums(req: &mut dyn Request) -> AppResult<Response> {
+    let names = req
+        .query()
+        .get("crates")
+        .map(|names| {
+            names
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(String::from)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    if names.len() > MAX_INDEX_CHECKSUMS {
+        return Err(bad_request(&format_args!(
+            "at most {} crates can be requested at once",
+            MAX_INDEX_CHECKSUMS
+        )));
+    }
+
+    let conn = req.db_read_only()?;
+    let known = crates::table
+        .inner_join(index_checksums::table)
+        .filter(crates::name.eq_any(&names))
+        .select((crates::name, index_checksums::checksum))
+        .load::<(String, String)>(&*conn)?
+        .into_iter()
+        .collect::<BTreeMap<_, _>>();
+    let checksums = names
+        .into_iter()
+        .map(|name| {
+            let checksum = known.get(&name).cloned();
+            (name, checksum)
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    #[derive(Serialize)]
+    struct R {
+        checksums: BTreeMap<String, Option<String>>,
+    }
+    Ok(req.json(&R { checksums }))
+}
+
 /// Handles the `GET /crates/:crate_id/versions` route.
 // FIXME: Not sure why this is necessary since /crates/:crate_id returns
 // this information already, but ember is definitely requesting it
 pub fn versions(req: &mut dyn Request) -> AppResult<Response> {
     let crate_name = &req.params()["crate_id"];
     let conn = req.db_read_only()?;
-    let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
+    let (crate_name, cache_version) = crate_cache_version(&*conn, crate_name)?;
+
+    let key = crate_key(&crate_name, "versions");
+    let body =
+        req.app()
+            .response_cache
+            .fetch(req.app(), &*conn, key, cache_version, move |conn| {
+                render_versions(conn, &crate_name)
+            })?;
+    Ok(raw_json_response(body.to_string()))
+}
+
+fn render_versions(conn: &PgConnection, crate_name: &str) -> AppResult<String> {
+    let krate = Crate::by_name(crate_name).first::<Crate>(conn)?;
     let mut versions_and_publishers: Vec<(Version, Option<User>)> = krate
         .all_versions()
         .left_outer_join(users::table)
         .select((versions::all_columns, users::all_columns.nullable()))
-        .load(&*conn)?;
+        .load(conn)?;
     versions_and_publishers.sort_by(|a, b| b.0.num.cmp(&a.0.num));
     let versions = versions_and_publishers
         .iter()
@@ -214,15 +485,15 @@ pub fn versions(req: &mut dyn Request) -> AppResult<Response> {
         .collect::<Vec<_>>();
     let versions = versions_and_publishers
         .into_iter()
-        .zip(VersionOwnerAction::for_versions(&conn, &versions)?.into_iter())
-        .map(|((v, pb), aas)| v.encodable(crate_name, pb, aas))
+        .zip(VersionOwnerAction::for_versions(conn, &versions)?.into_iter())
+        .map(|((v, pb), aas)| v.encodable(&krate.name, pb, aas))
         .collect();
 
     #[derive(Serialize)]
     struct R {
         versions: Vec<EncodableVersion>,
     }
-    Ok(req.json(&R { versions }))
+    Ok(serde_json::to_string(&R { versions })?)
 }
 
 /// Handles the `GET /crates/:crate_id/reverse_dependencies` route.
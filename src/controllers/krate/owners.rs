@@ -1,19 +1,22 @@
 //! All routes related to managing owners of a crate
 
+use crate::background_jobs::EnqueueVersioned;
 use crate::controllers::prelude::*;
-use crate::models::{Crate, Owner, Rights, Team, User};
+use crate::models::{Crate, OrgDomain, Owner, Rights, Team, User};
 use crate::views::EncodableOwner;
+use crate::{crate_page, og_image};
 
 /// Handles the `GET /crates/:crate_id/owners` route.
 pub fn owners(req: &mut dyn Request) -> AppResult<Response> {
     let crate_name = &req.params()["crate_id"];
     let conn = req.db_conn()?;
     let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
-    let owners = krate
+    let mut owners = krate
         .owners(&conn)?
         .into_iter()
         .map(Owner::encodable)
-        .collect();
+        .collect::<Vec<_>>();
+    OrgDomain::add_to_owners(&conn, &mut owners)?;
 
     #[derive(Serialize)]
     struct R {
@@ -27,10 +30,11 @@ pub fn owner_team(req: &mut dyn Request) -> AppResult<Response> {
     let crate_name = &req.params()["crate_id"];
     let conn = req.db_conn()?;
     let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
-    let owners = Team::owning(&krate, &conn)?
+    let mut owners = Team::owning(&krate, &conn)?
         .into_iter()
         .map(Owner::encodable)
-        .collect();
+        .collect::<Vec<_>>();
+    OrgDomain::add_to_owners(&conn, &mut owners)?;
 
     #[derive(Serialize)]
     struct R {
@@ -138,6 +142,12 @@ fn modify_owners(req: &mut dyn Request, add: bool) -> AppResult<Response> {
             "owners successfully removed".to_owned()
         };
 
+        crate_page::render_crate_page(krate.name.clone())
+            .enqueue_versioned(&conn)
+            .map_err(|e| AppError::from_std_error(e))?;
+        og_image::enqueue_render(&conn, &req.app().config, &krate.name)
+            .map_err(|e| AppError::from_std_error(e))?;
+
         #[derive(Serialize)]
         struct R {
             ok: bool,
@@ -1,30 +1,55 @@
 //! Functionality related to publishing a new crate or version of a crate.
 
 use hex::ToHex;
+use std::io::Read;
 use std::sync::Arc;
-use swirl::Job;
 
+use crate::background_jobs::EnqueueVersioned;
 use crate::controllers::cargo_prelude::*;
+use crate::crate_page;
 use crate::git;
-use crate::models::dependency;
+use crate::manifest_lints;
+use crate::models::default_versions;
+use crate::models::dependency::{self, DependencyCheck};
+use crate::models::security_policy::locate_security_policy;
 use crate::models::{
-    insert_version_owner_action, Badge, Category, Keyword, NewCrate, NewVersion, Rights,
-    VersionAction,
+    insert_version_owner_action, Badge, Category, CompressionFormat, ContentMatch, Crate,
+    CrateMetadata, CrateMetadataChange, CratePolicy, CrateQuality, CrateSecurityPolicy,
+    CrateUploadLimit, FundingLink, IndexSync, Keyword, NewCrate, NewVersion, PublishIdempotencyKey,
+    PublishJob, Rights, ScanHold, TransparencyLogEntry, Version, VersionAction, VersionAnalysis,
+    VersionFeatureDoc, VersionFile, VersionFingerprint, VersionScanResult,
 };
 
+use crate::og_image;
 use crate::render;
-use crate::util::{read_fill, read_le_u32, Maximums};
-use crate::views::{EncodableCrateUpload, GoodCrate, PublishWarnings};
+use crate::sbom;
+use crate::tasks;
+use crate::uploaders;
+use crate::util::errors::{internal, NotFound};
+use crate::util::{read_fill, read_le_u32, LimitErrorReader, Maximums};
+use crate::views::{EncodableCrateUpload, EncodablePublishStatus, GoodCrate, PublishWarnings};
 
 /// Handles the `PUT /crates/new` route.
 /// Used by `cargo publish` to publish a new crate or to publish a new version of an
 /// existing crate.
 ///
-/// Currently blocks the HTTP thread, perhaps some function calls can spawn new
-/// threads and return completion or error through other methods  a `cargo publish
-/// --status` command, via crates.io's front end, or email.
+/// Currently blocks the HTTP thread until the crate file is uploaded. With
+/// `?queued=true` the crate file is instead staged in the storage, and both
+/// uploading it and updating the index are left to a background job, whose
+/// progress is reported by `GET /crates/:crate_id/:version/publish-status`.
+/// This keeps uploads of large crates from timing out behind proxies.
+///
+/// Publishes with an `Idempotency-Key` header can be retried safely: for a
+/// day, a retry with the same key and crate file gets the response to the
+/// original publish instead of an error about the version already existing.
+///
+/// With `?dry_run=true` the publish is checked like a real one, including the
+/// crate file, the name and the rights of the user, and answered with the
+/// same warnings or errors, but nothing is stored or uploaded.
 pub fn publish(req: &mut dyn Request) -> AppResult<Response> {
     let app = Arc::clone(req.app());
+    let queued = req.query().get("queued").map(String::as_str) == Some("true");
+    let dry_run = req.query().get("dry_run").map(String::as_str) == Some("true");
 
     // The format of the req.body() of a publish request is as follows:
     //
@@ -36,18 +61,27 @@ pub fn publish(req: &mut dyn Request) -> AppResult<Response> {
     // - The metadata is read and interpreted in the parse_new_headers function.
     // - The .crate tarball length is read in this function in order to save the size of the file
     //   in the version record in the database.
-    // - Then the .crate tarball length is passed to the upload_crate function where the actual
-    //   file is read and uploaded.
+    // - Then the upload_crate function reads the .crate tarball as a stream, verifying and
+    //   checksumming it while it's spooled to a temporary file, and uploads it. The tarball is
+    //   never held in memory as a whole. Queued publishes upload it to a staging path instead,
+    //   which the background job copies it from.
 
     let new_crate = parse_new_headers(req)?;
 
     req.log_metadata("crate_name", new_crate.name.to_string());
     req.log_metadata("crate_version", new_crate.vers.to_string());
+    let metadata_lints = manifest_lints::check_metadata(&new_crate);
 
     let conn = app.primary_database.get()?;
     let ids = req.authenticate(&conn)?;
     let user = ids.find_user(&conn)?;
 
+    let idempotency_key = req
+        .headers()
+        .find("Idempotency-Key")
+        .and_then(|values| values.first().map(|key| key.to_string()))
+        .filter(|_| !dry_run);
+
     let verified_email_address = user.verified_email(&conn)?;
     let verified_email_address = verified_email_address.ok_or_else(|| {
         cargo_err(
@@ -57,11 +91,31 @@ pub fn publish(req: &mut dyn Request) -> AppResult<Response> {
     })?;
 
     // Create a transaction on the database, if there are no errors,
-    // commit the transactions to record a new or updated crate.
-    conn.transaction(|| {
+    // commit the transactions to record a new or updated crate. Dry runs
+    // always roll it back, keeping their response in `dry_run_response`.
+    let mut dry_run_response = None;
+    let result = conn.transaction(|| {
+        // Claiming the key first makes a concurrent retry wait for this
+        // publish, and then get its response
+        if let Some(key) = &idempotency_key {
+            let claimed = PublishIdempotencyKey::claim(
+                &conn,
+                user.id,
+                key,
+                &new_crate.name,
+                &new_crate.vers.to_string(),
+            )?;
+            if !claimed {
+                let previous = PublishIdempotencyKey::find(&conn, user.id, key)?
+                    .ok_or_else(|| internal("the `Idempotency-Key` expired during a retry"))?;
+                return replay_publish(req, &new_crate, &previous);
+            }
+        }
+
         let name = new_crate.name;
         let vers = &*new_crate.vers;
         let links = new_crate.links;
+        let rust_version = new_crate.rust_version.map(|v| v.0);
         let repo = new_crate.repository;
         let features = new_crate
             .features
@@ -90,9 +144,16 @@ pub fn publish(req: &mut dyn Request) -> AppResult<Response> {
             max_upload_size: None,
         };
 
+        // The metadata before this publish, to record what it changes. New
+        // crates have no history yet.
+        let previous_metadata = match Crate::by_name(&name).first::<Crate>(&*conn).optional()? {
+            Some(krate) => Some(CrateMetadata::load(&conn, krate.id)?),
+            None => None,
+        };
+
         let license_file = new_crate.license_file.as_deref();
-        let krate =
-            persist.create_or_update(&conn, user.id, Some(&app.config.publish_rate_limit))?;
+        let rate_limiter = Some(&*app.publish_rate_limiter).filter(|_| !dry_run);
+        let krate = persist.create_or_update(&conn, user.id, rate_limiter)?;
 
         let owners = krate.owners(&conn)?;
         if user.rights(req.app(), &owners)? < Rights::Publish {
@@ -121,11 +182,19 @@ pub fn publish(req: &mut dyn Request) -> AppResult<Response> {
             .content_length()
             .chain_error(|| cargo_err("missing header: Content-Length"))?;
 
+        // Limits set through the admin API take precedence until they expire
+        let max_upload_size =
+            CrateUploadLimit::active_for(&conn, krate.id)?.or(krate.max_upload_size);
         let maximums = Maximums::new(
-            krate.max_upload_size,
+            max_upload_size,
             app.config.max_upload_size,
             app.config.max_unpack_size,
-        );
+        )
+        .with_compression_ratios(
+            app.config.max_compression_ratio,
+            app.config.max_file_compression_ratio,
+        )
+        .with_entry_limits(app.config.max_file_count, app.config.max_path_length);
 
         if content_length > maximums.max_upload_size {
             return Err(cargo_err(&format_args!(
@@ -151,14 +220,30 @@ pub fn publish(req: &mut dyn Request) -> AppResult<Response> {
         )?
         .save(&conn, &new_crate.authors, &verified_email_address)?;
 
+        default_versions::update_default_version(krate.id, &conn)?;
+
         insert_version_owner_action(
             &conn,
             version.id,
             user.id,
             ids.api_token_id(),
             VersionAction::Publish,
+            None,
         )?;
 
+        let unmatched_dependencies = match app.config.dependency_check {
+            DependencyCheck::Off => vec![],
+            _ => dependency::unmatched_dependencies(&conn, &new_crate.deps)?,
+        };
+        if app.config.dependency_check == DependencyCheck::Deny
+            && !unmatched_dependencies.is_empty()
+        {
+            return Err(cargo_err(&format_args!(
+                "no version which isn't yanked matches the dependencies: {}",
+                unmatched_dependencies.join(", ")
+            )));
+        }
+
         // Link this new version to all dependencies
         let git_deps = dependency::add_dependencies(&conn, &new_crate.deps, version.id)?;
 
@@ -174,6 +259,39 @@ pub fn publish(req: &mut dyn Request) -> AppResult<Response> {
         let ignored_invalid_badges = Badge::update_crate(&conn, &krate, new_crate.badges.as_ref())?;
         let top_versions = krate.top_versions(&conn)?;
 
+        crate_page::render_crate_page(krate.name.clone())
+            .enqueue_versioned(&conn)
+            .map_err(|e| AppError::from_std_error(e))?;
+
+        tasks::detect_dependency_cycles(version.id)
+            .enqueue_versioned(&conn)
+            .map_err(|e| AppError::from_std_error(e))?;
+
+        og_image::enqueue_render(&conn, &app.config, &krate.name)
+            .map_err(|e| AppError::from_std_error(e))?;
+
+        let mut staged_path = None;
+        let mut uploaded = if queued || dry_run {
+            let (uploaded, file, content_length) =
+                uploaders::spool_crate(req, &krate, maximums, vers)?;
+            if !dry_run {
+                staged_path = Some(app.config.uploader.stage_crate_file(
+                    app.http_client(),
+                    &krate.name,
+                    &vers.to_string(),
+                    file,
+                    content_length,
+                )?);
+            }
+            uploaded
+        } else {
+            app.config
+                .uploader
+                .upload_crate(req, &krate, maximums, vers)?
+        };
+
+        // Relative links in the README point to the files of the commit
+        // the crate was packaged from
         if let Some(readme) = new_crate.readme {
             render::render_and_upload_readme(
                 version.id,
@@ -181,47 +299,208 @@ pub fn publish(req: &mut dyn Request) -> AppResult<Response> {
                 new_crate
                     .readme_file
                     .unwrap_or_else(|| String::from("README.md")),
-                repo,
+                repo.clone(),
+                uploaded.vcs_commit.clone(),
             )
-            .enqueue(&conn)
+            .enqueue_versioned(&conn)
             .map_err(|e| AppError::from_std_error(e))?;
         }
 
-        let cksum = app
-            .config
-            .uploader
-            .upload_crate(req, &krate, maximums, vers)?;
+        let mut lints = metadata_lints;
+        if let Some(manifest) = &uploaded.manifest {
+            lints.extend(manifest_lints::check_manifest(manifest));
+        }
+        if let (Some(declared), Some(report)) =
+            (&version.license_ids, uploaded.analyses.get_mut("license"))
+        {
+            lints.extend(manifest_lints::check_license_files(declared, report));
+        }
+        if let Some(lockfile) = &uploaded.lockfile {
+            let locked = manifest_lints::locked_packages(lockfile);
+            let published = dependency::locked_versions(&conn, &locked)?;
+            lints.extend(manifest_lints::check_lockfile(&locked, &published));
+        }
+
+        CratePolicy::update_crate(&conn, krate.id, version.id, uploaded.policy_file)?;
+        CrateQuality::record_tests(&conn, krate.id, uploaded.has_tests)?;
+        FundingLink::update_crate(&conn, krate.id, &uploaded.funding_links)?;
+        let security_policy = locate_security_policy(
+            &uploaded.files,
+            repo.as_deref(),
+            uploaded.vcs_commit.as_deref(),
+            uploaded.vcs_path.as_deref(),
+        );
+        CrateSecurityPolicy::update_crate(&conn, krate.id, version.id, security_policy)?;
+        VersionFeatureDoc::save(&conn, version.id, &features, uploaded.feature_docs)?;
+        let mut findings = VersionScanResult::save(&conn, version.id, &uploaded.analyses)?;
+        let hashes = VersionFingerprint::save(&conn, krate.id, version.id, &uploaded.analyses)?;
+        // Typosquats are new crates, so updates of existing ones aren't compared
+        if let (None, Some(hashes)) = (&previous_metadata, hashes) {
+            if ContentMatch::detect(&conn, krate.id, version.id, &hashes)?.is_some() {
+                findings += 1;
+            }
+        }
+        VersionAnalysis::save(&conn, version.id, uploaded.analyses)?;
+        VersionFile::save(&conn, version.id, &uploaded.files)?;
+        if let Some(previous) = &previous_metadata {
+            CrateMetadataChange::record(&conn, krate.id, user.id, previous)?;
+        }
 
-        let hex_cksum = cksum.encode_hex::<String>();
+        let hex_cksum = uploaded.checksum.encode_hex::<String>();
+        Version::record_checksum(version.id, &hex_cksum, &conn)?;
+        Version::record_compression(version.id, uploaded.compression, &conn)?;
+        Version::record_links(version.id, links.as_deref(), &conn)?;
+        Version::record_rust_version(version.id, rust_version.as_deref(), &conn)?;
+        Version::record_targets(version.id, &uploaded.targets, &conn)?;
+        TransparencyLogEntry::append(&conn, &krate.name, &version.num, &hex_cksum)?;
+        sbom::generate_sbom(version.id)
+            .enqueue_versioned(&conn)
+            .map_err(|e| AppError::from_std_error(e))?;
 
         // Register this crate in our local git repo.
         let git_crate = git::Crate {
             name: name.0,
             vers: vers.to_string(),
-            cksum: hex_cksum,
+            cksum: hex_cksum.clone(),
             features,
             deps: git_deps,
             yanked: Some(false),
             links,
+            compression: Some(uploaded.compression).filter(|&c| c != CompressionFormat::Gzip),
+            rust_version,
+            yanked_reason: None,
         };
-        git::add_crate(git_crate)
-            .enqueue(&conn)
+        let publish_job_id = match staged_path {
+            Some(staged_path) => Some(PublishJob::create(
+                &conn,
+                version.id,
+                user.id,
+                &staged_path,
+            )?),
+            None => None,
+        };
+        // Flagged versions are only added to the index once an admin released
+        // them, with `crates-io-admin scan-review`
+        let held = app.config.hold_flagged_versions && findings > 0;
+        if held {
+            let index_entry = serde_json::to_value(&git_crate)?;
+            ScanHold::create(&conn, version.id, index_entry, publish_job_id)?;
+        } else {
+            match publish_job_id {
+                Some(publish_job_id) => {
+                    git::add_queued_crate(publish_job_id, git_crate).enqueue_versioned(&conn)
+                }
+                None => git::add_crate(git_crate).enqueue_versioned(&conn),
+            }
             .map_err(|e| AppError::from_std_error(e))?;
+            IndexSync::queued(&conn, version.id)?;
+        }
+        if !dry_run {
+            app.response_cache.invalidate_crate(&krate.name);
+            app.download_cache.invalidate_crate(&krate.name);
+        }
 
-        // The `other` field on `PublishWarnings` was introduced to handle a temporary warning
-        // that is no longer needed. As such, crates.io currently does not return any `other`
-        // warnings at this time, but if we need to, the field is available.
+        // Cargo only prints the known fields of `PublishWarnings`, so unmatched dependencies
+        // are repeated in `other`.
+        let mut other = unmatched_dependencies
+            .iter()
+            .map(|dep| {
+                format!(
+                    "no version which isn't yanked matches the dependency `{}`",
+                    dep
+                )
+            })
+            .collect::<Vec<_>>();
+        if held {
+            other.push(
+                "the crate file contains executables, precompiled libraries or high-entropy \
+                 blobs, so the version is only added to the index once it has been reviewed"
+                    .into(),
+            );
+        }
         let warnings = PublishWarnings {
             invalid_categories: ignored_invalid_categories,
             invalid_badges: ignored_invalid_badges,
-            other: vec![],
+            unmatched_dependencies,
+            lints,
+            other,
         };
 
-        Ok(req.json(&GoodCrate {
+        let good_crate = GoodCrate {
             krate: krate.minimal_encodable(&top_versions, None, false, None),
             warnings,
-        }))
-    })
+        };
+        if dry_run {
+            dry_run_response = Some(req.json(&good_crate));
+            return Err(cargo_err("rolling back the dry run"));
+        }
+        if let Some(key) = &idempotency_key {
+            PublishIdempotencyKey::record(
+                &conn,
+                user.id,
+                key,
+                &hex_cksum,
+                &serde_json::to_value(&good_crate)?,
+            )?;
+        }
+
+        Ok(req.json(&good_crate))
+    });
+
+    match dry_run_response {
+        Some(response) => Ok(response),
+        None => result,
+    }
+}
+
+/// Answers a retry of a publish with the response to the original one, as
+/// long as the same crate file is published again.
+fn replay_publish(
+    req: &mut dyn Request,
+    new_crate: &EncodableCrateUpload,
+    previous: &PublishIdempotencyKey,
+) -> AppResult<Response> {
+    read_le_u32(req.body())?;
+    let max_upload_size = req.app().config.max_upload_size;
+    let mut tarball = Vec::new();
+    LimitErrorReader::new(req.body(), max_upload_size).read_to_end(&mut tarball)?;
+    let checksum = openssl::sha::sha256(&tarball).encode_hex::<String>();
+
+    if *new_crate.name != previous.crate_name
+        || new_crate.vers.to_string() != previous.version
+        || previous.checksum.as_ref() != Some(&checksum)
+    {
+        return Err(cargo_err(
+            "the `Idempotency-Key` was already used to publish a different crate file",
+        ));
+    }
+    Ok(req.json(&previous.response))
+}
+
+/// Handles the `GET /crates/:crate_id/:version/publish-status` route.
+///
+/// Reports whether the crate file of a publish with `?queued=true` was
+/// uploaded and added to the index yet. Only the user who published the
+/// version can see it.
+pub fn publish_status(req: &mut dyn Request) -> AppResult<Response> {
+    let conn = req.db_conn()?;
+    let user = req.authenticate(&conn)?.find_user(&conn)?;
+    let crate_name = &req.params()["crate_id"];
+    let semver = &req.params()["version"];
+    let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
+    let version = krate.find_version(&conn, semver)?;
+    let publish_job = match PublishJob::by_version(&conn, version.id)? {
+        Some(publish_job) if publish_job.user_id == user.id => publish_job,
+        _ => return Err(Box::new(NotFound)),
+    };
+
+    #[derive(Serialize)]
+    struct R {
+        publish_status: EncodablePublishStatus,
+    }
+    Ok(req.json(&R {
+        publish_status: publish_job.encodable(),
+    }))
 }
 
 /// Used by the `krate::new` function.
@@ -1,16 +1,39 @@
 //! Endpoint for searching and discovery functionality
 
+use chrono::{Duration, Utc};
 use diesel::dsl::*;
+use diesel::pg::Pg;
+use diesel::sql_types::{Array, BigInt, Integer, Text};
 use diesel_full_text_search::*;
+use indexmap::IndexMap;
 
 use crate::controllers::cargo_prelude::*;
 use crate::controllers::helpers::Paginate;
 use crate::models::{Crate, CrateBadge, CrateOwner, CrateVersions, OwnerKind, Version};
 use crate::schema::*;
+use crate::search_backend::TextSearch;
 use crate::util::errors::{bad_request, ChainError};
-use crate::views::EncodableCrate;
+use crate::views::{EncodableCrate, EncodableFacetCount, EncodableSearchFacets};
 
 use crate::models::krate::{canon_crate_name, ALL_COLUMNS};
+use crate::models::targets::is_known_target;
+
+/// How much the rank of a crate's README counts compared to the rank of its
+/// name, keywords and description when searching with `search_in=readme`.
+const README_RANK_WEIGHT: f32 = 0.2;
+
+/// The tsquery of a Postgres search is built from all of its variants, as
+/// returned by `SearchSynonym::expand_query`, and matches if any of them
+/// matches. The
+/// variants are bound as a text array between these two fragments.
+const TS_QUERY_START: &str = "(SELECT tsquery_or_agg(plainto_tsquery('english', v)) FROM unnest(";
+const TS_QUERY_END: &str = ") AS v)";
+
+/// The facets that can be requested via the `facets` query parameter.
+const FACETS: &[&str] = &["categories", "keywords", "licenses"];
+
+/// The maximum number of values returned for each facet.
+const MAX_FACET_VALUES: i64 = 20;
 
 /// Handles the `GET /crates` route.
 /// Returns a list of crates. Called in a variety of scenarios in the
@@ -19,6 +42,30 @@ use crate::models::krate::{canon_crate_name, ALL_COLUMNS};
 /// - List of crates under a specific owner
 /// - Listing a user's followed crates
 ///
+/// Passing `search_in=readme` along with a `q` parameter additionally
+/// searches the rendered READMEs of the crates' default versions.
+///
+/// The `q` parameter is resolved by the configured `SearchBackend`. The
+/// default Postgres backend expands searches with the curated synonyms from
+/// `src/boot/synonyms.toml`, e.g. a search for `http client` also finds
+/// crates matching `reqwest`.
+///
+/// Passing `facets=categories,keywords,licenses` (or a subset of them)
+/// additionally returns the most common values of those facets among all
+/// matching crates in `meta.facets`.
+///
+/// Passing `active_within=1y` (or a number of `d`ays, `w`eeks, `m`onths or
+/// `y`ears) only lists crates whose repository received a commit within that
+/// time, and `sort=recent-activity` lists the most recently active crates
+/// first. Repository activity is only known for GitHub and GitLab.
+///
+/// Passing `target=wasm32-unknown-unknown` only lists crates whose default
+/// version declares to support that target, see the `targets` module.
+///
+/// `sort=dependency-freshness` lists the crates whose dependencies are the
+/// most up to date first, as computed weekly by the
+/// `compute_dependency_freshness` job.
+///
 /// Notes:
 /// The different use cases this function covers is handled through passing
 /// in parameters in the GET request.
@@ -34,39 +81,42 @@ use crate::models::krate::{canon_crate_name, ALL_COLUMNS};
 /// function out to cover the different use cases, and create unit tests
 /// for them.
 pub fn search(req: &mut dyn Request) -> AppResult<Response> {
-    use diesel::sql_types::{Bool, Text};
+    use diesel::sql_types::{Bool, Float, Nullable};
 
     let conn = req.db_read_only()?;
     let params = req.query();
     let sort = params.get("sort").map(|s| &**s);
-    let include_yanked = params
-        .get("include_yanked")
-        .map(|s| s == "yes")
-        .unwrap_or(true);
+
+    let search_in_readme = match params.get("search_in").map(|s| &**s) {
+        None | Some("") => false,
+        Some("readme") => true,
+        Some(other) => {
+            return Err(bad_request(&format_args!(
+                "invalid `search_in` value: `{}`",
+                other
+            )))
+        }
+    };
+    let requested_facets = requested_facets(&params)?;
+    let text_search = match params.get("q") {
+        Some(q_string) if !q_string.is_empty() => {
+            Some(req.app().search_backend.text_search(&conn, q_string)?)
+        }
+        _ => None,
+    };
 
     let selection = (
         ALL_COLUMNS,
         false.into_sql::<Bool>(),
         recent_crate_downloads::downloads.nullable(),
     );
-    let mut query = crates::table
-        .left_join(recent_crate_downloads::table)
-        .select(selection)
-        .into_boxed();
+    let mut query = filter_crates(req, &conn, &params, text_search.as_ref(), search_in_readme)?
+        .select(selection);
 
     if let Some(q_string) = params.get("q") {
         if !q_string.is_empty() {
             let sort = params.get("sort").map(|s| &**s).unwrap_or("relevance");
 
-            let q = sql::<TsQuery>("plainto_tsquery('english', ")
-                .bind::<Text, _>(q_string)
-                .sql(")");
-            query = query.filter(
-                q.clone()
-                    .matches(crates::textsearchable_index_col)
-                    .or(Crate::loosly_matches_name(&q_string)),
-            );
-
             query = query.select((
                 ALL_COLUMNS,
                 Crate::with_name(q_string),
@@ -74,11 +124,239 @@ pub fn search(req: &mut dyn Request) -> AppResult<Response> {
             ));
             query = query.order(Crate::with_name(q_string).desc());
 
-            if sort == "relevance" {
-                let rank = ts_rank_cd(crates::textsearchable_index_col, q);
-                query = query.then_order_by(rank.desc())
+            match (sort, &text_search) {
+                ("relevance", Some(TextSearch::Postgres { variants })) if search_in_readme => {
+                    // The rank of the default version's README is added to the
+                    // regular rank with a lower weight, so that matches in the
+                    // name, keywords or description still count the most.
+                    let rank = sql::<Float>("ts_rank_cd(crates.textsearchable_index_col, ")
+                        .sql(TS_QUERY_START)
+                        .bind::<Array<Text>, _>(variants.clone())
+                        .sql(TS_QUERY_END)
+                        .sql(") + ")
+                        .bind::<Float, _>(README_RANK_WEIGHT)
+                        .sql(" * coalesce((SELECT ts_rank_cd(")
+                        .sql("readme_renderings.textsearchable_index_col, ")
+                        .sql(TS_QUERY_START)
+                        .bind::<Array<Text>, _>(variants.clone())
+                        .sql(TS_QUERY_END)
+                        .sql(") FROM default_versions INNER JOIN readme_renderings ")
+                        .sql("ON readme_renderings.version_id = default_versions.version_id ")
+                        .sql("WHERE default_versions.crate_id = crates.id), 0)");
+                    query = query.then_order_by(rank.desc())
+                }
+                ("relevance", Some(TextSearch::Postgres { variants })) => {
+                    let q = sql::<TsQuery>(TS_QUERY_START)
+                        .bind::<Array<Text>, _>(variants.clone())
+                        .sql(TS_QUERY_END);
+                    let rank = ts_rank_cd(crates::textsearchable_index_col, q);
+                    query = query.then_order_by(rank.desc())
+                }
+                ("relevance", Some(TextSearch::Ranked(crate_ids))) => {
+                    let position = sql::<Integer>("array_position(")
+                        .bind::<Array<Integer>, _>(crate_ids.clone())
+                        .sql(", crates.id)");
+                    query = query.then_order_by(position.asc())
+                }
+                _ => {}
+            }
+        }
+    }
+
+    if sort == Some("downloads") {
+        query = query.then_order_by(crates::downloads.desc())
+    } else if sort == Some("recent-downloads") {
+        query = query.then_order_by(recent_crate_downloads::downloads.desc().nulls_last())
+    } else if sort == Some("recent-updates") {
+        query = query.order(crates::updated_at.desc());
+    } else if sort == Some("new") {
+        query = query.order(crates::created_at.desc());
+    } else if sort == Some("quality") {
+        // Crates whose quality hasn't been computed yet are listed last
+        let score = sql::<Nullable<Float>>(
+            "(SELECT score FROM crate_quality WHERE crate_quality.crate_id = crates.id)",
+        );
+        query = query.then_order_by(score.desc().nulls_last())
+    } else if sort == Some("dependency-freshness") {
+        // Crates whose freshness hasn't been computed yet are listed last
+        let score = sql::<Nullable<Float>>(
+            "(SELECT score FROM crate_dependency_freshness \
+             WHERE crate_dependency_freshness.crate_id = crates.id)",
+        );
+        query = query.then_order_by(score.desc().nulls_last())
+    } else if sort == Some("recent-activity") {
+        query = query.then_order_by(crates::repository_last_commit_at.desc().nulls_last())
+    } else {
+        query = query.then_order_by(crates::name.asc())
+    }
+
+    let facets = if requested_facets.is_empty() {
+        None
+    } else {
+        Some(search_facets(
+            req,
+            &conn,
+            &params,
+            text_search.as_ref(),
+            search_in_readme,
+            &requested_facets,
+        )?)
+    };
+
+    let data = query
+        .paginate(&req.query())?
+        .load::<(Crate, bool, Option<i64>)>(&*conn)?;
+    let total = data.total();
+
+    let next_page = data.next_page_params().map(|p| req.query_with_params(p));
+    let prev_page = data.prev_page_params().map(|p| req.query_with_params(p));
+
+    let perfect_matches = data.iter().map(|&(_, b, _)| b).collect::<Vec<_>>();
+    let recent_downloads = data
+        .iter()
+        .map(|&(_, _, s)| s.unwrap_or(0))
+        .collect::<Vec<_>>();
+    let crates = data.into_iter().map(|(c, _, _)| c).collect::<Vec<_>>();
+
+    let versions = crates
+        .versions()
+        .load::<Version>(&*conn)?
+        .grouped_by(&crates)
+        .into_iter()
+        .map(|versions| Version::top(versions.into_iter().map(|v| (v.created_at, v.num))));
+
+    let badges = CrateBadge::belonging_to(&crates)
+        .select((badges::crate_id, badges::all_columns))
+        .load::<CrateBadge>(&*conn)?
+        .grouped_by(&crates)
+        .into_iter()
+        .map(|badges| badges.into_iter().map(|cb| cb.badge).collect());
+
+    let crates = versions
+        .zip(crates)
+        .zip(perfect_matches)
+        .zip(recent_downloads)
+        .zip(badges)
+        .map(
+            |((((max_version, krate), perfect_match), recent_downloads), badges)| {
+                krate.minimal_encodable(
+                    &max_version,
+                    Some(badges),
+                    perfect_match,
+                    Some(recent_downloads),
+                )
+            },
+        )
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        crates: Vec<EncodableCrate>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        total: Option<i64>,
+        next_page: Option<String>,
+        prev_page: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        facets: Option<EncodableSearchFacets>,
+    }
+
+    Ok(req.json(&R {
+        crates,
+        meta: Meta {
+            total,
+            next_page,
+            prev_page,
+            facets,
+        },
+    }))
+}
+
+type CratesQuery<'a> = IntoBoxed<'a, LeftJoin<crates::table, recent_crate_downloads::table>, Pg>;
+
+/// Applies the filters given in the query parameters to the list of all
+/// crates.
+///
+/// Both the search results and the facet counts are built on top of this, so
+/// that the counts always describe the same crates as the results.
+fn filter_crates<'a>(
+    req: &dyn Request,
+    conn: &PgConnection,
+    params: &'a IndexMap<String, String>,
+    text_search: Option<&TextSearch>,
+    search_in_readme: bool,
+) -> AppResult<CratesQuery<'a>> {
+    let include_yanked = params
+        .get("include_yanked")
+        .map(|s| s == "yes")
+        .unwrap_or(true);
+
+    let mut query = crates::table
+        .left_join(recent_crate_downloads::table)
+        .into_boxed();
+
+    let q_string = params.get("q").map(|s| &**s).unwrap_or_default();
+    match text_search {
+        Some(TextSearch::Postgres { variants }) => {
+            let q = sql::<TsQuery>(TS_QUERY_START)
+                .bind::<Array<Text>, _>(variants.clone())
+                .sql(TS_QUERY_END);
+            if search_in_readme {
+                let readme_matches = crates::id.eq_any(
+                    default_versions::table
+                        .inner_join(
+                            readme_renderings::table
+                                .on(readme_renderings::version_id.eq(default_versions::version_id)),
+                        )
+                        .filter(
+                            q.clone()
+                                .matches(readme_renderings::textsearchable_index_col),
+                        )
+                        .select(default_versions::crate_id),
+                );
+                query = query.filter(
+                    q.matches(crates::textsearchable_index_col)
+                        .or(Crate::loosly_matches_name(q_string))
+                        .or(readme_matches),
+                );
+            } else {
+                query = query.filter(
+                    q.matches(crates::textsearchable_index_col)
+                        .or(Crate::loosly_matches_name(q_string)),
+                );
             }
         }
+        // External engines only know the crates' names, descriptions and
+        // keywords, so `search_in=readme` makes no difference for them.
+        Some(TextSearch::Ranked(crate_ids)) => {
+            query = query.filter(
+                crates::id
+                    .eq_any(crate_ids.clone())
+                    .or(Crate::loosly_matches_name(q_string)),
+            );
+        }
+        None => {}
+    }
+
+    if let Some(active_within) = params.get("active_within") {
+        let since = Utc::now().naive_utc() - parse_active_within(active_within)?;
+        query = query.filter(crates::repository_last_commit_at.gt(since));
+    }
+
+    if let Some(target) = params.get("target") {
+        if !is_known_target(target) {
+            return Err(bad_request(&format_args!("unknown target `{}`", target)));
+        }
+        query = query.filter(
+            crates::id.eq_any(
+                default_versions::table
+                    .inner_join(versions::table)
+                    .filter(versions::targets.contains(vec![target.to_string()]))
+                    .select(default_versions::crate_id),
+            ),
+        );
     }
 
     if let Some(cat) = params.get("category") {
@@ -97,7 +375,6 @@ pub fn search(req: &mut dyn Request) -> AppResult<Response> {
     }
 
     if let Some(kws) = params.get("all_keywords") {
-        use diesel::sql_types::Array;
         sql_function!(#[aggregate] fn array_agg<T>(x: T) -> Array<T>);
 
         let names: Vec<_> = kws
@@ -154,7 +431,7 @@ pub fn search(req: &mut dyn Request) -> AppResult<Response> {
             ),
         );
     } else if params.get("following").is_some() {
-        let user_id = req.authenticate(&conn)?.user_id();
+        let user_id = req.authenticate(conn)?.user_id();
         query = query.filter(
             crates::id.eq_any(
                 follows::table
@@ -172,84 +449,147 @@ pub fn search(req: &mut dyn Request) -> AppResult<Response> {
         ));
     }
 
-    if sort == Some("downloads") {
-        query = query.then_order_by(crates::downloads.desc())
-    } else if sort == Some("recent-downloads") {
-        query = query.then_order_by(recent_crate_downloads::downloads.desc().nulls_last())
-    } else if sort == Some("recent-updates") {
-        query = query.order(crates::updated_at.desc());
-    } else if sort == Some("new") {
-        query = query.order(crates::created_at.desc());
-    } else {
-        query = query.then_order_by(crates::name.asc())
-    }
+    Ok(query)
+}
 
-    let data = query
-        .paginate(&req.query())?
-        .load::<(Crate, bool, Option<i64>)>(&*conn)?;
-    let total = data.total();
+/// Parses the comma separated list of the `facets` query parameter.
+/// Parses the `active_within` parameter, e.g. `30d`, `6m` or `1y`.
+fn parse_active_within(value: &str) -> AppResult<Duration> {
+    let invalid = || {
+        bad_request(&format_args!(
+            "invalid `active_within` value: `{}`, expected e.g. `30d`, `6m` or `1y`",
+            value
+        ))
+    };
 
-    let next_page = data.next_page_params().map(|p| req.query_with_params(p));
-    let prev_page = data.prev_page_params().map(|p| req.query_with_params(p));
+    if value.len() < 2 || !value.is_char_boundary(value.len() - 1) {
+        return Err(invalid());
+    }
+    let (amount, unit) = value.split_at(value.len() - 1);
+    let amount = amount.parse::<i64>().map_err(|_| invalid())?;
+    let days_per_unit = match unit {
+        "d" => 1,
+        "w" => 7,
+        "m" => 30,
+        "y" => 365,
+        _ => return Err(invalid()),
+    };
+    if amount <= 0 || amount > 100 {
+        return Err(invalid());
+    }
+    Ok(Duration::days(amount * days_per_unit))
+}
 
-    let perfect_matches = data.iter().map(|&(_, b, _)| b).collect::<Vec<_>>();
-    let recent_downloads = data
-        .iter()
-        .map(|&(_, _, s)| s.unwrap_or(0))
-        .collect::<Vec<_>>();
-    let crates = data.into_iter().map(|(c, _, _)| c).collect::<Vec<_>>();
+fn requested_facets(params: &IndexMap<String, String>) -> AppResult<Vec<&str>> {
+    let facets = match params.get("facets") {
+        Some(facets) => facets,
+        None => return Ok(Vec::new()),
+    };
 
-    let versions = crates
-        .versions()
-        .load::<Version>(&*conn)?
-        .grouped_by(&crates)
-        .into_iter()
-        .map(|versions| Version::top(versions.into_iter().map(|v| (v.created_at, v.num))));
-
-    let badges = CrateBadge::belonging_to(&crates)
-        .select((badges::crate_id, badges::all_columns))
-        .load::<CrateBadge>(&*conn)?
-        .grouped_by(&crates)
-        .into_iter()
-        .map(|badges| badges.into_iter().map(|cb| cb.badge).collect());
+    let mut requested = Vec::new();
+    for facet in facets.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+        if !FACETS.contains(&facet) {
+            return Err(bad_request(&format_args!(
+                "invalid `facets` value: `{}`",
+                facet
+            )));
+        }
+        if !requested.contains(&facet) {
+            requested.push(facet);
+        }
+    }
+    Ok(requested)
+}
 
-    let crates = versions
-        .zip(crates)
-        .zip(perfect_matches)
-        .zip(recent_downloads)
-        .zip(badges)
-        .map(
-            |((((max_version, krate), perfect_match), recent_downloads), badges)| {
-                krate.minimal_encodable(
-                    &max_version,
-                    Some(badges),
-                    perfect_match,
-                    Some(recent_downloads),
-                )
-            },
-        )
-        .collect();
+/// Counts the categories, keywords and licenses of all crates matching the
+/// search, not just the ones on the current page.
+///
+/// Every facet needs a grouped query over the whole result set, so the counts
+/// are cached for a short time. Searches for the crates a user follows are
+/// never cached, since their results depend on who is asking.
+fn search_facets(
+    req: &dyn Request,
+    conn: &PgConnection,
+    params: &IndexMap<String, String>,
+    text_search: Option<&TextSearch>,
+    search_in_readme: bool,
+    requested: &[&str],
+) -> AppResult<EncodableSearchFacets> {
+    let cache_key = if params.contains_key("following") {
+        None
+    } else {
+        let mut key = params
+            .iter()
+            .filter(|(name, _)| !["page", "per_page", "sort"].contains(&name.as_str()))
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>();
+        key.sort();
+        Some(key.join("&"))
+    };
 
-    #[derive(Serialize)]
-    struct R {
-        crates: Vec<EncodableCrate>,
-        meta: Meta,
+    let cache = &req.app().search_facets;
+    if let Some(facets) = cache_key.as_ref().and_then(|key| cache.get(key)) {
+        return Ok(facets);
     }
-    #[derive(Serialize)]
-    struct Meta {
-        total: Option<i64>,
-        next_page: Option<String>,
-        prev_page: Option<String>,
+
+    let crate_ids = || -> AppResult<_> {
+        Ok(filter_crates(req, conn, params, text_search, search_in_readme)?.select(crates::id))
+    };
+    let count = || sql::<BigInt>("COUNT(*)");
+    let to_counts = |rows: Vec<(String, i64)>| {
+        rows.into_iter()
+            .map(|(value, count)| EncodableFacetCount { value, count })
+            .collect()
+    };
+
+    let mut facets = EncodableSearchFacets::default();
+    for &facet in requested {
+        match facet {
+            "categories" => {
+                let rows = crates_categories::table
+                    .inner_join(categories::table)
+                    .filter(crates_categories::crate_id.eq_any(crate_ids()?))
+                    .group_by(categories::slug)
+                    .select((categories::slug, count()))
+                    .order((count().desc(), categories::slug.asc()))
+                    .limit(MAX_FACET_VALUES)
+                    .load(conn)?;
+                facets.categories = Some(to_counts(rows));
+            }
+            "keywords" => {
+                let rows = crates_keywords::table
+                    .inner_join(keywords::table)
+                    .filter(crates_keywords::crate_id.eq_any(crate_ids()?))
+                    .group_by(keywords::keyword)
+                    .select((keywords::keyword, count()))
+                    .order((count().desc(), keywords::keyword.asc()))
+                    .limit(MAX_FACET_VALUES)
+                    .load(conn)?;
+                facets.keywords = Some(to_counts(rows));
+            }
+            "licenses" => {
+                let rows = default_versions::table
+                    .inner_join(versions::table)
+                    .filter(default_versions::crate_id.eq_any(crate_ids()?))
+                    .filter(versions::license.is_not_null())
+                    .group_by(versions::license)
+                    .select((versions::license, count()))
+                    .order((count().desc(), versions::license.asc()))
+                    .limit(MAX_FACET_VALUES)
+                    .load::<(Option<String>, i64)>(conn)?
+                    .into_iter()
+                    .filter_map(|(license, count)| Some((license?, count)))
+                    .collect();
+                facets.licenses = Some(to_counts(rows));
+            }
+            _ => unreachable!("facets are validated by `requested_facets`"),
+        }
     }
 
-    Ok(req.json(&R {
-        crates,
-        meta: Meta {
-            total,
-            next_page,
-            prev_page,
-        },
-    }))
+    if let Some(key) = cache_key {
+        cache.insert(key, facets.clone());
+    }
+    Ok(facets)
 }
 
 diesel_infix_operator!(Contains, "@>");
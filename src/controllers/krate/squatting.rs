@@ -0,0 +1,37 @@
+//! Endpoint for reporting crate names which look squatted
+
+use chrono::Utc;
+
+use crate::controllers::frontend_prelude::*;
+
+use crate::models::{Crate, SquattingReport};
+
+#[derive(Deserialize)]
+struct NewReport {
+    reason: String,
+}
+
+/// Handles the `PUT /crates/:crate_id/squatting_report` route.
+///
+/// The report is checked by the `check_squatting_reports` job, which passes
+/// it on to the admins if the crate looks unused.
+pub fn report(req: &mut dyn Request) -> AppResult<Response> {
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let new_report: NewReport =
+        serde_json::from_str(&body).map_err(|e| bad_request(&format_args!("{}", e)))?;
+
+    let conn = req.db_conn()?;
+    let user = req.authenticate(&conn)?.find_user(&conn)?;
+    let crate_name = &req.params()["crate_id"];
+    let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
+    SquattingReport::create(
+        &conn,
+        &krate,
+        &user,
+        &new_report.reason,
+        Utc::now().naive_utc(),
+    )?;
+
+    ok_true()
+}
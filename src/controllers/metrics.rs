@@ -0,0 +1,89 @@
+//! Metrics for Prometheus, which scrapes them with the bearer token set in
+//! `METRICS_AUTHORIZATION_TOKEN`.
+
+use super::prelude::*;
+
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use crate::metrics::{Exposition, CONTENT_TYPE};
+use crate::models::index_sync::{IndexSync, SyncStage};
+use crate::util::errors::{NotFound, Unauthorized};
+
+/// Only the configured token is accepted. Without one, the endpoints don't
+/// exist.
+fn authenticate_scraper(req: &dyn Request) -> AppResult<()> {
+    let expected = match &req.app().config.metrics_authorization_token {
+        Some(token) => format!("Bearer {}", token),
+        None => return Err(Box::new(NotFound)),
+    };
+    let given = req
+        .headers()
+        .find("Authorization")
+        .and_then(|values| values.first().map(|value| value.to_string()))
+        .unwrap_or_default();
+    if given.len() != expected.len() || !openssl::memcmp::eq(given.as_bytes(), expected.as_bytes())
+    {
+        return Err(Box::new(Unauthorized));
+    }
+    Ok(())
+}
+
+/// Handles the `GET /api/private/metrics/index-sync` route.
+///
+/// Reports how long new versions take from their publish until their index
+/// entry was pushed, and from then until the CDN served it, how far behind
+/// the versions on their way currently are, and how often either failed.
+pub fn index_sync(req: &mut dyn Request) -> AppResult<Response> {
+    authenticate_scraper(req)?;
+    let conn = req.db_conn()?;
+    let indexing = IndexSync::durations(&conn, SyncStage::Index)?;
+    let visibility = IndexSync::durations(&conn, SyncStage::Visibility)?;
+    let lag = IndexSync::lag(&conn)?;
+
+    let mut exposition = Exposition::new();
+    exposition
+        .histogram(
+            "crates_io_index_sync_index_duration_seconds",
+            "Time from queueing the index update of a version until it was pushed.",
+            SyncStage::Index.buckets(),
+            &indexing.buckets,
+            indexing.count,
+            indexing.sum,
+        )
+        .histogram(
+            "crates_io_index_sync_visibility_duration_seconds",
+            "Time from pushing the index update of a version until the CDN served it.",
+            SyncStage::Visibility.buckets(),
+            &visibility.buckets,
+            visibility.count,
+            visibility.sum,
+        )
+        .gauge(
+            "crates_io_index_sync_index_lag_seconds",
+            "How long the oldest index update which wasn't pushed yet has been queued.",
+            lag.index_seconds,
+        )
+        .gauge(
+            "crates_io_index_sync_visibility_lag_seconds",
+            "How long ago the oldest version the CDN doesn't serve yet was pushed.",
+            lag.visibility_seconds,
+        )
+        .counter(
+            "crates_io_index_sync_failures_total",
+            "Failed attempts of pushing index updates and checks of the CDN.",
+            &[
+                ("stage=\"index\"", lag.index_failures),
+                ("stage=\"visibility\"", lag.visibility_failures),
+            ],
+        );
+
+    let mut headers = HashMap::new();
+    headers.insert("Content-Type".to_string(), vec![CONTENT_TYPE.to_string()]);
+    headers.insert("Cache-Control".to_string(), vec!["no-store".to_string()]);
+    Ok(Response {
+        status: (200, "OK"),
+        headers,
+        body: Box::new(Cursor::new(exposition.into_string().into_bytes())),
+    })
+}
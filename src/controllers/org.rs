@@ -0,0 +1,83 @@
+//! Endpoints for the domains GitHub organizations verify, see `OrgDomain`
+
+use crate::background_jobs::EnqueueVersioned;
+use crate::controllers::frontend_prelude::*;
+
+use crate::models::org_domain::{normalize_domain, org_teams_pattern};
+use crate::models::{is_org_admin, OrgDomain};
+use crate::schema::teams;
+use crate::tasks;
+use crate::views::EncodableOrgDomain;
+
+/// Handles the `GET /orgs/:org/domains` route.
+///
+/// Lists the domains the organization verified or asked to verify.
+pub fn domains(req: &mut dyn Request) -> AppResult<Response> {
+    let org = req.params()["org"].to_lowercase();
+    let conn = req.db_conn()?;
+    let domains = OrgDomain::by_org(&conn, &org)?
+        .into_iter()
+        .map(OrgDomain::encodable)
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        domains: Vec<EncodableOrgDomain>,
+    }
+    Ok(req.json(&R { domains }))
+}
+
+/// Handles the `PUT /orgs/:org/domains` route.
+///
+/// Asks to verify a domain for the organization, returning the TXT record
+/// which has to be added to the domain. The record is checked in the
+/// background, and again whenever the domain is requested again.
+///
+/// Only admins of the organization can verify its domains, once a team of it
+/// was added as an owner of a crate.
+pub fn request_domain(req: &mut dyn Request) -> AppResult<Response> {
+    #[derive(Deserialize)]
+    struct Body {
+        domain: String,
+    }
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let body: Body =
+        serde_json::from_str(&body).map_err(|e| bad_request(&format_args!("{}", e)))?;
+    let domain = normalize_domain(&body.domain)
+        .ok_or_else(|| bad_request(&format_args!("`{}` is not a valid domain", body.domain)))?;
+
+    let org = req.params()["org"].to_lowercase();
+    let conn = req.db_conn()?;
+    let user = req.authenticate(&conn)?.find_user(&conn)?;
+    let owns_crates = diesel::select(diesel::dsl::exists(
+        teams::table.filter(teams::login.like(org_teams_pattern(&org))),
+    ))
+    .get_result::<bool>(&*conn)?;
+    if !owns_crates {
+        return Err(cargo_err(&format_args!(
+            "no team of `{}` owns crates, so its domains can't be verified",
+            org
+        )));
+    }
+    if !is_org_admin(req.app(), &org, &user)? {
+        return Err(cargo_err(&format_args!(
+            "only admins of `{}` can verify its domains",
+            org
+        )));
+    }
+
+    let domain = OrgDomain::request(&conn, &org, &domain, user.id)?;
+    tasks::verify_org_domain(domain.id)
+        .enqueue_versioned(&conn)
+        .map_err(|e| AppError::from_std_error(e))?;
+
+    #[derive(Serialize)]
+    struct R {
+        domain: EncodableOrgDomain,
+    }
+    Ok(req.json(&R {
+        domain: domain.encodable(),
+    }))
+}
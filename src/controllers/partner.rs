@@ -0,0 +1,123 @@
+//! The API through which security partners submit verdicts about versions
+//!
+//! Partners are registered with the `security-partners` binary, which also
+//! works through the review queue the verdicts end up in.
+
+use chrono::{Duration, Utc};
+
+use super::frontend_prelude::*;
+
+use crate::models::security_partner::{self, VERDICTS};
+use crate::models::{Crate, SecurityPartner, Version, VersionVerdict};
+use crate::schema::{crates, versions};
+use crate::util::errors::{NotFound, TooManyRequests, Unauthorized};
+use crate::views::EncodableVerdict;
+
+#[derive(Deserialize)]
+struct NewVerdict {
+    #[serde(rename = "crate")]
+    krate: String,
+    version: String,
+    /// The hex encoded SHA-256 checksum of the scanned crate file.
+    checksum: String,
+    verdict: String,
+    details: Option<String>,
+}
+
+/// Handles the `POST /partners/verdicts` route.
+///
+/// Every request of an authenticated partner is recorded in its audit log,
+/// including the rejected ones.
+pub fn submit_verdict(req: &mut dyn Request) -> AppResult<Response> {
+    let api_key = req
+        .headers()
+        .find("X-Partner-Key")
+        .and_then(|values| values.first().map(|key| key.to_string()))
+        .ok_or_else(|| Box::new(Unauthorized) as Box<dyn AppError>)?;
+
+    let conn = req.db_conn()?;
+    let partner = SecurityPartner::find_by_api_key(&conn, &api_key)?
+        .ok_or_else(|| Box::new(Unauthorized) as Box<dyn AppError>)?;
+    req.log_metadata("security_partner", partner.name.clone());
+
+    let now = Utc::now().naive_utc();
+    let (submitted, oldest) = partner.verdicts_since(&conn, now - Duration::hours(24))?;
+    if submitted >= i64::from(partner.verdicts_per_day) {
+        partner.audit(&conn, "rate_limited", None, "daily verdict quota used up")?;
+        let retry_after = oldest.unwrap_or(now) + Duration::hours(24);
+        return Err(Box::new(TooManyRequests { retry_after }));
+    }
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let new_verdict = match serde_json::from_str::<NewVerdict>(&body) {
+        Ok(new_verdict) => new_verdict,
+        Err(e) => {
+            partner.audit(&conn, "invalid_request", None, &e.to_string())?;
+            return Err(bad_request(&format_args!("invalid verdict: {}", e)));
+        }
+    };
+    let subject = format!("{} {}", new_verdict.krate, new_verdict.version);
+
+    if !VERDICTS.contains(&new_verdict.verdict.as_str()) {
+        partner.audit(&conn, "invalid_request", None, &subject)?;
+        return Err(bad_request(&format_args!(
+            "unknown verdict `{}`, expected one of: {}",
+            new_verdict.verdict,
+            VERDICTS.join(", ")
+        )));
+    }
+
+    let version_id = versions::table
+        .inner_join(crates::table)
+        .filter(Crate::with_name(&new_verdict.krate))
+        .filter(versions::num.eq(&new_verdict.version))
+        .select(versions::id)
+        .first::<i32>(&*conn)
+        .optional()?;
+    let version_id = match version_id {
+        Some(version_id) => version_id,
+        None => {
+            partner.audit(&conn, "unknown_version", None, &subject)?;
+            return Err(Box::new(NotFound));
+        }
+    };
+
+    // Verdicts are pinned to the crate file the partner scanned. Versions
+    // published before checksums were recorded can't be checked.
+    let checksum = new_verdict.checksum.to_lowercase();
+    if let Some(expected) = Version::checksum(version_id, &conn)? {
+        if checksum != expected {
+            partner.audit(&conn, "checksum_mismatch", Some(version_id), &subject)?;
+            return Err(bad_request(&format_args!(
+                "the checksum doesn't match the crate file of {}",
+                subject
+            )));
+        }
+    }
+
+    let verdict = VersionVerdict::submit(
+        &conn,
+        &partner,
+        version_id,
+        &checksum,
+        &new_verdict.verdict,
+        new_verdict.details.as_deref(),
+    )?;
+    let details = format!("verdict {} on {}: {}", verdict.id, subject, verdict.verdict);
+    partner.audit(&conn, "submit_verdict", Some(version_id), &details)?;
+    let quarantined = security_partner::is_quarantined(&conn, version_id)?;
+    if quarantined {
+        req.app()
+            .download_cache
+            .invalidate_crate(&new_verdict.krate);
+    }
+
+    #[derive(Serialize)]
+    struct R {
+        verdict: EncodableVerdict,
+    }
+    Ok(req.json(&R {
+        verdict: verdict.encodable(quarantined),
+    }))
+}
@@ -0,0 +1,56 @@
+use super::prelude::*;
+
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use crate::sitemap::{sitemap_index_xml, Sitemap};
+
+/// Handles the `GET /sitemap.xml` route.
+///
+/// Lists the sitemap files written by the last `generate_sitemaps` run, the
+/// index itself is cheap enough to be rendered on every request.
+pub fn index(req: &mut dyn Request) -> AppResult<Response> {
+    let conn = req.db_conn()?;
+    let sitemaps = Sitemap::all(&conn)?;
+
+    let uploader = &req.app().config.uploader;
+    // Locally uploaded files are served by this server, but the sitemap
+    // protocol requires absolute URLs.
+    let host = req
+        .headers()
+        .find("Host")
+        .and_then(|hosts| hosts.first().map(|host| host.to_string()))
+        .unwrap_or_default();
+    let scheme = match req.scheme() {
+        conduit::Scheme::Https => "https",
+        _ => "http",
+    };
+    let sitemaps = sitemaps
+        .into_iter()
+        .map(|sitemap| {
+            let location = uploader.sitemap_location(&sitemap.file_name);
+            let location = if location.starts_with('/') {
+                format!("{}://{}{}", scheme, host, location)
+            } else {
+                location
+            };
+            (location, sitemap.generated_at)
+        })
+        .collect::<Vec<_>>();
+
+    let xml = sitemap_index_xml(&sitemaps);
+    let mut headers = HashMap::new();
+    headers.insert(
+        "Content-Type".to_string(),
+        vec!["application/xml; charset=utf-8".to_string()],
+    );
+    headers.insert(
+        "Cache-Control".to_string(),
+        vec!["public,max-age=3600".to_string()],
+    );
+    Ok(Response {
+        status: (200, "OK"),
+        headers,
+        body: Box::new(Cursor::new(xml.into_bytes())),
+    })
+}
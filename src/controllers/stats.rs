@@ -0,0 +1,41 @@
+//! Aggregate statistics of the whole registry, for sites reporting on the
+//! ecosystem.
+
+use super::frontend_prelude::*;
+
+use chrono::NaiveDate;
+
+use crate::models::EcosystemStats;
+use crate::views::EncodableEcosystemStats;
+
+/// The stats only change once a night, when they are rolled up.
+const MAX_AGE: u32 = 6 * 60 * 60;
+
+/// Handles the `GET /stats/ecosystem` route.
+///
+/// Returns the daily totals of crates, versions, users and downloads, oldest
+/// first. `?since=YYYY-MM-DD` limits the series to the days since then.
+pub fn ecosystem(req: &mut dyn Request) -> AppResult<Response> {
+    let since = match req.query().get("since") {
+        Some(since) => Some(
+            NaiveDate::parse_from_str(since, "%Y-%m-%d")
+                .map_err(|_| bad_request(&format_args!("`{}` is not a date", since)))?,
+        ),
+        None => None,
+    };
+    let conn = req.db_read_only()?;
+    let series = EcosystemStats::series(&conn, since)?;
+
+    #[derive(Serialize)]
+    struct R {
+        stats: Vec<EncodableEcosystemStats>,
+    }
+    let mut response = req.json(&R {
+        stats: EcosystemStats::encodable_series(series),
+    });
+    response.headers.insert(
+        "Cache-Control".into(),
+        vec![format!("public, max-age={}", MAX_AGE)],
+    );
+    Ok(response)
+}
@@ -0,0 +1,60 @@
+//! The public endpoints of the transparency log of published crate files, see
+//! the `transparency_log` module for how auditors can check it.
+
+use super::frontend_prelude::*;
+
+use crate::models::{SignedTreeHead, TransparencyLogEntry};
+use crate::util::errors::NotFound;
+use crate::views::{EncodableLogEntry, EncodableTreeHead};
+
+/// The most entries served at once.
+const MAX_ENTRIES: i64 = 1000;
+
+/// Handles the `GET /transparency-log/tree-head` route.
+pub fn tree_head(req: &mut dyn Request) -> AppResult<Response> {
+    let conn = req.db_read_only()?;
+    let head =
+        SignedTreeHead::latest(&conn)?.ok_or_else(|| Box::new(NotFound) as Box<dyn AppError>)?;
+
+    #[derive(Serialize)]
+    struct R {
+        tree_head: EncodableTreeHead,
+    }
+    Ok(req.json(&R {
+        tree_head: head.encodable(),
+    }))
+}
+
+/// Handles the `GET /transparency-log/entries` route.
+///
+/// Returns the entries of the tree in order, starting at the leaf index
+/// `start`. Entries appended since the latest tree head aren't included.
+pub fn entries(req: &mut dyn Request) -> AppResult<Response> {
+    let query = req.query();
+    let start = match query.get("start") {
+        Some(start) => start.parse::<i32>().map_err(|e| bad_request(&e))?,
+        None => 0,
+    };
+    let limit = match query.get("limit") {
+        Some(limit) => i64::from(limit.parse::<u32>().map_err(|e| bad_request(&e))?),
+        None => MAX_ENTRIES,
+    };
+    if limit > MAX_ENTRIES {
+        return Err(bad_request(&format_args!(
+            "at most {} entries can be requested at once",
+            MAX_ENTRIES
+        )));
+    }
+
+    let conn = req.db_read_only()?;
+    let entries = TransparencyLogEntry::range(&conn, start, limit)?
+        .into_iter()
+        .map(TransparencyLogEntry::encodable)
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        entries: Vec<EncodableLogEntry>,
+    }
+    Ok(req.json(&R { entries }))
+}
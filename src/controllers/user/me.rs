@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::controllers::frontend_prelude::*;
 
@@ -6,10 +6,17 @@ use crate::controllers::helpers::*;
 use crate::email;
 
 use crate::models::{
-    CrateOwner, Email, Follow, NewEmail, OwnerKind, User, Version, VersionOwnerAction,
+    publish_job, squatting_report, CrateOwner, CrateOwnerInvitation, Email, Follow, NewEmail,
+    OwnerKind, User, Version, VersionOwnerAction,
+};
+use crate::schema::{
+    crate_owner_invitations, crate_owners, crates, default_versions, dependency_cycles, emails,
+    follows, publish_jobs, squatting_reports, users, version_downloads, versions,
+};
+use crate::views::{
+    EncodableActionItem, EncodableDashboard, EncodableDashboardCrate, EncodableMe,
+    EncodableVersion, OwnedCrate,
 };
-use crate::schema::{crate_owners, crates, emails, follows, users, versions};
-use crate::views::{EncodableMe, EncodableVersion, OwnedCrate};
 
 /// Handles the `GET /me` route.
 pub fn me(req: &mut dyn Request) -> AppResult<Response> {
@@ -99,6 +106,162 @@ pub fn updates(req: &mut dyn Request) -> AppResult<Response> {
     }))
 }
 
+/// Handles the `GET /me/dashboard` route.
+///
+/// Summarizes the crates of the user along with what needs their attention,
+/// which the frontend would otherwise request from several endpoints. The
+/// number of queries doesn't grow with the number of crates.
+pub fn dashboard(req: &mut dyn Request) -> AppResult<Response> {
+    use diesel::dsl::*;
+    use diesel::sql_types::BigInt;
+
+    let conn = req.db_conn()?;
+    let user_id = req.authenticate(&conn)?.user_id();
+
+    let owned_crates = CrateOwner::by_owner_kind(OwnerKind::User)
+        .inner_join(crates::table)
+        .filter(crate_owners::owner_id.eq(user_id))
+        .select((crates::id, crates::name))
+        .order(crates::name.asc())
+        .load::<(i32, String)>(&*conn)?;
+    let crate_ids = owned_crates.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+
+    let latest_versions = default_versions::table
+        .inner_join(versions::table)
+        .filter(default_versions::crate_id.eq(any(&crate_ids)))
+        .select((
+            default_versions::crate_id,
+            versions::id,
+            versions::num,
+            versions::yanked,
+        ))
+        .load::<(i32, i32, String, bool)>(&*conn)?
+        .into_iter()
+        .map(|(crate_id, id, num, yanked)| (crate_id, (id, num, yanked)))
+        .collect::<HashMap<_, _>>();
+
+    // Cycles are kept for every version they were found in, so only the
+    // ones of the versions shown on the crate pages count.
+    let latest_version_ids = latest_versions
+        .values()
+        .map(|(id, _, _)| *id)
+        .collect::<Vec<_>>();
+    let with_cycles = dependency_cycles::table
+        .filter(dependency_cycles::version_id.eq(any(latest_version_ids)))
+        .select(dependency_cycles::crate_id)
+        .distinct()
+        .load::<i32>(&*conn)?
+        .into_iter()
+        .collect::<HashSet<_>>();
+
+    let reported = squatting_reports::table
+        .filter(squatting_reports::crate_id.eq(any(&crate_ids)))
+        .filter(squatting_reports::status.eq(any(vec![
+            squatting_report::PENDING,
+            squatting_report::CANDIDATE,
+        ])))
+        .select(squatting_reports::crate_id)
+        .distinct()
+        .load::<i32>(&*conn)?
+        .into_iter()
+        .collect::<HashSet<_>>();
+
+    let weekly_downloads = version_downloads::table
+        .inner_join(versions::table)
+        .filter(versions::crate_id.eq(any(&crate_ids)))
+        .filter(version_downloads::date.gt(date(now - 14.days())))
+        .group_by(versions::crate_id)
+        .select((
+            versions::crate_id,
+            sql::<BigInt>(
+                "COALESCE(SUM(version_downloads.downloads) \
+                 FILTER (WHERE version_downloads.date > CURRENT_DATE - 7), 0)",
+            ),
+            sql::<BigInt>(
+                "COALESCE(SUM(version_downloads.downloads) \
+                 FILTER (WHERE version_downloads.date <= CURRENT_DATE - 7), 0)",
+            ),
+        ))
+        .load::<(i32, i64, i64)>(&*conn)?
+        .into_iter()
+        .map(|(crate_id, this_week, last_week)| (crate_id, (this_week, last_week)))
+        .collect::<HashMap<_, _>>();
+
+    let crates = owned_crates
+        .into_iter()
+        .map(|(id, name)| {
+            let latest = latest_versions.get(&id);
+            let (this_week, last_week) = weekly_downloads.get(&id).cloned().unwrap_or((0, 0));
+
+            let mut advisories = Vec::new();
+            if with_cycles.contains(&id) {
+                advisories.push("dependency_cycle".to_string());
+            }
+            if latest.map_or(false, |(_, _, yanked)| *yanked) {
+                advisories.push("latest_version_yanked".to_string());
+            }
+            if reported.contains(&id) {
+                advisories.push("squatting_report".to_string());
+            }
+
+            EncodableDashboardCrate {
+                id,
+                name,
+                latest_version: latest.map(|(_, num, _)| num.clone()),
+                weekly_downloads: this_week,
+                weekly_downloads_delta: this_week - last_week,
+                advisories,
+            }
+        })
+        .collect();
+
+    let crate_owner_invitations = crate_owner_invitations::table
+        .filter(crate_owner_invitations::invited_user_id.eq(user_id))
+        .load::<CrateOwnerInvitation>(&*conn)?
+        .into_iter()
+        .map(|i| i.encodable(&conn))
+        .collect();
+
+    let mut action_items = Vec::new();
+    let verified = emails::table
+        .filter(emails::user_id.eq(user_id))
+        .select(emails::verified)
+        .first::<bool>(&*conn)
+        .optional()?;
+    if verified != Some(true) {
+        action_items.push(EncodableActionItem {
+            kind: "verify_email".into(),
+            crate_name: None,
+            detail: "A verified email address is required to publish crates.".into(),
+        });
+    }
+
+    let failed_publishes = publish_jobs::table
+        .inner_join(versions::table.inner_join(crates::table))
+        .filter(publish_jobs::user_id.eq(user_id))
+        .filter(publish_jobs::state.eq(publish_job::FAILED))
+        .select((crates::name, versions::num, publish_jobs::error))
+        .order(publish_jobs::updated_at.desc())
+        .load::<(String, String, Option<String>)>(&*conn)?;
+    for (crate_name, num, error) in failed_publishes {
+        action_items.push(EncodableActionItem {
+            kind: "failed_publish".into(),
+            detail: format!(
+                "Publishing version {} failed: {}",
+                num,
+                error.unwrap_or_default()
+            ),
+            crate_name: Some(crate_name),
+        });
+    }
+
+    Ok(req.json(&EncodableDashboard {
+        crates,
+        crate_owner_invitations,
+        action_items,
+    }))
+}
+
 /// Handles the `PUT /user/:user_id` route.
 pub fn update_user(req: &mut dyn Request) -> AppResult<Response> {
     use self::emails::user_id;
@@ -1,9 +1,12 @@
+use chrono::NaiveDate;
+
 use crate::controllers::frontend_prelude::*;
 
-use crate::models::{CrateOwner, OwnerKind, User};
-use crate::schema::{crate_owners, crates, users};
+use crate::controllers::helpers::Paginate;
+use crate::models::{CrateOwner, OwnerKind, User, Version, VersionOwnerAction};
+use crate::schema::{crate_owners, crates, users, versions};
 use crate::util::errors::ChainError;
-use crate::views::EncodablePublicUser;
+use crate::views::{EncodablePublicUser, EncodableVersion};
 
 /// Handles the `GET /users/:user_id` route.
 pub fn show(req: &mut dyn Request) -> AppResult<Response> {
@@ -49,3 +52,61 @@ pub fn stats(req: &mut dyn Request) -> AppResult<Response> {
         total_downloads: data,
     }))
 }
+
+/// Handles the `GET /users/:user_id/publishes` route.
+///
+/// Lists the versions the user published, most recent first, regardless of
+/// whether they still own the crates. With `?since=YYYY-MM-DD` only versions
+/// published on or after that day are listed.
+pub fn publishes(req: &mut dyn Request) -> AppResult<Response> {
+    let user_id = req.params()["user_id"]
+        .parse::<i32>()
+        .chain_error(|| bad_request("invalid user_id"))?;
+    let query = req.query();
+    let since = query
+        .get("since")
+        .map(|since| NaiveDate::parse_from_str(since, "%Y-%m-%d"))
+        .transpose()
+        .chain_error(|| bad_request("`since` must be a date like 2020-03-01"))?;
+    let conn = req.db_read_only()?;
+    let user = users::table.find(user_id).first::<User>(&*conn)?;
+
+    let mut versions_query = versions::table
+        .inner_join(crates::table)
+        .filter(versions::published_by.eq(user.id))
+        .order(versions::created_at.desc())
+        .select((versions::all_columns, crates::name))
+        .into_boxed();
+    if let Some(since) = since {
+        versions_query = versions_query.filter(versions::created_at.ge(since.and_hms(0, 0, 0)));
+    }
+    let data = versions_query
+        .paginate(&query)?
+        .load::<(Version, String)>(&*conn)?;
+    let total = data.total().unwrap_or_default();
+    let more = data.next_page_params().is_some();
+    let versions = data.iter().map(|(v, _)| v).cloned().collect::<Vec<_>>();
+    let actions = VersionOwnerAction::for_versions(&conn, &versions)?;
+    let versions = data
+        .into_iter()
+        .zip(actions)
+        .map(|((version, crate_name), actions)| {
+            version.encodable(&crate_name, Some(user.clone()), actions)
+        })
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        versions: Vec<EncodableVersion>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        total: i64,
+        more: bool,
+    }
+    Ok(req.json(&R {
+        versions,
+        meta: Meta { total, more },
+    }))
+}
@@ -1,6 +1,7 @@
 pub mod deprecated;
 pub mod downloads;
 pub mod metadata;
+pub mod signature;
 pub mod yank;
 
 use super::prelude::*;
@@ -1,9 +1,10 @@
 //! Deprecated api endpoints
 //!
 //! There are no known uses of these endpoints.  There is currently no plan for
-//! removing these endpoints.  At a minimum, logs should be reviewed over a
-//! period of time to ensure there are no external users of an endpoint before
-//! it is removed.
+//! removing these endpoints.  They are listed in `crate::deprecations`, so
+//! their responses announce the deprecation and `crates-io-admin
+//! deprecated-endpoints` shows who still uses them, which should be reviewed
+//! over a period of time before an endpoint is removed.
 
 use crate::controllers::frontend_prelude::*;
 
@@ -6,8 +6,10 @@ use crate::controllers::prelude::*;
 
 use chrono::{Duration, NaiveDate, Utc};
 
+use crate::download_cache::DownloadableVersion;
 use crate::models::{Crate, VersionDownload};
 use crate::schema::*;
+use crate::util::errors::{bad_request, NotFound};
 use crate::views::EncodableVersionDownload;
 
 use super::{extract_crate_name, extract_semver};
@@ -39,8 +41,9 @@ pub fn download(req: &mut dyn Request) -> AppResult<Response> {
 
 /// Increment the download counts for a given crate version.
 ///
-/// Returns the crate name as stored in the database, or an error if we could
-/// not load the version ID from the database.
+/// Returns the crate name as stored in the database, or an error if the
+/// version doesn't exist or is quarantined. The version is looked up through
+/// the `DownloadCache`, so that most downloads don't read from the database.
 ///
 /// This ignores any errors that occur updating the download count. Failure is
 /// expected if the application is in read only mode, or for API-only mirrors.
@@ -54,17 +57,43 @@ fn increment_download_counts(
     use self::versions::dsl::*;
 
     let conn = req.db_conn()?;
-    let (version_id, crate_name) = versions
-        .inner_join(crates::table)
-        .select((id, crates::name))
-        .filter(Crate::with_name(crate_name))
-        .filter(num.eq(version))
-        .first(&*conn)?;
+    let downloadable = req
+        .app()
+        .download_cache
+        .lookup(crate_name, version, || {
+            let found = versions
+                .inner_join(crates::table)
+                .left_join(version_quarantines::table)
+                .select((
+                    id,
+                    crates::name,
+                    version_quarantines::version_id.nullable().is_not_null(),
+                ))
+                .filter(Crate::with_name(crate_name))
+                .filter(num.eq(version))
+                .first::<(i32, String, bool)>(&*conn)
+                .optional()?;
+            Ok(found.map(
+                |(version_id, crate_name, quarantined)| DownloadableVersion {
+                    version_id,
+                    crate_name,
+                    quarantined,
+                },
+            ))
+        })?
+        .ok_or_else(|| Box::new(NotFound) as Box<dyn AppError>)?;
+
+    if downloadable.quarantined {
+        return Err(bad_request(
+            "this version is quarantined while a security report about it is reviewed",
+        ));
+    }
 
     // Wrap in a transaction so we don't poison the outer transaction if this
     // fails
-    let _ = conn.transaction(|| VersionDownload::create_or_increment(version_id, &conn));
-    Ok(crate_name)
+    let _ =
+        conn.transaction(|| VersionDownload::create_or_increment(downloadable.version_id, &conn));
+    Ok(downloadable.crate_name)
 }
 
 /// Handles the `GET /crates/:crate_id/:version/downloads` route.
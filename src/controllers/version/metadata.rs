@@ -4,11 +4,18 @@
 //! index or cached metadata which was extracted (client side) from the
 //! `Cargo.toml` file.
 
+use std::collections::BTreeMap;
+
 use crate::controllers::frontend_prelude::*;
 
-use crate::models::VersionOwnerAction;
+use crate::models::{VersionFeatureDoc, VersionFile, VersionOwnerAction};
+use crate::render::TocEntry;
 use crate::schema::*;
-use crate::views::{EncodableDependency, EncodablePublicUser, EncodableVersion};
+use crate::util::errors::NotFound;
+use crate::views::{
+    EncodableDependency, EncodableFeature, EncodablePublicUser, EncodableVersion,
+    EncodableVersionFile,
+};
 
 use super::version_and_crate;
 
@@ -34,6 +41,74 @@ pub fn dependencies(req: &mut dyn Request) -> AppResult<Response> {
     Ok(req.json(&R { dependencies: deps }))
 }
 
+/// Handles the `GET /crates/:crate_id/:version/features` route.
+///
+/// The features are the same as in the index, ordered by name. Descriptions
+/// are taken from the `## ` comments in the manifest of the crate file.
+pub fn features(req: &mut dyn Request) -> AppResult<Response> {
+    let (conn, version, _) = version_and_crate(req)?;
+    let mut docs = VersionFeatureDoc::by_version(&conn, version.id)?;
+    let features: BTreeMap<String, Vec<String>> =
+        serde_json::from_value(version.features).unwrap_or_default();
+    let features = features
+        .into_iter()
+        .map(|(name, enables)| EncodableFeature {
+            description: docs.remove(&name),
+            name,
+            enables,
+        })
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        features: Vec<EncodableFeature>,
+    }
+    Ok(req.json(&R { features }))
+}
+
+/// Handles the `GET /crates/:crate_id/:version/files` route.
+///
+/// Lists the regular files of the crate file with their sizes and SHA-256
+/// checksums, as recorded when the version was published. Versions which
+/// were published before files were recorded have no files listed.
+pub fn files(req: &mut dyn Request) -> AppResult<Response> {
+    let (conn, version, _) = version_and_crate(req)?;
+    let files = VersionFile::by_version(&conn, version.id)?
+        .into_iter()
+        .map(VersionFile::encodable)
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        files: Vec<EncodableVersionFile>,
+    }
+    Ok(req.json(&R { files }))
+}
+
+/// Handles the `GET /crates/:crate_id/:version/readme_toc` route.
+///
+/// Lists the headings of the rendered README with their anchors. READMEs
+/// which weren't rendered yet, or were rendered before headings were
+/// extracted, have none.
+pub fn readme_toc(req: &mut dyn Request) -> AppResult<Response> {
+    let (conn, version, _) = version_and_crate(req)?;
+    let toc = readme_renderings::table
+        .find(version.id)
+        .select(readme_renderings::toc)
+        .first::<Option<serde_json::Value>>(&*conn)
+        .optional()?
+        .and_then(|toc| toc)
+        .ok_or_else(|| Box::new(NotFound) as Box<dyn AppError>)?;
+    let toc = serde_json::from_value::<Vec<TocEntry>>(toc)
+        .map_err(|e| server_error(&format_args!("invalid README headings: {}", e)))?;
+
+    #[derive(Serialize)]
+    struct R {
+        toc: Vec<TocEntry>,
+    }
+    Ok(req.json(&R { toc }))
+}
+
 /// Handles the `GET /crates/:crate_id/:version/authors` route.
 pub fn authors(req: &mut dyn Request) -> AppResult<Response> {
     let (conn, version, _) = version_and_crate(req)?;
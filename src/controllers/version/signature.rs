@@ -0,0 +1,66 @@
+//! Endpoints for the sigstore bundles signing the crate files of versions
+
+use super::version_and_crate;
+use crate::controllers::cargo_prelude::*;
+use crate::models::{Rights, Version};
+use crate::sigstore;
+use crate::util::errors::{bad_request, NotFound};
+use crate::util::raw_json_response;
+
+/// Handles the `PUT /crates/:crate_id/:version/signature` route.
+///
+/// Stores a sigstore bundle signing the crate file of the version, after
+/// verifying it against the checksum recorded when the version was
+/// published and the sigstore trust root. A version can only be signed once,
+/// so only the user who published it or a user owner can sign it, and not
+/// every member of a team owning the crate.
+pub fn upload(req: &mut dyn Request) -> AppResult<Response> {
+    let mut bundle = String::new();
+    req.body().read_to_string(&mut bundle)?;
+
+    let (conn, version, krate) = version_and_crate(req)?;
+    let user = req.authenticate(&conn)?.find_user(&conn)?;
+    let owners = krate.owners(&conn)?;
+    let rights = user.rights(req.app(), &owners)?;
+    if rights < Rights::Publish {
+        return Err(cargo_err("must already be an owner to sign a version"));
+    }
+    if rights < Rights::Full && version.published_by != Some(user.id) {
+        return Err(cargo_err(
+            "only the user who published a version or an owner can sign it",
+        ));
+    }
+    let trust_root = req
+        .app()
+        .config
+        .sigstore_trust_root
+        .as_ref()
+        .ok_or_else(|| bad_request("this registry doesn't accept sigstore bundles"))?;
+
+    let checksum = Version::checksum(version.id, &conn)?.ok_or_else(|| {
+        bad_request(&format_args!(
+            "{} {} was published before checksums were recorded, so it can't be signed",
+            krate.name, version.num
+        ))
+    })?;
+    let signer = sigstore::verify_bundle(&bundle, &checksum, trust_root)?;
+
+    if !Version::record_signature(version.id, &bundle, signer.as_deref(), user.id, &conn)? {
+        return Err(bad_request(&format_args!(
+            "{} {} is already signed",
+            krate.name, version.num
+        )));
+    }
+    ok_true()
+}
+
+/// Handles the `GET /crates/:crate_id/:version/signature` route.
+///
+/// Returns the sigstore bundle exactly as it was uploaded.
+pub fn show(req: &mut dyn Request) -> AppResult<Response> {
+    let (conn, version, _) = version_and_crate(req)?;
+    match Version::signature(version.id, &conn)? {
+        Some(bundle) => Ok(raw_json_response(bundle)),
+        None => Err(Box::new(NotFound)),
+    }
+}
@@ -1,18 +1,29 @@
 //! Endpoints for yanking and unyanking specific versions of crates
 
-use swirl::Job;
+use chrono::{DateTime, NaiveDateTime, Utc};
 
 use super::version_and_crate;
+use crate::background_jobs::EnqueueVersioned;
 use crate::controllers::cargo_prelude::*;
-use crate::git;
-use crate::models::Rights;
-use crate::models::{insert_version_owner_action, VersionAction};
+use crate::controllers::helpers::Paginate;
+use crate::models::{insert_version_owner_action, VersionAction, YankEvent};
+use crate::models::{Crate, Rights, Version};
+use crate::schema::{crates, version_owner_actions, versions};
+use crate::util::errors::{bad_request, TooManyRequests};
+use crate::views::{EncodableBulkYankResult, EncodableYank, EncodableYankEvent};
+use crate::{crate_page, git, og_image, render};
+
+/// The maximum length of the optional `reason` of a yank or unyank.
+const MAX_REASON_LENGTH: usize = 256;
 
 /// Handles the `DELETE /crates/:crate_id/:version/yank` route.
 /// This does not delete a crate version, it makes the crate
 /// version accessible only to crates that already have a
 /// `Cargo.lock` containing this version.
 ///
+/// An optional `reason` query parameter is recorded alongside the yank and
+/// listed by `GET /yanks`.
+///
 /// Notes:
 /// Crate deletion is not implemented to avoid breaking builds,
 /// and the goal of yanking a crate is to prevent crates
@@ -27,7 +38,12 @@ pub fn unyank(req: &mut dyn Request) -> AppResult<Response> {
 }
 
 /// Changes `yanked` flag on a crate version record
+///
+/// Changing a version back within `Config::yank_cooldown` of its previous
+/// transition is rejected, so that rapid flapping doesn't confuse resolvers.
 fn modify_yank(req: &mut dyn Request, yanked: bool) -> AppResult<Response> {
+    let reason = validate_reason(req.query().get("reason").map(String::as_str))?;
+
     let (conn, version, krate) = version_and_crate(req)?;
     let ids = req.authenticate(&conn)?;
     let user = ids.find_user(&conn)?;
@@ -36,17 +52,338 @@ fn modify_yank(req: &mut dyn Request, yanked: bool) -> AppResult<Response> {
     if user.rights(req.app(), &owners)? < Rights::Publish {
         return Err(cargo_err("must already be an owner to yank or unyank"));
     }
+
+    let cooldown = chrono::Duration::from_std(req.app().config.yank_cooldown).unwrap();
+    conn.transaction::<_, Box<dyn AppError>, _>(|| {
+        lock_versions(&conn, &[version.id])?;
+        let outcome = apply_yank(
+            &conn,
+            cooldown,
+            &version,
+            user.id,
+            ids.api_token_id(),
+            yanked,
+            reason.as_deref(),
+        )?;
+        if let YankOutcome::TooSoon(retry_after) = outcome {
+            return Err(Box::new(TooManyRequests { retry_after }));
+        }
+
+        crate_page::render_crate_page(krate.name.clone())
+            .enqueue_versioned(&conn)
+            .map_err(|e| AppError::from_std_error(e))?;
+        og_image::enqueue_render(&conn, &req.app().config, &krate.name)
+            .map_err(|e| AppError::from_std_error(e))?;
+        git::yank(krate.name.clone(), version, yanked)
+            .enqueue_versioned(&conn)
+            .map_err(|e| AppError::from_std_error(e))?;
+        Ok(())
+    })?;
+    req.app().response_cache.invalidate_crate(&krate.name);
+    req.app().download_cache.invalidate_crate(&krate.name);
+
+    ok_true()
+}
+
+/// Handles the `POST /crates/:crate_id/yank_bulk` route.
+///
+/// Yanks or unyanks either the versions listed in `versions` or all versions
+/// matching the semver requirement in `range`, e.g. `>=1.0.0, <1.2.0`. All
+/// versions are changed in one transaction and the index is updated with a
+/// single commit. The response lists what happened to every version.
+pub fn yank_bulk(req: &mut dyn Request) -> AppResult<Response> {
+    #[derive(Deserialize)]
+    struct BulkYank {
+        yanked: bool,
+        versions: Option<Vec<String>>,
+        range: Option<String>,
+        reason: Option<String>,
+    }
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let bulk: BulkYank =
+        serde_json::from_str(&body).map_err(|e| bad_request(&format_args!("{}", e)))?;
+    let reason = validate_reason(bulk.reason.as_deref())?;
+    let yanked = bulk.yanked;
+
+    let conn = req.db_conn()?;
+    let ids = req.authenticate(&conn)?;
+    let user = ids.find_user(&conn)?;
+    let crate_name = &req.params()["crate_id"];
+    let krate = Crate::by_name(crate_name).first::<Crate>(&*conn)?;
+    let owners = krate.owners(&conn)?;
+
+    if user.rights(req.app(), &owners)? < Rights::Publish {
+        return Err(cargo_err("must already be an owner to yank or unyank"));
+    }
+
+    let mut all_versions = krate.all_versions().load::<Version>(&*conn)?;
+    all_versions.sort_by(|a, b| a.num.cmp(&b.num));
+
+    // The selected versions, and the requested ones which don't exist
+    let (selected, not_found) = match (bulk.versions, bulk.range) {
+        (Some(nums), None) => {
+            let mut selected = Vec::<Version>::new();
+            let mut not_found = Vec::new();
+            for num in nums {
+                // Versions listed more than once are only changed once
+                if selected.iter().any(|v| v.num.to_string() == num) {
+                    continue;
+                }
+                match all_versions.iter().position(|v| v.num.to_string() == num) {
+                    Some(i) => selected.push(all_versions.swap_remove(i)),
+                    None if !not_found.contains(&num) => not_found.push(num),
+                    None => {}
+                }
+            }
+            (selected, not_found)
+        }
+        (None, Some(range)) => {
+            let range = semver::VersionReq::parse(&range).map_err(|_| {
+                bad_request(&format_args!("invalid semver requirement: `{}`", range))
+            })?;
+            let selected = all_versions
+                .into_iter()
+                .filter(|v| range.matches(&v.num))
+                .collect();
+            (selected, Vec::new())
+        }
+        _ => {
+            return Err(bad_request(
+                "exactly one of `versions` and `range` must be given",
+            ))
+        }
+    };
+
+    let cooldown = chrono::Duration::from_std(req.app().config.yank_cooldown).unwrap();
+    let results = conn.transaction::<_, Box<dyn AppError>, _>(|| {
+        let selected_ids = selected.iter().map(|v| v.id).collect::<Vec<_>>();
+        lock_versions(&conn, &selected_ids)?;
+        let mut results = Vec::new();
+        let mut changed = Vec::new();
+        for version in &selected {
+            let outcome = apply_yank(
+                &conn,
+                cooldown,
+                version,
+                user.id,
+                ids.api_token_id(),
+                yanked,
+                reason.as_deref(),
+            )?;
+            let (status, retry_after) = match outcome {
+                YankOutcome::Changed if yanked => ("yanked", None),
+                YankOutcome::Changed => ("unyanked", None),
+                YankOutcome::Unchanged => ("unchanged", None),
+                YankOutcome::TooSoon(retry_after) => ("rate_limited", Some(retry_after)),
+            };
+            if let YankOutcome::Changed = outcome {
+                changed.push(version.id);
+            }
+            results.push(EncodableBulkYankResult {
+                num: version.num.to_string(),
+                status: status.into(),
+                retry_after,
+            });
+        }
+        results.extend(not_found.into_iter().map(|num| EncodableBulkYankResult {
+            num,
+            status: "not_found".into(),
+            retry_after: None,
+        }));
+
+        if !changed.is_empty() {
+            git::yank_bulk(krate.name.clone(), changed, yanked)
+                .enqueue_versioned(&conn)
+                .map_err(|e| AppError::from_std_error(e))?;
+            crate_page::render_crate_page(krate.name.clone())
+                .enqueue_versioned(&conn)
+                .map_err(|e| AppError::from_std_error(e))?;
+            og_image::enqueue_render(&conn, &req.app().config, &krate.name)
+                .map_err(|e| AppError::from_std_error(e))?;
+        }
+        Ok(results)
+    })?;
+    req.app().response_cache.invalidate_crate(&krate.name);
+    req.app().download_cache.invalidate_crate(&krate.name);
+
+    #[derive(Serialize)]
+    struct R {
+        results: Vec<EncodableBulkYankResult>,
+    }
+    Ok(req.json(&R { results }))
+}
+
+/// What `apply_yank` did to a version.
+enum YankOutcome {
+    Changed,
+    /// The version already was in the requested state.
+    Unchanged,
+    /// The version changed state too recently, it can be changed again at
+    /// the given time.
+    TooSoon(NaiveDateTime),
+}
+
+/// Locks the rows of the given versions until the end of the transaction, so
+/// that concurrent requests check the cool-down against each other's
+/// transitions instead of both passing it.
+fn lock_versions(conn: &PgConnection, version_ids: &[i32]) -> QueryResult<()> {
+    versions::table
+        .filter(versions::id.eq_any(version_ids))
+        .order(versions::id)
+        .select(versions::id)
+        .for_update()
+        .load::<i32>(conn)?;
+    Ok(())
+}
+
+/// Records the yank or unyank of a single version. The index is updated
+/// by a background job, which the caller has to enqueue.
+///
+/// The version has to be locked with `lock_versions` first.
+fn apply_yank(
+    conn: &PgConnection,
+    cooldown: chrono::Duration,
+    version: &Version,
+    user_id: i32,
+    api_token_id: Option<i32>,
+    yanked: bool,
+    reason: Option<&str>,
+) -> QueryResult<YankOutcome> {
+    // Yanks are only processed by a background job, so the latest recorded
+    // transition is more up to date than the version itself.
+    let latest_event = YankEvent::latest(conn, version.id)?;
+    let currently_yanked = latest_event.map_or(version.yanked, |event| event.yanked);
+    let outcome = if currently_yanked != yanked {
+        if let Some(event) = latest_event {
+            let retry_after = event.created_at + cooldown;
+            if retry_after > Utc::now().naive_utc() {
+                return Ok(YankOutcome::TooSoon(retry_after));
+            }
+        }
+        YankEvent::record(conn, version.id, user_id, api_token_id, yanked)?;
+        YankOutcome::Changed
+    } else {
+        YankOutcome::Unchanged
+    };
+
     let action = if yanked {
         VersionAction::Yank
     } else {
         VersionAction::Unyank
     };
 
-    insert_version_owner_action(&conn, version.id, user.id, ids.api_token_id(), action)?;
+    insert_version_owner_action(conn, version.id, user_id, api_token_id, action, reason)?;
+    Ok(outcome)
+}
 
-    git::yank(krate.name, version, yanked)
-        .enqueue(&conn)
-        .map_err(|e| AppError::from_std_error(e))?;
+/// Trims the optional reason of a yank, treating blank reasons as missing.
+fn validate_reason(reason: Option<&str>) -> AppResult<Option<String>> {
+    let reason = reason
+        .map(|reason| reason.trim().to_string())
+        .filter(|reason| !reason.is_empty());
+    if let Some(reason) = &reason {
+        if reason.chars().count() > MAX_REASON_LENGTH {
+            return Err(cargo_err(&format_args!(
+                "the reason must not be longer than {} characters",
+                MAX_REASON_LENGTH
+            )));
+        }
+    }
+    Ok(reason)
+}
 
-    ok_true()
+/// Handles the `GET /crates/:crate_id/:version/yank_history` route.
+///
+/// Lists every transition of the version between yanked and unyanked, oldest
+/// first.
+pub fn history(req: &mut dyn Request) -> AppResult<Response> {
+    let (conn, version, _) = version_and_crate(req)?;
+    let events = YankEvent::by_version(&conn, &version)?
+        .into_iter()
+        .map(|(event, user)| event.encodable(user))
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        yank_events: Vec<EncodableYankEvent>,
+    }
+    Ok(req.json(&R {
+        yank_events: events,
+    }))
+}
+
+/// Handles the `GET /yanks` route.
+///
+/// Lists all versions yanked or unyanked since the RFC 3339 timestamp given
+/// in the `since` query parameter, oldest first. This allows mirrors and
+/// security scanners to follow yanks without diffing the index.
+pub fn index(req: &mut dyn Request) -> AppResult<Response> {
+    let params = req.query();
+    let since = params
+        .get("since")
+        .ok_or_else(|| bad_request("missing `since` parameter"))?;
+    let since = DateTime::parse_from_rfc3339(since)
+        .map_err(|_| {
+            bad_request(&format_args!(
+                "invalid `since` value: `{}`, expected an RFC 3339 timestamp",
+                since
+            ))
+        })?
+        .naive_utc();
+
+    let conn = req.db_read_only()?;
+    let data = version_owner_actions::table
+        .inner_join(versions::table.inner_join(crates::table))
+        .filter(
+            version_owner_actions::action.eq_any(vec![VersionAction::Yank, VersionAction::Unyank]),
+        )
+        .filter(version_owner_actions::time.ge(since))
+        .order((version_owner_actions::time, version_owner_actions::id))
+        .select((
+            crates::name,
+            versions::num,
+            version_owner_actions::action,
+            version_owner_actions::reason,
+            version_owner_actions::time,
+        ))
+        .paginate(&params)?
+        .load::<(String, String, VersionAction, Option<String>, NaiveDateTime)>(&*conn)?;
+    let total = data.total();
+    let next_page = data.next_page_params().map(|p| req.query_with_params(p));
+    let prev_page = data.prev_page_params().map(|p| req.query_with_params(p));
+
+    let yanks = data
+        .into_iter()
+        .map(|(krate, num, action, reason, time)| EncodableYank {
+            krate,
+            num,
+            yanked: action == VersionAction::Yank,
+            reason_html: reason.as_deref().map(render::message_to_html),
+            reason,
+            time,
+        })
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        yanks: Vec<EncodableYank>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        total: Option<i64>,
+        next_page: Option<String>,
+        prev_page: Option<String>,
+    }
+
+    Ok(req.json(&R {
+        yanks,
+        meta: Meta {
+            total,
+            next_page,
+            prev_page,
+        },
+    }))
 }
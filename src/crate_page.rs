@@ -0,0 +1,100 @@
+//! Precomputed payloads for crate pages.
+//!
+//! The crate page of the frontend needs the crate with its versions, its
+//! owners and its download statistics. Instead of fetching them with several
+//! requests, it can load `GET /api/v1/crates/:crate_id/full`, which serves a
+//! payload containing all of them from the `crate_pages` table.
+//!
+//! The payload is rendered again by the `render_crate_page` job whenever a
+//! crate is published, yanked or changes its owners or links. Download
+//! counts change continuously, so payloads older than `MAX_AGE_HOURS` are
+//! rendered for the next request without being stored, and the job is
+//! enqueued to store a new one.
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::dsl::now;
+use diesel::prelude::*;
+use swirl::PerformError;
+
+use crate::background_jobs::Environment;
+use crate::controllers::krate::downloads::CrateDownloads;
+use crate::controllers::krate::metadata::CrateDetails;
+use crate::models::{Crate, OrgDomain, Owner};
+use crate::schema::crate_pages;
+use crate::views::EncodableOwner;
+
+/// Payloads older than this are rendered again when they are requested.
+pub const MAX_AGE_HOURS: i64 = 24;
+
+#[derive(Serialize)]
+struct CratePage {
+    #[serde(flatten)]
+    details: CrateDetails,
+    owners: Vec<EncodableOwner>,
+    downloads: CrateDownloads,
+}
+
+/// Renders the payload of a crate page without storing it.
+pub fn payload(conn: &PgConnection, krate: &Crate) -> Result<serde_json::Value, PerformError> {
+    let mut owners = krate
+        .owners(conn)?
+        .into_iter()
+        .map(Owner::encodable)
+        .collect::<Vec<_>>();
+    OrgDomain::add_to_owners(conn, &mut owners)?;
+    let page = CratePage {
+        details: CrateDetails::load(conn, krate)?,
+        owners,
+        downloads: CrateDownloads::load(conn, krate)?,
+    };
+    Ok(serde_json::to_value(&page)?)
+}
+
+/// Renders the payload of a crate page and stores it, replacing the
+/// previous one.
+fn render(conn: &PgConnection, krate: &Crate) -> Result<(), PerformError> {
+    let payload = payload(conn, krate)?;
+    diesel::insert_into(crate_pages::table)
+        .values((
+            crate_pages::crate_id.eq(krate.id),
+            crate_pages::payload.eq(&payload),
+            crate_pages::generated_at.eq(now),
+        ))
+        .on_conflict(crate_pages::crate_id)
+        .do_update()
+        .set((
+            crate_pages::payload.eq(&payload),
+            crate_pages::generated_at.eq(now),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// The stored payload of a crate page, unless it's missing or outdated.
+pub fn cached(conn: &PgConnection, crate_id: i32) -> QueryResult<Option<serde_json::Value>> {
+    let max_age = Utc::now().naive_utc() - Duration::hours(MAX_AGE_HOURS);
+    crate_pages::table
+        .find(crate_id)
+        .select((crate_pages::payload, crate_pages::generated_at))
+        .first::<(serde_json::Value, NaiveDateTime)>(conn)
+        .optional()
+        .map(|page| {
+            page.filter(|(_, generated_at)| *generated_at > max_age)
+                .map(|(payload, _)| payload)
+        })
+}
+
+#[swirl::background_job]
+pub fn render_crate_page(env: &Environment, crate_name: String) -> Result<(), PerformError> {
+    let _heartbeat = env.heartbeat("render_crate_page")?;
+    let conn = env.connection()?;
+    // The crate may have been deleted since the job was enqueued
+    let krate = match Crate::by_name(&crate_name)
+        .first::<Crate>(&*conn)
+        .optional()?
+    {
+        Some(krate) => krate,
+        None => return Ok(()),
+    };
+    render(&conn, &krate)
+}
@@ -0,0 +1,129 @@
+//! The registry of API endpoints which are slated for removal.
+//!
+//! Responses of the endpoints listed here get a `Deprecation` header with the
+//! date the endpoint was deprecated on, a `Sunset` header with the date it
+//! will be removed on, once that is decided, and a `meta.deprecations` field
+//! in their JSON body explaining what to use instead. Requests to them are
+//! counted per consumer, which `crates-io-admin deprecated-endpoints` lists,
+//! so that we know who still has to be contacted before an endpoint is
+//! removed.
+//!
+//! To deprecate an endpoint, add it to `DEPRECATED_ENDPOINTS`.
+
+use chrono::NaiveDate;
+use conduit::Method;
+
+/// An endpoint which is slated for removal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeprecatedEndpoint {
+    pub method: &'static str,
+    /// The route of the endpoint, like `/api/v1/versions/:version_id`.
+    pub path: &'static str,
+    /// The date the endpoint was deprecated on, as `YYYY-MM-DD`.
+    pub deprecated_on: &'static str,
+    /// The date the endpoint will be removed on, as `YYYY-MM-DD`, or `None`
+    /// if that isn't decided yet.
+    pub sunset: Option<&'static str>,
+    /// What to use instead.
+    pub message: &'static str,
+}
+
+pub const DEPRECATED_ENDPOINTS: &[DeprecatedEndpoint] = &[
+    DeprecatedEndpoint {
+        method: "GET",
+        path: "/api/v1/versions",
+        deprecated_on: "2020-03-25",
+        sunset: None,
+        message: "Use `GET /api/v1/crates/:crate_id/versions` to list the versions of a crate.",
+    },
+    DeprecatedEndpoint {
+        method: "GET",
+        path: "/api/v1/versions/:version_id",
+        deprecated_on: "2020-03-25",
+        sunset: None,
+        message: "Use `GET /api/v1/crates/:crate_id/:version` to look up a version.",
+    },
+];
+
+/// The deprecated endpoint which handles a request, if any.
+pub fn find(method: &Method, path: &str) -> Option<&'static DeprecatedEndpoint> {
+    DEPRECATED_ENDPOINTS
+        .iter()
+        .find(|endpoint| endpoint.matches(method, path))
+}
+
+impl DeprecatedEndpoint {
+    /// Whether a request is handled by the endpoint. Segments of the route
+    /// starting with `:` match any segment of the path.
+    pub fn matches(&self, method: &Method, path: &str) -> bool {
+        let method_matches = match method {
+            Method::Head => self.method == "GET",
+            method => method.to_string() == self.method,
+        };
+        let path = path.trim_end_matches('/');
+        method_matches
+            && self.path.split('/').count() == path.split('/').count()
+            && self
+                .path
+                .split('/')
+                .zip(path.split('/'))
+                .all(|(route, segment)| {
+                    route == segment || (route.starts_with(':') && !segment.is_empty())
+                })
+    }
+
+    /// The value of the `Deprecation` header.
+    pub fn deprecation_header(&self) -> String {
+        http_date(self.deprecated_on)
+    }
+
+    /// The value of the `Sunset` header, if a date was set.
+    pub fn sunset_header(&self) -> Option<String> {
+        self.sunset.map(http_date)
+    }
+}
+
+/// Formats a `YYYY-MM-DD` date as an HTTP date, at midnight UTC.
+fn http_date(date: &str) -> String {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .expect("dates of deprecated endpoints must be `YYYY-MM-DD`")
+        .and_hms(0, 0, 0)
+        .format("%a, %d %b %Y %H:%M:%S GMT")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routes_match_their_requests() {
+        let endpoint = find(&Method::Get, "/api/v1/versions/12").unwrap();
+        assert_eq!(endpoint.path, "/api/v1/versions/:version_id");
+        assert_eq!(
+            find(&Method::Head, "/api/v1/versions").unwrap().path,
+            "/api/v1/versions"
+        );
+        assert_eq!(
+            find(&Method::Get, "/api/v1/versions/").unwrap().path,
+            "/api/v1/versions"
+        );
+
+        assert_eq!(find(&Method::Delete, "/api/v1/versions/12"), None);
+        assert_eq!(find(&Method::Get, "/api/v1/versions/12/authors"), None);
+        assert_eq!(find(&Method::Get, "/api/v1/crates"), None);
+    }
+
+    #[test]
+    fn all_dates_are_valid() {
+        for endpoint in DEPRECATED_ENDPOINTS {
+            endpoint.deprecation_header();
+            endpoint.sunset_header();
+        }
+        let endpoint = DEPRECATED_ENDPOINTS[0];
+        assert_eq!(
+            endpoint.deprecation_header(),
+            "Wed, 25 Mar 2020 00:00:00 GMT"
+        );
+    }
+}
@@ -0,0 +1,156 @@
+//! A cache of the versions requested through the download endpoint.
+//!
+//! `GET /crates/:crate_id/:version/download` is by far the most requested
+//! endpoint, and most of its requests are for a small set of popular
+//! versions. The cache remembers which crate file a requested version maps
+//! to, and that a version doesn't exist, so that checking a download doesn't
+//! need a database read. Concurrent requests for a version which isn't
+//! cached wait for a single lookup instead of all querying the database.
+//!
+//! The entries of a crate are dropped when it's published, yanked or
+//! quarantined by this server. Changes made elsewhere, like deleting a
+//! version with the admin binaries, take effect once the entries expire.
+//! Quarantined versions are never cached, so releasing them takes effect
+//! right away.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+
+use crate::util::errors::AppResult;
+use crate::util::LruCache;
+
+/// How long a found version is cached.
+const FOUND_TTL: Duration = Duration::from_secs(60);
+/// How long a missing version is cached. This is short, since a version
+/// published to another server doesn't invalidate the entry.
+const MISSING_TTL: Duration = Duration::from_secs(10);
+
+/// A version which can be downloaded, unless it's quarantined.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DownloadableVersion {
+    pub version_id: i32,
+    /// The name of the crate as stored in the database, which is part of the
+    /// location of the crate file.
+    pub crate_name: String,
+    pub quarantined: bool,
+}
+
+#[derive(Debug)]
+pub struct DownloadCache {
+    versions: LruCache<Option<DownloadableVersion>>,
+    /// Locks of the versions which are currently looked up.
+    in_flight: Mutex<HashMap<String, Arc<Mutex<()>>>>,
+}
+
+/// Crate names are matched like `Crate::with_name` does, so that requests
+/// for `foo-bar` and `Foo_Bar` share an entry.
+fn crate_prefix(crate_name: &str) -> String {
+    format!("{}/", crate_name.to_lowercase().replace('-', "_"))
+}
+
+impl DownloadCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            versions: LruCache::new(capacity),
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the cached version, or the one returned by `load` if it isn't
+    /// cached.
+    pub fn lookup<F>(
+        &self,
+        crate_name: &str,
+        version: &str,
+        load: F,
+    ) -> AppResult<Option<DownloadableVersion>>
+    where
+        F: FnOnce() -> AppResult<Option<DownloadableVersion>>,
+    {
+        let key = format!("{}{}", crate_prefix(crate_name), version);
+        if let Some(cached) = self.versions.get(&key) {
+            return Ok(cached);
+        }
+
+        let lock = Arc::clone(self.in_flight.lock().entry(key.clone()).or_default());
+        let loaded = {
+            let _guard = lock.lock();
+            // The version may have been loaded while this request waited.
+            match self.versions.get(&key) {
+                Some(cached) => Ok(cached),
+                None => load().map(|loaded| {
+                    self.insert(key.clone(), &loaded);
+                    loaded
+                }),
+            }
+        };
+        self.in_flight.lock().remove(&key);
+        loaded
+    }
+
+    fn insert(&self, key: String, loaded: &Option<DownloadableVersion>) {
+        match loaded {
+            Some(version) if version.quarantined => {}
+            Some(_) => self.versions.insert(key, loaded.clone(), FOUND_TTL),
+            None => self.versions.insert(key, None, MISSING_TTL),
+        }
+    }
+
+    /// Drops the cached versions of the crate.
+    pub fn invalidate_crate(&self, crate_name: &str) {
+        self.versions.remove_prefix(&crate_prefix(crate_name));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn version(quarantined: bool) -> DownloadableVersion {
+        DownloadableVersion {
+            version_id: 1,
+            crate_name: "foo_bar".into(),
+            quarantined,
+        }
+    }
+
+    #[test]
+    fn lookups_are_cached_until_invalidated() {
+        let cache = DownloadCache::new(10);
+        let loads = Cell::new(0);
+        let lookup = |crate_name: &str, loaded: Option<DownloadableVersion>| {
+            cache
+                .lookup(crate_name, "1.0.0", || {
+                    loads.set(loads.get() + 1);
+                    Ok(loaded)
+                })
+                .unwrap()
+        };
+
+        assert_eq!(
+            lookup("foo_bar", Some(version(false))),
+            Some(version(false))
+        );
+        assert_eq!(lookup("Foo-Bar", None), Some(version(false)));
+        assert_eq!(loads.get(), 1);
+
+        cache.invalidate_crate("foo-bar");
+        assert_eq!(lookup("foo_bar", None), None);
+        assert_eq!(lookup("foo_bar", Some(version(false))), None);
+        assert_eq!(loads.get(), 2);
+    }
+
+    #[test]
+    fn quarantined_versions_are_not_cached() {
+        let cache = DownloadCache::new(10);
+        let loaded = cache.lookup("foo_bar", "1.0.0", || Ok(Some(version(true))));
+        assert_eq!(loaded.unwrap(), Some(version(true)));
+
+        let loaded = cache.lookup("foo_bar", "1.0.0", || Ok(Some(version(false))));
+        assert_eq!(loaded.unwrap(), Some(version(false)));
+    }
+}
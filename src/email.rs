@@ -102,6 +102,47 @@ or go to https://crates.io/me/pending-invites to manage all of your crate owners
     let _ = send_email(email, subject, &body);
 }
 
+/// Sends the daily summary of semver-incompatible releases of dependencies to
+/// an owner who subscribed to them, and returns errors.
+pub fn send_dependency_updates_email(
+    email: &str,
+    user_name: &str,
+    updates: &[&str],
+) -> AppResult<()> {
+    let subject = "New releases of your dependencies";
+    let body = format!(
+        "Hello {}! Dependencies of your crates have new releases which don't match
+the version requirements of your crates:\n
+{}\n
+You receive these emails because you enabled dependency update notifications
+for these crates on crates.io.",
+        user_name,
+        updates.join("\n")
+    );
+
+    send_email(email, subject, &body)
+}
+
+/// Sends the crates which look squatted and wait for a decision to the
+/// admins, and returns errors.
+pub fn send_squatting_report_email(email: &str, report: &str) -> AppResult<()> {
+    let subject = "Crates reported as squatted";
+    let body = format!(
+        "These crates were reported as squatted and look unused:\n
+{}
+Resolve them with `squatting-reports resolve <crate> <outcome>`.",
+        report
+    );
+
+    send_email(email, subject, &body)
+}
+
+/// Sends an email of an admin to the owner of some crates, see the
+/// `outreach` module.
+pub fn send_outreach_email(email: &str, subject: &str, body: &str) -> AppResult<()> {
+    send_email(email, subject, body)
+}
+
 fn send_email(recipient: &str, subject: &str, body: &str) -> AppResult<()> {
     let mailgun_config = init_config_vars();
     let email = build_email(recipient, subject, body, &mailgun_config)?;
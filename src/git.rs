@@ -1,18 +1,36 @@
 #![allow(missing_debug_implementations)]
 
-use std::collections::HashMap;
+use diesel::dsl::now;
+use diesel::prelude::*;
+use serde::{Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
 use std::fs::{self, OpenOptions};
+use std::io::{Cursor, Write};
 use std::path::{Path, PathBuf};
+use std::process::Command;
 use swirl::PerformError;
-use tempfile::{Builder, TempDir};
+use tempfile::{Builder, NamedTempFile, TempDir};
 use url::Url;
 
-use crate::background_jobs::Environment;
-use crate::models::{DependencyKind, Version};
-use crate::schema::versions;
+use crate::background_jobs::{EnqueueVersioned, Environment};
+use crate::index_signing::{SigningKey, COMMITTER_EMAIL};
+use crate::models::index_config::{IndexConfig, IndexSettings, CONFIG_FILE};
+use crate::models::{
+    default_versions, webhook, CompressionFormat, DependencyKind, IndexSync, PublishJob, Version,
+    VersionAction,
+};
+use crate::schema::{crates, index_checksums, version_owner_actions, versions};
+use crate::tasks;
 
 static DEFAULT_GIT_SSH_USERNAME: &str = "git";
 
+/// The host keys GitHub publishes for `github.com`, which `git` commands
+/// pushing over SSH accept.
+static GITHUB_KNOWN_HOSTS: &str = "\
+github.com ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIOMqqnkVzrm0SdG6UOoqKLsabgH5C9okWi0dh2l9GKJl
+github.com ecdsa-sha2-nistp256 AAAAE2VjZHNhLXNoYTItbmlzdHAyNTYAAAAIbmlzdHAyNTYAAABBBEmKSENjQEezOmxkZMy7opKgwFB9nkt5YRrYMjNuG5N87uRgg6CLrbo5wAdT/y6v0mKV0U2w0WZ2YB/++Tpockg=
+";
+
 #[derive(Clone)]
 pub enum Credentials {
     Missing,
@@ -52,21 +70,111 @@ impl Credentials {
             }
         }
     }
+
+    /// A `git` command authenticated with these credentials, for what
+    /// libgit2 can't do. The returned files hold the SSH key and the known
+    /// hosts, and must be kept until the command finished.
+    fn git_command(&self) -> Result<(Command, Vec<NamedTempFile>), PerformError> {
+        let mut command = Command::new("git");
+        let mut files = Vec::new();
+        match self {
+            Credentials::Missing => {}
+            Credentials::Http { username, password } => {
+                // Passed through the environment rather than as an argument,
+                // so that it isn't visible in the process list
+                let credentials = base64::encode(&format!("{}:{}", username, password));
+                command.env(
+                    "GIT_CONFIG_PARAMETERS",
+                    format!("'http.extraHeader=Authorization: Basic {}'", credentials),
+                );
+            }
+            Credentials::Ssh { key } => {
+                let mut key_file = NamedTempFile::new()?;
+                key_file.write_all(key.as_bytes())?;
+                let mut known_hosts = NamedTempFile::new()?;
+                known_hosts.write_all(GITHUB_KNOWN_HOSTS.as_bytes())?;
+                command.env(
+                    "GIT_SSH_COMMAND",
+                    format!(
+                        "ssh -i {} -o IdentitiesOnly=yes -o UserKnownHostsFile={} \
+                         -o StrictHostKeyChecking=yes",
+                        key_file.path().display(),
+                        known_hosts.path().display()
+                    ),
+                );
+                files.push(key_file);
+                files.push(known_hosts);
+            }
+        }
+        Ok((command, files))
+    }
+
+    fn run_git(&self, dir: &Path, args: &[&str]) -> Result<(), PerformError> {
+        let (mut command, _files) = self.git_command()?;
+        let output = command.current_dir(dir).args(args).output()?;
+        if !output.status.success() {
+            return Err(format!(
+                "`git {}` failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+            .into());
+        }
+        Ok(())
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// An index entry, one line of the index file of a crate.
+///
+/// `rust_version` and `yanked_reason` are only written with `INDEX_V3_FIELDS`,
+/// see `Repository::encode_entry`. Older versions of cargo ignore them.
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Crate {
     pub name: String,
     pub vers: String,
     pub deps: Vec<Dependency>,
     pub cksum: String,
+    #[serde(serialize_with = "sorted_features")]
     pub features: HashMap<String, Vec<String>>,
     pub yanked: Option<bool>,
     #[serde(default)]
     pub links: Option<String>,
+    /// How the crate file is compressed, left out for gzip, which is what
+    /// cargo assumes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<CompressionFormat>,
+    /// The `package.rust-version` value of the manifest.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rust_version: Option<String>,
+    /// Why a yanked version was yanked, as given by its owners.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub yanked_reason: Option<String>,
+}
+
+impl Crate {
+    /// The entry as it is written without `INDEX_V3_FIELDS`.
+    pub fn without_v3_fields(&self) -> Self {
+        Self {
+            rust_version: None,
+            yanked_reason: None,
+            ..self.clone()
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Serializes the features in the order of their names, so that serializing
+/// an index entry again, e.g. when it's yanked, doesn't reorder them.
+fn sorted_features<S: Serializer>(
+    features: &HashMap<String, Vec<String>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    features
+        .iter()
+        .collect::<BTreeMap<_, _>>()
+        .serialize(serializer)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Dependency {
     pub name: String,
     pub req: String,
@@ -82,6 +190,18 @@ pub struct Dependency {
 pub struct RepositoryConfig {
     pub index_location: Url,
     pub credentials: Credentials,
+    /// The key commits are signed with, from `INDEX_SIGNING_KEY`.
+    pub signing_key: Option<SigningKey>,
+    /// Whether index entries include `rust_version` and `yanked_reason`, from
+    /// `INDEX_V3_FIELDS`.
+    pub v3_fields: bool,
+    /// The number of commits to clone, from `GIT_CLONE_DEPTH`. The full
+    /// history is fetched once it's needed, like for squashing it.
+    pub clone_depth: Option<u32>,
+    /// Whether only the files at the root of the index are checked out, from
+    /// `GIT_SPARSE_CHECKOUT`. The index files of crates are checked out when
+    /// they're read or written.
+    pub sparse_checkout: bool,
 }
 
 impl RepositoryConfig {
@@ -93,7 +213,12 @@ impl RepositoryConfig {
         let ssh_key = dotenv::var("GIT_SSH_KEY");
         let ssh_url = dotenv::var("GIT_SSH_REPO_URL");
 
-        match (username, password, http_url, ssh_key, ssh_url) {
+        let signing_key = dotenv::var("INDEX_SIGNING_KEY").ok().map(|pem| {
+            SigningKey::from_pem(pem.as_bytes())
+                .unwrap_or_else(|e| panic!("failed to parse INDEX_SIGNING_KEY: {}", e))
+        });
+
+        let (index_location, credentials) = match (username, password, http_url, ssh_key, ssh_url) {
             (extra_user, extra_pass, extra_http_url, Ok(encoded_key), Ok(ssh_url)) => {
                 if let (Ok(_), Ok(_), Ok(_)) = (extra_user, extra_pass, extra_http_url) {
                     println!(
@@ -112,30 +237,34 @@ impl RepositoryConfig {
                     .expect("failed to convert the ssh key to a string"),
                 };
 
-                Self {
-                    index_location,
-                    credentials,
-                }
+                (index_location, credentials)
             }
             (Ok(username), Ok(password), Ok(http_url), Err(_), Err(_)) => {
                 let index_location = Url::parse(&http_url).expect("failed to parse GIT_REPO_URL");
                 let credentials = Credentials::Http { username, password };
 
-                Self {
-                    index_location,
-                    credentials,
-                }
+                (index_location, credentials)
             }
             (_, _, Ok(http_url), _, _) => {
                 let index_location = Url::parse(&http_url).expect("failed to parse GIT_REPO_URL");
                 let credentials = Credentials::Missing;
 
-                Self {
-                    index_location,
-                    credentials,
-                }
+                (index_location, credentials)
             }
             _ => panic!("must have `GIT_REPO_URL` defined"),
+        };
+
+        Self {
+            index_location,
+            credentials,
+            signing_key,
+            v3_fields: dotenv::var("INDEX_V3_FIELDS").is_ok(),
+            clone_depth: dotenv::var("GIT_CLONE_DEPTH").ok().map(|depth| {
+                depth
+                    .parse()
+                    .expect("failed to parse GIT_CLONE_DEPTH as a number of commits")
+            }),
+            sparse_checkout: dotenv::var("GIT_SPARSE_CHECKOUT").is_ok(),
         }
     }
 }
@@ -144,48 +273,122 @@ pub struct Repository {
     checkout_path: TempDir,
     repository: git2::Repository,
     credentials: Credentials,
+    signing_key: Option<SigningKey>,
+    v3_fields: bool,
+    sparse_checkout: bool,
 }
 
 impl Repository {
     pub fn open(repository_config: &RepositoryConfig) -> Result<Self, PerformError> {
         let checkout_path = Builder::new().prefix("git").tempdir()?;
 
-        let repository = git2::build::RepoBuilder::new()
-            .fetch_options(Self::fetch_options(&repository_config.credentials))
-            .clone(
-                repository_config.index_location.as_str(),
-                checkout_path.path(),
-            )?;
+        let repository =
+            if repository_config.clone_depth.is_some() || repository_config.sparse_checkout {
+                // libgit2 can clone neither shallow nor sparse
+                let mut args = vec!["clone".to_string(), "--quiet".to_string()];
+                if let Some(depth) = repository_config.clone_depth {
+                    args.push(format!("--depth={}", depth));
+                }
+                if repository_config.sparse_checkout {
+                    args.push("--sparse".to_string());
+                }
+                args.push(repository_config.index_location.to_string());
+                args.push(".".to_string());
+                let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+                repository_config
+                    .credentials
+                    .run_git(checkout_path.path(), &args)?;
+                git2::Repository::open(checkout_path.path())?
+            } else {
+                git2::build::RepoBuilder::new()
+                    .fetch_options(Self::fetch_options(&repository_config.credentials))
+                    .clone(
+                        repository_config.index_location.as_str(),
+                        checkout_path.path(),
+                    )?
+            };
 
         // All commits to the index registry made through crates.io will be made by bors, the Rust
         // community's friendly GitHub bot.
         let mut cfg = repository.config()?;
         cfg.set_str("user.name", "bors")?;
-        cfg.set_str("user.email", "bors@rust-lang.org")?;
+        cfg.set_str("user.email", COMMITTER_EMAIL)?;
 
         Ok(Self {
             checkout_path,
             repository,
             credentials: repository_config.credentials.clone(),
+            signing_key: repository_config.signing_key.clone(),
+            v3_fields: repository_config.v3_fields,
+            sparse_checkout: repository_config.sparse_checkout,
         })
     }
 
-    fn index_file(&self, name: &str) -> PathBuf {
-        self.checkout_path
-            .path()
-            .join(self.relative_index_file(name))
+    /// Whether index entries are written with `rust_version` and
+    /// `yanked_reason`.
+    pub fn writes_v3_fields(&self) -> bool {
+        self.v3_fields
     }
 
-    fn relative_index_file(&self, name: &str) -> PathBuf {
-        let name = name.to_lowercase();
-        match name.len() {
-            1 => Path::new("1").join(&name),
-            2 => Path::new("2").join(&name),
-            3 => Path::new("3").join(&name[..1]).join(&name),
-            _ => Path::new(&name[0..2]).join(&name[2..4]).join(&name),
+    /// Encodes an index entry as a line of an index file, without the
+    /// trailing newline.
+    pub fn encode_entry(&self, krate: &Crate) -> serde_json::Result<String> {
+        if self.v3_fields {
+            serde_json::to_string(krate)
+        } else {
+            serde_json::to_string(&krate.without_v3_fields())
         }
     }
 
+    /// The path of the index file of a crate in the checkout. With a sparse
+    /// checkout, the file is checked out first, if the crate has one.
+    fn index_file(&self, name: &str) -> Result<PathBuf, PerformError> {
+        let relative_path = self.relative_index_file(name);
+        let path = self.checkout_path.path().join(&relative_path);
+        if self.sparse_checkout && !path.exists() {
+            let tree = self.repository.head()?.peel_to_commit()?.tree()?;
+            match tree.get_path(&relative_path) {
+                Ok(entry) => {
+                    let blob = self.repository.find_blob(entry.id())?;
+                    fs::create_dir_all(path.parent().unwrap())?;
+                    fs::write(&path, blob.content())?;
+                }
+                Err(e) if e.code() == git2::ErrorCode::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(path)
+    }
+
+    /// Returns the path of a crate's index file, relative to the root of the
+    /// index.
+    pub fn relative_index_file(&self, name: &str) -> PathBuf {
+        PathBuf::from(relative_index_path(name))
+    }
+
+    /// Reads the index file of a crate, which is empty if the crate has none.
+    pub fn read_index_file(&self, name: &str) -> Result<String, PerformError> {
+        let path = self.index_file(name)?;
+        match fs::read_to_string(&path) {
+            Ok(contents) => Ok(contents),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+            Err(e) => Err(format!("failed to read {}: {}", path.display(), e).into()),
+        }
+    }
+
+    /// Reads the index entries of all versions of a crate, in the order in
+    /// which they were published.
+    pub fn crate_versions(&self, name: &str) -> Result<Vec<Crate>, PerformError> {
+        let path = self.index_file(name)?;
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        contents
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_str(line).map_err(Into::into))
+            .collect()
+    }
+
     fn perform_commit_and_push(&self, msg: &str, modified_file: &Path) -> Result<(), PerformError> {
         // git add $file
         let mut index = self.repository.index()?;
@@ -195,13 +398,48 @@ impl Repository {
         let tree = self.repository.find_tree(tree_id)?;
 
         // git commit -m "..."
-        let head = self.repository.head()?;
+        let mut head = self.repository.head()?;
         let parent = self.repository.find_commit(head.target().unwrap())?;
-        let sig = self.repository.signature()?;
-        self.repository
-            .commit(Some("HEAD"), &sig, &sig, &msg, &tree, &[&parent])?;
+        let commit = self.create_commit(msg, &tree, &[&parent])?;
+        head.set_target(commit, msg)?;
 
         // git push
+        self.push("refs/heads/master")
+    }
+
+    /// Creates a commit without updating any ref, signed if a signing key is
+    /// configured.
+    fn create_commit(
+        &self,
+        message: &str,
+        tree: &git2::Tree<'_>,
+        parents: &[&git2::Commit<'_>],
+    ) -> Result<git2::Oid, PerformError> {
+        let sig = self.repository.signature()?;
+        let signing_key = match &self.signing_key {
+            Some(signing_key) => signing_key,
+            None => {
+                return Ok(self
+                    .repository
+                    .commit(None, &sig, &sig, message, tree, parents)?)
+            }
+        };
+        let buffer = self
+            .repository
+            .commit_create_buffer(&sig, &sig, message, tree, parents)?;
+        let content = buffer
+            .as_str()
+            .ok_or("the commit to sign is not valid UTF-8")?;
+        let signature = signing_key.sign(content.as_bytes())?;
+        Ok(self
+            .repository
+            .commit_signed(content, &signature, Some("gpgsig"))?)
+    }
+
+    /// Pushes a single ref to the index, which is force-pushed if the
+    /// refspec starts with a `+`.
+    fn push(&self, refspec: &str) -> Result<(), PerformError> {
+        let refname = refspec.trim_start_matches('+');
         let mut ref_status = Ok(());
         let mut callback_called = false;
         {
@@ -210,8 +448,8 @@ impl Repository {
             callbacks.credentials(|_, user_from_url, cred_type| {
                 self.credentials.git2_callback(user_from_url, cred_type)
             });
-            callbacks.push_update_reference(|refname, status| {
-                assert_eq!(refname, "refs/heads/master");
+            callbacks.push_update_reference(|pushed, status| {
+                assert_eq!(pushed, refname);
                 if let Some(s) = status {
                     ref_status = Err(format!("failed to push a ref: {}", s).into())
                 }
@@ -220,7 +458,7 @@ impl Repository {
             });
             let mut opts = git2::PushOptions::new();
             opts.remote_callbacks(callbacks);
-            origin.push(&["refs/heads/master"], Some(&mut opts))?;
+            origin.push(&[refspec], Some(&mut opts))?;
         }
 
         if !callback_called {
@@ -241,6 +479,65 @@ impl Repository {
             })
     }
 
+    /// Fetches the full history of a shallow clone.
+    fn unshallow(&self) -> Result<(), PerformError> {
+        if self.repository.is_shallow() {
+            self.credentials.run_git(
+                self.checkout_path.path(),
+                &["fetch", "--quiet", "--unshallow", "origin"],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Counts the commits in the history of `HEAD`, up to `limit`.
+    pub fn count_commits(&self, limit: usize) -> Result<usize, PerformError> {
+        self.unshallow()?;
+        let mut revwalk = self.repository.revwalk()?;
+        revwalk.push_head()?;
+        let mut count = 0;
+        for oid in revwalk.take(limit) {
+            oid?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Replaces the history of the index with a single commit of its current
+    /// tree, and returns the ids of the previous and the new `HEAD`.
+    ///
+    /// The previous history is pushed to `snapshot_branch` first, which must
+    /// not exist yet, and `master` is only force-pushed once that succeeded.
+    pub fn squash(
+        &self,
+        snapshot_branch: &str,
+        message: &str,
+    ) -> Result<(String, String), PerformError> {
+        // The snapshot has to include the full history
+        self.unshallow()?;
+        let previous = self.repository.head()?.peel_to_commit()?;
+        let snapshot_ref = format!("refs/heads/{}", snapshot_branch);
+        self.repository
+            .reference(&snapshot_ref, previous.id(), false, "index snapshot")?;
+        self.push(&snapshot_ref)?;
+
+        let squashed = self.create_commit(message, &previous.tree()?, &[])?;
+        self.repository
+            .reference("refs/heads/master", squashed, true, "squash index")?;
+        if let Err(e) = self.push("+refs/heads/master") {
+            // Keep the checkout in line with the index, which still has the
+            // previous history
+            self.repository.reference(
+                "refs/heads/master",
+                previous.id(),
+                true,
+                "squash index failed",
+            )?;
+            return Err(e);
+        }
+        Ok((previous.id().to_string(), squashed.to_string()))
+    }
+
     pub fn reset_head(&self) -> Result<(), PerformError> {
         let mut origin = self.repository.find_remote("origin")?;
         origin.fetch(
@@ -250,7 +547,12 @@ impl Repository {
         )?;
         let head = self.repository.head()?.target().unwrap();
         let obj = self.repository.find_object(head, None)?;
-        self.repository.reset(&obj, git2::ResetType::Hard, None)?;
+        // libgit2 doesn't know about sparse checkouts, so only the files which
+        // are checked out already are updated
+        let mut checkout = git2::build::CheckoutBuilder::new();
+        checkout.update_only(self.sparse_checkout);
+        self.repository
+            .reset(&obj, git2::ResetType::Hard, Some(&mut checkout))?;
         Ok(())
     }
 
@@ -265,22 +567,183 @@ impl Repository {
     }
 }
 
+/// Returns the path of a crate's index file relative to the root of the
+/// index, separated by `/` as in the URLs the index is served from.
+pub fn relative_index_path(name: &str) -> String {
+    let name = name.to_lowercase();
+    match name.len() {
+        1 => format!("1/{}", name),
+        2 => format!("2/{}", name),
+        3 => format!("3/{}/{}", &name[..1], name),
+        _ => format!("{}/{}/{}", &name[0..2], &name[2..4], name),
+    }
+}
+
 #[swirl::background_job]
 pub fn add_crate(env: &Environment, krate: Crate) -> Result<(), PerformError> {
-    use std::io::prelude::*;
+    let _heartbeat = env.heartbeat("add_crate")?;
+    add_to_index(env, &krate)
+}
+
+/// Uploads the crate file of a publish with `?queued=true` and adds the crate
+/// to the index. The outcome is recorded for
+/// `GET /crates/:crate_id/:version/publish-status`, and like any other job
+/// a failed attempt is retried, so a failure is only final if the job is
+/// removed from the queue.
+#[swirl::background_job]
+pub fn add_queued_crate(
+    env: &Environment,
+    publish_job_id: i32,
+    krate: Crate,
+) -> Result<(), PerformError> {
+    let _heartbeat = env.heartbeat("add_queued_crate")?;
+    let staged_path = PublishJob::staged_path(&*env.connection()?, publish_job_id)?;
+    let result = upload_queued_crate(env, staged_path.as_deref(), &krate)
+        .and_then(|()| add_to_index(env, &krate));
+
+    let conn = env.connection()?;
+    match result {
+        Ok(()) => {
+            PublishJob::finish(&conn, publish_job_id)?;
+            if let Some(staged_path) = staged_path {
+                // A leftover staged file doesn't affect the publish
+                if let Err(e) = env
+                    .uploader
+                    .storage()
+                    .delete(env.http_client(), &staged_path)
+                {
+                    eprintln!("Failed to delete {}: {}", staged_path, e);
+                }
+            }
+            Ok(())
+        }
+        Err(e) => {
+            PublishJob::fail(&conn, publish_job_id, &e.to_string())?;
+            Err(e)
+        }
+    }
+}
+
+/// The staged crate file is only removed once the crate is in the index, so
+/// a retry after a failed index update uploads it again.
+fn upload_queued_crate(
+    env: &Environment,
+    staged_path: Option<&str>,
+    krate: &Crate,
+) -> Result<(), PerformError> {
+    if let Some(staged_path) = staged_path {
+        let tarball = env
+            .uploader
+            .storage()
+            .read(env.http_client(), staged_path)
+            .map_err(|e| e.to_string())?;
+        let content_length = tarball.len() as u64;
+        env.uploader
+            .store_crate_file(
+                env.http_client(),
+                &krate.name,
+                &krate.vers,
+                Cursor::new(tarball),
+                content_length,
+            )
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
 
+/// Pushes the index entry of a new version, and records when that happened
+/// or that it failed for the index sync metrics.
+fn add_to_index(env: &Environment, krate: &Crate) -> Result<(), PerformError> {
+    let result = push_index_entry(env, krate);
+    let conn = env.connection()?;
+    IndexSync::record_indexed(&conn, &krate.name, &krate.vers, result.is_ok())?;
+    result
+}
+
+fn push_index_entry(env: &Environment, krate: &Crate) -> Result<(), PerformError> {
     let repo = env.lock_index()?;
-    let dst = repo.index_file(&krate.name);
+    let dst = repo.index_file(&krate.name)?;
 
     // Add the crate to its relevant file
     fs::create_dir_all(dst.parent().unwrap())?;
     let mut file = OpenOptions::new().append(true).create(true).open(&dst)?;
-    serde_json::to_writer(&mut file, &krate)?;
+    file.write_all(repo.encode_entry(krate)?.as_bytes())?;
     file.write_all(b"\n")?;
 
     let message: String = format!("Updating crate `{}#{}`", krate.name, krate.vers);
 
-    repo.commit_and_push(&message, &repo.relative_index_file(&krate.name))
+    repo.commit_and_push(&message, &repo.relative_index_file(&krate.name))?;
+
+    let conn = env.connection()?;
+    let crate_id = crates::table
+        .filter(crates::name.eq(&krate.name))
+        .select(crates::id)
+        .first(&*conn)
+        .optional()?;
+    if let Some(crate_id) = crate_id {
+        record_index_checksum(&conn, crate_id, &fs::read(&dst)?)?;
+    }
+    Ok(())
+}
+
+/// Writes the `config.json` file at the root of the index from the index
+/// configuration in the database, which is managed with the `index-config`
+/// binary.
+#[swirl::background_job]
+pub fn update_index_config(env: &Environment) -> Result<(), PerformError> {
+    let _heartbeat = env.heartbeat("update_index_config")?;
+    let settings = IndexSettings::from_environment(&env.uploader)?;
+    let conn = env.connection()?;
+    let config = IndexConfig::load(&conn, &settings)?.to_json()?;
+
+    let repo = env.lock_index()?;
+    let dst = repo.checkout_path.path().join(CONFIG_FILE);
+    if fs::read_to_string(&dst).ok().as_deref() == Some(config.as_str()) {
+        return Ok(());
+    }
+    fs::write(&dst, config.as_bytes())?;
+
+    repo.commit_and_push(
+        "Updating the registry configuration",
+        Path::new(CONFIG_FILE),
+    )
+}
+
+/// Replaces the index file of a crate, e.g. one rebuilt from the database,
+/// and records its new checksum.
+pub fn replace_index_file(
+    repo: &Repository,
+    conn: &PgConnection,
+    crate_id: i32,
+    name: &str,
+    contents: &str,
+    message: &str,
+) -> Result<(), PerformError> {
+    let dst = repo.index_file(name)?;
+    fs::create_dir_all(dst.parent().unwrap())?;
+    fs::write(&dst, contents)?;
+    repo.commit_and_push(message, &repo.relative_index_file(name))?;
+    record_index_checksum(conn, crate_id, contents.as_bytes())?;
+    Ok(())
+}
+
+/// Records the checksum of a crate's index file after it has been pushed,
+/// which is served by `/api/v1/index-checksums`.
+fn record_index_checksum(conn: &PgConnection, crate_id: i32, contents: &[u8]) -> QueryResult<()> {
+    let checksum = hex::encode(openssl::sha::sha256(contents));
+    diesel::insert_into(index_checksums::table)
+        .values((
+            index_checksums::crate_id.eq(crate_id),
+            index_checksums::checksum.eq(&checksum),
+        ))
+        .on_conflict(index_checksums::crate_id)
+        .do_update()
+        .set((
+            index_checksums::checksum.eq(&checksum),
+            index_checksums::updated_at.eq(now),
+        ))
+        .execute(conn)?;
+    Ok(())
 }
 
 /// Yanks or unyanks a crate version. This requires finding the index
@@ -294,10 +757,9 @@ pub fn yank(
     version: Version,
     yanked: bool,
 ) -> Result<(), PerformError> {
-    use diesel::prelude::*;
-
+    let _heartbeat = env.heartbeat("yank")?;
     let repo = env.lock_index()?;
-    let dst = repo.index_file(&krate);
+    let dst = repo.index_file(&krate)?;
 
     let conn = env.connection()?;
 
@@ -314,20 +776,14 @@ pub fn yank(
         }
 
         let prev = fs::read_to_string(&dst)?;
-        let version_num = version.num.to_string();
-        let new = prev
-            .lines()
-            .map(|line| {
-                let mut git_crate = serde_json::from_str::<Crate>(line)
-                    .map_err(|_| format!("couldn't decode: `{}`", line))?;
-                if git_crate.name != krate || git_crate.vers != version_num {
-                    return Ok(line.to_string());
-                }
-                git_crate.yanked = Some(yanked);
-                Ok(serde_json::to_string(&git_crate)?)
-            })
-            .collect::<Result<Vec<_>, PerformError>>();
-        let new = new?.join("\n") + "\n";
+        let reason = yank_reasons(&conn, &[version.id])?.remove(&version.id);
+        let new = set_yanked(
+            &repo,
+            &prev,
+            &krate,
+            &[(version.num.to_string(), reason)],
+            yanked,
+        )?;
         fs::write(&dst, new.as_bytes())?;
 
         let message: String = format!(
@@ -338,11 +794,150 @@ pub fn yank(
         );
 
         repo.commit_and_push(&message, &repo.relative_index_file(&krate))?;
+        record_index_checksum(&conn, version.crate_id, new.as_bytes())?;
 
         diesel::update(&version)
             .set(versions::yanked.eq(yanked))
             .execute(&*conn)?;
 
+        default_versions::update_default_version(version.crate_id, &conn)?;
+        notify_webhooks(&conn, &krate, &[version.num.to_string()], yanked)?;
+
         Ok(())
     })
 }
+
+/// Yanks or unyanks several versions of a crate with a single commit.
+/// Versions which are already in the requested state are skipped.
+#[swirl::background_job]
+pub fn yank_bulk(
+    env: &Environment,
+    krate: String,
+    version_ids: Vec<i32>,
+    yanked: bool,
+) -> Result<(), PerformError> {
+    let _heartbeat = env.heartbeat("yank_bulk")?;
+    let repo = env.lock_index()?;
+    let dst = repo.index_file(&krate)?;
+
+    let conn = env.connection()?;
+
+    conn.transaction(|| {
+        let versions = versions::table
+            .filter(versions::id.eq_any(&version_ids))
+            .filter(versions::yanked.ne(yanked))
+            .for_update()
+            .load::<Version>(&*conn)?;
+        let crate_id = match versions.first() {
+            Some(version) => version.crate_id,
+            // All versions are already in the state requested
+            None => return Ok(()),
+        };
+
+        let prev = fs::read_to_string(&dst)?;
+        let nums = versions
+            .iter()
+            .map(|version| version.num.to_string())
+            .collect::<Vec<_>>();
+        let mut reasons = yank_reasons(&conn, &version_ids)?;
+        let changes = versions
+            .iter()
+            .map(|version| (version.num.to_string(), reasons.remove(&version.id)))
+            .collect::<Vec<_>>();
+        let new = set_yanked(&repo, &prev, &krate, &changes, yanked)?;
+        fs::write(&dst, new.as_bytes())?;
+
+        let message = format!(
+            "{} {} versions of crate `{}`",
+            if yanked { "Yanking" } else { "Unyanking" },
+            versions.len(),
+            krate
+        );
+
+        repo.commit_and_push(&message, &repo.relative_index_file(&krate))?;
+        record_index_checksum(&conn, crate_id, new.as_bytes())?;
+
+        let ids = versions
+            .iter()
+            .map(|version| version.id)
+            .collect::<Vec<_>>();
+        diesel::update(versions::table.filter(versions::id.eq_any(ids)))
+            .set(versions::yanked.eq(yanked))
+            .execute(&*conn)?;
+
+        default_versions::update_default_version(crate_id, &conn)?;
+        notify_webhooks(&conn, &krate, &nums, yanked)?;
+
+        Ok(())
+    })
+}
+
+/// Lets the webhooks of service consumers know that versions were yanked or
+/// unyanked, now that the index says so.
+fn notify_webhooks(
+    conn: &PgConnection,
+    krate: &str,
+    nums: &[String],
+    yanked: bool,
+) -> Result<(), PerformError> {
+    let mut deliveries = 0;
+    for num in nums {
+        deliveries += webhook::notify_yank(conn, krate, num, yanked)?;
+    }
+    if deliveries > 0 {
+        tasks::deliver_webhooks().enqueue_versioned(conn)?;
+    }
+    Ok(())
+}
+
+/// The reasons of the latest yanks of the given versions, as recorded in
+/// their audit actions. Versions which were yanked without a reason are left
+/// out.
+pub(crate) fn yank_reasons(
+    conn: &PgConnection,
+    version_ids: &[i32],
+) -> QueryResult<HashMap<i32, String>> {
+    let actions = version_owner_actions::table
+        .filter(version_owner_actions::version_id.eq_any(version_ids))
+        .filter(version_owner_actions::action.eq(VersionAction::Yank))
+        .order((version_owner_actions::time, version_owner_actions::id))
+        .select((
+            version_owner_actions::version_id,
+            version_owner_actions::reason,
+        ))
+        .load::<(i32, Option<String>)>(conn)?;
+    let latest = actions.into_iter().collect::<HashMap<_, _>>();
+    Ok(latest
+        .into_iter()
+        .filter_map(|(version_id, reason)| Some((version_id, reason?)))
+        .collect())
+}
+
+/// Sets the `yanked` field of the given versions in the contents of an index
+/// file, along with the reason of the yank, given by version number.
+fn set_yanked(
+    repo: &Repository,
+    index_file: &str,
+    krate: &str,
+    changes: &[(String, Option<String>)],
+    yanked: bool,
+) -> Result<String, PerformError> {
+    let new = index_file
+        .lines()
+        .map(|line| {
+            let mut git_crate = serde_json::from_str::<Crate>(line)
+                .map_err(|_| format!("couldn't decode: `{}`", line))?;
+            if git_crate.name != krate {
+                return Ok(line.to_string());
+            }
+            let reason = match changes.iter().find(|(num, _)| *num == git_crate.vers) {
+                Some((_, reason)) => reason,
+                None => return Ok(line.to_string()),
+            };
+            git_crate.yanked = Some(yanked);
+            git_crate.yanked_reason = reason.clone().filter(|_| yanked);
+            Ok(repo.encode_entry(&git_crate)?)
+        })
+        .collect::<Result<Vec<_>, PerformError>>();
+    Ok(new?.join("\n") + "\n")
+}
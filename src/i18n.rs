@@ -0,0 +1,222 @@
+//! A catalog of user-facing API messages and `Accept-Language` negotiation.
+//!
+//! Every message in the catalog has a stable `code` that is included in error
+//! responses, so that clients can react to specific errors without matching
+//! on the (possibly translated) English text.
+//!
+//! Besides English, a pseudo-locale (`x-pseudo`) is available. It decorates
+//! the English text in a recognizable way, which makes it easy to spot strings
+//! that bypass the catalog.
+
+use conduit::Request;
+
+use crate::util::request_header;
+
+/// A locale that messages can be rendered in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    En,
+    Pseudo,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Locale::En
+    }
+}
+
+impl Locale {
+    /// The language tag sent back in the `Content-Language` header.
+    pub fn tag(self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Pseudo => "x-pseudo",
+        }
+    }
+
+    fn from_tag(tag: &str) -> Option<Self> {
+        let tag = tag.to_ascii_lowercase();
+        if tag == "x-pseudo" {
+            return Some(Locale::Pseudo);
+        }
+        match tag.split('-').next() {
+            Some("en") => Some(Locale::En),
+            _ => None,
+        }
+    }
+
+    /// Picks the best supported locale for an `Accept-Language` header value.
+    ///
+    /// Language ranges are ordered by their quality value, ties are broken by
+    /// the order in which they appear. If none of the ranges is supported,
+    /// the default locale (English) is returned.
+    pub fn negotiate(accept_language: &str) -> Self {
+        let mut ranges = accept_language
+            .split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';').map(str::trim);
+                let tag = parts.next().filter(|tag| !tag.is_empty())?;
+                let quality = parts
+                    .find(|param| param.starts_with("q="))
+                    .map(|param| &param[2..])
+                    .map_or(Some(1.0), |q| q.parse::<f32>().ok())?;
+                Some((tag, quality))
+            })
+            .filter(|&(_, quality)| quality > 0.0)
+            .collect::<Vec<_>>();
+
+        // `sort_by` is stable, so equally weighted ranges keep their order
+        ranges.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+
+        ranges
+            .into_iter()
+            .filter(|&(tag, _)| tag != "*")
+            .find_map(|(tag, _)| Self::from_tag(tag))
+            .unwrap_or_default()
+    }
+
+    /// Picks the best supported locale for the given request.
+    pub fn from_request(req: &dyn Request) -> Self {
+        Self::negotiate(request_header(req, "Accept-Language"))
+    }
+}
+
+/// A user-facing message from the catalog.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    NotFound,
+    Unauthorized,
+    ReadOnlyMode,
+    TooManyRequests { retry_after: String },
+}
+
+impl Message {
+    /// A stable, machine readable identifier for the message.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Message::NotFound => "not_found",
+            Message::Unauthorized => "unauthorized",
+            Message::ReadOnlyMode => "read_only_mode",
+            Message::TooManyRequests { .. } => "too_many_requests",
+        }
+    }
+
+    /// Renders the message in the given locale.
+    pub fn render(&self, locale: Locale) -> String {
+        let template = match locale {
+            Locale::En => self.english().to_string(),
+            Locale::Pseudo => pseudo_localize(self.english()),
+        };
+        self.interpolate(template)
+    }
+
+    fn english(&self) -> &'static str {
+        match self {
+            Message::NotFound => "Not Found",
+            Message::Unauthorized => "must be logged in to perform that action",
+            Message::ReadOnlyMode => {
+                "Crates.io is currently in read-only mode for maintenance. \
+                 Please try again later."
+            }
+            Message::TooManyRequests { .. } => {
+                "You have published too many crates in a \
+                 short period of time. Please try again after {retry_after} or email \
+                 help@crates.io to have your limit increased."
+            }
+        }
+    }
+
+    fn interpolate(&self, template: String) -> String {
+        match self {
+            Message::TooManyRequests { retry_after } => {
+                template.replace("{retry_after}", retry_after)
+            }
+            _ => template,
+        }
+    }
+}
+
+/// Accents all ASCII vowels outside of `{placeholders}` and brackets the
+/// result, e.g. `Not Found` becomes `[Nót Fóúnd]`.
+fn pseudo_localize(text: &str) -> String {
+    let mut result = String::with_capacity(text.len() + 8);
+    let mut in_placeholder = false;
+    result.push('[');
+    for c in text.chars() {
+        let c = match c {
+            '{' => {
+                in_placeholder = true;
+                c
+            }
+            '}' => {
+                in_placeholder = false;
+                c
+            }
+            _ if in_placeholder => c,
+            'a' => 'á',
+            'e' => 'é',
+            'i' => 'í',
+            'o' => 'ó',
+            'u' => 'ú',
+            'A' => 'Á',
+            'E' => 'É',
+            'I' => 'Í',
+            'O' => 'Ó',
+            'U' => 'Ú',
+            _ => c,
+        };
+        result.push(c);
+    }
+    result.push(']');
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negotiate_defaults_to_english() {
+        assert_eq!(Locale::negotiate(""), Locale::En);
+        assert_eq!(Locale::negotiate("*"), Locale::En);
+        assert_eq!(Locale::negotiate("de-CH, fr;q=0.9"), Locale::En);
+        assert_eq!(Locale::negotiate("garbage;q=nope"), Locale::En);
+    }
+
+    #[test]
+    fn negotiate_respects_quality_values() {
+        assert_eq!(Locale::negotiate("en-US"), Locale::En);
+        assert_eq!(Locale::negotiate("x-pseudo"), Locale::Pseudo);
+        assert_eq!(Locale::negotiate("en;q=0.5, x-pseudo"), Locale::Pseudo);
+        assert_eq!(Locale::negotiate("x-pseudo;q=0.4, en;q=0.8"), Locale::En);
+        assert_eq!(Locale::negotiate("de, X-Pseudo;q=0.1"), Locale::Pseudo);
+        assert_eq!(Locale::negotiate("x-pseudo;q=0, en"), Locale::En);
+    }
+
+    #[test]
+    fn negotiate_keeps_order_for_equal_quality() {
+        assert_eq!(Locale::negotiate("x-pseudo, en"), Locale::Pseudo);
+        assert_eq!(Locale::negotiate("en, x-pseudo"), Locale::En);
+    }
+
+    #[test]
+    fn pseudo_locale_keeps_placeholders() {
+        let message = Message::TooManyRequests {
+            retry_after: "Tue, 07 Jan 2020 10:00:00 GMT".into(),
+        };
+        let rendered = message.render(Locale::Pseudo);
+        assert!(rendered.starts_with("[Yóú hávé públíshéd"));
+        assert!(rendered.contains("Tue, 07 Jan 2020 10:00:00 GMT"));
+        assert!(rendered.ends_with(']'));
+    }
+
+    #[test]
+    fn english_messages() {
+        assert_eq!(Message::NotFound.render(Locale::En), "Not Found");
+        assert_eq!(Message::NotFound.render(Locale::Pseudo), "[Nót Fóúnd]");
+        assert_eq!(
+            Message::Unauthorized.render(Locale::En),
+            "must be logged in to perform that action"
+        );
+    }
+}
@@ -0,0 +1,219 @@
+//! SSH signatures of the commits to the index
+//!
+//! With `INDEX_SIGNING_KEY` set to a PEM encoded Ed25519 private key, as
+//! generated by `openssl genpkey -algorithm ed25519`, every commit pushed to
+//! the index is signed the way git signs commits with `gpg.format = ssh`.
+//! Anyone can check the history with
+//!
+//! ```text
+//! curl https://crates.io/api/v1/index-signing-keys/allowed_signers > allowed_signers
+//! git -c gpg.ssh.allowedSignersFile=allowed_signers log --show-signature
+//! ```
+//!
+//! The key is rotated by changing the variable. The background worker
+//! records every key it signs with when it boots, and each key is listed as
+//! valid from then until `ROTATION_OVERLAP` after the next key was first
+//! used, which leaves workers still running with the previous key time to
+//! shut down.
+
+use chrono::{Duration, NaiveDateTime};
+use openssl::error::ErrorStack;
+use openssl::pkey::{Id, PKey, Private};
+use openssl::sha::{sha256, sha512};
+use openssl::sign::Signer;
+
+/// The identity the commits are made with, which is the principal of the
+/// keys in the `allowed_signers` file.
+pub const COMMITTER_EMAIL: &str = "bors@rust-lang.org";
+
+/// The namespace git signs commits in.
+const NAMESPACE: &str = "git";
+
+const KEY_TYPE: &str = "ssh-ed25519";
+
+/// How many minutes a rotated key stays valid after its successor was first
+/// used.
+pub const ROTATION_OVERLAP: i64 = 60;
+
+/// A key commits to the index are signed with.
+#[derive(Clone)]
+pub struct SigningKey {
+    key: PKey<Private>,
+    /// The public key in the SSH wire format.
+    public_key: Vec<u8>,
+}
+
+impl SigningKey {
+    pub fn from_pem(pem: &[u8]) -> Result<Self, String> {
+        let key = PKey::private_key_from_pem(pem).map_err(|e| e.to_string())?;
+        if key.id() != Id::ED25519 {
+            return Err("only Ed25519 keys are supported".into());
+        }
+        // The raw key makes up the end of its DER encoding
+        let der = key.public_key_to_der().map_err(|e| e.to_string())?;
+        let mut public_key = Vec::new();
+        put_string(&mut public_key, KEY_TYPE.as_bytes());
+        put_string(&mut public_key, &der[der.len() - 32..]);
+        Ok(Self { key, public_key })
+    }
+
+    /// The public key in the OpenSSH format, like `ssh-ed25519 AAAA...`.
+    pub fn public_key(&self) -> String {
+        format!("{} {}", KEY_TYPE, base64::encode(&self.public_key))
+    }
+
+    /// The fingerprint of the public key, as shown by `ssh-keygen -l`.
+    pub fn fingerprint(&self) -> String {
+        let hash = base64::encode(&sha256(&self.public_key));
+        format!("SHA256:{}", hash.trim_end_matches('='))
+    }
+
+    /// Signs a commit, returning the armored signature git stores in the
+    /// `gpgsig` header of the commit.
+    pub fn sign(&self, commit: &[u8]) -> Result<String, ErrorStack> {
+        let mut signer = Signer::new_without_digest(&self.key)?;
+        let signature = signer.sign_oneshot_to_vec(&signed_data(commit))?;
+
+        let mut blob = b"SSHSIG".to_vec();
+        blob.extend_from_slice(&1u32.to_be_bytes());
+        put_string(&mut blob, &self.public_key);
+        put_string(&mut blob, NAMESPACE.as_bytes());
+        put_string(&mut blob, b"");
+        put_string(&mut blob, b"sha512");
+        let mut signature_blob = Vec::new();
+        put_string(&mut signature_blob, KEY_TYPE.as_bytes());
+        put_string(&mut signature_blob, &signature);
+        put_string(&mut blob, &signature_blob);
+
+        let encoded = base64::encode(&blob);
+        let lines = encoded
+            .as_bytes()
+            .chunks(70)
+            .map(|line| std::str::from_utf8(line).unwrap())
+            .collect::<Vec<_>>();
+        Ok(format!(
+            "-----BEGIN SSH SIGNATURE-----\n{}\n-----END SSH SIGNATURE-----",
+            lines.join("\n")
+        ))
+    }
+}
+
+/// What is actually signed for a message, see `PROTOCOL.sshsig` of OpenSSH.
+fn signed_data(message: &[u8]) -> Vec<u8> {
+    let mut data = b"SSHSIG".to_vec();
+    put_string(&mut data, NAMESPACE.as_bytes());
+    put_string(&mut data, b"");
+    put_string(&mut data, b"sha512");
+    put_string(&mut data, &sha512(message));
+    data
+}
+
+fn put_string(buf: &mut Vec<u8>, bytes: &[u8]) {
+    buf.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+    buf.extend_from_slice(bytes);
+}
+
+/// A line of an `allowed_signers` file, for a key used from `valid_after`
+/// until `valid_before`.
+pub fn allowed_signer(
+    public_key: &str,
+    valid_after: NaiveDateTime,
+    valid_before: Option<NaiveDateTime>,
+) -> String {
+    const FORMAT: &str = "%Y%m%d%H%M%SZ";
+    let mut options = format!(
+        "namespaces=\"{}\",valid-after=\"{}\"",
+        NAMESPACE,
+        valid_after.format(FORMAT)
+    );
+    if let Some(valid_before) = valid_before {
+        let valid_before = valid_before + Duration::minutes(ROTATION_OVERLAP);
+        options.push_str(&format!(
+            ",valid-before=\"{}\"",
+            valid_before.format(FORMAT)
+        ));
+    }
+    format!("{} {} {}", COMMITTER_EMAIL, options, public_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use openssl::pkey::Public;
+    use openssl::sign::Verifier;
+
+    fn key() -> SigningKey {
+        let key = PKey::generate_ed25519().unwrap();
+        SigningKey::from_pem(&key.private_key_to_pem_pkcs8().unwrap()).unwrap()
+    }
+
+    fn read_string(buf: &mut &[u8]) -> Vec<u8> {
+        let mut len = [0; 4];
+        len.copy_from_slice(&buf[..4]);
+        let len = u32::from_be_bytes(len) as usize;
+        let string = buf[4..4 + len].to_vec();
+        *buf = &buf[4 + len..];
+        string
+    }
+
+    #[test]
+    fn commits_are_signed_in_the_sshsig_format() {
+        let key = key();
+        let commit =
+            b"tree 4b825dc642cb6eb9a060e54bf8d69288fbee4904\n\nUpdating crate `foo#1.0.0`\n";
+        let armored = key.sign(commit).unwrap();
+        assert!(armored.starts_with("-----BEGIN SSH SIGNATURE-----\n"));
+        assert!(armored.ends_with("\n-----END SSH SIGNATURE-----"));
+        assert!(armored.lines().all(|line| line.len() <= 70));
+
+        let encoded = armored
+            .lines()
+            .filter(|line| !line.starts_with("-----"))
+            .collect::<String>();
+        let blob = base64::decode(&encoded).unwrap();
+        assert_eq!(&blob[..10], b"SSHSIG\0\0\0\x01");
+        let mut rest = &blob[10..];
+        assert_eq!(read_string(&mut rest), key.public_key);
+        assert_eq!(read_string(&mut rest), b"git");
+        assert_eq!(read_string(&mut rest), b"");
+        assert_eq!(read_string(&mut rest), b"sha512");
+        let signature_blob = read_string(&mut rest);
+        assert!(rest.is_empty());
+
+        let mut signature_blob = &signature_blob[..];
+        assert_eq!(read_string(&mut signature_blob), b"ssh-ed25519");
+        let signature = read_string(&mut signature_blob);
+        let public_key: PKey<Public> =
+            PKey::public_key_from_der(&key.key.public_key_to_der().unwrap()).unwrap();
+        let mut verifier = Verifier::new_without_digest(&public_key).unwrap();
+        assert!(verifier
+            .verify_oneshot(&signature, &signed_data(commit))
+            .unwrap());
+    }
+
+    #[test]
+    fn only_ed25519_keys_are_accepted() {
+        let rsa = PKey::from_rsa(openssl::rsa::Rsa::generate(2048).unwrap()).unwrap();
+        assert!(SigningKey::from_pem(&rsa.private_key_to_pem_pkcs8().unwrap()).is_err());
+        assert!(key()
+            .public_key()
+            .starts_with("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAI"));
+        assert!(key().fingerprint().starts_with("SHA256:"));
+    }
+
+    #[test]
+    fn rotated_keys_are_valid_until_shortly_after_their_successor() {
+        let first_used = NaiveDate::from_ymd(2020, 3, 1).and_hms(12, 0, 0);
+        let succeeded = NaiveDate::from_ymd(2020, 4, 1).and_hms(8, 30, 0);
+        assert_eq!(
+            allowed_signer("ssh-ed25519 AAAA", first_used, Some(succeeded)),
+            "bors@rust-lang.org namespaces=\"git\",valid-after=\"20200301120000Z\",\
+             valid-before=\"20200401093000Z\" ssh-ed25519 AAAA"
+        );
+        assert_eq!(
+            allowed_signer("ssh-ed25519 AAAA", first_used, None),
+            "bors@rust-lang.org namespaces=\"git\",valid-after=\"20200301120000Z\" ssh-ed25519 AAAA"
+        );
+    }
+}
@@ -0,0 +1,197 @@
+//! Compares the entries of the git index with the database, for the
+//! `verify-index` binary.
+//!
+//! The index is only published as a git repository, there's no sparse index
+//! to compare it with.
+
+use std::collections::BTreeSet;
+
+use crate::git;
+
+/// A version as the database has it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DatabaseVersion {
+    pub num: String,
+    pub checksum: Option<String>,
+    pub yanked: bool,
+}
+
+/// A difference between the index file of a crate and the database.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Mismatch {
+    /// An index entry has another checksum than the database.
+    Checksum {
+        #[serde(rename = "crate")]
+        krate: String,
+        version: String,
+        expected: String,
+        found: String,
+    },
+    /// A version of the database isn't in the index.
+    MissingVersion {
+        #[serde(rename = "crate")]
+        krate: String,
+        version: String,
+    },
+    /// The index has a version the database doesn't know.
+    UnknownVersion {
+        #[serde(rename = "crate")]
+        krate: String,
+        version: String,
+    },
+    /// An index entry isn't yanked, or not unyanked, like the version in the
+    /// database.
+    StaleYank {
+        #[serde(rename = "crate")]
+        krate: String,
+        version: String,
+        yanked_in_database: bool,
+        yanked_in_index: bool,
+    },
+}
+
+/// Parses the lines of an index file, skipping blank ones.
+pub fn parse_entries(index_file: &str) -> Result<Vec<git::Crate>, serde_json::Error> {
+    index_file
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(serde_json::from_str)
+        .collect()
+}
+
+/// Compares the index entries of a crate with its versions in the database.
+pub fn compare(name: &str, expected: &[DatabaseVersion], entries: &[git::Crate]) -> Vec<Mismatch> {
+    let nums = expected
+        .iter()
+        .map(|version| &version.num)
+        .chain(entries.iter().map(|entry| &entry.vers))
+        .collect::<BTreeSet<_>>();
+
+    let mut mismatches = Vec::new();
+    for num in nums {
+        let database = expected.iter().find(|version| &version.num == num);
+        let entry = entries.iter().find(|entry| &entry.vers == num);
+        let krate = name.to_string();
+        let version = num.clone();
+
+        let (database, entry) = match (database, entry) {
+            (Some(database), Some(entry)) => (database, entry),
+            (Some(_), None) => {
+                mismatches.push(Mismatch::MissingVersion { krate, version });
+                continue;
+            }
+            (None, Some(_)) => {
+                mismatches.push(Mismatch::UnknownVersion { krate, version });
+                continue;
+            }
+            (None, None) => continue,
+        };
+        if let Some(checksum) = &database.checksum {
+            if checksum != &entry.cksum {
+                mismatches.push(Mismatch::Checksum {
+                    krate: krate.clone(),
+                    version: version.clone(),
+                    expected: checksum.clone(),
+                    found: entry.cksum.clone(),
+                });
+            }
+        }
+        let yanked_in_index = entry.yanked.unwrap_or(false);
+        if database.yanked != yanked_in_index {
+            mismatches.push(Mismatch::StaleYank {
+                krate,
+                version,
+                yanked_in_database: database.yanked,
+                yanked_in_index,
+            });
+        }
+    }
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CKSUM: &str = "c0ffee00c0ffee00c0ffee00c0ffee00c0ffee00c0ffee00c0ffee00c0ffee00";
+
+    fn version(num: &str, yanked: bool) -> DatabaseVersion {
+        DatabaseVersion {
+            num: num.into(),
+            checksum: Some(CKSUM.into()),
+            yanked,
+        }
+    }
+
+    fn line(num: &str, cksum: &str, yanked: bool) -> String {
+        format!(
+            r#"{{"name":"foo","vers":"{}","deps":[],"cksum":"{}","features":{{}},"yanked":{},"links":null}}"#,
+            num, cksum, yanked
+        )
+    }
+
+    fn entries(lines: &[String]) -> Vec<git::Crate> {
+        parse_entries(&lines.join("\n\n")).unwrap()
+    }
+
+    #[test]
+    fn matching_entries_have_no_mismatches() {
+        let expected = vec![version("1.0.0", false), version("1.1.0", true)];
+        let entries = entries(&[line("1.0.0", CKSUM, false), line("1.1.0", CKSUM, true)]);
+        assert_eq!(compare("foo", &expected, &entries), vec![]);
+    }
+
+    #[test]
+    fn missing_and_extra_lines_are_reported() {
+        let expected = vec![version("1.0.0", false), version("1.1.0", false)];
+        let entries = entries(&[line("1.0.0", CKSUM, false), line("2.0.0", CKSUM, false)]);
+        assert_eq!(
+            compare("foo", &expected, &entries),
+            vec![
+                Mismatch::MissingVersion {
+                    krate: "foo".into(),
+                    version: "1.1.0".into(),
+                },
+                Mismatch::UnknownVersion {
+                    krate: "foo".into(),
+                    version: "2.0.0".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn mismatched_lines_are_reported() {
+        let other = "deadbeef".repeat(8);
+        let mut unknown_checksum = version("1.2.0", false);
+        unknown_checksum.checksum = None;
+        let expected = vec![
+            version("1.0.0", false),
+            version("1.1.0", true),
+            unknown_checksum,
+        ];
+        let entries = entries(&[
+            line("1.0.0", &other, false),
+            line("1.1.0", CKSUM, false),
+            line("1.2.0", &other, false),
+        ]);
+        assert_eq!(
+            compare("foo", &expected, &entries),
+            vec![
+                Mismatch::Checksum {
+                    krate: "foo".into(),
+                    version: "1.0.0".into(),
+                    expected: CKSUM.into(),
+                    found: other,
+                },
+                Mismatch::StaleYank {
+                    krate: "foo".into(),
+                    version: "1.1.0".into(),
+                    yanked_in_database: true,
+                    yanked_in_index: false,
+                },
+            ]
+        );
+    }
+}
@@ -30,17 +30,38 @@ static ALLOC: Jemalloc = Jemalloc;
 mod app;
 pub mod background_jobs;
 pub mod boot;
-mod config;
+pub mod config;
+pub mod crate_page;
 pub mod db;
+pub mod deprecations;
+pub mod download_cache;
 pub mod email;
 pub mod git;
 pub mod github;
+pub mod i18n;
+pub mod index_signing;
+pub mod index_verification;
+pub mod manifest_lints;
+pub mod metrics;
 pub mod middleware;
-mod publish_rate_limit;
+pub mod migration_safety;
+pub mod og_image;
+pub mod outreach;
+pub mod publish_rate_limit;
 pub mod render;
+pub mod response_cache;
+pub mod sanitize;
+pub mod sbom;
 pub mod schema;
+pub mod search_backend;
+pub mod sigstore;
+pub mod sitemap;
+pub mod spdx;
+pub mod storage;
+pub mod tarball;
 pub mod tasks;
 mod test_util;
+pub mod transparency_log;
 pub mod uploaders;
 pub mod util;
 
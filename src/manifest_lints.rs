@@ -0,0 +1,324 @@
+//! Checks of the metadata, the manifest, the license files and the lock file
+//! of a crate which is published for things which are allowed, but probably
+//! not what the author wants. They are reported as `warnings.lints` in the response to
+//! the publish.
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::views::{EncodableCrateUpload, EncodableLint};
+
+/// Keywords longer than this are cut off on the crate pages.
+const MAX_KEYWORD_LENGTH: usize = 20;
+
+/// The sources cargo records in lock files for packages from crates.io, for
+/// the git and the sparse index.
+const CRATES_IO_SOURCES: &[&str] = &[
+    "registry+https://github.com/rust-lang/crates.io-index",
+    "sparse+https://index.crates.io/",
+];
+
+/// Lints of the metadata which Cargo sends along with the crate file.
+pub fn check_metadata(metadata: &EncodableCrateUpload) -> Vec<EncodableLint> {
+    let mut lints = Vec::new();
+
+    if metadata
+        .description
+        .as_ref()
+        .map_or(true, |description| description.trim().is_empty())
+    {
+        lints.push(lint(
+            "missing_description",
+            "the `description` is blank, so search results won't say what the crate does"
+                .to_string(),
+        ));
+    }
+
+    if metadata.repository.is_none() {
+        lints.push(lint(
+            "missing_repository",
+            "no `repository` is set, so there's no link to the source code of the crate"
+                .to_string(),
+        ));
+    }
+
+    for keyword in metadata.keywords.iter() {
+        if keyword.chars().count() > MAX_KEYWORD_LENGTH {
+            lints.push(lint(
+                "long_keyword",
+                format!(
+                    "the keyword `{}` is longer than {} characters and will be cut off",
+                    **keyword, MAX_KEYWORD_LENGTH
+                ),
+            ));
+        }
+    }
+
+    // Badges used to take URLs, but all of them expect a `user/repo` path
+    // on the respective service now.
+    let mut badges = metadata
+        .badges
+        .iter()
+        .flatten()
+        .filter(|(_, attributes)| {
+            attributes
+                .get("repository")
+                .map_or(false, |repository| repository.contains("://"))
+        })
+        .map(|(badge, _)| badge.as_str())
+        .collect::<Vec<_>>();
+    badges.sort();
+    for badge in badges {
+        lints.push(lint(
+            "deprecated_badge_syntax",
+            format!(
+                "the `repository` of the `{}` badge should be a `user/repo` path instead of a URL",
+                badge
+            ),
+        ));
+    }
+
+    lints
+}
+
+/// Lints of the normalized `Cargo.toml` in the crate file.
+pub fn check_manifest(manifest: &toml::Value) -> Vec<EncodableLint> {
+    let mut lints = Vec::new();
+
+    let package = manifest.get("package");
+    if package.and_then(|p| p.get("rust-version")).is_none() {
+        lints.push(lint(
+            "missing_rust_version",
+            "no `rust-version` is set, so users of older compilers won't know whether \
+             the crate supports them"
+                .to_string(),
+        ));
+    }
+
+    lints
+}
+
+/// Lints of the license files at the root of the crate against the licenses
+/// of the `license` field, which `declared` lists as stored in `license_ids`.
+///
+/// `report` is the report of the `license` analyzer, which identified the
+/// license files. The files whose license isn't declared are added to it as
+/// `mismatched_files`, so that the mismatches can be audited later.
+pub fn check_license_files(declared: &[String], report: &mut Value) -> Vec<EncodableLint> {
+    let mismatched = report["license_files"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|file| Some((file["path"].as_str()?, file["license"].as_str()?)))
+        .filter(|(_, license)| !declares(declared, license))
+        .map(|(path, license)| (path.to_string(), license.to_string()))
+        .collect::<Vec<_>>();
+    if let Some(report) = report.as_object_mut() {
+        let paths = mismatched.iter().map(|(path, _)| path).collect::<Vec<_>>();
+        report.insert("mismatched_files".into(), json!(paths));
+    }
+
+    mismatched
+        .into_iter()
+        .map(|(path, license)| {
+            lint(
+                "license_mismatch",
+                format!(
+                    "`{}` looks like the {} license, which the `license` field doesn't mention",
+                    path, license
+                ),
+            )
+        })
+        .collect()
+}
+
+/// The packages from crates.io which a `Cargo.lock` pins, as `(name,
+/// version)`. Path and git dependencies, and those from other registries,
+/// are left out.
+pub fn locked_packages(lockfile: &toml::Value) -> Vec<(String, String)> {
+    lockfile
+        .get("package")
+        .and_then(|packages| packages.as_array())
+        .into_iter()
+        .flatten()
+        .filter(|package| {
+            package
+                .get("source")
+                .and_then(|source| source.as_str())
+                .map_or(false, |source| CRATES_IO_SOURCES.contains(&source))
+        })
+        .filter_map(|package| {
+            let name = package.get("name")?.as_str()?;
+            let version = package.get("version")?.as_str()?;
+            Some((name.to_string(), version.to_string()))
+        })
+        .collect()
+}
+
+/// Lints of the packages a `Cargo.lock` in the crate file pins, as listed
+/// by `locked_packages`. `published` has whether each of the versions which
+/// exist is yanked, by `(name, version)`.
+pub fn check_lockfile(
+    locked: &[(String, String)],
+    published: &HashMap<(String, String), bool>,
+) -> Vec<EncodableLint> {
+    locked
+        .iter()
+        .filter_map(|package| {
+            let (name, version) = package;
+            match published.get(package) {
+                Some(false) => None,
+                Some(true) => Some(lint(
+                    "yanked_locked_version",
+                    format!(
+                        "`Cargo.lock` pins {} {}, which has been yanked",
+                        name, version
+                    ),
+                )),
+                None => Some(lint(
+                    "unknown_locked_version",
+                    format!(
+                        "`Cargo.lock` pins {} {}, which doesn't exist on crates.io",
+                        name, version
+                    ),
+                )),
+            }
+        })
+        .collect()
+}
+
+/// Whether one of the declared licenses is the detected one, which is
+/// identified without its `-only` or `-or-later` variant.
+fn declares(declared: &[String], detected: &str) -> bool {
+    declared.iter().any(|id| {
+        let id = id.trim_end_matches("-only").trim_end_matches("-or-later");
+        id == detected
+    })
+}
+
+fn lint(name: &str, message: String) -> EncodableLint {
+    EncodableLint {
+        lint: name.to_string(),
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(lints: &[EncodableLint]) -> Vec<&str> {
+        lints.iter().map(|lint| lint.lint.as_str()).collect()
+    }
+
+    #[test]
+    fn metadata_lints() {
+        let metadata = serde_json::from_value(json!({
+            "name": "foo",
+            "vers": "1.0.0",
+            "deps": [],
+            "features": {},
+            "authors": ["foo"],
+            "description": "  ",
+            "keywords": ["short", "a-keyword-which-goes-on-and-on"],
+            "badges": {
+                "travis-ci": { "repository": "https://github.com/foo/foo" },
+                "gitlab": { "repository": "foo/foo" }
+            }
+        }))
+        .unwrap();
+        assert_eq!(
+            names(&check_metadata(&metadata)),
+            [
+                "missing_description",
+                "missing_repository",
+                "long_keyword",
+                "deprecated_badge_syntax",
+            ]
+        );
+    }
+
+    #[test]
+    fn license_files_are_checked_against_the_license_field() {
+        let declared = vec!["Apache-2.0".to_string(), "GPL-3.0-or-later".to_string()];
+        let mut report = json!({ "license_files": [
+            { "path": "LICENSE-APACHE", "license": "Apache-2.0" },
+            { "path": "LICENSE-MIT", "license": "MIT" },
+            { "path": "COPYING", "license": "GPL-3.0" },
+            { "path": "LICENSE-OTHER", "license": null },
+        ]});
+
+        let lints = check_license_files(&declared, &mut report);
+        assert_eq!(names(&lints), ["license_mismatch"]);
+        assert!(lints[0]
+            .message
+            .starts_with("`LICENSE-MIT` looks like the MIT license"));
+        assert_eq!(report["mismatched_files"], json!(["LICENSE-MIT"]));
+    }
+
+    #[test]
+    fn locked_versions_must_exist_and_not_be_yanked() {
+        let lockfile = r#"
+            [[package]]
+            name = "foo"
+            version = "0.1.0"
+            dependencies = ["bar", "baz", "qux"]
+
+            [[package]]
+            name = "bar"
+            version = "1.0.0"
+            source = "registry+https://github.com/rust-lang/crates.io-index"
+
+            [[package]]
+            name = "baz"
+            version = "2.0.0"
+            source = "registry+https://github.com/rust-lang/crates.io-index"
+
+            [[package]]
+            name = "qux"
+            version = "3.0.0"
+            source = "sparse+https://index.crates.io/"
+
+            [[package]]
+            name = "quux"
+            version = "0.1.0"
+            source = "git+https://github.com/foo/quux#0123456789abcdef"
+        "#
+        .parse()
+        .unwrap();
+        let locked = locked_packages(&lockfile);
+        assert_eq!(
+            locked,
+            [
+                ("bar".to_string(), "1.0.0".to_string()),
+                ("baz".to_string(), "2.0.0".to_string()),
+                ("qux".to_string(), "3.0.0".to_string()),
+            ]
+        );
+
+        let mut published = HashMap::new();
+        published.insert(("bar".to_string(), "1.0.0".to_string()), false);
+        published.insert(("baz".to_string(), "2.0.0".to_string()), true);
+        let lints = check_lockfile(&locked, &published);
+        assert_eq!(
+            names(&lints),
+            ["yanked_locked_version", "unknown_locked_version"]
+        );
+        assert_eq!(
+            lints[1].message,
+            "`Cargo.lock` pins qux 3.0.0, which doesn't exist on crates.io"
+        );
+    }
+
+    #[test]
+    fn manifest_without_rust_version() {
+        let manifest = "[package]\nname = \"foo\"\n".parse().unwrap();
+        assert_eq!(names(&check_manifest(&manifest)), ["missing_rust_version"]);
+
+        let manifest = "[package]\nname = \"foo\"\nrust-version = \"1.40\"\n"
+            .parse()
+            .unwrap();
+        assert!(check_manifest(&manifest).is_empty());
+    }
+}
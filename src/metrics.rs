@@ -0,0 +1,110 @@
+//! Rendering of metrics in the Prometheus text exposition format
+//!
+//! The state the metrics are computed from lives in the database, shared by
+//! all servers and background workers, so rather than keeping counters in
+//! each process, the current values are queried and rendered on every scrape
+//! of the `/api/private/metrics` endpoints.
+
+use std::fmt::Write;
+
+/// The content type of the text exposition format.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+#[derive(Debug, Default)]
+pub struct Exposition {
+    text: String,
+}
+
+impl Exposition {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn gauge(&mut self, name: &str, help: &str, value: f64) -> &mut Self {
+        self.header(name, help, "gauge");
+        writeln!(self.text, "{} {}", name, value).unwrap();
+        self
+    }
+
+    /// A counter with one sample per set of labels, which are given in their
+    /// rendered form, like `stage="index"`.
+    pub fn counter(&mut self, name: &str, help: &str, samples: &[(&str, i64)]) -> &mut Self {
+        self.header(name, help, "counter");
+        for (labels, value) in samples {
+            writeln!(self.text, "{}{{{}}} {}", name, labels, value).unwrap();
+        }
+        self
+    }
+
+    /// A histogram, where `buckets` are the cumulative counts of the
+    /// observations up to each of the `bounds`.
+    pub fn histogram(
+        &mut self,
+        name: &str,
+        help: &str,
+        bounds: &[f64],
+        buckets: &[i64],
+        count: i64,
+        sum: f64,
+    ) -> &mut Self {
+        self.header(name, help, "histogram");
+        for (le, value) in bounds.iter().zip(buckets) {
+            writeln!(self.text, "{}_bucket{{le=\"{}\"}} {}", name, le, value).unwrap();
+        }
+        writeln!(self.text, "{}_bucket{{le=\"+Inf\"}} {}", name, count).unwrap();
+        writeln!(self.text, "{}_sum {}", name, sum).unwrap();
+        writeln!(self.text, "{}_count {}", name, count).unwrap();
+        self
+    }
+
+    fn header(&mut self, name: &str, help: &str, kind: &str) {
+        writeln!(self.text, "# HELP {} {}", name, help).unwrap();
+        writeln!(self.text, "# TYPE {} {}", name, kind).unwrap();
+    }
+
+    pub fn into_string(self) -> String {
+        self.text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metrics_are_rendered_in_the_text_format() {
+        let mut exposition = Exposition::new();
+        exposition
+            .gauge("lag_seconds", "The lag.", 2.5)
+            .counter(
+                "failures_total",
+                "The failures.",
+                &[("stage=\"a\"", 1), ("stage=\"b\"", 0)],
+            )
+            .histogram(
+                "duration_seconds",
+                "The durations.",
+                &[1.0, 5.0],
+                &[2, 3],
+                4,
+                12.5,
+            );
+        assert_eq!(
+            exposition.into_string(),
+            "# HELP lag_seconds The lag.\n\
+             # TYPE lag_seconds gauge\n\
+             lag_seconds 2.5\n\
+             # HELP failures_total The failures.\n\
+             # TYPE failures_total counter\n\
+             failures_total{stage=\"a\"} 1\n\
+             failures_total{stage=\"b\"} 0\n\
+             # HELP duration_seconds The durations.\n\
+             # TYPE duration_seconds histogram\n\
+             duration_seconds_bucket{le=\"1\"} 2\n\
+             duration_seconds_bucket{le=\"5\"} 3\n\
+             duration_seconds_bucket{le=\"+Inf\"} 4\n\
+             duration_seconds_sum 12.5\n\
+             duration_seconds_count 4\n"
+        );
+    }
+}
@@ -10,23 +10,29 @@ mod prelude {
 pub use prelude::Result;
 
 use self::app::AppMiddleware;
+use self::cors::{Cors, EndpointGroup};
 use self::current_user::CaptureUserIdFromCookie;
 use self::debug::*;
+use self::deprecation_notices::DeprecationNotices;
 use self::ember_index_rewrite::EmberIndexRewrite;
 use self::head::Head;
 use self::log_connection_pool_status::LogConnectionPoolStatus;
+use self::service_consumers::ServiceConsumerQuota;
 use self::static_or_continue::StaticOrContinue;
 
 pub mod app;
 mod block_traffic;
+pub mod cors;
 pub mod current_user;
 mod debug;
+mod deprecation_notices;
 mod ember_index_rewrite;
 mod ensure_well_formed_500;
 mod head;
 mod log_connection_pool_status;
 pub mod log_request;
 mod require_user_agent;
+pub mod service_consumers;
 mod static_or_continue;
 
 use conduit_conditional_get::ConditionalGet;
@@ -51,8 +57,11 @@ pub fn build_middleware(app: Arc<App>, endpoints: R404) -> MiddlewareBuilder {
     if env == Env::Development {
         // Print a log for each request.
         m.add(Debug);
+    }
+
+    if let Some(directory) = config.uploader.storage().local_directory() {
         // Locally serve crates and readmes
-        m.around(StaticOrContinue::new("local_uploads"));
+        m.around(StaticOrContinue::new(directory));
     }
 
     if env::var_os("DEBUG_REQUESTS").is_some() {
@@ -72,7 +81,7 @@ pub fn build_middleware(app: Arc<App>, endpoints: R404) -> MiddlewareBuilder {
         env == Env::Production,
     ));
 
-    m.add(AppMiddleware::new(app));
+    m.add(AppMiddleware::new(app.clone()));
 
     // Parse and save the user_id from the session cookie as part of the authentication logic
     m.add(CaptureUserIdFromCookie);
@@ -88,6 +97,19 @@ pub fn build_middleware(app: Arc<App>, endpoints: R404) -> MiddlewareBuilder {
 
     m.around(Head::default());
 
+    // Marks the responses of deprecated endpoints and counts who still uses them.
+    m.around(DeprecationNotices::new(&app));
+
+    // Counts requests with a service key towards the quota of its consumer.
+    m.around(ServiceConsumerQuota::new(&app));
+
+    if let Some(policy) = config.cors.public {
+        m.around(Cors::new(EndpointGroup::Public, policy));
+    }
+    if let Some(policy) = config.cors.private {
+        m.around(Cors::new(EndpointGroup::Private, policy));
+    }
+
     for (header, blocked_values) in config.blocked_traffic {
         m.around(block_traffic::BlockTraffic::new(header, blocked_values));
     }
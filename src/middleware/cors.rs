@@ -0,0 +1,324 @@
+//! Middleware that adds CORS headers to API responses
+//!
+//! The API differentiates between two groups of endpoints, each with its own
+//! policy:
+//!
+//! - `public`: `GET` and `HEAD` requests, which only return publicly
+//!   available data. Such responses don't depend on who made the request,
+//!   so credentials are never allowed.
+//! - `private`: all other requests, and everything below `/api/v1/me` and
+//!   `/api/private`. Origins have to be listed explicitly and may send
+//!   cookies.
+//!
+//! Each policy is configured through environment variables prefixed with
+//! `CORS_PUBLIC_` or `CORS_PRIVATE_`:
+//!
+//! - `ORIGINS`: A comma-separated list of allowed origins, or `*` to allow
+//!   any origin (public endpoints only). Without it the group doesn't allow
+//!   any cross-origin requests.
+//! - `METHODS`: A comma-separated list of allowed methods, defaults to
+//!   `GET,HEAD` for public and `GET,PUT,POST,DELETE,PATCH` for private
+//!   endpoints.
+//! - `MAX_AGE`: How many seconds browsers may cache the result of a
+//!   preflight request, defaults to one hour.
+
+use super::prelude::*;
+
+use conduit::Method;
+use std::collections::HashMap;
+use std::io;
+
+const DEFAULT_MAX_AGE: u32 = 60 * 60;
+const ALLOWED_HEADERS: &str = "Authorization, Content-Type";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EndpointGroup {
+    Public,
+    Private,
+}
+
+impl EndpointGroup {
+    /// The group of the endpoint which handles `method` requests to `path`,
+    /// if it is part of the API.
+    fn of(method: &Method, path: &str) -> Option<Self> {
+        if !path.starts_with("/api/") {
+            return None;
+        }
+
+        let is_private_path = path == "/api/v1/me"
+            || path.starts_with("/api/v1/me/")
+            || path.starts_with("/api/private/");
+        match method {
+            Method::Get | Method::Head if !is_private_path => Some(EndpointGroup::Public),
+            _ => Some(EndpointGroup::Private),
+        }
+    }
+
+    fn env_prefix(self) -> &'static str {
+        match self {
+            EndpointGroup::Public => "CORS_PUBLIC",
+            EndpointGroup::Private => "CORS_PRIVATE",
+        }
+    }
+
+    fn default_methods(self) -> &'static str {
+        match self {
+            EndpointGroup::Public => "GET,HEAD",
+            EndpointGroup::Private => "GET,PUT,POST,DELETE,PATCH",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AllowedOrigins {
+    Any,
+    List(Vec<String>),
+}
+
+#[derive(Clone, Debug)]
+pub struct CorsPolicy {
+    pub origins: AllowedOrigins,
+    /// The allowed methods, in upper case.
+    pub methods: Vec<String>,
+    pub max_age: u32,
+}
+
+impl CorsPolicy {
+    /// Reads the policy of the given group from the environment, or returns
+    /// `None` if the group doesn't allow cross-origin requests.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of the variables is invalid.
+    pub fn from_environment(group: EndpointGroup) -> Option<Self> {
+        let var = |name: &str| dotenv::var(format!("{}_{}", group.env_prefix(), name)).ok();
+
+        let origins = var("ORIGINS")?;
+        let origins = if origins.trim() == "*" {
+            if group == EndpointGroup::Private {
+                panic!("CORS_PRIVATE_ORIGINS must list the allowed origins");
+            }
+            AllowedOrigins::Any
+        } else {
+            AllowedOrigins::List(split_list(&origins).map(String::from).collect())
+        };
+        let methods = var("METHODS").unwrap_or_else(|| group.default_methods().into());
+        let max_age = var("MAX_AGE").map_or(DEFAULT_MAX_AGE, |max_age| {
+            max_age.parse().unwrap_or_else(|_| {
+                panic!("Invalid value for `{}_MAX_AGE`", group.env_prefix());
+            })
+        });
+
+        Some(Self {
+            origins,
+            methods: split_list(&methods).map(str::to_uppercase).collect(),
+            max_age,
+        })
+    }
+
+    fn allows_origin(&self, origin: &str) -> bool {
+        match &self.origins {
+            AllowedOrigins::Any => true,
+            AllowedOrigins::List(origins) => origins.iter().any(|o| o == origin),
+        }
+    }
+
+    fn allows_method(&self, method: &str) -> bool {
+        self.methods.iter().any(|m| m == method)
+    }
+}
+
+fn split_list(list: &str) -> impl Iterator<Item = &str> {
+    list.split(',').map(str::trim).filter(|s| !s.is_empty())
+}
+
+/// The CORS policies of all endpoint groups.
+#[derive(Clone, Debug, Default)]
+pub struct CorsConfig {
+    pub public: Option<CorsPolicy>,
+    pub private: Option<CorsPolicy>,
+}
+
+impl CorsConfig {
+    pub fn from_environment() -> Self {
+        Self {
+            public: CorsPolicy::from_environment(EndpointGroup::Public),
+            private: CorsPolicy::from_environment(EndpointGroup::Private),
+        }
+    }
+}
+
+/// Applies the CORS policy of a single endpoint group, and passes requests
+/// to other endpoints through unchanged.
+// Can't derive debug because of Handler.
+#[allow(missing_debug_implementations)]
+pub struct Cors {
+    group: EndpointGroup,
+    policy: CorsPolicy,
+    handler: Option<Box<dyn Handler>>,
+}
+
+impl Cors {
+    pub fn new(group: EndpointGroup, policy: CorsPolicy) -> Self {
+        Self {
+            group,
+            policy,
+            handler: None,
+        }
+    }
+
+    fn is_preflight(req: &dyn Request) -> Option<Method> {
+        if req.method() != Method::Options {
+            return None;
+        }
+        let requested = req.headers().find("Access-Control-Request-Method")?;
+        parse_method(requested.first()?)
+    }
+
+    fn add_headers(&self, origin: &str, headers: &mut HashMap<String, Vec<String>>) {
+        let mut insert = |name: &str, value: String| {
+            headers.insert(name.into(), vec![value]);
+        };
+
+        let varies_by_origin = self.policy.origins != AllowedOrigins::Any;
+        if varies_by_origin {
+            insert("Access-Control-Allow-Origin", origin.into());
+        } else {
+            insert("Access-Control-Allow-Origin", "*".into());
+        }
+        if self.group == EndpointGroup::Private {
+            insert("Access-Control-Allow-Credentials", "true".into());
+        }
+        // Responses may already vary by other headers, like localized errors
+        if varies_by_origin {
+            headers
+                .entry("Vary".into())
+                .or_insert_with(Vec::new)
+                .push("Origin".into());
+        }
+    }
+}
+
+impl AroundMiddleware for Cors {
+    fn with_handler(&mut self, handler: Box<dyn Handler>) {
+        self.handler = Some(handler);
+    }
+}
+
+impl Handler for Cors {
+    fn call(&self, req: &mut dyn Request) -> Result<Response> {
+        let handler = self.handler.as_ref().unwrap();
+        let origin = req
+            .headers()
+            .find("Origin")
+            .and_then(|values| values.first().map(|origin| origin.to_string()));
+        let origin = match origin {
+            Some(origin) => origin,
+            None => return handler.call(req),
+        };
+
+        if let Some(requested_method) = Self::is_preflight(req) {
+            let allowed = EndpointGroup::of(&requested_method, req.path()) == Some(self.group)
+                && self.policy.allows_origin(&origin)
+                && self.policy.allows_method(method_name(&requested_method));
+            if !allowed {
+                return handler.call(req);
+            }
+
+            let mut headers = HashMap::new();
+            self.add_headers(&origin, &mut headers);
+            headers.insert(
+                "Access-Control-Allow-Methods".into(),
+                vec![self.policy.methods.join(", ")],
+            );
+            headers.insert(
+                "Access-Control-Allow-Headers".into(),
+                vec![ALLOWED_HEADERS.into()],
+            );
+            headers.insert(
+                "Access-Control-Max-Age".into(),
+                vec![self.policy.max_age.to_string()],
+            );
+            return Ok(Response {
+                status: (204, "No Content"),
+                headers,
+                body: Box::new(io::empty()),
+            });
+        }
+
+        let applies = EndpointGroup::of(&req.method(), req.path()) == Some(self.group)
+            && self.policy.allows_origin(&origin)
+            && self.policy.allows_method(method_name(&req.method()));
+        let mut response = handler.call(req)?;
+        if applies {
+            self.add_headers(&origin, &mut response.headers);
+        }
+        Ok(response)
+    }
+}
+
+fn parse_method(method: &str) -> Option<Method> {
+    match method {
+        "GET" => Some(Method::Get),
+        "HEAD" => Some(Method::Head),
+        "PUT" => Some(Method::Put),
+        "POST" => Some(Method::Post),
+        "DELETE" => Some(Method::Delete),
+        "PATCH" => Some(Method::Patch),
+        _ => None,
+    }
+}
+
+fn method_name(method: &Method) -> &'static str {
+    match method {
+        Method::Get => "GET",
+        Method::Head => "HEAD",
+        Method::Put => "PUT",
+        Method::Post => "POST",
+        Method::Delete => "DELETE",
+        Method::Patch => "PATCH",
+        Method::Options => "OPTIONS",
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn endpoint_groups() {
+        use EndpointGroup::{Private, Public};
+
+        assert_eq!(
+            EndpointGroup::of(&Method::Get, "/api/v1/crates"),
+            Some(Public)
+        );
+        assert_eq!(
+            EndpointGroup::of(&Method::Head, "/api/v1/summary"),
+            Some(Public)
+        );
+        assert_eq!(
+            EndpointGroup::of(&Method::Put, "/api/v1/crates/new"),
+            Some(Private)
+        );
+        assert_eq!(
+            EndpointGroup::of(&Method::Delete, "/api/v1/crates/foo/1.0.0/yank"),
+            Some(Private)
+        );
+        assert_eq!(EndpointGroup::of(&Method::Get, "/api/v1/me"), Some(Private));
+        assert_eq!(
+            EndpointGroup::of(&Method::Get, "/api/v1/me/updates"),
+            Some(Private)
+        );
+        assert_eq!(
+            EndpointGroup::of(&Method::Get, "/api/v1/members"),
+            Some(Public)
+        );
+        assert_eq!(
+            EndpointGroup::of(&Method::Get, "/api/private/session/begin"),
+            Some(Private)
+        );
+        assert_eq!(EndpointGroup::of(&Method::Get, "/crates/foo"), None);
+    }
+}
@@ -0,0 +1,132 @@
+//! Middleware that marks the responses of deprecated endpoints
+//!
+//! Responses of the endpoints in `crate::deprecations` get `Deprecation` and
+//! `Sunset` headers, and JSON responses also get a `meta.deprecations` list
+//! explaining what to use instead, so that both tools and people looking at
+//! the responses notice.
+//!
+//! Each request to a deprecated endpoint is counted for its consumer: the
+//! service consumer which made it, or else its `User-Agent`.
+
+use super::prelude::*;
+
+use serde_json::{json, Value};
+use std::io::Cursor;
+use std::sync::Arc;
+
+use super::service_consumers::CurrentServiceConsumer;
+use crate::app::App;
+use crate::deprecations::{self, DeprecatedEndpoint};
+use crate::models::DeprecatedEndpointUsage;
+
+// Can't derive debug because of Handler.
+#[allow(missing_debug_implementations)]
+pub(super) struct DeprecationNotices {
+    app: Arc<App>,
+    handler: Option<Box<dyn Handler>>,
+}
+
+impl DeprecationNotices {
+    pub(super) fn new(app: &Arc<App>) -> Self {
+        Self {
+            app: app.clone(),
+            handler: None,
+        }
+    }
+
+    fn record_usage(&self, req: &dyn Request, endpoint: &DeprecatedEndpoint) {
+        let consumer = match req.extensions().find::<CurrentServiceConsumer>() {
+            Some(consumer) => consumer.name.clone(),
+            None => req
+                .headers()
+                .find("User-Agent")
+                .and_then(|values| values.first().map(|value| value.to_string()))
+                .unwrap_or_default(),
+        };
+        // Deprecated endpoints keep working if the usage can't be recorded,
+        // e.g. because the database is in read-only mode
+        let result = self
+            .app
+            .primary_database
+            .get()
+            .map_err(|e| e.to_string())
+            .and_then(|conn| {
+                DeprecatedEndpointUsage::record(&conn, endpoint.path, &consumer)
+                    .map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            eprintln!(
+                "Recording the usage of deprecated `{} {}` failed: {}",
+                endpoint.method, endpoint.path, e
+            );
+        }
+    }
+}
+
+impl AroundMiddleware for DeprecationNotices {
+    fn with_handler(&mut self, handler: Box<dyn Handler>) {
+        self.handler = Some(handler);
+    }
+}
+
+impl Handler for DeprecationNotices {
+    fn call(&self, req: &mut dyn Request) -> Result<Response> {
+        let handler = self.handler.as_ref().unwrap();
+        let endpoint = match deprecations::find(&req.method(), req.path()) {
+            Some(endpoint) => endpoint,
+            None => return handler.call(req),
+        };
+
+        super::log_request::add_custom_metadata(req, "deprecated_endpoint", endpoint.path);
+        self.record_usage(req, endpoint);
+
+        let mut response = handler.call(req)?;
+        response
+            .headers
+            .insert("Deprecation".into(), vec![endpoint.deprecation_header()]);
+        if let Some(sunset) = endpoint.sunset_header() {
+            response.headers.insert("Sunset".into(), vec![sunset]);
+        }
+        add_deprecation_meta(&mut response, endpoint)?;
+        Ok(response)
+    }
+}
+
+/// Adds the deprecation to the `meta` object of a JSON response. Other
+/// responses, like errors which aren't JSON objects, are left alone.
+fn add_deprecation_meta(response: &mut Response, endpoint: &DeprecatedEndpoint) -> Result<()> {
+    let is_json = response
+        .headers
+        .get("Content-Type")
+        .and_then(|values| values.first())
+        .map_or(false, |value| value.starts_with("application/json"));
+    if !is_json {
+        return Ok(());
+    }
+
+    let mut body = Vec::new();
+    response
+        .body
+        .write_body(&mut body)
+        .map_err(|e| Box::new(e) as BoxError)?;
+    let mut json = serde_json::from_slice::<Value>(&body).unwrap_or(Value::Null);
+    if let Some(object) = json.as_object_mut() {
+        let deprecation = json!({
+            "method": endpoint.method,
+            "path": endpoint.path,
+            "deprecated_on": endpoint.deprecated_on,
+            "sunset": endpoint.sunset,
+            "message": endpoint.message,
+        });
+        let meta = object.entry("meta").or_insert_with(|| json!({}));
+        if let Some(meta) = meta.as_object_mut() {
+            meta.insert("deprecations".into(), json!([deprecation]));
+        }
+        body = json.to_string().into_bytes();
+        response
+            .headers
+            .insert("Content-Length".into(), vec![body.len().to_string()]);
+    }
+    response.body = Box::new(Cursor::new(body));
+    Ok(())
+}
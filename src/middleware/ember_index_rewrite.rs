@@ -33,8 +33,8 @@ impl Handler for EmberIndexRewrite {
             .map(|accept| accept.iter().any(|s| s.contains("html")))
             .unwrap_or(false);
         // If the route starts with /api, just assume they want the API
-        // response and fall through.
-        let is_api_path = req.path().starts_with("/api");
+        // response and fall through. The same goes for the sitemap index.
+        let is_api_path = req.path().starts_with("/api") || req.path() == "/sitemap.xml";
         let handler = self.handler.as_ref().unwrap();
         if wants_html && !is_api_path {
             handler.call(&mut RequestProxy::rewrite_path(req, "/index.html"))
@@ -0,0 +1,118 @@
+//! Middleware that authenticates registered service consumers and enforces
+//! their quotas
+//!
+//! Requests with an `X-Service-Key` header are counted towards the hourly
+//! quota of the consumer owning the key. Once the quota is used up, further
+//! requests are rejected with a `429 Too Many Requests` until the next hour
+//! starts. Service keys are only valid for read-only requests.
+//!
+//! The name of the consumer is added to the request log, so that its traffic
+//! can be told apart from anonymous traffic.
+
+use super::prelude::*;
+
+use chrono::{Duration, Utc};
+use conduit::Method;
+use std::sync::Arc;
+
+use crate::app::App;
+use crate::models::service_consumer::{start_of_hour, ServiceConsumer};
+use crate::util::errors::{bad_request, AppError, TooManyRequests, Unauthorized};
+
+/// The service consumer a request was made by.
+#[derive(Clone, Debug)]
+pub struct CurrentServiceConsumer {
+    pub id: i32,
+    pub name: String,
+}
+
+// Can't derive debug because of Handler.
+#[allow(missing_debug_implementations)]
+pub(super) struct ServiceConsumerQuota {
+    app: Arc<App>,
+    handler: Option<Box<dyn Handler>>,
+}
+
+impl ServiceConsumerQuota {
+    pub(super) fn new(app: &Arc<App>) -> Self {
+        Self {
+            app: app.clone(),
+            handler: None,
+        }
+    }
+}
+
+impl AroundMiddleware for ServiceConsumerQuota {
+    fn with_handler(&mut self, handler: Box<dyn Handler>) {
+        self.handler = Some(handler);
+    }
+}
+
+impl Handler for ServiceConsumerQuota {
+    fn call(&self, req: &mut dyn Request) -> Result<Response> {
+        let handler = self.handler.as_ref().unwrap();
+        let api_key = req
+            .headers()
+            .find("X-Service-Key")
+            .and_then(|values| values.first().map(|key| key.to_string()));
+        let api_key = match api_key {
+            Some(api_key) => api_key,
+            None => return handler.call(req),
+        };
+
+        if req.method() != Method::Get && req.method() != Method::Head {
+            return error_response(bad_request(
+                "service keys can only be used for read-only requests",
+            ));
+        }
+
+        let now = Utc::now().naive_utc();
+        let (consumer, requests) = {
+            let conn = self
+                .app
+                .primary_database
+                .get()
+                .map_err(|e| Box::new(e) as BoxError)?;
+            let consumer = match ServiceConsumer::find_by_api_key(&conn, &api_key) {
+                Ok(Some(consumer)) => consumer,
+                Ok(None) => return error_response(Box::new(Unauthorized)),
+                Err(e) => return Err(Box::new(e)),
+            };
+            // Requests are let through if the usage can't be recorded, e.g.
+            // because the database is in read-only mode
+            let requests = consumer
+                .record_request(&conn, now)
+                .map_err(|e| eprintln!("Recording the usage of `{}` failed: {}", consumer.name, e))
+                .ok();
+            (consumer, requests)
+        };
+
+        super::log_request::add_custom_metadata(req, "service_consumer", &consumer.name);
+        let remaining = match requests {
+            Some(requests) if requests > consumer.requests_per_hour => {
+                let retry_after = start_of_hour(now) + Duration::hours(1);
+                return error_response(Box::new(TooManyRequests { retry_after }));
+            }
+            Some(requests) => consumer.requests_per_hour - requests,
+            None => consumer.requests_per_hour,
+        };
+
+        req.mut_extensions().insert(CurrentServiceConsumer {
+            id: consumer.id,
+            name: consumer.name.clone(),
+        });
+        let mut response = handler.call(req)?;
+        response.headers.insert(
+            "X-RateLimit-Limit".into(),
+            vec![consumer.requests_per_hour.to_string()],
+        );
+        response
+            .headers
+            .insert("X-RateLimit-Remaining".into(), vec![remaining.to_string()]);
+        Ok(response)
+    }
+}
+
+fn error_response(error: Box<dyn AppError>) -> Result<Response> {
+    Ok(error.response().unwrap())
+}
@@ -3,6 +3,7 @@
 use super::prelude::*;
 
 use conduit_static::Static;
+use std::path::Path;
 
 // Can't derive debug because of Handler and Static.
 #[allow(missing_debug_implementations)]
@@ -12,7 +13,7 @@ pub struct StaticOrContinue {
 }
 
 impl StaticOrContinue {
-    pub fn new(directory: &str) -> StaticOrContinue {
+    pub fn new(directory: impl AsRef<Path>) -> StaticOrContinue {
         StaticOrContinue {
             fallback_handler: None,
             static_handler: Static::new(directory),
@@ -0,0 +1,601 @@
+//! Safeguards against migrations which lock busy tables for long.
+//!
+//! A migration runs in a single transaction while the app keeps serving
+//! requests, so every lock one of its statements takes is held until the
+//! whole migration committed. Migrations which create or drop an index
+//! `CONCURRENTLY` are the exception, since Postgres refuses to do that in a
+//! transaction: they run statement by statement, each committed on its own,
+//! so they shouldn't contain anything else. diesel runs every migration in a
+//! transaction, so such migrations can only be run with the `migrate` binary.
+//! Two checks build on that:
+//!
+//! - `Migration::lint` looks for statements which lock a table for as long as
+//!   it takes to scan or rewrite it, like creating an index. The `migrate`
+//!   binary refuses to run such migrations in production. A statement which
+//!   is known to be quick, e.g. because the table is small, is allowed with a
+//!   `-- migration-safety: allow <rule>` line in the migration.
+//! - `Migration::apply_measured` runs a migration statement by statement, and
+//!   reports how long each of them took and how long the locks on existing
+//!   tables were held. `migrate check` uses it to apply the pending
+//!   migrations to a restored database dump.
+//!
+//! Migrations are recorded in `__diesel_schema_migrations` like the diesel
+//! CLI does, so both can be used on the same database.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use diesel::connection::SimpleConnection;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Text};
+
+table! {
+    __diesel_schema_migrations (version) {
+        version -> Varchar,
+        run_on -> Timestamp,
+    }
+}
+
+/// The line which allows the statements a rule would refuse, followed by the
+/// comma separated rules.
+const ALLOW_DIRECTIVE: &str = "-- migration-safety: allow";
+
+/// The lock modes which keep other sessions from writing to a table.
+const WRITE_BLOCKING_LOCKS: &[&str] = &[
+    "ShareLock",
+    "ShareRowExclusiveLock",
+    "ExclusiveLock",
+    "AccessExclusiveLock",
+];
+
+/// A migration of the `migrations` directory.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Migration {
+    /// The version diesel records, the date of the directory name without
+    /// the dashes.
+    pub version: String,
+    /// The name of the directory.
+    pub name: String,
+    pub up_sql: String,
+}
+
+/// A statement which `Migration::lint` found to lock a table for long.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Violation {
+    pub rule: &'static str,
+    pub statement: String,
+    pub reason: String,
+}
+
+/// How long a statement of a migration took.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct StatementTiming {
+    pub statement: String,
+    pub duration_ms: u64,
+}
+
+/// A lock a migration took on a table which existed before, and for how
+/// long it was held, from the start of the statement which took it until the
+/// migration committed.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct LockHold {
+    pub relation: String,
+    pub mode: String,
+    pub held_ms: u64,
+}
+
+impl LockHold {
+    pub fn blocks_writes(&self) -> bool {
+        WRITE_BLOCKING_LOCKS.contains(&self.mode.as_str())
+    }
+}
+
+/// What applying a migration with `Migration::apply_measured` took.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Report {
+    pub migration: String,
+    pub duration_ms: u64,
+    pub statements: Vec<StatementTiming>,
+    pub locks: Vec<LockHold>,
+    pub violations: Vec<Violation>,
+}
+
+#[derive(QueryableByName)]
+struct HeldLock {
+    #[sql_type = "BigInt"]
+    oid: i64,
+    #[sql_type = "Text"]
+    relation: String,
+    #[sql_type = "Text"]
+    mode: String,
+}
+
+impl Migration {
+    /// Reads the migrations in `dir`, oldest first.
+    pub fn load_all(dir: &Path) -> io::Result<Vec<Self>> {
+        let mut migrations = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let up_sql = path.join("up.sql");
+            if !up_sql.is_file() {
+                continue;
+            }
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            let version = name.split('_').next().unwrap_or_default().replace('-', "");
+            migrations.push(Self {
+                version,
+                name,
+                up_sql: fs::read_to_string(up_sql)?,
+            });
+        }
+        migrations.sort_by(|a, b| a.version.cmp(&b.version));
+        Ok(migrations)
+    }
+
+    /// The migrations which weren't run on the database yet.
+    pub fn pending(conn: &PgConnection, migrations: Vec<Self>) -> QueryResult<Vec<Self>> {
+        conn.batch_execute(
+            "CREATE TABLE IF NOT EXISTS __diesel_schema_migrations (\
+               version VARCHAR(50) PRIMARY KEY NOT NULL, \
+               run_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP\
+             )",
+        )?;
+        let applied = __diesel_schema_migrations::table
+            .select(__diesel_schema_migrations::version)
+            .load::<String>(conn)?
+            .into_iter()
+            .collect::<HashSet<_>>();
+        Ok(migrations
+            .into_iter()
+            .filter(|migration| !applied.contains(&migration.version))
+            .collect())
+    }
+
+    pub fn statements(&self) -> Vec<String> {
+        split_statements(&self.up_sql)
+    }
+
+    /// The rules allowed with `ALLOW_DIRECTIVE` lines.
+    fn allowed_rules(&self) -> HashSet<&str> {
+        self.up_sql
+            .lines()
+            .map(str::trim)
+            .filter(|line| line.starts_with(ALLOW_DIRECTIVE))
+            .flat_map(|line| line[ALLOW_DIRECTIVE.len()..].split(','))
+            .map(str::trim)
+            .collect()
+    }
+
+    /// Finds the statements which lock a table for as long as it takes to
+    /// scan or rewrite it. Tables created by the migration itself aren't
+    /// used by anyone yet, so anything goes for them.
+    pub fn lint(&self) -> Vec<Violation> {
+        let allowed = self.allowed_rules();
+        let mut created = HashSet::new();
+        let mut violations = Vec::new();
+        for statement in self.statements() {
+            let normalized = statement
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+                .to_uppercase();
+            let words = normalized.split(' ').collect::<Vec<_>>();
+            if words.starts_with(&["CREATE", "TABLE"]) {
+                created.extend(table_name(&words[2..]));
+                continue;
+            }
+            for (rule, table, reason) in check_statement(&normalized, &words) {
+                if !created.contains(&table) && !allowed.contains(&rule) {
+                    violations.push(Violation {
+                        rule,
+                        statement: statement.clone(),
+                        reason,
+                    });
+                }
+            }
+        }
+        violations
+    }
+
+    /// Whether the migration can run in a transaction, which it can't if it
+    /// creates or drops an index `CONCURRENTLY`.
+    pub fn runs_in_transaction(&self) -> bool {
+        !self.statements().iter().any(|statement| {
+            let normalized = statement.to_uppercase();
+            let words = normalized.split_whitespace().collect::<Vec<_>>();
+            is_concurrent(&words)
+        })
+    }
+
+    /// Runs the migration like diesel does, in a transaction, unless it
+    /// can't run in one. Locks are only waited for up to `lock_timeout`, so
+    /// that a migration which can't get its locks fails instead of queueing
+    /// every query behind it.
+    pub fn run(&self, conn: &PgConnection, lock_timeout: Option<Duration>) -> QueryResult<()> {
+        if !self.runs_in_transaction() {
+            return self.run_without_transaction(conn, lock_timeout);
+        }
+        conn.transaction(|| {
+            if let Some(lock_timeout) = lock_timeout {
+                conn.batch_execute(&format!(
+                    "SET LOCAL lock_timeout = {}",
+                    lock_timeout.as_millis()
+                ))?;
+            }
+            conn.batch_execute(&self.up_sql)?;
+            self.record(conn)
+        })
+    }
+
+    /// Runs the statements one at a time, since several statements sent at
+    /// once would run in an implicit transaction.
+    fn run_without_transaction(
+        &self,
+        conn: &PgConnection,
+        lock_timeout: Option<Duration>,
+    ) -> QueryResult<()> {
+        if let Some(lock_timeout) = lock_timeout {
+            conn.batch_execute(&format!("SET lock_timeout = {}", lock_timeout.as_millis()))?;
+        }
+        let result = self
+            .statements()
+            .iter()
+            .try_for_each(|statement| conn.batch_execute(statement))
+            .and_then(|_| self.record(conn));
+        if lock_timeout.is_some() {
+            conn.batch_execute("RESET lock_timeout")?;
+        }
+        result
+    }
+
+    /// Runs the migration statement by statement, in a transaction unless it
+    /// can't run in one, and measures how long the statements took and the
+    /// locks were held. Without a transaction, the locks are released when
+    /// each statement finished, so only the durations of the statements are
+    /// reported.
+    pub fn apply_measured(&self, conn: &PgConnection) -> QueryResult<Report> {
+        let existing = diesel::sql_query("SELECT oid::int8 AS oid FROM pg_class")
+            .load::<Oid>(conn)?
+            .into_iter()
+            .map(|row| row.oid)
+            .collect::<HashSet<_>>();
+
+        let started = Instant::now();
+        let mut statements = Vec::new();
+        let mut locks = Vec::<(HeldLock, Instant)>::new();
+        let mut apply = || -> QueryResult<()> {
+            for statement in self.statements() {
+                let statement_started = Instant::now();
+                conn.batch_execute(&statement)?;
+                statements.push(StatementTiming {
+                    statement,
+                    duration_ms: millis(statement_started.elapsed()),
+                });
+                for lock in held_locks(conn)? {
+                    let known = locks
+                        .iter()
+                        .any(|(held, _)| held.oid == lock.oid && held.mode == lock.mode);
+                    if existing.contains(&lock.oid) && !known {
+                        locks.push((lock, statement_started));
+                    }
+                }
+            }
+            self.record(conn)
+        };
+        if self.runs_in_transaction() {
+            conn.transaction(apply)?;
+        } else {
+            apply()?;
+        }
+
+        let committed = Instant::now();
+        Ok(Report {
+            migration: self.name.clone(),
+            duration_ms: millis(committed - started),
+            statements,
+            locks: locks
+                .into_iter()
+                .map(|(lock, since)| LockHold {
+                    relation: lock.relation,
+                    mode: lock.mode,
+                    held_ms: millis(committed - since),
+                })
+                .collect(),
+            violations: self.lint(),
+        })
+    }
+
+    fn record(&self, conn: &PgConnection) -> QueryResult<()> {
+        diesel::insert_into(__diesel_schema_migrations::table)
+            .values(__diesel_schema_migrations::version.eq(&self.version))
+            .execute(conn)?;
+        Ok(())
+    }
+}
+
+#[derive(QueryableByName)]
+struct Oid {
+    #[sql_type = "BigInt"]
+    oid: i64,
+}
+
+/// The locks on relations the current session holds, except for the ones on
+/// the system catalogs.
+fn held_locks(conn: &PgConnection) -> QueryResult<Vec<HeldLock>> {
+    diesel::sql_query(
+        "SELECT pg_class.oid::int8 AS oid, pg_class.relname::text AS relation, pg_locks.mode \
+         FROM pg_locks INNER JOIN pg_class ON pg_class.oid = pg_locks.relation \
+         WHERE pg_locks.pid = pg_backend_pid() AND pg_locks.granted \
+         AND pg_locks.locktype = 'relation' \
+         AND pg_class.relnamespace <> 'pg_catalog'::regnamespace",
+    )
+    .load(conn)
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis() as u64
+}
+
+/// The rules a statement breaks, with the table it locks and why that's a
+/// problem. `normalized` is the statement in upper case, with single spaces
+/// between its `words`.
+fn check_statement(normalized: &str, words: &[&str]) -> Vec<(&'static str, String, String)> {
+    let mut broken = Vec::new();
+    if is_index_creation(words) && !words.contains(&"CONCURRENTLY") {
+        let on = words.iter().position(|&word| word == "ON");
+        if let Some(table) = on.and_then(|on| table_name(&words[on + 1..])) {
+            let reason = format!("blocks writes to `{}` while the index is built", table);
+            broken.push(("index-not-concurrent", table, reason));
+        }
+    }
+
+    if !words.starts_with(&["ALTER", "TABLE"]) {
+        return broken;
+    }
+    let table = match table_name(&words[2..]) {
+        Some(table) => table,
+        None => return broken,
+    };
+    if normalized.contains(" ALTER ") && normalized.contains(" TYPE ") {
+        let reason = format!("rewrites `{}` while blocking reads and writes", table);
+        broken.push(("column-type-change", table.clone(), reason));
+    }
+    if normalized.contains(" SET NOT NULL") {
+        let reason = format!("scans `{}` while blocking reads and writes", table);
+        broken.push(("set-not-null", table.clone(), reason));
+    }
+    if normalized.contains(" ADD CONSTRAINT ")
+        && normalized.contains(" FOREIGN KEY")
+        && !normalized.contains(" NOT VALID")
+    {
+        let reason = format!(
+            "validates all rows of `{}` while blocking writes, add it `NOT VALID` \
+             and validate it separately",
+            table
+        );
+        broken.push(("validated-foreign-key", table, reason));
+    }
+    broken
+}
+
+fn is_index_creation(words: &[&str]) -> bool {
+    words.starts_with(&["CREATE", "INDEX"]) || words.starts_with(&["CREATE", "UNIQUE", "INDEX"])
+}
+
+/// Whether the statement with these upper case `words` creates or drops an
+/// index `CONCURRENTLY`, which Postgres doesn't allow in a transaction.
+fn is_concurrent(words: &[&str]) -> bool {
+    (is_index_creation(words) || words.starts_with(&["DROP", "INDEX"]))
+        && words.contains(&"CONCURRENTLY")
+}
+
+/// The table named by the first of `words` which isn't a modifier like
+/// `IF NOT EXISTS`, in lower case and without the schema.
+fn table_name(words: &[&str]) -> Option<String> {
+    let name = words
+        .iter()
+        .find(|&&word| !["IF", "NOT", "EXISTS", "ONLY"].contains(&word))?;
+    let name = name.split('(').next().unwrap_or_default();
+    let name = name
+        .rsplit('.')
+        .next()
+        .unwrap_or_default()
+        .trim_matches('"');
+    if name.is_empty() {
+        None
+    } else {
+        Some(name.to_lowercase())
+    }
+}
+
+/// Splits SQL into its statements, without comments and the terminating
+/// semicolons. Semicolons in strings, quoted identifiers and dollar quoted
+/// function bodies don't end a statement.
+pub fn split_statements(sql: &str) -> Vec<String> {
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    let mut rest = sql;
+    while let Some(c) = rest.chars().next() {
+        let length = if rest.starts_with("--") {
+            current.push(' ');
+            rest.find('\n').unwrap_or_else(|| rest.len())
+        } else if rest.starts_with("/*") {
+            current.push(' ');
+            rest.find("*/").map_or(rest.len(), |end| end + 2)
+        } else if c == '\'' || c == '"' {
+            let length = rest[1..].find(c).map_or(rest.len(), |end| end + 2);
+            current.push_str(&rest[..length]);
+            length
+        } else if let Some(tag) = dollar_quote_tag(rest) {
+            let length = rest[tag.len()..]
+                .find(tag)
+                .map_or(rest.len(), |end| end + 2 * tag.len());
+            current.push_str(&rest[..length]);
+            length
+        } else if c == ';' {
+            statements.push(current.trim().to_string());
+            current.clear();
+            1
+        } else {
+            current.push(c);
+            c.len_utf8()
+        };
+        rest = &rest[length..];
+    }
+    statements.push(current.trim().to_string());
+    statements.retain(|statement| !statement.is_empty());
+    statements
+}
+
+/// The tag starting a dollar quoted string, like `$$` or `$body$`.
+fn dollar_quote_tag(sql: &str) -> Option<&str> {
+    if !sql.starts_with('$') || sql[1..].starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let end = sql[1..].find(|c: char| !c.is_ascii_alphanumeric() && c != '_')? + 1;
+    if sql[end..].starts_with('$') {
+        Some(&sql[..=end])
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::pg_connection;
+
+    fn migration(up_sql: &str) -> Migration {
+        Migration {
+            version: "20200405120000".into(),
+            name: "2020-04-05-120000_test".into(),
+            up_sql: up_sql.into(),
+        }
+    }
+
+    fn rules(up_sql: &str) -> Vec<&'static str> {
+        migration(up_sql)
+            .lint()
+            .into_iter()
+            .map(|violation| violation.rule)
+            .collect()
+    }
+
+    #[test]
+    fn statements_are_split_at_semicolons_outside_of_quotes() {
+        let sql = r#"
+-- A comment; with a semicolon
+CREATE TABLE "a;b" (name TEXT DEFAULT 'x;y');
+/* another; comment */
+CREATE FUNCTION f() RETURNS trigger AS $body$
+BEGIN
+  NEW.name := 'z'; RETURN NEW;
+END;
+$body$ LANGUAGE plpgsql;
+SELECT 1"#;
+        let statements = split_statements(sql);
+        assert_eq!(statements.len(), 3);
+        assert_eq!(
+            statements[0],
+            r#"CREATE TABLE "a;b" (name TEXT DEFAULT 'x;y')"#
+        );
+        assert!(statements[1].ends_with("$body$ LANGUAGE plpgsql"));
+        assert_eq!(statements[2], "SELECT 1");
+    }
+
+    #[test]
+    fn locking_statements_on_existing_tables_are_found() {
+        assert_eq!(
+            rules("CREATE INDEX crates_name ON crates (name);"),
+            vec!["index-not-concurrent"]
+        );
+        assert_eq!(
+            rules("CREATE UNIQUE INDEX CONCURRENTLY crates_name ON crates (name);"),
+            Vec::<&str>::new()
+        );
+        assert_eq!(
+            rules(
+                "ALTER TABLE versions ALTER COLUMN num TYPE TEXT;
+                 ALTER TABLE ONLY versions ALTER COLUMN license SET NOT NULL;
+                 ALTER TABLE versions ADD CONSTRAINT fk FOREIGN KEY (crate_id) REFERENCES crates;
+                 ALTER TABLE versions ADD CONSTRAINT fk2 FOREIGN KEY (crate_id) REFERENCES crates NOT VALID;
+                 ALTER TABLE versions ADD COLUMN targets TEXT[] NOT NULL DEFAULT '{}';"
+            ),
+            vec!["column-type-change", "set-not-null", "validated-foreign-key"]
+        );
+    }
+
+    #[test]
+    fn new_tables_and_allowed_rules_are_not_reported() {
+        assert!(rules(
+            "CREATE TABLE IF NOT EXISTS foo (id SERIAL PRIMARY KEY, name TEXT);
+             CREATE INDEX foo_name ON foo (name);
+             ALTER TABLE foo ALTER COLUMN name SET NOT NULL;"
+        )
+        .is_empty());
+        assert!(rules(
+            "-- migration-safety: allow index-not-concurrent, set-not-null
+             CREATE INDEX categories_slug ON categories (slug);"
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn concurrent_index_changes_run_outside_of_a_transaction() {
+        assert!(
+            !migration("CREATE INDEX CONCURRENTLY crates_name ON crates (name);")
+                .runs_in_transaction()
+        );
+        assert!(!migration("drop index concurrently if exists crates_name;").runs_in_transaction());
+        assert!(migration(
+            "CREATE INDEX crates_name ON crates (name);
+             CREATE FUNCTION refresh() RETURNS VOID AS $$
+               REFRESH MATERIALIZED VIEW CONCURRENTLY recent_crate_downloads;
+             $$ LANGUAGE SQL;"
+        )
+        .runs_in_transaction());
+    }
+
+    /// The migrations before 2020 ran before these checks existed.
+    #[test]
+    fn migrations_pass_the_checks() {
+        let migrations = Migration::load_all(Path::new("migrations")).unwrap();
+        assert!(!migrations.is_empty());
+        let violations = migrations
+            .iter()
+            .filter(|migration| migration.version.as_str() >= "20200101000000")
+            .flat_map(|migration| {
+                migration
+                    .lint()
+                    .into_iter()
+                    .map(move |violation| format!("{}: {}", migration.name, violation.reason))
+            })
+            .collect::<Vec<_>>();
+        assert!(violations.is_empty(), "{:#?}", violations);
+    }
+
+    #[test]
+    fn applied_migrations_are_measured_and_recorded() {
+        let conn = pg_connection();
+        conn.batch_execute("CREATE TABLE migration_safety_test (name TEXT)")
+            .unwrap();
+
+        let migration = migration(
+            "ALTER TABLE migration_safety_test ADD COLUMN size INTEGER;
+             CREATE INDEX migration_safety_test_name ON migration_safety_test (name);",
+        );
+        let report = migration.apply_measured(&conn).unwrap();
+        assert_eq!(report.statements.len(), 2);
+        assert!(report
+            .locks
+            .iter()
+            .any(|lock| lock.relation == "migration_safety_test" && lock.blocks_writes()));
+        assert_eq!(report.violations[0].rule, "index-not-concurrent");
+
+        let pending = Migration::pending(&conn, vec![migration]).unwrap();
+        assert!(pending.is_empty());
+    }
+}
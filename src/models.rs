@@ -1,35 +1,109 @@
 pub use self::action::{insert_version_owner_action, VersionAction, VersionOwnerAction};
 pub use self::badge::{Badge, CrateBadge, MaintenanceStatus};
 pub use self::category::{Category, CrateCategory, NewCategory};
+pub use self::config_override::ConfigOverride;
+pub use self::crate_link::{CrateLinkEvent, CrateLinks};
 pub use self::crate_owner_invitation::{CrateOwnerInvitation, NewCrateOwnerInvitation};
+pub use self::crate_policy::{CratePolicy, PolicyFile};
+pub use self::crate_quality::CrateQuality;
+pub use self::crate_upload_limit::CrateUploadLimit;
+pub use self::deleted_crate::DeletedCrate;
 pub use self::dependency::{Dependency, DependencyKind, ReverseDependency};
+pub use self::dependency_cycle::DependencyCycle;
+pub use self::dependency_freshness::DependencyFreshness;
+pub use self::deprecated_endpoint_usage::DeprecatedEndpointUsage;
 pub use self::download::VersionDownload;
+pub use self::download_anomaly::DownloadAnomaly;
+pub use self::ecosystem_stats::EcosystemStats;
 pub use self::email::{Email, NewEmail};
+pub use self::feature_docs::VersionFeatureDoc;
 pub use self::follow::Follow;
+pub use self::funding::FundingLink;
+pub use self::idempotency_key::PublishIdempotencyKey;
+pub use self::index_config::{IndexConfig, IndexSettings};
+pub use self::index_signing_key::IndexSigningKey;
+pub use self::index_squash::IndexSquash;
+pub use self::index_sync::IndexSync;
 pub use self::keyword::{CrateKeyword, Keyword};
 pub use self::krate::{Crate, CrateVersions, NewCrate, RecentCrateDownloads};
+pub use self::metadata_history::{CrateMetadata, CrateMetadataChange};
+pub use self::org_domain::OrgDomain;
 pub use self::owner::{CrateOwner, Owner, OwnerKind};
+pub use self::publish_job::PublishJob;
+pub use self::release_stats::{NewReleaseStats, ReleaseStats};
 pub use self::rights::Rights;
-pub use self::team::{NewTeam, Team};
+pub use self::search_synonym::SearchSynonym;
+pub use self::security_partner::{SecurityPartner, VersionVerdict};
+pub use self::security_policy::CrateSecurityPolicy;
+pub use self::service_consumer::ServiceConsumer;
+pub use self::squatting_report::{SquattingHeuristics, SquattingReport};
+pub use self::team::{is_org_admin, NewTeam, Team};
 pub use self::token::ApiToken;
+pub use self::transparency_log::{SignedTreeHead, TransparencyLogEntry};
 pub use self::user::{NewUser, User};
-pub use self::version::{NewVersion, Version};
+pub use self::user_merge::{MergeReport, UserMerge};
+pub use self::version::{CompressionFormat, NewVersion, PublishChannel, Version};
+pub use self::version_analysis::VersionAnalysis;
+pub use self::version_file::VersionFile;
+pub use self::version_fingerprint::{ContentMatch, VersionFingerprint};
+pub use self::version_install_check::VersionInstallCheck;
+pub use self::version_scan_result::{ScanHold, VersionScanResult};
+pub use self::webhook::{Webhook, WebhookDelivery};
+pub use self::yank_event::YankEvent;
 
 pub mod helpers;
 
 mod action;
 mod badge;
 pub mod category;
+mod config_override;
+mod crate_link;
 mod crate_owner_invitation;
+pub mod crate_policy;
+pub mod crate_quality;
+mod crate_upload_limit;
+pub mod default_versions;
+pub mod deleted_crate;
 pub mod dependency;
+mod dependency_cycle;
+pub mod dependency_freshness;
+mod deprecated_endpoint_usage;
 mod download;
+mod download_anomaly;
+mod ecosystem_stats;
 mod email;
+pub mod feature_docs;
 mod follow;
+pub mod funding;
+mod idempotency_key;
+pub mod index_config;
+pub mod index_signing_key;
+pub mod index_squash;
+pub mod index_sync;
 mod keyword;
 pub mod krate;
+mod metadata_history;
+pub mod org_domain;
 mod owner;
+pub mod publish_job;
+mod release_stats;
 mod rights;
+pub mod search_synonym;
+pub mod security_partner;
+pub mod security_policy;
+pub mod service_consumer;
+pub mod squatting_report;
+pub mod targets;
 mod team;
 mod token;
+mod transparency_log;
 pub mod user;
+mod user_merge;
 mod version;
+mod version_analysis;
+mod version_file;
+mod version_fingerprint;
+mod version_install_check;
+mod version_scan_result;
+pub mod webhook;
+mod yank_event;
@@ -55,7 +55,7 @@ impl ToSql<Integer, Pg> for VersionAction {
     }
 }
 
-#[derive(Debug, Clone, Copy, Queryable, Identifiable, Associations)]
+#[derive(Debug, Clone, Queryable, Identifiable, Associations)]
 #[belongs_to(Version)]
 #[belongs_to(User, foreign_key = "user_id")]
 #[belongs_to(ApiToken, foreign_key = "api_token_id")]
@@ -67,6 +67,9 @@ pub struct VersionOwnerAction {
     pub api_token_id: Option<i32>,
     pub action: VersionAction,
     pub time: NaiveDateTime,
+    /// An optional explanation given by the owner, e.g. why a version was
+    /// yanked.
+    pub reason: Option<String>,
 }
 
 impl VersionOwnerAction {
@@ -102,8 +105,9 @@ pub fn insert_version_owner_action(
     user_id_: i32,
     api_token_id_: Option<i32>,
     action_: VersionAction,
+    reason_: Option<&str>,
 ) -> QueryResult<VersionOwnerAction> {
-    use version_owner_actions::dsl::{action, api_token_id, user_id, version_id};
+    use version_owner_actions::dsl::{action, api_token_id, reason, user_id, version_id};
 
     diesel::insert_into(version_owner_actions::table)
         .values((
@@ -111,6 +115,7 @@ pub fn insert_version_owner_action(
             user_id.eq(user_id_),
             api_token_id.eq(api_token_id_),
             action.eq(action_),
+            reason.eq(reason_),
         ))
         .get_result(conn)
 }
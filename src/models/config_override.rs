@@ -0,0 +1,47 @@
+use chrono::NaiveDateTime;
+use diesel::dsl::now;
+use diesel::prelude::*;
+
+use crate::schema::config_overrides;
+
+/// An environment variable set in the database with `crates-io-admin
+/// set-config`, which takes precedence over the other layers of the
+/// configuration the next time the server or the background worker starts.
+#[derive(Clone, Debug, PartialEq, Eq, Queryable, Identifiable)]
+#[primary_key(name)]
+pub struct ConfigOverride {
+    pub name: String,
+    pub value: String,
+    pub updated_at: NaiveDateTime,
+}
+
+impl ConfigOverride {
+    pub fn all(conn: &PgConnection) -> QueryResult<Vec<Self>> {
+        config_overrides::table
+            .order(config_overrides::name)
+            .load(conn)
+    }
+
+    /// Sets a variable, replacing its previous value.
+    pub fn set(conn: &PgConnection, name: &str, value: &str) -> QueryResult<()> {
+        diesel::insert_into(config_overrides::table)
+            .values((
+                config_overrides::name.eq(name),
+                config_overrides::value.eq(value),
+            ))
+            .on_conflict(config_overrides::name)
+            .do_update()
+            .set((
+                config_overrides::value.eq(value),
+                config_overrides::updated_at.eq(now),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Removes a variable, returning whether it was set.
+    pub fn remove(conn: &PgConnection, name: &str) -> QueryResult<bool> {
+        let deleted = diesel::delete(config_overrides::table.find(name)).execute(conn)?;
+        Ok(deleted > 0)
+    }
+}
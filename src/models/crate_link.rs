@@ -0,0 +1,206 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use crate::models::User;
+use crate::schema::{crate_link_events, crate_links, users};
+use crate::util::errors::{cargo_err, AppResult};
+use crate::views::{EncodableCrateLinkEvent, EncodableCustomLinks};
+
+/// The maximum length of a link, in bytes.
+const MAX_URL_LENGTH: usize = 512;
+
+/// The hosts chat links may point to.
+const CHAT_HOSTS: &[&str] = &["matrix.to", "discord.gg", "discord.com"];
+
+const FUNDING: &str = "funding";
+const CHAT: &str = "chat";
+const SECURITY_POLICY: &str = "security_policy";
+
+/// The links owners can add to a crate in addition to the ones from its
+/// manifest.
+#[derive(Clone, Copy, Debug)]
+pub struct CrateLinks;
+
+impl CrateLinks {
+    pub fn load(conn: &PgConnection, crate_id: i32) -> QueryResult<EncodableCustomLinks> {
+        let rows = crate_links::table
+            .filter(crate_links::crate_id.eq(crate_id))
+            .select((crate_links::kind, crate_links::url))
+            .load::<(String, String)>(conn)?;
+
+        let mut links = EncodableCustomLinks::default();
+        for (kind, url) in rows {
+            match kind.as_str() {
+                FUNDING => links.funding = Some(url),
+                CHAT => links.chat = Some(url),
+                SECURITY_POLICY => links.security_policy = Some(url),
+                _ => {}
+            }
+        }
+        Ok(links)
+    }
+
+    /// Replaces the links of a crate, and records a `CrateLinkEvent` for
+    /// every link that changed. Blank links are removed.
+    pub fn update(
+        conn: &PgConnection,
+        crate_id: i32,
+        user_id: i32,
+        links: &EncodableCustomLinks,
+    ) -> AppResult<EncodableCustomLinks> {
+        let new = entries(links);
+        for &(kind, url) in &new {
+            if let Some(url) = url {
+                validate(kind, url)?;
+            }
+        }
+
+        conn.transaction(|| {
+            let old_links = Self::load(conn, crate_id)?;
+            let old = entries(&old_links);
+            for (&(kind, url), &(_, old_url)) in new.iter().zip(old.iter()) {
+                if url == old_url {
+                    continue;
+                }
+
+                match url {
+                    Some(url) => {
+                        diesel::insert_into(crate_links::table)
+                            .values((
+                                crate_links::crate_id.eq(crate_id),
+                                crate_links::kind.eq(kind),
+                                crate_links::url.eq(url),
+                            ))
+                            .on_conflict((crate_links::crate_id, crate_links::kind))
+                            .do_update()
+                            .set(crate_links::url.eq(url))
+                            .execute(conn)?;
+                    }
+                    None => {
+                        diesel::delete(crate_links::table.find((crate_id, kind))).execute(conn)?;
+                    }
+                }
+                diesel::insert_into(crate_link_events::table)
+                    .values((
+                        crate_link_events::crate_id.eq(crate_id),
+                        crate_link_events::user_id.eq(user_id),
+                        crate_link_events::kind.eq(kind),
+                        crate_link_events::url.eq(url),
+                    ))
+                    .execute(conn)?;
+            }
+            Ok(Self::load(conn, crate_id)?)
+        })
+    }
+}
+
+/// The links by kind, with surrounding whitespace removed and blank links
+/// treated as missing.
+fn entries(links: &EncodableCustomLinks) -> [(&'static str, Option<&str>); 3] {
+    fn normalize(url: &Option<String>) -> Option<&str> {
+        url.as_ref()
+            .map(|url| url.trim())
+            .filter(|url| !url.is_empty())
+    }
+
+    [
+        (FUNDING, normalize(&links.funding)),
+        (CHAT, normalize(&links.chat)),
+        (SECURITY_POLICY, normalize(&links.security_policy)),
+    ]
+}
+
+fn validate(kind: &str, url: &str) -> AppResult<()> {
+    if url.len() > MAX_URL_LENGTH {
+        return Err(cargo_err(&format_args!(
+            "the {} link must not be longer than {} bytes",
+            kind, MAX_URL_LENGTH
+        )));
+    }
+    let parsed = url::Url::parse(url)
+        .map_err(|_| cargo_err(&format_args!("the {} link `{}` is not a URL", kind, url)))?;
+    if parsed.scheme() != "https" {
+        return Err(cargo_err(&format_args!(
+            "the {} link `{}` must use https",
+            kind, url
+        )));
+    }
+    if kind == CHAT {
+        let host = parsed.host_str().unwrap_or_default();
+        if !CHAT_HOSTS.contains(&host) {
+            return Err(cargo_err(&format_args!(
+                "the chat link must point to one of {}",
+                CHAT_HOSTS.join(", ")
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// A change of one of the links of a crate.
+#[derive(Debug, Clone, Queryable, Identifiable)]
+pub struct CrateLinkEvent {
+    pub id: i32,
+    pub crate_id: i32,
+    pub user_id: i32,
+    pub kind: String,
+    pub url: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl CrateLinkEvent {
+    /// All changes of the links of the given crate, oldest first.
+    pub fn by_crate(conn: &PgConnection, crate_id: i32) -> QueryResult<Vec<(Self, User)>> {
+        crate_link_events::table
+            .inner_join(users::table)
+            .filter(crate_link_events::crate_id.eq(crate_id))
+            .order((crate_link_events::created_at, crate_link_events::id))
+            .load(conn)
+    }
+
+    pub fn encodable(self, user: User) -> EncodableCrateLinkEvent {
+        EncodableCrateLinkEvent {
+            kind: self.kind,
+            url: self.url,
+            user: user.encodable_public(),
+            time: self.created_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links_are_validated() {
+        assert!(validate(FUNDING, "https://github.com/sponsors/someone").is_ok());
+        assert!(validate(FUNDING, "http://github.com/sponsors/someone").is_err());
+        assert!(validate(FUNDING, "github.com/sponsors/someone").is_err());
+        assert!(validate(
+            SECURITY_POLICY,
+            &format!("https://x.io/{}", "a".repeat(512))
+        )
+        .is_err());
+        assert!(validate(CHAT, "https://matrix.to/#/#rust:matrix.org").is_ok());
+        assert!(validate(CHAT, "https://discord.gg/rust-lang").is_ok());
+        assert!(validate(CHAT, "https://example.com/chat").is_err());
+    }
+
+    #[test]
+    fn blank_links_are_missing() {
+        let links = EncodableCustomLinks {
+            funding: Some("  ".into()),
+            chat: None,
+            security_policy: Some(" https://example.com/SECURITY.md ".into()),
+        };
+        assert_eq!(
+            entries(&links),
+            [
+                (FUNDING, None),
+                (CHAT, None),
+                (SECURITY_POLICY, Some("https://example.com/SECURITY.md")),
+            ]
+        );
+    }
+}
@@ -0,0 +1,184 @@
+use diesel::prelude::*;
+
+use crate::models::Crate;
+use crate::schema::crate_policies;
+use crate::util::errors::{cargo_err, AppResult};
+use crate::views::{EncodableCratePolicy, EncodableDeprecation};
+
+/// The name of the policy file, relative to the root of a crate.
+pub const POLICY_FILE: &str = ".crates-io.toml";
+
+/// The policy declared in the `.crates-io.toml` file of the most recently
+/// published version of a crate.
+#[derive(Clone, Debug, PartialEq, Eq, Queryable, Identifiable, Insertable, AsChangeset)]
+#[primary_key(crate_id)]
+#[table_name = "crate_policies"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct CratePolicy {
+    pub crate_id: i32,
+    pub version_id: i32,
+    pub replaced_by: Option<String>,
+    pub deprecation_message: Option<String>,
+}
+
+/// The format of the `.crates-io.toml` policy file.
+///
+/// ```toml
+/// [deprecation]
+/// replaced-by = "new-crate"
+/// message = "This crate is no longer maintained."
+/// ```
+///
+/// A `[publish]` table, for required CI checks and publish approvals, is
+/// rejected until publishing enforces it.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PolicyFile {
+    publish: Option<toml::Value>,
+    deprecation: Option<DeprecationPolicy>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct DeprecationPolicy {
+    replaced_by: Option<String>,
+    message: Option<String>,
+}
+
+impl PolicyFile {
+    /// Parses and validates the contents of a policy file.
+    pub fn parse(contents: &str) -> AppResult<Self> {
+        let file: PolicyFile = toml::from_str(contents)
+            .map_err(|e| cargo_err(&format_args!("invalid `{}`: {}", POLICY_FILE, e)))?;
+
+        if file.publish.is_some() {
+            return Err(cargo_err(&format_args!(
+                "invalid `{}`: `[publish]` policies are not supported yet",
+                POLICY_FILE
+            )));
+        }
+
+        let replaced_by = file
+            .deprecation
+            .as_ref()
+            .and_then(|deprecation| deprecation.replaced_by.as_ref());
+        if let Some(name) = replaced_by {
+            if !Crate::valid_name(name) {
+                return Err(cargo_err(&format_args!(
+                    "invalid `{}`: `{}` is not a valid crate name",
+                    POLICY_FILE, name
+                )));
+            }
+        }
+
+        Ok(file)
+    }
+}
+
+impl CratePolicy {
+    pub fn new(crate_id: i32, version_id: i32, file: PolicyFile) -> Self {
+        let (replaced_by, deprecation_message) = match file.deprecation {
+            Some(deprecation) => (deprecation.replaced_by, deprecation.message),
+            None => (None, None),
+        };
+
+        Self {
+            crate_id,
+            version_id,
+            replaced_by,
+            deprecation_message,
+        }
+    }
+
+    /// Replaces the policy of a crate with the one published with the given
+    /// version.
+    ///
+    /// Publishing a version without a policy file removes the crate's policy,
+    /// so that the stored policy always reflects the most recent release.
+    pub fn update_crate(
+        conn: &PgConnection,
+        crate_id: i32,
+        version_id: i32,
+        policy_file: Option<PolicyFile>,
+    ) -> QueryResult<()> {
+        let policy = policy_file.map(|file| Self::new(crate_id, version_id, file));
+        Self::replace(conn, crate_id, policy)
+    }
+
+    /// Replaces the policy of a crate, or removes it.
+    pub fn replace(conn: &PgConnection, crate_id: i32, policy: Option<Self>) -> QueryResult<()> {
+        match policy {
+            Some(policy) => {
+                diesel::insert_into(crate_policies::table)
+                    .values(&policy)
+                    .on_conflict(crate_policies::crate_id)
+                    .do_update()
+                    .set(&policy)
+                    .execute(conn)?;
+            }
+            None => {
+                diesel::delete(crate_policies::table.find(crate_id)).execute(conn)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn encodable(self, version: String) -> EncodableCratePolicy {
+        let deprecation = if self.replaced_by.is_some() || self.deprecation_message.is_some() {
+            Some(EncodableDeprecation {
+                replaced_by: self.replaced_by,
+                message: self.deprecation_message,
+            })
+        } else {
+            None
+        };
+
+        EncodableCratePolicy {
+            version,
+            deprecation,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CratePolicy, PolicyFile};
+
+    #[test]
+    fn parse_full_policy() {
+        let file = PolicyFile::parse(
+            r#"
+[deprecation]
+replaced-by = "new-crate"
+message = "Use new-crate instead."
+"#,
+        )
+        .unwrap();
+        let policy = CratePolicy::new(1, 2, file);
+
+        assert_eq!(policy.crate_id, 1);
+        assert_eq!(policy.version_id, 2);
+        assert_eq!(policy.replaced_by.as_deref(), Some("new-crate"));
+        assert_eq!(
+            policy.deprecation_message.as_deref(),
+            Some("Use new-crate instead.")
+        );
+    }
+
+    #[test]
+    fn parse_empty_policy() {
+        let policy = CratePolicy::new(1, 2, PolicyFile::parse("").unwrap());
+        assert_eq!(policy.replaced_by, None);
+        assert_eq!(policy.deprecation_message, None);
+    }
+
+    #[test]
+    fn parse_rejects_invalid_policies() {
+        let err = |contents| PolicyFile::parse(contents).unwrap_err().to_string();
+
+        assert!(err("[deprecation]\nreplaced_by = \"bar\"").contains("unknown field"));
+        assert!(err("[publish]\nrequire-approval = true").contains("not supported yet"));
+        assert!(err("[deprecation]\nreplaced-by = \"no spaces\"").contains("not a valid"));
+        assert!(err("deprecation = 1").contains("invalid `.crates-io.toml`"));
+    }
+}
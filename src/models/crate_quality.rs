@@ -0,0 +1,146 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use crate::schema::crate_quality;
+use crate::views::EncodableCrateQuality;
+
+/// How much each of the five quality signals contributes to the score.
+const SIGNAL_WEIGHT: f32 = 0.2;
+
+/// READMEs of at least this many characters get the full README score.
+const FULL_README_LENGTH: i32 = 2000;
+
+/// Crates with at least this many releases in the last year get the full
+/// release cadence score.
+const FULL_RELEASES_PER_YEAR: i32 = 4;
+
+/// Quality signals of a crate, recomputed nightly by the
+/// `compute_crate_quality` job and used by `sort=quality`.
+#[derive(Clone, Debug, PartialEq, Queryable, Identifiable)]
+#[primary_key(crate_id)]
+#[table_name = "crate_quality"]
+pub struct CrateQuality {
+    pub crate_id: i32,
+    pub has_documentation: bool,
+    pub readme_length: i32,
+    pub has_tests: Option<bool>,
+    pub releases_last_year: i32,
+    pub yanked_ratio: f32,
+    pub score: f32,
+    pub computed_at: NaiveDateTime,
+}
+
+/// The raw signals a quality score is computed from.
+#[derive(Clone, Debug, PartialEq, Insertable, AsChangeset)]
+#[primary_key(crate_id)]
+#[table_name = "crate_quality"]
+pub struct QualitySignals {
+    pub crate_id: i32,
+    pub has_documentation: bool,
+    pub readme_length: i32,
+    pub releases_last_year: i32,
+    pub yanked_ratio: f32,
+}
+
+impl QualitySignals {
+    /// Combines the signals into a score between 0 and 1.
+    ///
+    /// `has_tests` is only known for crates published since it's recorded,
+    /// and counts as missing otherwise.
+    pub fn score(&self, has_tests: Option<bool>) -> f32 {
+        let ratio = |value: i32, full: i32| (value.max(0) as f32 / full as f32).min(1.0);
+        let flag = |value: bool| if value { 1.0 } else { 0.0 };
+
+        let signals = [
+            flag(self.has_documentation),
+            ratio(self.readme_length, FULL_README_LENGTH),
+            flag(has_tests.unwrap_or(false)),
+            ratio(self.releases_last_year, FULL_RELEASES_PER_YEAR),
+            1.0 - self.yanked_ratio.max(0.0).min(1.0),
+        ];
+        signals.iter().map(|signal| signal * SIGNAL_WEIGHT).sum()
+    }
+}
+
+impl CrateQuality {
+    /// Records whether the most recently published version of a crate
+    /// contains a `tests` directory.
+    ///
+    /// The remaining signals are left alone until the next run of the
+    /// `compute_crate_quality` job.
+    pub fn record_tests(conn: &PgConnection, crate_id: i32, has_tests: bool) -> QueryResult<()> {
+        diesel::insert_into(crate_quality::table)
+            .values((
+                crate_quality::crate_id.eq(crate_id),
+                crate_quality::has_tests.eq(has_tests),
+            ))
+            .on_conflict(crate_quality::crate_id)
+            .do_update()
+            .set(crate_quality::has_tests.eq(has_tests))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn encodable(self) -> EncodableCrateQuality {
+        EncodableCrateQuality {
+            score: self.score,
+            has_documentation: self.has_documentation,
+            readme_length: self.readme_length,
+            has_tests: self.has_tests,
+            releases_last_year: self.releases_last_year,
+            yanked_ratio: self.yanked_ratio,
+            computed_at: self.computed_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::QualitySignals;
+
+    fn signals() -> QualitySignals {
+        QualitySignals {
+            crate_id: 1,
+            has_documentation: true,
+            readme_length: 5000,
+            releases_last_year: 12,
+            yanked_ratio: 0.0,
+        }
+    }
+
+    fn assert_score(signals: &QualitySignals, has_tests: Option<bool>, expected: f32) {
+        let score = signals.score(has_tests);
+        assert!((score - expected).abs() < 1e-6, "{} != {}", score, expected);
+    }
+
+    #[test]
+    fn score_of_a_perfect_crate() {
+        assert_score(&signals(), Some(true), 1.0);
+        assert_score(&signals(), Some(false), 0.8);
+        assert_score(&signals(), None, 0.8);
+    }
+
+    #[test]
+    fn partial_signals() {
+        let signals = QualitySignals {
+            has_documentation: false,
+            readme_length: 500,
+            releases_last_year: 1,
+            yanked_ratio: 0.5,
+            ..signals()
+        };
+        assert_score(&signals, Some(true), 0.2 + 0.05 + 0.05 + 0.1);
+    }
+
+    #[test]
+    fn score_is_bounded() {
+        let signals = QualitySignals {
+            has_documentation: false,
+            readme_length: -1,
+            releases_last_year: 0,
+            yanked_ratio: 2.0,
+            ..signals()
+        };
+        assert_score(&signals, None, 0.0);
+    }
+}
@@ -0,0 +1,82 @@
+use chrono::NaiveDateTime;
+use diesel::dsl::now;
+use diesel::prelude::*;
+
+use crate::schema::crate_upload_limits;
+use crate::views::EncodableCrateUploadLimit;
+
+/// An upload size limit of a crate set by an admin, usually to let it publish
+/// a larger crate file for a while without a deploy. It takes precedence over
+/// `Crate::max_upload_size` and `Config::max_upload_size` until it expires.
+#[derive(Clone, Debug, PartialEq, Eq, Queryable, Identifiable)]
+#[primary_key(crate_id)]
+pub struct CrateUploadLimit {
+    pub crate_id: i32,
+    pub max_upload_size: i32,
+    /// Why the limit was set, e.g. a link to the support request.
+    pub reason: String,
+    /// When the limit stops applying, or `None` if it's permanent.
+    pub expires_at: Option<NaiveDateTime>,
+    /// The admin who set the limit.
+    pub created_by: i32,
+    pub created_at: NaiveDateTime,
+}
+
+impl CrateUploadLimit {
+    /// Sets the limit of a crate, replacing the one set before.
+    pub fn set(
+        conn: &PgConnection,
+        crate_id: i32,
+        max_upload_size: i32,
+        reason: &str,
+        expires_at: Option<NaiveDateTime>,
+        created_by: i32,
+    ) -> QueryResult<Self> {
+        use self::crate_upload_limits::dsl;
+
+        let values = (
+            dsl::crate_id.eq(crate_id),
+            dsl::max_upload_size.eq(max_upload_size),
+            dsl::reason.eq(reason),
+            dsl::expires_at.eq(expires_at),
+            dsl::created_by.eq(created_by),
+            dsl::created_at.eq(now),
+        );
+        diesel::insert_into(crate_upload_limits::table)
+            .values(values)
+            .on_conflict(dsl::crate_id)
+            .do_update()
+            .set(values)
+            .get_result(conn)
+    }
+
+    /// Removes the limit of a crate. Returns whether it had one.
+    pub fn remove(conn: &PgConnection, crate_id: i32) -> QueryResult<bool> {
+        let deleted = diesel::delete(crate_upload_limits::table.find(crate_id)).execute(conn)?;
+        Ok(deleted > 0)
+    }
+
+    /// The upload size limit set for a crate, if it hasn't expired yet.
+    pub fn active_for(conn: &PgConnection, crate_id: i32) -> QueryResult<Option<i32>> {
+        crate_upload_limits::table
+            .find(crate_id)
+            .filter(
+                crate_upload_limits::expires_at
+                    .is_null()
+                    .or(crate_upload_limits::expires_at.gt(now)),
+            )
+            .select(crate_upload_limits::max_upload_size)
+            .first(conn)
+            .optional()
+    }
+
+    pub fn encodable(self, crate_name: &str) -> EncodableCrateUploadLimit {
+        EncodableCrateUploadLimit {
+            krate: crate_name.to_string(),
+            max_upload_size: self.max_upload_size,
+            reason: self.reason,
+            expires_at: self.expires_at,
+            created_at: self.created_at,
+        }
+    }
+}
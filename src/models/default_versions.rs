@@ -0,0 +1,130 @@
+use diesel::prelude::*;
+
+use crate::schema::{default_versions, versions};
+
+/// A subset of the columns of the `versions` table.
+///
+/// This struct is used to load all versions of a crate from the database,
+/// without loading all the other data that is not needed to calculate the
+/// default version of a crate.
+#[derive(Clone, Debug, PartialEq, Eq, Queryable)]
+struct Version {
+    id: i32,
+    num: semver::Version,
+    yanked: bool,
+}
+
+impl Version {
+    /// Returns the `(not yanked, stable, num, id)` tuple used to determine
+    /// the default version. Versions with a higher value are preferred.
+    fn ord_tuple(&self) -> (bool, bool, &semver::Version, i32) {
+        (!self.yanked, !self.num.is_prerelease(), &self.num, self.id)
+    }
+}
+
+/// Updates the `default_versions` table entry for the specified crate.
+///
+/// This function first loads all versions of the crate from the database,
+/// then determines the default version based on the following criteria:
+///
+/// 1. The highest non-prerelease version that is not yanked.
+/// 2. The highest non-yanked version.
+/// 3. The highest version.
+///
+/// The default version is then written to the `default_versions` table.
+pub fn update_default_version(crate_id: i32, conn: &PgConnection) -> QueryResult<()> {
+    let default_version = calculate_default_version(crate_id, conn)?;
+
+    diesel::insert_into(default_versions::table)
+        .values((
+            default_versions::crate_id.eq(crate_id),
+            default_versions::version_id.eq(default_version.id),
+        ))
+        .on_conflict(default_versions::crate_id)
+        .do_update()
+        .set(default_versions::version_id.eq(default_version.id))
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Verifies that the default version for the specified crate is up-to-date.
+///
+/// Returns `Ok(None)` if the stored default version is correct, or the
+/// `(stored, expected)` version IDs if it has drifted. A missing row is
+/// reported with a stored version ID of `None`.
+pub fn verify_default_version(
+    crate_id: i32,
+    conn: &PgConnection,
+) -> QueryResult<Option<(Option<i32>, i32)>> {
+    let calculated = calculate_default_version(crate_id, conn)?;
+
+    let saved = default_versions::table
+        .select(default_versions::version_id)
+        .filter(default_versions::crate_id.eq(crate_id))
+        .first::<i32>(conn)
+        .optional()?;
+
+    if saved == Some(calculated.id) {
+        Ok(None)
+    } else {
+        Ok(Some((saved, calculated.id)))
+    }
+}
+
+fn calculate_default_version(crate_id: i32, conn: &PgConnection) -> QueryResult<Version> {
+    let versions = versions::table
+        .filter(versions::crate_id.eq(crate_id))
+        .select((versions::id, versions::num, versions::yanked))
+        .load::<Version>(conn)?;
+
+    find_default_version(&versions)
+        .cloned()
+        .ok_or(diesel::result::Error::NotFound)
+}
+
+fn find_default_version(versions: &[Version]) -> Option<&Version> {
+    versions
+        .iter()
+        .max_by(|a, b| a.ord_tuple().cmp(&b.ord_tuple()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(id: i32, num: &str, yanked: bool) -> Version {
+        Version {
+            id,
+            num: semver::Version::parse(num).unwrap(),
+            yanked,
+        }
+    }
+
+    #[test]
+    fn find_default_version_prefers_stable_non_yanked() {
+        let versions = vec![
+            v(1, "1.0.0", false),
+            v(2, "1.1.0", true),
+            v(3, "2.0.0-beta.1", false),
+        ];
+        assert_eq!(find_default_version(&versions).unwrap().id, 1);
+    }
+
+    #[test]
+    fn find_default_version_falls_back_to_prerelease() {
+        let versions = vec![v(1, "1.0.0", true), v(2, "2.0.0-beta.1", false)];
+        assert_eq!(find_default_version(&versions).unwrap().id, 2);
+    }
+
+    #[test]
+    fn find_default_version_falls_back_to_yanked() {
+        let versions = vec![v(1, "1.0.0", true), v(2, "0.9.0", true)];
+        assert_eq!(find_default_version(&versions).unwrap().id, 1);
+    }
+
+    #[test]
+    fn find_default_version_empty() {
+        assert!(find_default_version(&[]).is_none());
+    }
+}
@@ -0,0 +1,48 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use crate::schema::deleted_crates;
+use crate::views::EncodableDeletedCrate;
+
+/// The coarse reasons a crate or version can be deleted for, which are
+/// published as they are. Any details stay with the team who deleted it.
+pub const REASONS: &[&str] = &["malware", "legal", "policy", "owner_request", "other"];
+
+/// A crate, or a single version of it, which was removed from the registry.
+#[derive(Clone, Debug, PartialEq, Eq, Identifiable, Queryable)]
+pub struct DeletedCrate {
+    pub id: i32,
+    pub name: String,
+    /// `None` if the whole crate was deleted.
+    pub version: Option<String>,
+    pub reason: String,
+    pub deleted_at: NaiveDateTime,
+}
+
+impl DeletedCrate {
+    /// Records the deletion of a crate, or of one of its versions. Must be
+    /// called in the transaction deleting it.
+    pub fn record(
+        conn: &PgConnection,
+        name: &str,
+        version: Option<&str>,
+        reason: &str,
+    ) -> QueryResult<Self> {
+        diesel::insert_into(deleted_crates::table)
+            .values((
+                deleted_crates::name.eq(name),
+                deleted_crates::version.eq(version),
+                deleted_crates::reason.eq(reason),
+            ))
+            .get_result(conn)
+    }
+
+    pub fn encodable(self) -> EncodableDeletedCrate {
+        EncodableDeletedCrate {
+            krate: self.name,
+            version: self.version,
+            reason: self.reason,
+            deleted_at: self.deleted_at,
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use diesel::prelude::*;
 
 use crate::git;
@@ -21,6 +23,9 @@ pub struct Dependency {
     pub features: Vec<String>,
     pub target: Option<String>,
     pub kind: DependencyKind,
+    /// The name the dependency was renamed to in the manifest. Unknown for
+    /// versions published before it was recorded.
+    pub explicit_name: Option<String>,
 }
 
 #[derive(Debug, QueryableByName)]
@@ -129,6 +134,7 @@ pub fn add_dependencies(
                     default_features.eq(dep.default_features),
                     features.eq(&dep.features),
                     target.eq(dep.target.as_deref()),
+                    explicit_name.eq(dep.explicit_name_in_toml.as_ref().map(|n| n.to_string())),
                 ),
             ))
         })
@@ -144,6 +150,81 @@ pub fn add_dependencies(
     Ok(git_deps)
 }
 
+/// Whether publishing checks that a version which isn't yanked matches the
+/// requirement of every dependency.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DependencyCheck {
+    Off,
+    /// Unmatched dependencies are listed in the warnings of the response.
+    Warn,
+    /// Unmatched dependencies reject the publish.
+    Deny,
+}
+
+impl DependencyCheck {
+    /// Reads `PUBLISH_DEPENDENCY_CHECK`, which is either `warn` or `deny`.
+    /// The check is off if it isn't set.
+    pub fn from_environment() -> Self {
+        match dotenv::var("PUBLISH_DEPENDENCY_CHECK").ok().as_deref() {
+            None | Some("off") => DependencyCheck::Off,
+            Some("warn") => DependencyCheck::Warn,
+            Some("deny") => DependencyCheck::Deny,
+            Some(s) => panic!("unknown PUBLISH_DEPENDENCY_CHECK `{}`", s),
+        }
+    }
+}
+
+/// The dependencies whose requirement isn't matched by any version that isn't
+/// yanked, formatted as `name req`. Dependencies on unknown crates are
+/// rejected by `add_dependencies`, so they're skipped here.
+pub fn unmatched_dependencies(
+    conn: &PgConnection,
+    deps: &[EncodableCrateDependency],
+) -> QueryResult<Vec<String>> {
+    let mut unmatched = Vec::new();
+    for dep in deps {
+        let krate = match Crate::by_exact_name(&dep.name)
+            .first::<Crate>(conn)
+            .optional()?
+        {
+            Some(krate) => krate,
+            None => continue,
+        };
+        let nums = Version::belonging_to(&krate)
+            .filter(versions::yanked.eq(false))
+            .select(versions::num)
+            .load::<String>(conn)?;
+        let matched = nums
+            .iter()
+            .filter_map(|num| semver::Version::parse(num).ok())
+            .any(|num| dep.version_req.matches(&num));
+        if !matched {
+            unmatched.push(format!("{} {}", &*dep.name, dep.version_req.0));
+        }
+    }
+    Ok(unmatched)
+}
+
+/// Whether the versions pinned by the lock file of a crate are yanked, by
+/// `(name, version)`. Versions which don't exist are left out.
+pub fn locked_versions(
+    conn: &PgConnection,
+    locked: &[(String, String)],
+) -> QueryResult<HashMap<(String, String), bool>> {
+    let names = locked.iter().map(|(name, _)| name).collect::<Vec<_>>();
+    let nums = locked.iter().map(|(_, num)| num).collect::<Vec<_>>();
+    let published = versions::table
+        .inner_join(crates::table)
+        .filter(crates::name.eq_any(names))
+        .filter(versions::num.eq_any(nums))
+        .select((crates::name, versions::num, versions::yanked))
+        .load::<(String, String, bool)>(conn)?;
+    Ok(published
+        .into_iter()
+        .map(|(name, num, yanked)| ((name, num), yanked))
+        .collect())
+}
+
 use diesel::deserialize::{self, FromSql};
 use diesel::pg::Pg;
 use diesel::sql_types::Integer;
@@ -0,0 +1,226 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+use crate::models::DependencyKind;
+use crate::schema::{crates, default_versions, dependencies, dependency_cycles, versions};
+use crate::views::EncodableDependencyCycle;
+
+/// Cycles longer than this many crates aren't searched for. Longer cycles
+/// are rare, and the graph grows quickly with every step.
+const MAX_CYCLE_LENGTH: usize = 6;
+
+/// A cycle in the crate-level dependency graph, made up of the dependencies
+/// of the default version of every crate.
+#[derive(Clone, Debug, PartialEq, Identifiable, Queryable)]
+pub struct DependencyCycle {
+    pub id: i32,
+    pub crate_id: i32,
+    pub version_id: i32,
+    pub crates: Vec<String>,
+    pub kinds: Vec<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl DependencyCycle {
+    /// Finds the cycles through the crate of the given version, using the
+    /// dependencies of that version instead of the ones of the default
+    /// version of the crate. Cycles made up only of normal dependencies are
+    /// skipped, since they can only exist between different versions of the
+    /// crates and don't affect tooling working on a workspace. Records the
+    /// cycles which weren't known yet, and returns how many there were.
+    pub fn detect(conn: &PgConnection, version_id: i32) -> QueryResult<usize> {
+        let crate_id = versions::table
+            .find(version_id)
+            .select(versions::crate_id)
+            .first::<i32>(conn)?;
+        let direct = dependencies::table
+            .filter(dependencies::version_id.eq(version_id))
+            .select((dependencies::crate_id, dependencies::kind))
+            .load::<(i32, DependencyKind)>(conn)?;
+
+        // Breadth-first search from the dependencies of the version back to
+        // its crate. Each crate is reached once, through the shortest path.
+        let mut parents = HashMap::<i32, (i32, DependencyKind)>::new();
+        let mut cycles = Vec::new();
+        let mut frontier = Vec::new();
+        for (dep_id, kind) in direct {
+            if dep_id == crate_id {
+                cycles.push(vec![(crate_id, kind)]);
+            } else if !parents.contains_key(&dep_id) {
+                parents.insert(dep_id, (crate_id, kind));
+                frontier.push(dep_id);
+            }
+        }
+
+        for _ in 1..MAX_CYCLE_LENGTH {
+            if frontier.is_empty() {
+                break;
+            }
+            let edges = default_versions::table
+                .inner_join(
+                    dependencies::table
+                        .on(dependencies::version_id.eq(default_versions::version_id)),
+                )
+                .filter(default_versions::crate_id.eq_any(&frontier))
+                .select((
+                    default_versions::crate_id,
+                    dependencies::crate_id,
+                    dependencies::kind,
+                ))
+                .load::<(i32, i32, DependencyKind)>(conn)?;
+
+            let mut next = Vec::new();
+            for (from, to, kind) in edges {
+                if to == crate_id {
+                    cycles.push(path_to(&parents, crate_id, from, kind));
+                } else if !parents.contains_key(&to) {
+                    parents.insert(to, (from, kind));
+                    next.push(to);
+                }
+            }
+            frontier = next;
+        }
+
+        let crate_ids = cycles
+            .iter()
+            .flatten()
+            .map(|&(id, _)| id)
+            .collect::<HashSet<_>>();
+        let names = crates::table
+            .filter(crates::id.eq_any(crate_ids.into_iter().collect::<Vec<_>>()))
+            .select((crates::id, crates::name))
+            .load::<(i32, String)>(conn)?
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+
+        let mut recorded = 0;
+        for cycle in cycles {
+            let only_normal = cycle.iter().all(|(_, kind)| match kind {
+                DependencyKind::Normal => true,
+                DependencyKind::Build | DependencyKind::Dev => false,
+            });
+            if only_normal {
+                continue;
+            }
+            let cycle = cycle
+                .into_iter()
+                .map(|(id, kind)| (names[&id].clone(), kind_name(kind).to_string()))
+                .collect();
+            let (crates, kinds): (Vec<_>, Vec<_>) = canonical(cycle).into_iter().unzip();
+            recorded += diesel::insert_into(dependency_cycles::table)
+                .values((
+                    dependency_cycles::crate_id.eq(crate_id),
+                    dependency_cycles::version_id.eq(version_id),
+                    dependency_cycles::crates.eq(crates),
+                    dependency_cycles::kinds.eq(kinds),
+                ))
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+        }
+        Ok(recorded)
+    }
+
+    /// The cycles introduced by publishes of the given crate, newest first,
+    /// with the number of the version which introduced them.
+    pub fn by_crate(conn: &PgConnection, crate_id: i32) -> QueryResult<Vec<(Self, String)>> {
+        dependency_cycles::table
+            .inner_join(versions::table)
+            .filter(dependency_cycles::crate_id.eq(crate_id))
+            .order((dependency_cycles::created_at.desc(), dependency_cycles::id))
+            .select((dependency_cycles::all_columns, versions::num))
+            .load(conn)
+    }
+
+    /// The most recently found cycles, with the crate and version which
+    /// introduced them.
+    pub fn recent(conn: &PgConnection, limit: i64) -> QueryResult<Vec<(Self, String, String)>> {
+        dependency_cycles::table
+            .inner_join(crates::table)
+            .inner_join(versions::table)
+            .order((dependency_cycles::created_at.desc(), dependency_cycles::id))
+            .select((dependency_cycles::all_columns, crates::name, versions::num))
+            .limit(limit)
+            .load(conn)
+    }
+
+    pub fn encodable(self, version: String) -> EncodableDependencyCycle {
+        EncodableDependencyCycle {
+            version,
+            crates: self.crates,
+            kinds: self.kinds,
+            created_at: self.created_at,
+        }
+    }
+}
+
+/// The path from `crate_id` to `last`, followed by the dependency of `last`
+/// on `crate_id`, as pairs of a crate and the kind of its dependency on the
+/// next one.
+fn path_to(
+    parents: &HashMap<i32, (i32, DependencyKind)>,
+    crate_id: i32,
+    last: i32,
+    kind: DependencyKind,
+) -> Vec<(i32, DependencyKind)> {
+    let mut path = vec![(last, kind)];
+    let mut current = last;
+    while current != crate_id {
+        let (parent, kind) = parents[&current];
+        path.push((parent, kind));
+        current = parent;
+    }
+    path.reverse();
+    path
+}
+
+fn kind_name(kind: DependencyKind) -> &'static str {
+    match kind {
+        DependencyKind::Normal => "normal",
+        DependencyKind::Build => "build",
+        DependencyKind::Dev => "dev",
+    }
+}
+
+/// Rotates a cycle to start with the smallest crate name, so that the same
+/// cycle found from different crates is only recorded once.
+fn canonical(mut cycle: Vec<(String, String)>) -> Vec<(String, String)> {
+    let start = cycle
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| a.0.cmp(&b.0))
+        .map_or(0, |(i, _)| i);
+    cycle.rotate_left(start);
+    cycle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cycles_start_with_the_smallest_name() {
+        let cycle = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| (name.to_string(), "dev".to_string()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(canonical(cycle(&["c", "a", "b"])), cycle(&["a", "b", "c"]));
+        assert_eq!(canonical(cycle(&["a", "b"])), cycle(&["a", "b"]));
+    }
+
+    #[test]
+    fn paths_follow_the_parents() {
+        let mut parents = HashMap::new();
+        parents.insert(2, (1, DependencyKind::Dev));
+        parents.insert(3, (2, DependencyKind::Normal));
+        assert_eq!(
+            path_to(&parents, 1, 3, DependencyKind::Build)
+                .into_iter()
+                .map(|(id, kind)| (id, kind_name(kind)))
+                .collect::<Vec<_>>(),
+            vec![(1, "dev"), (2, "normal"), (3, "build")]
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use chrono::NaiveDateTime;
+use semver::{Version, VersionReq};
+
+use crate::schema::crate_dependency_freshness;
+use crate::views::EncodableDependencyFreshness;
+
+/// How far the normal and build dependencies of the default version of a
+/// crate are behind their latest releases, recomputed weekly by the
+/// `compute_dependency_freshness` job and used by
+/// `sort=dependency-freshness`.
+#[derive(Clone, Debug, PartialEq, Queryable, Identifiable)]
+#[primary_key(crate_id)]
+#[table_name = "crate_dependency_freshness"]
+pub struct DependencyFreshness {
+    pub crate_id: i32,
+    pub version_id: i32,
+    pub dependencies: i32,
+    pub outdated_dependencies: i32,
+    pub releases_behind: i32,
+    pub score: f32,
+    pub computed_at: NaiveDateTime,
+}
+
+impl DependencyFreshness {
+    pub fn encodable(self) -> EncodableDependencyFreshness {
+        EncodableDependencyFreshness {
+            score: self.score,
+            dependencies: self.dependencies,
+            outdated_dependencies: self.outdated_dependencies,
+            releases_behind: self.releases_behind,
+            computed_at: self.computed_at,
+        }
+    }
+}
+
+/// The number of semver incompatible releases of a dependency which are
+/// newer than the newest of its `releases` matching `req`, e.g. 2 for `^0.2`
+/// if `0.4.1` is out. `releases` are the stable, non-yanked versions of the
+/// dependency.
+///
+/// Returns `None` if no release matches, e.g. if only prereleases do.
+pub fn releases_behind(req: &VersionReq, releases: &[Version]) -> Option<i32> {
+    let newest_match = releases.iter().filter(|v| req.matches(v)).max()?;
+    let mut newer = releases
+        .iter()
+        .filter(|v| *v > newest_match)
+        .map(compatibility_range)
+        .filter(|range| *range != compatibility_range(newest_match))
+        .collect::<Vec<_>>();
+    newer.sort();
+    newer.dedup();
+    Some(newer.len() as i32)
+}
+
+/// Versions in the same range are semver compatible with each other.
+fn compatibility_range(version: &Version) -> (u64, u64, u64) {
+    match (version.major, version.minor) {
+        (0, 0) => (0, 0, version.patch),
+        (0, minor) => (0, minor, 0),
+        (major, _) => (major, 0, 0),
+    }
+}
+
+/// Combines the releases each dependency is behind into a score between 0
+/// and 1, where 1 means that all dependencies are up to date. Crates without
+/// dependencies are up to date as well.
+pub fn score(releases_behind: &[i32]) -> f32 {
+    if releases_behind.is_empty() {
+        return 1.0;
+    }
+    let sum = releases_behind
+        .iter()
+        .map(|&behind| 1.0 / (1.0 + behind.max(0) as f32))
+        .sum::<f32>();
+    sum / releases_behind.len() as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn releases(nums: &[&str]) -> Vec<Version> {
+        nums.iter()
+            .map(|num| Version::parse(num).unwrap())
+            .collect()
+    }
+
+    fn behind(req: &str, nums: &[&str]) -> Option<i32> {
+        releases_behind(&VersionReq::parse(req).unwrap(), &releases(nums))
+    }
+
+    #[test]
+    fn releases_behind_counts_incompatible_ranges() {
+        let nums = &["0.1.0", "0.2.0", "0.2.3", "0.3.0", "0.4.0", "0.4.1"];
+        assert_eq!(behind("^0.4", nums), Some(0));
+        assert_eq!(behind("^0.2", nums), Some(2));
+        assert_eq!(behind("^0.1", nums), Some(3));
+
+        let nums = &["1.0.0", "1.5.0", "2.0.0", "3.0.0", "3.1.0"];
+        assert_eq!(behind("^1", nums), Some(2));
+        assert_eq!(behind("^3.0", nums), Some(0));
+        // Patch releases within the same range don't count
+        assert_eq!(behind("=1.0.0", nums), Some(2));
+        assert_eq!(behind("=3.0.0", nums), Some(0));
+        assert_eq!(behind("^4", nums), None);
+    }
+
+    #[test]
+    fn score_is_the_average_freshness() {
+        assert!((score(&[]) - 1.0).abs() < 1e-6);
+        assert!((score(&[0, 0]) - 1.0).abs() < 1e-6);
+        assert!((score(&[0, 1]) - 0.75).abs() < 1e-6);
+        assert!((score(&[3]) - 0.25).abs() < 1e-6);
+    }
+}
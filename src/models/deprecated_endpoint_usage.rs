@@ -0,0 +1,66 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::dsl::now;
+use diesel::prelude::*;
+
+use crate::schema::deprecated_endpoint_usage;
+
+/// The requests a consumer made to a deprecated endpoint on a day, counted
+/// by the `DeprecationNotices` middleware.
+#[derive(Clone, Debug, PartialEq, Eq, Queryable)]
+pub struct DeprecatedEndpointUsage {
+    /// The route of the endpoint, see `crate::deprecations`.
+    pub endpoint: String,
+    /// The name of the service consumer which made the requests, or their
+    /// `User-Agent` if they were made without a service key.
+    pub consumer: String,
+    pub date: NaiveDate,
+    pub requests: i32,
+    pub last_used_at: NaiveDateTime,
+}
+
+impl DeprecatedEndpointUsage {
+    /// Counts a request made today.
+    pub fn record(conn: &PgConnection, endpoint: &str, consumer: &str) -> QueryResult<()> {
+        use self::deprecated_endpoint_usage::dsl;
+
+        diesel::insert_into(deprecated_endpoint_usage::table)
+            .values((dsl::endpoint.eq(endpoint), dsl::consumer.eq(consumer)))
+            .on_conflict((dsl::endpoint, dsl::consumer, dsl::date))
+            .do_update()
+            .set((
+                dsl::requests.eq(dsl::requests + 1),
+                dsl::last_used_at.eq(now),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// The usage on all days since `since`, by endpoint and consumer, with
+    /// the requests added up and the consumers with the most requests first.
+    /// `date` and `last_used_at` are those of the last day with requests.
+    pub fn since(conn: &PgConnection, since: NaiveDate) -> QueryResult<Vec<Self>> {
+        let mut usage = deprecated_endpoint_usage::table
+            .filter(deprecated_endpoint_usage::date.ge(since))
+            .order((
+                deprecated_endpoint_usage::endpoint,
+                deprecated_endpoint_usage::consumer,
+                deprecated_endpoint_usage::date,
+            ))
+            .load::<Self>(conn)?;
+        usage.dedup_by(|later, earlier| {
+            let same = later.endpoint == earlier.endpoint && later.consumer == earlier.consumer;
+            if same {
+                earlier.requests += later.requests;
+                earlier.date = later.date;
+                earlier.last_used_at = later.last_used_at;
+            }
+            same
+        });
+        usage.sort_by(|a, b| {
+            a.endpoint
+                .cmp(&b.endpoint)
+                .then(b.requests.cmp(&a.requests))
+        });
+        Ok(usage)
+    }
+}
@@ -0,0 +1,82 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::dsl::now;
+use diesel::prelude::*;
+
+use crate::schema::{crates, download_anomalies};
+use crate::views::EncodableDownloadAnomaly;
+
+/// A day on which a crate was downloaded far more often than usual, which
+/// hints at scraping or at someone probing for dependency confusion.
+/// Detected by the `detect_download_anomalies` job.
+#[derive(Clone, Debug, PartialEq, Queryable, Identifiable)]
+#[primary_key(crate_id, date)]
+pub struct DownloadAnomaly {
+    pub crate_id: i32,
+    pub date: NaiveDate,
+    pub downloads: i64,
+    /// The average daily downloads of the weeks before.
+    pub baseline: f64,
+    /// How many deviations the downloads are above the baseline.
+    pub score: f64,
+    pub detected_at: NaiveDateTime,
+}
+
+impl DownloadAnomaly {
+    /// Records an anomaly, or updates it with the downloads counted since
+    /// it was first detected.
+    pub fn record(
+        conn: &PgConnection,
+        crate_id: i32,
+        date: NaiveDate,
+        downloads: i64,
+        baseline: f64,
+        score: f64,
+    ) -> QueryResult<()> {
+        diesel::insert_into(download_anomalies::table)
+            .values((
+                download_anomalies::crate_id.eq(crate_id),
+                download_anomalies::date.eq(date),
+                download_anomalies::downloads.eq(downloads),
+                download_anomalies::baseline.eq(baseline),
+                download_anomalies::score.eq(score),
+                download_anomalies::detected_at.eq(now),
+            ))
+            .on_conflict((download_anomalies::crate_id, download_anomalies::date))
+            .do_update()
+            .set((
+                download_anomalies::downloads.eq(downloads),
+                download_anomalies::baseline.eq(baseline),
+                download_anomalies::score.eq(score),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// The anomalies of a crate, newest first.
+    pub fn by_crate(conn: &PgConnection, crate_id: i32) -> QueryResult<Vec<Self>> {
+        download_anomalies::table
+            .filter(download_anomalies::crate_id.eq(crate_id))
+            .order(download_anomalies::date.desc())
+            .load(conn)
+    }
+
+    /// The anomalies of all crates since `since`, as `(anomaly, crate)`,
+    /// highest score first.
+    pub fn since(conn: &PgConnection, since: NaiveDate) -> QueryResult<Vec<(Self, String)>> {
+        download_anomalies::table
+            .inner_join(crates::table)
+            .filter(download_anomalies::date.ge(since))
+            .select((download_anomalies::all_columns, crates::name))
+            .order(download_anomalies::score.desc())
+            .load(conn)
+    }
+
+    pub fn encodable(self) -> EncodableDownloadAnomaly {
+        EncodableDownloadAnomaly {
+            date: self.date,
+            downloads: self.downloads,
+            baseline: self.baseline,
+            score: self.score,
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::prelude::*;
+
+use crate::schema::ecosystem_stats;
+use crate::views::EncodableEcosystemStats;
+
+/// The totals of the whole registry on a day, rolled up nightly by the
+/// `compute_ecosystem_stats` job.
+#[derive(Clone, Debug, PartialEq, Queryable, Identifiable)]
+#[primary_key(date)]
+#[table_name = "ecosystem_stats"]
+pub struct EcosystemStats {
+    pub date: NaiveDate,
+    /// The crates which existed at the end of the day.
+    pub total_crates: i32,
+    pub new_crates: i32,
+    pub new_versions: i32,
+    pub downloads: i64,
+    /// The users which existed when the day was last rolled up, or `None`
+    /// for days before the job ran for the first time.
+    pub total_users: Option<i32>,
+    pub computed_at: NaiveDateTime,
+}
+
+impl EcosystemStats {
+    /// The stats of all days since `since`, or of all days, oldest first.
+    pub fn series(conn: &PgConnection, since: Option<NaiveDate>) -> QueryResult<Vec<Self>> {
+        let mut query = ecosystem_stats::table
+            .order(ecosystem_stats::date)
+            .into_boxed();
+        if let Some(since) = since {
+            query = query.filter(ecosystem_stats::date.ge(since));
+        }
+        query.load(conn)
+    }
+
+    /// Encodes a series of days, oldest first. The new users of a day are
+    /// only known if the users were counted on it and on the day before.
+    pub fn encodable_series(series: Vec<Self>) -> Vec<EncodableEcosystemStats> {
+        let mut previous_total_users = None;
+        series
+            .into_iter()
+            .map(|stats| {
+                let new_users = match (stats.total_users, previous_total_users) {
+                    (Some(total), Some(previous)) => Some(total - previous),
+                    _ => None,
+                };
+                previous_total_users = stats.total_users;
+                EncodableEcosystemStats {
+                    date: stats.date,
+                    total_crates: stats.total_crates,
+                    new_crates: stats.new_crates,
+                    new_versions: stats.new_versions,
+                    new_users,
+                    downloads: stats.downloads,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(day: u32, total_users: Option<i32>) -> EcosystemStats {
+        EcosystemStats {
+            date: NaiveDate::from_ymd(2020, 3, day),
+            total_crates: 10,
+            new_crates: 1,
+            new_versions: 2,
+            downloads: 100,
+            total_users,
+            computed_at: NaiveDate::from_ymd(2020, 3, 20).and_hms(0, 0, 0),
+        }
+    }
+
+    #[test]
+    fn new_users_are_the_difference_to_the_day_before() {
+        let series = EcosystemStats::encodable_series(vec![
+            day(1, None),
+            day(2, Some(40)),
+            day(3, Some(45)),
+            day(4, None),
+            day(5, Some(50)),
+        ]);
+        let new_users = series.iter().map(|s| s.new_users).collect::<Vec<_>>();
+        assert_eq!(new_users, vec![None, None, Some(5), None, None]);
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::{BTreeMap, HashMap};
+
+use diesel::prelude::*;
+
+use crate::schema::version_feature_docs;
+
+/// The original manifest in a crate file. The normalized `Cargo.toml` next
+/// to it doesn't contain any comments anymore.
+pub const ORIGINAL_MANIFEST_FILE: &str = "Cargo.toml.orig";
+
+/// The description of a feature of a version.
+#[derive(Clone, Debug, PartialEq, Eq, Queryable, Insertable)]
+#[table_name = "version_feature_docs"]
+pub struct VersionFeatureDoc {
+    pub version_id: i32,
+    pub feature: String,
+    pub description: String,
+}
+
+impl VersionFeatureDoc {
+    /// Saves the descriptions of a newly published version. Descriptions of
+    /// features the version doesn't have are ignored.
+    pub fn save(
+        conn: &PgConnection,
+        version_id: i32,
+        features: &HashMap<String, Vec<String>>,
+        docs: BTreeMap<String, String>,
+    ) -> QueryResult<()> {
+        let rows = docs
+            .into_iter()
+            .filter(|(feature, _)| features.contains_key(feature))
+            .map(|(feature, description)| VersionFeatureDoc {
+                version_id,
+                feature,
+                description,
+            })
+            .collect::<Vec<_>>();
+        diesel::insert_into(version_feature_docs::table)
+            .values(&rows)
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Replaces the descriptions of the features of a version.
+    pub fn replace(
+        conn: &PgConnection,
+        version_id: i32,
+        features: &HashMap<String, Vec<String>>,
+        docs: BTreeMap<String, String>,
+    ) -> QueryResult<()> {
+        diesel::delete(
+            version_feature_docs::table.filter(version_feature_docs::version_id.eq(version_id)),
+        )
+        .execute(conn)?;
+        Self::save(conn, version_id, features, docs)
+    }
+
+    /// The descriptions of the features of a version, by feature name.
+    pub fn by_version(
+        conn: &PgConnection,
+        version_id: i32,
+    ) -> QueryResult<HashMap<String, String>> {
+        version_feature_docs::table
+            .filter(version_feature_docs::version_id.eq(version_id))
+            .select((
+                version_feature_docs::feature,
+                version_feature_docs::description,
+            ))
+            .load(conn)
+            .map(|docs: Vec<(String, String)>| docs.into_iter().collect())
+    }
+}
+
+/// Extracts the descriptions of features from a manifest.
+///
+/// Features are documented with `## ` comments directly above them:
+///
+/// ```toml
+/// [features]
+/// ## Enables the `std` feature of all dependencies.
+/// std = ["serde/std"]
+/// ```
+///
+/// Comments spanning multiple lines are joined with newlines, and any other
+/// comment or a blank line in between discards them.
+pub fn parse_feature_docs(manifest: &str) -> BTreeMap<String, String> {
+    let mut docs = BTreeMap::new();
+    let mut in_features = false;
+    let mut in_array = false;
+    let mut pending = Vec::new();
+
+    for line in manifest.lines() {
+        let line = line.trim();
+        if in_array {
+            in_array = !line.contains(']');
+            continue;
+        }
+
+        if line.starts_with('[') {
+            let header = line.split('#').next().unwrap_or_default().trim();
+            in_features = header == "[features]";
+            pending.clear();
+            continue;
+        }
+        if !in_features {
+            continue;
+        }
+
+        if line.starts_with("##") {
+            let doc = &line[2..];
+            pending.push(if doc.starts_with(' ') { &doc[1..] } else { doc });
+        } else if line.is_empty() || line.starts_with('#') {
+            pending.clear();
+        } else if let Some(equals) = line.find('=') {
+            let name = line[..equals].trim().trim_matches('"');
+            let value = &line[equals + 1..];
+            in_array = value.contains('[') && !value.contains(']');
+            if !pending.is_empty() {
+                docs.insert(name.to_string(), pending.join("\n"));
+            }
+            pending.clear();
+        }
+    }
+    docs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_documented_features() {
+        let manifest = r#"
+[package]
+## Not a feature
+name = "foo"
+
+[features] # optional functionality
+## Everything needed for most users.
+default = ["std"]
+## Uses the standard library.
+##
+## Disable it for `no_std` targets.
+"std" = [
+    "serde/std",
+    "not-a-feature = [",
+]
+# Not a description
+undocumented = []
+## Separated by a blank line
+
+also-undocumented = []
+
+[dependencies]
+## Not a feature either
+serde = "1"
+"#;
+        let docs = parse_feature_docs(manifest);
+        let expected = vec![
+            ("default", "Everything needed for most users."),
+            (
+                "std",
+                "Uses the standard library.\n\nDisable it for `no_std` targets.",
+            ),
+        ];
+        assert_eq!(
+            docs.iter()
+                .map(|(k, v)| (k.as_str(), v.as_str()))
+                .collect::<Vec<_>>(),
+            expected
+        );
+    }
+}
@@ -0,0 +1,282 @@
+use diesel::prelude::*;
+
+use crate::schema::crate_funding_links;
+use crate::views::EncodableFundingLink;
+
+/// GitHub's funding file, relative to the root of a crate.
+pub const FUNDING_FILE: &str = ".github/FUNDING.yml";
+
+/// At most this many funding links are stored per crate, the rest are
+/// ignored.
+const MAX_FUNDING_LINKS: usize = 16;
+
+/// A funding link of a crate, declared in its `.github/FUNDING.yml` file or
+/// in the `[package.metadata.funding]` table of its manifest.
+///
+/// Both use the platforms of GitHub's funding file, e.g.
+///
+/// ```toml
+/// [package.metadata.funding]
+/// github = ["octocat", "surftocat"]
+/// open_collective = "octocat"
+/// custom = "https://example.com/donate"
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Queryable)]
+pub struct FundingLink {
+    pub platform: String,
+    pub url: String,
+}
+
+impl FundingLink {
+    /// Turns an account on one of the supported platforms, or a custom URL,
+    /// into a link. Unknown platforms and malformed accounts are ignored.
+    fn new(platform: &str, account: &str) -> Option<Self> {
+        let account = account.trim().trim_matches(|c| c == '"' || c == '\'');
+        if account.is_empty() {
+            return None;
+        }
+
+        let url = if platform == "custom" {
+            let url = if account.contains("://") {
+                account.to_string()
+            } else {
+                format!("https://{}", account)
+            };
+            let parsed = url::Url::parse(&url).ok()?;
+            if parsed.scheme() != "https" && parsed.scheme() != "http" {
+                return None;
+            }
+            parsed.into_string()
+        } else {
+            let slashes = if platform == "tidelift" { 1 } else { 0 };
+            let valid_account = account.matches('/').count() == slashes
+                && account
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_./".contains(c));
+            if !valid_account {
+                return None;
+            }
+
+            match platform {
+                "github" => format!("https://github.com/sponsors/{}", account),
+                "patreon" => format!("https://www.patreon.com/{}", account),
+                "open_collective" => format!("https://opencollective.com/{}", account),
+                "ko_fi" => format!("https://ko-fi.com/{}", account),
+                "liberapay" => format!("https://liberapay.com/{}", account),
+                "issuehunt" => format!("https://issuehunt.io/r/{}", account),
+                "community_bridge" => {
+                    format!("https://funding.communitybridge.org/projects/{}", account)
+                }
+                "tidelift" => format!(
+                    "https://tidelift.com/subscription/pkg/{}",
+                    account.replace('/', "-")
+                ),
+                _ => return None,
+            }
+        };
+
+        Some(Self {
+            platform: platform.to_string(),
+            url,
+        })
+    }
+
+    /// Replaces the funding links of a crate with the ones found in its most
+    /// recently published version.
+    pub fn update_crate(conn: &PgConnection, crate_id: i32, links: &[Self]) -> QueryResult<()> {
+        diesel::delete(
+            crate_funding_links::table.filter(crate_funding_links::crate_id.eq(crate_id)),
+        )
+        .execute(conn)?;
+
+        let rows = links
+            .iter()
+            .map(|link| {
+                (
+                    crate_funding_links::crate_id.eq(crate_id),
+                    crate_funding_links::platform.eq(&link.platform),
+                    crate_funding_links::url.eq(&link.url),
+                )
+            })
+            .collect::<Vec<_>>();
+        diesel::insert_into(crate_funding_links::table)
+            .values(&rows)
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn by_crate(conn: &PgConnection, crate_id: i32) -> QueryResult<Vec<Self>> {
+        crate_funding_links::table
+            .filter(crate_funding_links::crate_id.eq(crate_id))
+            .select((crate_funding_links::platform, crate_funding_links::url))
+            .order((crate_funding_links::platform, crate_funding_links::url))
+            .load(conn)
+    }
+
+    pub fn encodable(self) -> EncodableFundingLink {
+        EncodableFundingLink {
+            platform: self.platform,
+            url: self.url,
+        }
+    }
+}
+
+/// Collects the funding links of a crate, with the ones from the manifest
+/// first. Duplicates are removed, and anything that can't be parsed is
+/// ignored, since funding information must never prevent a publish.
+pub fn collect_funding_links(
+    manifest: Option<&str>,
+    funding_file: Option<&str>,
+) -> Vec<FundingLink> {
+    let mut links = Vec::new();
+    let found = manifest
+        .map(parse_manifest_funding)
+        .into_iter()
+        .chain(funding_file.map(parse_funding_file))
+        .flatten();
+    for link in found {
+        if links.len() == MAX_FUNDING_LINKS {
+            break;
+        }
+        if !links.contains(&link) {
+            links.push(link);
+        }
+    }
+    links
+}
+
+/// Extracts the `[package.metadata.funding]` table of a manifest.
+fn parse_manifest_funding(manifest: &str) -> Vec<FundingLink> {
+    let manifest = match manifest.parse::<toml::Value>() {
+        Ok(manifest) => manifest,
+        Err(_) => return Vec::new(),
+    };
+    let funding = manifest
+        .get("package")
+        .and_then(|package| package.get("metadata"))
+        .and_then(|metadata| metadata.get("funding"))
+        .and_then(|funding| funding.as_table());
+
+    let mut links = Vec::new();
+    for (platform, accounts) in funding.into_iter().flatten() {
+        let accounts = match accounts {
+            toml::Value::Array(accounts) => accounts.iter().collect(),
+            account => vec![account],
+        };
+        links.extend(
+            accounts
+                .into_iter()
+                .filter_map(|account| account.as_str())
+                .filter_map(|account| FundingLink::new(platform, account)),
+        );
+    }
+    links
+}
+
+/// Extracts the links of a GitHub funding file.
+///
+/// Only the subset of YAML these files use in practice is supported: one
+/// platform per line, with a single account, a flow sequence like
+/// `[a, b]`, or a block sequence of `- a` lines.
+fn parse_funding_file(contents: &str) -> Vec<FundingLink> {
+    let mut links = Vec::new();
+    let mut platform = None;
+    for line in contents.lines() {
+        // Comments have to be preceded by whitespace, so that URLs with
+        // fragments are kept intact.
+        let line = match line.find(" #") {
+            Some(comment) => &line[..comment],
+            None if line.trim_start().starts_with('#') => "",
+            None => line,
+        };
+        let line = line.trim_end();
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("- ") {
+            if let Some(platform) = platform {
+                links.extend(FundingLink::new(platform, &trimmed[2..]));
+            }
+            continue;
+        }
+
+        platform = None;
+        let colon = match line.find(':') {
+            Some(colon) => colon,
+            None => continue,
+        };
+        let key = line[..colon].trim();
+        let value = line[colon + 1..].trim();
+        if value.is_empty() {
+            platform = Some(key);
+        } else if value.starts_with('[') && value.ends_with(']') {
+            let accounts = value[1..value.len() - 1].split(',');
+            links.extend(accounts.filter_map(|account| FundingLink::new(key, account)));
+        } else {
+            links.extend(FundingLink::new(key, value));
+        }
+    }
+    links
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn urls(links: Vec<FundingLink>) -> Vec<String> {
+        links.into_iter().map(|link| link.url).collect()
+    }
+
+    #[test]
+    fn parses_funding_files() {
+        let contents = r#"
+# These are supported funding model platforms
+
+github: [octocat, "surftocat"] # Up to 4 GitHub Sponsors-enabled usernames
+patreon: octocat
+open_collective: # Replace with a single Open Collective username
+ko_fi: not/valid
+tidelift: cargo/serde
+unknown: octocat
+custom:
+  - https://www.paypal.me/octocat#donate
+  - octocat.com
+"#;
+        assert_eq!(
+            urls(parse_funding_file(contents)),
+            vec![
+                "https://github.com/sponsors/octocat",
+                "https://github.com/sponsors/surftocat",
+                "https://www.patreon.com/octocat",
+                "https://tidelift.com/subscription/pkg/cargo-serde",
+                "https://www.paypal.me/octocat#donate",
+                "https://octocat.com/",
+            ]
+        );
+    }
+
+    #[test]
+    fn manifest_links_come_first() {
+        let manifest = r#"
+[package]
+name = "foo"
+
+[package.metadata.funding]
+liberapay = "octocat"
+github = ["octocat"]
+custom = ["javascript:alert(1)"]
+"#;
+        let funding_file = "github: octocat\npatreon: octocat\n";
+        let links = collect_funding_links(Some(manifest), Some(funding_file));
+        assert_eq!(
+            links
+                .iter()
+                .map(|link| link.platform.as_str())
+                .collect::<Vec<_>>(),
+            vec!["github", "liberapay", "patreon"]
+        );
+        assert!(collect_funding_links(Some("not toml ["), None).is_empty());
+    }
+}
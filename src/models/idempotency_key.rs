@@ -0,0 +1,98 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::dsl::now;
+use diesel::prelude::*;
+use serde_json::Value;
+
+use crate::schema::publish_idempotency_keys;
+
+/// How long retries with the same key get the original response.
+const LIFETIME_HOURS: i64 = 24;
+
+/// The response to a publish with an `Idempotency-Key` header.
+#[derive(Clone, Debug, PartialEq, Queryable)]
+pub struct PublishIdempotencyKey {
+    pub user_id: i32,
+    pub key: String,
+    pub crate_name: String,
+    pub version: String,
+    /// The hex encoded SHA-256 checksum of the crate file.
+    ///
+    /// It's only missing while the publish which claimed the key is in
+    /// progress, so other transactions never see that.
+    pub checksum: Option<String>,
+    pub response: Option<Value>,
+    pub created_at: NaiveDateTime,
+}
+
+fn cutoff() -> NaiveDateTime {
+    (Utc::now() - Duration::hours(LIFETIME_HOURS)).naive_utc()
+}
+
+impl PublishIdempotencyKey {
+    /// The key of the user, unless it expired.
+    pub fn find(conn: &PgConnection, user_id: i32, key: &str) -> QueryResult<Option<Self>> {
+        publish_idempotency_keys::table
+            .find((user_id, key))
+            .filter(publish_idempotency_keys::created_at.gt(cutoff()))
+            .first(conn)
+            .optional()
+    }
+
+    /// Claims the key for a publish, replacing an expired use of it. Returns
+    /// `false` if the key was already used.
+    ///
+    /// This has to run in the transaction of the publish. A concurrent
+    /// publish with the same key holds its claim until its transaction ends,
+    /// so the key is only claimed once it rolled back.
+    pub fn claim(
+        conn: &PgConnection,
+        user_id: i32,
+        key: &str,
+        crate_name: &str,
+        version: &str,
+    ) -> QueryResult<bool> {
+        diesel::delete(
+            publish_idempotency_keys::table
+                .find((user_id, key))
+                .filter(publish_idempotency_keys::created_at.le(cutoff())),
+        )
+        .execute(conn)?;
+        let inserted = diesel::insert_into(publish_idempotency_keys::table)
+            .values((
+                publish_idempotency_keys::user_id.eq(user_id),
+                publish_idempotency_keys::key.eq(key),
+                publish_idempotency_keys::crate_name.eq(crate_name),
+                publish_idempotency_keys::version.eq(version),
+                publish_idempotency_keys::created_at.eq(now),
+            ))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        Ok(inserted == 1)
+    }
+
+    /// Records the response to the publish which claimed the key.
+    pub fn record(
+        conn: &PgConnection,
+        user_id: i32,
+        key: &str,
+        checksum: &str,
+        response: &Value,
+    ) -> QueryResult<()> {
+        diesel::update(publish_idempotency_keys::table.find((user_id, key)))
+            .set((
+                publish_idempotency_keys::checksum.eq(checksum),
+                publish_idempotency_keys::response.eq(response),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Removes all expired keys, returning how many there were.
+    pub fn prune_expired(conn: &PgConnection) -> QueryResult<usize> {
+        diesel::delete(
+            publish_idempotency_keys::table
+                .filter(publish_idempotency_keys::created_at.le(cutoff())),
+        )
+        .execute(conn)
+    }
+}
@@ -0,0 +1,249 @@
+use diesel::prelude::*;
+
+use crate::schema::{index_download_endpoints, index_settings};
+use crate::uploaders::Uploader;
+
+/// The name of the configuration file at the root of the index.
+pub const CONFIG_FILE: &str = "config.json";
+
+/// The `index_settings` entry holding the checksum URL template.
+const CHECKSUM_URL: &str = "checksum-url";
+
+/// The contents of the `config.json` file of the index.
+///
+/// `dl` and `api` are all that older versions of cargo understand. The
+/// other fields are only written if they have been configured, either with
+/// the `index-config` binary, through the admin API or through
+/// `IndexSettings`.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct IndexConfig {
+    pub dl: String,
+    pub api: String,
+    /// Additional download endpoints, highest priority first.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dl_endpoints: Vec<DownloadEndpoint>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checksum_url: Option<String>,
+    #[serde(skip_serializing_if = "is_false")]
+    pub auth_required: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Queryable, Serialize)]
+pub struct DownloadEndpoint {
+    pub url: String,
+    pub priority: i32,
+}
+
+/// The parts of the `config.json` file which follow the configuration of
+/// the server, so that moving the API or the crate files to another host
+/// doesn't require editing the index.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IndexSettings {
+    pub dl: String,
+    pub api: String,
+    pub auth_required: bool,
+    /// The crate files in the storage, e.g. behind the CDN, advertised as an
+    /// additional download endpoint.
+    pub storage_endpoint: Option<DownloadEndpoint>,
+}
+
+impl IndexSettings {
+    /// Reads the settings from the following environment variables:
+    ///
+    /// - `INDEX_API_URL`: `https://$DOMAIN_NAME` by default.
+    /// - `INDEX_DL_URL`: The download URL template, `$INDEX_API_URL/api/v1/crates`
+    ///   by default, so that downloads are counted.
+    /// - `INDEX_AUTH_REQUIRED`: If set, cargo is told to authenticate when
+    ///   fetching the index and downloading crates.
+    /// - `INDEX_STORAGE_ENDPOINT_PRIORITY`: If set, the URLs of the crate files
+    ///   in the storage of `uploader` are advertised as a download endpoint with
+    ///   this priority. These follow `S3_CDN`.
+    pub fn from_environment(uploader: &Uploader) -> Result<Self, String> {
+        let api = match dotenv::var("INDEX_API_URL") {
+            Ok(api) => api.trim_end_matches('/').to_string(),
+            Err(_) => format!(
+                "https://{}",
+                dotenv::var("DOMAIN_NAME").unwrap_or_else(|_| "crates.io".into())
+            ),
+        };
+        let dl = dotenv::var("INDEX_DL_URL").unwrap_or_else(|_| format!("{}/api/v1/crates", api));
+        validate_url_template(&api, false)?;
+        validate_url_template(&dl, false)?;
+
+        let storage_endpoint = match dotenv::var("INDEX_STORAGE_ENDPOINT_PRIORITY") {
+            Ok(priority) => Some(DownloadEndpoint {
+                url: uploader.crate_location("{crate}", "{version}"),
+                priority: priority.parse().map_err(|_| {
+                    format!(
+                        "INDEX_STORAGE_ENDPOINT_PRIORITY `{}` isn't a number",
+                        priority
+                    )
+                })?,
+            }),
+            Err(_) => None,
+        };
+        if let Some(endpoint) = &storage_endpoint {
+            validate_url_template(&endpoint.url, false)?;
+        }
+
+        Ok(Self {
+            dl,
+            api,
+            auth_required: dotenv::var("INDEX_AUTH_REQUIRED").is_ok(),
+            storage_endpoint,
+        })
+    }
+}
+
+impl IndexConfig {
+    /// Loads the configuration of the index, combining the server `settings`
+    /// with what was configured with the `index-config` binary. Endpoints
+    /// added with the binary take precedence over the storage endpoint if
+    /// they have the same URL.
+    pub fn load(conn: &PgConnection, settings: &IndexSettings) -> QueryResult<Self> {
+        let mut dl_endpoints: Vec<DownloadEndpoint> = index_download_endpoints::table.load(conn)?;
+        if let Some(endpoint) = &settings.storage_endpoint {
+            if dl_endpoints.iter().all(|e| e.url != endpoint.url) {
+                dl_endpoints.push(endpoint.clone());
+            }
+        }
+        dl_endpoints.sort_by(|a, b| b.priority.cmp(&a.priority).then(a.url.cmp(&b.url)));
+        let checksum_url = index_settings::table
+            .find(CHECKSUM_URL)
+            .select(index_settings::value)
+            .first(conn)
+            .optional()?;
+
+        Ok(Self {
+            dl: settings.dl.clone(),
+            api: settings.api.clone(),
+            dl_endpoints,
+            checksum_url,
+            auth_required: settings.auth_required,
+        })
+    }
+
+    /// Renders the `config.json` file.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        Ok(serde_json::to_string_pretty(self)? + "\n")
+    }
+
+    /// Adds a download endpoint, or changes the priority of an existing one.
+    pub fn add_download_endpoint(
+        conn: &PgConnection,
+        url: &str,
+        priority: i32,
+    ) -> Result<(), String> {
+        validate_url_template(url, false)?;
+        diesel::insert_into(index_download_endpoints::table)
+            .values((
+                index_download_endpoints::url.eq(url),
+                index_download_endpoints::priority.eq(priority),
+            ))
+            .on_conflict(index_download_endpoints::url)
+            .do_update()
+            .set(index_download_endpoints::priority.eq(priority))
+            .execute(conn)
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Removes a download endpoint, and returns whether it existed.
+    pub fn remove_download_endpoint(conn: &PgConnection, url: &str) -> QueryResult<bool> {
+        let deleted = diesel::delete(index_download_endpoints::table.find(url)).execute(conn)?;
+        Ok(deleted > 0)
+    }
+
+    /// Sets or removes the checksum URL template.
+    pub fn set_checksum_url(conn: &PgConnection, template: Option<&str>) -> Result<(), String> {
+        let result = match template {
+            Some(template) => {
+                validate_url_template(template, true)?;
+                diesel::insert_into(index_settings::table)
+                    .values((
+                        index_settings::name.eq(CHECKSUM_URL),
+                        index_settings::value.eq(template),
+                    ))
+                    .on_conflict(index_settings::name)
+                    .do_update()
+                    .set(index_settings::value.eq(template))
+                    .execute(conn)
+            }
+            None => diesel::delete(index_settings::table.find(CHECKSUM_URL)).execute(conn),
+        };
+        result.map(|_| ()).map_err(|e| e.to_string())
+    }
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+/// Checks that a URL template is an absolute HTTP(S) URL. Download templates
+/// without markers are valid, since cargo appends `/{crate}/{version}/download`
+/// to them, but checksum templates have to contain both markers.
+fn validate_url_template(template: &str, require_markers: bool) -> Result<(), String> {
+    let url =
+        url::Url::parse(template).map_err(|e| format!("invalid URL `{}`: {}", template, e))?;
+    if url.scheme() != "https" && url.scheme() != "http" {
+        return Err(format!("`{}` is not an HTTP(S) URL", template));
+    }
+    if require_markers && !(template.contains("{crate}") && template.contains("{version}")) {
+        return Err(format!(
+            "`{}` must contain the `{{crate}}` and `{{version}}` markers",
+            template
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_configured_fields_are_written() {
+        let mut config = IndexConfig {
+            dl: "https://crates.io/api/v1/crates".into(),
+            api: "https://crates.io".into(),
+            dl_endpoints: Vec::new(),
+            checksum_url: None,
+            auth_required: false,
+        };
+        assert_eq!(
+            config.to_json().unwrap(),
+            "{\n  \"dl\": \"https://crates.io/api/v1/crates\",\n  \"api\": \"https://crates.io\"\n}\n"
+        );
+
+        config.dl_endpoints.push(DownloadEndpoint {
+            url: "https://static.crates.io/crates/{crate}/{crate}-{version}.crate".into(),
+            priority: 10,
+        });
+        config.checksum_url = Some("https://static.crates.io/{crate}/{version}.sha256".into());
+        config.auth_required = true;
+        let json: serde_json::Value = serde_json::from_str(&config.to_json().unwrap()).unwrap();
+        assert_eq!(
+            json,
+            json!({
+                "dl": "https://crates.io/api/v1/crates",
+                "api": "https://crates.io",
+                "dl-endpoints": [{
+                    "url": "https://static.crates.io/crates/{crate}/{crate}-{version}.crate",
+                    "priority": 10,
+                }],
+                "checksum-url": "https://static.crates.io/{crate}/{version}.sha256",
+                "auth-required": true,
+            })
+        );
+    }
+
+    #[test]
+    fn url_templates_are_validated() {
+        assert!(validate_url_template("https://dl.example.com/crates", false).is_ok());
+        assert!(validate_url_template("dl.example.com/crates", false).is_err());
+        assert!(validate_url_template("ftp://dl.example.com/crates", false).is_err());
+        assert!(validate_url_template("https://example.com/{crate}/{version}", true).is_ok());
+        assert!(validate_url_template("https://example.com/{crate}", true).is_err());
+    }
+}
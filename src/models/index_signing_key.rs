@@ -0,0 +1,48 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use crate::index_signing::{allowed_signer, SigningKey};
+use crate::schema::index_signing_keys;
+
+/// A key commits to the index were signed with.
+#[derive(Clone, Debug, PartialEq, Eq, Identifiable, Queryable)]
+#[primary_key(fingerprint)]
+pub struct IndexSigningKey {
+    pub fingerprint: String,
+    pub public_key: String,
+    pub first_used_at: NaiveDateTime,
+}
+
+impl IndexSigningKey {
+    /// Records a key when a worker starts signing with it. Keys which were
+    /// used before keep the time they were first used.
+    pub fn record(conn: &PgConnection, key: &SigningKey) -> QueryResult<()> {
+        diesel::insert_into(index_signing_keys::table)
+            .values((
+                index_signing_keys::fingerprint.eq(key.fingerprint()),
+                index_signing_keys::public_key.eq(key.public_key()),
+            ))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// All keys, in the order they were first used.
+    pub fn all(conn: &PgConnection) -> QueryResult<Vec<Self>> {
+        index_signing_keys::table
+            .order(index_signing_keys::first_used_at)
+            .load(conn)
+    }
+
+    /// The `allowed_signers` file git verifies the signatures of the index
+    /// with, where each key is valid until its successor was first used.
+    pub fn allowed_signers(keys: &[Self]) -> String {
+        keys.iter()
+            .enumerate()
+            .map(|(i, key)| {
+                let successor = keys.get(i + 1).map(|next| next.first_used_at);
+                allowed_signer(&key.public_key, key.first_used_at, successor) + "\n"
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,46 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use crate::schema::index_squashes;
+
+/// A squash of the history of the index into a single commit.
+#[derive(Clone, Debug, PartialEq, Eq, Identifiable, Queryable)]
+#[table_name = "index_squashes"]
+pub struct IndexSquash {
+    pub id: i32,
+    /// The commit the history ended with, which the snapshot branch points to.
+    pub previous_head: String,
+    /// The rollup commit replacing the history.
+    pub squashed_commit: String,
+    pub snapshot_branch: String,
+    /// How many commits the history had.
+    pub commit_count: i32,
+    pub created_at: NaiveDateTime,
+}
+
+impl IndexSquash {
+    pub fn record(
+        conn: &PgConnection,
+        previous_head: &str,
+        squashed_commit: &str,
+        snapshot_branch: &str,
+        commit_count: i32,
+    ) -> QueryResult<Self> {
+        diesel::insert_into(index_squashes::table)
+            .values((
+                index_squashes::previous_head.eq(previous_head),
+                index_squashes::squashed_commit.eq(squashed_commit),
+                index_squashes::snapshot_branch.eq(snapshot_branch),
+                index_squashes::commit_count.eq(commit_count),
+            ))
+            .get_result(conn)
+    }
+
+    /// The most recent squash, if the index was ever squashed.
+    pub fn latest(conn: &PgConnection) -> QueryResult<Option<Self>> {
+        index_squashes::table
+            .order(index_squashes::id.desc())
+            .first(conn)
+            .optional()
+    }
+}
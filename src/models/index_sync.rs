@@ -0,0 +1,165 @@
+use chrono::NaiveDateTime;
+use diesel::dsl::now;
+use diesel::prelude::*;
+use diesel::sql_types::{Array, BigInt, Double};
+
+use crate::schema::{crates, index_syncs, versions};
+
+/// The upper bounds in seconds of the buckets durations are counted in.
+pub const INDEX_BUCKETS: &[f64] = &[1.0, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1800.0];
+/// The CDN is only checked every few minutes, after `INSTALL_CHECK_DELAY`,
+/// so these durations are upper bounds, and shorter buckets would stay empty.
+pub const VISIBILITY_BUCKETS: &[f64] = &[
+    300.0, 600.0, 900.0, 1800.0, 3600.0, 7200.0, 21600.0, 86400.0,
+];
+
+/// How the way of a version into the index went. Its index update is queued
+/// when it's published or released from a scan hold, pushed to the index by
+/// the `add_crate` or `add_queued_crate` job, and then fetched through the
+/// CDN by the `verify_installability` job.
+#[derive(Clone, Debug, PartialEq, Eq, Queryable, Identifiable)]
+#[primary_key(version_id)]
+pub struct IndexSync {
+    pub version_id: i32,
+    pub queued_at: NaiveDateTime,
+    pub indexed_at: Option<NaiveDateTime>,
+    /// When the index entry and the crate file were first served by the CDN.
+    pub visible_at: Option<NaiveDateTime>,
+    /// How many attempts of pushing the index update failed.
+    pub index_failures: i32,
+    /// How many checks found the version missing or outdated on the CDN.
+    pub visibility_failures: i32,
+}
+
+/// The stages of `IndexSync` which take a measurable time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyncStage {
+    /// From queueing the index update until it was pushed.
+    Index,
+    /// From pushing the index update until the CDN served it.
+    Visibility,
+}
+
+/// The durations of a stage in the form of a Prometheus histogram: how many
+/// were at most as long as each bucket's bound, their count and their sum.
+#[derive(Clone, Debug, PartialEq, QueryableByName)]
+pub struct SyncDurations {
+    #[sql_type = "Array<BigInt>"]
+    pub buckets: Vec<i64>,
+    #[sql_type = "BigInt"]
+    pub count: i64,
+    #[sql_type = "Double"]
+    pub sum: f64,
+}
+
+/// The current state of the syncs which haven't finished.
+#[derive(Clone, Debug, PartialEq, QueryableByName)]
+pub struct SyncLag {
+    /// How long the oldest index update which wasn't pushed has been queued.
+    #[sql_type = "Double"]
+    pub index_seconds: f64,
+    /// How long ago the oldest version which isn't visible on the CDN yet
+    /// was pushed. Versions pushed more than a day ago or yanked since are
+    /// no longer checked, and left out.
+    #[sql_type = "Double"]
+    pub visibility_seconds: f64,
+    #[sql_type = "BigInt"]
+    pub index_failures: i64,
+    #[sql_type = "BigInt"]
+    pub visibility_failures: i64,
+}
+
+impl SyncStage {
+    pub fn buckets(self) -> &'static [f64] {
+        match self {
+            SyncStage::Index => INDEX_BUCKETS,
+            SyncStage::Visibility => VISIBILITY_BUCKETS,
+        }
+    }
+
+    fn columns(self) -> (&'static str, &'static str) {
+        match self {
+            SyncStage::Index => ("queued_at", "indexed_at"),
+            SyncStage::Visibility => ("indexed_at", "visible_at"),
+        }
+    }
+}
+
+impl IndexSync {
+    /// Starts the clock once the index update of a version was enqueued.
+    /// Enqueuing it again keeps the original time.
+    pub fn queued(conn: &PgConnection, version_id: i32) -> QueryResult<()> {
+        diesel::insert_into(index_syncs::table)
+            .values(index_syncs::version_id.eq(version_id))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Records the outcome of an attempt to push the index update of a
+    /// version, which is identified by the index entry the job carries.
+    pub fn record_indexed(
+        conn: &PgConnection,
+        crate_name: &str,
+        num: &str,
+        succeeded: bool,
+    ) -> QueryResult<()> {
+        let version_id = versions::table
+            .inner_join(crates::table)
+            .filter(crates::name.eq(crate_name))
+            .filter(versions::num.eq(num))
+            .select(versions::id);
+        let syncs = index_syncs::table.filter(index_syncs::version_id.eq_any(version_id));
+        if succeeded {
+            diesel::update(syncs.filter(index_syncs::indexed_at.is_null()))
+                .set(index_syncs::indexed_at.eq(now))
+                .execute(conn)?;
+        } else {
+            diesel::update(syncs)
+                .set(index_syncs::index_failures.eq(index_syncs::index_failures + 1))
+                .execute(conn)?;
+        }
+        Ok(())
+    }
+
+    /// Records the outcome of checking a version through the CDN.
+    pub fn record_visible(conn: &PgConnection, version_id: i32, visible: bool) -> QueryResult<()> {
+        let sync = index_syncs::table.find(version_id);
+        if visible {
+            diesel::update(sync.filter(index_syncs::visible_at.is_null()))
+                .set(index_syncs::visible_at.eq(now))
+                .execute(conn)?;
+        } else {
+            diesel::update(sync)
+                .set(index_syncs::visibility_failures.eq(index_syncs::visibility_failures + 1))
+                .execute(conn)?;
+        }
+        Ok(())
+    }
+
+    /// The durations of all finished syncs of a stage.
+    pub fn durations(conn: &PgConnection, stage: SyncStage) -> QueryResult<SyncDurations> {
+        let (start, end) = stage.columns();
+        let buckets = stage
+            .buckets()
+            .iter()
+            .map(|le| format!("COUNT(*) FILTER (WHERE seconds <= {})", le))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let query = format!(
+            "SELECT ARRAY[{buckets}]::int8[] AS buckets, COUNT(*) AS count, \
+                    COALESCE(SUM(seconds), 0)::float8 AS sum \
+             FROM (SELECT EXTRACT(EPOCH FROM {end} - {start})::float8 AS seconds \
+                   FROM index_syncs \
+                   WHERE {start} IS NOT NULL AND {end} IS NOT NULL) durations",
+            buckets = buckets,
+            start = start,
+            end = end,
+        );
+        diesel::sql_query(query).get_result(conn)
+    }
+
+    pub fn lag(conn: &PgConnection) -> QueryResult<SyncLag> {
+        diesel::sql_query(include_str!("index_sync_lag.sql")).get_result(conn)
+    }
+}
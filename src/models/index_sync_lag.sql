@@ -0,0 +1,13 @@
+SELECT
+  COALESCE(EXTRACT(EPOCH FROM MAX(now() - queued_at) FILTER (
+    WHERE indexed_at IS NULL
+  )), 0)::float8 AS index_seconds,
+  COALESCE(EXTRACT(EPOCH FROM MAX(now() - indexed_at) FILTER (
+    WHERE visible_at IS NULL
+      AND indexed_at > now() - INTERVAL '1 day'
+      AND NOT versions.yanked
+  )), 0)::float8 AS visibility_seconds,
+  COALESCE(SUM(index_failures), 0)::int8 AS index_failures,
+  COALESCE(SUM(visibility_failures), 0)::int8 AS visibility_failures
+FROM index_syncs
+INNER JOIN versions ON versions.id = index_syncs.version_id
@@ -17,7 +17,7 @@ use crate::util::errors::{cargo_err, AppResult};
 use crate::views::{EncodableCrate, EncodableCrateLinks};
 
 use crate::models::helpers::with_count::*;
-use crate::publish_rate_limit::PublishRateLimit;
+use crate::publish_rate_limit::RateLimiter;
 use crate::schema::*;
 
 /// Hosts in this list are known to not be hosting documentation,
@@ -46,6 +46,7 @@ pub struct Crate {
     pub documentation: Option<String>,
     pub repository: Option<String>,
     pub max_upload_size: Option<i32>,
+    pub repository_last_commit_at: Option<NaiveDateTime>,
 }
 
 /// We literally never want to select `textsearchable_index_col`
@@ -61,6 +62,7 @@ type AllColumns = (
     crates::documentation,
     crates::repository,
     crates::max_upload_size,
+    crates::repository_last_commit_at,
 );
 
 pub const ALL_COLUMNS: AllColumns = (
@@ -74,6 +76,7 @@ pub const ALL_COLUMNS: AllColumns = (
     crates::documentation,
     crates::repository,
     crates::max_upload_size,
+    crates::repository_last_commit_at,
 );
 
 pub const MAX_NAME_LENGTH: usize = 64;
@@ -103,7 +106,7 @@ impl<'a> NewCrate<'a> {
         self,
         conn: &PgConnection,
         uploader: i32,
-        rate_limit: Option<&PublishRateLimit>,
+        rate_limit: Option<&dyn RateLimiter>,
     ) -> AppResult<Crate> {
         use diesel::update;
 
@@ -329,6 +332,7 @@ impl Crate {
             homepage,
             documentation,
             repository,
+            repository_last_commit_at,
             ..
         } = self;
         let versions_link = match versions {
@@ -358,6 +362,10 @@ impl Crate {
             exact_match,
             description,
             repository,
+            repository_last_commit_at,
+            custom_links: None,
+            verified_publisher: None,
+            security_policy_url: None,
             links: EncodableCrateLinks {
                 version_downloads: format!("/api/v1/crates/{}/downloads", name),
                 versions: versions_link,
@@ -0,0 +1,191 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde_json::Value;
+
+use crate::models::{CrateLinks, User};
+use crate::schema::{
+    categories, crate_metadata_history, crate_policies, crates, crates_categories, crates_keywords,
+    keywords, users,
+};
+use crate::views::{EncodableDeprecation, EncodableMetadataChange};
+
+/// The crate-level metadata whose changes are recorded, as a JSON value per
+/// field. Lists are sorted, so that reordering them isn't a change.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CrateMetadata {
+    fields: Vec<(&'static str, Value)>,
+}
+
+impl CrateMetadata {
+    pub fn load(conn: &PgConnection, crate_id: i32) -> QueryResult<Self> {
+        let (description, homepage, documentation, repository) = crates::table
+            .find(crate_id)
+            .select((
+                crates::description,
+                crates::homepage,
+                crates::documentation,
+                crates::repository,
+            ))
+            .first::<(
+                Option<String>,
+                Option<String>,
+                Option<String>,
+                Option<String>,
+            )>(conn)?;
+        let categories = crates_categories::table
+            .inner_join(categories::table)
+            .filter(crates_categories::crate_id.eq(crate_id))
+            .select(categories::slug)
+            .order(categories::slug)
+            .load::<String>(conn)?;
+        let keywords = crates_keywords::table
+            .inner_join(keywords::table)
+            .filter(crates_keywords::crate_id.eq(crate_id))
+            .select(keywords::keyword)
+            .order(keywords::keyword)
+            .load::<String>(conn)?;
+        let links = CrateLinks::load(conn, crate_id)?;
+        let deprecation = crate_policies::table
+            .find(crate_id)
+            .select((
+                crate_policies::replaced_by,
+                crate_policies::deprecation_message,
+            ))
+            .first::<(Option<String>, Option<String>)>(conn)
+            .optional()?
+            .filter(|(replaced_by, message)| replaced_by.is_some() || message.is_some())
+            .map(|(replaced_by, message)| EncodableDeprecation {
+                replaced_by,
+                message,
+            });
+
+        Ok(Self {
+            fields: vec![
+                ("description", json!(description)),
+                ("homepage", json!(homepage)),
+                ("documentation", json!(documentation)),
+                ("repository", json!(repository)),
+                ("categories", json!(categories)),
+                ("keywords", json!(keywords)),
+                ("links", json!(links)),
+                ("deprecation", json!(deprecation)),
+            ],
+        })
+    }
+
+    /// The fields which differ from `previous`, with the previous and the
+    /// current value.
+    fn changes_since<'a>(
+        &'a self,
+        previous: &'a Self,
+    ) -> Vec<(&'static str, &'a Value, &'a Value)> {
+        self.fields
+            .iter()
+            .zip(&previous.fields)
+            .filter(|((_, value), (_, old_value))| value != old_value)
+            .map(|((field, value), (_, old_value))| (*field, old_value, value))
+            .collect()
+    }
+}
+
+/// A change of one field of the metadata of a crate.
+#[derive(Clone, Debug, PartialEq, Identifiable, Queryable)]
+#[table_name = "crate_metadata_history"]
+pub struct CrateMetadataChange {
+    pub id: i32,
+    pub crate_id: i32,
+    pub user_id: i32,
+    pub field: String,
+    pub old_value: Value,
+    pub new_value: Value,
+    pub created_at: NaiveDateTime,
+}
+
+impl CrateMetadataChange {
+    /// Records a change for every field of the metadata of a crate which
+    /// differs from `previous`, attributed to `user_id`. Returns the number
+    /// of changed fields.
+    pub fn record(
+        conn: &PgConnection,
+        crate_id: i32,
+        user_id: i32,
+        previous: &CrateMetadata,
+    ) -> QueryResult<usize> {
+        let current = CrateMetadata::load(conn, crate_id)?;
+        let rows = current
+            .changes_since(previous)
+            .into_iter()
+            .map(|(field, old_value, new_value)| {
+                (
+                    crate_metadata_history::crate_id.eq(crate_id),
+                    crate_metadata_history::user_id.eq(user_id),
+                    crate_metadata_history::field.eq(field),
+                    crate_metadata_history::old_value.eq(old_value),
+                    crate_metadata_history::new_value.eq(new_value),
+                )
+            })
+            .collect::<Vec<_>>();
+        diesel::insert_into(crate_metadata_history::table)
+            .values(&rows)
+            .execute(conn)
+    }
+
+    /// All changes of the metadata of the given crate, oldest first.
+    pub fn by_crate(conn: &PgConnection, crate_id: i32) -> QueryResult<Vec<(Self, User)>> {
+        crate_metadata_history::table
+            .inner_join(users::table)
+            .filter(crate_metadata_history::crate_id.eq(crate_id))
+            .order((
+                crate_metadata_history::created_at,
+                crate_metadata_history::id,
+            ))
+            .load(conn)
+    }
+
+    pub fn encodable(self, user: User) -> EncodableMetadataChange {
+        EncodableMetadataChange {
+            field: self.field,
+            old_value: self.old_value,
+            new_value: self.new_value,
+            user: user.encodable_public(),
+            time: self.created_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes_are_compared_per_field() {
+        let previous = CrateMetadata {
+            fields: vec![
+                ("description", json!("old")),
+                ("keywords", json!(["a", "b"])),
+                ("deprecation", Value::Null),
+            ],
+        };
+        let current = CrateMetadata {
+            fields: vec![
+                ("description", json!("old")),
+                ("keywords", json!(["b"])),
+                (
+                    "deprecation",
+                    json!({"replaced_by": "foo", "message": null}),
+                ),
+            ],
+        };
+        let changes = current.changes_since(&previous);
+        assert_eq!(
+            changes
+                .iter()
+                .map(|(field, _, _)| *field)
+                .collect::<Vec<_>>(),
+            vec!["keywords", "deprecation"]
+        );
+        assert_eq!(changes[0].1, &json!(["a", "b"]));
+        assert_eq!(changes[0].2, &json!(["b"]));
+        assert!(current.changes_since(&current).is_empty());
+    }
+}
@@ -0,0 +1,268 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::dsl::now;
+use diesel::prelude::*;
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+
+use crate::models::{Crate, OwnerKind};
+use crate::schema::{crate_owners, crates, org_domains, teams};
+use crate::views::{EncodableOrgDomain, EncodableOwner};
+
+/// The TXT record proving control over a domain is looked up at this
+/// subdomain of it.
+pub const CHALLENGE_SUBDOMAIN: &str = "_crates-io-challenge";
+
+/// A domain a GitHub organization asked to verify, proving that the crates
+/// owned by its teams are published by whoever controls the domain.
+///
+/// The organization proves its control with a TXT record containing
+/// `crates-io-verification=<challenge>` at `_crates-io-challenge.<domain>`,
+/// which is looked up by the `verify_org_domain` job.
+#[derive(Clone, Debug, PartialEq, Eq, Queryable, Identifiable)]
+pub struct OrgDomain {
+    pub id: i32,
+    /// The lowercased login of the GitHub organization.
+    pub org: String,
+    pub domain: String,
+    pub challenge: String,
+    pub requested_by: i32,
+    pub created_at: NaiveDateTime,
+    pub checked_at: Option<NaiveDateTime>,
+    pub verified_at: Option<NaiveDateTime>,
+}
+
+impl OrgDomain {
+    /// Starts the verification of a domain, or returns the pending or
+    /// finished verification if it was requested before. The challenge of a
+    /// domain never changes, so that its TXT record stays valid.
+    pub fn request(
+        conn: &PgConnection,
+        org: &str,
+        domain: &str,
+        user_id: i32,
+    ) -> QueryResult<OrgDomain> {
+        let challenge = thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .collect::<String>();
+        diesel::insert_into(org_domains::table)
+            .values((
+                org_domains::org.eq(org),
+                org_domains::domain.eq(domain),
+                org_domains::challenge.eq(challenge),
+                org_domains::requested_by.eq(user_id),
+            ))
+            .on_conflict((org_domains::org, org_domains::domain))
+            .do_update()
+            .set(org_domains::requested_by.eq(user_id))
+            .get_result(conn)
+    }
+
+    /// The domains of an organization, ordered by name.
+    pub fn by_org(conn: &PgConnection, org: &str) -> QueryResult<Vec<OrgDomain>> {
+        org_domains::table
+            .filter(org_domains::org.eq(org))
+            .order(org_domains::domain)
+            .load(conn)
+    }
+
+    /// The verified domains of the given organizations, as pairs of the
+    /// organization and the domain.
+    pub fn verified(conn: &PgConnection, orgs: &[String]) -> QueryResult<Vec<(String, String)>> {
+        if orgs.is_empty() {
+            return Ok(Vec::new());
+        }
+        org_domains::table
+            .filter(org_domains::org.eq_any(orgs))
+            .filter(org_domains::verified_at.is_not_null())
+            .select((org_domains::org, org_domains::domain))
+            .order((org_domains::org, org_domains::domain))
+            .load(conn)
+    }
+
+    /// The domain crates owned by teams of a verified organization are
+    /// shown as published by, if any.
+    pub fn verified_publisher(conn: &PgConnection, krate: &Crate) -> QueryResult<Option<String>> {
+        let team_logins = crate_owners::table
+            .inner_join(teams::table.on(teams::id.eq(crate_owners::owner_id)))
+            .filter(crate_owners::crate_id.eq(krate.id))
+            .filter(crate_owners::deleted.eq(false))
+            .filter(crate_owners::owner_kind.eq(OwnerKind::Team as i32))
+            .select(teams::login)
+            .load::<String>(conn)?;
+        let orgs = team_logins
+            .iter()
+            .filter_map(|login| org_of_team(login))
+            .collect::<Vec<_>>();
+        Ok(OrgDomain::verified(conn, &orgs)?
+            .into_iter()
+            .map(|(_, domain)| domain)
+            .next())
+    }
+
+    /// Adds the verified domains of their organizations to team owners.
+    pub fn add_to_owners(conn: &PgConnection, owners: &mut [EncodableOwner]) -> QueryResult<()> {
+        let orgs = owners
+            .iter()
+            .filter(|owner| owner.kind == "team")
+            .filter_map(|owner| org_of_team(&owner.login))
+            .collect::<Vec<_>>();
+        let verified = OrgDomain::verified(conn, &orgs)?;
+        for owner in owners.iter_mut().filter(|owner| owner.kind == "team") {
+            let org = org_of_team(&owner.login);
+            owner.verified_domains = verified
+                .iter()
+                .filter(|(verified_org, _)| Some(verified_org) == org.as_ref())
+                .map(|(_, domain)| domain.clone())
+                .collect();
+        }
+        Ok(())
+    }
+
+    /// The names of the crates owned by teams of an organization.
+    pub fn crates_of_org(conn: &PgConnection, org: &str) -> QueryResult<Vec<String>> {
+        crate_owners::table
+            .inner_join(teams::table.on(teams::id.eq(crate_owners::owner_id)))
+            .inner_join(crates::table)
+            .filter(crate_owners::deleted.eq(false))
+            .filter(crate_owners::owner_kind.eq(OwnerKind::Team as i32))
+            .filter(teams::login.like(org_teams_pattern(org)))
+            .select(crates::name)
+            .distinct()
+            .load(conn)
+    }
+
+    /// Looks up the TXT records of the challenge subdomain with `lookup` and
+    /// records whether the domain is verified. A domain whose record was
+    /// removed isn't verified anymore.
+    ///
+    /// Returns whether the domain is verified, or `None` if the lookup failed.
+    pub fn check<F>(&self, conn: &PgConnection, lookup: F) -> QueryResult<Option<bool>>
+    where
+        F: FnOnce(&str) -> Result<Vec<String>, String>,
+    {
+        let records = match lookup(&self.challenge_record_name()) {
+            Ok(records) => records,
+            Err(error) => {
+                eprintln!(
+                    "Looking up the TXT records of {} failed: {}",
+                    self.domain, error
+                );
+                return Ok(None);
+            }
+        };
+        let expected = self.challenge_record_value();
+        let verified = records.iter().any(|record| record.trim() == expected);
+
+        let verified_at = if verified {
+            self.verified_at.or_else(|| Some(Utc::now().naive_utc()))
+        } else {
+            None
+        };
+        diesel::update(org_domains::table.find(self.id))
+            .set((
+                org_domains::checked_at.eq(now),
+                org_domains::verified_at.eq(verified_at),
+            ))
+            .execute(conn)?;
+        Ok(Some(verified))
+    }
+
+    /// The name of the TXT record which proves control over the domain.
+    pub fn challenge_record_name(&self) -> String {
+        format!("{}.{}", CHALLENGE_SUBDOMAIN, self.domain)
+    }
+
+    /// The contents of the TXT record which proves control over the domain.
+    pub fn challenge_record_value(&self) -> String {
+        format!("crates-io-verification={}", self.challenge)
+    }
+
+    pub fn encodable(self) -> EncodableOrgDomain {
+        EncodableOrgDomain {
+            txt_record_name: self.challenge_record_name(),
+            txt_record_value: self.challenge_record_value(),
+            org: self.org,
+            domain: self.domain,
+            checked_at: self.checked_at,
+            verified_at: self.verified_at,
+        }
+    }
+}
+
+/// The lowercased organization of a team login like `github:org:team`.
+pub fn org_of_team(login: &str) -> Option<String> {
+    let mut chunks = login.split(':');
+    match (chunks.next(), chunks.next(), chunks.next()) {
+        (Some("github"), Some(org), Some(_)) => Some(org.to_lowercase()),
+        _ => None,
+    }
+}
+
+/// The `LIKE` pattern matching the logins of the teams of an organization,
+/// with the wildcards in its name escaped.
+pub fn org_teams_pattern(org: &str) -> String {
+    let org = org
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("github:{}:%", org)
+}
+
+/// Normalizes a domain name, returning `None` if it isn't one.
+///
+/// Only lowercase ASCII names with at least two labels are accepted, so
+/// internationalized domains have to be given in their punycode form.
+pub fn normalize_domain(domain: &str) -> Option<String> {
+    let domain = domain.trim().trim_end_matches('.').to_lowercase();
+    let labels = domain.split('.').collect::<Vec<_>>();
+    let valid_label = |label: &&str| {
+        !label.is_empty()
+            && label.len() <= 63
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    };
+    if domain.len() > 253 || labels.len() < 2 || !labels.iter().all(valid_label) {
+        return None;
+    }
+    Some(domain)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orgs_of_team_logins() {
+        assert_eq!(
+            org_of_team("github:Rust-Lang:owners"),
+            Some("rust-lang".into())
+        );
+        assert_eq!(org_of_team("github:rust-lang"), None);
+        assert_eq!(org_of_team("gitlab:rust-lang:owners"), None);
+    }
+
+    #[test]
+    fn wildcards_in_org_names_are_escaped() {
+        assert_eq!(org_teams_pattern("rust-lang"), "github:rust-lang:%");
+        assert_eq!(org_teams_pattern(r"a_b%c\d"), r"github:a\_b\%c\\d:%");
+    }
+
+    #[test]
+    fn domains_are_normalized() {
+        assert_eq!(
+            normalize_domain(" Example.COM. "),
+            Some("example.com".into())
+        );
+        assert_eq!(
+            normalize_domain("sub-domain.example.com"),
+            Some("sub-domain.example.com".into())
+        );
+        assert_eq!(normalize_domain("localhost"), None);
+        assert_eq!(normalize_domain("-foo.example.com"), None);
+        assert_eq!(normalize_domain("foo..example.com"), None);
+        assert_eq!(normalize_domain("foo_bar.example.com"), None);
+        assert_eq!(normalize_domain("https://example.com"), None);
+    }
+}
@@ -116,6 +116,7 @@ impl Owner {
                     url: Some(url),
                     name,
                     kind: String::from("user"),
+                    verified_domains: Vec::new(),
                 }
             }
             Owner::Team(Team {
@@ -133,6 +134,7 @@ impl Owner {
                     avatar,
                     name,
                     kind: String::from("team"),
+                    verified_domains: Vec::new(),
                 }
             }
         }
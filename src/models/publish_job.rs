@@ -0,0 +1,117 @@
+use chrono::NaiveDateTime;
+use diesel::dsl::now;
+use diesel::prelude::*;
+
+use crate::schema::publish_jobs;
+use crate::views::{EncodablePublishError, EncodablePublishStatus};
+
+/// The crate file waits to be uploaded and added to the index.
+pub const QUEUED: &str = "queued";
+/// The crate file was uploaded and the crate is in the index.
+pub const INDEXED: &str = "indexed";
+/// The last attempt to upload the crate file or update the index failed.
+pub const FAILED: &str = "failed";
+
+/// A publish with `?queued=true`, see `git::add_queued_crate`.
+#[derive(Clone, Debug, PartialEq, Queryable)]
+pub struct PublishJob {
+    pub id: i32,
+    pub version_id: i32,
+    pub user_id: i32,
+    pub state: String,
+    pub error: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+/// All columns but the path of the staged crate file.
+type Columns = (
+    publish_jobs::id,
+    publish_jobs::version_id,
+    publish_jobs::user_id,
+    publish_jobs::state,
+    publish_jobs::error,
+    publish_jobs::created_at,
+    publish_jobs::updated_at,
+);
+
+const COLUMNS: Columns = (
+    publish_jobs::id,
+    publish_jobs::version_id,
+    publish_jobs::user_id,
+    publish_jobs::state,
+    publish_jobs::error,
+    publish_jobs::created_at,
+    publish_jobs::updated_at,
+);
+
+impl PublishJob {
+    /// Records a queued publish of the version, returning its ID.
+    pub fn create(
+        conn: &PgConnection,
+        version_id: i32,
+        user_id: i32,
+        staged_path: &str,
+    ) -> QueryResult<i32> {
+        diesel::insert_into(publish_jobs::table)
+            .values((
+                publish_jobs::version_id.eq(version_id),
+                publish_jobs::user_id.eq(user_id),
+                publish_jobs::state.eq(QUEUED),
+                publish_jobs::staged_path.eq(staged_path),
+            ))
+            .returning(publish_jobs::id)
+            .get_result(conn)
+    }
+
+    pub fn by_version(conn: &PgConnection, version_id: i32) -> QueryResult<Option<Self>> {
+        publish_jobs::table
+            .select(COLUMNS)
+            .filter(publish_jobs::version_id.eq(version_id))
+            .first(conn)
+            .optional()
+    }
+
+    /// Where the crate file is staged in the storage, see
+    /// `Uploader::stage_crate_file`, until it was uploaded and added to the
+    /// index.
+    pub fn staged_path(conn: &PgConnection, id: i32) -> QueryResult<Option<String>> {
+        publish_jobs::table
+            .find(id)
+            .select(publish_jobs::staged_path)
+            .first(conn)
+    }
+
+    /// Marks the publish as indexed, forgetting the staged crate file.
+    pub fn finish(conn: &PgConnection, id: i32) -> QueryResult<()> {
+        diesel::update(publish_jobs::table.find(id))
+            .set((
+                publish_jobs::state.eq(INDEXED),
+                publish_jobs::error.eq(None::<String>),
+                publish_jobs::staged_path.eq(None::<String>),
+                publish_jobs::updated_at.eq(now),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn fail(conn: &PgConnection, id: i32, error: &str) -> QueryResult<()> {
+        diesel::update(publish_jobs::table.find(id))
+            .set((
+                publish_jobs::state.eq(FAILED),
+                publish_jobs::error.eq(error),
+                publish_jobs::updated_at.eq(now),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub fn encodable(self) -> EncodablePublishStatus {
+        EncodablePublishStatus {
+            state: self.state,
+            error: self.error.map(|detail| EncodablePublishError { detail }),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
+}
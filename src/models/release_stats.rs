@@ -0,0 +1,150 @@
+use chrono::{Duration, NaiveDateTime};
+use diesel::dsl::now;
+use diesel::prelude::*;
+
+use crate::schema::crate_release_stats;
+use crate::views::EncodableReleaseStats;
+
+/// Crates which were first released more recently count as being this many
+/// days old for `releases_per_year`, so that a few quick releases of a new
+/// crate don't add up to a high rate.
+const MIN_SPAN_DAYS: f32 = 365.0;
+
+/// Statistics on the release cadence of a crate, recomputed nightly by the
+/// `compute_release_stats` job.
+#[derive(Clone, Debug, PartialEq, Queryable, Identifiable)]
+#[primary_key(crate_id)]
+#[table_name = "crate_release_stats"]
+pub struct ReleaseStats {
+    pub crate_id: i32,
+    pub releases: i32,
+    pub avg_days_between_releases: Option<f32>,
+    pub longest_gap_days: Option<f32>,
+    pub releases_per_year: f32,
+    pub yank_rate: f32,
+    pub computed_at: NaiveDateTime,
+}
+
+#[derive(Clone, Debug, PartialEq, Insertable, AsChangeset)]
+#[table_name = "crate_release_stats"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct NewReleaseStats {
+    pub crate_id: i32,
+    pub releases: i32,
+    /// `None` for crates with a single release, like `longest_gap_days`.
+    pub avg_days_between_releases: Option<f32>,
+    pub longest_gap_days: Option<f32>,
+    pub releases_per_year: f32,
+    pub yank_rate: f32,
+}
+
+impl NewReleaseStats {
+    /// Computes the statistics of a crate as of `today` from whether each
+    /// of its versions is yanked and when it was published.
+    pub fn compute(
+        crate_id: i32,
+        versions: &[(bool, NaiveDateTime)],
+        today: NaiveDateTime,
+    ) -> Self {
+        let mut dates = versions
+            .iter()
+            .map(|&(_, created_at)| created_at)
+            .collect::<Vec<_>>();
+        dates.sort();
+
+        let gaps = dates
+            .windows(2)
+            .map(|pair| days(pair[1] - pair[0]))
+            .collect::<Vec<_>>();
+        let avg_days_between_releases = if gaps.is_empty() {
+            None
+        } else {
+            Some(gaps.iter().sum::<f32>() / gaps.len() as f32)
+        };
+        let longest_gap_days = gaps.iter().cloned().fold(None, |longest, gap| {
+            Some(longest.map_or(gap, |longest: f32| longest.max(gap)))
+        });
+
+        let span = dates
+            .first()
+            .map_or(0.0, |&first| days(today - first))
+            .max(MIN_SPAN_DAYS);
+        let yanked = versions.iter().filter(|(yanked, _)| *yanked).count();
+
+        Self {
+            crate_id,
+            releases: versions.len() as i32,
+            avg_days_between_releases,
+            longest_gap_days,
+            releases_per_year: versions.len() as f32 * 365.0 / span,
+            yank_rate: if versions.is_empty() {
+                0.0
+            } else {
+                yanked as f32 / versions.len() as f32
+            },
+        }
+    }
+
+    pub fn save(&self, conn: &PgConnection) -> QueryResult<()> {
+        diesel::insert_into(crate_release_stats::table)
+            .values((self, crate_release_stats::computed_at.eq(now)))
+            .on_conflict(crate_release_stats::crate_id)
+            .do_update()
+            .set((self, crate_release_stats::computed_at.eq(now)))
+            .execute(conn)?;
+        Ok(())
+    }
+}
+
+impl ReleaseStats {
+    pub fn encodable(self) -> EncodableReleaseStats {
+        EncodableReleaseStats {
+            releases: self.releases,
+            avg_days_between_releases: self.avg_days_between_releases,
+            longest_gap_days: self.longest_gap_days,
+            releases_per_year: self.releases_per_year,
+            yank_rate: self.yank_rate,
+            computed_at: self.computed_at,
+        }
+    }
+}
+
+fn days(duration: Duration) -> f32 {
+    duration.num_seconds() as f32 / 86_400.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd(y, m, d).and_hms(0, 0, 0)
+    }
+
+    #[test]
+    fn stats_of_regular_releases() {
+        let versions = [
+            (false, date(2018, 1, 1)),
+            (true, date(2018, 1, 11)),
+            (false, date(2018, 1, 31)),
+            (false, date(2019, 1, 1)),
+        ];
+        let stats = NewReleaseStats::compute(1, &versions, date(2020, 1, 1));
+        assert_eq!(stats.releases, 4);
+        assert_eq!(stats.avg_days_between_releases, Some(365.0 / 3.0));
+        assert_eq!(stats.longest_gap_days, Some(335.0));
+        assert!((stats.releases_per_year - 4.0 * 365.0 / 730.0).abs() < 1e-6);
+        assert_eq!(stats.yank_rate, 0.25);
+    }
+
+    #[test]
+    fn stats_of_a_new_crate() {
+        let versions = [(false, date(2019, 12, 1))];
+        let stats = NewReleaseStats::compute(1, &versions, date(2020, 1, 1));
+        assert_eq!(stats.avg_days_between_releases, None);
+        assert_eq!(stats.longest_gap_days, None);
+        assert_eq!(stats.releases_per_year, 1.0);
+        assert_eq!(stats.yank_rate, 0.0);
+    }
+}
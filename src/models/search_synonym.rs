@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use std::iter;
+
+use diesel::prelude::*;
+
+use crate::schema::search_synonyms;
+
+/// The maximum number of words of a term in the synonym dictionary.
+pub const MAX_TERM_WORDS: usize = 3;
+
+/// The maximum number of synonyms of a single term.
+pub const MAX_SYNONYMS: usize = 8;
+
+/// Searches with more words than this are not expanded at all.
+const MAX_QUERY_WORDS: usize = 10;
+
+/// The maximum number of variants a search is expanded to, including the
+/// original search.
+const MAX_QUERY_VARIANTS: usize = 10;
+
+/// An entry of the curated synonym dictionary in `src/boot/synonyms.toml`.
+#[derive(Clone, Debug, PartialEq, Eq, Queryable, Insertable)]
+#[table_name = "search_synonyms"]
+pub struct SearchSynonym {
+    pub term: String,
+    pub synonyms: Vec<String>,
+}
+
+impl SearchSynonym {
+    /// Expands a search into the variants that should be searched for.
+    ///
+    /// The first variant is always the search itself. Every further variant
+    /// replaces a single term of the search with one of its synonyms, e.g.
+    /// `fast http client` is expanded to `fast reqwest`, `fast hyper`, etc.
+    /// Synonyms are never combined with each other or expanded again, so the
+    /// number of variants grows linearly with the size of the dictionary and
+    /// is capped at a small maximum.
+    pub fn expand_query(conn: &PgConnection, q: &str) -> QueryResult<Vec<String>> {
+        let words = query_words(q);
+        let terms = candidate_terms(&words);
+        let dictionary = if terms.is_empty() {
+            HashMap::new()
+        } else {
+            search_synonyms::table
+                .filter(search_synonyms::term.eq_any(terms))
+                .load::<SearchSynonym>(conn)?
+                .into_iter()
+                .map(|synonym| (synonym.term, synonym.synonyms))
+                .collect()
+        };
+        Ok(query_variants(q, &words, &dictionary))
+    }
+}
+
+fn query_words(q: &str) -> Vec<String> {
+    q.split_whitespace().map(str::to_lowercase).collect()
+}
+
+/// Returns all sequences of words in the search that could be a term of the
+/// dictionary.
+fn candidate_terms(words: &[String]) -> Vec<String> {
+    if words.len() > MAX_QUERY_WORDS {
+        return Vec::new();
+    }
+
+    (1..=MAX_TERM_WORDS)
+        .flat_map(|len| words.windows(len).map(|window| window.join(" ")))
+        .collect()
+}
+
+fn query_variants(
+    q: &str,
+    words: &[String],
+    dictionary: &HashMap<String, Vec<String>>,
+) -> Vec<String> {
+    let mut variants = vec![q.to_string()];
+    if words.len() > MAX_QUERY_WORDS {
+        return variants;
+    }
+
+    // Longer terms are more specific, so their synonyms take precedence when
+    // the number of variants is capped.
+    for len in (1..=MAX_TERM_WORDS).rev() {
+        for (start, window) in words.windows(len).enumerate() {
+            let synonyms = match dictionary.get(&window.join(" ")) {
+                Some(synonyms) => synonyms,
+                None => continue,
+            };
+
+            for synonym in synonyms.iter().take(MAX_SYNONYMS) {
+                let variant = words[..start]
+                    .iter()
+                    .chain(iter::once(synonym))
+                    .chain(&words[start + len..])
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                if !variants.contains(&variant) {
+                    variants.push(variant);
+                }
+                if variants.len() >= MAX_QUERY_VARIANTS {
+                    return variants;
+                }
+            }
+        }
+    }
+
+    variants
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(q: &str, dictionary: &[(&str, &[&str])]) -> Vec<String> {
+        let dictionary = dictionary
+            .iter()
+            .map(|(term, synonyms)| {
+                let synonyms = synonyms.iter().map(|s| s.to_string()).collect();
+                (term.to_string(), synonyms)
+            })
+            .collect();
+        query_variants(q, &query_words(q), &dictionary)
+    }
+
+    #[test]
+    fn replaces_terms_with_synonyms() {
+        let dictionary: &[(&str, &[&str])] = &[
+            ("http client", &["reqwest", "hyper"]),
+            ("client", &["consumer"]),
+        ];
+
+        assert_eq!(expand("serde", dictionary), vec!["serde"]);
+        assert_eq!(
+            expand("Async HTTP Client", dictionary),
+            vec![
+                "Async HTTP Client",
+                "async reqwest",
+                "async hyper",
+                "async http consumer",
+            ]
+        );
+    }
+
+    #[test]
+    fn synonyms_are_not_expanded_again() {
+        let dictionary: &[(&str, &[&str])] =
+            &[("cli", &["command line"]), ("command line", &["cli"])];
+
+        assert_eq!(expand("cli", dictionary), vec!["cli", "command line"]);
+        assert_eq!(expand("cli cli", dictionary).len(), 3);
+    }
+
+    #[test]
+    fn expansion_is_capped() {
+        let many_synonyms = (0..100).map(|i| format!("s{}", i)).collect::<Vec<_>>();
+        let many_synonyms = many_synonyms.iter().map(|s| &**s).collect::<Vec<_>>();
+        let dictionary: &[(&str, &[&str])] = &[("a", &many_synonyms), ("b", &many_synonyms)];
+
+        assert_eq!(expand("a", dictionary).len(), 1 + MAX_SYNONYMS);
+        assert_eq!(expand("a b a b a b", dictionary).len(), MAX_QUERY_VARIANTS);
+
+        let long_query = vec!["a"; MAX_QUERY_WORDS + 1].join(" ");
+        assert_eq!(expand(&long_query, dictionary), vec![long_query.clone()]);
+        assert!(candidate_terms(&query_words(&long_query)).is_empty());
+    }
+}
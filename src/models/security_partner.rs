@@ -0,0 +1,261 @@
+use chrono::NaiveDateTime;
+use diesel::dsl::{count_star, exists, min};
+use diesel::prelude::*;
+
+use crate::schema::{
+    crates, partner_audit_log, security_partners, version_quarantines, version_verdicts, versions,
+};
+use crate::views::EncodableVerdict;
+
+pub const CLEAN: &str = "clean";
+pub const SUSPICIOUS: &str = "suspicious";
+pub const MALICIOUS: &str = "malicious";
+
+/// The verdicts partners can submit.
+pub const VERDICTS: &[&str] = &[CLEAN, SUSPICIOUS, MALICIOUS];
+
+/// Clean verdicts, which need no review.
+pub const RECORDED: &str = "recorded";
+/// Verdicts waiting in the review queue.
+pub const PENDING: &str = "pending";
+/// Verdicts an admin agreed with.
+pub const CONFIRMED: &str = "confirmed";
+/// Verdicts an admin found to be wrong.
+pub const DISMISSED: &str = "dismissed";
+
+/// A vetted security vendor, which submits verdicts about versions.
+///
+/// Partners authenticate with their `api_key` in the `X-Partner-Key` header,
+/// and can submit `verdicts_per_day` verdicts within any 24 hours. Malicious
+/// verdicts of partners with `auto_quarantine` quarantine the version right
+/// away, instead of only after a review.
+#[derive(Clone, Debug, PartialEq, Eq, Identifiable, Queryable)]
+pub struct SecurityPartner {
+    pub id: i32,
+    pub name: String,
+    pub contact_email: String,
+    pub api_key: String,
+    pub verdicts_per_day: i32,
+    pub auto_quarantine: bool,
+    pub created_at: NaiveDateTime,
+    pub revoked: bool,
+}
+
+impl SecurityPartner {
+    /// Registers a new partner, generating its API key.
+    pub fn create(
+        conn: &PgConnection,
+        name: &str,
+        contact_email: &str,
+        verdicts_per_day: i32,
+        auto_quarantine: bool,
+    ) -> QueryResult<Self> {
+        diesel::insert_into(security_partners::table)
+            .values((
+                security_partners::name.eq(name),
+                security_partners::contact_email.eq(contact_email),
+                security_partners::verdicts_per_day.eq(verdicts_per_day),
+                security_partners::auto_quarantine.eq(auto_quarantine),
+            ))
+            .get_result(conn)
+    }
+
+    pub fn find_by_api_key(conn: &PgConnection, api_key: &str) -> QueryResult<Option<Self>> {
+        security_partners::table
+            .filter(security_partners::api_key.eq(api_key))
+            .filter(security_partners::revoked.eq(false))
+            .first(conn)
+            .optional()
+    }
+
+    /// The number of verdicts submitted since `since`, and when the oldest
+    /// of them was submitted.
+    pub fn verdicts_since(
+        &self,
+        conn: &PgConnection,
+        since: NaiveDateTime,
+    ) -> QueryResult<(i64, Option<NaiveDateTime>)> {
+        version_verdicts::table
+            .filter(version_verdicts::partner_id.eq(self.id))
+            .filter(version_verdicts::created_at.gt(since))
+            .select((count_star(), min(version_verdicts::created_at)))
+            .first(conn)
+    }
+
+    /// Adds an entry to the audit log of the partner.
+    pub fn audit(
+        &self,
+        conn: &PgConnection,
+        action: &str,
+        version_id: Option<i32>,
+        details: &str,
+    ) -> QueryResult<()> {
+        diesel::insert_into(partner_audit_log::table)
+            .values((
+                partner_audit_log::partner_id.eq(self.id),
+                partner_audit_log::action.eq(action),
+                partner_audit_log::version_id.eq(version_id),
+                partner_audit_log::details.eq(details),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// The most recent entries of the audit log, as `(action, details, time)`.
+    pub fn audit_log(
+        &self,
+        conn: &PgConnection,
+        limit: i64,
+    ) -> QueryResult<Vec<(String, String, NaiveDateTime)>> {
+        partner_audit_log::table
+            .filter(partner_audit_log::partner_id.eq(self.id))
+            .select((
+                partner_audit_log::action,
+                partner_audit_log::details,
+                partner_audit_log::created_at,
+            ))
+            .order(partner_audit_log::created_at.desc())
+            .limit(limit)
+            .load(conn)
+    }
+}
+
+/// A verdict of a partner about the crate file of a version, pinned to the
+/// checksum of the file the partner scanned.
+#[derive(Clone, Debug, PartialEq, Identifiable, Queryable)]
+pub struct VersionVerdict {
+    pub id: i32,
+    pub partner_id: i32,
+    pub version_id: i32,
+    pub checksum: String,
+    pub verdict: String,
+    pub details: Option<String>,
+    pub status: String,
+    pub reviewed_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+impl VersionVerdict {
+    /// Records a verdict, adding it to the review queue unless it's clean.
+    pub fn submit(
+        conn: &PgConnection,
+        partner: &SecurityPartner,
+        version_id: i32,
+        checksum: &str,
+        verdict: &str,
+        details: Option<&str>,
+    ) -> QueryResult<Self> {
+        conn.transaction(|| {
+            let status = if verdict == CLEAN { RECORDED } else { PENDING };
+            let submitted: Self = diesel::insert_into(version_verdicts::table)
+                .values((
+                    version_verdicts::partner_id.eq(partner.id),
+                    version_verdicts::version_id.eq(version_id),
+                    version_verdicts::checksum.eq(checksum),
+                    version_verdicts::verdict.eq(verdict),
+                    version_verdicts::details.eq(details),
+                    version_verdicts::status.eq(status),
+                ))
+                .get_result(conn)?;
+            if partner.auto_quarantine && submitted.verdict == MALICIOUS {
+                quarantine(conn, version_id, Some(submitted.id))?;
+            }
+            Ok(submitted)
+        })
+    }
+
+    /// The review queue, as `(verdict, partner, crate, version)`. Malicious
+    /// verdicts come first, then the oldest ones.
+    pub fn pending(conn: &PgConnection) -> QueryResult<Vec<(Self, String, String, String)>> {
+        version_verdicts::table
+            .inner_join(security_partners::table)
+            .inner_join(versions::table.inner_join(crates::table))
+            .filter(version_verdicts::status.eq(PENDING))
+            .select((
+                version_verdicts::all_columns,
+                security_partners::name,
+                crates::name,
+                versions::num,
+            ))
+            .order((
+                version_verdicts::verdict.eq(MALICIOUS).desc(),
+                version_verdicts::created_at.asc(),
+            ))
+            .load(conn)
+    }
+
+    /// Records the decision about a pending verdict in the audit log of its
+    /// partner, releasing the quarantine the verdict caused if it was
+    /// dismissed. Returns `None` if there's no such pending verdict.
+    pub fn review(
+        conn: &PgConnection,
+        id: i32,
+        status: &str,
+        now: NaiveDateTime,
+    ) -> QueryResult<Option<Self>> {
+        conn.transaction(|| {
+            let verdict = diesel::update(
+                version_verdicts::table
+                    .find(id)
+                    .filter(version_verdicts::status.eq(PENDING)),
+            )
+            .set((
+                version_verdicts::status.eq(status),
+                version_verdicts::reviewed_at.eq(now),
+            ))
+            .get_result::<Self>(conn)
+            .optional()?;
+            if let Some(verdict) = &verdict {
+                let partner = security_partners::table
+                    .find(verdict.partner_id)
+                    .first::<SecurityPartner>(conn)?;
+                let details = format!("verdict {} was {}", verdict.id, status);
+                partner.audit(conn, "review", Some(verdict.version_id), &details)?;
+                if status == DISMISSED {
+                    diesel::delete(
+                        version_quarantines::table
+                            .filter(version_quarantines::verdict_id.eq(verdict.id)),
+                    )
+                    .execute(conn)?;
+                }
+            }
+            Ok(verdict)
+        })
+    }
+
+    pub fn encodable(self, quarantined: bool) -> EncodableVerdict {
+        EncodableVerdict {
+            id: self.id,
+            verdict: self.verdict,
+            status: self.status,
+            quarantined,
+            created_at: self.created_at,
+        }
+    }
+}
+
+/// Keeps the version from being downloaded until it's released again.
+pub fn quarantine(
+    conn: &PgConnection,
+    version_id: i32,
+    verdict_id: Option<i32>,
+) -> QueryResult<()> {
+    diesel::insert_into(version_quarantines::table)
+        .values((
+            version_quarantines::version_id.eq(version_id),
+            version_quarantines::verdict_id.eq(verdict_id),
+        ))
+        .on_conflict_do_nothing()
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Lifts the quarantine of the version, returning whether it was quarantined.
+pub fn release(conn: &PgConnection, version_id: i32) -> QueryResult<bool> {
+    let released = diesel::delete(version_quarantines::table.find(version_id)).execute(conn)?;
+    Ok(released > 0)
+}
+
+pub fn is_quarantined(conn: &PgConnection, version_id: i32) -> QueryResult<bool> {
+    diesel::select(exists(version_quarantines::table.find(version_id))).get_result(conn)
+}
@@ -0,0 +1,165 @@
+use chrono::NaiveDateTime;
+use diesel::dsl::now;
+use diesel::prelude::*;
+
+use crate::models::VersionFile;
+use crate::sanitize::repository_file_url;
+use crate::schema::crate_security_policies;
+
+/// Where GitHub looks for a security policy, in the order of precedence.
+/// The file names are compared case-insensitively.
+const POLICY_PATHS: &[&str] = &["SECURITY.md", ".github/SECURITY.md", "docs/SECURITY.md"];
+
+/// The security policy file published with the most recent version of a
+/// crate, linked to in its repository.
+#[derive(Clone, Debug, PartialEq, Eq, Queryable, Identifiable)]
+#[primary_key(crate_id)]
+#[table_name = "crate_security_policies"]
+pub struct CrateSecurityPolicy {
+    pub crate_id: i32,
+    pub version_id: i32,
+    /// The path of the file in the crate file.
+    pub path: String,
+    pub url: String,
+    pub updated_at: NaiveDateTime,
+}
+
+/// Finds the security policy among the files of a crate file, and returns
+/// its path and the URL of the file in the repository, at the commit the
+/// crate was packaged from.
+///
+/// The URL can only be built for repositories on the hosts README links are
+/// resolved for. `vcs_path` is the directory of the package in the
+/// repository, which older versions of cargo don't record, in which case the
+/// package is assumed to be at the root.
+pub fn locate_security_policy(
+    files: &[VersionFile],
+    repository: Option<&str>,
+    vcs_commit: Option<&str>,
+    vcs_path: Option<&str>,
+) -> Option<(String, String)> {
+    let path = POLICY_PATHS.iter().find_map(|candidate| {
+        files
+            .iter()
+            .find(|file| file.path.eq_ignore_ascii_case(candidate))
+            .map(|file| &file.path)
+    })?;
+    let path_in_repository = match vcs_path {
+        Some(dir) if !dir.is_empty() => format!("{}/{}", dir.trim_end_matches('/'), path),
+        _ => path.clone(),
+    };
+    let url = repository_file_url(repository?, vcs_commit, &path_in_repository)?;
+    Some((path.clone(), url))
+}
+
+impl CrateSecurityPolicy {
+    /// Replaces the security policy of a crate with the one published with
+    /// the given version, as `(path, url)`.
+    ///
+    /// Like the crate policy, publishing a version without one removes it,
+    /// so that it always reflects the most recent release.
+    pub fn update_crate(
+        conn: &PgConnection,
+        crate_id: i32,
+        version_id: i32,
+        policy: Option<(String, String)>,
+    ) -> QueryResult<()> {
+        match policy {
+            Some((path, url)) => {
+                diesel::insert_into(crate_security_policies::table)
+                    .values((
+                        crate_security_policies::crate_id.eq(crate_id),
+                        crate_security_policies::version_id.eq(version_id),
+                        crate_security_policies::path.eq(&path),
+                        crate_security_policies::url.eq(&url),
+                    ))
+                    .on_conflict(crate_security_policies::crate_id)
+                    .do_update()
+                    .set((
+                        crate_security_policies::version_id.eq(version_id),
+                        crate_security_policies::path.eq(&path),
+                        crate_security_policies::url.eq(&url),
+                        crate_security_policies::updated_at.eq(now),
+                    ))
+                    .execute(conn)?;
+            }
+            None => {
+                diesel::delete(crate_security_policies::table.find(crate_id)).execute(conn)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn url(conn: &PgConnection, crate_id: i32) -> QueryResult<Option<String>> {
+        crate_security_policies::table
+            .find(crate_id)
+            .select(crate_security_policies::url)
+            .first(conn)
+            .optional()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(paths: &[&str]) -> Vec<VersionFile> {
+        paths
+            .iter()
+            .map(|path| VersionFile {
+                path: path.to_string(),
+                size: 0,
+                sha256: String::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn security_policies_are_linked_in_the_repository() {
+        let repository = Some("https://github.com/rust-lang/foo");
+        let commit = Some("0123456789abcdef0123456789abcdef01234567");
+        assert_eq!(
+            locate_security_policy(
+                &files(&["Cargo.toml", ".github/SECURITY.md", "security.md"]),
+                repository,
+                commit,
+                None
+            ),
+            Some((
+                "security.md".into(),
+                "https://github.com/rust-lang/foo/blob/0123456789abcdef0123456789abcdef01234567/security.md".into()
+            ))
+        );
+        assert_eq!(
+            locate_security_policy(
+                &files(&["docs/SECURITY.md"]),
+                Some("https://gitlab.com/foo/bar.git"),
+                None,
+                Some("crates/foo")
+            ),
+            Some((
+                "docs/SECURITY.md".into(),
+                "https://gitlab.com/foo/bar/-/blob/HEAD/crates/foo/docs/SECURITY.md".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn security_policies_need_a_known_repository() {
+        let root = files(&["SECURITY.md"]);
+        assert_eq!(locate_security_policy(&root, None, None, None), None);
+        assert_eq!(
+            locate_security_policy(&root, Some("https://example.com/foo"), None, None),
+            None
+        );
+        assert_eq!(
+            locate_security_policy(
+                &files(&["src/SECURITY.md"]),
+                Some("https://github.com/foo/bar"),
+                None,
+                None
+            ),
+            None
+        );
+    }
+}
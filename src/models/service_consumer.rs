@@ -0,0 +1,91 @@
+use chrono::{NaiveDateTime, Timelike};
+use diesel::prelude::*;
+
+use crate::schema::{service_consumer_usage, service_consumers};
+
+/// A registered tool which reads from the API at a high volume, like a
+/// mirror or a dependency scanner.
+///
+/// Service consumers authenticate with their read-only `api_key` in the
+/// `X-Service-Key` header, and are allowed `requests_per_hour` requests.
+#[derive(Clone, Debug, PartialEq, Eq, Identifiable, Queryable)]
+pub struct ServiceConsumer {
+    pub id: i32,
+    pub name: String,
+    pub contact_email: String,
+    pub api_key: String,
+    pub requests_per_hour: i32,
+    pub created_at: NaiveDateTime,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub revoked: bool,
+}
+
+impl ServiceConsumer {
+    /// Registers a new service consumer, generating its API key.
+    pub fn create(
+        conn: &PgConnection,
+        name: &str,
+        contact_email: &str,
+        requests_per_hour: i32,
+    ) -> QueryResult<Self> {
+        diesel::insert_into(service_consumers::table)
+            .values((
+                service_consumers::name.eq(name),
+                service_consumers::contact_email.eq(contact_email),
+                service_consumers::requests_per_hour.eq(requests_per_hour),
+            ))
+            .get_result(conn)
+    }
+
+    pub fn find_by_api_key(conn: &PgConnection, api_key: &str) -> QueryResult<Option<Self>> {
+        service_consumers::table
+            .filter(service_consumers::api_key.eq(api_key))
+            .filter(service_consumers::revoked.eq(false))
+            .first(conn)
+            .optional()
+    }
+
+    /// Counts a request made at `now`, and returns the number of requests made
+    /// within the same hour, including this one.
+    pub fn record_request(&self, conn: &PgConnection, now: NaiveDateTime) -> QueryResult<i32> {
+        use self::service_consumer_usage::dsl::*;
+
+        conn.transaction(|| {
+            diesel::update(self)
+                .set(service_consumers::last_used_at.eq(now))
+                .execute(conn)?;
+            diesel::insert_into(service_consumer_usage)
+                .values((
+                    service_consumer_id.eq(self.id),
+                    hour.eq(start_of_hour(now)),
+                    requests.eq(1),
+                ))
+                .on_conflict((service_consumer_id, hour))
+                .do_update()
+                .set(requests.eq(requests + 1))
+                .returning(requests)
+                .get_result(conn)
+        })
+    }
+
+    /// The number of requests made in each of the hours since `since`, most
+    /// recent first.
+    pub fn usage_since(
+        &self,
+        conn: &PgConnection,
+        since: NaiveDateTime,
+    ) -> QueryResult<Vec<(NaiveDateTime, i32)>> {
+        use self::service_consumer_usage::dsl::*;
+
+        service_consumer_usage
+            .filter(service_consumer_id.eq(self.id))
+            .filter(hour.ge(start_of_hour(since)))
+            .order(hour.desc())
+            .select((hour, requests))
+            .load(conn)
+    }
+}
+
+pub fn start_of_hour(time: NaiveDateTime) -> NaiveDateTime {
+    time.date().and_hms(time.hour(), 0, 0)
+}
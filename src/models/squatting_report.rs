@@ -0,0 +1,288 @@
+use chrono::{Duration, NaiveDateTime};
+use diesel::prelude::*;
+
+use crate::models::{Crate, Owner, User};
+use crate::schema::{crates, squatting_reports};
+use crate::util::errors::{cargo_err, AppResult};
+
+/// Users can report at most this many crates within a day.
+const MAX_REPORTS_PER_DAY: i64 = 5;
+
+/// The maximum length of the reason of a report, in bytes.
+const MAX_REASON_LENGTH: usize = 1000;
+
+/// Crates with fewer downloads than this count as unused. Mirrors and
+/// scanners download every new version, so this can't be zero.
+const MAX_DOWNLOADS: i32 = 500;
+
+/// Tarballs up to this size, in bytes, are assumed to contain no code. A
+/// crate containing a manifest and an empty `lib.rs` is about half of that.
+const MAX_EMPTY_CRATE_SIZE: i32 = 2_000;
+
+/// Crates which haven't been updated for this many days count as inactive.
+const MIN_INACTIVE_DAYS: i64 = 365;
+
+/// Words in the description or README of a crate that suggest it only
+/// reserves its name.
+const PLACEHOLDER_WORDS: &[&str] = &["placeholder", "reserved", "squat", "coming soon"];
+
+/// Reports waiting for the `check_squatting_reports` job.
+pub const PENDING: &str = "pending";
+/// Reports whose crate matched all heuristics, waiting for an admin.
+pub const CANDIDATE: &str = "candidate";
+/// Reports whose crate didn't match all heuristics.
+pub const DISMISSED: &str = "dismissed";
+/// Reports an admin has decided on.
+pub const RESOLVED: &str = "resolved";
+
+/// The decisions admins can make about a reported crate.
+pub const OUTCOMES: &[&str] = &["released", "transferred", "kept"];
+
+/// A report of a crate name which looks squatted.
+#[derive(Clone, Debug, PartialEq, Identifiable, Queryable)]
+pub struct SquattingReport {
+    pub id: i32,
+    pub crate_id: i32,
+    pub reporter_id: i32,
+    pub reason: String,
+    pub status: String,
+    pub heuristics: Option<serde_json::Value>,
+    pub checked_at: Option<NaiveDateTime>,
+    pub outcome: Option<String>,
+    pub resolved_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+/// The signs of squatting a reported crate was checked for. They are stored
+/// with every report, so that the heuristics can be tuned by comparing them
+/// with the outcomes of resolved reports.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SquattingHeuristics {
+    pub no_code: bool,
+    pub no_downloads: bool,
+    pub single_version: bool,
+    pub inactive: bool,
+    pub placeholder: bool,
+}
+
+impl SquattingHeuristics {
+    pub fn evaluate(conn: &PgConnection, krate: &Crate, now: NaiveDateTime) -> QueryResult<Self> {
+        use crate::schema::versions;
+
+        let sizes = krate
+            .all_versions()
+            .select(versions::crate_size)
+            .load::<Option<i32>>(conn)?;
+        let readme = crates::table
+            .find(krate.id)
+            .select(crates::readme)
+            .first::<Option<String>>(conn)?;
+
+        let texts = [krate.description.as_ref(), readme.as_ref()];
+        let texts = texts
+            .iter()
+            .filter_map(|text| *text)
+            .map(|text| text.to_lowercase())
+            .filter(|text| !text.trim().is_empty())
+            .collect::<Vec<_>>();
+
+        Ok(Self {
+            no_code: !sizes.is_empty()
+                && sizes
+                    .iter()
+                    .all(|size| size.map_or(false, |size| size <= MAX_EMPTY_CRATE_SIZE)),
+            no_downloads: krate.downloads < MAX_DOWNLOADS,
+            single_version: sizes.len() == 1,
+            inactive: krate.updated_at < now - Duration::days(MIN_INACTIVE_DAYS),
+            placeholder: texts.is_empty()
+                || texts
+                    .iter()
+                    .any(|text| PLACEHOLDER_WORDS.iter().any(|word| text.contains(word))),
+        })
+    }
+
+    /// Whether a crate looks squatted enough to be reviewed by an admin.
+    /// `placeholder` is only recorded, since many real crates lack a
+    /// description.
+    pub fn is_candidate(&self) -> bool {
+        self.no_code && self.no_downloads && self.single_version && self.inactive
+    }
+}
+
+impl SquattingReport {
+    /// Records a report by `reporter`. Only users with a verified email
+    /// address can report crates, at most `MAX_REPORTS_PER_DAY` per day and
+    /// each crate once.
+    pub fn create(
+        conn: &PgConnection,
+        krate: &Crate,
+        reporter: &User,
+        reason: &str,
+        now: NaiveDateTime,
+    ) -> AppResult<Self> {
+        let reason = reason.trim();
+        if reason.is_empty() {
+            return Err(cargo_err("a reason is required to report a crate"));
+        }
+        if reason.len() > MAX_REASON_LENGTH {
+            return Err(cargo_err(&format_args!(
+                "the reason must not be longer than {} bytes",
+                MAX_REASON_LENGTH
+            )));
+        }
+        if reporter.verified_email(conn)?.is_none() {
+            return Err(cargo_err(
+                "a verified email address is required to report crates",
+            ));
+        }
+        let is_owner = krate.owners(conn)?.iter().any(|owner| match owner {
+            Owner::User(user) => user.id == reporter.id,
+            Owner::Team(_) => false,
+        });
+        if is_owner {
+            return Err(cargo_err("you can't report your own crate"));
+        }
+
+        let recent = squatting_reports::table
+            .filter(squatting_reports::reporter_id.eq(reporter.id))
+            .filter(squatting_reports::created_at.gt(now - Duration::days(1)))
+            .count()
+            .get_result::<i64>(conn)?;
+        if recent >= MAX_REPORTS_PER_DAY {
+            return Err(cargo_err(&format_args!(
+                "you can report at most {} crates per day",
+                MAX_REPORTS_PER_DAY
+            )));
+        }
+
+        diesel::insert_into(squatting_reports::table)
+            .values((
+                squatting_reports::crate_id.eq(krate.id),
+                squatting_reports::reporter_id.eq(reporter.id),
+                squatting_reports::reason.eq(reason),
+                squatting_reports::created_at.eq(now),
+            ))
+            .on_conflict_do_nothing()
+            .get_result(conn)
+            .optional()?
+            .ok_or_else(|| cargo_err("you have already reported this crate"))
+    }
+
+    /// Checks the crates of all pending reports, and returns how many
+    /// reports were checked.
+    pub fn check_pending(conn: &PgConnection, now: NaiveDateTime) -> QueryResult<usize> {
+        let crate_ids = squatting_reports::table
+            .filter(squatting_reports::status.eq(PENDING))
+            .select(squatting_reports::crate_id)
+            .distinct()
+            .load::<i32>(conn)?;
+
+        let mut checked = 0;
+        for crate_id in crate_ids {
+            let krate = Crate::all()
+                .filter(crates::id.eq(crate_id))
+                .first::<Crate>(conn)?;
+            let heuristics = SquattingHeuristics::evaluate(conn, &krate, now)?;
+            let status = if heuristics.is_candidate() {
+                CANDIDATE
+            } else {
+                DISMISSED
+            };
+            checked += diesel::update(
+                squatting_reports::table
+                    .filter(squatting_reports::crate_id.eq(crate_id))
+                    .filter(squatting_reports::status.eq(PENDING)),
+            )
+            .set((
+                squatting_reports::status.eq(status),
+                squatting_reports::heuristics.eq(serde_json::to_value(heuristics).ok()),
+                squatting_reports::checked_at.eq(now),
+            ))
+            .execute(conn)?;
+        }
+        Ok(checked)
+    }
+
+    /// The crates which are waiting for an admin, with the number of reports
+    /// and the reasons given, oldest report first.
+    pub fn candidates(conn: &PgConnection) -> QueryResult<Vec<(String, Vec<String>)>> {
+        let reports = squatting_reports::table
+            .inner_join(crates::table)
+            .filter(squatting_reports::status.eq(CANDIDATE))
+            .order((squatting_reports::created_at, squatting_reports::id))
+            .select((crates::name, squatting_reports::reason))
+            .load::<(String, String)>(conn)?;
+
+        let mut candidates: Vec<(String, Vec<String>)> = Vec::new();
+        for (name, reason) in reports {
+            match candidates
+                .iter_mut()
+                .find(|(candidate, _)| *candidate == name)
+            {
+                Some((_, reasons)) => reasons.push(reason),
+                None => candidates.push((name, vec![reason])),
+            }
+        }
+        Ok(candidates)
+    }
+
+    /// Marks all unresolved reports of a crate as resolved, and returns how
+    /// many there were.
+    pub fn resolve(
+        conn: &PgConnection,
+        crate_id: i32,
+        outcome: &str,
+        now: NaiveDateTime,
+    ) -> QueryResult<usize> {
+        diesel::update(
+            squatting_reports::table
+                .filter(squatting_reports::crate_id.eq(crate_id))
+                .filter(squatting_reports::status.ne(RESOLVED)),
+        )
+        .set((
+            squatting_reports::status.eq(RESOLVED),
+            squatting_reports::outcome.eq(outcome),
+            squatting_reports::resolved_at.eq(now),
+        ))
+        .execute(conn)
+    }
+
+    /// The heuristics and outcomes of all resolved reports which were
+    /// checked, for comparing them.
+    pub fn outcomes(conn: &PgConnection) -> QueryResult<Vec<(SquattingHeuristics, String)>> {
+        let resolved = squatting_reports::table
+            .filter(squatting_reports::status.eq(RESOLVED))
+            .select((squatting_reports::heuristics, squatting_reports::outcome))
+            .load::<(Option<serde_json::Value>, Option<String>)>(conn)?;
+        Ok(resolved
+            .into_iter()
+            .filter_map(|(heuristics, outcome)| {
+                let heuristics = serde_json::from_value(heuristics?).ok()?;
+                Some((heuristics, outcome?))
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn candidates_match_all_but_the_placeholder_heuristic() {
+        let heuristics = SquattingHeuristics {
+            no_code: true,
+            no_downloads: true,
+            single_version: true,
+            inactive: true,
+            placeholder: false,
+        };
+        assert!(heuristics.is_candidate());
+        assert!(!SquattingHeuristics {
+            inactive: false,
+            placeholder: true,
+            ..heuristics
+        }
+        .is_candidate());
+    }
+}
@@ -0,0 +1,117 @@
+//! The targets a crate declares to support, for finding platform specific
+//! crates with the `target` filter of the crate search.
+//!
+//! Targets are declared in the manifest, either as a list
+//!
+//! ```toml
+//! [package.metadata.crates-io]
+//! targets = ["wasm32-unknown-unknown", "thumbv7em-none-eabihf"]
+//! ```
+//!
+//! or as the keys of a table, which leaves room for settings per target:
+//!
+//! ```toml
+//! [package.metadata.crates-io.targets]
+//! wasm32-unknown-unknown = {}
+//! ```
+//!
+//! Unknown targets are rejected, so that typos don't hide a crate from the
+//! filter.
+
+use crate::util::errors::{cargo_err, AppResult};
+
+/// The targets rustc knows about, see `targets.txt`.
+fn known_targets() -> impl Iterator<Item = &'static str> {
+    include_str!("targets.txt")
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+}
+
+pub fn is_known_target(target: &str) -> bool {
+    known_targets().any(|known| known == target)
+}
+
+/// Extracts the targets declared in a manifest, sorted and without
+/// duplicates. Returns an error if one of them is unknown or malformed.
+pub fn declared_targets(manifest: &toml::Value) -> AppResult<Vec<String>> {
+    let declared = match manifest
+        .get("package")
+        .and_then(|package| package.get("metadata"))
+        .and_then(|metadata| metadata.get("crates-io"))
+        .and_then(|crates_io| crates_io.get("targets"))
+    {
+        Some(declared) => declared,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut targets = match declared {
+        toml::Value::Array(targets) => targets
+            .iter()
+            .map(|target| {
+                target.as_str().map(String::from).ok_or_else(|| {
+                    cargo_err("`package.metadata.crates-io.targets` must only contain strings")
+                })
+            })
+            .collect::<AppResult<Vec<_>>>()?,
+        toml::Value::Table(targets) => targets.keys().cloned().collect(),
+        _ => {
+            return Err(cargo_err(
+                "`package.metadata.crates-io.targets` must be a list or a table of targets",
+            ))
+        }
+    };
+
+    if let Some(unknown) = targets.iter().find(|target| !is_known_target(target)) {
+        return Err(cargo_err(&format_args!(
+            "unknown target `{}` in `package.metadata.crates-io.targets`, \
+             see `rustc --print target-list` for the known ones",
+            unknown
+        )));
+    }
+    targets.sort();
+    targets.dedup();
+    Ok(targets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn targets(manifest: &str) -> AppResult<Vec<String>> {
+        declared_targets(&manifest.parse().unwrap())
+    }
+
+    #[test]
+    fn targets_are_declared_as_a_list_or_table() {
+        let list = r#"
+[package.metadata.crates-io]
+targets = ["wasm32-unknown-unknown", "thumbv7em-none-eabihf", "wasm32-unknown-unknown"]
+"#;
+        assert_eq!(
+            targets(list).unwrap(),
+            vec!["thumbv7em-none-eabihf", "wasm32-unknown-unknown"]
+        );
+
+        let table = r#"
+[package.metadata.crates-io.targets]
+x86_64-unknown-linux-gnu = {}
+wasm32-wasi = {}
+"#;
+        assert_eq!(
+            targets(table).unwrap(),
+            vec!["wasm32-wasi", "x86_64-unknown-linux-gnu"]
+        );
+        assert!(targets("[package]\nname = \"foo\"\n").unwrap().is_empty());
+    }
+
+    #[test]
+    fn unknown_targets_are_rejected() {
+        let typo = "[package.metadata.crates-io]\ntargets = [\"wasm32-unknown-unknwon\"]\n";
+        assert!(targets(typo).is_err());
+        assert!(targets("[package.metadata.crates-io]\ntargets = [1]\n").is_err());
+        assert!(targets("[package.metadata.crates-io]\ntargets = \"wasm32-wasi\"\n").is_err());
+        assert!(is_known_target("thumbv8m.main-none-eabihf"));
+        assert!(!is_known_target("# The targets"));
+    }
+}
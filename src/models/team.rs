@@ -211,6 +211,29 @@ impl Team {
     }
 }
 
+/// Whether the user is an admin of the GitHub organization.
+///
+/// Like `Team::contains_user`, this assumes that the given user is the one
+/// interested in the answer.
+pub fn is_org_admin(app: &App, org: &str, user: &User) -> AppResult<bool> {
+    // GET orgs/:org/memberships/:user_name
+    // check that "state": "active" and "role": "admin"
+
+    #[derive(Deserialize)]
+    struct Membership {
+        state: String,
+        role: String,
+    }
+
+    let url = format!("/orgs/{}/memberships/{}", org, &user.gh_login);
+    let token = AccessToken::new(user.gh_access_token.clone());
+    let membership = match github_api::<Membership>(app, &url, &token) {
+        Err(ref e) if e.is::<NotFound>() => return Ok(false),
+        x => x?,
+    };
+    Ok(membership.state == "active" && membership.role == "admin")
+}
+
 fn team_with_gh_id_contains_user(app: &App, github_id: i32, user: &User) -> AppResult<bool> {
     // GET teams/:team_id/memberships/:user_name
     // check that "state": "active"
@@ -0,0 +1,151 @@
+use chrono::NaiveDateTime;
+use diesel::dsl::max;
+use diesel::prelude::*;
+use hex::ToHex;
+use openssl::pkey::{PKey, Private};
+
+use crate::schema::{signed_tree_heads, transparency_log_entries};
+use crate::transparency_log::{self, Hash};
+use crate::util::errors::AppResult;
+use crate::views::{EncodableLogEntry, EncodableTreeHead};
+
+/// The record of the crate file of a published version in the transparency
+/// log. `leaf_index` is set once the entry is part of a signed tree head.
+#[derive(Clone, Debug, PartialEq, Eq, Identifiable, Queryable)]
+#[table_name = "transparency_log_entries"]
+pub struct TransparencyLogEntry {
+    pub id: i32,
+    pub leaf_index: Option<i32>,
+    pub crate_name: String,
+    pub num: String,
+    pub checksum: String,
+    pub published_at: NaiveDateTime,
+}
+
+impl TransparencyLogEntry {
+    pub fn append(
+        conn: &PgConnection,
+        crate_name: &str,
+        num: &str,
+        checksum: &str,
+    ) -> QueryResult<()> {
+        diesel::insert_into(transparency_log_entries::table)
+            .values((
+                transparency_log_entries::crate_name.eq(crate_name),
+                transparency_log_entries::num.eq(num),
+                transparency_log_entries::checksum.eq(checksum),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Up to `limit` entries of the tree, starting at the leaf `start`.
+    pub fn range(conn: &PgConnection, start: i32, limit: i64) -> QueryResult<Vec<Self>> {
+        transparency_log_entries::table
+            .filter(transparency_log_entries::leaf_index.ge(start))
+            .order(transparency_log_entries::leaf_index)
+            .limit(limit)
+            .load(conn)
+    }
+
+    pub fn leaf_hash(&self) -> Hash {
+        let data = transparency_log::leaf_data(
+            &self.crate_name,
+            &self.num,
+            &self.checksum,
+            self.published_at,
+        );
+        transparency_log::leaf_hash(data.as_bytes())
+    }
+
+    pub fn encodable(self) -> EncodableLogEntry {
+        EncodableLogEntry {
+            leaf_index: self.leaf_index.unwrap_or_default(),
+            krate: self.crate_name,
+            num: self.num,
+            checksum: self.checksum,
+            published_at: transparency_log::format_timestamp(self.published_at),
+        }
+    }
+}
+
+/// A signature of the root hash of the first `tree_size` entries.
+#[derive(Clone, Debug, PartialEq, Eq, Identifiable, Queryable)]
+pub struct SignedTreeHead {
+    pub id: i32,
+    pub tree_size: i32,
+    pub root_hash: String,
+    pub signature: String,
+    pub public_key: String,
+    pub signed_at: NaiveDateTime,
+}
+
+impl SignedTreeHead {
+    pub fn latest(conn: &PgConnection) -> QueryResult<Option<Self>> {
+        signed_tree_heads::table
+            .order(signed_tree_heads::id.desc())
+            .first(conn)
+            .optional()
+    }
+
+    /// Adds the entries appended since the last tree head to the tree, and
+    /// signs the head of the grown tree.
+    ///
+    /// A head is signed even if no entries were appended, so that auditors
+    /// can tell that the log is still kept.
+    pub fn sign(conn: &PgConnection, key: &PKey<Private>, now: NaiveDateTime) -> AppResult<Self> {
+        conn.transaction(|| {
+            // Leaf indexes have to be assigned by one job at a time.
+            diesel::sql_query("LOCK TABLE signed_tree_heads IN EXCLUSIVE MODE").execute(conn)?;
+
+            let mut tree_size = transparency_log_entries::table
+                .select(max(transparency_log_entries::leaf_index))
+                .first::<Option<i32>>(conn)?
+                .map_or(0, |index| index + 1);
+            let new_entries = transparency_log_entries::table
+                .filter(transparency_log_entries::leaf_index.is_null())
+                .select(transparency_log_entries::id)
+                .order(transparency_log_entries::id)
+                .load::<i32>(conn)?;
+            for id in new_entries {
+                diesel::update(transparency_log_entries::table.find(id))
+                    .set(transparency_log_entries::leaf_index.eq(tree_size))
+                    .execute(conn)?;
+                tree_size += 1;
+            }
+
+            let leaves = transparency_log_entries::table
+                .filter(transparency_log_entries::leaf_index.is_not_null())
+                .order(transparency_log_entries::leaf_index)
+                .load::<TransparencyLogEntry>(conn)?
+                .iter()
+                .map(TransparencyLogEntry::leaf_hash)
+                .collect::<Vec<_>>();
+            let root_hash = transparency_log::root_hash(&leaves).encode_hex::<String>();
+            let message = transparency_log::tree_head_message(tree_size, &root_hash, now);
+            let signature = transparency_log::sign(key, &message)?;
+            let public_key = String::from_utf8(key.public_key_to_pem()?)?;
+
+            let head = diesel::insert_into(signed_tree_heads::table)
+                .values((
+                    signed_tree_heads::tree_size.eq(tree_size),
+                    signed_tree_heads::root_hash.eq(root_hash),
+                    signed_tree_heads::signature.eq(hex::encode(signature)),
+                    signed_tree_heads::public_key.eq(public_key),
+                    signed_tree_heads::signed_at.eq(now),
+                ))
+                .get_result(conn)?;
+            Ok(head)
+        })
+    }
+
+    pub fn encodable(self) -> EncodableTreeHead {
+        EncodableTreeHead {
+            tree_size: self.tree_size,
+            root_hash: self.root_hash,
+            timestamp: transparency_log::format_timestamp(self.signed_at),
+            signature: self.signature,
+            public_key: self.public_key,
+        }
+    }
+}
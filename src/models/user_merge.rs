@@ -0,0 +1,195 @@
+use chrono::NaiveDateTime;
+use diesel::dsl::now;
+use diesel::prelude::*;
+use serde_json::Value;
+
+use crate::models::{OwnerKind, User};
+use crate::schema::{
+    api_tokens, crate_link_events, crate_metadata_history, crate_owners, emails, follows,
+    user_merges, version_owner_actions, versions, yank_events,
+};
+
+/// A duplicate user account which was merged into another one, e.g. an
+/// account of an old OAuth identity into the current one.
+#[derive(Clone, Debug, PartialEq, Identifiable, Queryable)]
+pub struct UserMerge {
+    pub id: i32,
+    pub from_user_id: i32,
+    pub into_user_id: i32,
+    /// The `MergeReport`, which lists everything needed to reverse the merge.
+    pub report: Value,
+    pub created_at: NaiveDateTime,
+}
+
+/// What merging a user account into another one changed. Crates and
+/// versions are listed by ID, as are the moved rows.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MergeReport {
+    /// Crates which are owned by the other account now, instead of the
+    /// merged one.
+    pub moved_ownerships: Vec<i32>,
+    /// Crates which were owned by both accounts, and are no longer owned by
+    /// the merged one.
+    pub removed_ownerships: Vec<i32>,
+    /// Crates which are followed by the other account now, instead of the
+    /// merged one.
+    pub moved_follows: Vec<i32>,
+    /// Crates which were followed by both accounts, and are no longer
+    /// followed by the merged one.
+    pub removed_follows: Vec<i32>,
+    /// The API tokens of the merged account, which were revoked rather than
+    /// moved, since their owners may not know about the merge.
+    pub revoked_tokens: Vec<i32>,
+    /// The email address of the merged account, if the other account had
+    /// none.
+    pub moved_email: Option<i32>,
+    pub published_versions: Vec<i32>,
+    pub version_owner_actions: Vec<i32>,
+    pub yank_events: Vec<i32>,
+    pub crate_metadata_history: Vec<i32>,
+    pub crate_link_events: Vec<i32>,
+}
+
+impl UserMerge {
+    /// Moves the crates, follows, email address and audit entries of `from`
+    /// to `into`, revokes the API tokens of `from` and records the merge.
+    /// Must be called in a transaction.
+    pub fn merge(
+        conn: &PgConnection,
+        from: &User,
+        into: &User,
+    ) -> QueryResult<(Self, MergeReport)> {
+        let mut report = MergeReport::default();
+
+        let owned_crates = |user_id: i32| {
+            crate_owners::table
+                .filter(crate_owners::owner_id.eq(user_id))
+                .filter(crate_owners::owner_kind.eq(OwnerKind::User as i32))
+                .filter(crate_owners::deleted.eq(false))
+                .select((crate_owners::crate_id, crate_owners::created_by))
+                .load::<(i32, Option<i32>)>(conn)
+        };
+        let already_owned = owned_crates(into.id)?
+            .into_iter()
+            .map(|(crate_id, _)| crate_id)
+            .collect::<Vec<_>>();
+        for (crate_id, created_by) in owned_crates(from.id)? {
+            if already_owned.contains(&crate_id) {
+                report.removed_ownerships.push(crate_id);
+                continue;
+            }
+            // `into` may have been an owner before, so its row is reused
+            diesel::insert_into(crate_owners::table)
+                .values((
+                    crate_owners::crate_id.eq(crate_id),
+                    crate_owners::owner_id.eq(into.id),
+                    crate_owners::owner_kind.eq(OwnerKind::User as i32),
+                    crate_owners::created_by.eq(created_by),
+                ))
+                .on_conflict((
+                    crate_owners::crate_id,
+                    crate_owners::owner_id,
+                    crate_owners::owner_kind,
+                ))
+                .do_update()
+                .set((
+                    crate_owners::deleted.eq(false),
+                    crate_owners::updated_at.eq(now),
+                ))
+                .execute(conn)?;
+            report.moved_ownerships.push(crate_id);
+        }
+        diesel::update(
+            crate_owners::table
+                .filter(crate_owners::owner_id.eq(from.id))
+                .filter(crate_owners::owner_kind.eq(OwnerKind::User as i32)),
+        )
+        .set((
+            crate_owners::deleted.eq(true),
+            crate_owners::updated_at.eq(now),
+        ))
+        .execute(conn)?;
+
+        let followed = |user_id: i32| {
+            follows::table
+                .filter(follows::user_id.eq(user_id))
+                .select(follows::crate_id)
+                .load::<i32>(conn)
+        };
+        let already_followed = followed(into.id)?;
+        let (removed, moved): (Vec<_>, Vec<_>) = followed(from.id)?
+            .into_iter()
+            .partition(|crate_id| already_followed.contains(crate_id));
+        report.removed_follows = removed;
+        report.moved_follows = moved;
+        diesel::delete(follows::table.filter(follows::user_id.eq(from.id))).execute(conn)?;
+        let new_follows = report
+            .moved_follows
+            .iter()
+            .map(|&crate_id| (follows::user_id.eq(into.id), follows::crate_id.eq(crate_id)))
+            .collect::<Vec<_>>();
+        if !new_follows.is_empty() {
+            diesel::insert_into(follows::table)
+                .values(&new_follows)
+                .execute(conn)?;
+        }
+
+        report.revoked_tokens = diesel::update(
+            api_tokens::table
+                .filter(api_tokens::user_id.eq(from.id))
+                .filter(api_tokens::revoked.eq(false)),
+        )
+        .set(api_tokens::revoked.eq(true))
+        .returning(api_tokens::id)
+        .get_results(conn)?;
+
+        let has_email = diesel::select(diesel::dsl::exists(
+            emails::table.filter(emails::user_id.eq(into.id)),
+        ))
+        .get_result::<bool>(conn)?;
+        if !has_email {
+            report.moved_email = diesel::update(emails::table.filter(emails::user_id.eq(from.id)))
+                .set(emails::user_id.eq(into.id))
+                .returning(emails::id)
+                .get_result(conn)
+                .optional()?;
+        }
+
+        report.published_versions =
+            diesel::update(versions::table.filter(versions::published_by.eq(from.id)))
+                .set(versions::published_by.eq(into.id))
+                .returning(versions::id)
+                .get_results(conn)?;
+        report.version_owner_actions = diesel::update(
+            version_owner_actions::table.filter(version_owner_actions::user_id.eq(from.id)),
+        )
+        .set(version_owner_actions::user_id.eq(into.id))
+        .returning(version_owner_actions::id)
+        .get_results(conn)?;
+        report.yank_events =
+            diesel::update(yank_events::table.filter(yank_events::user_id.eq(from.id)))
+                .set(yank_events::user_id.eq(into.id))
+                .returning(yank_events::id)
+                .get_results(conn)?;
+        report.crate_metadata_history = diesel::update(
+            crate_metadata_history::table.filter(crate_metadata_history::user_id.eq(from.id)),
+        )
+        .set(crate_metadata_history::user_id.eq(into.id))
+        .returning(crate_metadata_history::id)
+        .get_results(conn)?;
+        report.crate_link_events =
+            diesel::update(crate_link_events::table.filter(crate_link_events::user_id.eq(from.id)))
+                .set(crate_link_events::user_id.eq(into.id))
+                .returning(crate_link_events::id)
+                .get_results(conn)?;
+
+        let merge = diesel::insert_into(user_merges::table)
+            .values((
+                user_merges::from_user_id.eq(from.id),
+                user_merges::into_user_id.eq(into.id),
+                user_merges::report.eq(serde_json::to_value(&report).unwrap()),
+            ))
+            .get_result(conn)?;
+        Ok((merge, report))
+    }
+}
@@ -2,7 +2,15 @@ use std::collections::HashMap;
 
 use chrono::NaiveDateTime;
 use diesel::prelude::*;
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::Pg,
+    serialize::{self, Output, ToSql},
+    sql_types::Integer,
+};
+use std::io::Write;
 
+use crate::spdx::LicenseExpr;
 use crate::util::errors::{cargo_err, AppResult};
 
 use crate::models::{Crate, Dependency, User, VersionOwnerAction};
@@ -24,6 +32,28 @@ pub struct Version {
     pub license: Option<String>,
     pub crate_size: Option<i32>,
     pub published_by: Option<i32>,
+    pub publish_channel: Option<PublishChannel>,
+    #[serde(default)]
+    pub compression: CompressionFormat,
+    /// The license as a normalized SPDX expression, see the `spdx` module.
+    #[serde(default)]
+    pub license_expression: Option<String>,
+    /// The licenses the expression mentions, for filtering by license.
+    #[serde(default)]
+    pub license_ids: Option<Vec<String>>,
+    /// The `package.links` value of the manifest. Unknown for versions
+    /// published before it was recorded.
+    #[serde(default)]
+    pub links: Option<String>,
+    /// The `package.rust-version` value of the manifest, the oldest Rust
+    /// version the crate supports. Unknown for versions published before it
+    /// was recorded.
+    #[serde(default)]
+    pub rust_version: Option<String>,
+    /// The targets the crate declared to support, see the `targets` module.
+    /// Empty if it didn't declare any.
+    #[serde(default)]
+    pub targets: Vec<String>,
 }
 
 #[derive(Insertable, Debug)]
@@ -35,6 +65,89 @@ pub struct NewVersion {
     license: Option<String>,
     crate_size: Option<i32>,
     published_by: i32,
+    publish_channel: PublishChannel,
+    license_expression: Option<String>,
+    license_ids: Option<Vec<String>>,
+}
+
+/// How a version entered the registry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromSqlRow, AsExpression, Serialize, Deserialize)]
+#[repr(i32)]
+#[sql_type = "Integer"]
+#[serde(rename_all = "kebab-case")]
+pub enum PublishChannel {
+    /// Uploaded by `cargo publish` through the API.
+    Registry = 0,
+    /// Uploaded by a CI workflow using trusted publishing.
+    TrustedPublishing = 1,
+    /// Imported from another registry instance by an administrator.
+    AdminImport = 2,
+}
+
+impl Into<&'static str> for PublishChannel {
+    fn into(self) -> &'static str {
+        match self {
+            PublishChannel::Registry => "registry",
+            PublishChannel::TrustedPublishing => "trusted-publishing",
+            PublishChannel::AdminImport => "admin-import",
+        }
+    }
+}
+
+impl Into<String> for PublishChannel {
+    fn into(self) -> String {
+        let string: &'static str = self.into();
+
+        string.into()
+    }
+}
+
+impl FromSql<Integer, Pg> for PublishChannel {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match <i32 as FromSql<Integer, Pg>>::from_sql(bytes)? {
+            0 => Ok(PublishChannel::Registry),
+            1 => Ok(PublishChannel::TrustedPublishing),
+            2 => Ok(PublishChannel::AdminImport),
+            n => Err(format!("unknown publish channel: {}", n).into()),
+        }
+    }
+}
+
+impl ToSql<Integer, Pg> for PublishChannel {
+    fn to_sql<W: Write>(&self, out: &mut Output<'_, W, Pg>) -> serialize::Result {
+        ToSql::<Integer, Pg>::to_sql(&(*self as i32), out)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromSqlRow, AsExpression, Serialize, Deserialize)]
+#[repr(i32)]
+#[sql_type = "Integer"]
+#[serde(rename_all = "lowercase")]
+pub enum CompressionFormat {
+    Gzip = 0,
+    Zstd = 1,
+}
+
+impl Default for CompressionFormat {
+    fn default() -> Self {
+        CompressionFormat::Gzip
+    }
+}
+
+impl FromSql<Integer, Pg> for CompressionFormat {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match <i32 as FromSql<Integer, Pg>>::from_sql(bytes)? {
+            0 => Ok(CompressionFormat::Gzip),
+            1 => Ok(CompressionFormat::Zstd),
+            n => Err(format!("unknown compression format: {}", n).into()),
+        }
+    }
+}
+
+impl ToSql<Integer, Pg> for CompressionFormat {
+    fn to_sql<W: Write>(&self, out: &mut Output<'_, W, Pg>) -> serialize::Result {
+        ToSql::<Integer, Pg>::to_sql(&(*self as i32), out)
+    }
 }
 
 /// The highest version (semver order) and the most recently updated version.
@@ -73,6 +186,9 @@ impl Version {
             yanked,
             license,
             crate_size,
+            publish_channel,
+            compression,
+            targets,
             ..
         } = self;
         let num = num.to_string();
@@ -95,6 +211,9 @@ impl Version {
             },
             crate_size,
             published_by: published_by.map(User::encodable_public),
+            publish_channel: publish_channel.map(Into::into),
+            compression,
+            targets,
             audit_actions: audit_actions
                 .into_iter()
                 .map(|(audit_action, user)| EncodableAuditAction {
@@ -151,6 +270,114 @@ impl Version {
             .execute(conn)
     }
 
+    /// Records the hex encoded SHA-256 checksum of the crate file.
+    pub fn record_checksum(
+        version_id_: i32,
+        checksum_: &str,
+        conn: &PgConnection,
+    ) -> QueryResult<usize> {
+        use crate::schema::version_checksums::dsl::*;
+
+        diesel::insert_into(version_checksums)
+            .values((version_id.eq(version_id_), checksum.eq(checksum_)))
+            .on_conflict(version_id)
+            .do_update()
+            .set(checksum.eq(checksum_))
+            .execute(conn)
+    }
+
+    /// The checksum of the crate file, unless the version was published
+    /// before checksums were recorded.
+    pub fn checksum(version_id_: i32, conn: &PgConnection) -> QueryResult<Option<String>> {
+        use crate::schema::version_checksums::dsl::*;
+
+        version_checksums
+            .find(version_id_)
+            .select(checksum)
+            .first(conn)
+            .optional()
+    }
+
+    /// Records the `package.links` value, which is only needed to rebuild the
+    /// index entry.
+    pub fn record_links(
+        version_id: i32,
+        links: Option<&str>,
+        conn: &PgConnection,
+    ) -> QueryResult<usize> {
+        diesel::update(versions::table.find(version_id))
+            .set(versions::links.eq(links))
+            .execute(conn)
+    }
+
+    /// Records the `package.rust-version` value.
+    pub fn record_rust_version(
+        version_id: i32,
+        rust_version: Option<&str>,
+        conn: &PgConnection,
+    ) -> QueryResult<usize> {
+        diesel::update(versions::table.find(version_id))
+            .set(versions::rust_version.eq(rust_version))
+            .execute(conn)
+    }
+
+    /// Records the targets declared in the manifest.
+    pub fn record_targets(
+        version_id: i32,
+        targets: &[String],
+        conn: &PgConnection,
+    ) -> QueryResult<usize> {
+        diesel::update(versions::table.find(version_id))
+            .set(versions::targets.eq(targets))
+            .execute(conn)
+    }
+
+    /// Records how the crate file is compressed, which is only known once it
+    /// was verified.
+    pub fn record_compression(
+        version_id: i32,
+        compression: CompressionFormat,
+        conn: &PgConnection,
+    ) -> QueryResult<usize> {
+        diesel::update(versions::table.find(version_id))
+            .set(versions::compression.eq(compression))
+            .execute(conn)
+    }
+
+    /// Stores the sigstore bundle signing the crate file, unless the version
+    /// is signed already. Returns whether it was stored.
+    pub fn record_signature(
+        version_id_: i32,
+        bundle_: &str,
+        signer_: Option<&str>,
+        user_id: i32,
+        conn: &PgConnection,
+    ) -> QueryResult<bool> {
+        use crate::schema::version_signatures::dsl::*;
+
+        let inserted = diesel::insert_into(version_signatures)
+            .values((
+                version_id.eq(version_id_),
+                bundle.eq(bundle_),
+                signer.eq(signer_),
+                uploaded_by.eq(user_id),
+            ))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        Ok(inserted > 0)
+    }
+
+    /// The sigstore bundle signing the crate file, if one was uploaded.
+    pub fn signature(version_id_: i32, conn: &PgConnection) -> QueryResult<Option<String>> {
+        use crate::schema::version_signatures::dsl::*;
+
+        version_signatures
+            .find(version_id_)
+            .select(bundle)
+            .first(conn)
+            .optional()
+    }
+
     /// Gets the User who ran `cargo publish` for this version, if recorded.
     /// Not for use when you have a group of versions you need the publishers for.
     pub fn published_by(&self, conn: &PgConnection) -> Option<User> {
@@ -180,6 +407,9 @@ impl NewVersion {
             license,
             crate_size: Some(crate_size),
             published_by,
+            publish_channel: PublishChannel::Registry,
+            license_expression: None,
+            license_ids: None,
         };
 
         new_version.validate_license(license_file)?;
@@ -187,6 +417,15 @@ impl NewVersion {
         Ok(new_version)
     }
 
+    /// Records that the version entered the registry through `publish_channel`
+    /// instead of a regular `cargo publish`.
+    pub fn with_publish_channel(self, publish_channel: PublishChannel) -> Self {
+        Self {
+            publish_channel,
+            ..self
+        }
+    }
+
     pub fn save(
         &self,
         conn: &PgConnection,
@@ -235,16 +474,20 @@ impl NewVersion {
 
     fn validate_license(&mut self, license_file: Option<&str>) -> AppResult<()> {
         if let Some(ref license) = self.license {
+            let invalid = |e: &dyn std::fmt::Display| {
+                cargo_err(&format_args!(
+                    "{}; see http://opensource.org/licenses \
+                     for options, and http://spdx.org/licenses/ \
+                     for their identifiers",
+                    e
+                ))
+            };
             for part in license.split('/') {
-                license_exprs::validate_license_expr(part).map_err(|e| {
-                    cargo_err(&format_args!(
-                        "{}; see http://opensource.org/licenses \
-                         for options, and http://spdx.org/licenses/ \
-                         for their identifiers",
-                        e
-                    ))
-                })?;
+                license_exprs::validate_license_expr(part).map_err(|e| invalid(&e))?;
             }
+            let expr = LicenseExpr::parse(license).map_err(|e| invalid(&e))?;
+            self.license_expression = Some(expr.to_string());
+            self.license_ids = Some(expr.licenses());
         } else if license_file.is_some() {
             // If no license is given, but a license file is given, flag this
             // crate as having a nonstandard license. Note that we don't
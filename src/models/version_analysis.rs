@@ -0,0 +1,56 @@
+use std::collections::BTreeMap;
+
+use diesel::prelude::*;
+use serde_json::Value;
+
+use crate::schema::version_analyses;
+
+/// The report of an analyzer run over the crate file of a version, see the
+/// `tarball` module.
+#[derive(Clone, Debug, PartialEq, Queryable, Insertable)]
+#[table_name = "version_analyses"]
+pub struct VersionAnalysis {
+    pub version_id: i32,
+    pub analyzer: String,
+    pub result: Value,
+}
+
+impl VersionAnalysis {
+    /// Saves the reports of the analyzers, replacing earlier reports of the
+    /// same analyzers, e.g. when they are run again by a backfill.
+    pub fn save(
+        conn: &PgConnection,
+        version_id: i32,
+        analyses: BTreeMap<String, Value>,
+    ) -> QueryResult<()> {
+        use diesel::pg::upsert::excluded;
+
+        let rows = analyses
+            .into_iter()
+            .map(|(analyzer, result)| VersionAnalysis {
+                version_id,
+                analyzer,
+                result,
+            })
+            .collect::<Vec<_>>();
+        diesel::insert_into(version_analyses::table)
+            .values(&rows)
+            .on_conflict((version_analyses::version_id, version_analyses::analyzer))
+            .do_update()
+            .set(version_analyses::result.eq(excluded(version_analyses::result)))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// The reports stored for a version, by analyzer.
+    pub fn by_version(
+        conn: &PgConnection,
+        version_id: i32,
+    ) -> QueryResult<BTreeMap<String, Value>> {
+        version_analyses::table
+            .filter(version_analyses::version_id.eq(version_id))
+            .select((version_analyses::analyzer, version_analyses::result))
+            .load(conn)
+            .map(|analyses: Vec<(String, Value)>| analyses.into_iter().collect())
+    }
+}
@@ -0,0 +1,74 @@
+use diesel::prelude::*;
+
+use crate::schema::version_files;
+use crate::views::EncodableVersionFile;
+
+/// Files are inserted in batches of this many rows, which keeps large crates
+/// below the limit of bind parameters per query.
+const INSERT_BATCH_SIZE: usize = 1000;
+
+/// A file in the crate file of a version, as it was when it was published.
+#[derive(Clone, Debug, PartialEq, Eq, Queryable)]
+pub struct VersionFile {
+    /// The path of the file, relative to the root of the crate.
+    pub path: String,
+    pub size: i64,
+    /// The hex encoded SHA-256 checksum of the file's contents.
+    pub sha256: String,
+}
+
+#[derive(Insertable)]
+#[table_name = "version_files"]
+struct NewVersionFile<'a> {
+    version_id: i32,
+    path: &'a str,
+    size: i64,
+    sha256: &'a str,
+}
+
+impl VersionFile {
+    /// Saves the files of a version, replacing the files saved before, e.g.
+    /// when they are collected again by a backfill.
+    pub fn save(conn: &PgConnection, version_id: i32, files: &[VersionFile]) -> QueryResult<()> {
+        conn.transaction(|| {
+            diesel::delete(version_files::table.filter(version_files::version_id.eq(version_id)))
+                .execute(conn)?;
+            for batch in files.chunks(INSERT_BATCH_SIZE) {
+                let rows = batch
+                    .iter()
+                    .map(|file| NewVersionFile {
+                        version_id,
+                        path: &file.path,
+                        size: file.size,
+                        sha256: &file.sha256,
+                    })
+                    .collect::<Vec<_>>();
+                diesel::insert_into(version_files::table)
+                    .values(&rows)
+                    .execute(conn)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// The files stored for a version, ordered by path.
+    pub fn by_version(conn: &PgConnection, version_id: i32) -> QueryResult<Vec<VersionFile>> {
+        version_files::table
+            .filter(version_files::version_id.eq(version_id))
+            .select((
+                version_files::path,
+                version_files::size,
+                version_files::sha256,
+            ))
+            .order(version_files::path)
+            .load(conn)
+    }
+
+    pub fn encodable(self) -> EncodableVersionFile {
+        EncodableVersionFile {
+            path: self.path,
+            size: self.size,
+            sha256: self.sha256,
+        }
+    }
+}
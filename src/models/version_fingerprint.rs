@@ -0,0 +1,226 @@
+use std::collections::{BTreeMap, HashSet};
+
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde_json::Value;
+
+use crate::schema::{crates, version_content_matches, version_fingerprints, versions};
+
+/// Crates need this many downloads for new crates to be compared with them,
+/// since copying a crate nobody uses doesn't trick anyone.
+const MIN_POPULAR_DOWNLOADS: i32 = 10_000;
+
+/// The similarity from which a new crate is considered a copy.
+const MIN_SIMILARITY: f32 = 0.8;
+
+/// Versions sharing a source file with a new crate which are compared with
+/// it, those of the most downloaded crates first.
+const MAX_CANDIDATES: i64 = 100;
+
+/// The hashes the `fingerprint` analyzer reported for the crate file of a
+/// version.
+#[derive(Clone, Debug, PartialEq, Eq, Queryable)]
+pub struct VersionFingerprint {
+    pub version_id: i32,
+    pub crate_id: i32,
+    pub hashes: Vec<String>,
+}
+
+impl VersionFingerprint {
+    /// Saves the hashes in the `fingerprint` report of `analyses`, replacing
+    /// the ones saved before, and returns them. Nothing is changed if the
+    /// analyzer didn't run.
+    pub fn save(
+        conn: &PgConnection,
+        crate_id: i32,
+        version_id: i32,
+        analyses: &BTreeMap<String, Value>,
+    ) -> QueryResult<Option<Vec<String>>> {
+        let hashes = match analyses.get("fingerprint") {
+            Some(report) => {
+                serde_json::from_value::<Vec<String>>(report["hashes"].clone()).unwrap_or_default()
+            }
+            None => return Ok(None),
+        };
+        diesel::insert_into(version_fingerprints::table)
+            .values((
+                version_fingerprints::version_id.eq(version_id),
+                version_fingerprints::crate_id.eq(crate_id),
+                version_fingerprints::hashes.eq(&hashes),
+            ))
+            .on_conflict(version_fingerprints::version_id)
+            .do_update()
+            .set(version_fingerprints::hashes.eq(&hashes))
+            .execute(conn)?;
+        Ok(Some(hashes))
+    }
+}
+
+/// A version of a new crate whose sources are nearly identical to the ones
+/// of a version of a popular crate, which is what typosquats look like.
+#[derive(Clone, Debug, PartialEq, Queryable)]
+pub struct ContentMatch {
+    pub version_id: i32,
+    pub matched_version_id: i32,
+    /// The share of the fingerprinted files both versions have, from
+    /// `MIN_SIMILARITY` to 1.
+    pub similarity: f32,
+    pub created_at: NaiveDateTime,
+}
+
+impl ContentMatch {
+    /// Compares the hashes of a version with the ones of the versions of
+    /// popular crates sharing any of them, and records the most similar one
+    /// if it's a copy.
+    pub fn detect(
+        conn: &PgConnection,
+        crate_id: i32,
+        version_id: i32,
+        hashes: &[String],
+    ) -> QueryResult<Option<Self>> {
+        if hashes.is_empty() {
+            return Ok(None);
+        }
+        let candidates = version_fingerprints::table
+            .inner_join(crates::table)
+            .filter(version_fingerprints::crate_id.ne(crate_id))
+            .filter(crates::downloads.ge(MIN_POPULAR_DOWNLOADS))
+            .filter(version_fingerprints::hashes.overlaps_with(hashes))
+            .order(crates::downloads.desc())
+            .limit(MAX_CANDIDATES)
+            .select((
+                version_fingerprints::version_id,
+                version_fingerprints::hashes,
+            ))
+            .load::<(i32, Vec<String>)>(conn)?;
+
+        // On ties, the most downloaded crate is the likely original
+        let mut best = None;
+        for (matched_version_id, other) in candidates {
+            let similarity = similarity(hashes, &other);
+            if similarity >= MIN_SIMILARITY && best.map_or(true, |(_, s)| similarity > s) {
+                best = Some((matched_version_id, similarity));
+            }
+        }
+        let (matched_version_id, similarity) = match best {
+            Some(best) => best,
+            None => return Ok(None),
+        };
+
+        let values = (
+            version_content_matches::matched_version_id.eq(matched_version_id),
+            version_content_matches::similarity.eq(similarity),
+        );
+        diesel::insert_into(version_content_matches::table)
+            .values((version_content_matches::version_id.eq(version_id), values))
+            .on_conflict(version_content_matches::version_id)
+            .do_update()
+            .set(values)
+            .get_result(conn)
+            .map(Some)
+    }
+
+    /// The match of a version, with the crate name and number of the matched
+    /// version.
+    pub fn by_version(
+        conn: &PgConnection,
+        version_id: i32,
+    ) -> QueryResult<Option<(Self, String, String)>> {
+        let content_match = match version_content_matches::table
+            .find(version_id)
+            .first::<Self>(conn)
+            .optional()?
+        {
+            Some(content_match) => content_match,
+            None => return Ok(None),
+        };
+        let (name, num) = versions::table
+            .inner_join(crates::table)
+            .filter(versions::id.eq(content_match.matched_version_id))
+            .select((crates::name, versions::num))
+            .first(conn)?;
+        Ok(Some((content_match, name, num)))
+    }
+}
+
+/// The share of all hashes of two versions which both have.
+fn similarity(a: &[String], b: &[String]) -> f32 {
+    let a = a.iter().collect::<HashSet<_>>();
+    let b = b.iter().collect::<HashSet<_>>();
+    let all = a.union(&b).count();
+    if all == 0 {
+        return 0.0;
+    }
+    a.intersection(&b).count() as f32 / all as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{new_crate, new_user, new_version, pg_connection};
+
+    /// Publishes a crate with the given downloads and fingerprint, returning
+    /// the IDs of the crate and its version.
+    fn publish(conn: &PgConnection, name: &str, downloads: i32, hashes: &[String]) -> (i32, i32) {
+        let user = new_user(conn, 2, "login");
+        let krate = new_crate(conn, name, user.id);
+        diesel::update(crates::table.find(krate.id))
+            .set(crates::downloads.eq(downloads))
+            .execute(conn)
+            .unwrap();
+        let version = new_version(conn, krate.id, "1.0.0", user.id);
+        let analyses = vec![("fingerprint".to_string(), json!({ "hashes": hashes }))]
+            .into_iter()
+            .collect();
+        VersionFingerprint::save(conn, krate.id, version.id, &analyses).unwrap();
+        (krate.id, version.id)
+    }
+
+    fn hashes(hashes: &[&str]) -> Vec<String> {
+        hashes.iter().map(|hash| hash.to_string()).collect()
+    }
+
+    #[test]
+    fn similarity_is_the_share_of_common_hashes() {
+        let original = hashes(&["a", "b", "c", "d"]);
+        assert!((similarity(&original, &original) - 1.0).abs() < 1e-6);
+        // A copy with an added file is still flagged
+        let copy = hashes(&["a", "b", "c", "d", "evil"]);
+        assert!((similarity(&copy, &original) - 0.8).abs() < 1e-6);
+        assert!((similarity(&hashes(&["a", "x"]), &original) - 0.2).abs() < 1e-6);
+        assert!((similarity(&[], &[]) - 0.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn copies_of_popular_crates_are_matched() {
+        let conn = pg_connection();
+        let original = hashes(&["a", "b", "c", "d"]);
+        let (_, popular_version_id) = publish(&conn, "popular", 50_000, &original);
+        // Copies of crates nobody uses aren't looked for
+        publish(&conn, "obscure", 10, &hashes(&["x", "y"]));
+
+        let copy = hashes(&["a", "b", "c", "d", "evil"]);
+        let (crate_id, version_id) = publish(&conn, "p0pular", 0, &copy);
+        let found = ContentMatch::detect(&conn, crate_id, version_id, &copy)
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.matched_version_id, popular_version_id);
+        assert!((found.similarity - 0.8).abs() < 1e-6);
+        let (_, name, num) = ContentMatch::by_version(&conn, version_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!((name.as_str(), num.as_str()), ("popular", "1.0.0"));
+
+        let unrelated = hashes(&["a", "x", "y", "z"]);
+        let (crate_id, version_id) = publish(&conn, "unrelated", 0, &unrelated);
+        assert_eq!(
+            ContentMatch::detect(&conn, crate_id, version_id, &unrelated).unwrap(),
+            None
+        );
+        let (crate_id, version_id) = publish(&conn, "obscure-copy", 0, &hashes(&["x", "y"]));
+        assert_eq!(
+            ContentMatch::detect(&conn, crate_id, version_id, &hashes(&["x", "y"])).unwrap(),
+            None
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use chrono::NaiveDateTime;
+use diesel::dsl::{now, IntervalDsl};
+use diesel::prelude::*;
+
+use crate::models::publish_job::INDEXED;
+use crate::schema::{crates, publish_jobs, version_install_checks, version_scan_holds, versions};
+
+/// Versions are only checked for this long after they were published, unless
+/// their last check failed.
+const CHECK_WINDOW_HOURS: i32 = 24;
+
+/// The outcome of the last check that a version can be installed, by fetching
+/// its index entry and crate file the way cargo does. Recorded by the
+/// `verify_installability` job.
+#[derive(Clone, Debug, PartialEq, Eq, Queryable, Identifiable)]
+#[primary_key(version_id)]
+pub struct VersionInstallCheck {
+    pub version_id: i32,
+    pub passed: bool,
+    /// Why the last check failed.
+    pub error: Option<String>,
+    /// How many times the version was checked.
+    pub attempts: i32,
+    pub checked_at: NaiveDateTime,
+}
+
+impl VersionInstallCheck {
+    /// The versions which are due to be checked, as `(version_id, crate,
+    /// version)`: those published between `delay_minutes` and a day ago,
+    /// which were never checked, and those whose last check failed, until
+    /// they pass or are yanked.
+    ///
+    /// Versions held back from the index or whose queued publish hasn't
+    /// finished yet aren't expected to be installable, and are left out.
+    pub fn due(conn: &PgConnection, delay_minutes: i32) -> QueryResult<Vec<(i32, String, String)>> {
+        let held = version_scan_holds::table.select(version_scan_holds::version_id);
+        let unindexed = publish_jobs::table
+            .filter(publish_jobs::state.ne(INDEXED))
+            .select(publish_jobs::version_id);
+        let mut due = versions::table
+            .inner_join(crates::table)
+            .left_join(version_install_checks::table)
+            .filter(version_install_checks::version_id.is_null())
+            .filter(versions::created_at.lt(now - delay_minutes.minutes()))
+            .filter(versions::created_at.gt(now - CHECK_WINDOW_HOURS.hours()))
+            .filter(versions::yanked.eq(false))
+            .filter(versions::id.ne_all(held))
+            .filter(versions::id.ne_all(unindexed))
+            .select((versions::id, crates::name, versions::num))
+            .order(versions::created_at)
+            .load(conn)?;
+
+        let failed = version_install_checks::table
+            .inner_join(versions::table.inner_join(crates::table))
+            .filter(version_install_checks::passed.eq(false))
+            .filter(versions::yanked.eq(false))
+            .select((versions::id, crates::name, versions::num))
+            .order(version_install_checks::checked_at)
+            .load::<(i32, String, String)>(conn)?;
+        due.extend(failed);
+        Ok(due)
+    }
+
+    /// Records the outcome of a check, counting the attempts.
+    pub fn record(
+        conn: &PgConnection,
+        version_id: i32,
+        result: Result<(), String>,
+    ) -> QueryResult<()> {
+        let passed = result.is_ok();
+        let error = result.err();
+        diesel::insert_into(version_install_checks::table)
+            .values((
+                version_install_checks::version_id.eq(version_id),
+                version_install_checks::passed.eq(passed),
+                version_install_checks::error.eq(&error),
+            ))
+            .on_conflict(version_install_checks::version_id)
+            .do_update()
+            .set((
+                version_install_checks::passed.eq(passed),
+                version_install_checks::error.eq(&error),
+                version_install_checks::attempts.eq(version_install_checks::attempts + 1),
+                version_install_checks::checked_at.eq(now),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// The failed checks of versions which aren't yanked, as `(check,
+    /// crate, version)`, oldest first.
+    pub fn failing(conn: &PgConnection) -> QueryResult<Vec<(Self, String, String)>> {
+        version_install_checks::table
+            .inner_join(versions::table.inner_join(crates::table))
+            .filter(version_install_checks::passed.eq(false))
+            .filter(versions::yanked.eq(false))
+            .select((
+                version_install_checks::all_columns,
+                crates::name,
+                versions::num,
+            ))
+            .order(versions::created_at)
+            .load(conn)
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::BTreeMap;
+
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde_json::Value;
+
+use crate::schema::{crates, version_scan_holds, version_scan_results, versions};
+use crate::tarball::ScanFinding;
+
+/// Something the `executables` analyzer found in the crate file of a version,
+/// like an executable or a precompiled library.
+#[derive(Clone, Debug, PartialEq, Eq, Queryable, Insertable)]
+#[table_name = "version_scan_results"]
+pub struct VersionScanResult {
+    pub version_id: i32,
+    pub path: String,
+    pub kind: String,
+    pub detail: String,
+}
+
+impl VersionScanResult {
+    /// Saves the findings in the `executables` report of `analyses`,
+    /// replacing the ones found before. Nothing is changed if the analyzer
+    /// didn't run.
+    ///
+    /// Returns the number of findings.
+    pub fn save(
+        conn: &PgConnection,
+        version_id: i32,
+        analyses: &BTreeMap<String, Value>,
+    ) -> QueryResult<usize> {
+        let findings = match analyses.get("executables") {
+            Some(report) => serde_json::from_value::<Vec<ScanFinding>>(report["findings"].clone())
+                .unwrap_or_default(),
+            None => return Ok(0),
+        };
+        let rows = findings
+            .into_iter()
+            .map(|finding| VersionScanResult {
+                version_id,
+                path: finding.path,
+                kind: finding.kind,
+                detail: finding.detail,
+            })
+            .collect::<Vec<_>>();
+
+        conn.transaction(|| {
+            diesel::delete(
+                version_scan_results::table.filter(version_scan_results::version_id.eq(version_id)),
+            )
+            .execute(conn)?;
+            diesel::insert_into(version_scan_results::table)
+                .values(&rows)
+                .execute(conn)
+        })
+    }
+
+    /// The findings of a version, ordered by path.
+    pub fn by_version(conn: &PgConnection, version_id: i32) -> QueryResult<Vec<Self>> {
+        version_scan_results::table
+            .filter(version_scan_results::version_id.eq(version_id))
+            .order(version_scan_results::path)
+            .load(conn)
+    }
+}
+
+/// A version with findings which is kept out of the index until an admin
+/// reviewed it, see `Config::hold_flagged_versions`.
+///
+/// The hold keeps the index entry of the version, and the queued publish
+/// whose crate file still has to be uploaded, if it was published with
+/// `?queued=true`.
+#[derive(Clone, Debug, PartialEq, Queryable)]
+pub struct ScanHold {
+    pub version_id: i32,
+    pub index_entry: Value,
+    pub publish_job_id: Option<i32>,
+    pub created_at: NaiveDateTime,
+}
+
+impl ScanHold {
+    pub fn create(
+        conn: &PgConnection,
+        version_id: i32,
+        index_entry: Value,
+        publish_job_id: Option<i32>,
+    ) -> QueryResult<()> {
+        diesel::insert_into(version_scan_holds::table)
+            .values((
+                version_scan_holds::version_id.eq(version_id),
+                version_scan_holds::index_entry.eq(index_entry),
+                version_scan_holds::publish_job_id.eq(publish_job_id),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// The review queue, as `(hold, crate, version)`, oldest first.
+    pub fn pending(conn: &PgConnection) -> QueryResult<Vec<(Self, String, String)>> {
+        version_scan_holds::table
+            .inner_join(versions::table.inner_join(crates::table))
+            .select((version_scan_holds::all_columns, crates::name, versions::num))
+            .order(version_scan_holds::created_at)
+            .load(conn)
+    }
+
+    /// Ends the hold of a version, returning it so that the version can be
+    /// added to the index. Returns `None` if the version isn't held.
+    pub fn release(conn: &PgConnection, version_id: i32) -> QueryResult<Option<Self>> {
+        diesel::delete(version_scan_holds::table.find(version_id))
+            .get_result(conn)
+            .optional()
+    }
+}
@@ -0,0 +1,136 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Jsonb, Text};
+use openssl::error::ErrorStack;
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use serde_json::Value;
+
+use crate::models::{DeletedCrate, ServiceConsumer};
+use crate::schema::{webhook_deliveries, webhooks};
+
+/// An endpoint of a service consumer, like docs.rs or a mirror, which is sent
+/// a `POST` request whenever a version is yanked, unyanked or deleted.
+#[derive(Clone, Debug, PartialEq, Eq, Identifiable, Queryable, Associations)]
+#[belongs_to(ServiceConsumer)]
+pub struct Webhook {
+    pub id: i32,
+    pub service_consumer_id: i32,
+    pub url: String,
+    /// The key the payloads sent to the endpoint are signed with.
+    pub secret: String,
+    pub created_at: NaiveDateTime,
+}
+
+/// A notification sent, or still to be sent, to a webhook.
+#[derive(Clone, Debug, PartialEq, Identifiable, Queryable, Associations)]
+#[belongs_to(Webhook)]
+#[table_name = "webhook_deliveries"]
+pub struct WebhookDelivery {
+    pub id: i32,
+    pub webhook_id: i32,
+    /// One of `yank`, `unyank` or `delete`.
+    pub event: String,
+    pub payload: Value,
+    pub attempts: i32,
+    /// The response status of the last failed attempt, if there was a
+    /// response.
+    pub last_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub next_attempt_at: NaiveDateTime,
+    pub delivered_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+impl Webhook {
+    /// Registers an endpoint of a service consumer, generating its secret.
+    pub fn create(conn: &PgConnection, consumer: &ServiceConsumer, url: &str) -> QueryResult<Self> {
+        diesel::insert_into(webhooks::table)
+            .values((
+                webhooks::service_consumer_id.eq(consumer.id),
+                webhooks::url.eq(url),
+            ))
+            .get_result(conn)
+    }
+
+    /// The value of the `X-Crates-Io-Signature` header of a request with the
+    /// given body, which is the hex encoded HMAC-SHA256 of the body keyed
+    /// with the secret of the webhook.
+    pub fn signature(&self, body: &[u8]) -> Result<String, ErrorStack> {
+        let key = PKey::hmac(self.secret.as_bytes())?;
+        let mut signer = Signer::new(MessageDigest::sha256(), &key)?;
+        signer.update(body)?;
+        Ok(format!("sha256={}", hex::encode(signer.sign_to_vec()?)))
+    }
+}
+
+/// Queues a notification of a version being yanked or unyanked for every
+/// webhook, returning the number of deliveries. Must be called once the
+/// index was updated.
+pub fn notify_yank(
+    conn: &PgConnection,
+    krate: &str,
+    version: &str,
+    yanked: bool,
+) -> QueryResult<usize> {
+    let event = if yanked { "yank" } else { "unyank" };
+    let payload = json!({
+        "event": event,
+        "crate": krate,
+        "version": version,
+        "time": now(),
+    });
+    notify(conn, event, &payload)
+}
+
+/// Queues a notification of a crate, or of one of its versions, being
+/// deleted for every webhook, returning the number of deliveries.
+pub fn notify_deletion(conn: &PgConnection, deleted: &DeletedCrate) -> QueryResult<usize> {
+    let payload = json!({
+        "event": "delete",
+        "crate": deleted.name,
+        "version": deleted.version,
+        "reason": deleted.reason,
+        "time": now(),
+    });
+    notify(conn, "delete", &payload)
+}
+
+fn notify(conn: &PgConnection, event: &str, payload: &Value) -> QueryResult<usize> {
+    diesel::sql_query(
+        "INSERT INTO webhook_deliveries (webhook_id, event, payload) \
+         SELECT webhooks.id, $1, $2 FROM webhooks \
+         INNER JOIN service_consumers ON service_consumers.id = webhooks.service_consumer_id \
+         WHERE NOT service_consumers.revoked",
+    )
+    .bind::<Text, _>(event)
+    .bind::<Jsonb, _>(payload)
+    .execute(conn)
+}
+
+fn now() -> String {
+    Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payloads_are_signed_with_the_secret() {
+        let webhook = Webhook {
+            id: 1,
+            service_consumer_id: 1,
+            url: "https://docs.rs/webhook".into(),
+            secret: "key".into(),
+            created_at: Utc::now().naive_utc(),
+        };
+        assert_eq!(
+            webhook
+                .signature(b"The quick brown fox jumps over the lazy dog")
+                .unwrap(),
+            "sha256=f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+    }
+}
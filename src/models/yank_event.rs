@@ -0,0 +1,64 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use crate::models::{ApiToken, User, Version};
+use crate::schema::{users, yank_events};
+use crate::views::EncodableYankEvent;
+
+/// A version changing from unyanked to yanked or vice versa.
+#[derive(Debug, Clone, Copy, Queryable, Identifiable, Associations)]
+#[belongs_to(Version)]
+#[belongs_to(User, foreign_key = "user_id")]
+#[belongs_to(ApiToken, foreign_key = "api_token_id")]
+pub struct YankEvent {
+    pub id: i32,
+    pub version_id: i32,
+    pub user_id: i32,
+    pub api_token_id: Option<i32>,
+    pub yanked: bool,
+    pub created_at: NaiveDateTime,
+}
+
+impl YankEvent {
+    pub fn record(
+        conn: &PgConnection,
+        version_id: i32,
+        user_id: i32,
+        api_token_id: Option<i32>,
+        yanked: bool,
+    ) -> QueryResult<Self> {
+        diesel::insert_into(yank_events::table)
+            .values((
+                yank_events::version_id.eq(version_id),
+                yank_events::user_id.eq(user_id),
+                yank_events::api_token_id.eq(api_token_id),
+                yank_events::yanked.eq(yanked),
+            ))
+            .get_result(conn)
+    }
+
+    /// The most recent transition of the given version, if it ever changed.
+    pub fn latest(conn: &PgConnection, version_id: i32) -> QueryResult<Option<Self>> {
+        yank_events::table
+            .filter(yank_events::version_id.eq(version_id))
+            .order((yank_events::created_at.desc(), yank_events::id.desc()))
+            .first(conn)
+            .optional()
+    }
+
+    /// All transitions of the given version, oldest first.
+    pub fn by_version(conn: &PgConnection, version: &Version) -> QueryResult<Vec<(Self, User)>> {
+        Self::belonging_to(version)
+            .inner_join(users::table)
+            .order((yank_events::created_at, yank_events::id))
+            .load(conn)
+    }
+
+    pub fn encodable(self, user: User) -> EncodableYankEvent {
+        EncodableYankEvent {
+            yanked: self.yanked,
+            user: user.encodable_public(),
+            time: self.created_at,
+        }
+    }
+}
@@ -0,0 +1,339 @@
+//! Open Graph preview images for crate pages.
+//!
+//! The preview is generated as an SVG document and then converted to PNG by
+//! an external renderer, since most social platforms don't accept SVG images.
+//! The renderer is configured with the `OG_IMAGE_RENDERER` environment
+//! variable and must read SVG from stdin and write PNG to stdout, e.g.
+//! `rsvg-convert`.
+//!
+//! Images are rendered again when a crate is published, yanked or its owners
+//! or links change, and daily for crates which are downloaded often enough
+//! that their image would show an outdated download count, see
+//! `refresh_og_images`.
+
+use diesel::prelude::*;
+use diesel::sql_types::{Integer, Text};
+use htmlescape::encode_minimal;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use swirl::{EnqueueError, PerformError};
+
+use crate::background_jobs::{EnqueueVersioned, Environment};
+use crate::schema::{crates, default_versions, versions};
+use crate::Config;
+
+const WIDTH: u32 = 1200;
+const HEIGHT: u32 = 630;
+
+/// The maximum number of characters per line of the description.
+const DESCRIPTION_LINE_LENGTH: usize = 52;
+/// The maximum number of description lines that are rendered.
+const DESCRIPTION_LINES: usize = 3;
+
+/// Crates downloaded at least this often on a day get their image rendered
+/// again by `refresh_og_images`. The images of the others are only slightly
+/// behind until the next update of the crate.
+const REFRESH_MIN_DOWNLOADS: i32 = 100;
+
+/// The data shown on the preview image of a crate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OgImageData {
+    pub name: String,
+    pub description: Option<String>,
+    pub downloads: i32,
+    pub version: String,
+}
+
+impl OgImageData {
+    fn load(crate_name: &str, conn: &PgConnection) -> QueryResult<Self> {
+        let (name, description, downloads, version) = crates::table
+            .inner_join(default_versions::table.inner_join(versions::table))
+            .filter(crates::name.eq(crate_name))
+            .select((
+                crates::name,
+                crates::description,
+                crates::downloads,
+                versions::num,
+            ))
+            .first(conn)?;
+
+        Ok(Self {
+            name,
+            description,
+            downloads,
+            version,
+        })
+    }
+
+    /// Renders the preview as an SVG document.
+    pub fn to_svg(&self) -> String {
+        let description = self
+            .description
+            .as_ref()
+            .map(|description| wrap(description, DESCRIPTION_LINE_LENGTH, DESCRIPTION_LINES))
+            .unwrap_or_default()
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                format!(
+                    r#"<tspan x="80" dy="{}">{}</tspan>"#,
+                    if i == 0 { 0 } else { 52 },
+                    encode_minimal(line)
+                )
+            })
+            .collect::<String>();
+
+        format!(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">
+<rect width="{width}" height="{height}" fill="#f9f7ec"/>
+<rect y="{bar_y}" width="{width}" height="16" fill="#3b6837"/>
+<text x="80" y="170" font-family="Fira Sans, sans-serif" font-size="88" font-weight="bold" fill="#383838">{name}</text>
+<text x="80" y="270" font-family="Fira Sans, sans-serif" font-size="40" fill="#525252">{description}</text>
+<text x="80" y="540" font-family="Fira Sans, sans-serif" font-size="40" fill="#383838">v{version}</text>
+<text x="1120" y="540" font-family="Fira Sans, sans-serif" font-size="40" fill="#383838" text-anchor="end">{downloads} downloads</text>
+</svg>
+"##,
+            width = WIDTH,
+            height = HEIGHT,
+            bar_y = HEIGHT - 16,
+            name = encode_minimal(&self.name),
+            description = description,
+            version = encode_minimal(&self.version),
+            downloads = format_number(self.downloads),
+        )
+    }
+}
+
+/// Splits `text` into at most `max_lines` lines of at most `line_length`
+/// characters, breaking at whitespace where possible. If the text doesn't
+/// fit, the last line is truncated with an ellipsis.
+fn wrap(text: &str, line_length: usize, max_lines: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut truncated = false;
+
+    for word in text.split_whitespace() {
+        let word = if word.chars().count() > line_length {
+            word.chars().take(line_length - 1).collect::<String>() + "…"
+        } else {
+            word.to_string()
+        };
+        let needed = if current.is_empty() {
+            word.chars().count()
+        } else {
+            current.chars().count() + 1 + word.chars().count()
+        };
+
+        if needed > line_length {
+            if lines.len() + 1 == max_lines {
+                truncated = true;
+                break;
+            }
+            lines.push(std::mem::replace(&mut current, String::new()));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(&word);
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+
+    if truncated {
+        if let Some(last) = lines.last_mut() {
+            let keep = last.chars().count().min(line_length - 1);
+            *last = last.chars().take(keep).collect::<String>() + "…";
+        }
+    }
+    lines
+}
+
+/// Formats a download count with `,` as the thousands separator.
+fn format_number(number: i32) -> String {
+    let digits = number.to_string();
+    let mut result = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            result.push(',');
+        }
+        result.push(digit);
+    }
+    result
+}
+
+/// Converts an SVG document to PNG using the given renderer command.
+fn svg_to_png(renderer: &str, svg: &str) -> Result<Vec<u8>, PerformError> {
+    let mut args = renderer.split_whitespace();
+    let program = args.next().ok_or("`OG_IMAGE_RENDERER` is empty")?;
+
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .ok_or("failed to open renderer stdin")?
+        .write_all(svg.as_bytes())?;
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("`{}` failed ({}): {}", renderer, output.status, stderr).into());
+    }
+    Ok(output.stdout)
+}
+
+/// Renders the Open Graph preview image of a crate and uploads it.
+///
+/// The image is stored at a stable location per crate (see
+/// `Uploader::og_image_location`), so re-rendering it replaces the previous
+/// version.
+#[swirl::background_job]
+pub fn render_og_image(env: &Environment, crate_name: String) -> Result<(), PerformError> {
+    let renderer = env
+        .og_image_renderer()
+        .ok_or("`OG_IMAGE_RENDERER` must be set to render preview images")?;
+    let _heartbeat = env.heartbeat("render_og_image")?;
+
+    let data = {
+        let conn = env.connection()?;
+        OgImageData::load(&crate_name, &conn)?
+    };
+
+    let png = svg_to_png(renderer, &data.to_svg())?;
+    env.uploader
+        .upload_og_image(env.http_client(), &data.name, png)?;
+    Ok(())
+}
+
+/// Enqueues a job rendering the image of a crate again, if images are
+/// rendered at all.
+pub fn enqueue_render(
+    conn: &PgConnection,
+    config: &Config,
+    crate_name: &str,
+) -> Result<(), EnqueueError> {
+    if config.og_image_renderer.is_some() {
+        render_og_image(crate_name.into()).enqueue_versioned(conn)?;
+    }
+    Ok(())
+}
+
+/// Renders the images of the crates downloaded at least
+/// `REFRESH_MIN_DOWNLOADS` times yesterday again, to update their download
+/// counts.
+///
+/// This is meant to be run daily via `enqueue-job refresh_og_images`.
+#[swirl::background_job]
+pub fn refresh_og_images(env: &Environment) -> Result<(), PerformError> {
+    if env.og_image_renderer().is_none() {
+        return Ok(());
+    }
+    let _heartbeat = env.heartbeat("refresh_og_images")?;
+    let conn = env.connection()?;
+    let names = downloaded_yesterday(&conn, REFRESH_MIN_DOWNLOADS)?;
+    for name in &names {
+        render_og_image(name.clone()).enqueue_versioned(&conn)?;
+    }
+    println!("og_images.refreshed_crates={}", names.len());
+    Ok(())
+}
+
+#[derive(QueryableByName)]
+struct CrateName {
+    #[sql_type = "Text"]
+    name: String,
+}
+
+fn downloaded_yesterday(conn: &PgConnection, min_downloads: i32) -> QueryResult<Vec<String>> {
+    let rows = diesel::sql_query(
+        "SELECT crates.name FROM version_downloads \
+         INNER JOIN versions ON versions.id = version_downloads.version_id \
+         INNER JOIN crates ON crates.id = versions.crate_id \
+         WHERE version_downloads.date = CURRENT_DATE - 1 \
+         GROUP BY crates.name HAVING SUM(version_downloads.downloads) >= $1 \
+         ORDER BY crates.name",
+    )
+    .bind::<Integer, _>(min_downloads)
+    .load::<CrateName>(conn)?;
+    Ok(rows.into_iter().map(|row| row.name).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::version_downloads;
+    use crate::test_util::{new_crate, new_user, new_version, pg_connection};
+    use chrono::{Duration, Utc};
+
+    #[test]
+    fn wrap_breaks_at_whitespace() {
+        assert_eq!(wrap("a fast parser", 8, 3), vec!["a fast", "parser"]);
+        assert_eq!(wrap("  spaced   out  ", 20, 3), vec!["spaced out"]);
+        assert!(wrap("", 8, 3).is_empty());
+    }
+
+    #[test]
+    fn wrap_truncates_long_text() {
+        assert_eq!(
+            wrap("one two three four five six", 9, 2),
+            vec!["one two", "three…"]
+        );
+        assert_eq!(wrap("abcdefghijkl", 5, 2), vec!["abcd…"]);
+    }
+
+    #[test]
+    fn format_number_adds_separators() {
+        assert_eq!(format_number(0), "0");
+        assert_eq!(format_number(999), "999");
+        assert_eq!(format_number(1000), "1,000");
+        assert_eq!(format_number(1_234_567), "1,234,567");
+    }
+
+    #[test]
+    fn svg_escapes_user_content() {
+        let data = OgImageData {
+            name: "foo".into(),
+            description: Some("<script>alert(1)</script> & more".into()),
+            downloads: 1234,
+            version: "1.0.0".into(),
+        };
+        let svg = data.to_svg();
+        assert!(!svg.contains("<script>"));
+        assert!(svg.contains("&lt;script&gt;alert(1)&lt;/script&gt; &amp; more"));
+        assert!(svg.contains(">1,234 downloads<"));
+        assert!(svg.contains(">v1.0.0<"));
+    }
+
+    #[test]
+    fn often_downloaded_crates_are_refreshed() {
+        let conn = pg_connection();
+        let user = new_user(&conn, 2, "login");
+        let today = Utc::today().naive_utc();
+        for &(name, yesterday, today_downloads) in &[("busy", 150, 0), ("quiet", 50, 500)] {
+            let krate = new_crate(&conn, name, user.id);
+            let version = new_version(&conn, krate.id, "1.0.0", user.id);
+            diesel::insert_into(version_downloads::table)
+                .values(&vec![
+                    (
+                        version_downloads::version_id.eq(version.id),
+                        version_downloads::downloads.eq(yesterday),
+                        version_downloads::date.eq(today - Duration::days(1)),
+                    ),
+                    (
+                        version_downloads::version_id.eq(version.id),
+                        version_downloads::downloads.eq(today_downloads),
+                        version_downloads::date.eq(today),
+                    ),
+                ])
+                .execute(&conn)
+                .unwrap();
+        }
+
+        assert_eq!(downloaded_yesterday(&conn, 100).unwrap(), vec!["busy"]);
+    }
+}
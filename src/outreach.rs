@@ -0,0 +1,371 @@
+//! Emails sent by admins to the owners of a selection of crates, e.g. to
+//! warn them about a dependency which is about to break.
+//!
+//! Crates are selected with a filter like
+//! `depends_on = 'time' and downloads >= 1000`, or a saved segment which
+//! names such a filter. Every owner gets a single email listing all of their
+//! crates which matched, and every email sent is recorded in
+//! `outreach_emails`, so that running a campaign again only reaches owners
+//! who weren't emailed yet.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::prelude::*;
+
+use crate::models::OwnerKind;
+use crate::schema::{
+    categories, crate_owners, crates, crates_categories, crates_keywords, default_versions,
+    dependencies, emails, keywords, outreach_emails, users,
+};
+
+/// A field of a crate which can be filtered on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Field {
+    Name,
+    /// The crates whose default version depends on the given crate.
+    DependsOn,
+    Keyword,
+    Category,
+    Downloads,
+    UpdatedAt,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Op {
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Condition {
+    pub field: Field,
+    pub op: Op,
+    pub value: String,
+}
+
+/// A filter selecting crates, made up of conditions which all have to match.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CrateFilter {
+    pub conditions: Vec<Condition>,
+}
+
+impl CrateFilter {
+    /// Parses conditions like `name = 'foo'` joined by `and`. `name`,
+    /// `depends_on`, `keyword` and `category` can only be compared with `=`,
+    /// `downloads` and `updated_at` with `=`, `<`, `<=`, `>` and `>=`.
+    /// Dates are given as `YYYY-MM-DD`.
+    pub fn parse(filter: &str) -> Result<Self, String> {
+        let conditions = filter
+            .split(" and ")
+            .map(parse_condition)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { conditions })
+    }
+
+    /// The IDs of the crates matching all conditions.
+    pub fn matching_crates(&self, conn: &PgConnection) -> QueryResult<Vec<i32>> {
+        let mut query = crates::table.select(crates::id).into_boxed();
+        for Condition { field, op, value } in &self.conditions {
+            query = match field {
+                Field::Name => query.filter(crates::name.eq(value)),
+                Field::DependsOn => {
+                    let dependents = default_versions::table
+                        .inner_join(
+                            dependencies::table
+                                .on(dependencies::version_id.eq(default_versions::version_id)),
+                        )
+                        .inner_join(crates::table.on(crates::id.eq(dependencies::crate_id)))
+                        .filter(crates::name.eq(value))
+                        .select(default_versions::crate_id)
+                        .load::<i32>(conn)?;
+                    query.filter(crates::id.eq_any(dependents))
+                }
+                Field::Keyword => query.filter(
+                    crates::id.eq_any(
+                        crates_keywords::table
+                            .inner_join(keywords::table)
+                            .filter(keywords::keyword.eq(value))
+                            .select(crates_keywords::crate_id),
+                    ),
+                ),
+                Field::Category => query.filter(
+                    crates::id.eq_any(
+                        crates_categories::table
+                            .inner_join(categories::table)
+                            .filter(categories::slug.eq(value))
+                            .select(crates_categories::crate_id),
+                    ),
+                ),
+                Field::Downloads => {
+                    // Validated by `parse_condition`
+                    let downloads = value.parse::<i32>().unwrap();
+                    match op {
+                        Op::Eq => query.filter(crates::downloads.eq(downloads)),
+                        Op::Lt => query.filter(crates::downloads.lt(downloads)),
+                        Op::Le => query.filter(crates::downloads.le(downloads)),
+                        Op::Gt => query.filter(crates::downloads.gt(downloads)),
+                        Op::Ge => query.filter(crates::downloads.ge(downloads)),
+                    }
+                }
+                Field::UpdatedAt => {
+                    let start = parse_date(value).unwrap();
+                    let end = start + chrono::Duration::days(1);
+                    match op {
+                        Op::Eq => query
+                            .filter(crates::updated_at.ge(start))
+                            .filter(crates::updated_at.lt(end)),
+                        Op::Lt => query.filter(crates::updated_at.lt(start)),
+                        Op::Le => query.filter(crates::updated_at.lt(end)),
+                        Op::Gt => query.filter(crates::updated_at.ge(end)),
+                        Op::Ge => query.filter(crates::updated_at.ge(start)),
+                    }
+                }
+            };
+        }
+        query.order(crates::name).load(conn)
+    }
+}
+
+fn parse_condition(condition: &str) -> Result<Condition, String> {
+    let condition = condition.trim();
+    let field_end = condition
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(condition.len());
+    let (field, rest) = condition.split_at(field_end);
+    let field = match field {
+        "name" => Field::Name,
+        "depends_on" => Field::DependsOn,
+        "keyword" => Field::Keyword,
+        "category" => Field::Category,
+        "downloads" => Field::Downloads,
+        "updated_at" => Field::UpdatedAt,
+        _ => return Err(format!("unknown field in `{}`", condition)),
+    };
+
+    let rest = rest.trim_start();
+    let (op, value) = [
+        ("<=", Op::Le),
+        (">=", Op::Ge),
+        ("=", Op::Eq),
+        ("<", Op::Lt),
+        (">", Op::Gt),
+    ]
+    .iter()
+    .find(|(token, _)| rest.starts_with(*token))
+    .map(|(token, op)| (*op, rest[token.len()..].trim()))
+    .ok_or_else(|| format!("missing comparison in `{}`", condition))?;
+
+    let value = if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+        &value[1..value.len() - 1]
+    } else {
+        value
+    };
+    if value.is_empty() {
+        return Err(format!("missing value in `{}`", condition));
+    }
+
+    match field {
+        Field::Downloads => {
+            value
+                .parse::<i32>()
+                .map_err(|_| format!("`{}` is not a number", value))?;
+        }
+        Field::UpdatedAt => {
+            parse_date(value)?;
+        }
+        _ if op != Op::Eq => {
+            return Err(format!("`{}` can only be compared with `=`", condition));
+        }
+        _ => {}
+    }
+
+    Ok(Condition {
+        field,
+        op,
+        value: value.to_string(),
+    })
+}
+
+fn parse_date(value: &str) -> Result<NaiveDateTime, String> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(|date| date.and_hms(0, 0, 0))
+        .map_err(|_| format!("`{}` is not a date like 2020-01-31", value))
+}
+
+/// An owner of some of the selected crates.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recipient {
+    pub user_id: i32,
+    pub login: String,
+    pub email: String,
+    pub crates: Vec<String>,
+}
+
+impl fmt::Display for Recipient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} <{}>: {}",
+            self.login,
+            self.email,
+            self.crates.join(", ")
+        )
+    }
+}
+
+/// The owners of the given crates who have a verified email address, each
+/// with the crates they own. Teams aren't emailed, only their members who
+/// own crates themselves.
+pub fn recipients(conn: &PgConnection, crate_ids: &[i32]) -> QueryResult<Vec<Recipient>> {
+    let owners = crate_owners::table
+        .inner_join(crates::table)
+        .inner_join(users::table.on(users::id.eq(crate_owners::owner_id)))
+        .inner_join(emails::table.on(emails::user_id.eq(users::id)))
+        .filter(crate_owners::crate_id.eq_any(crate_ids))
+        .filter(crate_owners::deleted.eq(false))
+        .filter(crate_owners::owner_kind.eq(OwnerKind::User as i32))
+        .filter(emails::verified.eq(true))
+        .order((users::id, crates::name))
+        .select((users::id, users::gh_login, emails::email, crates::name))
+        .load::<(i32, String, String, String)>(conn)?;
+
+    let mut recipients = BTreeMap::new();
+    for (user_id, login, email, krate) in owners {
+        recipients
+            .entry(user_id)
+            .or_insert_with(|| Recipient {
+                user_id,
+                login,
+                email,
+                crates: vec![],
+            })
+            .crates
+            .push(krate);
+    }
+    Ok(recipients.into_iter().map(|(_, r)| r).collect())
+}
+
+/// An email template. The first line is `Subject: ...`, followed by an
+/// empty line and the body. `{login}` is replaced by the GitHub login of
+/// the recipient and `{crates}` by a list of their crates.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template {
+    pub subject: String,
+    pub body: String,
+}
+
+impl Template {
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let mut parts = template.splitn(2, "\n\n");
+        let header = parts.next().unwrap_or_default();
+        let body = parts.next().unwrap_or_default();
+        let subject = match header.trim() {
+            header if header.starts_with("Subject:") => header["Subject:".len()..].trim(),
+            _ => return Err("templates must start with a `Subject:` line".into()),
+        };
+        if subject.is_empty() || body.trim().is_empty() {
+            return Err("templates need a subject and a body".into());
+        }
+        Ok(Self {
+            subject: subject.into(),
+            body: body.into(),
+        })
+    }
+
+    pub fn render(&self, recipient: &Recipient) -> String {
+        let crates = recipient
+            .crates
+            .iter()
+            .map(|krate| format!("- {}", krate))
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.body
+            .replace("{login}", &recipient.login)
+            .replace("{crates}", &crates)
+    }
+}
+
+/// An email sent as part of a campaign.
+#[derive(Clone, Debug, PartialEq, Identifiable, Queryable)]
+pub struct OutreachEmail {
+    pub id: i32,
+    pub campaign: String,
+    pub user_id: i32,
+    pub email: String,
+    pub crates: Vec<String>,
+    pub sent_at: NaiveDateTime,
+}
+
+impl OutreachEmail {
+    /// The users who were already emailed as part of the campaign.
+    pub fn already_sent(conn: &PgConnection, campaign: &str) -> QueryResult<Vec<i32>> {
+        outreach_emails::table
+            .filter(outreach_emails::campaign.eq(campaign))
+            .select(outreach_emails::user_id)
+            .load(conn)
+    }
+
+    pub fn record(conn: &PgConnection, campaign: &str, recipient: &Recipient) -> QueryResult<()> {
+        diesel::insert_into(outreach_emails::table)
+            .values((
+                outreach_emails::campaign.eq(campaign),
+                outreach_emails::user_id.eq(recipient.user_id),
+                outreach_emails::email.eq(&recipient.email),
+                outreach_emails::crates.eq(&recipient.crates),
+            ))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_are_parsed() {
+        let filter = CrateFilter::parse("depends_on = 'time' and downloads >= 1000").unwrap();
+        assert_eq!(
+            filter.conditions,
+            vec![
+                Condition {
+                    field: Field::DependsOn,
+                    op: Op::Eq,
+                    value: "time".into(),
+                },
+                Condition {
+                    field: Field::Downloads,
+                    op: Op::Ge,
+                    value: "1000".into(),
+                },
+            ]
+        );
+        assert!(CrateFilter::parse("updated_at<2018-01-01").is_ok());
+        assert!(CrateFilter::parse("owner = 'foo'").is_err());
+        assert!(CrateFilter::parse("name > 'foo'").is_err());
+        assert!(CrateFilter::parse("downloads > many").is_err());
+        assert!(CrateFilter::parse("updated_at < yesterday").is_err());
+        assert!(CrateFilter::parse("keyword =").is_err());
+    }
+
+    #[test]
+    fn templates_are_rendered() {
+        let template =
+            Template::parse("Subject: Your crates\n\nHi {login},\n\n{crates}\n").unwrap();
+        assert_eq!(template.subject, "Your crates");
+        let recipient = Recipient {
+            user_id: 1,
+            login: "foo".into(),
+            email: "foo@example.com".into(),
+            crates: vec!["a".into(), "b".into()],
+        };
+        assert_eq!(template.render(&recipient), "Hi foo,\n\n- a\n- b\n");
+        assert!(Template::parse("Hi {login}").is_err());
+    }
+}
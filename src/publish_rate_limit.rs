@@ -1,11 +1,75 @@
 use chrono::{NaiveDateTime, Utc};
 use diesel::data_types::PgInterval;
 use diesel::prelude::*;
+use diesel::r2d2;
+use std::sync::Arc;
 use std::time::Duration;
 
+use crate::env;
 use crate::schema::{publish_limit_buckets, publish_rate_overrides};
 use crate::util::errors::{AppResult, TooManyRequests};
 
+/// How long to wait for a Redis connection before falling back to the
+/// database.
+const REDIS_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Takes a token from the bucket of a user, see `PublishRateLimit::take_token`
+/// for the semantics, which this script mirrors with times in milliseconds.
+///
+/// A missing bucket is the same as a full one, so buckets expire once they
+/// would have been refilled completely.
+const TAKE_TOKEN_SCRIPT: &str = r#"
+local now = tonumber(ARGV[1])
+local rate = tonumber(ARGV[2])
+local burst = tonumber(ARGV[3])
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'last_refill')
+local tokens = burst
+local last_refill = now
+if bucket[1] then
+    local tokens_to_add = math.floor((now - tonumber(bucket[2])) / rate)
+    tokens = math.min(burst, math.max(0, tonumber(bucket[1]) - 1) + tokens_to_add)
+    last_refill = tonumber(bucket[2]) + rate * tokens_to_add
+end
+redis.call('HMSET', KEYS[1], 'tokens', tokens, 'last_refill', last_refill)
+redis.call('PEXPIRE', KEYS[1], rate * (burst + 1))
+return {tokens, last_refill}
+"#;
+
+/// Limits how many new crates a user can publish.
+pub trait RateLimiter: Send + Sync {
+    fn check_rate_limit(&self, uploader: i32, conn: &PgConnection) -> AppResult<()>;
+}
+
+/// Where the token buckets of the publish rate limit are kept.
+#[derive(Clone, Debug)]
+pub enum RateLimiterConfig {
+    Database,
+    Redis { url: String },
+}
+
+impl RateLimiterConfig {
+    /// Reads the rate limiter backend from the environment.
+    ///
+    /// - `PUBLISH_RATE_LIMIT_BACKEND`: Either `database` (the default) or `redis`.
+    /// - `REDIS_URL`: The URL of the Redis instance.
+    pub fn from_environment() -> Self {
+        match dotenv::var("PUBLISH_RATE_LIMIT_BACKEND").ok().as_deref() {
+            None | Some("database") => RateLimiterConfig::Database,
+            Some("redis") => RateLimiterConfig::Redis {
+                url: env("REDIS_URL"),
+            },
+            Some(other) => panic!("Unknown PUBLISH_RATE_LIMIT_BACKEND `{}`", other),
+        }
+    }
+
+    pub fn build(&self, limit: PublishRateLimit) -> Arc<dyn RateLimiter> {
+        match self {
+            RateLimiterConfig::Database => Arc::new(limit),
+            RateLimiterConfig::Redis { url } => Arc::new(RedisRateLimit::new(limit, url)),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct PublishRateLimit {
     pub rate: Duration,
@@ -30,9 +94,15 @@ struct Bucket {
     last_refill: NaiveDateTime,
 }
 
-impl PublishRateLimit {
-    pub fn check_rate_limit(&self, uploader: i32, conn: &PgConnection) -> AppResult<()> {
+impl RateLimiter for PublishRateLimit {
+    fn check_rate_limit(&self, uploader: i32, conn: &PgConnection) -> AppResult<()> {
         let bucket = self.take_token(uploader, Utc::now().naive_utc(), conn)?;
+        self.check_bucket(&bucket)
+    }
+}
+
+impl PublishRateLimit {
+    fn check_bucket(&self, bucket: &Bucket) -> AppResult<()> {
         if bucket.tokens >= 1 {
             Ok(())
         } else {
@@ -42,6 +112,16 @@ impl PublishRateLimit {
         }
     }
 
+    /// The burst of the given user, which may be raised by an override.
+    fn burst(&self, uploader: i32, conn: &PgConnection) -> QueryResult<i32> {
+        Ok(publish_rate_overrides::table
+            .find(uploader)
+            .select(publish_rate_overrides::burst)
+            .first::<i32>(conn)
+            .optional()?
+            .unwrap_or(self.burst))
+    }
+
     /// Refill a user's bucket as needed, take a token from it,
     /// and returns the result.
     ///
@@ -68,12 +148,7 @@ impl PublishRateLimit {
         sql_function!(fn greatest<T>(x: T, y: T) -> T);
         sql_function!(fn least<T>(x: T, y: T) -> T);
 
-        let burst = publish_rate_overrides::table
-            .find(uploader)
-            .select(publish_rate_overrides::burst)
-            .first::<i32>(conn)
-            .optional()?
-            .unwrap_or(self.burst);
+        let burst = self.burst(uploader, conn)?;
 
         // Interval division is poorly defined in general (what is 1 month / 30 days?)
         // However, for the intervals we're dealing with, it is always well
@@ -101,6 +176,80 @@ impl PublishRateLimit {
     }
 }
 
+/// Keeps the token buckets in Redis, so that checking the rate limit doesn't
+/// write to the database. Overrides of the burst are still read from the
+/// database.
+///
+/// If Redis is unavailable, the buckets in the database are used instead.
+/// Users may get a few more tokens than they should while switching between
+/// the two, which is fine for an outage.
+#[allow(missing_debug_implementations)]
+pub struct RedisRateLimit {
+    limit: PublishRateLimit,
+    pool: r2d2::Pool<redis::Client>,
+    script: redis::Script,
+}
+
+impl RedisRateLimit {
+    pub fn new(limit: PublishRateLimit, url: &str) -> Self {
+        let client = redis::Client::open(url).expect("Invalid REDIS_URL");
+        // Don't connect eagerly, Redis may be down when we start
+        let pool = r2d2::Pool::builder()
+            .connection_timeout(REDIS_TIMEOUT)
+            .build_unchecked(client);
+        Self {
+            limit,
+            pool,
+            script: redis::Script::new(TAKE_TOKEN_SCRIPT),
+        }
+    }
+
+    fn take_token(
+        &self,
+        redis: &mut redis::Connection,
+        uploader: i32,
+        burst: i32,
+        now: NaiveDateTime,
+    ) -> redis::RedisResult<Bucket> {
+        let (tokens, last_refill): (i32, i64) = self
+            .script
+            .key(format!("publish_limit_buckets:{}", uploader))
+            .arg(now.timestamp_millis())
+            .arg(self.limit.rate.as_millis() as i64)
+            .arg(burst)
+            .invoke(redis)?;
+        Ok(Bucket {
+            user_id: uploader,
+            tokens,
+            last_refill: NaiveDateTime::from_timestamp(
+                last_refill.div_euclid(1000),
+                last_refill.rem_euclid(1000) as u32 * 1_000_000,
+            ),
+        })
+    }
+}
+
+impl RateLimiter for RedisRateLimit {
+    fn check_rate_limit(&self, uploader: i32, conn: &PgConnection) -> AppResult<()> {
+        let burst = self.limit.burst(uploader, conn)?;
+        let bucket = self
+            .pool
+            .get()
+            .map_err(|e| e.to_string())
+            .and_then(|mut redis| {
+                self.take_token(&mut redis, uploader, burst, Utc::now().naive_utc())
+                    .map_err(|e| e.to_string())
+            });
+        match bucket {
+            Ok(bucket) => self.limit.check_bucket(&bucket),
+            Err(e) => {
+                eprintln!("Redis rate limiter unavailable, using the database: {}", e);
+                self.limit.check_rate_limit(uploader, conn)
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -318,6 +467,59 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn redis_buckets_match_database_buckets() -> QueryResult<()> {
+        let redis_url = match dotenv::var("TEST_REDIS_URL") {
+            Ok(url) => url,
+            // Redis is optional for running the tests
+            Err(_) => return Ok(()),
+        };
+        let conn = pg_connection();
+        let now = now_millis();
+
+        let rate = PublishRateLimit {
+            rate: Duration::from_millis(100),
+            burst: 3,
+        };
+        let redis_rate = RedisRateLimit::new(rate, &redis_url);
+        let mut redis = redis_rate.pool.get().unwrap();
+        let user_id = new_user(&conn, "user1")?;
+        let _: () = redis::cmd("DEL")
+            .arg(format!("publish_limit_buckets:{}", user_id))
+            .query(&mut *redis)
+            .unwrap();
+
+        // Drain the bucket, wait for a partial and a complete refill
+        for &offset in &[0, 0, 10, 20, 30, 250, 260, 1_000, 1_050, 1_100] {
+            let time = now + chrono::Duration::milliseconds(offset);
+            let expected = rate.take_token(user_id, time, &conn)?;
+            let bucket = redis_rate.take_token(&mut redis, user_id, 3, time).unwrap();
+            assert_eq!(expected, bucket, "after {}ms", offset);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn redis_rate_limit_falls_back_to_the_database() -> QueryResult<()> {
+        let conn = pg_connection();
+        let rate = PublishRateLimit {
+            rate: Duration::from_secs(1),
+            burst: 1,
+        };
+        // Nothing listens on port 1
+        let redis_rate = RedisRateLimit::new(rate, "redis://127.0.0.1:1/");
+        let user_id = new_user(&conn, "user1")?;
+
+        assert!(redis_rate.check_rate_limit(user_id, &conn).is_ok());
+        assert!(redis_rate.check_rate_limit(user_id, &conn).is_err());
+        let tokens = publish_limit_buckets::table
+            .find(user_id)
+            .select(publish_limit_buckets::tokens)
+            .first::<i32>(&conn)?;
+        assert_eq!(0, tokens);
+        Ok(())
+    }
+
     fn new_user(conn: &PgConnection, gh_login: &str) -> QueryResult<i32> {
         use crate::models::NewUser;
 
@@ -352,4 +554,11 @@ mod tests {
         let nanos = now.timestamp_subsec_nanos();
         now - chrono::Duration::nanoseconds(nanos.into())
     }
+
+    /// Like `now`, with the millisecond precision of the Redis buckets.
+    fn now_millis() -> NaiveDateTime {
+        let now = Utc::now().naive_utc();
+        let nanos = now.timestamp_subsec_nanos() % 1_000_000;
+        now - chrono::Duration::nanoseconds(nanos.into())
+    }
 }
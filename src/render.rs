@@ -1,57 +1,35 @@
 //! Render README files to HTML.
 
-use ammonia::{Builder, UrlRelative, UrlRelativeEvaluate};
+use diesel::prelude::*;
 use htmlescape::encode_minimal;
-use std::borrow::Cow;
-use std::path::Path;
 use swirl::PerformError;
-use url::Url;
 
 use crate::background_jobs::Environment;
 use crate::models::Version;
+use crate::sanitize::{ImageProxy, Sanitizer, ID_PREFIX};
+
+mod asciidoc;
+mod org;
 
 /// Context for markdown to HTML rendering.
 #[allow(missing_debug_implementations)]
-struct MarkdownRenderer<'a> {
-    html_sanitizer: Builder<'a>,
+struct MarkdownRenderer {
+    html_sanitizer: Sanitizer,
 }
 
-impl<'a> MarkdownRenderer<'a> {
+impl MarkdownRenderer {
     /// Creates a new renderer instance.
     ///
     /// Per `readme_to_html`, `base_url` is the base URL prepended to any
     /// relative links in the input document.  See that function for more detail.
-    fn new(base_url: Option<&'a str>) -> MarkdownRenderer<'a> {
-        let allowed_classes = hashmap(&[(
-            "code",
-            hashset(&[
-                "language-bash",
-                "language-clike",
-                "language-glsl",
-                "language-go",
-                "language-ini",
-                "language-javascript",
-                "language-json",
-                "language-markup",
-                "language-protobuf",
-                "language-ruby",
-                "language-rust",
-                "language-scss",
-                "language-sql",
-                "yaml",
-            ]),
-        )]);
-        let sanitize_url = UrlRelative::Custom(Box::new(SanitizeUrl::new(base_url)));
-
-        let mut html_sanitizer = Builder::default();
-        html_sanitizer
-            .add_tags(&["input"])
-            .link_rel(Some("nofollow noopener noreferrer"))
-            .add_tag_attributes("a", &["id", "target"])
-            .add_tag_attributes("input", &["checked", "disabled", "type"])
-            .allowed_classes(allowed_classes)
-            .url_relative(sanitize_url)
-            .id_prefix(Some("user-content-"));
+    ///
+    /// If the `IMAGE_PROXY_URL` environment variable is set, absolute image
+    /// URLs are rewritten to be loaded through that proxy.
+    fn new(base_url: Option<&str>, vcs_ref: Option<&str>) -> MarkdownRenderer {
+        let mut html_sanitizer = Sanitizer::document(base_url, vcs_ref);
+        if let Some(proxy) = ImageProxy::from_environment() {
+            html_sanitizer.rewrite_image_urls(proxy.into_rewriter());
+        }
         MarkdownRenderer { html_sanitizer }
     }
 
@@ -64,121 +42,35 @@ impl<'a> MarkdownRenderer<'a> {
             ext_table: true,
             ext_tagfilter: true,
             ext_tasklist: true,
-            ext_header_ids: Some("user-content-".to_string()),
+            ext_header_ids: Some(ID_PREFIX.to_string()),
             ..comrak::ComrakOptions::default()
         };
         let rendered = comrak::markdown_to_html(text, &options);
-        self.html_sanitizer.clean(&rendered).to_string()
-    }
-}
-
-/// Add trailing slash and remove `.git` suffix of base URL.
-fn canon_base_url(mut base_url: String) -> String {
-    if !base_url.ends_with('/') {
-        base_url.push('/');
-    }
-    if base_url.ends_with(".git/") {
-        let offset = base_url.len() - 5;
-        base_url.drain(offset..offset + 4);
-    }
-    base_url
-}
-
-/// Sanitize relative URLs in README files.
-struct SanitizeUrl {
-    base_url: Option<String>,
-}
-
-impl SanitizeUrl {
-    fn new(base_url: Option<&str>) -> Self {
-        let base_url = base_url
-            .and_then(|base_url| Url::parse(base_url).ok())
-            .and_then(|url| match url.host_str() {
-                Some("github.com") | Some("gitlab.com") | Some("bitbucket.org") => {
-                    Some(canon_base_url(url.into_string()))
-                }
-                _ => None,
-            });
-        Self { base_url }
-    }
-}
-
-/// Groups media-related URL info
-struct MediaUrl {
-    is_media: bool,
-    add_sanitize_query: bool,
-}
-
-/// Determine whether the given URL has a media file externsion.
-/// Also check if `sanitize=true` must be added to the query string,
-/// which is required to load SVGs properly from GitHub.
-fn is_media_url(url: &str) -> MediaUrl {
-    Path::new(url)
-        .extension()
-        .and_then(std::ffi::OsStr::to_str)
-        .map_or(
-            MediaUrl {
-                is_media: false,
-                add_sanitize_query: false,
-            },
-            |e| match e {
-                "svg" => MediaUrl {
-                    is_media: true,
-                    add_sanitize_query: true,
-                },
-                "png" | "jpg" | "jpeg" | "gif" | "mp4" | "webm" | "ogg" => MediaUrl {
-                    is_media: true,
-                    add_sanitize_query: false,
-                },
-                _ => MediaUrl {
-                    is_media: false,
-                    add_sanitize_query: false,
-                },
-            },
-        )
-}
-
-impl UrlRelativeEvaluate for SanitizeUrl {
-    fn evaluate<'a>(&self, url: &'a str) -> Option<Cow<'a, str>> {
-        if url.starts_with('#') {
-            // Always allow fragment URLs.
-            return Some(Cow::Borrowed(url));
-        }
-        self.base_url.as_ref().map(|base_url| {
-            let mut new_url = base_url.clone();
-            // Assumes GitHub’s URL scheme. GitHub renders text and markdown
-            // better in the "blob" view, but images need to be served raw.
-            let MediaUrl {
-                is_media,
-                add_sanitize_query,
-            } = is_media_url(url);
-            new_url += if is_media {
-                "raw/master"
-            } else {
-                "blob/master"
-            };
-            if !url.starts_with('/') {
-                new_url.push('/');
-            }
-            new_url += url;
-            if add_sanitize_query {
-                if let Ok(mut parsed_url) = Url::parse(&new_url) {
-                    parsed_url.query_pairs_mut().append_pair("sanitize", "true");
-                    new_url = parsed_url.into_string();
-                }
-            }
-            Cow::Owned(new_url)
-        })
+        self.html_sanitizer.clean(&rendered)
     }
 }
 
 /// Renders Markdown text to sanitized HTML with a given `base_url`.
-/// See `readme_to_html` for the interpretation of `base_url`.
-fn markdown_to_html(text: &str, base_url: Option<&str>) -> String {
-    let renderer = MarkdownRenderer::new(base_url);
+/// See `readme_to_html` for the interpretation of `base_url` and `vcs_ref`.
+fn markdown_to_html(text: &str, base_url: Option<&str>, vcs_ref: Option<&str>) -> String {
+    let renderer = MarkdownRenderer::new(base_url, vcs_ref);
     renderer.to_html(text)
 }
 
+/// Renders a short Markdown message, like the reason of a yank, to sanitized
+/// HTML. Only inline formatting and absolute links are kept, see
+/// `Sanitizer::inline`.
+pub fn message_to_html(text: &str) -> String {
+    let options = comrak::ComrakOptions {
+        unsafe_: true, // The output will be sanitized with `ammonia`
+        ext_autolink: true,
+        ext_strikethrough: true,
+        ..comrak::ComrakOptions::default()
+    };
+    let rendered = comrak::markdown_to_html(text, &options);
+    Sanitizer::inline().clean(&rendered).trim().to_string()
+}
+
 /// Any readme with a filename ending in one of these extensions will be rendered as Markdown.
 /// Note we also render a readme as Markdown if _no_ extension is on the filename.
 static MARKDOWN_EXTENSIONS: [&str; 7] = [
@@ -191,6 +83,12 @@ static MARKDOWN_EXTENSIONS: [&str; 7] = [
     ".mkdown",
 ];
 
+/// Any readme with a filename ending in one of these extensions will be rendered as AsciiDoc.
+static ASCIIDOC_EXTENSIONS: [&str; 3] = [".adoc", ".asciidoc", ".asc"];
+
+/// Any readme with a filename ending in one of these extensions will be rendered as Org.
+static ORG_EXTENSIONS: [&str; 1] = [".org"];
+
 /// Renders a readme to sanitized HTML.  An appropriate rendering method is chosen depending
 /// on the extension of the supplied `filename`.
 ///
@@ -202,24 +100,233 @@ static MARKDOWN_EXTENSIONS: [&str; 7] = [
 /// supplied URL will be used as a directory base whether or not the relative link is
 /// prefixed with '/'.  If `None` is passed, relative links will be omitted.
 ///
+/// Relative links point to the files at `vcs_ref`, which is the commit the crate was
+/// published from if cargo recorded it in `.cargo_vcs_info.json`. Without it, they point to
+/// the default branch of the repository.
+///
 /// # Examples
 ///
 /// ```
 /// use render::render_to_html;
 ///
 /// let text = "[Rust](https://rust-lang.org/) is an awesome *systems programming* language!";
-/// let rendered = readme_to_html(text, "README.md", None)?;
+/// let rendered = readme_to_html(text, "README.md", None, None)?;
 /// ```
-pub fn readme_to_html(text: &str, filename: &str, base_url: Option<&str>) -> String {
+pub fn readme_to_html(
+    text: &str,
+    filename: &str,
+    base_url: Option<&str>,
+    vcs_ref: Option<&str>,
+) -> String {
     let filename = filename.to_lowercase();
 
     if !filename.contains('.') || MARKDOWN_EXTENSIONS.iter().any(|e| filename.ends_with(e)) {
-        return markdown_to_html(text, base_url);
+        return markdown_to_html(text, base_url, vcs_ref);
+    }
+    // AsciiDoc and Org are translated to Markdown, so that they get the same
+    // heading anchors, relative link handling and sanitization
+    if ASCIIDOC_EXTENSIONS.iter().any(|e| filename.ends_with(e)) {
+        return markdown_to_html(&asciidoc::to_markdown(text), base_url, vcs_ref);
+    }
+    if ORG_EXTENSIONS.iter().any(|e| filename.ends_with(e)) {
+        return markdown_to_html(&org::to_markdown(text), base_url, vcs_ref);
     }
 
     encode_minimal(text).replace("\n", "<br>\n")
 }
 
+/// A fenced Markdown code block, with a fence longer than any run of
+/// backticks in the code.
+fn fence(language: &str, lines: &[&str]) -> String {
+    let longest_run = lines
+        .iter()
+        .flat_map(|line| line.split(|c| c != '`'))
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    let mut block = format!("{}{}\n", fence, language);
+    for line in lines {
+        block.push_str(line);
+        block.push('\n');
+    }
+    block.push_str(&fence);
+    block.push('\n');
+    block
+}
+
+/// Applies `f` to the parts of a line of Markdown outside of code spans.
+fn map_outside_code(line: &str, f: impl Fn(&str) -> String) -> String {
+    line.split('`')
+        .enumerate()
+        .map(|(i, part)| {
+            if i % 2 == 0 {
+                f(part)
+            } else {
+                part.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("`")
+}
+
+/// Replaces the `marker` around spans like `*strong*` with `replacement`.
+/// Like in AsciiDoc and Org, markers only count at the boundaries of words,
+/// so that URLs and paths are left alone, and the span can't start or end
+/// with whitespace.
+fn replace_delimited(line: &str, marker: char, replacement: &str) -> String {
+    let chars = line.chars().collect::<Vec<_>>();
+    let opens = |i: usize| {
+        chars[i] == marker
+            && (i == 0 || chars[i - 1].is_whitespace() || "-([{'\"".contains(chars[i - 1]))
+            && chars
+                .get(i + 1)
+                .map_or(false, |&c| !c.is_whitespace() && c != marker)
+    };
+    let closes = |i: usize| {
+        chars[i] == marker
+            && !chars[i - 1].is_whitespace()
+            && chars
+                .get(i + 1)
+                .map_or(true, |&c| c.is_whitespace() || "-.,;:!?)]}'\"".contains(c))
+    };
+
+    let mut out = String::with_capacity(line.len());
+    let mut i = 0;
+    while i < chars.len() {
+        if opens(i) {
+            if let Some(end) = (i + 2..chars.len()).find(|&j| closes(j)) {
+                out.push_str(replacement);
+                out.extend(&chars[i + 1..end]);
+                out.push_str(replacement);
+                i = end + 1;
+                continue;
+            }
+        }
+        out.push(chars[i]);
+        i += 1;
+    }
+    out
+}
+
+/// The maximum number of bytes of README text that are indexed for search.
+///
+/// PostgreSQL limits the size of a `tsvector` to 1MB, and the beginning of a
+/// README usually describes what the crate is about anyway.
+const MAX_INDEXED_README_LENGTH: usize = 100_000;
+
+/// Extracts the text content of sanitized HTML, e.g. for search indexing.
+///
+/// This only works for HTML that went through the sanitizer, which guarantees
+/// that `<` and `>` only appear as part of tags.
+fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => {
+                in_tag = true;
+                // Keep words from adjacent elements apart
+                text.push(' ');
+            }
+            '>' => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// A heading of a rendered README, for a table of contents.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TocEntry {
+    /// 1 for `<h1>` up to 6 for `<h6>`.
+    pub level: u8,
+    /// The anchor of the heading, linked to as `#{slug}`. The element itself
+    /// has the id `user-content-{slug}`.
+    pub slug: String,
+    /// The text of the heading.
+    pub title: String,
+}
+
+/// Extracts the headings of sanitized HTML which have an anchor, which are
+/// those Markdown headings were rendered to. Headings written as HTML have
+/// none and are skipped.
+pub fn extract_toc(html: &str) -> Vec<TocEntry> {
+    let mut toc = Vec::new();
+    let mut rest = html;
+    while let Some(start) = rest.find("<h") {
+        rest = &rest[start + 2..];
+        let level = match rest.as_bytes().get(0).cloned() {
+            Some(c @ b'1'..=b'6') => c - b'0',
+            _ => continue,
+        };
+        if !rest[1..].starts_with('>') && !rest[1..].starts_with(' ') {
+            continue;
+        }
+        let content_start = match rest.find('>') {
+            Some(i) => i + 1,
+            None => break,
+        };
+        let closing_tag = format!("</h{}>", level);
+        let content_end = match rest.find(&closing_tag) {
+            Some(i) => i,
+            None => break,
+        };
+        if content_end < content_start {
+            continue;
+        }
+        let content = &rest[content_start..content_end];
+        rest = &rest[content_end + closing_tag.len()..];
+
+        let id_attribute = format!("id=\"{}", ID_PREFIX);
+        let slug = content.find(&id_attribute).and_then(|i| {
+            let slug = &content[i + id_attribute.len()..];
+            slug.find('"').map(|end| &slug[..end])
+        });
+        let slug = match slug {
+            Some(slug) if !slug.is_empty() => slug.to_string(),
+            _ => continue,
+        };
+        let title = html_to_text(content)
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        toc.push(TocEntry { level, slug, title });
+    }
+    toc
+}
+
+/// Stores the search index of a rendered README.
+fn index_readme(version_id: i32, rendered: &str, conn: &PgConnection) -> QueryResult<()> {
+    use crate::schema::readme_renderings;
+    use diesel::dsl::sql;
+    use diesel::sql_types::Text;
+    use diesel_full_text_search::TsVector;
+
+    let mut text = html_to_text(rendered);
+    if text.len() > MAX_INDEXED_README_LENGTH {
+        let mut end = MAX_INDEXED_README_LENGTH;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+    }
+
+    let tsvector = sql::<TsVector>("to_tsvector('english', ")
+        .bind::<Text, _>(text)
+        .sql(")");
+    diesel::update(readme_renderings::table.find(version_id))
+        .set(readme_renderings::textsearchable_index_col.eq(tsvector))
+        .execute(conn)?;
+    Ok(())
+}
+
 #[swirl::background_job]
 pub fn render_and_upload_readme(
     env: &Environment,
@@ -227,15 +334,21 @@ pub fn render_and_upload_readme(
     text: String,
     file_name: String,
     base_url: Option<String>,
+    vcs_ref: Option<String>,
 ) -> Result<(), PerformError> {
     use crate::schema::*;
-    use diesel::prelude::*;
 
-    let rendered = readme_to_html(&text, &file_name, base_url.as_deref());
+    let _heartbeat = env.heartbeat("render_and_upload_readme")?;
+    let rendered = readme_to_html(&text, &file_name, base_url.as_deref(), vcs_ref.as_deref());
+    let toc = serde_json::to_value(extract_toc(&rendered))?;
     let conn = env.connection()?;
 
     conn.transaction(|| {
         Version::record_readme_rendering(version_id, &conn)?;
+        index_readme(version_id, &rendered, &conn)?;
+        diesel::update(readme_renderings::table.find(version_id))
+            .set(readme_renderings::toc.eq(toc))
+            .execute(&*conn)?;
         let (crate_name, vers) = versions::table
             .find(version_id)
             .inner_join(crates::table)
@@ -247,23 +360,6 @@ pub fn render_and_upload_readme(
     })
 }
 
-/// Helper function to build a new `HashSet` from the items slice.
-fn hashset<T>(items: &[T]) -> std::collections::HashSet<T>
-where
-    T: Clone + Eq + std::hash::Hash,
-{
-    items.iter().cloned().collect()
-}
-
-/// Helper function to build a new `HashMap` from a slice of key-value pairs.
-fn hashmap<K, V>(items: &[(K, V)]) -> std::collections::HashMap<K, V>
-where
-    K: Clone + Eq + std::hash::Hash,
-    V: Clone,
-{
-    items.iter().cloned().collect()
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -271,14 +367,14 @@ mod tests {
     #[test]
     fn empty_text() {
         let text = "";
-        let result = markdown_to_html(text, None);
+        let result = markdown_to_html(text, None, None);
         assert_eq!(result, "");
     }
 
     #[test]
     fn text_with_script_tag() {
         let text = "foo_readme\n\n<script>alert('Hello World')</script>";
-        let result = markdown_to_html(text, None);
+        let result = markdown_to_html(text, None, None);
         assert_eq!(
             result,
             "<p>foo_readme</p>\n&lt;script&gt;alert(\'Hello World\')&lt;/script&gt;\n"
@@ -288,7 +384,7 @@ mod tests {
     #[test]
     fn text_with_iframe_tag() {
         let text = "foo_readme\n\n<iframe>alert('Hello World')</iframe>";
-        let result = markdown_to_html(text, None);
+        let result = markdown_to_html(text, None, None);
         assert_eq!(
             result,
             "<p>foo_readme</p>\n&lt;iframe&gt;alert(\'Hello World\')&lt;/iframe&gt;\n"
@@ -298,14 +394,14 @@ mod tests {
     #[test]
     fn text_with_unknown_tag() {
         let text = "foo_readme\n\n<unknown>alert('Hello World')</unknown>";
-        let result = markdown_to_html(text, None);
+        let result = markdown_to_html(text, None, None);
         assert_eq!(result, "<p>foo_readme</p>\n<p>alert(\'Hello World\')</p>\n");
     }
 
     #[test]
     fn text_with_inline_javascript() {
         let text = r#"foo_readme\n\n<a href="https://crates.io/crates/cargo-registry" onclick="window.alert('Got you')">Crate page</a>"#;
-        let result = markdown_to_html(text, None);
+        let result = markdown_to_html(text, None, None);
         assert_eq!(
             result,
             "<p>foo_readme\\n\\n<a href=\"https://crates.io/crates/cargo-registry\" rel=\"nofollow noopener noreferrer\">Crate page</a></p>\n"
@@ -317,7 +413,7 @@ mod tests {
     #[test]
     fn text_with_fancy_single_quotes() {
         let text = r#"wb’"#;
-        let result = markdown_to_html(text, None);
+        let result = markdown_to_html(text, None, None);
         assert_eq!(result, "<p>wb’</p>\n");
     }
 
@@ -326,14 +422,14 @@ mod tests {
         let code_block = r#"```rust \
                             println!("Hello World"); \
                            ```"#;
-        let result = markdown_to_html(code_block, None);
+        let result = markdown_to_html(code_block, None, None);
         assert!(result.contains("<code class=\"language-rust\">"));
     }
 
     #[test]
     fn text_with_forbidden_class_attribute() {
         let text = "<p class='bad-class'>Hello World!</p>";
-        let result = markdown_to_html(text, None);
+        let result = markdown_to_html(text, None, None);
         assert_eq!(result, "<p>Hello World!</p>\n");
     }
 
@@ -344,7 +440,11 @@ mod tests {
         let image = "![alt](img.png)";
         let svg = "![alt](sanitize.svg)";
 
-        for host in &["github.com", "gitlab.com", "bitbucket.org"] {
+        for &(host, blob, raw) in &[
+            ("github.com", "blob", "raw"),
+            ("gitlab.com", "-/blob", "-/raw"),
+            ("bitbucket.org", "src", "raw"),
+        ] {
             for (&extra_slash, &dot_git) in [true, false].iter().zip(&[true, false]) {
                 let url = format!(
                     "https://{}/rust-lang/test{}{}",
@@ -353,57 +453,84 @@ mod tests {
                     if extra_slash { "/" } else { "" },
                 );
 
-                let result = markdown_to_html(absolute, Some(&url));
+                let result = markdown_to_html(absolute, Some(&url), None);
                 assert_eq!(
                     result,
                     format!(
-                        "<p><a href=\"https://{}/rust-lang/test/blob/master/hi\" rel=\"nofollow noopener noreferrer\">hi</a></p>\n",
-                        host
+                        "<p><a href=\"https://{}/rust-lang/test/{}/HEAD/hi\" rel=\"nofollow noopener noreferrer\">hi</a></p>\n",
+                        host, blob
                     )
                 );
 
-                let result = markdown_to_html(relative, Some(&url));
+                let result = markdown_to_html(relative, Some(&url), None);
                 assert_eq!(
                     result,
                     format!(
-                        "<p><a href=\"https://{}/rust-lang/test/blob/master/there\" rel=\"nofollow noopener noreferrer\">there</a></p>\n",
-                        host
+                        "<p><a href=\"https://{}/rust-lang/test/{}/HEAD/there\" rel=\"nofollow noopener noreferrer\">there</a></p>\n",
+                        host, blob
                     )
                 );
 
-                let result = markdown_to_html(image, Some(&url));
+                let result = markdown_to_html(image, Some(&url), None);
                 assert_eq!(
                     result,
                     format!(
-                 "<p><img src=\"https://{}/rust-lang/test/raw/master/img.png\" alt=\"alt\"></p>\n",
-                        host
+                 "<p><img src=\"https://{}/rust-lang/test/{}/HEAD/img.png\" alt=\"alt\"></p>\n",
+                        host, raw
                     )
                 );
 
-                let result = markdown_to_html(svg, Some(&url));
+                let result = markdown_to_html(svg, Some(&url), None);
                 assert_eq!(
                     result,
                     format!(
-                        "<p><img src=\"https://{}/rust-lang/test/raw/master/sanitize.svg?sanitize=true\" alt=\"alt\"></p>\n",
-                        host
+                        "<p><img src=\"https://{}/rust-lang/test/{}/HEAD/sanitize.svg?sanitize=true\" alt=\"alt\"></p>\n",
+                        host, raw
                     )
                 );
             }
         }
 
-        let result = markdown_to_html(absolute, Some("https://google.com/"));
+        let result = markdown_to_html(absolute, Some("https://google.com/"), None);
         assert_eq!(
             result,
             "<p><a rel=\"nofollow noopener noreferrer\">hi</a></p>\n"
         );
     }
 
+    #[test]
+    fn relative_links_point_to_the_published_commit() {
+        let text = "[guide](docs/guide.md) ![logo](logo.png)";
+        let commit = "0123456789abcdef0123456789abcdef01234567";
+        let result = markdown_to_html(
+            text,
+            Some("https://github.com/rust-lang/test"),
+            Some(commit),
+        );
+        assert_eq!(
+            result,
+            format!(
+                "<p><a href=\"https://github.com/rust-lang/test/blob/{0}/docs/guide.md\" rel=\"nofollow noopener noreferrer\">guide</a> \
+                 <img src=\"https://github.com/rust-lang/test/raw/{0}/logo.png\" alt=\"logo\"></p>\n",
+                commit
+            )
+        );
+
+        // Refs which would change the meaning of the URL aren't used
+        let result = markdown_to_html(
+            text,
+            Some("https://github.com/rust-lang/test"),
+            Some("../x?"),
+        );
+        assert!(result.contains("/blob/HEAD/docs/guide.md"));
+    }
+
     #[test]
     fn absolute_links_dont_get_resolved() {
         let readme_text =
             "[![Crates.io](https://img.shields.io/crates/v/clap.svg)](https://crates.io/crates/clap)";
         let repository = "https://github.com/kbknapp/clap-rs/";
-        let result = markdown_to_html(readme_text, Some(repository));
+        let result = markdown_to_html(readme_text, Some(repository), None);
 
         assert_eq!(
             result,
@@ -415,7 +542,7 @@ mod tests {
     fn readme_to_html_renders_markdown() {
         for f in &["README", "readme.md", "README.MARKDOWN", "whatever.mkd"] {
             assert_eq!(
-                readme_to_html("*lobster*", f, None),
+                readme_to_html("*lobster*", f, None, None),
                 "<p><em>lobster</em></p>\n"
             );
         }
@@ -423,18 +550,69 @@ mod tests {
 
     #[test]
     fn readme_to_html_renders_other_things() {
-        for f in &["readme.exe", "readem.org", "blah.adoc"] {
+        for f in &["readme.exe", "readme.txt", "blah.rst"] {
             assert_eq!(
-                readme_to_html("<script>lobster</script>\n\nis my friend\n", f, None),
+                readme_to_html("<script>lobster</script>\n\nis my friend\n", f, None, None),
                 "&lt;script&gt;lobster&lt;/script&gt;<br>\n<br>\nis my friend<br>\n"
             );
         }
     }
 
+    #[test]
+    fn readme_to_html_renders_asciidoc() {
+        let text = "== Usage\n\nSee link:docs/intro.adoc[the *intro*].\n\n++++\n<script>alert(1)</script>\n++++\n";
+        for f in &["README.adoc", "readme.asciidoc"] {
+            assert_eq!(
+                readme_to_html(text, f, Some("https://github.com/rust-lang/cargo"), None),
+                "<h2><a href=\"#usage\" id=\"user-content-usage\" rel=\"nofollow noopener noreferrer\"></a>Usage</h2>\n\
+                 <p>See <a href=\"https://github.com/rust-lang/cargo/blob/HEAD/docs/intro.adoc\" rel=\"nofollow noopener noreferrer\">the <strong>intro</strong></a>.</p>\n\
+                 &lt;script&gt;alert(1)&lt;/script&gt;\n"
+            );
+        }
+    }
+
+    #[test]
+    fn readme_to_html_renders_org() {
+        let text = "* Usage\n\nSee [[docs/intro.org][the /intro/]].\n\n#+BEGIN_HTML\n<script>alert(1)</script>\n#+END_HTML\n";
+        assert_eq!(
+            readme_to_html(text, "README.org", Some("https://github.com/rust-lang/cargo"), None),
+            "<h1><a href=\"#usage\" id=\"user-content-usage\" rel=\"nofollow noopener noreferrer\"></a>Usage</h1>\n\
+             <p>See <a href=\"https://github.com/rust-lang/cargo/blob/HEAD/docs/intro.org\" rel=\"nofollow noopener noreferrer\">the <em>intro</em></a>.</p>\n\
+             &lt;script&gt;alert(1)&lt;/script&gt;\n"
+        );
+    }
+
+    #[test]
+    fn delimited_spans_are_replaced_at_word_boundaries() {
+        assert_eq!(
+            replace_delimited("*a* b*c* *d e*", '*', "**"),
+            "**a** b*c* **d e**"
+        );
+        assert_eq!(replace_delimited("2 * 3 * 4", '*', "**"), "2 * 3 * 4");
+        assert_eq!(
+            replace_delimited("a/b/c and /d/", '/', "*"),
+            "a/b/c and *d*"
+        );
+        assert_eq!(
+            replace_delimited("https://docs.rs/foo/", '/', "*"),
+            "https://docs.rs/foo/"
+        );
+    }
+
+    #[test]
+    fn messages_only_keep_inline_formatting() {
+        let text = "Use `bar` *instead*, see https://example.com/\n\n# Heading <img src=x>";
+        assert_eq!(
+            message_to_html(text),
+            "Use <code>bar</code> <em>instead</em>, see <a href=\"https://example.com/\" \
+             rel=\"nofollow noopener noreferrer\">https://example.com/</a>\nHeading"
+        );
+    }
+
     #[test]
     fn header_has_tags() {
         let text = "# My crate\n\nHello, world!\n";
-        let result = markdown_to_html(text, None);
+        let result = markdown_to_html(text, None, None);
         assert_eq!(
             result,
             "<h1><a href=\"#my-crate\" id=\"user-content-my-crate\" rel=\"nofollow noopener noreferrer\"></a>My crate</h1>\n<p>Hello, world!</p>\n"
@@ -445,7 +623,7 @@ mod tests {
     fn manual_anchor_is_sanitized() {
         let text =
             "<h1><a href=\"#my-crate\" id=\"my-crate\"></a>My crate</h1>\n<p>Hello, world!</p>\n";
-        let result = markdown_to_html(text, None);
+        let result = markdown_to_html(text, None, None);
         assert_eq!(
             result,
             "<h1><a href=\"#my-crate\" id=\"user-content-my-crate\" rel=\"nofollow noopener noreferrer\"></a>My crate</h1>\n<p>Hello, world!</p>\n"
@@ -455,10 +633,45 @@ mod tests {
     #[test]
     fn tables_with_rowspan_and_colspan() {
         let text = "<table><tr><th rowspan=\"1\" colspan=\"2\">Target</th></tr></table>\n";
-        let result = markdown_to_html(text, None);
+        let result = markdown_to_html(text, None, None);
         assert_eq!(
             result,
             "<table><tbody><tr><th rowspan=\"1\" colspan=\"2\">Target</th></tr></tbody></table>\n"
         );
     }
+
+    #[test]
+    fn toc_lists_headings_with_anchors() {
+        let text =
+            "# My crate\n\n## Usage & *examples*\n\ntext\n\n<h2>No anchor</h2>\n\n### Usage\n";
+        let toc = extract_toc(&markdown_to_html(text, None, None));
+        let entry = |level, slug: &str, title: &str| TocEntry {
+            level,
+            slug: slug.into(),
+            title: title.into(),
+        };
+        assert_eq!(
+            toc,
+            vec![
+                entry(1, "my-crate", "My crate"),
+                entry(2, "usage--examples", "Usage & examples"),
+                entry(3, "usage-1", "Usage"),
+            ]
+        );
+        assert_eq!(
+            extract_toc("<p>no headings</p><hr><header></header>"),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn html_to_text_strips_tags() {
+        let html = "<h1>foo</h1><p>A <em>fast</em> &amp; <a href=\"https://example.com\">safe</a> \
+                    parser for &lt;foo&gt; files.</p>";
+        let text = html_to_text(html);
+        assert_eq!(
+            text.split_whitespace().collect::<Vec<_>>(),
+            vec!["foo", "A", "fast", "&", "safe", "parser", "for", "<foo>", "files."]
+        );
+    }
 }
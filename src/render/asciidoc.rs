@@ -0,0 +1,265 @@
+//! Translates AsciiDoc to Markdown
+//!
+//! Only the subset of AsciiDoc which READMEs commonly use is understood:
+//! section titles, paragraphs, lists, listing, literal, quote and
+//! passthrough blocks, admonition paragraphs, `*strong*` and `_emphasis_`
+//! text, links and images. Document attributes, block attributes and
+//! comments are dropped, and anything else is left as it is.
+
+use super::{fence, map_outside_code, replace_delimited};
+
+const ADMONITIONS: [(&str, &str); 5] = [
+    ("NOTE: ", "Note"),
+    ("TIP: ", "Tip"),
+    ("IMPORTANT: ", "Important"),
+    ("WARNING: ", "Warning"),
+    ("CAUTION: ", "Caution"),
+];
+
+pub(super) fn to_markdown(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut lines = text.lines();
+    // The language of a `[source,rust]` attribute line, applying to the
+    // listing block which follows it.
+    let mut language = String::new();
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_end();
+        match trimmed {
+            "////" => {
+                lines.by_ref().find(|line| line.trim_end() == "////");
+                continue;
+            }
+            "----" | "...." => {
+                let body = delimited_block(&mut lines, trimmed);
+                out.push_str(&fence(&language, &body));
+                language.clear();
+                continue;
+            }
+            "++++" => {
+                out.push('\n');
+                for line in delimited_block(&mut lines, trimmed) {
+                    out.push_str(line);
+                    out.push('\n');
+                }
+                out.push('\n');
+                continue;
+            }
+            // Example and sidebar blocks and open blocks only group their
+            // content
+            "====" | "****" | "--" => continue,
+            "____" => {
+                for line in delimited_block(&mut lines, trimmed) {
+                    out.push_str("> ");
+                    out.push_str(&inline(line));
+                    out.push('\n');
+                }
+                out.push('\n');
+                continue;
+            }
+            _ => {}
+        }
+
+        if trimmed.starts_with("//") || is_attribute_entry(trimmed) {
+            continue;
+        }
+        if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            if trimmed.starts_with("[source") {
+                language = trimmed[1..trimmed.len() - 1]
+                    .split(',')
+                    .nth(1)
+                    .unwrap_or("")
+                    .trim()
+                    .to_string();
+            }
+            continue;
+        }
+
+        if let Some((level, title)) = prefixed(trimmed, '=') {
+            out.push_str(&"#".repeat(level.min(6)));
+            out.push(' ');
+            out.push_str(&inline(title));
+        } else if let Some((depth, item)) = prefixed(trimmed, '*') {
+            out.push_str(&"  ".repeat(depth - 1));
+            out.push_str("- ");
+            out.push_str(&inline(item));
+        } else if let Some((depth, item)) = prefixed(trimmed, '.') {
+            out.push_str(&"   ".repeat(depth - 1));
+            out.push_str("1. ");
+            out.push_str(&inline(item));
+        } else if let Some(&(prefix, label)) = ADMONITIONS
+            .iter()
+            .find(|(prefix, _)| trimmed.starts_with(prefix))
+        {
+            out.push_str(&format!(
+                "**{}:** {}",
+                label,
+                inline(&trimmed[prefix.len()..])
+            ));
+        } else {
+            out.push_str(&inline(line));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// The lines up to the line closing a delimited block.
+fn delimited_block<'a>(lines: &mut std::str::Lines<'a>, delimiter: &str) -> Vec<&'a str> {
+    lines
+        .take_while(|line| line.trim_end() != delimiter)
+        .collect()
+}
+
+/// Lines like `:toc: left` or `:toc:`, which set document attributes.
+fn is_attribute_entry(line: &str) -> bool {
+    if !line.starts_with(':') {
+        return false;
+    }
+    match line[1..].find(':') {
+        Some(end) => {
+            let name = &line[1..=end];
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '!')
+                && line[end + 2..]
+                    .chars()
+                    .next()
+                    .map_or(true, char::is_whitespace)
+        }
+        None => false,
+    }
+}
+
+/// Splits lines like `== Title` or `** item` into the number of markers and
+/// the rest of the line.
+fn prefixed(line: &str, marker: char) -> Option<(usize, &str)> {
+    let count = line.chars().take_while(|&c| c == marker).count();
+    let rest = &line[count..];
+    if count > 0 && rest.starts_with(' ') && !rest.trim().is_empty() {
+        Some((count, rest.trim()))
+    } else {
+        None
+    }
+}
+
+fn inline(text: &str) -> String {
+    let text = links(text);
+    map_outside_code(&text, |text| {
+        let text = replace_delimited(text, '*', "**");
+        replace_delimited(&text, '_', "*")
+    })
+}
+
+/// Translates `link:url[text]`, `https://url[text]`, `image:url[alt]` and
+/// `image::url[alt]` to Markdown links and images.
+fn links(text: &str) -> String {
+    const PREFIXES: [(&str, &str, bool); 5] = [
+        ("image::", "!", false),
+        ("image:", "!", false),
+        ("link:", "", false),
+        ("https://", "", true),
+        ("http://", "", true),
+    ];
+
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    'outer: while !rest.is_empty() {
+        for &(prefix, bang, keep_prefix) in &PREFIXES {
+            if !rest.starts_with(prefix) {
+                continue;
+            }
+            let target_start = if keep_prefix { 0 } else { prefix.len() };
+            let after_prefix = &rest[prefix.len()..];
+            let target_end = after_prefix
+                .find(|c: char| c == '[' || c.is_whitespace())
+                .map_or(rest.len(), |i| prefix.len() + i);
+            let target = &rest[target_start..target_end];
+            let attributes = &rest[target_end..];
+            if !target.is_empty() && attributes.starts_with('[') {
+                if let Some(close) = attributes.find(']') {
+                    let label = attributes[1..close].split(',').next().unwrap_or("");
+                    let label = if label.is_empty() && bang.is_empty() {
+                        target
+                    } else {
+                        label
+                    };
+                    out.push_str(&format!("{}[{}]({})", bang, label, target));
+                    rest = &attributes[close + 1..];
+                    continue 'outer;
+                }
+            }
+            if keep_prefix {
+                out.push_str(&rest[..target_end]);
+                rest = &rest[target_end..];
+                continue 'outer;
+            }
+        }
+        let next = rest.chars().next().unwrap();
+        out.push(next);
+        rest = &rest[next.len_utf8()..];
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_are_translated() {
+        let text = "\
+= My crate
+:toc:
+// a comment
+
+Some *bold* and _emphasized_ text, with `some_code`.
+
+== Usage
+
+* first
+** nested
+. one
+
+[source,rust]
+----
+let x = *y;
+----
+
+NOTE: Experimental.
+";
+        assert_eq!(
+            to_markdown(text),
+            "\
+# My crate
+
+Some **bold** and *emphasized* text, with `some_code`.
+
+## Usage
+
+- first
+  - nested
+1. one
+
+```rust
+let x = *y;
+```
+
+**Note:** Experimental.
+"
+        );
+    }
+
+    #[test]
+    fn links_are_translated() {
+        assert_eq!(
+            links("See https://docs.rs[the docs] or link:CONTRIBUTING.adoc[]."),
+            "See [the docs](https://docs.rs) or [CONTRIBUTING.adoc](CONTRIBUTING.adoc)."
+        );
+        assert_eq!(
+            links("image::logo.png[Logo,200] and https://example.com"),
+            "![Logo](logo.png) and https://example.com"
+        );
+    }
+}
@@ -0,0 +1,291 @@
+//! Translates Org to Markdown
+//!
+//! Only the subset of Org which READMEs commonly use is understood:
+//! headlines, paragraphs, lists with checkboxes, tables, source, example,
+//! quote and HTML export blocks, fixed-width lines, `*bold*`, `/italic/`,
+//! `+strike-through+`, `=verbatim=` and `~code~` text, and links. Keywords
+//! other than `#+TITLE`, comments and drawers are dropped, and anything else
+//! is left as it is.
+
+use super::{fence, map_outside_code, replace_delimited};
+
+pub(super) fn to_markdown(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut lines = text.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim();
+
+        if let Some((keyword, argument)) = keyword(trimmed) {
+            let is_html_block = keyword == "BEGIN_HTML"
+                || (keyword == "BEGIN_EXPORT" && argument.eq_ignore_ascii_case("html"));
+            if is_html_block {
+                out.push('\n');
+                for line in block(&mut lines, &format!("END{}", &keyword[5..])) {
+                    out.push_str(line);
+                    out.push('\n');
+                }
+                out.push('\n');
+                continue;
+            }
+            match keyword.as_str() {
+                "TITLE" => {
+                    out.push_str("# ");
+                    out.push_str(&inline(argument));
+                    out.push('\n');
+                }
+                "BEGIN_SRC" => {
+                    let language = argument.split_whitespace().next().unwrap_or("");
+                    out.push_str(&fence(language, &block(&mut lines, "END_SRC")));
+                }
+                "BEGIN_EXAMPLE" => {
+                    out.push_str(&fence("", &block(&mut lines, "END_EXAMPLE")));
+                }
+                "BEGIN_QUOTE" => {
+                    for line in block(&mut lines, "END_QUOTE") {
+                        out.push_str("> ");
+                        out.push_str(&inline(line.trim()));
+                        out.push('\n');
+                    }
+                    out.push('\n');
+                }
+                "BEGIN_EXPORT" | "BEGIN_COMMENT" => {
+                    block(&mut lines, &format!("END{}", &keyword[5..]));
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        if trimmed == "#" || trimmed.starts_with("# ") {
+            continue;
+        }
+        if trimmed.len() > 2
+            && trimmed.starts_with(':')
+            && trimmed.ends_with(':')
+            && !trimmed[1..trimmed.len() - 1].contains(char::is_whitespace)
+        {
+            // A drawer like `:PROPERTIES:`, which lasts until `:END:`
+            if !trimmed.eq_ignore_ascii_case(":END:") {
+                while let Some(line) = lines.next() {
+                    if line.trim().eq_ignore_ascii_case(":END:") {
+                        break;
+                    }
+                }
+            }
+            continue;
+        }
+        if trimmed == ":" || trimmed.starts_with(": ") {
+            let mut fixed_width = vec![fixed_width_line(trimmed)];
+            while let Some(line) = lines.peek() {
+                let line = line.trim();
+                if line != ":" && !line.starts_with(": ") {
+                    break;
+                }
+                fixed_width.push(fixed_width_line(line));
+                lines.next();
+            }
+            out.push_str(&fence("", &fixed_width));
+            continue;
+        }
+
+        if let Some((level, title)) = headline(line) {
+            out.push_str(&"#".repeat(level.min(6)));
+            out.push(' ');
+            out.push_str(&inline(title));
+        } else if trimmed.starts_with("|-") {
+            // Org separates the header row with `|---+---|`
+            out.push_str(&trimmed.replace('+', "|"));
+        } else if let Some((indent, item)) = list_item(line) {
+            out.push_str(indent);
+            out.push_str("- ");
+            out.push_str(&inline(&item));
+        } else {
+            out.push_str(&inline(line));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Splits `#+KEYWORD: argument` and `#+BEGIN_BLOCK argument` lines into the
+/// name of the keyword, in uppercase, and its argument.
+fn keyword(line: &str) -> Option<(String, &str)> {
+    if !line.starts_with("#+") {
+        return None;
+    }
+    let rest = &line[2..];
+    let end = rest
+        .find(|c: char| c == ':' || c.is_whitespace())
+        .unwrap_or_else(|| rest.len());
+    if end == 0 {
+        return None;
+    }
+    let argument = rest[end..].trim_start_matches(':').trim();
+    Some((rest[..end].to_uppercase(), argument))
+}
+
+/// The lines up to the `#+END_...` line closing a block.
+fn block<'a, I: Iterator<Item = &'a str>>(lines: &mut I, end: &str) -> Vec<&'a str> {
+    lines
+        .take_while(|line| keyword(line.trim()).map_or(true, |(keyword, _)| keyword != end))
+        .collect()
+}
+
+fn fixed_width_line(line: &str) -> &str {
+    if line.len() > 2 {
+        &line[2..]
+    } else {
+        ""
+    }
+}
+
+/// Splits headlines like `** TODO Title :tag:` into their level and title,
+/// without the tags.
+fn headline(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|&c| c == '*').count();
+    let rest = &line[level..];
+    if level == 0 || !rest.starts_with(' ') || rest.trim().is_empty() {
+        return None;
+    }
+    let mut title = rest.trim();
+    if let Some(start) = title.rfind(char::is_whitespace) {
+        let tags = &title[start + 1..];
+        if tags.len() > 2 && tags.starts_with(':') && tags.ends_with(':') {
+            title = title[..start].trim_end();
+        }
+    }
+    Some((level, title))
+}
+
+/// Splits list items like `  + [X] done` into their indentation and content,
+/// with Markdown checkboxes.
+fn list_item(line: &str) -> Option<(&str, String)> {
+    let content = line.trim_start();
+    let indent = &line[..line.len() - content.len()];
+    let item = if content.starts_with("- ") || content.starts_with("+ ") {
+        &content[2..]
+    } else {
+        return None;
+    };
+    let item = if item.starts_with("[X] ") {
+        format!("[x] {}", &item[4..])
+    } else {
+        item.to_string()
+    };
+    Some((indent, item))
+}
+
+fn inline(text: &str) -> String {
+    let text = verbatim(&links(text));
+    map_outside_code(&text, |text| {
+        let text = replace_delimited(text, '*', "**");
+        let text = replace_delimited(&text, '/', "*");
+        replace_delimited(&text, '+', "~~")
+    })
+}
+
+/// Translates `=verbatim=` and `~code~` to Markdown code spans.
+fn verbatim(text: &str) -> String {
+    map_outside_code(text, |text| {
+        let text = replace_delimited(text, '=', "`");
+        replace_delimited(&text, '~', "`")
+    })
+}
+
+/// Translates `[[target][description]]` and `[[target]]` to Markdown links,
+/// and to images if an image file is linked to without a description.
+fn links(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("[[") {
+        let end = match rest[start..].find("]]") {
+            Some(end) => start + end,
+            None => break,
+        };
+        out.push_str(&rest[..start]);
+        let link = &rest[start + 2..end];
+        let mut parts = link.splitn(2, "][");
+        let target = parts.next().unwrap_or("");
+        let target = if target.starts_with("file:") {
+            &target["file:".len()..]
+        } else {
+            target
+        };
+        match parts.next() {
+            Some(description) => out.push_str(&format!("[{}]({})", description, target)),
+            None if is_image(target) => out.push_str(&format!("![]({})", target)),
+            None => out.push_str(&format!("[{}]({})", target, target)),
+        }
+        rest = &rest[end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn is_image(target: &str) -> bool {
+    let target = target.to_lowercase();
+    [".png", ".jpg", ".jpeg", ".gif", ".svg", ".webp"]
+        .iter()
+        .any(|extension| target.ends_with(extension))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_are_translated() {
+        let text = "\
+#+TITLE: My crate
+#+OPTIONS: toc:nil
+# a comment
+
+Some *bold*, /italic/ and =verbatim= text.
+
+* Usage :docs:
+:PROPERTIES:
+:CUSTOM_ID: usage
+:END:
+- [X] first
+  + second
+
+#+begin_src rust
+let x = *y;
+#+end_src
+
+| a | b |
+|---+---|
+| 1 | 2 |
+";
+        assert_eq!(
+            to_markdown(text),
+            "\
+# My crate
+
+Some **bold**, *italic* and `verbatim` text.
+
+# Usage
+- [x] first
+  - second
+
+```rust
+let x = *y;
+```
+
+| a | b |
+|---|---|
+| 1 | 2 |
+"
+        );
+    }
+
+    #[test]
+    fn links_are_translated() {
+        assert_eq!(
+            links("See [[https://docs.rs][the docs]] or [[https://crates.io]]."),
+            "See [the docs](https://docs.rs) or [https://crates.io](https://crates.io)."
+        );
+        assert_eq!(links("[[file:logo.png]]"), "![](logo.png)");
+    }
+}
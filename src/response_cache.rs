@@ -0,0 +1,282 @@
+//! A cache for the responses of hot read endpoints.
+//!
+//! Responses are cached together with a version, usually the `updated_at` of
+//! the data they were rendered from, so that changed data is never served
+//! from the cache. The responses of a crate use the `updated_at` of the crate
+//! and the latest one of its versions, since yanks only touch the version.
+//! Parts of a response which change without touching
+//! `updated_at`, like download counts, are refreshed with
+//! stale-while-revalidate semantics: a response is served from the cache for
+//! `fresh_for`, and for another `stale_for` while a background thread renders
+//! it again. Only after that do requests wait for a new rendering.
+//!
+//! The cache is in-process by default. A shared store like Redis can be
+//! plugged in by implementing `CacheStore`.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use diesel::PgConnection;
+use parking_lot::Mutex;
+
+use crate::util::errors::AppResult;
+use crate::App;
+
+/// The key of the `GET /summary` response.
+pub const SUMMARY_KEY: &str = "summary";
+
+/// The key of the response of the given crate endpoint, e.g. `show` or
+/// `versions`.
+pub fn crate_key(crate_name: &str, endpoint: &str) -> String {
+    format!("crate:{}/{}", crate_name, endpoint)
+}
+
+/// A rendered response body.
+#[derive(Clone, Debug)]
+pub struct CachedResponse {
+    pub version: String,
+    pub body: Arc<String>,
+    pub rendered_at: SystemTime,
+}
+
+/// Where cached responses are kept.
+pub trait CacheStore: Send + Sync {
+    fn get(&self, key: &str) -> Option<CachedResponse>;
+    fn insert(&self, key: String, response: CachedResponse);
+    /// Removes all entries whose key starts with `prefix`.
+    fn invalidate(&self, prefix: &str);
+}
+
+/// Keeps cached responses in memory. Like `TtlCache`, the whole store is
+/// cleared when it is full, which is rare enough for the few endpoints using
+/// it.
+#[derive(Debug)]
+pub struct InMemoryStore {
+    capacity: usize,
+    entries: Mutex<HashMap<String, CachedResponse>>,
+}
+
+impl InMemoryStore {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl CacheStore for InMemoryStore {
+    fn get(&self, key: &str) -> Option<CachedResponse> {
+        self.entries.lock().get(key).cloned()
+    }
+
+    fn insert(&self, key: String, response: CachedResponse) {
+        let mut entries = self.entries.lock();
+        if entries.len() >= self.capacity && !entries.contains_key(&key) {
+            entries.clear();
+        }
+        entries.insert(key, response);
+    }
+
+    fn invalidate(&self, prefix: &str) {
+        self.entries
+            .lock()
+            .retain(|key, _| !key.starts_with(prefix));
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ResponseCacheConfig {
+    pub fresh_for: Duration,
+    pub stale_for: Duration,
+}
+
+impl ResponseCacheConfig {
+    /// Reads `RESPONSE_CACHE_FRESH_SECONDS` and
+    /// `RESPONSE_CACHE_STALE_SECONDS`, which default to 30 seconds and 5
+    /// minutes.
+    pub fn from_environment() -> Self {
+        let seconds = |var, default| {
+            let seconds = dotenv::var(var)
+                .map(|s| {
+                    s.parse()
+                        .unwrap_or_else(|_| panic!("{} must be a number", var))
+                })
+                .unwrap_or(default);
+            Duration::from_secs(seconds)
+        };
+        Self {
+            fresh_for: seconds("RESPONSE_CACHE_FRESH_SECONDS", 30),
+            stale_for: seconds("RESPONSE_CACHE_STALE_SECONDS", 5 * 60),
+        }
+    }
+
+    /// Whether a response rendered at `rendered_at` is still fresh, or may
+    /// be served while it's rendered again.
+    fn freshness(&self, rendered_at: SystemTime, now: SystemTime) -> Freshness {
+        let age = now.duration_since(rendered_at).unwrap_or_default();
+        if age < self.fresh_for {
+            Freshness::Fresh
+        } else if age < self.fresh_for + self.stale_for {
+            Freshness::Stale
+        } else {
+            Freshness::Expired
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Freshness {
+    Fresh,
+    Stale,
+    Expired,
+}
+
+#[allow(missing_debug_implementations)]
+pub struct ResponseCache {
+    config: ResponseCacheConfig,
+    store: Arc<dyn CacheStore>,
+    /// The keys which are currently rendered by a background thread.
+    revalidating: Arc<Mutex<HashSet<String>>>,
+}
+
+impl ResponseCache {
+    pub fn new(config: ResponseCacheConfig, store: Arc<dyn CacheStore>) -> Self {
+        Self {
+            config,
+            store,
+            revalidating: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// Returns the cached response body for `key` if it was rendered from the
+    /// given version of the data, and renders it with `conn` otherwise.
+    ///
+    /// Stale responses are revalidated with a connection of the read-only
+    /// replica, or the primary database if there is none.
+    pub fn fetch<F>(
+        &self,
+        app: &App,
+        conn: &PgConnection,
+        key: String,
+        version: String,
+        render: F,
+    ) -> AppResult<Arc<String>>
+    where
+        F: Fn(&PgConnection) -> AppResult<String> + Send + 'static,
+    {
+        if let Some(cached) = self.store.get(&key) {
+            if cached.version == version {
+                match self.config.freshness(cached.rendered_at, SystemTime::now()) {
+                    Freshness::Fresh => return Ok(cached.body),
+                    Freshness::Stale => {
+                        self.revalidate(app, key, version, render);
+                        return Ok(cached.body);
+                    }
+                    Freshness::Expired => {}
+                }
+            }
+        }
+
+        let body = Arc::new(render(conn)?);
+        self.store.insert(
+            key,
+            CachedResponse {
+                version,
+                body: Arc::clone(&body),
+                rendered_at: SystemTime::now(),
+            },
+        );
+        Ok(body)
+    }
+
+    fn revalidate<F>(&self, app: &App, key: String, version: String, render: F)
+    where
+        F: Fn(&PgConnection) -> AppResult<String> + Send + 'static,
+    {
+        if !self.revalidating.lock().insert(key.clone()) {
+            return;
+        }
+
+        let pool = app
+            .read_only_replica_database
+            .as_ref()
+            .unwrap_or(&app.primary_database)
+            .clone();
+        let store = Arc::clone(&self.store);
+        let revalidating = Arc::clone(&self.revalidating);
+        thread::spawn(move || {
+            let rendered = match pool.get() {
+                Ok(conn) => render(&*conn).map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+            match rendered {
+                Ok(body) => store.insert(
+                    key.clone(),
+                    CachedResponse {
+                        version,
+                        body: Arc::new(body),
+                        rendered_at: SystemTime::now(),
+                    },
+                ),
+                Err(e) => eprintln!("Revalidating the cached response {} failed: {}", key, e),
+            }
+            revalidating.lock().remove(&key);
+        });
+    }
+
+    /// Removes all cached responses of a crate, and the summary which may
+    /// list it. Called whenever a crate is published or yanked. This only
+    /// affects the cache of the current process, other ones notice the change
+    /// through the version of the responses.
+    pub fn invalidate_crate(&self, crate_name: &str) {
+        self.store.invalidate(&crate_key(crate_name, ""));
+        self.store.invalidate(SUMMARY_KEY);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn freshness() {
+        let config = ResponseCacheConfig {
+            fresh_for: Duration::from_secs(30),
+            stale_for: Duration::from_secs(60),
+        };
+        let now = SystemTime::now();
+        let rendered = |seconds_ago| now - Duration::from_secs(seconds_ago);
+        assert_eq!(config.freshness(rendered(0), now), Freshness::Fresh);
+        assert_eq!(config.freshness(rendered(29), now), Freshness::Fresh);
+        assert_eq!(config.freshness(rendered(30), now), Freshness::Stale);
+        assert_eq!(config.freshness(rendered(89), now), Freshness::Stale);
+        assert_eq!(config.freshness(rendered(90), now), Freshness::Expired);
+        // Clocks may go backwards
+        assert_eq!(
+            config.freshness(now + Duration::from_secs(5), now),
+            Freshness::Fresh
+        );
+    }
+
+    #[test]
+    fn invalidation_is_per_crate() {
+        let store = InMemoryStore::new(10);
+        let response = CachedResponse {
+            version: "1".into(),
+            body: Arc::new("{}".into()),
+            rendered_at: SystemTime::now(),
+        };
+        for key in &[crate_key("foo", "show"), crate_key("foobar", "show")] {
+            store.insert(key.clone(), response.clone());
+        }
+        store.insert(SUMMARY_KEY.into(), response);
+
+        store.invalidate(&crate_key("foo", ""));
+        assert!(store.get(&crate_key("foo", "show")).is_none());
+        assert!(store.get(&crate_key("foobar", "show")).is_some());
+        assert!(store.get(SUMMARY_KEY).is_some());
+    }
+}
@@ -4,6 +4,7 @@ use conduit::{Handler, Request, Response};
 use conduit_router::{RequestParams, RouteBuilder};
 
 use crate::controllers::*;
+use crate::i18n::Locale;
 use crate::util::errors::{std_error, AppError, AppResult, NotFound};
 use crate::util::RequestProxy;
 use crate::{middleware, App, Env};
@@ -24,6 +25,7 @@ pub fn build_router(app: &App) -> R404 {
         "/crates/:crate_id/:version/unyank",
         C(version::yank::unyank),
     );
+    api_router.post("/crates/:crate_id/yank_bulk", C(version::yank::yank_bulk));
     api_router.get(
         "/crates/:crate_id/:version/download",
         C(version::downloads::download),
@@ -35,11 +37,21 @@ pub fn build_router(app: &App) -> R404 {
 
     // Routes used by the frontend
     api_router.get("/crates/:crate_id", C(krate::metadata::show));
+    api_router.get("/crates/:crate_id/full", C(krate::metadata::full));
     api_router.get("/crates/:crate_id/:version", C(version::metadata::show));
     api_router.get(
         "/crates/:crate_id/:version/readme",
         C(krate::metadata::readme),
     );
+    api_router.get("/crates/:crate_id/:version/sbom", C(krate::metadata::sbom));
+    api_router.get(
+        "/crates/:crate_id/:version/signature",
+        C(version::signature::show),
+    );
+    api_router.put(
+        "/crates/:crate_id/:version/signature",
+        C(version::signature::upload),
+    );
     api_router.get(
         "/crates/:crate_id/:version/dependencies",
         C(version::metadata::dependencies),
@@ -48,18 +60,91 @@ pub fn build_router(app: &App) -> R404 {
         "/crates/:crate_id/:version/downloads",
         C(version::downloads::downloads),
     );
+    api_router.get(
+        "/crates/:crate_id/:version/features",
+        C(version::metadata::features),
+    );
+    api_router.get(
+        "/crates/:crate_id/:version/files",
+        C(version::metadata::files),
+    );
+    api_router.get(
+        "/crates/:crate_id/:version/readme_toc",
+        C(version::metadata::readme_toc),
+    );
     api_router.get(
         "/crates/:crate_id/:version/authors",
         C(version::metadata::authors),
     );
+    api_router.get(
+        "/crates/:crate_id/:version/yank_history",
+        C(version::yank::history),
+    );
+    api_router.get(
+        "/crates/:crate_id/:version/publish-status",
+        C(krate::publish::publish_status),
+    );
     api_router.get(
         "/crates/:crate_id/downloads",
         C(krate::downloads::downloads),
     );
+    api_router.get(
+        "/crates/:crate_id/download_anomalies",
+        C(krate::downloads::anomalies),
+    );
     api_router.get("/crates/:crate_id/versions", C(krate::metadata::versions));
+    api_router.get("/crates/:crate_id/og_image", C(krate::metadata::og_image));
+    api_router.get("/crates/:crate_id/policy", C(krate::metadata::policy));
+    api_router.get("/crates/:crate_id/funding", C(krate::metadata::funding));
+    api_router.get(
+        "/crates/:crate_id/release_stats",
+        C(krate::metadata::release_stats),
+    );
+    api_router.get(
+        "/crates/:crate_id/badges/downloads",
+        C(krate::badges::downloads),
+    );
+    api_router.get(
+        "/crates/:crate_id/badges/version",
+        C(krate::badges::version),
+    );
+    api_router.get(
+        "/crates/:crate_id/badges/license",
+        C(krate::badges::license),
+    );
+    api_router.get("/crates/:crate_id/badges/msrv", C(krate::badges::msrv));
+    api_router.put(
+        "/crates/:crate_id/custom_links",
+        C(krate::custom_links::update),
+    );
+    api_router.get(
+        "/crates/:crate_id/custom_links/history",
+        C(krate::custom_links::history),
+    );
+    api_router.put(
+        "/crates/:crate_id/squatting_report",
+        C(krate::squatting::report),
+    );
+    api_router.get("/crates/:crate_id/history", C(krate::history::history));
+    api_router.get(
+        "/crates/:crate_id/dependency_cycles",
+        C(krate::dependency_cycles::list),
+    );
     api_router.put("/crates/:crate_id/follow", C(krate::follow::follow));
     api_router.delete("/crates/:crate_id/follow", C(krate::follow::unfollow));
     api_router.get("/crates/:crate_id/following", C(krate::follow::following));
+    api_router.get(
+        "/crates/:crate_id/dependency_updates",
+        C(krate::dependency_updates::subscribed),
+    );
+    api_router.put(
+        "/crates/:crate_id/dependency_updates",
+        C(krate::dependency_updates::subscribe),
+    );
+    api_router.delete(
+        "/crates/:crate_id/dependency_updates",
+        C(krate::dependency_updates::unsubscribe),
+    );
     api_router.get("/crates/:crate_id/owner_team", C(krate::owners::owner_team));
     api_router.get("/crates/:crate_id/owner_user", C(krate::owners::owner_user));
     api_router.get(
@@ -74,9 +159,13 @@ pub fn build_router(app: &App) -> R404 {
     api_router.get("/users/:user_id", C(user::other::show));
     api_router.put("/users/:user_id", C(user::me::update_user));
     api_router.get("/users/:user_id/stats", C(user::other::stats));
+    api_router.get("/users/:user_id/publishes", C(user::other::publishes));
     api_router.get("/teams/:team_id", C(team::show_team));
+    api_router.get("/orgs/:org/domains", C(org::domains));
+    api_router.put("/orgs/:org/domains", C(org::request_domain));
     api_router.get("/me", C(user::me::me));
     api_router.get("/me/updates", C(user::me::updates));
+    api_router.get("/me/dashboard", C(user::me::dashboard));
     api_router.get("/me/tokens", C(token::list));
     api_router.put("/me/tokens", C(token::new));
     api_router.delete("/me/tokens/:id", C(token::revoke));
@@ -97,12 +186,28 @@ pub fn build_router(app: &App) -> R404 {
         C(user::me::update_email_notifications),
     );
     api_router.get("/summary", C(krate::metadata::summary));
+    api_router.get("/stats/ecosystem", C(stats::ecosystem));
+    api_router.get("/index-checksums", C(krate::metadata::index_checksums));
+    api_router.get("/yanks", C(version::yank::index));
+    api_router.get("/deleted_crates", C(krate::deleted::index));
     api_router.put("/confirm/:email_token", C(user::me::confirm_user_email));
     api_router.put(
         "/users/:user_id/resend",
         C(user::me::regenerate_token_and_send),
     );
     api_router.get("/site_metadata", C(site_metadata::show_deployed_sha));
+    api_router.post("/partners/verdicts", C(partner::submit_verdict));
+    api_router.get(
+        "/transparency-log/tree-head",
+        C(transparency_log::tree_head),
+    );
+    api_router.get("/transparency-log/entries", C(transparency_log::entries));
+    api_router.get("/index-config", C(index_config::show));
+    api_router.get("/index-signing-keys", C(index_signing::keys));
+    api_router.get(
+        "/index-signing-keys/allowed_signers",
+        C(index_signing::allowed_signers),
+    );
     let api_router = Arc::new(R404(api_router));
 
     let mut router = RouteBuilder::new();
@@ -115,6 +220,9 @@ pub fn build_router(app: &App) -> R404 {
     router.head("/api/v1/*path", R(Arc::clone(&api_router)));
     router.delete("/api/v1/*path", R(api_router));
 
+    // Crawlers only know this file from robots.txt, so it can't live below /api
+    router.get("/sitemap.xml", C(sitemap::index));
+
     // Session management
     router.get("/api/private/session/begin", C(user::session::begin));
     router.get(
@@ -123,6 +231,23 @@ pub fn build_router(app: &App) -> R404 {
     );
     router.delete("/api/private/session", C(user::session::logout));
 
+    // Admin API
+    router.put(
+        "/api/private/admin/crates/:crate_id/upload-limit",
+        C(admin::set_upload_limit),
+    );
+    router.delete(
+        "/api/private/admin/crates/:crate_id/upload-limit",
+        C(admin::remove_upload_limit),
+    );
+    router.get("/api/private/admin/config", C(admin::config));
+    router.put(
+        "/api/private/admin/index-config",
+        C(admin::set_index_config),
+    );
+
+    router.get("/api/private/metrics/index-sync", C(metrics::index_sync));
+
     // Only serve the local checkout of the git index in development mode.
     // In production, for crates.io, cargo gets the index from
     // https://github.com/rust-lang/crates.io-index directly.
@@ -147,7 +272,7 @@ impl Handler for C {
                 if let Some(cause) = e.cause() {
                     req.log_metadata("cause", cause.to_string())
                 };
-                match e.response() {
+                match e.localized_response(Locale::from_request(req)) {
                     Some(response) => Ok(response),
                     None => Err(std_error(e)),
                 }
@@ -178,7 +303,9 @@ impl Handler for R404 {
                 req.mut_extensions().insert(m.params.clone());
                 m.handler.call(req)
             }
-            Err(..) => Ok(NotFound.response().unwrap()),
+            Err(..) => Ok(NotFound
+                .localized_response(Locale::from_request(req))
+                .unwrap()),
         }
     }
 }
@@ -255,4 +382,33 @@ mod tests {
                 .is_err()
         );
     }
+
+    #[test]
+    fn localized_error_responses() {
+        let mut req = MockRequest::new(::conduit::Method::Get, "/");
+        req.header("Accept-Language", "x-pseudo, en;q=0.5");
+
+        let mut response = C(|_| err(Unauthorized)).call(&mut req).unwrap();
+        assert_eq!(response.status.0, 403);
+        assert_eq!(response.headers["Content-Language"], vec!["x-pseudo"]);
+        assert_eq!(response.headers["Vary"], vec!["Accept-Language"]);
+
+        let mut body = Vec::new();
+        response.body.write_body(&mut body).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            json,
+            json!({ "errors": [{
+                "detail": "[múst bé lóggéd ín tó pérfórm thát áctíón]",
+                "code": "unauthorized",
+            }] })
+        );
+
+        // Errors outside of the catalog are not translated and have no code
+        let mut response = C(|_| Err(bad_request("oops"))).call(&mut req).unwrap();
+        let mut body = Vec::new();
+        response.body.write_body(&mut body).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json, json!({ "errors": [{ "detail": "oops" }] }));
+    }
 }
@@ -71,6 +71,25 @@ impl Bucket {
             .map_err(Into::into)
     }
 
+    pub fn get(&self, client: &Client, path: &str) -> Result<Response, Error> {
+        let path = if path.starts_with('/') {
+            &path[1..]
+        } else {
+            path
+        };
+        let date = Utc::now().to_rfc2822();
+        let auth = self.auth("GET", &date, path, "", "")?;
+        let url = self.url(path);
+
+        client
+            .get(&url)
+            .header(header::DATE, date)
+            .header(header::AUTHORIZATION, auth)
+            .send()?
+            .error_for_status()
+            .map_err(Into::into)
+    }
+
     pub fn delete(&self, client: &Client, path: &str) -> Result<Response, Error> {
         let path = if path.starts_with('/') {
             &path[1..]
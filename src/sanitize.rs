@@ -0,0 +1,570 @@
+//! Sanitization of user-supplied HTML.
+//!
+//! Every surface that renders user-authored rich text (READMEs, yank reasons,
+//! ...) must pass the generated HTML through a [`Sanitizer`] before it is
+//! stored or served. Two policies are available:
+//!
+//! - [`Sanitizer::document`] for full documents like READMEs, which allows
+//!   headings, tables, images, etc.
+//! - [`Sanitizer::inline`] for short messages like yank reasons, which only
+//!   allows a handful of inline formatting tags and absolute links.
+
+use ammonia::{Builder, UrlRelative, UrlRelativeEvaluate};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+use url::percent_encoding::{utf8_percent_encode, PATH_SEGMENT_ENCODE_SET};
+use url::Url;
+
+/// The `rel` attribute added to all links in sanitized output.
+const LINK_REL: &str = "nofollow noopener noreferrer";
+
+/// The prefix added to all `id` attributes, so that user content can't
+/// clash with the ids used by the website itself.
+pub const ID_PREFIX: &str = "user-content-";
+
+/// The tags allowed by the `inline` policy.
+const INLINE_TAGS: &[&str] = &["a", "b", "code", "del", "em", "i", "s", "strong"];
+
+/// A callback used to rewrite the `src` of absolute image URLs, e.g. to serve
+/// them through an image proxy. Returning `None` removes the attribute.
+pub type ImageUrlRewriter = Arc<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+/// An HTML sanitizer configured with one of the policies of this module.
+#[allow(missing_debug_implementations)]
+pub struct Sanitizer {
+    builder: Builder<'static>,
+}
+
+impl Sanitizer {
+    /// Creates a sanitizer for full documents like READMEs.
+    ///
+    /// Relative URLs are resolved against `base_url` if it points to a
+    /// repository on github.com, gitlab.com or bitbucket.org, and are
+    /// removed otherwise. Fragment URLs are always kept. They point to the
+    /// files at `vcs_ref`, usually the commit the crate was published from,
+    /// or on the default branch of the repository if it's `None`.
+    pub fn document(base_url: Option<&str>, vcs_ref: Option<&str>) -> Self {
+        let allowed_classes = hashmap(&[(
+            "code",
+            hashset(&[
+                "language-bash",
+                "language-clike",
+                "language-glsl",
+                "language-go",
+                "language-ini",
+                "language-javascript",
+                "language-json",
+                "language-markup",
+                "language-protobuf",
+                "language-ruby",
+                "language-rust",
+                "language-scss",
+                "language-sql",
+                "yaml",
+            ]),
+        )]);
+        let sanitize_url = UrlRelative::Custom(Box::new(SanitizeUrl::new(base_url, vcs_ref)));
+
+        let mut builder = Builder::default();
+        builder
+            .add_tags(&["input"])
+            .link_rel(Some(LINK_REL))
+            .add_tag_attributes("a", &["id", "target"])
+            .add_tag_attributes("input", &["checked", "disabled", "type"])
+            .allowed_classes(allowed_classes)
+            .url_relative(sanitize_url)
+            .id_prefix(Some(ID_PREFIX));
+        Sanitizer { builder }
+    }
+
+    /// Creates a sanitizer for short, single paragraph messages.
+    ///
+    /// Only basic inline formatting and absolute links are kept. Everything
+    /// else, including images, is stripped from the output.
+    pub fn inline() -> Self {
+        let mut builder = Builder::default();
+        builder
+            .tags(hashset(INLINE_TAGS))
+            .tag_attributes(hashmap(&[("a", hashset(&["href"]))]))
+            .generic_attributes(HashSet::new())
+            .link_rel(Some(LINK_REL))
+            .url_relative(UrlRelative::Deny);
+        Sanitizer { builder }
+    }
+
+    /// Rewrites the `src` attribute of all images with an absolute URL.
+    ///
+    /// Relative image URLs are not passed to `rewrite`, since they have not
+    /// been resolved against the base URL at that point.
+    ///
+    /// # Panics
+    ///
+    /// If called more than once on the same sanitizer.
+    pub fn rewrite_image_urls(&mut self, rewrite: ImageUrlRewriter) -> &mut Self {
+        self.builder
+            .attribute_filter(
+                move |element, attribute, value| match (element, attribute) {
+                    ("img", "src") if is_absolute_url(value) => rewrite(value).map(Cow::Owned),
+                    _ => Some(Cow::Borrowed(value)),
+                },
+            );
+        self
+    }
+
+    /// Sanitizes the given HTML according to the configured policy.
+    pub fn clean(&self, html: &str) -> String {
+        self.builder.clean(html).to_string()
+    }
+}
+
+/// Rewrites image URLs so that they are loaded through an image proxy.
+///
+/// The original URL is percent-encoded and appended to the configured prefix,
+/// e.g. `https://images.example.com/?url=`.
+#[derive(Clone, Debug)]
+pub struct ImageProxy {
+    prefix: String,
+}
+
+impl ImageProxy {
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+        }
+    }
+
+    /// Reads the proxy prefix from the `IMAGE_PROXY_URL` environment variable.
+    pub fn from_environment() -> Option<Self> {
+        dotenv::var("IMAGE_PROXY_URL")
+            .ok()
+            .filter(|prefix| !prefix.is_empty())
+            .map(Self::new)
+    }
+
+    pub fn proxy_url(&self, url: &str) -> String {
+        let encoded: String = url::form_urlencoded::byte_serialize(url.as_bytes()).collect();
+        format!("{}{}", self.prefix, encoded)
+    }
+
+    /// Returns a rewriter for [`Sanitizer::rewrite_image_urls`].
+    ///
+    /// Only `http` and `https` URLs are proxied. Images using any other
+    /// scheme are dropped.
+    pub fn into_rewriter(self) -> ImageUrlRewriter {
+        Arc::new(move |url| match Url::parse(url) {
+            Ok(ref parsed) if parsed.scheme() == "http" || parsed.scheme() == "https" => {
+                Some(self.proxy_url(url))
+            }
+            _ => None,
+        })
+    }
+}
+
+fn is_absolute_url(url: &str) -> bool {
+    Url::parse(url).is_ok()
+}
+
+/// Add trailing slash and remove `.git` suffix of base URL.
+fn canon_base_url(mut base_url: String) -> String {
+    if !base_url.ends_with('/') {
+        base_url.push('/');
+    }
+    if base_url.ends_with(".git/") {
+        let offset = base_url.len() - 5;
+        base_url.drain(offset..offset + 4);
+    }
+    base_url
+}
+
+/// The hosts whose URL schemes relative links are resolved with.
+#[derive(Clone, Copy)]
+enum RepositoryHost {
+    GitHub,
+    GitLab,
+    Bitbucket,
+}
+
+impl RepositoryHost {
+    fn from_url(url: &Url) -> Option<Self> {
+        match url.host_str() {
+            Some("github.com") => Some(RepositoryHost::GitHub),
+            Some("gitlab.com") => Some(RepositoryHost::GitLab),
+            Some("bitbucket.org") => Some(RepositoryHost::Bitbucket),
+            _ => None,
+        }
+    }
+
+    /// The path segments leading to the rendered and the raw view of a file.
+    fn views(self) -> (&'static str, &'static str) {
+        match self {
+            RepositoryHost::GitHub => ("blob", "raw"),
+            RepositoryHost::GitLab => ("-/blob", "-/raw"),
+            RepositoryHost::Bitbucket => ("src", "raw"),
+        }
+    }
+}
+
+/// The URL of the rendered view of a file in a repository on one of the
+/// hosts relative links are resolved for, at `vcs_ref` or else at the default
+/// branch.
+pub fn repository_file_url(repository: &str, vcs_ref: Option<&str>, path: &str) -> Option<String> {
+    let url = Url::parse(repository).ok()?;
+    let host = RepositoryHost::from_url(&url)?;
+    let vcs_ref = vcs_ref.filter(|r| is_plain_ref(r)).unwrap_or("HEAD");
+    let path = path
+        .split('/')
+        .map(|segment| utf8_percent_encode(segment, PATH_SEGMENT_ENCODE_SET).to_string())
+        .collect::<Vec<_>>()
+        .join("/");
+    Some(format!(
+        "{}{}/{}/{}",
+        canon_base_url(url.into_string()),
+        host.views().0,
+        vcs_ref,
+        path
+    ))
+}
+
+/// Whether `vcs_ref` looks like a commit or branch name which can be put into
+/// a URL as it is.
+fn is_plain_ref(vcs_ref: &str) -> bool {
+    !vcs_ref.is_empty()
+        && !vcs_ref.contains("..")
+        && vcs_ref
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '_' || c == '-' || c == '/')
+}
+
+/// Sanitize relative URLs in README files.
+struct SanitizeUrl {
+    base_url: Option<(String, RepositoryHost)>,
+    /// All of the hosts resolve `HEAD` to the default branch.
+    vcs_ref: String,
+}
+
+impl SanitizeUrl {
+    fn new(base_url: Option<&str>, vcs_ref: Option<&str>) -> Self {
+        let base_url = base_url
+            .and_then(|base_url| Url::parse(base_url).ok())
+            .and_then(|url| {
+                let host = RepositoryHost::from_url(&url)?;
+                Some((canon_base_url(url.into_string()), host))
+            });
+        let vcs_ref = vcs_ref.filter(|r| is_plain_ref(r)).unwrap_or("HEAD");
+        Self {
+            base_url,
+            vcs_ref: vcs_ref.to_string(),
+        }
+    }
+}
+
+/// Groups media-related URL info
+struct MediaUrl {
+    is_media: bool,
+    add_sanitize_query: bool,
+}
+
+/// Determine whether the given URL has a media file externsion.
+/// Also check if `sanitize=true` must be added to the query string,
+/// which is required to load SVGs properly from GitHub.
+fn is_media_url(url: &str) -> MediaUrl {
+    Path::new(url)
+        .extension()
+        .and_then(std::ffi::OsStr::to_str)
+        .map_or(
+            MediaUrl {
+                is_media: false,
+                add_sanitize_query: false,
+            },
+            |e| match e {
+                "svg" => MediaUrl {
+                    is_media: true,
+                    add_sanitize_query: true,
+                },
+                "png" | "jpg" | "jpeg" | "gif" | "mp4" | "webm" | "ogg" => MediaUrl {
+                    is_media: true,
+                    add_sanitize_query: false,
+                },
+                _ => MediaUrl {
+                    is_media: false,
+                    add_sanitize_query: false,
+                },
+            },
+        )
+}
+
+impl UrlRelativeEvaluate for SanitizeUrl {
+    fn evaluate<'a>(&self, url: &'a str) -> Option<Cow<'a, str>> {
+        if url.starts_with('#') {
+            // Always allow fragment URLs.
+            return Some(Cow::Borrowed(url));
+        }
+        self.base_url.as_ref().map(|(base_url, host)| {
+            let mut new_url = base_url.clone();
+            // Text and markdown are rendered better in the "blob" view, but
+            // images need to be served raw.
+            let MediaUrl {
+                is_media,
+                add_sanitize_query,
+            } = is_media_url(url);
+            let (blob, raw) = host.views();
+            new_url += if is_media { raw } else { blob };
+            new_url.push('/');
+            new_url += &self.vcs_ref;
+            if !url.starts_with('/') {
+                new_url.push('/');
+            }
+            new_url += url;
+            if add_sanitize_query {
+                if let Ok(mut parsed_url) = Url::parse(&new_url) {
+                    parsed_url.query_pairs_mut().append_pair("sanitize", "true");
+                    new_url = parsed_url.into_string();
+                }
+            }
+            Cow::Owned(new_url)
+        })
+    }
+}
+
+/// Helper function to build a new `HashSet` from the items slice.
+fn hashset<T>(items: &[T]) -> HashSet<T>
+where
+    T: Clone + Eq + std::hash::Hash,
+{
+    items.iter().cloned().collect()
+}
+
+/// Helper function to build a new `HashMap` from a slice of key-value pairs.
+fn hashmap<K, V>(items: &[(K, V)]) -> HashMap<K, V>
+where
+    K: Clone + Eq + std::hash::Hash,
+    V: Clone,
+{
+    items.iter().cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    /// Fragments that are commonly used to smuggle markup or scripts past
+    /// sanitizers. The fuzz tests below glue them together randomly.
+    const FRAGMENTS: &[&str] = &[
+        "<",
+        ">",
+        "</",
+        "/>",
+        "\"",
+        "'",
+        "`",
+        "=",
+        " ",
+        "\n",
+        "&",
+        "&lt;",
+        "&#60;",
+        "&#x3C;",
+        "<!--",
+        "-->",
+        "<![CDATA[",
+        "]]>",
+        "<script>",
+        "</script>",
+        "<scr<script>ipt>",
+        "<svg",
+        "<math",
+        "<style>",
+        "<iframe",
+        "<object",
+        "<embed",
+        "<form",
+        "<img",
+        "<a",
+        "<p",
+        "<input",
+        "src=",
+        "href=",
+        "onerror=",
+        "onload=",
+        "onclick=",
+        "style=",
+        "srcdoc=",
+        "formaction=",
+        "javascript:",
+        "JaVaScRiPt:",
+        "java\tscript:",
+        "data:text/html,",
+        "vbscript:",
+        "alert(1)",
+        "x",
+        "https://example.com/a.png",
+        "#frag",
+    ];
+
+    fn random_input(rng: &mut StdRng) -> String {
+        let len = rng.gen_range(1, 40);
+        (0..len)
+            .map(|_| FRAGMENTS[rng.gen_range(0, FRAGMENTS.len())])
+            .collect()
+    }
+
+    /// A tag of the sanitized output, split into its name and attributes.
+    #[derive(Debug)]
+    struct Tag {
+        name: String,
+        attributes: Vec<(String, String)>,
+    }
+
+    /// Extracts all tags from sanitized HTML.
+    ///
+    /// This relies on the serializer always quoting attribute values with `"`
+    /// and escaping `<` in text, so it is not a general purpose HTML parser.
+    fn tags(html: &str) -> Vec<Tag> {
+        let mut tags = Vec::new();
+        let mut rest = html;
+        while let Some(start) = rest.find('<') {
+            rest = &rest[start + 1..];
+            let mut parts = Vec::new();
+            let mut in_value = false;
+            let mut current = String::new();
+            let mut end = rest.len();
+            for (i, c) in rest.char_indices() {
+                match c {
+                    '"' => {
+                        parts.push(std::mem::replace(&mut current, String::new()));
+                        in_value = !in_value;
+                    }
+                    '>' if !in_value => {
+                        end = i + 1;
+                        break;
+                    }
+                    _ => current.push(c),
+                }
+            }
+            parts.push(current);
+            rest = &rest[end.min(rest.len())..];
+
+            let mut head = parts[0].trim().splitn(2, char::is_whitespace);
+            let name = head.next().unwrap_or("").to_lowercase();
+            let mut attr_name = head.next().unwrap_or("").to_string();
+            let mut attributes = Vec::new();
+            for (i, part) in parts.into_iter().enumerate().skip(1) {
+                if i % 2 == 1 {
+                    let attr = attr_name.trim().trim_end_matches('=').to_lowercase();
+                    attributes.push((attr, part));
+                } else {
+                    attr_name = part;
+                }
+            }
+            tags.push(Tag { name, attributes });
+        }
+        tags
+    }
+
+    fn assert_harmless(input: &str, output: &str) {
+        for tag in tags(output) {
+            let name = tag.name.trim_start_matches('/');
+            for forbidden in &[
+                "script", "svg", "math", "style", "iframe", "object", "embed", "form",
+            ] {
+                assert_ne!(name, *forbidden, "input: {:?}\noutput: {:?}", input, output);
+            }
+            for (attr, value) in &tag.attributes {
+                assert!(
+                    !attr.starts_with("on")
+                        && !["style", "srcdoc", "formaction"].contains(&attr.as_str()),
+                    "found attribute {:?}\ninput: {:?}\noutput: {:?}",
+                    attr,
+                    input,
+                    output
+                );
+                let value: String = value
+                    .chars()
+                    .filter(|c| !c.is_whitespace() && !c.is_control())
+                    .collect::<String>()
+                    .to_lowercase();
+                for scheme in &["javascript:", "vbscript:", "data:"] {
+                    assert!(
+                        !value.starts_with(scheme),
+                        "found {:?} URL\ninput: {:?}\noutput: {:?}",
+                        scheme,
+                        input,
+                        output
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fuzz_document_policy() {
+        let sanitizer = Sanitizer::document(Some("https://github.com/rust-lang/crates.io"), None);
+        let mut rng = StdRng::seed_from_u64(0x5a17);
+        for _ in 0..5000 {
+            let input = random_input(&mut rng);
+            assert_harmless(&input, &sanitizer.clean(&input));
+        }
+    }
+
+    #[test]
+    fn fuzz_inline_policy() {
+        let sanitizer = Sanitizer::inline();
+        let mut rng = StdRng::seed_from_u64(0x1a11e);
+        for _ in 0..5000 {
+            let input = random_input(&mut rng);
+            let output = sanitizer.clean(&input);
+            assert_harmless(&input, &output);
+            for tag in tags(&output) {
+                let name = tag.name.trim_start_matches('/');
+                assert!(INLINE_TAGS.contains(&name), "unexpected tag {:?}", tag);
+            }
+        }
+    }
+
+    #[test]
+    fn inline_policy_strips_block_elements_and_images() {
+        let sanitizer = Sanitizer::inline();
+        let html = r#"<h1 id="x">Broken</h1><p>see <a href="https://example.com/" class="c">this</a> <img src="https://example.com/a.png"></p>"#;
+        assert_eq!(
+            sanitizer.clean(html),
+            r#"Brokensee <a href="https://example.com/" rel="nofollow noopener noreferrer">this</a> "#
+        );
+    }
+
+    #[test]
+    fn inline_policy_removes_relative_links() {
+        let sanitizer = Sanitizer::inline();
+        assert_eq!(
+            sanitizer.clean(r#"<a href="/me">me</a>"#),
+            r#"<a rel="nofollow noopener noreferrer">me</a>"#
+        );
+    }
+
+    #[test]
+    fn image_urls_are_rewritten() {
+        let mut sanitizer = Sanitizer::document(None, None);
+        sanitizer.rewrite_image_urls(ImageProxy::new("https://proxy.test/?url=").into_rewriter());
+
+        assert_eq!(
+            sanitizer.clean(r#"<img src="https://example.com/a b.png">"#),
+            r#"<img src="https://proxy.test/?url=https%3A%2F%2Fexample.com%2Fa+b.png">"#
+        );
+        assert_eq!(
+            sanitizer.clean(r##"<a href="https://example.com/"><img src="#x"></a>"##),
+            r##"<a href="https://example.com/" rel="nofollow noopener noreferrer"><img src="#x"></a>"##
+        );
+    }
+
+    #[test]
+    fn image_proxy_drops_unsupported_schemes() {
+        let rewrite = ImageProxy::new("https://proxy.test/?url=").into_rewriter();
+        assert_eq!(rewrite("ftp://example.com/a.png"), None);
+        assert_eq!(
+            rewrite("http://example.com/a.png").as_deref(),
+            Some("https://proxy.test/?url=http%3A%2F%2Fexample.com%2Fa.png")
+        );
+    }
+}
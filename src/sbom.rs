@@ -0,0 +1,145 @@
+//! Software bills of materials of published versions.
+//!
+//! The `generate_sbom` job describes a version and its declared dependencies
+//! as a CycloneDX 1.3 document, which is stored next to the crate file and
+//! served through `GET /api/v1/crates/:crate_id/:version/sbom`.
+//!
+//! The document is generated from the database instead of a lock file, so
+//! the dependencies are listed with their version requirements. Optional
+//! dependencies are marked as optional, and dev-dependencies as excluded,
+//! since they aren't part of builds of dependent crates.
+
+use diesel::prelude::*;
+use swirl::PerformError;
+
+use crate::background_jobs::Environment;
+use crate::models::krate;
+use crate::models::{Crate, Dependency, DependencyKind, Version};
+use crate::schema::{crates, versions};
+
+/// The package URL of a crate, see https://github.com/package-url/purl-spec.
+fn purl(crate_name: &str, version: Option<&str>) -> String {
+    match version {
+        Some(version) => format!("pkg:cargo/{}@{}", crate_name, version),
+        None => format!("pkg:cargo/{}", crate_name),
+    }
+}
+
+/// Builds the CycloneDX document of the version.
+pub fn build_sbom(
+    krate: &Crate,
+    version: &Version,
+    checksum: Option<&str>,
+    dependencies: &[(Dependency, String)],
+) -> serde_json::Value {
+    let num = version.num.to_string();
+    let root_ref = purl(&krate.name, Some(&num));
+
+    let mut component = json!({
+        "type": "library",
+        "bom-ref": root_ref,
+        "name": krate.name,
+        "version": num,
+        "purl": root_ref
+    });
+    if let Some(description) = &krate.description {
+        component["description"] = json!(description);
+    }
+    if let Some(license) = &version.license {
+        component["licenses"] = json!([{ "expression": license }]);
+    }
+    if let Some(checksum) = checksum {
+        component["hashes"] = json!([{ "alg": "SHA-256", "content": checksum }]);
+    }
+    let references = [
+        ("vcs", &krate.repository),
+        ("website", &krate.homepage),
+        ("documentation", &krate.documentation),
+    ]
+    .iter()
+    .filter_map(|&(kind, url)| url.as_ref().map(|url| json!({ "type": kind, "url": url })))
+    .collect::<Vec<_>>();
+    if !references.is_empty() {
+        component["externalReferences"] = json!(references);
+    }
+
+    let components = dependencies
+        .iter()
+        .map(|(dependency, crate_name)| {
+            let scope = match dependency.kind {
+                DependencyKind::Dev => "excluded",
+                _ if dependency.optional => "optional",
+                _ => "required",
+            };
+            let kind = match dependency.kind {
+                DependencyKind::Normal => "normal",
+                DependencyKind::Build => "build",
+                DependencyKind::Dev => "dev",
+            };
+            let mut properties = vec![json!({ "name": "cargo:kind", "value": kind })];
+            if let Some(target) = &dependency.target {
+                properties.push(json!({ "name": "cargo:target", "value": target }));
+            }
+            json!({
+                "type": "library",
+                "bom-ref": purl(crate_name, None),
+                "name": crate_name,
+                "version": dependency.req.to_string(),
+                "scope": scope,
+                "purl": purl(crate_name, None),
+                "properties": properties
+            })
+        })
+        .collect::<Vec<_>>();
+    let depends_on = dependencies
+        .iter()
+        .map(|(_, crate_name)| purl(crate_name, None))
+        .collect::<Vec<_>>();
+
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.3",
+        "version": 1,
+        "metadata": {
+            "timestamp": version.created_at.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            "tools": [{ "vendor": "crates.io", "name": "crates.io" }],
+            "component": component
+        },
+        "components": components,
+        "dependencies": [{ "ref": root_ref, "dependsOn": depends_on }]
+    })
+}
+
+#[swirl::background_job]
+pub fn generate_sbom(env: &Environment, version_id: i32) -> Result<(), PerformError> {
+    let _heartbeat = env.heartbeat("generate_sbom")?;
+    let conn = env.connection()?;
+
+    let (version, krate) = versions::table
+        .find(version_id)
+        .inner_join(crates::table)
+        .select((versions::all_columns, krate::ALL_COLUMNS))
+        .first::<(Version, Crate)>(&*conn)?;
+    let checksum = Version::checksum(version.id, &conn)?;
+    let dependencies = version.dependencies(&conn)?;
+
+    let sbom = build_sbom(&krate, &version, checksum.as_deref(), &dependencies);
+    env.uploader.upload_sbom(
+        env.http_client(),
+        &krate.name,
+        &version.num.to_string(),
+        serde_json::to_string_pretty(&sbom)?,
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn purls() {
+        assert_eq!(purl("foo", Some("1.0.0")), "pkg:cargo/foo@1.0.0");
+        assert_eq!(purl("foo", None), "pkg:cargo/foo");
+    }
+}
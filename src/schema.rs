@@ -53,6 +53,53 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `background_job_heartbeats` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    background_job_heartbeats (id) {
+        /// The `id` column of the `background_job_heartbeats` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `job_type` column of the `background_job_heartbeats` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        job_type -> Text,
+        /// The `worker` column of the `background_job_heartbeats` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        worker -> Text,
+        /// The `started_at` column of the `background_job_heartbeats` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        started_at -> Timestamp,
+        /// The `heartbeat_at` column of the `background_job_heartbeats` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        heartbeat_at -> Timestamp,
+        /// The `cancel_requested_at` column of the `background_job_heartbeats` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        cancel_requested_at -> Nullable<Timestamp>,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
@@ -97,6 +144,12 @@ table! {
         ///
         /// (Automatically generated by Diesel.)
         created_at -> Timestamp,
+        /// The `payload_version` column of the `background_jobs` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        payload_version -> Int4,
     }
 }
 
@@ -180,46 +233,28 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `crate_owner_invitations` table.
+    /// Representation of the `config_overrides` table.
     ///
     /// (Automatically generated by Diesel.)
-    crate_owner_invitations (invited_user_id, crate_id) {
-        /// The `invited_user_id` column of the `crate_owner_invitations` table.
-        ///
-        /// Its SQL type is `Int4`.
-        ///
-        /// (Automatically generated by Diesel.)
-        invited_user_id -> Int4,
-        /// The `invited_by_user_id` column of the `crate_owner_invitations` table.
+    config_overrides (name) {
+        /// The `name` column of the `config_overrides` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        invited_by_user_id -> Int4,
-        /// The `crate_id` column of the `crate_owner_invitations` table.
+        name -> Varchar,
+        /// The `value` column of the `config_overrides` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        crate_id -> Int4,
-        /// The `created_at` column of the `crate_owner_invitations` table.
+        value -> Varchar,
+        /// The `updated_at` column of the `config_overrides` table.
         ///
         /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        created_at -> Timestamp,
-        /// The `token` column of the `crate_owner_invitations` table.
-        ///
-        /// Its SQL type is `Text`.
-        ///
-        /// (Automatically generated by Diesel.)
-        token -> Text,
-        /// The `token_generated_at` column of the `crate_owner_invitations` table.
-        ///
-        /// Its SQL type is `Nullable<Timestamp>`.
-        ///
-        /// (Automatically generated by Diesel.)
-        token_generated_at -> Nullable<Timestamp>,
+        updated_at -> Timestamp,
     }
 }
 
@@ -227,58 +262,52 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `crate_owners` table.
+    /// Representation of the `crate_dependency_freshness` table.
     ///
     /// (Automatically generated by Diesel.)
-    crate_owners (crate_id, owner_id, owner_kind) {
-        /// The `crate_id` column of the `crate_owners` table.
+    crate_dependency_freshness (crate_id) {
+        /// The `crate_id` column of the `crate_dependency_freshness` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
         crate_id -> Int4,
-        /// The `owner_id` column of the `crate_owners` table.
+        /// The `version_id` column of the `crate_dependency_freshness` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        owner_id -> Int4,
-        /// The `created_at` column of the `crate_owners` table.
-        ///
-        /// Its SQL type is `Timestamp`.
-        ///
-        /// (Automatically generated by Diesel.)
-        created_at -> Timestamp,
-        /// The `created_by` column of the `crate_owners` table.
+        version_id -> Int4,
+        /// The `dependencies` column of the `crate_dependency_freshness` table.
         ///
-        /// Its SQL type is `Nullable<Int4>`.
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        created_by -> Nullable<Int4>,
-        /// The `deleted` column of the `crate_owners` table.
+        dependencies -> Int4,
+        /// The `outdated_dependencies` column of the `crate_dependency_freshness` table.
         ///
-        /// Its SQL type is `Bool`.
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        deleted -> Bool,
-        /// The `updated_at` column of the `crate_owners` table.
+        outdated_dependencies -> Int4,
+        /// The `releases_behind` column of the `crate_dependency_freshness` table.
         ///
-        /// Its SQL type is `Timestamp`.
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        updated_at -> Timestamp,
-        /// The `owner_kind` column of the `crate_owners` table.
+        releases_behind -> Int4,
+        /// The `score` column of the `crate_dependency_freshness` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Float4`.
         ///
         /// (Automatically generated by Diesel.)
-        owner_kind -> Int4,
-        /// The `email_notifications` column of the `crate_owners` table.
+        score -> Float4,
+        /// The `computed_at` column of the `crate_dependency_freshness` table.
         ///
-        /// Its SQL type is `Bool`.
+        /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        email_notifications -> Bool,
+        computed_at -> Timestamp,
     }
 }
 
@@ -286,82 +315,75 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `crates` table.
+    /// Representation of the `crate_funding_links` table.
     ///
     /// (Automatically generated by Diesel.)
-    crates (id) {
-        /// The `id` column of the `crates` table.
+    crate_funding_links (crate_id, url) {
+        /// The `crate_id` column of the `crate_funding_links` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        id -> Int4,
-        /// The `name` column of the `crates` table.
+        crate_id -> Int4,
+        /// The `platform` column of the `crate_funding_links` table.
         ///
         /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        name -> Varchar,
-        /// The `updated_at` column of the `crates` table.
-        ///
-        /// Its SQL type is `Timestamp`.
-        ///
-        /// (Automatically generated by Diesel.)
-        updated_at -> Timestamp,
-        /// The `created_at` column of the `crates` table.
+        platform -> Varchar,
+        /// The `url` column of the `crate_funding_links` table.
         ///
-        /// Its SQL type is `Timestamp`.
+        /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        created_at -> Timestamp,
-        /// The `downloads` column of the `crates` table.
+        url -> Varchar,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crate_link_events` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crate_link_events (id) {
+        /// The `id` column of the `crate_link_events` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        downloads -> Int4,
-        /// The `description` column of the `crates` table.
-        ///
-        /// Its SQL type is `Nullable<Varchar>`.
-        ///
-        /// (Automatically generated by Diesel.)
-        description -> Nullable<Varchar>,
-        /// The `homepage` column of the `crates` table.
-        ///
-        /// Its SQL type is `Nullable<Varchar>`.
-        ///
-        /// (Automatically generated by Diesel.)
-        homepage -> Nullable<Varchar>,
-        /// The `documentation` column of the `crates` table.
+        id -> Int4,
+        /// The `crate_id` column of the `crate_link_events` table.
         ///
-        /// Its SQL type is `Nullable<Varchar>`.
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        documentation -> Nullable<Varchar>,
-        /// The `readme` column of the `crates` table.
+        crate_id -> Int4,
+        /// The `user_id` column of the `crate_link_events` table.
         ///
-        /// Its SQL type is `Nullable<Varchar>`.
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        readme -> Nullable<Varchar>,
-        /// The `textsearchable_index_col` column of the `crates` table.
+        user_id -> Int4,
+        /// The `kind` column of the `crate_link_events` table.
         ///
-        /// Its SQL type is `Tsvector`.
+        /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        textsearchable_index_col -> Tsvector,
-        /// The `repository` column of the `crates` table.
+        kind -> Varchar,
+        /// The `url` column of the `crate_link_events` table.
         ///
         /// Its SQL type is `Nullable<Varchar>`.
         ///
         /// (Automatically generated by Diesel.)
-        repository -> Nullable<Varchar>,
-        /// The `max_upload_size` column of the `crates` table.
+        url -> Nullable<Varchar>,
+        /// The `created_at` column of the `crate_link_events` table.
         ///
-        /// Its SQL type is `Nullable<Int4>`.
+        /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        max_upload_size -> Nullable<Int4>,
+        created_at -> Timestamp,
     }
 }
 
@@ -369,45 +391,28 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `crates_categories` table.
+    /// Representation of the `crate_links` table.
     ///
     /// (Automatically generated by Diesel.)
-    crates_categories (crate_id, category_id) {
-        /// The `crate_id` column of the `crates_categories` table.
+    crate_links (crate_id, kind) {
+        /// The `crate_id` column of the `crate_links` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
         crate_id -> Int4,
-        /// The `category_id` column of the `crates_categories` table.
-        ///
-        /// Its SQL type is `Int4`.
-        ///
-        /// (Automatically generated by Diesel.)
-        category_id -> Int4,
-    }
-}
-
-table! {
-    use diesel::sql_types::*;
-    use diesel_full_text_search::{TsVector as Tsvector};
-
-    /// Representation of the `crates_keywords` table.
-    ///
-    /// (Automatically generated by Diesel.)
-    crates_keywords (crate_id, keyword_id) {
-        /// The `crate_id` column of the `crates_keywords` table.
+        /// The `kind` column of the `crate_links` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        crate_id -> Int4,
-        /// The `keyword_id` column of the `crates_keywords` table.
+        kind -> Varchar,
+        /// The `url` column of the `crate_links` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        keyword_id -> Int4,
+        url -> Varchar,
     }
 }
 
@@ -415,64 +420,52 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `dependencies` table.
+    /// Representation of the `crate_metadata_history` table.
     ///
     /// (Automatically generated by Diesel.)
-    dependencies (id) {
-        /// The `id` column of the `dependencies` table.
+    crate_metadata_history (id) {
+        /// The `id` column of the `crate_metadata_history` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
         id -> Int4,
-        /// The `version_id` column of the `dependencies` table.
+        /// The `crate_id` column of the `crate_metadata_history` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        version_id -> Int4,
-        /// The `crate_id` column of the `dependencies` table.
+        crate_id -> Int4,
+        /// The `user_id` column of the `crate_metadata_history` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        crate_id -> Int4,
-        /// The `req` column of the `dependencies` table.
+        user_id -> Int4,
+        /// The `field` column of the `crate_metadata_history` table.
         ///
         /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        req -> Varchar,
-        /// The `optional` column of the `dependencies` table.
-        ///
-        /// Its SQL type is `Bool`.
-        ///
-        /// (Automatically generated by Diesel.)
-        optional -> Bool,
-        /// The `default_features` column of the `dependencies` table.
-        ///
-        /// Its SQL type is `Bool`.
-        ///
-        /// (Automatically generated by Diesel.)
-        default_features -> Bool,
-        /// The `features` column of the `dependencies` table.
+        field -> Varchar,
+        /// The `old_value` column of the `crate_metadata_history` table.
         ///
-        /// Its SQL type is `Array<Text>`.
+        /// Its SQL type is `Jsonb`.
         ///
         /// (Automatically generated by Diesel.)
-        features -> Array<Text>,
-        /// The `target` column of the `dependencies` table.
+        old_value -> Jsonb,
+        /// The `new_value` column of the `crate_metadata_history` table.
         ///
-        /// Its SQL type is `Nullable<Varchar>`.
+        /// Its SQL type is `Jsonb`.
         ///
         /// (Automatically generated by Diesel.)
-        target -> Nullable<Varchar>,
-        /// The `kind` column of the `dependencies` table.
+        new_value -> Jsonb,
+        /// The `created_at` column of the `crate_metadata_history` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        kind -> Int4,
+        created_at -> Timestamp,
     }
 }
 
@@ -480,41 +473,957 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `emails` table.
+    /// Representation of the `crate_owner_invitations` table.
     ///
     /// (Automatically generated by Diesel.)
-    emails (id) {
-        /// The `id` column of the `emails` table.
+    crate_owner_invitations (invited_user_id, crate_id) {
+        /// The `invited_user_id` column of the `crate_owner_invitations` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        id -> Int4,
-        /// The `user_id` column of the `emails` table.
+        invited_user_id -> Int4,
+        /// The `invited_by_user_id` column of the `crate_owner_invitations` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        user_id -> Int4,
-        /// The `email` column of the `emails` table.
+        invited_by_user_id -> Int4,
+        /// The `crate_id` column of the `crate_owner_invitations` table.
         ///
-        /// Its SQL type is `Varchar`.
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        email -> Varchar,
-        /// The `verified` column of the `emails` table.
+        crate_id -> Int4,
+        /// The `created_at` column of the `crate_owner_invitations` table.
         ///
-        /// Its SQL type is `Bool`.
+        /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        verified -> Bool,
-        /// The `token` column of the `emails` table.
+        created_at -> Timestamp,
+        /// The `token` column of the `crate_owner_invitations` table.
         ///
         /// Its SQL type is `Text`.
         ///
         /// (Automatically generated by Diesel.)
         token -> Text,
-        /// The `token_generated_at` column of the `emails` table.
+        /// The `token_generated_at` column of the `crate_owner_invitations` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        token_generated_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crate_owners` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crate_owners (crate_id, owner_id, owner_kind) {
+        /// The `crate_id` column of the `crate_owners` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `owner_id` column of the `crate_owners` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        owner_id -> Int4,
+        /// The `created_at` column of the `crate_owners` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+        /// The `created_by` column of the `crate_owners` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_by -> Nullable<Int4>,
+        /// The `deleted` column of the `crate_owners` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        deleted -> Bool,
+        /// The `updated_at` column of the `crate_owners` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        updated_at -> Timestamp,
+        /// The `owner_kind` column of the `crate_owners` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        owner_kind -> Int4,
+        /// The `email_notifications` column of the `crate_owners` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        email_notifications -> Bool,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crate_pages` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crate_pages (crate_id) {
+        /// The `crate_id` column of the `crate_pages` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `payload` column of the `crate_pages` table.
+        ///
+        /// Its SQL type is `Jsonb`.
+        ///
+        /// (Automatically generated by Diesel.)
+        payload -> Jsonb,
+        /// The `generated_at` column of the `crate_pages` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        generated_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crate_policies` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crate_policies (crate_id) {
+        /// The `crate_id` column of the `crate_policies` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `version_id` column of the `crate_policies` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `replaced_by` column of the `crate_policies` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        replaced_by -> Nullable<Varchar>,
+        /// The `deprecation_message` column of the `crate_policies` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        deprecation_message -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crate_quality` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crate_quality (crate_id) {
+        /// The `crate_id` column of the `crate_quality` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `has_documentation` column of the `crate_quality` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        has_documentation -> Bool,
+        /// The `readme_length` column of the `crate_quality` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        readme_length -> Int4,
+        /// The `has_tests` column of the `crate_quality` table.
+        ///
+        /// Its SQL type is `Nullable<Bool>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        has_tests -> Nullable<Bool>,
+        /// The `releases_last_year` column of the `crate_quality` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        releases_last_year -> Int4,
+        /// The `yanked_ratio` column of the `crate_quality` table.
+        ///
+        /// Its SQL type is `Float4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        yanked_ratio -> Float4,
+        /// The `score` column of the `crate_quality` table.
+        ///
+        /// Its SQL type is `Float4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        score -> Float4,
+        /// The `computed_at` column of the `crate_quality` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        computed_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crate_release_stats` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crate_release_stats (crate_id) {
+        /// The `crate_id` column of the `crate_release_stats` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `releases` column of the `crate_release_stats` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        releases -> Int4,
+        /// The `avg_days_between_releases` column of the `crate_release_stats` table.
+        ///
+        /// Its SQL type is `Nullable<Float4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        avg_days_between_releases -> Nullable<Float4>,
+        /// The `longest_gap_days` column of the `crate_release_stats` table.
+        ///
+        /// Its SQL type is `Nullable<Float4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        longest_gap_days -> Nullable<Float4>,
+        /// The `releases_per_year` column of the `crate_release_stats` table.
+        ///
+        /// Its SQL type is `Float4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        releases_per_year -> Float4,
+        /// The `yank_rate` column of the `crate_release_stats` table.
+        ///
+        /// Its SQL type is `Float4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        yank_rate -> Float4,
+        /// The `computed_at` column of the `crate_release_stats` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        computed_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crate_security_policies` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crate_security_policies (crate_id) {
+        /// The `crate_id` column of the `crate_security_policies` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `version_id` column of the `crate_security_policies` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `path` column of the `crate_security_policies` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        path -> Varchar,
+        /// The `url` column of the `crate_security_policies` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        url -> Varchar,
+        /// The `updated_at` column of the `crate_security_policies` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crate_upload_limits` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crate_upload_limits (crate_id) {
+        /// The `crate_id` column of the `crate_upload_limits` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `max_upload_size` column of the `crate_upload_limits` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        max_upload_size -> Int4,
+        /// The `reason` column of the `crate_upload_limits` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        reason -> Varchar,
+        /// The `expires_at` column of the `crate_upload_limits` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        expires_at -> Nullable<Timestamp>,
+        /// The `created_by` column of the `crate_upload_limits` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_by -> Int4,
+        /// The `created_at` column of the `crate_upload_limits` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crates` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crates (id) {
+        /// The `id` column of the `crates` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `name` column of the `crates` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        name -> Varchar,
+        /// The `updated_at` column of the `crates` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        updated_at -> Timestamp,
+        /// The `created_at` column of the `crates` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+        /// The `downloads` column of the `crates` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        downloads -> Int4,
+        /// The `description` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        description -> Nullable<Varchar>,
+        /// The `homepage` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        homepage -> Nullable<Varchar>,
+        /// The `documentation` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        documentation -> Nullable<Varchar>,
+        /// The `readme` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        readme -> Nullable<Varchar>,
+        /// The `textsearchable_index_col` column of the `crates` table.
+        ///
+        /// Its SQL type is `Tsvector`.
+        ///
+        /// (Automatically generated by Diesel.)
+        textsearchable_index_col -> Tsvector,
+        /// The `repository` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        repository -> Nullable<Varchar>,
+        /// The `max_upload_size` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        max_upload_size -> Nullable<Int4>,
+        /// The `repository_last_commit_at` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        repository_last_commit_at -> Nullable<Timestamp>,
+        /// The `repository_checked_at` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        repository_checked_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crates_categories` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crates_categories (crate_id, category_id) {
+        /// The `crate_id` column of the `crates_categories` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `category_id` column of the `crates_categories` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        category_id -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crates_keywords` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crates_keywords (crate_id, keyword_id) {
+        /// The `crate_id` column of the `crates_keywords` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `keyword_id` column of the `crates_keywords` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        keyword_id -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `default_versions` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    default_versions (crate_id) {
+        /// The `crate_id` column of the `default_versions` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `version_id` column of the `default_versions` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `deleted_crates` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    deleted_crates (id) {
+        /// The `id` column of the `deleted_crates` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `name` column of the `deleted_crates` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        name -> Varchar,
+        /// The `version` column of the `deleted_crates` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version -> Nullable<Varchar>,
+        /// The `reason` column of the `deleted_crates` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        reason -> Varchar,
+        /// The `deleted_at` column of the `deleted_crates` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        deleted_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `dependencies` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    dependencies (id) {
+        /// The `id` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `version_id` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `crate_id` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `req` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        req -> Varchar,
+        /// The `optional` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        optional -> Bool,
+        /// The `default_features` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        default_features -> Bool,
+        /// The `features` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Array<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        features -> Array<Text>,
+        /// The `target` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        target -> Nullable<Varchar>,
+        /// The `kind` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        kind -> Int4,
+        /// The `explicit_name` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        explicit_name -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `dependency_cycles` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    dependency_cycles (id) {
+        /// The `id` column of the `dependency_cycles` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `crate_id` column of the `dependency_cycles` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `version_id` column of the `dependency_cycles` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `crates` column of the `dependency_cycles` table.
+        ///
+        /// Its SQL type is `Array<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crates -> Array<Text>,
+        /// The `kinds` column of the `dependency_cycles` table.
+        ///
+        /// Its SQL type is `Array<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        kinds -> Array<Text>,
+        /// The `created_at` column of the `dependency_cycles` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `dependency_update_notifications` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    dependency_update_notifications (id) {
+        /// The `id` column of the `dependency_update_notifications` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `user_id` column of the `dependency_update_notifications` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `crate_id` column of the `dependency_update_notifications` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `dependency_version_id` column of the `dependency_update_notifications` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        dependency_version_id -> Int4,
+        /// The `requirement` column of the `dependency_update_notifications` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        requirement -> Varchar,
+        /// The `created_at` column of the `dependency_update_notifications` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+        /// The `sent_at` column of the `dependency_update_notifications` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        sent_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `dependency_update_subscriptions` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    dependency_update_subscriptions (crate_id, user_id) {
+        /// The `crate_id` column of the `dependency_update_subscriptions` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `user_id` column of the `dependency_update_subscriptions` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `created_at` column of the `dependency_update_subscriptions` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `deprecated_endpoint_usage` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    deprecated_endpoint_usage (endpoint, consumer, date) {
+        /// The `endpoint` column of the `deprecated_endpoint_usage` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        endpoint -> Varchar,
+        /// The `consumer` column of the `deprecated_endpoint_usage` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        consumer -> Varchar,
+        /// The `date` column of the `deprecated_endpoint_usage` table.
+        ///
+        /// Its SQL type is `Date`.
+        ///
+        /// (Automatically generated by Diesel.)
+        date -> Date,
+        /// The `requests` column of the `deprecated_endpoint_usage` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        requests -> Int4,
+        /// The `last_used_at` column of the `deprecated_endpoint_usage` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        last_used_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `download_anomalies` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    download_anomalies (crate_id, date) {
+        /// The `crate_id` column of the `download_anomalies` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `date` column of the `download_anomalies` table.
+        ///
+        /// Its SQL type is `Date`.
+        ///
+        /// (Automatically generated by Diesel.)
+        date -> Date,
+        /// The `downloads` column of the `download_anomalies` table.
+        ///
+        /// Its SQL type is `Int8`.
+        ///
+        /// (Automatically generated by Diesel.)
+        downloads -> Int8,
+        /// The `baseline` column of the `download_anomalies` table.
+        ///
+        /// Its SQL type is `Float8`.
+        ///
+        /// (Automatically generated by Diesel.)
+        baseline -> Float8,
+        /// The `score` column of the `download_anomalies` table.
+        ///
+        /// Its SQL type is `Float8`.
+        ///
+        /// (Automatically generated by Diesel.)
+        score -> Float8,
+        /// The `detected_at` column of the `download_anomalies` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        detected_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `ecosystem_stats` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    ecosystem_stats (date) {
+        /// The `date` column of the `ecosystem_stats` table.
+        ///
+        /// Its SQL type is `Date`.
+        ///
+        /// (Automatically generated by Diesel.)
+        date -> Date,
+        /// The `total_crates` column of the `ecosystem_stats` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        total_crates -> Int4,
+        /// The `new_crates` column of the `ecosystem_stats` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        new_crates -> Int4,
+        /// The `new_versions` column of the `ecosystem_stats` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        new_versions -> Int4,
+        /// The `downloads` column of the `ecosystem_stats` table.
+        ///
+        /// Its SQL type is `Int8`.
+        ///
+        /// (Automatically generated by Diesel.)
+        downloads -> Int8,
+        /// The `total_users` column of the `ecosystem_stats` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        total_users -> Nullable<Int4>,
+        /// The `computed_at` column of the `ecosystem_stats` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        computed_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `emails` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    emails (id) {
+        /// The `id` column of the `emails` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `user_id` column of the `emails` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `email` column of the `emails` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        email -> Varchar,
+        /// The `verified` column of the `emails` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        verified -> Bool,
+        /// The `token` column of the `emails` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        token -> Text,
+        /// The `token_generated_at` column of the `emails` table.
         ///
         /// Its SQL type is `Nullable<Timestamp>`.
         ///
@@ -530,19 +1439,1201 @@ table! {
     /// Representation of the `follows` table.
     ///
     /// (Automatically generated by Diesel.)
-    follows (user_id, crate_id) {
-        /// The `user_id` column of the `follows` table.
+    follows (user_id, crate_id) {
+        /// The `user_id` column of the `follows` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `crate_id` column of the `follows` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `index_checksums` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    index_checksums (crate_id) {
+        /// The `crate_id` column of the `index_checksums` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `checksum` column of the `index_checksums` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        checksum -> Varchar,
+        /// The `updated_at` column of the `index_checksums` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `index_download_endpoints` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    index_download_endpoints (url) {
+        /// The `url` column of the `index_download_endpoints` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        url -> Varchar,
+        /// The `priority` column of the `index_download_endpoints` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        priority -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `index_settings` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    index_settings (name) {
+        /// The `name` column of the `index_settings` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        name -> Varchar,
+        /// The `value` column of the `index_settings` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        value -> Varchar,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `index_signing_keys` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    index_signing_keys (fingerprint) {
+        /// The `fingerprint` column of the `index_signing_keys` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        fingerprint -> Varchar,
+        /// The `public_key` column of the `index_signing_keys` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        public_key -> Varchar,
+        /// The `first_used_at` column of the `index_signing_keys` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        first_used_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `index_squashes` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    index_squashes (id) {
+        /// The `id` column of the `index_squashes` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `previous_head` column of the `index_squashes` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        previous_head -> Varchar,
+        /// The `squashed_commit` column of the `index_squashes` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        squashed_commit -> Varchar,
+        /// The `snapshot_branch` column of the `index_squashes` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        snapshot_branch -> Varchar,
+        /// The `commit_count` column of the `index_squashes` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        commit_count -> Int4,
+        /// The `created_at` column of the `index_squashes` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `index_syncs` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    index_syncs (version_id) {
+        /// The `version_id` column of the `index_syncs` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `queued_at` column of the `index_syncs` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        queued_at -> Timestamp,
+        /// The `indexed_at` column of the `index_syncs` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        indexed_at -> Nullable<Timestamp>,
+        /// The `visible_at` column of the `index_syncs` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        visible_at -> Nullable<Timestamp>,
+        /// The `index_failures` column of the `index_syncs` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        index_failures -> Int4,
+        /// The `visibility_failures` column of the `index_syncs` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        visibility_failures -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `keywords` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    keywords (id) {
+        /// The `id` column of the `keywords` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `keyword` column of the `keywords` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        keyword -> Text,
+        /// The `crates_cnt` column of the `keywords` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crates_cnt -> Int4,
+        /// The `created_at` column of the `keywords` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `metadata` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    metadata (total_downloads) {
+        /// The `total_downloads` column of the `metadata` table.
+        ///
+        /// Its SQL type is `Int8`.
+        ///
+        /// (Automatically generated by Diesel.)
+        total_downloads -> Int8,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `org_domains` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    org_domains (id) {
+        /// The `id` column of the `org_domains` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `org` column of the `org_domains` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        org -> Varchar,
+        /// The `domain` column of the `org_domains` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        domain -> Varchar,
+        /// The `challenge` column of the `org_domains` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        challenge -> Varchar,
+        /// The `requested_by` column of the `org_domains` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        requested_by -> Int4,
+        /// The `created_at` column of the `org_domains` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+        /// The `checked_at` column of the `org_domains` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        checked_at -> Nullable<Timestamp>,
+        /// The `verified_at` column of the `org_domains` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        verified_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `outreach_emails` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    outreach_emails (id) {
+        /// The `id` column of the `outreach_emails` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `campaign` column of the `outreach_emails` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        campaign -> Varchar,
+        /// The `user_id` column of the `outreach_emails` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `email` column of the `outreach_emails` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        email -> Varchar,
+        /// The `crates` column of the `outreach_emails` table.
+        ///
+        /// Its SQL type is `Array<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crates -> Array<Text>,
+        /// The `sent_at` column of the `outreach_emails` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        sent_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `partner_audit_log` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    partner_audit_log (id) {
+        /// The `id` column of the `partner_audit_log` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `partner_id` column of the `partner_audit_log` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        partner_id -> Int4,
+        /// The `action` column of the `partner_audit_log` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        action -> Varchar,
+        /// The `version_id` column of the `partner_audit_log` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Nullable<Int4>,
+        /// The `details` column of the `partner_audit_log` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        details -> Varchar,
+        /// The `created_at` column of the `partner_audit_log` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `publish_idempotency_keys` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    publish_idempotency_keys (user_id, key) {
+        /// The `user_id` column of the `publish_idempotency_keys` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `key` column of the `publish_idempotency_keys` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        key -> Varchar,
+        /// The `crate_name` column of the `publish_idempotency_keys` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_name -> Varchar,
+        /// The `version` column of the `publish_idempotency_keys` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version -> Varchar,
+        /// The `checksum` column of the `publish_idempotency_keys` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        checksum -> Nullable<Varchar>,
+        /// The `response` column of the `publish_idempotency_keys` table.
+        ///
+        /// Its SQL type is `Nullable<Jsonb>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        response -> Nullable<Jsonb>,
+        /// The `created_at` column of the `publish_idempotency_keys` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `publish_jobs` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    publish_jobs (id) {
+        /// The `id` column of the `publish_jobs` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `version_id` column of the `publish_jobs` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `user_id` column of the `publish_jobs` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `state` column of the `publish_jobs` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        state -> Varchar,
+        /// The `error` column of the `publish_jobs` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        error -> Nullable<Varchar>,
+        /// The `staged_path` column of the `publish_jobs` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        staged_path -> Nullable<Varchar>,
+        /// The `created_at` column of the `publish_jobs` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+        /// The `updated_at` column of the `publish_jobs` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `publish_limit_buckets` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    publish_limit_buckets (user_id) {
+        /// The `user_id` column of the `publish_limit_buckets` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `tokens` column of the `publish_limit_buckets` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        tokens -> Int4,
+        /// The `last_refill` column of the `publish_limit_buckets` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        last_refill -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `publish_rate_overrides` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    publish_rate_overrides (user_id) {
+        /// The `user_id` column of the `publish_rate_overrides` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `burst` column of the `publish_rate_overrides` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        burst -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `readme_renderings` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    readme_renderings (version_id) {
+        /// The `version_id` column of the `readme_renderings` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `rendered_at` column of the `readme_renderings` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        rendered_at -> Timestamp,
+        /// The `textsearchable_index_col` column of the `readme_renderings` table.
+        ///
+        /// Its SQL type is `Tsvector`.
+        ///
+        /// (Automatically generated by Diesel.)
+        textsearchable_index_col -> Tsvector,
+        /// The `toc` column of the `readme_renderings` table.
+        ///
+        /// Its SQL type is `Nullable<Jsonb>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        toc -> Nullable<Jsonb>,
+    }
+}
+
+table! {
+    /// Representation of the `recent_crate_downloads` view.
+    ///
+    /// This data represents the downloads in the last 90 days.
+    /// This view does not contain realtime data.
+    /// It is refreshed by the `update-downloads` script.
+    recent_crate_downloads (crate_id) {
+        /// The `crate_id` column of the `recent_crate_downloads` view.
+        ///
+        /// Its SQL type is `Integer`.
+        crate_id -> Integer,
+        /// The `downloads` column of the `recent_crate_downloads` table.
+        ///
+        /// Its SQL type is `BigInt`.
+        downloads -> BigInt,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `reserved_crate_names` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    reserved_crate_names (name) {
+        /// The `name` column of the `reserved_crate_names` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        name -> Text,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `search_index_cursors` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    search_index_cursors (backend) {
+        /// The `backend` column of the `search_index_cursors` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        backend -> Text,
+        /// The `synced_until` column of the `search_index_cursors` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        synced_until -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `search_synonyms` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    search_synonyms (term) {
+        /// The `term` column of the `search_synonyms` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        term -> Text,
+        /// The `synonyms` column of the `search_synonyms` table.
+        ///
+        /// Its SQL type is `Array<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        synonyms -> Array<Text>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `security_partners` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    security_partners (id) {
+        /// The `id` column of the `security_partners` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `name` column of the `security_partners` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        name -> Varchar,
+        /// The `contact_email` column of the `security_partners` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        contact_email -> Varchar,
+        /// The `api_key` column of the `security_partners` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        api_key -> Varchar,
+        /// The `verdicts_per_day` column of the `security_partners` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        verdicts_per_day -> Int4,
+        /// The `auto_quarantine` column of the `security_partners` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        auto_quarantine -> Bool,
+        /// The `created_at` column of the `security_partners` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+        /// The `revoked` column of the `security_partners` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        revoked -> Bool,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `service_consumer_usage` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    service_consumer_usage (service_consumer_id, hour) {
+        /// The `service_consumer_id` column of the `service_consumer_usage` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        service_consumer_id -> Int4,
+        /// The `hour` column of the `service_consumer_usage` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        hour -> Timestamp,
+        /// The `requests` column of the `service_consumer_usage` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        requests -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `service_consumers` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    service_consumers (id) {
+        /// The `id` column of the `service_consumers` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `name` column of the `service_consumers` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        name -> Varchar,
+        /// The `contact_email` column of the `service_consumers` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        contact_email -> Varchar,
+        /// The `api_key` column of the `service_consumers` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        api_key -> Varchar,
+        /// The `requests_per_hour` column of the `service_consumers` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        requests_per_hour -> Int4,
+        /// The `created_at` column of the `service_consumers` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+        /// The `last_used_at` column of the `service_consumers` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        last_used_at -> Nullable<Timestamp>,
+        /// The `revoked` column of the `service_consumers` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        revoked -> Bool,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `signed_tree_heads` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    signed_tree_heads (id) {
+        /// The `id` column of the `signed_tree_heads` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `tree_size` column of the `signed_tree_heads` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        tree_size -> Int4,
+        /// The `root_hash` column of the `signed_tree_heads` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        root_hash -> Varchar,
+        /// The `signature` column of the `signed_tree_heads` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        signature -> Varchar,
+        /// The `public_key` column of the `signed_tree_heads` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        public_key -> Varchar,
+        /// The `signed_at` column of the `signed_tree_heads` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        signed_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `sitemaps` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    sitemaps (file_name) {
+        /// The `file_name` column of the `sitemaps` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        file_name -> Varchar,
+        /// The `url_count` column of the `sitemaps` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        url_count -> Int4,
+        /// The `generated_at` column of the `sitemaps` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        generated_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `squatting_reports` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    squatting_reports (id) {
+        /// The `id` column of the `squatting_reports` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `crate_id` column of the `squatting_reports` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `reporter_id` column of the `squatting_reports` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        reporter_id -> Int4,
+        /// The `reason` column of the `squatting_reports` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        reason -> Varchar,
+        /// The `status` column of the `squatting_reports` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        status -> Varchar,
+        /// The `heuristics` column of the `squatting_reports` table.
+        ///
+        /// Its SQL type is `Nullable<Jsonb>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        heuristics -> Nullable<Jsonb>,
+        /// The `checked_at` column of the `squatting_reports` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        checked_at -> Nullable<Timestamp>,
+        /// The `outcome` column of the `squatting_reports` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        outcome -> Nullable<Varchar>,
+        /// The `resolved_at` column of the `squatting_reports` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        resolved_at -> Nullable<Timestamp>,
+        /// The `created_at` column of the `squatting_reports` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `teams` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    teams (id) {
+        /// The `id` column of the `teams` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `login` column of the `teams` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        login -> Varchar,
+        /// The `github_id` column of the `teams` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        github_id -> Int4,
+        /// The `name` column of the `teams` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        name -> Nullable<Varchar>,
+        /// The `avatar` column of the `teams` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        avatar -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `transparency_log_entries` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    transparency_log_entries (id) {
+        /// The `id` column of the `transparency_log_entries` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `leaf_index` column of the `transparency_log_entries` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        leaf_index -> Nullable<Int4>,
+        /// The `crate_name` column of the `transparency_log_entries` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_name -> Varchar,
+        /// The `num` column of the `transparency_log_entries` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        num -> Varchar,
+        /// The `checksum` column of the `transparency_log_entries` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        checksum -> Varchar,
+        /// The `published_at` column of the `transparency_log_entries` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        published_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `user_merges` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    user_merges (id) {
+        /// The `id` column of the `user_merges` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `from_user_id` column of the `user_merges` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        from_user_id -> Int4,
+        /// The `into_user_id` column of the `user_merges` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        into_user_id -> Int4,
+        /// The `report` column of the `user_merges` table.
+        ///
+        /// Its SQL type is `Jsonb`.
+        ///
+        /// (Automatically generated by Diesel.)
+        report -> Jsonb,
+        /// The `created_at` column of the `user_merges` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `user_profile_syncs` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    user_profile_syncs (user_id) {
+        /// The `user_id` column of the `user_profile_syncs` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `etag` column of the `user_profile_syncs` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        etag -> Nullable<Varchar>,
+        /// The `checked_at` column of the `user_profile_syncs` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        checked_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `users` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    users (id) {
+        /// The `id` column of the `users` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        user_id -> Int4,
-        /// The `crate_id` column of the `follows` table.
+        id -> Int4,
+        /// The `gh_access_token` column of the `users` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        gh_access_token -> Varchar,
+        /// The `gh_login` column of the `users` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        gh_login -> Varchar,
+        /// The `name` column of the `users` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        name -> Nullable<Varchar>,
+        /// The `gh_avatar` column of the `users` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        gh_avatar -> Nullable<Varchar>,
+        /// The `gh_id` column of the `users` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        crate_id -> Int4,
+        gh_id -> Int4,
     }
 }
 
@@ -550,34 +2641,63 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `keywords` table.
+    /// Representation of the `version_analyses` table.
     ///
     /// (Automatically generated by Diesel.)
-    keywords (id) {
-        /// The `id` column of the `keywords` table.
+    version_analyses (version_id, analyzer) {
+        /// The `version_id` column of the `version_analyses` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        id -> Int4,
-        /// The `keyword` column of the `keywords` table.
+        version_id -> Int4,
+        /// The `analyzer` column of the `version_analyses` table.
         ///
-        /// Its SQL type is `Text`.
+        /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        keyword -> Text,
-        /// The `crates_cnt` column of the `keywords` table.
+        analyzer -> Varchar,
+        /// The `result` column of the `version_analyses` table.
+        ///
+        /// Its SQL type is `Jsonb`.
+        ///
+        /// (Automatically generated by Diesel.)
+        result -> Jsonb,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `version_authors` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    version_authors (id) {
+        /// The `id` column of the `version_authors` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        crates_cnt -> Int4,
-        /// The `created_at` column of the `keywords` table.
+        id -> Int4,
+        /// The `version_id` column of the `version_authors` table.
         ///
-        /// Its SQL type is `Timestamp`.
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        created_at -> Timestamp,
+        version_id -> Int4,
+        /// The `user_id` column of the `version_authors` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Nullable<Int4>,
+        /// The `name` column of the `version_authors` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        name -> Varchar,
     }
 }
 
@@ -585,16 +2705,22 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `metadata` table.
+    /// Representation of the `version_checksums` table.
     ///
     /// (Automatically generated by Diesel.)
-    metadata (total_downloads) {
-        /// The `total_downloads` column of the `metadata` table.
+    version_checksums (version_id) {
+        /// The `version_id` column of the `version_checksums` table.
         ///
-        /// Its SQL type is `Int8`.
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        total_downloads -> Int8,
+        version_id -> Int4,
+        /// The `checksum` column of the `version_checksums` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        checksum -> Varchar,
     }
 }
 
@@ -602,28 +2728,34 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `publish_limit_buckets` table.
+    /// Representation of the `version_content_matches` table.
     ///
     /// (Automatically generated by Diesel.)
-    publish_limit_buckets (user_id) {
-        /// The `user_id` column of the `publish_limit_buckets` table.
+    version_content_matches (version_id) {
+        /// The `version_id` column of the `version_content_matches` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        user_id -> Int4,
-        /// The `tokens` column of the `publish_limit_buckets` table.
+        version_id -> Int4,
+        /// The `matched_version_id` column of the `version_content_matches` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        tokens -> Int4,
-        /// The `last_refill` column of the `publish_limit_buckets` table.
+        matched_version_id -> Int4,
+        /// The `similarity` column of the `version_content_matches` table.
+        ///
+        /// Its SQL type is `Float4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        similarity -> Float4,
+        /// The `created_at` column of the `version_content_matches` table.
         ///
         /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        last_refill -> Timestamp,
+        created_at -> Timestamp,
     }
 }
 
@@ -631,22 +2763,40 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `publish_rate_overrides` table.
+    /// Representation of the `version_downloads` table.
     ///
     /// (Automatically generated by Diesel.)
-    publish_rate_overrides (user_id) {
-        /// The `user_id` column of the `publish_rate_overrides` table.
+    version_downloads (version_id, date) {
+        /// The `version_id` column of the `version_downloads` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        user_id -> Int4,
-        /// The `burst` column of the `publish_rate_overrides` table.
+        version_id -> Int4,
+        /// The `downloads` column of the `version_downloads` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        burst -> Int4,
+        downloads -> Int4,
+        /// The `counted` column of the `version_downloads` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        counted -> Int4,
+        /// The `date` column of the `version_downloads` table.
+        ///
+        /// Its SQL type is `Date`.
+        ///
+        /// (Automatically generated by Diesel.)
+        date -> Date,
+        /// The `processed` column of the `version_downloads` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        processed -> Bool,
     }
 }
 
@@ -654,40 +2804,63 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `readme_renderings` table.
+    /// Representation of the `version_feature_docs` table.
     ///
     /// (Automatically generated by Diesel.)
-    readme_renderings (version_id) {
-        /// The `version_id` column of the `readme_renderings` table.
+    version_feature_docs (version_id, feature) {
+        /// The `version_id` column of the `version_feature_docs` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
         version_id -> Int4,
-        /// The `rendered_at` column of the `readme_renderings` table.
+        /// The `feature` column of the `version_feature_docs` table.
         ///
-        /// Its SQL type is `Timestamp`.
+        /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        rendered_at -> Timestamp,
+        feature -> Varchar,
+        /// The `description` column of the `version_feature_docs` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        description -> Text,
     }
 }
 
 table! {
-    /// Representation of the `recent_crate_downloads` view.
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `version_files` table.
     ///
-    /// This data represents the downloads in the last 90 days.
-    /// This view does not contain realtime data.
-    /// It is refreshed by the `update-downloads` script.
-    recent_crate_downloads (crate_id) {
-        /// The `crate_id` column of the `recent_crate_downloads` view.
+    /// (Automatically generated by Diesel.)
+    version_files (version_id, path) {
+        /// The `version_id` column of the `version_files` table.
         ///
-        /// Its SQL type is `Integer`.
-        crate_id -> Integer,
-        /// The `downloads` column of the `recent_crate_downloads` table.
+        /// Its SQL type is `Int4`.
         ///
-        /// Its SQL type is `BigInt`.
-        downloads -> BigInt,
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `path` column of the `version_files` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        path -> Varchar,
+        /// The `size` column of the `version_files` table.
+        ///
+        /// Its SQL type is `Int8`.
+        ///
+        /// (Automatically generated by Diesel.)
+        size -> Int8,
+        /// The `sha256` column of the `version_files` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        sha256 -> Varchar,
     }
 }
 
@@ -695,16 +2868,28 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `reserved_crate_names` table.
+    /// Representation of the `version_fingerprints` table.
     ///
     /// (Automatically generated by Diesel.)
-    reserved_crate_names (name) {
-        /// The `name` column of the `reserved_crate_names` table.
+    version_fingerprints (version_id) {
+        /// The `version_id` column of the `version_fingerprints` table.
         ///
-        /// Its SQL type is `Text`.
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        name -> Text,
+        version_id -> Int4,
+        /// The `crate_id` column of the `version_fingerprints` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `hashes` column of the `version_fingerprints` table.
+        ///
+        /// Its SQL type is `Array<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        hashes -> Array<Text>,
     }
 }
 
@@ -712,40 +2897,93 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `teams` table.
+    /// Representation of the `version_install_checks` table.
     ///
     /// (Automatically generated by Diesel.)
-    teams (id) {
-        /// The `id` column of the `teams` table.
+    version_install_checks (version_id) {
+        /// The `version_id` column of the `version_install_checks` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `passed` column of the `version_install_checks` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        passed -> Bool,
+        /// The `error` column of the `version_install_checks` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        error -> Nullable<Varchar>,
+        /// The `attempts` column of the `version_install_checks` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        attempts -> Int4,
+        /// The `checked_at` column of the `version_install_checks` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        checked_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `version_owner_actions` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    version_owner_actions (id) {
+        /// The `id` column of the `version_owner_actions` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
         id -> Int4,
-        /// The `login` column of the `teams` table.
+        /// The `version_id` column of the `version_owner_actions` table.
         ///
-        /// Its SQL type is `Varchar`.
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        login -> Varchar,
-        /// The `github_id` column of the `teams` table.
+        version_id -> Int4,
+        /// The `user_id` column of the `version_owner_actions` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        github_id -> Int4,
-        /// The `name` column of the `teams` table.
+        user_id -> Int4,
+        /// The `api_token_id` column of the `version_owner_actions` table.
         ///
-        /// Its SQL type is `Nullable<Varchar>`.
+        /// Its SQL type is `Nullable<Int4>`.
         ///
         /// (Automatically generated by Diesel.)
-        name -> Nullable<Varchar>,
-        /// The `avatar` column of the `teams` table.
+        api_token_id -> Nullable<Int4>,
+        /// The `action` column of the `version_owner_actions` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        action -> Int4,
+        /// The `time` column of the `version_owner_actions` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        time -> Timestamp,
+        /// The `reason` column of the `version_owner_actions` table.
         ///
         /// Its SQL type is `Nullable<Varchar>`.
         ///
         /// (Automatically generated by Diesel.)
-        avatar -> Nullable<Varchar>,
+        reason -> Nullable<Varchar>,
     }
 }
 
@@ -753,46 +2991,63 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `users` table.
+    /// Representation of the `version_quarantines` table.
     ///
     /// (Automatically generated by Diesel.)
-    users (id) {
-        /// The `id` column of the `users` table.
+    version_quarantines (version_id) {
+        /// The `version_id` column of the `version_quarantines` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        id -> Int4,
-        /// The `gh_access_token` column of the `users` table.
+        version_id -> Int4,
+        /// The `verdict_id` column of the `version_quarantines` table.
         ///
-        /// Its SQL type is `Varchar`.
+        /// Its SQL type is `Nullable<Int4>`.
         ///
         /// (Automatically generated by Diesel.)
-        gh_access_token -> Varchar,
-        /// The `gh_login` column of the `users` table.
+        verdict_id -> Nullable<Int4>,
+        /// The `created_at` column of the `version_quarantines` table.
         ///
-        /// Its SQL type is `Varchar`.
+        /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        gh_login -> Varchar,
-        /// The `name` column of the `users` table.
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `version_scan_holds` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    version_scan_holds (version_id) {
+        /// The `version_id` column of the `version_scan_holds` table.
         ///
-        /// Its SQL type is `Nullable<Varchar>`.
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        name -> Nullable<Varchar>,
-        /// The `gh_avatar` column of the `users` table.
+        version_id -> Int4,
+        /// The `index_entry` column of the `version_scan_holds` table.
         ///
-        /// Its SQL type is `Nullable<Varchar>`.
+        /// Its SQL type is `Jsonb`.
         ///
         /// (Automatically generated by Diesel.)
-        gh_avatar -> Nullable<Varchar>,
-        /// The `gh_id` column of the `users` table.
+        index_entry -> Jsonb,
+        /// The `publish_job_id` column of the `version_scan_holds` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Nullable<Int4>`.
         ///
         /// (Automatically generated by Diesel.)
-        gh_id -> Int4,
+        publish_job_id -> Nullable<Int4>,
+        /// The `created_at` column of the `version_scan_holds` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
     }
 }
 
@@ -800,34 +3055,34 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `version_authors` table.
+    /// Representation of the `version_scan_results` table.
     ///
     /// (Automatically generated by Diesel.)
-    version_authors (id) {
-        /// The `id` column of the `version_authors` table.
+    version_scan_results (version_id, path) {
+        /// The `version_id` column of the `version_scan_results` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        id -> Int4,
-        /// The `version_id` column of the `version_authors` table.
+        version_id -> Int4,
+        /// The `path` column of the `version_scan_results` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        version_id -> Int4,
-        /// The `user_id` column of the `version_authors` table.
+        path -> Varchar,
+        /// The `kind` column of the `version_scan_results` table.
         ///
-        /// Its SQL type is `Nullable<Int4>`.
+        /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        user_id -> Nullable<Int4>,
-        /// The `name` column of the `version_authors` table.
+        kind -> Varchar,
+        /// The `detail` column of the `version_scan_results` table.
         ///
         /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        name -> Varchar,
+        detail -> Varchar,
     }
 }
 
@@ -835,40 +3090,40 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `version_downloads` table.
+    /// Representation of the `version_signatures` table.
     ///
     /// (Automatically generated by Diesel.)
-    version_downloads (version_id, date) {
-        /// The `version_id` column of the `version_downloads` table.
+    version_signatures (version_id) {
+        /// The `version_id` column of the `version_signatures` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
         version_id -> Int4,
-        /// The `downloads` column of the `version_downloads` table.
+        /// The `bundle` column of the `version_signatures` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        downloads -> Int4,
-        /// The `counted` column of the `version_downloads` table.
+        bundle -> Varchar,
+        /// The `signer` column of the `version_signatures` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Nullable<Varchar>`.
         ///
         /// (Automatically generated by Diesel.)
-        counted -> Int4,
-        /// The `date` column of the `version_downloads` table.
+        signer -> Nullable<Varchar>,
+        /// The `uploaded_by` column of the `version_signatures` table.
         ///
-        /// Its SQL type is `Date`.
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        date -> Date,
-        /// The `processed` column of the `version_downloads` table.
+        uploaded_by -> Int4,
+        /// The `created_at` column of the `version_signatures` table.
         ///
-        /// Its SQL type is `Bool`.
+        /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        processed -> Bool,
+        created_at -> Timestamp,
     }
 }
 
@@ -876,46 +3131,64 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `version_owner_actions` table.
+    /// Representation of the `version_verdicts` table.
     ///
     /// (Automatically generated by Diesel.)
-    version_owner_actions (id) {
-        /// The `id` column of the `version_owner_actions` table.
+    version_verdicts (id) {
+        /// The `id` column of the `version_verdicts` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
         id -> Int4,
-        /// The `version_id` column of the `version_owner_actions` table.
+        /// The `partner_id` column of the `version_verdicts` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        version_id -> Int4,
-        /// The `user_id` column of the `version_owner_actions` table.
+        partner_id -> Int4,
+        /// The `version_id` column of the `version_verdicts` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        user_id -> Int4,
-        /// The `api_token_id` column of the `version_owner_actions` table.
+        version_id -> Int4,
+        /// The `checksum` column of the `version_verdicts` table.
         ///
-        /// Its SQL type is `Nullable<Int4>`.
+        /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        api_token_id -> Nullable<Int4>,
-        /// The `action` column of the `version_owner_actions` table.
+        checksum -> Varchar,
+        /// The `verdict` column of the `version_verdicts` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        action -> Int4,
-        /// The `time` column of the `version_owner_actions` table.
+        verdict -> Varchar,
+        /// The `details` column of the `version_verdicts` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        details -> Nullable<Varchar>,
+        /// The `status` column of the `version_verdicts` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        status -> Varchar,
+        /// The `reviewed_at` column of the `version_verdicts` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        reviewed_at -> Nullable<Timestamp>,
+        /// The `created_at` column of the `version_verdicts` table.
         ///
         /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        time -> Timestamp,
+        created_at -> Timestamp,
     }
 }
 
@@ -993,6 +3266,48 @@ table! {
         ///
         /// (Automatically generated by Diesel.)
         published_by -> Nullable<Int4>,
+        /// The `publish_channel` column of the `versions` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        publish_channel -> Nullable<Int4>,
+        /// The `compression` column of the `versions` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        compression -> Int4,
+        /// The `license_expression` column of the `versions` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        license_expression -> Nullable<Varchar>,
+        /// The `license_ids` column of the `versions` table.
+        ///
+        /// Its SQL type is `Nullable<Array<Text>>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        license_ids -> Nullable<Array<Text>>,
+        /// The `links` column of the `versions` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        links -> Nullable<Varchar>,
+        /// The `rust_version` column of the `versions` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        rust_version -> Nullable<Varchar>,
+        /// The `targets` column of the `versions` table.
+        ///
+        /// Its SQL type is `Array<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        targets -> Array<Text>,
     }
 }
 
@@ -1019,60 +3334,339 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `webhook_deliveries` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    webhook_deliveries (id) {
+        /// The `id` column of the `webhook_deliveries` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `webhook_id` column of the `webhook_deliveries` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        webhook_id -> Int4,
+        /// The `event` column of the `webhook_deliveries` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        event -> Varchar,
+        /// The `payload` column of the `webhook_deliveries` table.
+        ///
+        /// Its SQL type is `Jsonb`.
+        ///
+        /// (Automatically generated by Diesel.)
+        payload -> Jsonb,
+        /// The `attempts` column of the `webhook_deliveries` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        attempts -> Int4,
+        /// The `last_status` column of the `webhook_deliveries` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        last_status -> Nullable<Int4>,
+        /// The `last_error` column of the `webhook_deliveries` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        last_error -> Nullable<Varchar>,
+        /// The `next_attempt_at` column of the `webhook_deliveries` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        next_attempt_at -> Timestamp,
+        /// The `delivered_at` column of the `webhook_deliveries` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        delivered_at -> Nullable<Timestamp>,
+        /// The `created_at` column of the `webhook_deliveries` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `webhooks` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    webhooks (id) {
+        /// The `id` column of the `webhooks` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `service_consumer_id` column of the `webhooks` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        service_consumer_id -> Int4,
+        /// The `url` column of the `webhooks` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        url -> Varchar,
+        /// The `secret` column of the `webhooks` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        secret -> Varchar,
+        /// The `created_at` column of the `webhooks` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `yank_events` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    yank_events (id) {
+        /// The `id` column of the `yank_events` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `version_id` column of the `yank_events` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `user_id` column of the `yank_events` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `api_token_id` column of the `yank_events` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        api_token_id -> Nullable<Int4>,
+        /// The `yanked` column of the `yank_events` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        yanked -> Bool,
+        /// The `created_at` column of the `yank_events` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
 joinable!(api_tokens -> users (user_id));
 joinable!(badges -> crates (crate_id));
+joinable!(crate_dependency_freshness -> crates (crate_id));
+joinable!(crate_dependency_freshness -> versions (version_id));
+joinable!(crate_funding_links -> crates (crate_id));
+joinable!(crate_link_events -> crates (crate_id));
+joinable!(crate_link_events -> users (user_id));
+joinable!(crate_links -> crates (crate_id));
+joinable!(crate_metadata_history -> crates (crate_id));
+joinable!(crate_metadata_history -> users (user_id));
 joinable!(crate_owner_invitations -> crates (crate_id));
 joinable!(crate_owners -> crates (crate_id));
 joinable!(crate_owners -> teams (owner_id));
 joinable!(crate_owners -> users (owner_id));
+joinable!(crate_pages -> crates (crate_id));
+joinable!(crate_policies -> crates (crate_id));
+joinable!(crate_policies -> versions (version_id));
+joinable!(crate_quality -> crates (crate_id));
+joinable!(crate_release_stats -> crates (crate_id));
+joinable!(crate_security_policies -> crates (crate_id));
+joinable!(crate_security_policies -> versions (version_id));
+joinable!(crate_upload_limits -> crates (crate_id));
+joinable!(crate_upload_limits -> users (created_by));
 joinable!(crates_categories -> categories (category_id));
 joinable!(crates_categories -> crates (crate_id));
 joinable!(crates_keywords -> crates (crate_id));
 joinable!(crates_keywords -> keywords (keyword_id));
+joinable!(default_versions -> crates (crate_id));
+joinable!(default_versions -> versions (version_id));
 joinable!(dependencies -> crates (crate_id));
 joinable!(dependencies -> versions (version_id));
+joinable!(dependency_cycles -> crates (crate_id));
+joinable!(dependency_cycles -> versions (version_id));
+joinable!(dependency_update_notifications -> crates (crate_id));
+joinable!(dependency_update_notifications -> users (user_id));
+joinable!(dependency_update_notifications -> versions (dependency_version_id));
+joinable!(dependency_update_subscriptions -> crates (crate_id));
+joinable!(dependency_update_subscriptions -> users (user_id));
+joinable!(download_anomalies -> crates (crate_id));
 joinable!(emails -> users (user_id));
 joinable!(follows -> crates (crate_id));
 joinable!(follows -> users (user_id));
+joinable!(index_checksums -> crates (crate_id));
+joinable!(index_syncs -> versions (version_id));
+joinable!(org_domains -> users (requested_by));
+joinable!(outreach_emails -> users (user_id));
+joinable!(partner_audit_log -> security_partners (partner_id));
+joinable!(partner_audit_log -> versions (version_id));
+joinable!(publish_idempotency_keys -> users (user_id));
+joinable!(publish_jobs -> users (user_id));
+joinable!(publish_jobs -> versions (version_id));
 joinable!(publish_limit_buckets -> users (user_id));
 joinable!(publish_rate_overrides -> users (user_id));
 joinable!(readme_renderings -> versions (version_id));
 joinable!(recent_crate_downloads -> crates (crate_id));
+joinable!(service_consumer_usage -> service_consumers (service_consumer_id));
+joinable!(squatting_reports -> crates (crate_id));
+joinable!(squatting_reports -> users (reporter_id));
+joinable!(user_profile_syncs -> users (user_id));
+joinable!(version_analyses -> versions (version_id));
 joinable!(version_authors -> users (user_id));
 joinable!(version_authors -> versions (version_id));
+joinable!(version_checksums -> versions (version_id));
+joinable!(version_content_matches -> versions (version_id));
 joinable!(version_downloads -> versions (version_id));
+joinable!(version_feature_docs -> versions (version_id));
+joinable!(version_files -> versions (version_id));
+joinable!(version_fingerprints -> crates (crate_id));
+joinable!(version_fingerprints -> versions (version_id));
+joinable!(version_install_checks -> versions (version_id));
 joinable!(version_owner_actions -> api_tokens (api_token_id));
 joinable!(version_owner_actions -> users (user_id));
 joinable!(version_owner_actions -> versions (version_id));
+joinable!(version_quarantines -> version_verdicts (verdict_id));
+joinable!(version_quarantines -> versions (version_id));
+joinable!(version_scan_holds -> publish_jobs (publish_job_id));
+joinable!(version_scan_holds -> versions (version_id));
+joinable!(version_scan_results -> versions (version_id));
+joinable!(version_signatures -> users (uploaded_by));
+joinable!(version_signatures -> versions (version_id));
+joinable!(version_verdicts -> security_partners (partner_id));
+joinable!(version_verdicts -> versions (version_id));
 joinable!(versions -> crates (crate_id));
 joinable!(versions -> users (published_by));
 joinable!(versions_published_by -> versions (version_id));
+joinable!(webhook_deliveries -> webhooks (webhook_id));
+joinable!(webhooks -> service_consumers (service_consumer_id));
+joinable!(yank_events -> api_tokens (api_token_id));
+joinable!(yank_events -> users (user_id));
+joinable!(yank_events -> versions (version_id));
 
 allow_tables_to_appear_in_same_query!(
     api_tokens,
+    background_job_heartbeats,
     background_jobs,
     badges,
     categories,
+    config_overrides,
+    crate_dependency_freshness,
+    crate_funding_links,
+    crate_link_events,
+    crate_links,
+    crate_metadata_history,
     crate_owner_invitations,
     crate_owners,
+    crate_pages,
+    crate_policies,
+    crate_quality,
+    crate_release_stats,
+    crate_security_policies,
+    crate_upload_limits,
     crates,
     crates_categories,
     crates_keywords,
+    default_versions,
+    deleted_crates,
     dependencies,
+    dependency_cycles,
+    dependency_update_notifications,
+    dependency_update_subscriptions,
+    deprecated_endpoint_usage,
+    download_anomalies,
+    ecosystem_stats,
     emails,
     follows,
+    index_checksums,
+    index_download_endpoints,
+    index_settings,
+    index_signing_keys,
+    index_squashes,
+    index_syncs,
     keywords,
     metadata,
+    org_domains,
+    outreach_emails,
+    partner_audit_log,
+    publish_idempotency_keys,
+    publish_jobs,
     publish_limit_buckets,
     publish_rate_overrides,
     readme_renderings,
     recent_crate_downloads,
     reserved_crate_names,
+    search_index_cursors,
+    search_synonyms,
+    security_partners,
+    service_consumer_usage,
+    service_consumers,
+    signed_tree_heads,
+    sitemaps,
+    squatting_reports,
     teams,
+    transparency_log_entries,
+    user_merges,
+    user_profile_syncs,
     users,
+    version_analyses,
     version_authors,
+    version_checksums,
+    version_content_matches,
     version_downloads,
+    version_feature_docs,
+    version_files,
+    version_fingerprints,
+    version_install_checks,
     version_owner_actions,
+    version_quarantines,
+    version_scan_holds,
+    version_scan_results,
+    version_signatures,
+    version_verdicts,
     versions,
     versions_published_by,
+    webhook_deliveries,
+    webhooks,
+    yank_events,
 );
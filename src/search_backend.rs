@@ -0,0 +1,248 @@
+//! Backends for the full text part of crate searches
+//!
+//! By default searches run in Postgres. Deployments needing better relevance
+//! or typo tolerance can use an external Meilisearch instance instead, which
+//! is kept up to date by the `sync_search_index` background job.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use diesel::prelude::*;
+use reqwest::blocking::{Client, RequestBuilder};
+use reqwest::Method;
+use swirl::PerformError;
+
+use crate::env;
+use crate::models::SearchSynonym;
+use crate::schema::{crates, crates_keywords, keywords};
+use crate::util::errors::AppResult;
+
+/// The maximum number of results requested from an external search engine.
+const MAX_EXTERNAL_RESULTS: usize = 1000;
+
+/// How long a search may take before falling back to Postgres.
+const SEARCH_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long other requests to an external search engine may take, e.g. to
+/// index a batch of crates.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The crates matching the full text part of a search.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TextSearch {
+    /// The crates are matched and ranked in Postgres, using all of the given
+    /// variants of the search (see `SearchSynonym::expand_query`).
+    Postgres { variants: Vec<String> },
+    /// The IDs of the matching crates, most relevant first.
+    Ranked(Vec<i32>),
+}
+
+pub trait SearchBackend: Send + Sync {
+    /// A unique name of the backend, used to remember how far its index has
+    /// been synced.
+    fn name(&self) -> &'static str;
+
+    /// Resolves the full text search for `q`.
+    fn text_search(&self, conn: &PgConnection, q: &str) -> AppResult<TextSearch>;
+
+    /// Updates the index of the backend for the given crates.
+    ///
+    /// Backends searching the database directly don't need to do anything.
+    fn index_crates(&self, _conn: &PgConnection, _crate_ids: &[i32]) -> Result<(), PerformError> {
+        Ok(())
+    }
+
+    /// Removes deleted crates from the index of the backend.
+    fn remove_crates(&self, _crate_ids: &[i32]) -> Result<(), PerformError> {
+        Ok(())
+    }
+}
+
+/// Which search backend to use.
+#[derive(Clone, Debug)]
+pub enum SearchConfig {
+    Postgres,
+    Meilisearch {
+        url: String,
+        api_key: Option<String>,
+        index: String,
+    },
+}
+
+impl SearchConfig {
+    /// Reads the search backend from the environment.
+    ///
+    /// - `SEARCH_BACKEND`: Either `postgres` (the default) or `meilisearch`.
+    /// - `MEILISEARCH_URL`: The URL of the Meilisearch instance.
+    /// - `MEILISEARCH_API_KEY`: An optional API key for Meilisearch.
+    /// - `MEILISEARCH_INDEX`: The Meilisearch index to use, `crates` by default.
+    pub fn from_environment() -> Self {
+        match dotenv::var("SEARCH_BACKEND").ok().as_deref() {
+            None | Some("postgres") => SearchConfig::Postgres,
+            Some("meilisearch") => SearchConfig::Meilisearch {
+                url: env("MEILISEARCH_URL"),
+                api_key: dotenv::var("MEILISEARCH_API_KEY").ok(),
+                index: dotenv::var("MEILISEARCH_INDEX").unwrap_or_else(|_| "crates".into()),
+            },
+            Some(other) => panic!("Unknown SEARCH_BACKEND `{}`", other),
+        }
+    }
+
+    pub fn build(&self) -> Arc<dyn SearchBackend> {
+        match self {
+            SearchConfig::Postgres => Arc::new(Postgres),
+            SearchConfig::Meilisearch {
+                url,
+                api_key,
+                index,
+            } => Arc::new(Meilisearch {
+                url: url.trim_end_matches('/').into(),
+                api_key: api_key.clone(),
+                index: index.clone(),
+                client: Client::builder()
+                    .timeout(REQUEST_TIMEOUT)
+                    .build()
+                    .expect("Couldn't build the Meilisearch client"),
+            }),
+        }
+    }
+}
+
+/// Searches the full text index of the `crates` table, expanding the search
+/// with the curated synonyms.
+#[derive(Clone, Copy, Debug)]
+pub struct Postgres;
+
+impl SearchBackend for Postgres {
+    fn name(&self) -> &'static str {
+        "postgres"
+    }
+
+    fn text_search(&self, conn: &PgConnection, q: &str) -> AppResult<TextSearch> {
+        let variants = SearchSynonym::expand_query(conn, q)?;
+        Ok(TextSearch::Postgres { variants })
+    }
+}
+
+/// Searches an index of the crates in a Meilisearch instance.
+#[derive(Debug)]
+pub struct Meilisearch {
+    url: String,
+    api_key: Option<String>,
+    index: String,
+    client: Client,
+}
+
+#[derive(Serialize)]
+struct CrateDocument {
+    id: i32,
+    name: String,
+    description: Option<String>,
+    keywords: Vec<String>,
+    downloads: i32,
+}
+
+impl Meilisearch {
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let url = format!("{}/indexes/{}/{}", self.url, self.index, path);
+        let request = self.client.request(method, &url);
+        match &self.api_key {
+            Some(api_key) => request.header("X-Meili-API-Key", api_key),
+            None => request,
+        }
+    }
+
+    fn search(&self, q: &str) -> reqwest::Result<Vec<i32>> {
+        #[derive(Deserialize)]
+        struct Hit {
+            id: i32,
+        }
+        #[derive(Deserialize)]
+        struct Response {
+            hits: Vec<Hit>,
+        }
+
+        let limit = MAX_EXTERNAL_RESULTS.to_string();
+        let response: Response = self
+            .request(Method::GET, "search")
+            .query(&[
+                ("q", q),
+                ("limit", limit.as_str()),
+                ("attributesToRetrieve", "id"),
+            ])
+            .timeout(SEARCH_TIMEOUT)
+            .send()?
+            .error_for_status()?
+            .json()?;
+        Ok(response.hits.into_iter().map(|hit| hit.id).collect())
+    }
+}
+
+impl SearchBackend for Meilisearch {
+    fn name(&self) -> &'static str {
+        "meilisearch"
+    }
+
+    fn text_search(&self, conn: &PgConnection, q: &str) -> AppResult<TextSearch> {
+        match self.search(q) {
+            Ok(crate_ids) => Ok(TextSearch::Ranked(crate_ids)),
+            Err(error) => {
+                // An outage of the search engine shouldn't take crate search
+                // down with it.
+                eprintln!(
+                    "Meilisearch failed, searching in Postgres instead: {}",
+                    error
+                );
+                Postgres.text_search(conn, q)
+            }
+        }
+    }
+
+    fn index_crates(&self, conn: &PgConnection, crate_ids: &[i32]) -> Result<(), PerformError> {
+        let mut keywords_by_crate = HashMap::<i32, Vec<String>>::new();
+        let crate_keywords = crates_keywords::table
+            .inner_join(keywords::table)
+            .filter(crates_keywords::crate_id.eq_any(crate_ids))
+            .select((crates_keywords::crate_id, keywords::keyword))
+            .load::<(i32, String)>(conn)?;
+        for (crate_id, keyword) in crate_keywords {
+            keywords_by_crate.entry(crate_id).or_default().push(keyword);
+        }
+
+        let documents = crates::table
+            .filter(crates::id.eq_any(crate_ids))
+            .select((
+                crates::id,
+                crates::name,
+                crates::description,
+                crates::downloads,
+            ))
+            .load::<(i32, String, Option<String>, i32)>(conn)?
+            .into_iter()
+            .map(|(id, name, description, downloads)| CrateDocument {
+                id,
+                name,
+                description,
+                keywords: keywords_by_crate.remove(&id).unwrap_or_default(),
+                downloads,
+            })
+            .collect::<Vec<_>>();
+
+        if !documents.is_empty() {
+            self.request(Method::POST, "documents")
+                .json(&documents)
+                .send()?
+                .error_for_status()?;
+        }
+        Ok(())
+    }
+
+    fn remove_crates(&self, crate_ids: &[i32]) -> Result<(), PerformError> {
+        self.request(Method::POST, "documents/delete-batch")
+            .json(crate_ids)
+            .send()?
+            .error_for_status()?;
+        Ok(())
+    }
+}
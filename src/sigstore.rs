@@ -0,0 +1,660 @@
+//! Verification of the sigstore bundles which owners upload for versions.
+//!
+//! A bundle has to sign the crate file of the version: its message digest
+//! must be the SHA-256 checksum recorded when the version was published, and
+//! the signature must be valid for that digest with the key of the signing
+//! certificate. Bundles of both the `x509CertificateChain` layout of version
+//! 0.1 and the `certificate` layout of later versions are accepted.
+//!
+//! The identity in the signing certificate is only trusted if the certificate
+//! was issued by one of the Fulcio certificates of the `TrustRoot`, and the
+//! bundle has a transparency log entry whose signed entry timestamp was
+//! signed by one of its Rekor keys. The entry has to record the same digest,
+//! signature and certificate, and the certificate has to be valid at the time
+//! the entry was integrated into the log, since Fulcio certificates expire
+//! within minutes.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use openssl::asn1::Asn1Time;
+use openssl::ecdsa::EcdsaSig;
+use openssl::hash::MessageDigest as Digest;
+use openssl::pkey::{PKey, Public};
+use openssl::sign::Verifier;
+use openssl::x509::{X509VerifyResult, X509};
+
+use crate::util::errors::{bad_request, AppError, AppResult};
+
+const MEDIA_TYPE_PREFIX: &str = "application/vnd.dev.sigstore.bundle";
+
+/// The certificates and keys bundles are verified against.
+#[derive(Clone)]
+pub struct TrustRoot {
+    /// The Fulcio root and intermediate certificates, which may issue
+    /// signing certificates.
+    certificates: Vec<X509>,
+    /// The keys of the Rekor transparency logs.
+    log_keys: Vec<PKey<Public>>,
+}
+
+impl fmt::Debug for TrustRoot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TrustRoot")
+            .field("certificates", &self.certificates.len())
+            .field("log_keys", &self.log_keys.len())
+            .finish()
+    }
+}
+
+impl TrustRoot {
+    pub fn new(certificates: Vec<X509>, log_keys: Vec<PKey<Public>>) -> Self {
+        TrustRoot {
+            certificates,
+            log_keys,
+        }
+    }
+
+    /// Reads the PEM encoded Fulcio certificates from
+    /// `SIGSTORE_FULCIO_CERTIFICATES` and the PEM encoded Rekor public keys
+    /// from `SIGSTORE_REKOR_KEYS`. Without both, signatures aren't accepted.
+    pub fn from_environment() -> Option<Self> {
+        let certificates = dotenv::var("SIGSTORE_FULCIO_CERTIFICATES").ok()?;
+        let log_keys = dotenv::var("SIGSTORE_REKOR_KEYS").ok()?;
+        let certificates = X509::stack_from_pem(certificates.as_bytes())
+            .unwrap_or_else(|e| panic!("failed to parse SIGSTORE_FULCIO_CERTIFICATES: {}", e));
+        let log_keys = pem_blocks(&log_keys)
+            .map(|pem| PKey::public_key_from_pem(pem.as_bytes()))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap_or_else(|e| panic!("failed to parse SIGSTORE_REKOR_KEYS: {}", e));
+        Some(TrustRoot::new(certificates, log_keys))
+    }
+}
+
+/// Splits concatenated PEM blocks, since keys can't be read as a stack.
+fn pem_blocks(pem: &str) -> impl Iterator<Item = String> + '_ {
+    const END: &str = "-----END PUBLIC KEY-----";
+    pem.split_terminator(END)
+        .filter(|block| !block.trim().is_empty())
+        .map(|block| format!("{}{}\n", block.trim_start(), END))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Bundle {
+    media_type: String,
+    verification_material: VerificationMaterial,
+    message_signature: Option<MessageSignature>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VerificationMaterial {
+    x509_certificate_chain: Option<CertificateChain>,
+    certificate: Option<Certificate>,
+    #[serde(default)]
+    tlog_entries: Vec<TlogEntry>,
+}
+
+#[derive(Deserialize)]
+struct CertificateChain {
+    certificates: Vec<Certificate>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Certificate {
+    raw_bytes: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MessageSignature {
+    message_digest: MessageDigest,
+    signature: String,
+}
+
+#[derive(Deserialize)]
+struct MessageDigest {
+    algorithm: String,
+    digest: String,
+}
+
+/// An entry of a Rekor transparency log. Its 64 bit integers are strings, as
+/// usual in the JSON encoding of protobuf messages.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TlogEntry {
+    log_index: String,
+    log_id: LogId,
+    integrated_time: String,
+    inclusion_promise: Option<InclusionPromise>,
+    canonicalized_body: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LogId {
+    key_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InclusionPromise {
+    signed_entry_timestamp: String,
+}
+
+/// What Rekor signs as the signed entry timestamp of an entry, serialized as
+/// canonical JSON: sorted keys and no whitespace.
+#[derive(Serialize)]
+struct SignedEntry<'a> {
+    body: &'a str,
+    #[serde(rename = "integratedTime")]
+    integrated_time: i64,
+    #[serde(rename = "logID")]
+    log_id: String,
+    #[serde(rename = "logIndex")]
+    log_index: i64,
+}
+
+/// The canonicalized body of a `hashedrekord` entry.
+#[derive(Deserialize)]
+struct RekorEntry {
+    kind: String,
+    spec: HashedRekord,
+}
+
+#[derive(Deserialize)]
+struct HashedRekord {
+    data: HashedData,
+    signature: RekorSignature,
+}
+
+#[derive(Deserialize)]
+struct HashedData {
+    hash: RekorHash,
+}
+
+#[derive(Deserialize)]
+struct RekorHash {
+    algorithm: String,
+    value: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RekorSignature {
+    content: String,
+    public_key: RekorPublicKey,
+}
+
+#[derive(Deserialize)]
+struct RekorPublicKey {
+    content: String,
+}
+
+fn invalid(reason: &str) -> Box<dyn AppError> {
+    bad_request(&format_args!("invalid sigstore bundle: {}", reason))
+}
+
+/// Verifies that the bundle signs the crate file with the given hex encoded
+/// checksum, and that its signing certificate and transparency log entry are
+/// trusted. Returns the identity of the signing certificate if it has an
+/// email address or URI.
+pub fn verify_bundle(
+    bundle: &str,
+    checksum: &str,
+    trust_root: &TrustRoot,
+) -> AppResult<Option<String>> {
+    let bundle = serde_json::from_str::<Bundle>(bundle).map_err(|e| invalid(&e.to_string()))?;
+    if !bundle.media_type.starts_with(MEDIA_TYPE_PREFIX) {
+        return Err(invalid(&format!(
+            "unknown media type `{}`",
+            bundle.media_type
+        )));
+    }
+
+    let material = bundle.verification_material;
+    let certificate = match (material.certificate, material.x509_certificate_chain) {
+        (Some(certificate), _) => certificate,
+        (None, Some(chain)) => chain
+            .certificates
+            .into_iter()
+            .next()
+            .ok_or_else(|| invalid("the certificate chain is empty"))?,
+        (None, None) => return Err(invalid("it has no signing certificate")),
+    };
+
+    let signature = bundle
+        .message_signature
+        .ok_or_else(|| invalid("only message signatures are supported"))?;
+    if signature.message_digest.algorithm != "SHA2_256" {
+        return Err(invalid(&format!(
+            "unsupported digest algorithm `{}`",
+            signature.message_digest.algorithm
+        )));
+    }
+    let digest = base64::decode(&signature.message_digest.digest)
+        .map_err(|_| invalid("the message digest isn't valid base64"))?;
+    if hex::decode(checksum).ok().as_ref() != Some(&digest) {
+        return Err(invalid("the message digest doesn't match the crate file"));
+    }
+
+    let der = base64::decode(&certificate.raw_bytes)
+        .map_err(|_| invalid("the certificate isn't valid base64"))?;
+    let certificate = X509::from_der(&der).map_err(|_| invalid("the certificate is malformed"))?;
+    let key = certificate
+        .public_key()
+        .and_then(|key| key.ec_key())
+        .map_err(|_| invalid("only ECDSA certificates are supported"))?;
+    let signature =
+        base64::decode(&signature.signature).map_err(|_| invalid("the signature is malformed"))?;
+    let valid = EcdsaSig::from_der(&signature)
+        .and_then(|signature| signature.verify(&digest, &key))
+        .unwrap_or(false);
+    if !valid {
+        return Err(invalid("the signature doesn't match the certificate"));
+    }
+
+    if !is_issued_by_fulcio(&certificate, trust_root) {
+        return Err(invalid("the certificate wasn't issued by Fulcio"));
+    }
+    let integrated_time = verify_tlog_entries(
+        &material.tlog_entries,
+        checksum,
+        &signature,
+        &certificate,
+        trust_root,
+    )?;
+    if !is_valid_at(&certificate, integrated_time) {
+        return Err(invalid(
+            "the certificate wasn't valid when the signature was logged",
+        ));
+    }
+
+    let signer = certificate.subject_alt_names().and_then(|names| {
+        names
+            .iter()
+            .filter_map(|name| name.email().or_else(|| name.uri()))
+            .map(String::from)
+            .next()
+    });
+    Ok(signer)
+}
+
+/// Only the certificates of the trust root may have issued the signing
+/// certificate. The intermediate certificates of a bundle aren't used, since
+/// without checking their constraints any certificate could pass as one.
+fn is_issued_by_fulcio(certificate: &X509, trust_root: &TrustRoot) -> bool {
+    trust_root.certificates.iter().any(|issuer| {
+        issuer.issued(certificate) == X509VerifyResult::OK
+            && issuer
+                .public_key()
+                .and_then(|key| certificate.verify(&key))
+                .unwrap_or(false)
+    })
+}
+
+fn is_valid_at(certificate: &X509, unix_time: i64) -> bool {
+    let time = match Asn1Time::from_unix(unix_time as _) {
+        Ok(time) => time,
+        Err(_) => return false,
+    };
+    let started = certificate
+        .not_before()
+        .compare(&time)
+        .map(|order| order != Ordering::Greater);
+    let expired = certificate
+        .not_after()
+        .compare(&time)
+        .map(|order| order == Ordering::Less);
+    started.unwrap_or(false) && !expired.unwrap_or(true)
+}
+
+/// Verifies the transparency log entries of a bundle, returning the time at
+/// which the first one signed by a known log was integrated into it.
+fn verify_tlog_entries(
+    entries: &[TlogEntry],
+    checksum: &str,
+    signature: &[u8],
+    certificate: &X509,
+    trust_root: &TrustRoot,
+) -> AppResult<i64> {
+    for entry in entries {
+        let key_id = base64::decode(&entry.log_id.key_id)
+            .map_err(|_| invalid("the log ID isn't valid base64"))?;
+        let log_key = trust_root.log_keys.iter().find(|key| {
+            key.public_key_to_der()
+                .map(|der| openssl::sha::sha256(&der)[..] == key_id[..])
+                .unwrap_or(false)
+        });
+        if let Some(log_key) = log_key {
+            return verify_tlog_entry(entry, &key_id, log_key, checksum, signature, certificate);
+        }
+    }
+    Err(invalid("it has no transparency log entry of a known log"))
+}
+
+fn verify_tlog_entry(
+    entry: &TlogEntry,
+    key_id: &[u8],
+    log_key: &PKey<Public>,
+    checksum: &str,
+    signature: &[u8],
+    certificate: &X509,
+) -> AppResult<i64> {
+    let integrated_time = entry
+        .integrated_time
+        .parse::<i64>()
+        .map_err(|_| invalid("the integrated time of the log entry isn't a number"))?;
+    let log_index = entry
+        .log_index
+        .parse::<i64>()
+        .map_err(|_| invalid("the index of the log entry isn't a number"))?;
+    let promise = entry
+        .inclusion_promise
+        .as_ref()
+        .ok_or_else(|| invalid("the log entry has no inclusion promise"))?;
+    let timestamp = base64::decode(&promise.signed_entry_timestamp)
+        .map_err(|_| invalid("the signed entry timestamp isn't valid base64"))?;
+    let signed_entry = serde_json::to_vec(&SignedEntry {
+        body: &entry.canonicalized_body,
+        integrated_time,
+        log_id: hex::encode(key_id),
+        log_index,
+    })?;
+    let valid = Verifier::new(Digest::sha256(), log_key)
+        .and_then(|mut verifier| {
+            verifier.update(&signed_entry)?;
+            verifier.verify(&timestamp)
+        })
+        .unwrap_or(false);
+    if !valid {
+        return Err(invalid(
+            "the signed entry timestamp doesn't match the log entry",
+        ));
+    }
+
+    let body = base64::decode(&entry.canonicalized_body)
+        .ok()
+        .and_then(|body| serde_json::from_slice::<RekorEntry>(&body).ok())
+        .filter(|body| body.kind == "hashedrekord")
+        .ok_or_else(|| invalid("the log entry isn't a `hashedrekord` entry"))?;
+    let logged_certificate = base64::decode(&body.spec.signature.public_key.content)
+        .ok()
+        .and_then(|pem| X509::from_pem(&pem).ok())
+        .and_then(|certificate| certificate.to_der().ok());
+    let matches = body.spec.data.hash.algorithm == "sha256"
+        && body.spec.data.hash.value.eq_ignore_ascii_case(checksum)
+        && base64::decode(&body.spec.signature.content).ok().as_deref() == Some(signature)
+        && logged_certificate.is_some()
+        && logged_certificate == certificate.to_der().ok();
+    if !matches {
+        return Err(invalid("the log entry doesn't record this signature"));
+    }
+    Ok(integrated_time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::asn1::Asn1Time;
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::hash::MessageDigest;
+    use openssl::nid::Nid;
+    use openssl::pkey::Private;
+    use openssl::sign::Signer;
+    use openssl::x509::extension::{BasicConstraints, KeyUsage, SubjectAlternativeName};
+    use openssl::x509::{X509Builder, X509NameBuilder};
+
+    const CHECKSUM: &str = "5d41402abc4b2a76b9719d911017c592aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+
+    fn ec_key() -> PKey<Private> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+    }
+
+    fn public(key: &PKey<Private>) -> PKey<Public> {
+        PKey::public_key_from_der(&key.public_key_to_der().unwrap()).unwrap()
+    }
+
+    /// A certificate for `key`, issued by the CA if there is one, and
+    /// otherwise a self-signed CA certificate.
+    fn certificate(key: &PKey<Private>, ca: Option<(&X509, &PKey<Private>)>) -> X509 {
+        let mut builder = X509Builder::new().unwrap();
+        builder.set_pubkey(key).unwrap();
+        builder
+            .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        match ca {
+            Some((ca, ca_key)) => {
+                builder.set_issuer_name(ca.subject_name()).unwrap();
+                let san = SubjectAlternativeName::new()
+                    .email("owner@example.com")
+                    .build(&builder.x509v3_context(Some(ca), None))
+                    .unwrap();
+                builder.append_extension(san).unwrap();
+                builder.sign(ca_key, MessageDigest::sha256()).unwrap();
+            }
+            None => {
+                let mut name = X509NameBuilder::new().unwrap();
+                name.append_entry_by_text("CN", "fulcio.test").unwrap();
+                let name = name.build();
+                builder.set_subject_name(&name).unwrap();
+                builder.set_issuer_name(&name).unwrap();
+                let constraints = BasicConstraints::new().critical().ca().build().unwrap();
+                builder.append_extension(constraints).unwrap();
+                let usage = KeyUsage::new().critical().key_cert_sign().build().unwrap();
+                builder.append_extension(usage).unwrap();
+                builder.sign(key, MessageDigest::sha256()).unwrap();
+            }
+        }
+        builder.build()
+    }
+
+    /// A Fulcio CA and a Rekor log, which bundles are signed and logged with.
+    struct Sigstore {
+        ca_key: PKey<Private>,
+        ca: X509,
+        log_key: PKey<Private>,
+    }
+
+    /// How a bundle deviates from a valid one.
+    #[derive(Default)]
+    struct Tampering {
+        signed_checksum: Option<&'static str>,
+        logged_checksum: Option<&'static str>,
+        self_signed: bool,
+        unknown_log: bool,
+        logged_days_ago: i64,
+    }
+
+    impl Sigstore {
+        fn new() -> Self {
+            let ca_key = ec_key();
+            let ca = certificate(&ca_key, None);
+            Sigstore {
+                ca_key,
+                ca,
+                log_key: ec_key(),
+            }
+        }
+
+        fn trust_root(&self) -> TrustRoot {
+            TrustRoot::new(vec![self.ca.clone()], vec![public(&self.log_key)])
+        }
+
+        fn bundle(&self, checksum: &str, tampering: Tampering) -> String {
+            let key = ec_key();
+            // A self-signed certificate has the same issuer name as the real ones
+            let certificate = if tampering.self_signed {
+                certificate(&key, Some((&certificate(&key, None), &key)))
+            } else {
+                certificate(&key, Some((&self.ca, &self.ca_key)))
+            };
+
+            let signed_checksum = tampering.signed_checksum.unwrap_or(checksum);
+            let signature = EcdsaSig::sign(
+                &hex::decode(signed_checksum).unwrap(),
+                &key.ec_key().unwrap(),
+            )
+            .unwrap()
+            .to_der()
+            .unwrap();
+
+            let body = json!({
+                "apiVersion": "0.0.1",
+                "kind": "hashedrekord",
+                "spec": {
+                    "data": {
+                        "hash": {
+                            "algorithm": "sha256",
+                            "value": tampering.logged_checksum.unwrap_or(checksum)
+                        }
+                    },
+                    "signature": {
+                        "content": base64::encode(&signature),
+                        "publicKey": { "content": base64::encode(&certificate.to_pem().unwrap()) }
+                    }
+                }
+            });
+            let body = base64::encode(&body.to_string());
+            let log_key = if tampering.unknown_log {
+                ec_key()
+            } else {
+                self.log_key.clone()
+            };
+            let key_id = openssl::sha::sha256(&log_key.public_key_to_der().unwrap());
+            let integrated_time =
+                chrono::Utc::now().timestamp() - tampering.logged_days_ago * 24 * 60 * 60;
+            let signed_entry = serde_json::to_vec(&SignedEntry {
+                body: &body,
+                integrated_time,
+                log_id: hex::encode(&key_id),
+                log_index: 1,
+            })
+            .unwrap();
+            let mut signer = Signer::new(MessageDigest::sha256(), &log_key).unwrap();
+            signer.update(&signed_entry).unwrap();
+            let timestamp = signer.sign_to_vec().unwrap();
+
+            json!({
+                "mediaType": "application/vnd.dev.sigstore.bundle+json;version=0.2",
+                "verificationMaterial": {
+                    "certificate": { "rawBytes": base64::encode(&certificate.to_der().unwrap()) },
+                    "tlogEntries": [{
+                        "logIndex": "1",
+                        "logId": { "keyId": base64::encode(&key_id) },
+                        "kindVersion": { "kind": "hashedrekord", "version": "0.0.1" },
+                        "integratedTime": integrated_time.to_string(),
+                        "inclusionPromise": { "signedEntryTimestamp": base64::encode(&timestamp) },
+                        "canonicalizedBody": body
+                    }]
+                },
+                "messageSignature": {
+                    "messageDigest": {
+                        "algorithm": "SHA2_256",
+                        "digest": base64::encode(&hex::decode(checksum).unwrap())
+                    },
+                    "signature": base64::encode(&signature)
+                }
+            })
+            .to_string()
+        }
+    }
+
+    fn error(sigstore: &Sigstore, bundle: &str) -> String {
+        verify_bundle(bundle, CHECKSUM, &sigstore.trust_root())
+            .unwrap_err()
+            .to_string()
+    }
+
+    #[test]
+    fn concatenated_log_keys_are_split() {
+        let keys = [ec_key(), ec_key()];
+        let pem = keys
+            .iter()
+            .map(|key| String::from_utf8(key.public_key_to_pem().unwrap()).unwrap())
+            .collect::<String>();
+        let parsed = pem_blocks(&pem)
+            .map(|pem| PKey::public_key_from_pem(pem.as_bytes()).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(parsed.len(), 2);
+        assert!(parsed[1].public_eq(&keys[1]));
+    }
+
+    #[test]
+    fn valid_bundles_return_the_signer() {
+        let sigstore = Sigstore::new();
+        let bundle = sigstore.bundle(CHECKSUM, Tampering::default());
+        let signer = verify_bundle(&bundle, CHECKSUM, &sigstore.trust_root()).unwrap();
+        assert_eq!(signer.as_deref(), Some("owner@example.com"));
+    }
+
+    #[test]
+    fn bundles_have_to_sign_the_crate_file() {
+        let sigstore = Sigstore::new();
+        let other = "0".repeat(64);
+        let bundle = sigstore.bundle(&other, Tampering::default());
+        assert!(error(&sigstore, &bundle).contains("doesn't match the crate file"));
+        let tampering = Tampering {
+            signed_checksum: Some(
+                "00000000000000000000000000000000000000000000000000000000000000ff",
+            ),
+            ..Tampering::default()
+        };
+        let bundle = sigstore.bundle(CHECKSUM, tampering);
+        assert!(error(&sigstore, &bundle).contains("doesn't match the certificate"));
+        assert!(verify_bundle("{}", CHECKSUM, &sigstore.trust_root()).is_err());
+    }
+
+    #[test]
+    fn certificates_have_to_be_issued_by_fulcio() {
+        let sigstore = Sigstore::new();
+        let tampering = Tampering {
+            self_signed: true,
+            ..Tampering::default()
+        };
+        let bundle = sigstore.bundle(CHECKSUM, tampering);
+        assert!(error(&sigstore, &bundle).contains("wasn't issued by Fulcio"));
+    }
+
+    #[test]
+    fn signatures_have_to_be_in_a_known_log() {
+        let sigstore = Sigstore::new();
+        let tampering = Tampering {
+            unknown_log: true,
+            ..Tampering::default()
+        };
+        let bundle = sigstore.bundle(CHECKSUM, tampering);
+        assert!(error(&sigstore, &bundle).contains("no transparency log entry of a known log"));
+
+        let tampering = Tampering {
+            logged_checksum: Some(
+                "00000000000000000000000000000000000000000000000000000000000000ff",
+            ),
+            ..Tampering::default()
+        };
+        let bundle = sigstore.bundle(CHECKSUM, tampering);
+        assert!(error(&sigstore, &bundle).contains("doesn't record this signature"));
+
+        let bundle = sigstore
+            .bundle(CHECKSUM, Tampering::default())
+            .replace("\"logIndex\":\"1\"", "\"logIndex\":\"2\"");
+        assert!(error(&sigstore, &bundle).contains("signed entry timestamp doesn't match"));
+    }
+
+    #[test]
+    fn certificates_have_to_be_valid_when_the_signature_was_logged() {
+        let sigstore = Sigstore::new();
+        let tampering = Tampering {
+            logged_days_ago: 2,
+            ..Tampering::default()
+        };
+        let bundle = sigstore.bundle(CHECKSUM, tampering);
+        assert!(error(&sigstore, &bundle).contains("wasn't valid when the signature was logged"));
+    }
+}
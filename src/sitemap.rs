@@ -0,0 +1,242 @@
+//! Sitemaps for search engine crawlers.
+//!
+//! The `generate_sitemaps` job writes the pages of all crates, users,
+//! categories and keywords into sitemap files in the object storage, and
+//! records the files in the `sitemaps` table. `/sitemap.xml` only lists those
+//! files, so crawlers don't cause any expensive queries.
+//!
+//! The pages are linked on the domain in the `DOMAIN_NAME` environment
+//! variable, `crates.io` by default.
+
+use chrono::NaiveDateTime;
+use diesel::dsl::exists;
+use diesel::prelude::*;
+use htmlescape::encode_minimal;
+use swirl::PerformError;
+
+use crate::background_jobs::Environment;
+use crate::models::OwnerKind;
+use crate::schema::{categories, crate_owners, crates, keywords, sitemaps, users};
+
+/// The maximum number of URLs in a single sitemap file, as defined by
+/// <https://www.sitemaps.org/protocol.html>.
+const MAX_URLS_PER_FILE: usize = 50_000;
+
+/// A page of the frontend, and when its content last changed, if known.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SitemapUrl {
+    pub path: String,
+    pub last_modified: Option<NaiveDateTime>,
+}
+
+impl SitemapUrl {
+    fn new(path: String, last_modified: Option<NaiveDateTime>) -> Self {
+        Self {
+            path,
+            last_modified,
+        }
+    }
+}
+
+/// A sitemap file which has been generated by the last `generate_sitemaps`
+/// run.
+#[derive(Debug, Clone, Queryable)]
+pub struct Sitemap {
+    pub file_name: String,
+    pub url_count: i32,
+    pub generated_at: NaiveDateTime,
+}
+
+impl Sitemap {
+    pub fn all(conn: &PgConnection) -> QueryResult<Vec<Self>> {
+        sitemaps::table.order(sitemaps::file_name).load(conn)
+    }
+}
+
+/// Renders a sitemap file containing the given URLs.
+pub fn urlset_xml(base_url: &str, urls: &[SitemapUrl]) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+    );
+    for url in urls {
+        xml.push_str("<url><loc>");
+        xml.push_str(&encode_minimal(&format!("{}{}", base_url, url.path)));
+        xml.push_str("</loc>");
+        if let Some(last_modified) = url.last_modified {
+            xml.push_str("<lastmod>");
+            xml.push_str(&w3c_datetime(last_modified));
+            xml.push_str("</lastmod>");
+        }
+        xml.push_str("</url>\n");
+    }
+    xml.push_str("</urlset>\n");
+    xml
+}
+
+/// Renders a sitemap index listing the given sitemap files, each with its URL
+/// and the time it was generated.
+pub fn sitemap_index_xml(sitemaps: &[(String, NaiveDateTime)]) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <sitemapindex xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+    );
+    for (location, generated_at) in sitemaps {
+        xml.push_str("<sitemap><loc>");
+        xml.push_str(&encode_minimal(location));
+        xml.push_str("</loc><lastmod>");
+        xml.push_str(&w3c_datetime(*generated_at));
+        xml.push_str("</lastmod></sitemap>\n");
+    }
+    xml.push_str("</sitemapindex>\n");
+    xml
+}
+
+fn w3c_datetime(time: NaiveDateTime) -> String {
+    time.format("%Y-%m-%dT%H:%M:%S+00:00").to_string()
+}
+
+/// Splits the URLs of one kind of page into files named `<kind>-<n>.xml`.
+fn split_into_files(kind: &str, urls: &[SitemapUrl]) -> Vec<(String, &[SitemapUrl])> {
+    urls.chunks(MAX_URLS_PER_FILE)
+        .enumerate()
+        .map(|(i, urls)| (format!("{}-{}.xml", kind, i + 1), urls))
+        .collect()
+}
+
+fn crate_urls(conn: &PgConnection) -> QueryResult<Vec<SitemapUrl>> {
+    let crates = crates::table
+        .select((crates::name, crates::updated_at))
+        .order(crates::name)
+        .load::<(String, NaiveDateTime)>(conn)?;
+    Ok(crates
+        .into_iter()
+        .map(|(name, updated_at)| SitemapUrl::new(format!("/crates/{}", name), Some(updated_at)))
+        .collect())
+}
+
+/// Only users owning at least one crate have a profile worth indexing.
+fn user_urls(conn: &PgConnection) -> QueryResult<Vec<SitemapUrl>> {
+    let logins = users::table
+        .filter(exists(
+            crate_owners::table
+                .filter(crate_owners::owner_id.eq(users::id))
+                .filter(crate_owners::owner_kind.eq(OwnerKind::User as i32))
+                .filter(crate_owners::deleted.eq(false)),
+        ))
+        .select(users::gh_login)
+        .order(users::gh_login)
+        .load::<String>(conn)?;
+    Ok(logins
+        .into_iter()
+        .map(|login| SitemapUrl::new(format!("/users/{}", login), None))
+        .collect())
+}
+
+fn category_urls(conn: &PgConnection) -> QueryResult<Vec<SitemapUrl>> {
+    let slugs = categories::table
+        .select(categories::slug)
+        .order(categories::slug)
+        .load::<String>(conn)?;
+    Ok(slugs
+        .into_iter()
+        .map(|slug| SitemapUrl::new(format!("/categories/{}", slug), None))
+        .collect())
+}
+
+fn keyword_urls(conn: &PgConnection) -> QueryResult<Vec<SitemapUrl>> {
+    let keywords = keywords::table
+        .filter(keywords::crates_cnt.gt(0))
+        .select(keywords::keyword)
+        .order(keywords::keyword)
+        .load::<String>(conn)?;
+    Ok(keywords
+        .into_iter()
+        .map(|keyword| SitemapUrl::new(format!("/keywords/{}", keyword), None))
+        .collect())
+}
+
+#[swirl::background_job]
+pub fn generate_sitemaps(env: &Environment) -> Result<(), PerformError> {
+    let _heartbeat = env.heartbeat("generate_sitemaps")?;
+    let domain_name = dotenv::var("DOMAIN_NAME").unwrap_or_else(|_| "crates.io".into());
+    let base_url = format!("https://{}", domain_name);
+
+    let conn = env.connection()?;
+    let pages = vec![
+        ("crates", crate_urls(&conn)?),
+        ("users", user_urls(&conn)?),
+        ("categories", category_urls(&conn)?),
+        ("keywords", keyword_urls(&conn)?),
+    ];
+
+    let mut files = Vec::new();
+    for (kind, urls) in &pages {
+        for (file_name, urls) in split_into_files(kind, urls) {
+            let xml = urlset_xml(&base_url, urls);
+            env.uploader
+                .upload_sitemap(env.http_client(), &file_name, xml)?;
+            files.push((file_name, urls.len() as i32));
+        }
+    }
+
+    // Files which are no longer generated stay in the object storage, but
+    // aren't listed anymore.
+    conn.transaction::<_, diesel::result::Error, _>(|| {
+        diesel::delete(sitemaps::table).execute(&*conn)?;
+        let rows = files
+            .iter()
+            .map(|(file_name, url_count)| {
+                (
+                    sitemaps::file_name.eq(file_name),
+                    sitemaps::url_count.eq(url_count),
+                )
+            })
+            .collect::<Vec<_>>();
+        diesel::insert_into(sitemaps::table)
+            .values(&rows)
+            .execute(&*conn)?;
+        Ok(())
+    })?;
+
+    println!("Generated {} sitemap files", files.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn urlset() {
+        let updated_at = NaiveDate::from_ymd(2020, 2, 17).and_hms(9, 30, 0);
+        let urls = vec![
+            SitemapUrl::new("/crates/serde".into(), Some(updated_at)),
+            SitemapUrl::new("/keywords/a&b".into(), None),
+        ];
+        assert_eq!(
+            urlset_xml("https://crates.io", &urls),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n\
+             <url><loc>https://crates.io/crates/serde</loc><lastmod>2020-02-17T09:30:00+00:00</lastmod></url>\n\
+             <url><loc>https://crates.io/keywords/a&amp;b</loc></url>\n\
+             </urlset>\n"
+        );
+    }
+
+    #[test]
+    fn large_sitemaps_are_split() {
+        let urls = (0..MAX_URLS_PER_FILE + 1)
+            .map(|i| SitemapUrl::new(format!("/crates/{}", i), None))
+            .collect::<Vec<_>>();
+        let files = split_into_files("crates", &urls);
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].0, "crates-1.xml");
+        assert_eq!(files[0].1.len(), MAX_URLS_PER_FILE);
+        assert_eq!(files[1].0, "crates-2.xml");
+        assert_eq!(files[1].1.len(), 1);
+
+        assert!(split_into_files("users", &[]).is_empty());
+    }
+}
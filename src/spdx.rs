@@ -0,0 +1,295 @@
+//! Normalization of SPDX license expressions, like `MIT OR Apache-2.0`.
+//!
+//! The identifiers themselves are validated by the `license_exprs` crate.
+//! This module parses the structure of an expression, so that it can be
+//! stored with canonical operators, spacing and parentheses, together with
+//! the list of licenses it mentions, which is what filtering by license
+//! needs.
+//!
+//! Older crates separate alternatives with `/`, as in `MIT/Apache-2.0`,
+//! which is normalized to `OR`.
+
+use std::fmt;
+
+/// A parsed license expression.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LicenseExpr {
+    /// A license like `GPL-2.0+`, optionally with an exception like
+    /// `LLVM-exception`.
+    License {
+        id: String,
+        exception: Option<String>,
+    },
+    And(Vec<LicenseExpr>),
+    Or(Vec<LicenseExpr>),
+}
+
+impl LicenseExpr {
+    /// Parses an expression. The operators `AND`, `OR` and `WITH` are
+    /// accepted in any case.
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let tokens = tokenize(expression);
+        if tokens.is_empty() {
+            return Err("the license expression is empty".into());
+        }
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.or()?;
+        match parser.next() {
+            None => Ok(expr),
+            Some(token) => Err(format!("unexpected `{}` in the license expression", token)),
+        }
+    }
+
+    /// The licenses mentioned by the expression, sorted and without
+    /// duplicates. The `+` of "or later" licenses is left out, so that
+    /// `GPL-2.0+` is found as `GPL-2.0`.
+    pub fn licenses(&self) -> Vec<String> {
+        fn collect(expr: &LicenseExpr, licenses: &mut Vec<String>) {
+            match expr {
+                LicenseExpr::License { id, .. } => {
+                    licenses.push(id.trim_end_matches('+').to_string())
+                }
+                LicenseExpr::And(terms) | LicenseExpr::Or(terms) => {
+                    for term in terms {
+                        collect(term, licenses);
+                    }
+                }
+            }
+        }
+
+        let mut licenses = Vec::new();
+        collect(self, &mut licenses);
+        licenses.sort();
+        licenses.dedup();
+        licenses
+    }
+}
+
+/// Writes the expression with uppercase operators, single spaces, and only
+/// the parentheses which are needed, around `OR` within `AND`.
+impl fmt::Display for LicenseExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LicenseExpr::License { id, exception } => {
+                write!(f, "{}", id)?;
+                if let Some(exception) = exception {
+                    write!(f, " WITH {}", exception)?;
+                }
+                Ok(())
+            }
+            LicenseExpr::And(terms) => {
+                for (i, term) in terms.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" AND ")?;
+                    }
+                    match term {
+                        LicenseExpr::Or(_) => write!(f, "({})", term)?,
+                        _ => write!(f, "{}", term)?,
+                    }
+                }
+                Ok(())
+            }
+            LicenseExpr::Or(terms) => {
+                for (i, term) in terms.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" OR ")?;
+                    }
+                    write!(f, "{}", term)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn tokenize(expression: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    for c in expression.chars() {
+        if c.is_whitespace() || c == '(' || c == ')' || c == '/' {
+            if !word.is_empty() {
+                tokens.push(std::mem::replace(&mut word, String::new()));
+            }
+            if !c.is_whitespace() {
+                tokens.push(c.to_string());
+            }
+        } else {
+            word.push(c);
+        }
+    }
+    if !word.is_empty() {
+        tokens.push(word);
+    }
+    tokens
+}
+
+fn is_operator(token: &str) -> bool {
+    ["AND", "OR", "WITH"]
+        .iter()
+        .any(|operator| token.eq_ignore_ascii_case(operator))
+}
+
+fn is_punctuation(token: &str) -> bool {
+    token == "(" || token == ")" || token == "/"
+}
+
+/// A recursive descent parser, where `WITH` binds tighter than `AND`, which
+/// binds tighter than `OR`.
+struct Parser {
+    tokens: Vec<String>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Option<String> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<LicenseExpr, String> {
+        let mut terms = vec![self.and()?];
+        while let Some(token) = self.peek() {
+            if !token.eq_ignore_ascii_case("OR") && token != "/" {
+                break;
+            }
+            self.pos += 1;
+            terms.push(self.and()?);
+        }
+        Ok(flatten(terms, LicenseExpr::Or, |expr| match expr {
+            LicenseExpr::Or(terms) => Ok(terms),
+            other => Err(other),
+        }))
+    }
+
+    fn and(&mut self) -> Result<LicenseExpr, String> {
+        let mut terms = vec![self.license()?];
+        while self
+            .peek()
+            .map_or(false, |token| token.eq_ignore_ascii_case("AND"))
+        {
+            self.pos += 1;
+            terms.push(self.license()?);
+        }
+        Ok(flatten(terms, LicenseExpr::And, |expr| match expr {
+            LicenseExpr::And(terms) => Ok(terms),
+            other => Err(other),
+        }))
+    }
+
+    fn license(&mut self) -> Result<LicenseExpr, String> {
+        let token = self.next().ok_or_else(|| {
+            String::from("the license expression ends where a license was expected")
+        })?;
+        if token == "(" {
+            let expr = self.or()?;
+            return match self.next() {
+                Some(ref token) if token == ")" => Ok(expr),
+                _ => Err("a `(` in the license expression is never closed".into()),
+            };
+        }
+        if is_punctuation(&token) || is_operator(&token) {
+            return Err(format!(
+                "expected a license instead of `{}` in the license expression",
+                token
+            ));
+        }
+
+        let exception = if self
+            .peek()
+            .map_or(false, |token| token.eq_ignore_ascii_case("WITH"))
+        {
+            self.pos += 1;
+            match self.next() {
+                Some(exception) if !is_operator(&exception) && !is_punctuation(&exception) => {
+                    Some(exception)
+                }
+                _ => return Err(format!("`{} WITH` isn't followed by an exception", token)),
+            }
+        } else {
+            None
+        };
+        Ok(LicenseExpr::License {
+            id: token,
+            exception,
+        })
+    }
+}
+
+/// Joins the terms of an operator, merging in the terms of parenthesized
+/// expressions using the same operator.
+fn flatten(
+    terms: Vec<LicenseExpr>,
+    operator: fn(Vec<LicenseExpr>) -> LicenseExpr,
+    split: impl Fn(LicenseExpr) -> Result<Vec<LicenseExpr>, LicenseExpr>,
+) -> LicenseExpr {
+    if terms.len() == 1 {
+        return terms.into_iter().next().unwrap();
+    }
+    let mut flat = Vec::new();
+    for term in terms {
+        match split(term) {
+            Ok(inner) => flat.extend(inner),
+            Err(term) => flat.push(term),
+        }
+    }
+    operator(flat)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalize(expression: &str) -> String {
+        LicenseExpr::parse(expression).unwrap().to_string()
+    }
+
+    #[test]
+    fn expressions_are_normalized() {
+        assert_eq!(normalize("MIT"), "MIT");
+        assert_eq!(normalize("MIT/Apache-2.0"), "MIT OR Apache-2.0");
+        assert_eq!(normalize("MIT  or\tApache-2.0"), "MIT OR Apache-2.0");
+        assert_eq!(
+            normalize("(MIT OR (ISC or Zlib)) AND (Unicode-DFS-2016)"),
+            "(MIT OR ISC OR Zlib) AND Unicode-DFS-2016"
+        );
+        assert_eq!(
+            normalize("MIT AND ISC OR Apache-2.0 with LLVM-exception"),
+            "MIT AND ISC OR Apache-2.0 WITH LLVM-exception"
+        );
+        assert_eq!(normalize("((GPL-2.0+))"), "GPL-2.0+");
+    }
+
+    #[test]
+    fn licenses_are_listed_once() {
+        let expr = LicenseExpr::parse("(MIT OR Apache-2.0) AND (MIT OR GPL-2.0+)").unwrap();
+        assert_eq!(expr.licenses(), vec!["Apache-2.0", "GPL-2.0", "MIT"]);
+        let expr = LicenseExpr::parse("Apache-2.0 WITH LLVM-exception").unwrap();
+        assert_eq!(expr.licenses(), vec!["Apache-2.0"]);
+    }
+
+    #[test]
+    fn invalid_expressions_are_rejected() {
+        for expression in &[
+            "",
+            "MIT OR",
+            "AND MIT",
+            "MIT Apache-2.0",
+            "(MIT OR Apache-2.0",
+            "MIT)",
+            "MIT //",
+            "Apache-2.0 WITH",
+            "MIT WITH OR",
+        ] {
+            assert!(
+                LicenseExpr::parse(expression).is_err(),
+                "`{}` was accepted",
+                expression
+            );
+        }
+    }
+}
@@ -0,0 +1,383 @@
+//! The places uploaded files (crate files, rendered readmes, SBOMs, ...) can
+//! be stored in.
+//!
+//! Production stores everything in S3, self-hosted registries can keep the
+//! files in a local directory and tests can use an in-memory store, which
+//! lets them inspect what would have been uploaded.
+
+use parking_lot::Mutex;
+use reqwest::{blocking::Client, header};
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::util::Error;
+use crate::{env, Env, Replica};
+
+/// A place to store uploaded files in.
+pub trait Storage: fmt::Debug + Send + Sync {
+    /// Stores `content` at `path`, replacing any previous file.
+    ///
+    /// It returns where the file has been stored, if the backend knows.
+    fn put(
+        &self,
+        client: &Client,
+        path: &str,
+        content: Box<dyn Read + Send>,
+        content_length: u64,
+        content_type: &str,
+        extra_headers: header::HeaderMap,
+    ) -> Result<Option<String>, Error>;
+
+    /// Reads the file stored at `path`.
+    fn read(&self, client: &Client, path: &str) -> Result<Vec<u8>, Error>;
+
+    /// Removes the file stored at `path`.
+    fn delete(&self, client: &Client, path: &str) -> Result<(), Error>;
+
+    /// Returns the URL the file at `path` is served from.
+    ///
+    /// The function doesn't check for the existence of the file.
+    fn url(&self, path: &str) -> String;
+
+    /// Returns the directory files are stored in, if they have to be served
+    /// by the application itself.
+    fn local_directory(&self) -> Option<&Path> {
+        None
+    }
+}
+
+/// Which storage backend to use.
+#[derive(Clone, Debug)]
+pub enum StorageConfig {
+    S3 {
+        bucket: s3::Bucket,
+        cdn: Option<String>,
+    },
+    Local {
+        directory: PathBuf,
+    },
+    Memory,
+}
+
+impl StorageConfig {
+    /// Reads the storage backend from the environment.
+    ///
+    /// - `STORAGE_BACKEND`: Either `s3`, `local` or `memory`. When it is not
+    ///   set, production uses `s3` and development uses `s3` if `S3_BUCKET`
+    ///   is set, `local` otherwise.
+    /// - `S3_BUCKET`, `S3_REGION`, `S3_ACCESS_KEY`, `S3_SECRET_KEY`, `S3_CDN`:
+    ///   The bucket of the `s3` backend. The keys are only required for the
+    ///   primary instance in production, since read-only mirrors don't upload.
+    /// - `LOCAL_UPLOADS_DIR`: The directory of the `local` backend,
+    ///   `local_uploads` by default.
+    pub fn from_environment(api_protocol: &str, cargo_env: Env, mirror: Replica) -> Self {
+        let production = cargo_env == Env::Production;
+        let backend = dotenv::var("STORAGE_BACKEND").ok();
+        let backend = backend.as_deref().unwrap_or_else(|| {
+            if production || dotenv::var("S3_BUCKET").is_ok() {
+                "s3"
+            } else {
+                "local"
+            }
+        });
+
+        match backend {
+            "s3" => {
+                let key = |var: &str| {
+                    if production && mirror == Replica::Primary {
+                        env(var)
+                    } else {
+                        dotenv::var(var).unwrap_or_default()
+                    }
+                };
+                StorageConfig::S3 {
+                    bucket: s3::Bucket::new(
+                        env("S3_BUCKET"),
+                        dotenv::var("S3_REGION").ok(),
+                        key("S3_ACCESS_KEY"),
+                        key("S3_SECRET_KEY"),
+                        api_protocol,
+                    ),
+                    cdn: dotenv::var("S3_CDN").ok(),
+                }
+            }
+            "local" => StorageConfig::Local {
+                directory: dotenv::var("LOCAL_UPLOADS_DIR")
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|_| PathBuf::from("local_uploads")),
+            },
+            "memory" => StorageConfig::Memory,
+            other => panic!("Unknown STORAGE_BACKEND `{}`", other),
+        }
+    }
+
+    pub fn build(&self) -> Arc<dyn Storage> {
+        match self {
+            StorageConfig::S3 { bucket, cdn } => Arc::new(S3Storage {
+                bucket: bucket.clone(),
+                cdn: cdn.clone(),
+            }),
+            StorageConfig::Local { directory } => Arc::new(LocalStorage::new(directory)),
+            StorageConfig::Memory => Arc::new(MemoryStorage::default()),
+        }
+    }
+}
+
+/// Uploads files to an S3 bucket and serves them from the bucket or its CDN.
+#[derive(Clone, Debug)]
+pub struct S3Storage {
+    pub bucket: s3::Bucket,
+    pub cdn: Option<String>,
+}
+
+impl Storage for S3Storage {
+    fn put(
+        &self,
+        client: &Client,
+        path: &str,
+        content: Box<dyn Read + Send>,
+        content_length: u64,
+        content_type: &str,
+        extra_headers: header::HeaderMap,
+    ) -> Result<Option<String>, Error> {
+        self.bucket.put(
+            client,
+            path,
+            content,
+            content_length,
+            content_type,
+            extra_headers,
+        )?;
+        Ok(Some(String::from(path)))
+    }
+
+    fn read(&self, client: &Client, path: &str) -> Result<Vec<u8>, Error> {
+        let response = self.bucket.get(client, path)?;
+        Ok(response.bytes()?.to_vec())
+    }
+
+    fn delete(&self, client: &Client, path: &str) -> Result<(), Error> {
+        self.bucket.delete(client, path)?;
+        Ok(())
+    }
+
+    fn url(&self, path: &str) -> String {
+        let host = match self.cdn {
+            Some(ref s) => s.clone(),
+            None => self.bucket.host(),
+        };
+        format!("https://{}/{}", host, path)
+    }
+}
+
+/// Stores files in a local directory, which the application serves them from.
+///
+/// Headers like `Cache-Control` are not kept, since the files are served as
+/// static files.
+#[derive(Clone, Debug)]
+pub struct LocalStorage {
+    directory: PathBuf,
+}
+
+impl LocalStorage {
+    /// Stores files in `directory`, which is resolved against the current
+    /// directory if it is relative.
+    pub fn new(directory: impl AsRef<Path>) -> Self {
+        let directory = directory.as_ref();
+        let directory = if directory.is_relative() {
+            std::env::current_dir().unwrap().join(directory)
+        } else {
+            directory.to_path_buf()
+        };
+        LocalStorage { directory }
+    }
+}
+
+impl Storage for LocalStorage {
+    fn put(
+        &self,
+        _client: &Client,
+        path: &str,
+        mut content: Box<dyn Read + Send>,
+        _content_length: u64,
+        _content_type: &str,
+        _extra_headers: header::HeaderMap,
+    ) -> Result<Option<String>, Error> {
+        let filename = self.directory.join(path.trim_start_matches('/'));
+        if let Some(dir) = filename.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = File::create(&filename)?;
+        io::copy(&mut content, &mut file)?;
+        Ok(filename.to_str().map(String::from))
+    }
+
+    fn read(&self, _client: &Client, path: &str) -> Result<Vec<u8>, Error> {
+        Ok(fs::read(self.directory.join(path.trim_start_matches('/')))?)
+    }
+
+    fn delete(&self, _client: &Client, path: &str) -> Result<(), Error> {
+        fs::remove_file(self.directory.join(path.trim_start_matches('/')))?;
+        Ok(())
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("/{}", path)
+    }
+
+    fn local_directory(&self) -> Option<&Path> {
+        Some(&self.directory)
+    }
+}
+
+/// A file kept by `MemoryStorage`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StoredFile {
+    pub content: Vec<u8>,
+    pub content_type: String,
+    pub headers: header::HeaderMap,
+}
+
+/// Keeps files in memory, for tests and throwaway instances.
+///
+/// Files are "served" from `memory://`, so nothing will be able to download
+/// them.
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    files: Mutex<BTreeMap<String, StoredFile>>,
+}
+
+impl MemoryStorage {
+    /// Returns the file stored at `path`.
+    pub fn get(&self, path: &str) -> Option<StoredFile> {
+        self.files.lock().get(path).cloned()
+    }
+
+    /// Returns the paths of all stored files, in order.
+    pub fn paths(&self) -> Vec<String> {
+        self.files.lock().keys().cloned().collect()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn put(
+        &self,
+        _client: &Client,
+        path: &str,
+        mut content: Box<dyn Read + Send>,
+        content_length: u64,
+        content_type: &str,
+        extra_headers: header::HeaderMap,
+    ) -> Result<Option<String>, Error> {
+        let mut buffer = Vec::with_capacity(content_length as usize);
+        content.read_to_end(&mut buffer)?;
+        let file = StoredFile {
+            content: buffer,
+            content_type: content_type.into(),
+            headers: extra_headers,
+        };
+        self.files.lock().insert(path.into(), file);
+        Ok(Some(String::from(path)))
+    }
+
+    fn read(&self, _client: &Client, path: &str) -> Result<Vec<u8>, Error> {
+        self.files
+            .lock()
+            .get(path)
+            .map(|file| file.content.clone())
+            .ok_or_else(|| Error::from(format!("no file is stored at `{}`", path)))
+    }
+
+    fn delete(&self, _client: &Client, path: &str) -> Result<(), Error> {
+        self.files.lock().remove(path);
+        Ok(())
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("memory://{}", path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn put(storage: &dyn Storage, path: &str, content: &'static [u8]) {
+        let client = Client::new();
+        let mut headers = header::HeaderMap::new();
+        headers.insert(header::CACHE_CONTROL, "public".parse().unwrap());
+        storage
+            .put(
+                &client,
+                path,
+                Box::new(Cursor::new(content)),
+                content.len() as u64,
+                "text/plain",
+                headers,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn memory_storage_keeps_files_and_headers() {
+        let storage = MemoryStorage::default();
+        put(&storage, "crates/foo/foo-1.0.0.crate", b"old");
+        put(&storage, "crates/foo/foo-1.0.0.crate", b"new");
+        put(&storage, "readmes/foo/foo-1.0.0.html", b"readme");
+
+        assert_eq!(
+            storage.paths(),
+            vec!["crates/foo/foo-1.0.0.crate", "readmes/foo/foo-1.0.0.html"]
+        );
+        let file = storage.get("crates/foo/foo-1.0.0.crate").unwrap();
+        assert_eq!(file.content, b"new");
+        assert_eq!(file.content_type, "text/plain");
+        assert_eq!(file.headers[header::CACHE_CONTROL], "public");
+        assert_eq!(storage.get("crates/bar/bar-1.0.0.crate"), None);
+    }
+
+    #[test]
+    fn local_storage_writes_files_below_its_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = LocalStorage::new(dir.path());
+        put(&storage, "crates/foo/foo-1.0.0.crate", b"crate");
+
+        let written = fs::read(dir.path().join("crates/foo/foo-1.0.0.crate")).unwrap();
+        assert_eq!(written, b"crate");
+        assert_eq!(storage.local_directory(), Some(dir.path()));
+        assert_eq!(
+            storage.url("crates/foo/foo-1.0.0.crate"),
+            "/crates/foo/foo-1.0.0.crate"
+        );
+
+        let client = Client::new();
+        let read = storage.read(&client, "crates/foo/foo-1.0.0.crate").unwrap();
+        assert_eq!(read, b"crate");
+        storage
+            .delete(&client, "crates/foo/foo-1.0.0.crate")
+            .unwrap();
+        assert!(!dir.path().join("crates/foo/foo-1.0.0.crate").exists());
+    }
+
+    #[test]
+    fn s3_storage_urls_prefer_the_cdn() {
+        let bucket = s3::Bucket::new("bucket".into(), None, "".into(), "".into(), "https");
+        let storage = S3Storage {
+            bucket: bucket.clone(),
+            cdn: None,
+        };
+        assert_eq!(storage.url("a/b"), format!("https://{}/a/b", bucket.host()));
+
+        let storage = S3Storage {
+            bucket,
+            cdn: Some("static.crates.io".into()),
+        };
+        assert_eq!(storage.url("a/b"), "https://static.crates.io/a/b");
+    }
+}
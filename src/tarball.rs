@@ -0,0 +1,591 @@
+//! Analyzers which inspect the entries of a crate file while it's verified.
+//!
+//! All analyzers run in the single pass over the archive which verifies it,
+//! so adding one doesn't require reading crate files again, and backfills can
+//! run several of them at once. Every analyzer produces a JSON report, which
+//! is stored in `version_analyses` under its name.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use serde_json::Value;
+
+/// The names of all analyzers, in the order they run.
+pub const ANALYZERS: &[&str] = &[
+    "size",
+    "binary",
+    "no_std",
+    "license",
+    "executables",
+    "fingerprint",
+];
+
+/// Bytes at the start of a file which are checked for NUL bytes.
+const BINARY_SNIFF_LENGTH: usize = 8 * 1024;
+
+/// Bytes of a source or license file which are read at most.
+const MAX_TEXT_LENGTH: usize = 64 * 1024;
+
+/// The number of files listed by the size report.
+const LARGEST_FILES: usize = 5;
+
+/// Bytes of a file whose entropy is measured, and the size files need to
+/// have for it to be measured at all, since the entropy of a few bytes says
+/// little about them.
+const ENTROPY_SAMPLE_LENGTH: usize = 64 * 1024;
+const MIN_ENTROPY_SIZE: u64 = 4 * 1024;
+
+/// Bits per byte above which a file looks compressed or encrypted.
+const HIGH_ENTROPY: f64 = 7.5;
+
+/// Bytes a normalized source file needs to have to be fingerprinted, since
+/// short files like a `mod` declaration are shared by unrelated crates.
+const MIN_FINGERPRINT_LENGTH: usize = 256;
+
+/// A pass over the entries of a crate file.
+pub trait Analyzer {
+    /// The name the report is stored under.
+    fn name(&self) -> &'static str;
+
+    /// How many bytes of the file at `path` the analyzer needs to see.
+    fn wants_contents(&self, _path: &Path) -> usize {
+        0
+    }
+
+    /// Called for every entry of the archive. `path` is relative to the
+    /// `$name-$vers/` directory, and `contents` holds at least the bytes
+    /// asked for by `wants_contents`, unless the file is shorter.
+    fn entry(&mut self, path: &Path, size: u64, contents: &[u8]);
+
+    /// The report, once all entries were seen.
+    fn finish(&mut self) -> Value;
+}
+
+/// Creates the analyzers with the given names.
+pub fn analyzers(names: &[&str]) -> Result<Vec<Box<dyn Analyzer>>, String> {
+    names
+        .iter()
+        .map(|name| -> Result<Box<dyn Analyzer>, String> {
+            match *name {
+                "size" => Ok(Box::new(SizeReport::default())),
+                "binary" => Ok(Box::new(BinaryDetector::default())),
+                "no_std" => Ok(Box::new(NoStdDetector::default())),
+                "license" => Ok(Box::new(LicenseExtractor::default())),
+                "executables" => Ok(Box::new(ExecutableScanner::default())),
+                "fingerprint" => Ok(Box::new(ContentFingerprint::default())),
+                _ => Err(format!("unknown analyzer `{}`", name)),
+            }
+        })
+        .collect()
+}
+
+/// Creates all analyzers, as run on every publish.
+pub fn default_analyzers() -> Vec<Box<dyn Analyzer>> {
+    analyzers(ANALYZERS).unwrap()
+}
+
+/// The number of files, their unpacked size, and the largest ones.
+#[derive(Debug, Default)]
+struct SizeReport {
+    files: Vec<(String, u64)>,
+}
+
+impl Analyzer for SizeReport {
+    fn name(&self) -> &'static str {
+        "size"
+    }
+
+    fn entry(&mut self, path: &Path, size: u64, _contents: &[u8]) {
+        self.files.push((path.display().to_string(), size));
+    }
+
+    fn finish(&mut self) -> Value {
+        let unpacked_size = self.files.iter().map(|(_, size)| size).sum::<u64>();
+        self.files
+            .sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        let largest = self
+            .files
+            .iter()
+            .take(LARGEST_FILES)
+            .map(|(path, size)| json!({ "path": path, "size": size }))
+            .collect::<Vec<_>>();
+        json!({
+            "files": self.files.len(),
+            "unpacked_size": unpacked_size,
+            "largest": largest,
+        })
+    }
+}
+
+/// Files which look binary, because they start with a NUL byte within their
+/// first few kilobytes.
+#[derive(Debug, Default)]
+struct BinaryDetector {
+    binary_files: Vec<String>,
+}
+
+impl Analyzer for BinaryDetector {
+    fn name(&self) -> &'static str {
+        "binary"
+    }
+
+    fn wants_contents(&self, _path: &Path) -> usize {
+        BINARY_SNIFF_LENGTH
+    }
+
+    fn entry(&mut self, path: &Path, _size: u64, contents: &[u8]) {
+        let start = &contents[..contents.len().min(BINARY_SNIFF_LENGTH)];
+        if start.contains(&0) {
+            self.binary_files.push(path.display().to_string());
+        }
+    }
+
+    fn finish(&mut self) -> Value {
+        json!({ "binary_files": self.binary_files })
+    }
+}
+
+/// Whether `src/lib.rs` declares the crate `no_std`, possibly only when a
+/// feature is disabled. Crates without `src/lib.rs` are reported as `null`.
+#[derive(Debug, Default)]
+struct NoStdDetector {
+    no_std: Option<bool>,
+}
+
+impl Analyzer for NoStdDetector {
+    fn name(&self) -> &'static str {
+        "no_std"
+    }
+
+    fn wants_contents(&self, path: &Path) -> usize {
+        if path == Path::new("src/lib.rs") {
+            MAX_TEXT_LENGTH
+        } else {
+            0
+        }
+    }
+
+    fn entry(&mut self, path: &Path, _size: u64, contents: &[u8]) {
+        if path != Path::new("src/lib.rs") {
+            return;
+        }
+        let source = String::from_utf8_lossy(contents);
+        let no_std = source.lines().any(|line| {
+            let line = line.split_whitespace().collect::<String>();
+            line.starts_with("#![no_std]")
+                || (line.starts_with("#![cfg_attr(") && line.contains(",no_std)]"))
+        });
+        self.no_std = Some(no_std);
+    }
+
+    fn finish(&mut self) -> Value {
+        json!({ "no_std": self.no_std })
+    }
+}
+
+/// The license files at the root of the crate, with the license their text
+/// matches, if it's one of the common ones.
+#[derive(Debug, Default)]
+struct LicenseExtractor {
+    license_files: Vec<Value>,
+}
+
+impl LicenseExtractor {
+    fn is_license_file(path: &Path) -> bool {
+        if path.components().count() != 1 {
+            return false;
+        }
+        let name = path.to_string_lossy().to_uppercase();
+        ["LICENSE", "LICENCE", "COPYING", "UNLICENSE"]
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+    }
+}
+
+/// Identifies a license text by phrases which only appear in it.
+fn identify_license(text: &str) -> Option<&'static str> {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let has = |phrase: &str| text.contains(phrase);
+    if has("Permission is hereby granted, free of charge") {
+        Some("MIT")
+    } else if has("Apache License") && has("Version 2.0") {
+        Some("Apache-2.0")
+    } else if has("Mozilla Public License Version 2.0") {
+        Some("MPL-2.0")
+    } else if has("GNU GENERAL PUBLIC LICENSE") && has("Version 3") {
+        Some("GPL-3.0")
+    } else if has("Redistribution and use in source and binary forms") {
+        if has("Neither the name") {
+            Some("BSD-3-Clause")
+        } else {
+            Some("BSD-2-Clause")
+        }
+    } else if has("Permission to use, copy, modify, and/or distribute this software") {
+        Some("ISC")
+    } else if has("This is free and unencumbered software released into the public domain") {
+        Some("Unlicense")
+    } else {
+        None
+    }
+}
+
+impl Analyzer for LicenseExtractor {
+    fn name(&self) -> &'static str {
+        "license"
+    }
+
+    fn wants_contents(&self, path: &Path) -> usize {
+        if Self::is_license_file(path) {
+            MAX_TEXT_LENGTH
+        } else {
+            0
+        }
+    }
+
+    fn entry(&mut self, path: &Path, _size: u64, contents: &[u8]) {
+        if !Self::is_license_file(path) {
+            return;
+        }
+        let license = identify_license(&String::from_utf8_lossy(contents));
+        self.license_files.push(json!({
+            "path": path.display().to_string(),
+            "license": license,
+        }));
+    }
+
+    fn finish(&mut self) -> Value {
+        json!({ "license_files": self.license_files })
+    }
+}
+
+/// Something in a crate file which doesn't belong into source code, found by
+/// the `executables` analyzer.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanFinding {
+    pub path: String,
+    /// One of `elf`, `pe`, `mach-o`, `native-library` or `high-entropy`.
+    pub kind: String,
+    pub detail: String,
+}
+
+/// Executables, precompiled libraries, and blobs which look compressed or
+/// encrypted without having the extension of a format which is.
+///
+/// Executables are recognized by their headers, libraries by their
+/// extension. Every file is reported once, with the first of these which
+/// applies to it.
+#[derive(Debug, Default)]
+struct ExecutableScanner {
+    findings: Vec<ScanFinding>,
+}
+
+impl ExecutableScanner {
+    fn is_native_library(path: &Path) -> bool {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let extension = name.rsplit('.').next().unwrap_or_default();
+        name.contains(".so.")
+            || (name.contains('.')
+                && ["so", "dll", "dylib", "exe", "a", "lib"].contains(&extension))
+    }
+
+    /// Whether the file has the extension of a compressed format, whose
+    /// entropy is always high.
+    fn is_compressed_format(path: &Path) -> bool {
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        [
+            "gz", "xz", "bz2", "zst", "zip", "png", "jpg", "jpeg", "gif", "webp", "ico", "woff",
+            "woff2",
+        ]
+        .contains(&extension.as_str())
+    }
+}
+
+/// The executable format of a file, recognized by its header.
+fn executable_format(contents: &[u8]) -> Option<&'static str> {
+    let u32_at = |offset: usize, big_endian: bool| -> Option<u32> {
+        let bytes = contents.get(offset..offset + 4)?;
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        Some(if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    };
+
+    if contents.starts_with(b"\x7fELF") {
+        return Some("elf");
+    }
+    // `MZ` alone is too likely to start a text file, so the PE header the
+    // DOS header points to has to be there as well
+    if contents.starts_with(b"MZ") {
+        let pe_header = u32_at(0x3c, false)
+            .and_then(|offset| contents.get(offset as usize..offset as usize + 4));
+        if pe_header == Some(&b"PE\0\0"[..]) {
+            return Some("pe");
+        }
+    }
+    match u32_at(0, true)? {
+        0xfeed_face | 0xfeed_facf | 0xcefa_edfe | 0xcffa_edfe => Some("mach-o"),
+        // Java class files start with the same magic, followed by their
+        // version instead of the small number of architectures
+        0xcafe_babe if u32_at(4, true)? < 20 => Some("mach-o"),
+        _ => None,
+    }
+}
+
+/// The Shannon entropy of `contents`, in bits per byte.
+fn entropy(contents: &[u8]) -> f64 {
+    let mut counts = [0u64; 256];
+    for &byte in contents {
+        counts[byte as usize] += 1;
+    }
+    let length = contents.len() as f64;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / length;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+impl Analyzer for ExecutableScanner {
+    fn name(&self) -> &'static str {
+        "executables"
+    }
+
+    fn wants_contents(&self, _path: &Path) -> usize {
+        ENTROPY_SAMPLE_LENGTH
+    }
+
+    fn entry(&mut self, path: &Path, size: u64, contents: &[u8]) {
+        let finding = |kind: &str, detail: String| ScanFinding {
+            path: path.display().to_string(),
+            kind: kind.into(),
+            detail,
+        };
+        let sample = &contents[..contents.len().min(ENTROPY_SAMPLE_LENGTH)];
+
+        if let Some(format) = executable_format(sample) {
+            let detail = format!("{} executable header", format);
+            self.findings.push(finding(format, detail));
+        } else if Self::is_native_library(path) {
+            let detail = "precompiled library extension".to_string();
+            self.findings.push(finding("native-library", detail));
+        } else if size >= MIN_ENTROPY_SIZE && !Self::is_compressed_format(path) {
+            let entropy = entropy(sample);
+            if entropy > HIGH_ENTROPY {
+                let detail = format!("{:.2} bits of entropy per byte", entropy);
+                self.findings.push(finding("high-entropy", detail));
+            }
+        }
+    }
+
+    fn finish(&mut self) -> Value {
+        json!({ "findings": self.findings })
+    }
+}
+
+/// Hashes of the normalized Rust source files, which are compared with the
+/// ones of popular crates to find copies of them, see `ContentMatch`.
+///
+/// Sources are normalized by dropping blank lines, comments at the start of
+/// a line and the whitespace within lines, so that reformatting a copy or
+/// rewriting its docs doesn't change the hashes. Only the first 8 bytes of
+/// each SHA-256 are kept.
+#[derive(Debug, Default)]
+struct ContentFingerprint {
+    hashes: BTreeSet<String>,
+}
+
+fn is_rust_source(path: &Path) -> bool {
+    path.extension()
+        .map_or(false, |extension| extension == "rs")
+}
+
+fn normalize_source(source: &str) -> String {
+    source
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty() && !line.starts_with("//"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl Analyzer for ContentFingerprint {
+    fn name(&self) -> &'static str {
+        "fingerprint"
+    }
+
+    fn wants_contents(&self, path: &Path) -> usize {
+        if is_rust_source(path) {
+            MAX_TEXT_LENGTH
+        } else {
+            0
+        }
+    }
+
+    fn entry(&mut self, path: &Path, _size: u64, contents: &[u8]) {
+        if !is_rust_source(path) {
+            return;
+        }
+        let normalized = normalize_source(&String::from_utf8_lossy(contents));
+        if normalized.len() >= MIN_FINGERPRINT_LENGTH {
+            let hash = openssl::sha::sha256(normalized.as_bytes());
+            self.hashes.insert(hex::encode(&hash[..8]));
+        }
+    }
+
+    fn finish(&mut self) -> Value {
+        json!({ "hashes": self.hashes })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(analyzer: &mut dyn Analyzer, files: &[(&str, &[u8])]) -> Value {
+        for (path, contents) in files {
+            let path = Path::new(path);
+            let wanted = analyzer.wants_contents(path).min(contents.len());
+            analyzer.entry(path, contents.len() as u64, &contents[..wanted]);
+        }
+        analyzer.finish()
+    }
+
+    #[test]
+    fn sizes_are_reported() {
+        let report = run(
+            &mut SizeReport::default(),
+            &[("Cargo.toml", b"[package]"), ("src/lib.rs", b"")],
+        );
+        assert_eq!(report["files"], 2);
+        assert_eq!(report["unpacked_size"], 9);
+        assert_eq!(report["largest"][0]["path"], "Cargo.toml");
+    }
+
+    #[test]
+    fn binary_files_are_detected() {
+        let report = run(
+            &mut BinaryDetector::default(),
+            &[
+                ("src/lib.rs", b"fn main() {}"),
+                ("blob.bin", b"\x7fELF\0\0"),
+            ],
+        );
+        assert_eq!(report, json!({ "binary_files": ["blob.bin"] }));
+    }
+
+    #[test]
+    fn no_std_is_detected() {
+        let detect = |source: &[u8]| run(&mut NoStdDetector::default(), &[("src/lib.rs", source)]);
+        assert_eq!(detect(b"#![no_std]\n")["no_std"], true);
+        assert_eq!(
+            detect(b"#![cfg_attr(not(feature = \"std\"), no_std)]\n")["no_std"],
+            true
+        );
+        assert_eq!(detect(b"//! #![no_std] in docs\n")["no_std"], false);
+        let report = run(&mut NoStdDetector::default(), &[("src/main.rs", b"")]);
+        assert_eq!(report["no_std"], Value::Null);
+    }
+
+    #[test]
+    fn licenses_are_identified() {
+        let report = run(
+            &mut LicenseExtractor::default(),
+            &[
+                (
+                    "LICENSE-MIT",
+                    b"Permission is hereby granted, free of\n  charge, to any person",
+                ),
+                ("LICENSE-OTHER", b"All rights reserved"),
+                ("src/LICENSE", b"ignored"),
+            ],
+        );
+        assert_eq!(
+            report,
+            json!({ "license_files": [
+                { "path": "LICENSE-MIT", "license": "MIT" },
+                { "path": "LICENSE-OTHER", "license": null },
+            ]})
+        );
+    }
+
+    #[test]
+    fn executables_and_blobs_are_found() {
+        let mut pe = b"MZ".to_vec();
+        pe.resize(0x40, 0);
+        pe[0x3c] = 0x40;
+        pe.extend_from_slice(b"PE\0\0");
+        // A multiplicative sequence, which covers all bytes about evenly
+        let blob = (0..8192u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8)
+            .collect::<Vec<_>>();
+        let text = b"fn main() {}\n".repeat(400);
+
+        let report = run(
+            &mut ExecutableScanner::default(),
+            &[
+                ("src/lib.rs", &text),
+                ("bin/tool", b"\x7fELF\x02\x01\x01\0"),
+                ("tool.exe", &pe),
+                ("libfoo.dylib", b"\xcf\xfa\xed\xfe\x07\0\0\x01"),
+                ("Foo.class", b"\xca\xfe\xba\xbe\0\0\0\x34"),
+                ("MZ.txt", b"MZ is not a PE file"),
+                ("lib/libbar.so.1", b"not really"),
+                ("data/blob.bin", &blob),
+                ("data/blob.png", &blob),
+            ],
+        );
+        let findings =
+            serde_json::from_value::<Vec<ScanFinding>>(report["findings"].clone()).unwrap();
+        let found = findings
+            .iter()
+            .map(|finding| (finding.path.as_str(), finding.kind.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![
+                ("bin/tool", "elf"),
+                ("tool.exe", "pe"),
+                ("libfoo.dylib", "mach-o"),
+                ("lib/libbar.so.1", "native-library"),
+                ("data/blob.bin", "high-entropy"),
+            ]
+        );
+        assert!(entropy(&text) < 4.0);
+    }
+
+    #[test]
+    fn fingerprints_ignore_formatting_and_comments() {
+        let source = b"pub fn parse(input: &str) -> Result<Value, Error> {\n    ".repeat(10);
+        let mut reformatted = b"// Copied from somewhere else\n\n".to_vec();
+        reformatted.extend(source.iter().map(|&b| if b == b' ' { b'\t' } else { b }));
+
+        let fingerprint = |files: &[(&str, &[u8])]| {
+            run(&mut ContentFingerprint::default(), files)["hashes"].clone()
+        };
+        let original = fingerprint(&[("src/lib.rs", &source)]);
+        assert_eq!(original.as_array().unwrap().len(), 1);
+        assert_eq!(fingerprint(&[("src/parser.rs", &reformatted)]), original);
+        // Short files and files other than sources aren't fingerprinted
+        assert_eq!(
+            fingerprint(&[("src/lib.rs", b"mod parser;\n"), ("README.md", &source)]),
+            json!([])
+        );
+    }
+
+    #[test]
+    fn unknown_analyzers_are_rejected() {
+        assert_eq!(analyzers(ANALYZERS).unwrap().len(), 6);
+        assert!(analyzers(&["size", "virus"]).is_err());
+    }
+}
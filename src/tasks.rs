@@ -1,5 +1,45 @@
+mod check_squatting_reports;
+mod compute_crate_quality;
+mod compute_dependency_freshness;
+mod compute_ecosystem_stats;
+mod compute_release_stats;
+mod deliver_webhooks;
+mod detect_dependency_cycles;
+mod detect_download_anomalies;
 pub mod dump_db;
+mod notify_dependency_updates;
+mod prune_audit_tables;
+mod prune_idempotency_keys;
+mod reconcile_index;
+mod refresh_user_profiles;
+mod sign_transparency_log;
+mod squash_index;
+mod sync_default_versions;
+mod sync_repository_activity;
+mod sync_search_index;
 mod update_downloads;
+mod verify_installability;
+mod verify_org_domain;
 
+pub use check_squatting_reports::check_squatting_reports;
+pub use compute_crate_quality::compute_crate_quality;
+pub use compute_dependency_freshness::compute_dependency_freshness;
+pub use compute_ecosystem_stats::compute_ecosystem_stats;
+pub use compute_release_stats::compute_release_stats;
+pub use deliver_webhooks::deliver_webhooks;
+pub use detect_dependency_cycles::detect_dependency_cycles;
+pub use detect_download_anomalies::detect_download_anomalies;
 pub use dump_db::dump_db;
+pub use notify_dependency_updates::notify_dependency_updates;
+pub use prune_audit_tables::prune_audit_tables;
+pub use prune_idempotency_keys::prune_idempotency_keys;
+pub use reconcile_index::{reconcile_index, sync_index};
+pub use refresh_user_profiles::refresh_user_profiles;
+pub use sign_transparency_log::sign_transparency_log;
+pub use squash_index::{squash_index, squash_index_history};
+pub use sync_default_versions::sync_default_versions;
+pub use sync_repository_activity::sync_repository_activity;
+pub use sync_search_index::{remove_from_search_index, sync_search_index};
 pub use update_downloads::update_downloads;
+pub use verify_installability::verify_installability;
+pub use verify_org_domain::verify_org_domain;
@@ -0,0 +1,76 @@
+//! Checks reports of squatted crate names
+//!
+//! Every crate with a pending report is checked for signs of squatting, see
+//! `SquattingHeuristics`. Reports of crates matching all of them become
+//! candidates, which are sent to `SQUATTING_REPORTS_EMAIL` as a single report
+//! until an admin resolves them with the `squatting-reports` binary. Without
+//! the variable the report is only printed.
+
+use chrono::Utc;
+use swirl::PerformError;
+
+use crate::background_jobs::Environment;
+use crate::email;
+use crate::models::SquattingReport;
+
+/// Meant to be run daily via `enqueue-job check_squatting_reports`.
+#[swirl::background_job]
+pub fn check_squatting_reports(env: &Environment) -> Result<(), PerformError> {
+    let _heartbeat = env.heartbeat("check_squatting_reports")?;
+    let conn = env.connection()?;
+
+    let checked = SquattingReport::check_pending(&conn, Utc::now().naive_utc())?;
+    let candidates = SquattingReport::candidates(&conn)?;
+    println!(
+        "squatting_reports.checked={} squatting_reports.candidates={}",
+        checked,
+        candidates.len()
+    );
+    if candidates.is_empty() {
+        return Ok(());
+    }
+
+    let report = format_report(&candidates);
+    match dotenv::var("SQUATTING_REPORTS_EMAIL") {
+        Ok(recipient) => email::send_squatting_report_email(&recipient, &report)
+            .map_err(|e| format!("Sending the squatting report failed: {}", e))?,
+        Err(_) => println!("{}", report),
+    }
+    Ok(())
+}
+
+fn format_report(candidates: &[(String, Vec<String>)]) -> String {
+    let mut report = String::new();
+    for (name, reasons) in candidates {
+        report.push_str(&format!(
+            "- https://crates.io/crates/{} ({} reports)\n",
+            name,
+            reasons.len()
+        ));
+        for reason in reasons {
+            report.push_str(&format!("    - {}\n", reason.replace('\n', " ")));
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_lists_reasons_by_crate() {
+        let candidates = vec![
+            (
+                "foo".to_string(),
+                vec!["empty".to_string(), "no\ncode".to_string()],
+            ),
+            ("bar".to_string(), vec!["placeholder".to_string()]),
+        ];
+        assert_eq!(
+            format_report(&candidates),
+            "- https://crates.io/crates/foo (2 reports)\n    - empty\n    - no code\n\
+             - https://crates.io/crates/bar (1 reports)\n    - placeholder\n"
+        );
+    }
+}
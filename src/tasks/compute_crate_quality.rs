@@ -0,0 +1,160 @@
+use crate::{
+    background_jobs::Environment,
+    models::crate_quality::QualitySignals,
+    schema::{crate_quality, crates, versions},
+};
+
+use std::collections::HashMap;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::dsl::{now, sql};
+use diesel::prelude::*;
+use diesel::sql_types::Integer;
+use swirl::PerformError;
+
+/// The number of crates loaded from the database at a time.
+const BATCH_SIZE: i64 = 1000;
+
+/// Recomputes the quality signals and scores of all crates.
+///
+/// This is meant to be run nightly via `enqueue-job compute_crate_quality`.
+#[swirl::background_job]
+pub fn compute_crate_quality(env: &Environment) -> Result<(), PerformError> {
+    let _heartbeat = env.heartbeat("compute_crate_quality")?;
+    let conn = env.connection()?;
+    let computed = compute(&conn)?;
+    println!("crate_quality.computed_crates={}", computed);
+    Ok(())
+}
+
+fn compute(conn: &PgConnection) -> QueryResult<usize> {
+    let one_year_ago = (Utc::now() - Duration::days(365)).naive_utc();
+    let mut computed = 0;
+    let mut last_id = 0;
+
+    loop {
+        let batch = crates::table
+            .select((
+                crates::id,
+                crates::documentation.is_not_null(),
+                sql::<Integer>("coalesce(length(crates.readme), 0)"),
+            ))
+            .filter(crates::id.gt(last_id))
+            .order(crates::id)
+            .limit(BATCH_SIZE)
+            .load::<(i32, bool, i32)>(conn)?;
+
+        let crate_ids = batch.iter().map(|&(id, _, _)| id).collect::<Vec<_>>();
+        last_id = match crate_ids.last() {
+            Some(&id) => id,
+            None => break,
+        };
+
+        let mut versions_by_crate = HashMap::<i32, Vec<(bool, NaiveDateTime)>>::new();
+        let crate_versions = versions::table
+            .filter(versions::crate_id.eq_any(&crate_ids))
+            .select((versions::crate_id, versions::yanked, versions::created_at))
+            .load::<(i32, bool, NaiveDateTime)>(conn)?;
+        for (crate_id, yanked, created_at) in crate_versions {
+            versions_by_crate
+                .entry(crate_id)
+                .or_default()
+                .push((yanked, created_at));
+        }
+
+        let has_tests = crate_quality::table
+            .filter(crate_quality::crate_id.eq_any(&crate_ids))
+            .select((crate_quality::crate_id, crate_quality::has_tests))
+            .load::<(i32, Option<bool>)>(conn)?
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+
+        conn.transaction::<_, diesel::result::Error, _>(|| {
+            for (crate_id, has_documentation, readme_length) in batch {
+                let versions = versions_by_crate.remove(&crate_id).unwrap_or_default();
+                let yanked = versions.iter().filter(|(yanked, _)| *yanked).count();
+                let releases_last_year = versions
+                    .iter()
+                    .filter(|(_, created_at)| *created_at > one_year_ago)
+                    .count();
+
+                let signals = QualitySignals {
+                    crate_id,
+                    has_documentation,
+                    readme_length,
+                    releases_last_year: releases_last_year as i32,
+                    yanked_ratio: if versions.is_empty() {
+                        0.0
+                    } else {
+                        yanked as f32 / versions.len() as f32
+                    },
+                };
+                let score = signals.score(has_tests.get(&crate_id).cloned().flatten());
+
+                diesel::insert_into(crate_quality::table)
+                    .values((
+                        &signals,
+                        crate_quality::score.eq(score),
+                        crate_quality::computed_at.eq(now),
+                    ))
+                    .on_conflict(crate_quality::crate_id)
+                    .do_update()
+                    .set((
+                        &signals,
+                        crate_quality::score.eq(score),
+                        crate_quality::computed_at.eq(now),
+                    ))
+                    .execute(conn)?;
+            }
+            Ok(())
+        })?;
+
+        computed += crate_ids.len();
+    }
+
+    Ok(computed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CrateQuality;
+    use crate::test_util::{new_crate, new_user, new_version, pg_connection};
+
+    #[test]
+    fn signals_are_computed() {
+        let conn = pg_connection();
+        let user = new_user(&conn, 2, "login");
+        let krate = new_crate(&conn, "foo", user.id);
+        diesel::update(&krate)
+            .set((
+                crates::documentation.eq("https://docs.rs/foo"),
+                crates::readme.eq("# foo"),
+            ))
+            .execute(&conn)
+            .unwrap();
+        for num in &["1.0.0", "1.0.1"] {
+            let version = new_version(&conn, krate.id, num, user.id);
+            if *num == "1.0.0" {
+                diesel::update(&version)
+                    .set(versions::yanked.eq(true))
+                    .execute(&conn)
+                    .unwrap();
+            }
+        }
+        CrateQuality::record_tests(&conn, krate.id, true).unwrap();
+
+        assert_eq!(compute(&conn).unwrap(), 1);
+
+        let quality = crate_quality::table
+            .find(krate.id)
+            .first::<CrateQuality>(&conn)
+            .unwrap();
+        assert!(quality.has_documentation);
+        assert_eq!(quality.readme_length, 5);
+        assert_eq!(quality.has_tests, Some(true));
+        assert_eq!(quality.releases_last_year, 2);
+        assert!((quality.yanked_ratio - 0.5).abs() < 1e-6);
+        assert!(quality.score > 0.0 && quality.score < 1.0);
+    }
+}
@@ -0,0 +1,196 @@
+use crate::{
+    background_jobs::Environment,
+    models::{dependency_freshness, DependencyKind},
+    schema::{crate_dependency_freshness, default_versions, dependencies, versions},
+};
+
+use std::collections::HashMap;
+
+use diesel::dsl::now;
+use diesel::prelude::*;
+use semver::VersionReq;
+use swirl::PerformError;
+
+/// The number of crates loaded from the database at a time.
+const BATCH_SIZE: i64 = 1000;
+
+/// Recomputes how far the dependencies of the default version of each crate
+/// are behind their latest releases.
+///
+/// This is meant to be run weekly via `enqueue-job compute_dependency_freshness`.
+#[swirl::background_job]
+pub fn compute_dependency_freshness(env: &Environment) -> Result<(), PerformError> {
+    let _heartbeat = env.heartbeat("compute_dependency_freshness")?;
+    let conn = env.connection()?;
+    let computed = compute(&conn)?;
+    println!("dependency_freshness.computed_crates={}", computed);
+    Ok(())
+}
+
+fn compute(conn: &PgConnection) -> QueryResult<usize> {
+    // The releases a dependency can be updated to, as they are in the index
+    let mut releases = HashMap::<i32, Vec<semver::Version>>::new();
+    let all_versions = versions::table
+        .filter(versions::yanked.eq(false))
+        .select((versions::crate_id, versions::num))
+        .load::<(i32, semver::Version)>(conn)?;
+    for (crate_id, num) in all_versions {
+        if !num.is_prerelease() {
+            releases.entry(crate_id).or_default().push(num);
+        }
+    }
+
+    let mut computed = 0;
+    let mut last_id = 0;
+
+    loop {
+        let batch = default_versions::table
+            .filter(default_versions::crate_id.gt(last_id))
+            .order(default_versions::crate_id)
+            .limit(BATCH_SIZE)
+            .load::<(i32, i32)>(conn)?;
+        last_id = match batch.last() {
+            Some(&(crate_id, _)) => crate_id,
+            None => break,
+        };
+
+        let version_ids = batch.iter().map(|&(_, id)| id).collect::<Vec<_>>();
+        let mut dependencies_by_version = HashMap::<i32, Vec<(i32, String)>>::new();
+        let batch_dependencies = dependencies::table
+            .filter(dependencies::version_id.eq_any(&version_ids))
+            .filter(dependencies::kind.ne(DependencyKind::Dev as i32))
+            .select((
+                dependencies::version_id,
+                dependencies::crate_id,
+                dependencies::req,
+            ))
+            .load::<(i32, i32, String)>(conn)?;
+        for (version_id, crate_id, req) in batch_dependencies {
+            dependencies_by_version
+                .entry(version_id)
+                .or_default()
+                .push((crate_id, req));
+        }
+
+        conn.transaction::<_, diesel::result::Error, _>(|| {
+            for &(crate_id, version_id) in &batch {
+                let deps = dependencies_by_version
+                    .remove(&version_id)
+                    .unwrap_or_default();
+                let releases_behind = deps
+                    .iter()
+                    .filter_map(|(dependency_id, req)| {
+                        let req = VersionReq::parse(req).ok()?;
+                        dependency_freshness::releases_behind(&req, releases.get(dependency_id)?)
+                    })
+                    .collect::<Vec<_>>();
+
+                let outdated = releases_behind.iter().filter(|&&behind| behind > 0).count();
+
+                let values = (
+                    crate_dependency_freshness::version_id.eq(version_id),
+                    crate_dependency_freshness::dependencies.eq(releases_behind.len() as i32),
+                    crate_dependency_freshness::outdated_dependencies.eq(outdated as i32),
+                    crate_dependency_freshness::releases_behind
+                        .eq(releases_behind.iter().sum::<i32>()),
+                    crate_dependency_freshness::score
+                        .eq(dependency_freshness::score(&releases_behind)),
+                    crate_dependency_freshness::computed_at.eq(now),
+                );
+                diesel::insert_into(crate_dependency_freshness::table)
+                    .values((crate_dependency_freshness::crate_id.eq(crate_id), values))
+                    .on_conflict(crate_dependency_freshness::crate_id)
+                    .do_update()
+                    .set(values)
+                    .execute(conn)?;
+            }
+            Ok(())
+        })?;
+
+        computed += batch.len();
+    }
+
+    Ok(computed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{DependencyFreshness, Version};
+    use crate::test_util::{new_crate, new_user, new_version, pg_connection};
+
+    fn publish(conn: &PgConnection, user_id: i32, name: &str, nums: &[&str]) -> (i32, Version) {
+        let krate = new_crate(conn, name, user_id);
+        let mut version = None;
+        for num in nums {
+            version = Some(new_version(conn, krate.id, num, user_id));
+        }
+        crate::models::default_versions::update_default_version(krate.id, conn).unwrap();
+        (krate.id, version.unwrap())
+    }
+
+    fn depend(conn: &PgConnection, version: &Version, crate_id: i32, req: &str, kind: i32) {
+        diesel::insert_into(dependencies::table)
+            .values((
+                dependencies::version_id.eq(version.id),
+                dependencies::crate_id.eq(crate_id),
+                dependencies::req.eq(req),
+                dependencies::optional.eq(false),
+                dependencies::default_features.eq(true),
+                dependencies::features.eq(Vec::<String>::new()),
+                dependencies::kind.eq(kind),
+            ))
+            .execute(conn)
+            .unwrap();
+    }
+
+    #[test]
+    fn freshness_is_computed_for_normal_and_build_dependencies() {
+        let conn = pg_connection();
+        let user = new_user(&conn, 2, "login");
+        let (fresh_id, _) = publish(&conn, user.id, "fresh", &["1.0.0", "1.2.0"]);
+        let (stale_id, _) = publish(&conn, user.id, "stale", &["0.1.0", "0.2.0", "0.3.0"]);
+        let (krate_id, version) = publish(&conn, user.id, "foo", &["1.0.0"]);
+        depend(
+            &conn,
+            &version,
+            fresh_id,
+            "^1.0",
+            DependencyKind::Normal as i32,
+        );
+        depend(
+            &conn,
+            &version,
+            stale_id,
+            "^0.1",
+            DependencyKind::Build as i32,
+        );
+        // Outdated dev-dependencies don't affect users of the crate
+        depend(
+            &conn,
+            &version,
+            stale_id,
+            "^0.2",
+            DependencyKind::Dev as i32,
+        );
+
+        assert_eq!(compute(&conn).unwrap(), 3);
+
+        let freshness = crate_dependency_freshness::table
+            .find(krate_id)
+            .first::<DependencyFreshness>(&conn)
+            .unwrap();
+        assert_eq!(freshness.version_id, version.id);
+        assert_eq!(freshness.dependencies, 2);
+        assert_eq!(freshness.outdated_dependencies, 1);
+        assert_eq!(freshness.releases_behind, 2);
+        assert!((freshness.score - (1.0 + 1.0 / 3.0) / 2.0).abs() < 1e-6);
+
+        let without_dependencies = crate_dependency_freshness::table
+            .find(fresh_id)
+            .first::<DependencyFreshness>(&conn)
+            .unwrap();
+        assert_eq!(without_dependencies.dependencies, 0);
+        assert!((without_dependencies.score - 1.0).abs() < 1e-6);
+    }
+}
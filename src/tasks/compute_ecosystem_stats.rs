@@ -0,0 +1,100 @@
+use crate::{
+    background_jobs::Environment,
+    schema::{crates, ecosystem_stats, users},
+};
+
+use chrono::{Duration, NaiveDate, NaiveDateTime, Utc};
+use diesel::dsl::{count_star, max, min, now};
+use diesel::prelude::*;
+use diesel::sql_types::Date;
+use swirl::PerformError;
+
+/// Downloads are counted with a delay, so the last few days are rolled up
+/// again every night.
+const RECOMPUTED_DAYS: i64 = 7;
+
+/// Rolls up the daily totals of the registry, which are served by
+/// `GET /stats/ecosystem`. The first run rolls up every day since the first
+/// crate was published.
+///
+/// This is meant to be run nightly via `enqueue-job compute_ecosystem_stats`.
+#[swirl::background_job]
+pub fn compute_ecosystem_stats(env: &Environment) -> Result<(), PerformError> {
+    let _heartbeat = env.heartbeat("compute_ecosystem_stats")?;
+    let conn = env.connection()?;
+    let days = compute(&conn, Utc::today().naive_utc())?;
+    println!("ecosystem_stats.computed_days={}", days);
+    Ok(())
+}
+
+fn compute(conn: &PgConnection, today: NaiveDate) -> QueryResult<usize> {
+    let last_computed = ecosystem_stats::table
+        .select(max(ecosystem_stats::date))
+        .first::<Option<NaiveDate>>(conn)?;
+    let since = match last_computed {
+        Some(date) => date.min(today - Duration::days(RECOMPUTED_DAYS)),
+        None => crates::table
+            .select(min(crates::created_at))
+            .first::<Option<NaiveDateTime>>(conn)?
+            .map(|created_at| created_at.date())
+            .unwrap_or(today),
+    };
+
+    conn.transaction(|| {
+        let days = diesel::sql_query(include_str!("compute_ecosystem_stats.sql"))
+            .bind::<Date, _>(since)
+            .bind::<Date, _>(today)
+            .execute(conn)?;
+        // Users can only be counted as of now
+        let total_users = users::table.select(count_star()).first::<i64>(conn)? as i32;
+        diesel::update(ecosystem_stats::table.find(today))
+            .set((
+                ecosystem_stats::total_users.eq(total_users),
+                ecosystem_stats::computed_at.eq(now),
+            ))
+            .execute(conn)?;
+        Ok(days)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::EcosystemStats;
+    use crate::test_util::{new_crate, new_user, pg_connection};
+
+    #[test]
+    fn days_are_rolled_up() {
+        let conn = pg_connection();
+        let user = new_user(&conn, 2, "login");
+        let today = Utc::today().naive_utc();
+        for &(name, days_ago) in &[("foo", 2), ("bar", 0), ("baz", 0)] {
+            let krate = new_crate(&conn, name, user.id);
+            diesel::update(&krate)
+                .set(crates::created_at.eq((today - Duration::days(days_ago)).and_hms(12, 0, 0)))
+                .execute(&conn)
+                .unwrap();
+        }
+
+        assert_eq!(compute(&conn, today).unwrap(), 3);
+
+        let series = EcosystemStats::series(&conn, None).unwrap();
+        let totals = series
+            .iter()
+            .map(|stats| (stats.total_crates, stats.new_crates))
+            .collect::<Vec<_>>();
+        assert_eq!(totals, vec![(1, 1), (1, 0), (3, 2)]);
+        assert_eq!(series[0].total_users, None);
+        assert_eq!(series[2].total_users, Some(1));
+
+        // Later runs only roll up the last days again
+        let tomorrow = today + Duration::days(1);
+        assert_eq!(
+            compute(&conn, tomorrow).unwrap() as i64,
+            RECOMPUTED_DAYS + 1
+        );
+        let series = EcosystemStats::series(&conn, Some(today)).unwrap();
+        assert_eq!(series.len(), 2);
+        assert_eq!(series[1].total_crates, 3);
+    }
+}
@@ -0,0 +1,15 @@
+INSERT INTO ecosystem_stats (date, total_crates, new_crates, new_versions, downloads)
+SELECT day::date,
+       (SELECT COUNT(*) FROM crates WHERE created_at < day + INTERVAL '1 day'),
+       (SELECT COUNT(*) FROM crates
+         WHERE created_at >= day AND created_at < day + INTERVAL '1 day'),
+       (SELECT COUNT(*) FROM versions
+         WHERE created_at >= day AND created_at < day + INTERVAL '1 day'),
+       (SELECT COALESCE(SUM(downloads), 0) FROM version_downloads WHERE date = day::date)
+FROM generate_series($1::date, $2::date, INTERVAL '1 day') AS day
+ON CONFLICT (date) DO UPDATE
+SET total_crates = excluded.total_crates,
+    new_crates = excluded.new_crates,
+    new_versions = excluded.new_versions,
+    downloads = excluded.downloads,
+    computed_at = now()
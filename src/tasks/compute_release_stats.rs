@@ -0,0 +1,105 @@
+use crate::{
+    background_jobs::Environment,
+    models::NewReleaseStats,
+    schema::{crates, versions},
+};
+
+use std::collections::HashMap;
+
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use swirl::PerformError;
+
+/// The number of crates loaded from the database at a time.
+const BATCH_SIZE: i64 = 1000;
+
+/// Recomputes the release cadence statistics of all crates, which are served
+/// by `GET /crates/:crate_id/release_stats`.
+///
+/// This is meant to be run nightly via `enqueue-job compute_release_stats`.
+#[swirl::background_job]
+pub fn compute_release_stats(env: &Environment) -> Result<(), PerformError> {
+    let _heartbeat = env.heartbeat("compute_release_stats")?;
+    let conn = env.connection()?;
+    let computed = compute(&conn, Utc::now().naive_utc())?;
+    println!("release_stats.computed_crates={}", computed);
+    Ok(())
+}
+
+fn compute(conn: &PgConnection, today: NaiveDateTime) -> QueryResult<usize> {
+    let mut computed = 0;
+    let mut last_id = 0;
+
+    loop {
+        let crate_ids = crates::table
+            .select(crates::id)
+            .filter(crates::id.gt(last_id))
+            .order(crates::id)
+            .limit(BATCH_SIZE)
+            .load::<i32>(conn)?;
+        last_id = match crate_ids.last() {
+            Some(&id) => id,
+            None => break,
+        };
+
+        let mut versions_by_crate = HashMap::<i32, Vec<(bool, NaiveDateTime)>>::new();
+        let crate_versions = versions::table
+            .filter(versions::crate_id.eq_any(&crate_ids))
+            .select((versions::crate_id, versions::yanked, versions::created_at))
+            .load::<(i32, bool, NaiveDateTime)>(conn)?;
+        for (crate_id, yanked, created_at) in crate_versions {
+            versions_by_crate
+                .entry(crate_id)
+                .or_default()
+                .push((yanked, created_at));
+        }
+
+        conn.transaction::<_, diesel::result::Error, _>(|| {
+            for &crate_id in &crate_ids {
+                let versions = versions_by_crate.remove(&crate_id).unwrap_or_default();
+                NewReleaseStats::compute(crate_id, &versions, today).save(conn)?;
+            }
+            Ok(())
+        })?;
+
+        computed += crate_ids.len();
+    }
+
+    Ok(computed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ReleaseStats;
+    use crate::schema::crate_release_stats;
+    use crate::test_util::{new_crate, new_user, new_version, pg_connection};
+    use chrono::Duration;
+
+    #[test]
+    fn stats_are_computed() {
+        let conn = pg_connection();
+        let user = new_user(&conn, 2, "login");
+        let krate = new_crate(&conn, "foo", user.id);
+        let today = Utc::now().naive_utc();
+        for (num, days_ago) in &[("1.0.0", 30), ("1.0.1", 10)] {
+            let version = new_version(&conn, krate.id, num, user.id);
+            diesel::update(&version)
+                .set(versions::created_at.eq(today - Duration::days(*days_ago)))
+                .execute(&conn)
+                .unwrap();
+        }
+
+        assert_eq!(compute(&conn, today).unwrap(), 1);
+
+        let stats = crate_release_stats::table
+            .find(krate.id)
+            .first::<ReleaseStats>(&conn)
+            .unwrap();
+        assert_eq!(stats.releases, 2);
+        assert_eq!(stats.avg_days_between_releases, Some(20.0));
+        assert_eq!(stats.longest_gap_days, Some(20.0));
+        assert_eq!(stats.releases_per_year, 2.0);
+        assert_eq!(stats.yank_rate, 0.0);
+    }
+}
@@ -0,0 +1,317 @@
+//! Sends the notifications queued for the webhooks of service consumers
+//!
+//! Versions being yanked, unyanked or deleted are announced to the webhooks
+//! registered with `service-consumers add-webhook`, so that docs.rs and
+//! mirrors don't have to poll the index for them. Each notification is a
+//! `POST` request with a JSON body like
+//!
+//! ```json
+//! {"event": "yank", "crate": "foo", "version": "1.0.0", "time": "2020-04-02T08:15:44Z"}
+//! ```
+//!
+//! and the headers `X-Crates-Io-Event`, `X-Crates-Io-Delivery` with the id of
+//! the delivery, and `X-Crates-Io-Signature` with the HMAC-SHA256 of the body
+//! keyed with the secret of the webhook, like `sha256=<hex>`.
+//!
+//! Any response other than a `2xx` is retried with an exponential backoff,
+//! up to `MAX_ATTEMPTS` times. Deliveries can arrive out of order when
+//! retried, so the `time` of the payload is the one to go by.
+//!
+//! Runs can overlap, since every yank enqueues one. A run claims the
+//! deliveries it attempts by moving their `next_attempt_at` past the end of
+//! the run, so that other runs skip them.
+
+use std::collections::HashSet;
+use std::time::Duration as StdDuration;
+
+use chrono::{Duration, Utc};
+use diesel::dsl::now;
+use diesel::prelude::*;
+use reqwest::{header, StatusCode};
+use swirl::PerformError;
+
+use crate::background_jobs::Environment;
+use crate::models::{Webhook, WebhookDelivery};
+use crate::schema::{service_consumers, webhook_deliveries, webhooks};
+
+/// The number of attempts after which a delivery is given up, about three
+/// days after the first one.
+const MAX_ATTEMPTS: i32 = 12;
+
+/// The maximum number of deliveries attempted per run.
+const MAX_DELIVERIES_PER_RUN: i64 = 500;
+
+const REQUEST_TIMEOUT: StdDuration = StdDuration::from_secs(10);
+
+/// How long the deliveries of a run are claimed, longer than the
+/// `MAX_DELIVERIES_PER_RUN` requests of a run can take. A run which crashes
+/// leaves its deliveries to the runs after this.
+fn claim_duration() -> Duration {
+    Duration::minutes(90)
+}
+
+/// This is enqueued whenever notifications are queued, and is meant to be
+/// run periodically (e.g. every 10 minutes) via `enqueue-job deliver_webhooks`
+/// as well, to retry failed deliveries.
+#[swirl::background_job]
+pub fn deliver_webhooks(env: &Environment) -> Result<(), PerformError> {
+    let _heartbeat = env.heartbeat("deliver_webhooks")?;
+    let conn = env.connection()?;
+    let client = env.http_client();
+
+    let (delivered, failed) = deliver(&conn, |request| {
+        let response = client
+            .post(request.url)
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::USER_AGENT, "crates.io (https://crates.io)")
+            .header("X-Crates-Io-Event", request.event)
+            .header("X-Crates-Io-Delivery", request.delivery_id)
+            .header("X-Crates-Io-Signature", &request.signature)
+            .body(request.body.clone())
+            .timeout(REQUEST_TIMEOUT)
+            .send()
+            .map_err(|e| e.to_string())?;
+        Ok(response.status())
+    })?;
+    println!(
+        "count#webhooks.delivered={} count#webhooks.failed={}",
+        delivered, failed
+    );
+    Ok(())
+}
+
+/// A notification as it is sent.
+struct Request<'a> {
+    url: &'a str,
+    event: &'a str,
+    delivery_id: i32,
+    body: Vec<u8>,
+    signature: String,
+}
+
+/// Attempts the deliveries which are due, returning the number of
+/// successful and failed ones.
+fn deliver<F>(conn: &PgConnection, mut send: F) -> Result<(usize, usize), PerformError>
+where
+    F: FnMut(&Request<'_>) -> Result<StatusCode, String>,
+{
+    let claimed = claim_due_deliveries(conn)?;
+    let due = webhook_deliveries::table
+        .inner_join(webhooks::table)
+        .filter(webhook_deliveries::id.eq_any(&claimed))
+        .order(webhook_deliveries::id)
+        .select((webhook_deliveries::all_columns, webhooks::all_columns))
+        .load::<(WebhookDelivery, Webhook)>(conn)?;
+
+    let mut delivered = 0;
+    let mut failed = 0;
+    // An endpoint which failed isn't sent anything else until the next run
+    let mut failing = HashSet::new();
+    let mut skipped = Vec::new();
+    for (delivery, webhook) in due {
+        if failing.contains(&webhook.id) {
+            skipped.push(delivery.id);
+            continue;
+        }
+        let body = serde_json::to_vec(&delivery.payload)?;
+        let request = Request {
+            url: &webhook.url,
+            event: &delivery.event,
+            delivery_id: delivery.id,
+            signature: webhook.signature(&body)?,
+            body,
+        };
+        let (status, error) = match send(&request) {
+            Ok(status) if status.is_success() => {
+                diesel::update(&delivery)
+                    .set((
+                        webhook_deliveries::attempts.eq(delivery.attempts + 1),
+                        webhook_deliveries::delivered_at.eq(now),
+                    ))
+                    .execute(conn)?;
+                delivered += 1;
+                continue;
+            }
+            Ok(status) => (
+                Some(i32::from(status.as_u16())),
+                format!("unexpected status {}", status),
+            ),
+            Err(error) => (None, error),
+        };
+
+        failed += 1;
+        failing.insert(webhook.id);
+        let attempts = delivery.attempts + 1;
+        let next_attempt_at = Utc::now().naive_utc() + Duration::minutes(1 << attempts);
+        diesel::update(&delivery)
+            .set((
+                webhook_deliveries::attempts.eq(attempts),
+                webhook_deliveries::last_status.eq(status),
+                webhook_deliveries::last_error.eq(&error),
+                webhook_deliveries::next_attempt_at.eq(next_attempt_at),
+            ))
+            .execute(conn)?;
+        if attempts == MAX_ATTEMPTS {
+            eprintln!(
+                "Gave up delivering notification {} to {}: {}",
+                delivery.id, webhook.url, error
+            );
+        }
+    }
+
+    // Skipped deliveries are due again for the next run
+    diesel::update(webhook_deliveries::table.filter(webhook_deliveries::id.eq_any(&skipped)))
+        .set(webhook_deliveries::next_attempt_at.eq(now))
+        .execute(conn)?;
+    Ok((delivered, failed))
+}
+
+/// Claims the deliveries which are due and not claimed by another run,
+/// returning their ids.
+fn claim_due_deliveries(conn: &PgConnection) -> QueryResult<Vec<i32>> {
+    conn.transaction(|| {
+        let active_webhooks = webhooks::table
+            .inner_join(service_consumers::table)
+            .filter(service_consumers::revoked.eq(false))
+            .select(webhooks::id);
+        let due = webhook_deliveries::table
+            .filter(webhook_deliveries::delivered_at.is_null())
+            .filter(webhook_deliveries::attempts.lt(MAX_ATTEMPTS))
+            .filter(webhook_deliveries::next_attempt_at.le(now))
+            .filter(webhook_deliveries::webhook_id.eq_any(active_webhooks))
+            .order(webhook_deliveries::id)
+            .limit(MAX_DELIVERIES_PER_RUN)
+            .select(webhook_deliveries::id)
+            .for_update()
+            .skip_locked()
+            .load::<i32>(conn)?;
+
+        diesel::update(webhook_deliveries::table.filter(webhook_deliveries::id.eq_any(&due)))
+            .set(webhook_deliveries::next_attempt_at.eq(Utc::now().naive_utc() + claim_duration()))
+            .execute(conn)?;
+        Ok(due)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{webhook, ServiceConsumer};
+    use crate::test_util::pg_connection;
+
+    fn register(conn: &PgConnection, name: &str, url: &str) -> Webhook {
+        let consumer = ServiceConsumer::create(conn, name, "ops@example.com", 10_000).unwrap();
+        Webhook::create(conn, &consumer, url).unwrap()
+    }
+
+    fn deliveries(conn: &PgConnection) -> Vec<WebhookDelivery> {
+        webhook_deliveries::table
+            .order(webhook_deliveries::id)
+            .load(conn)
+            .unwrap()
+    }
+
+    #[test]
+    fn notifications_are_signed_and_sent_to_every_webhook() {
+        let conn = pg_connection();
+        let docs_rs = register(&conn, "docs.rs", "https://docs.rs/webhook");
+        let mirror = register(&conn, "mirror", "https://mirror.example.com/hook");
+
+        assert_eq!(
+            webhook::notify_yank(&conn, "foo", "1.0.0", true).unwrap(),
+            2
+        );
+
+        let mut sent = Vec::new();
+        let result = deliver(&conn, |request| {
+            let payload: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            assert_eq!(payload["event"], "yank");
+            assert_eq!(payload["crate"], "foo");
+            assert_eq!(payload["version"], "1.0.0");
+            assert_eq!(request.event, "yank");
+            sent.push((request.url.to_string(), request.signature.clone()));
+            Ok(StatusCode::NO_CONTENT)
+        })
+        .unwrap();
+        assert_eq!(result, (2, 0));
+
+        let body = serde_json::to_vec(&deliveries(&conn)[0].payload).unwrap();
+        assert_eq!(
+            sent,
+            vec![
+                (docs_rs.url.clone(), docs_rs.signature(&body).unwrap()),
+                (mirror.url.clone(), mirror.signature(&body).unwrap()),
+            ]
+        );
+        assert!(deliveries(&conn).iter().all(|d| d.delivered_at.is_some()));
+
+        // Delivered notifications aren't sent again
+        let result = deliver(&conn, |_| panic!("unexpected delivery")).unwrap();
+        assert_eq!(result, (0, 0));
+    }
+
+    #[test]
+    fn failed_deliveries_are_retried_later() {
+        let conn = pg_connection();
+        register(&conn, "mirror", "https://mirror.example.com/hook");
+        webhook::notify_yank(&conn, "foo", "1.0.0", true).unwrap();
+        webhook::notify_yank(&conn, "foo", "1.0.0", false).unwrap();
+
+        // The second notification isn't attempted after the first one failed
+        let mut attempts = 0;
+        let result = deliver(&conn, |_| {
+            attempts += 1;
+            Ok(StatusCode::SERVICE_UNAVAILABLE)
+        })
+        .unwrap();
+        assert_eq!((attempts, result), (1, (0, 1)));
+
+        let failed = &deliveries(&conn)[0];
+        assert_eq!(failed.attempts, 1);
+        assert_eq!(failed.last_status, Some(503));
+        assert!(failed.delivered_at.is_none());
+        assert!(failed.next_attempt_at > Utc::now().naive_utc());
+
+        // Only the second notification is due now
+        let mut events = Vec::new();
+        deliver(&conn, |request| {
+            events.push(request.event.to_string());
+            Err("connection refused".into())
+        })
+        .unwrap();
+        assert_eq!(events, vec!["unyank"]);
+        assert_eq!(
+            deliveries(&conn)[1].last_error.as_deref(),
+            Some("connection refused")
+        );
+    }
+
+    #[test]
+    fn claimed_deliveries_are_skipped_by_other_runs() {
+        let conn = pg_connection();
+        register(&conn, "mirror", "https://mirror.example.com/hook");
+        webhook::notify_yank(&conn, "foo", "1.0.0", true).unwrap();
+
+        let result = deliver(&conn, |_| {
+            let overlapping = deliver(&conn, |_| panic!("delivered twice")).unwrap();
+            assert_eq!(overlapping, (0, 0));
+            Ok(StatusCode::NO_CONTENT)
+        })
+        .unwrap();
+        assert_eq!(result, (1, 0));
+    }
+
+    #[test]
+    fn revoked_consumers_are_not_notified() {
+        let conn = pg_connection();
+        register(&conn, "mirror", "https://mirror.example.com/hook");
+        diesel::update(service_consumers::table)
+            .set(service_consumers::revoked.eq(true))
+            .execute(&conn)
+            .unwrap();
+        assert_eq!(
+            webhook::notify_yank(&conn, "foo", "1.0.0", true).unwrap(),
+            0
+        );
+    }
+}
@@ -0,0 +1,21 @@
+//! Finds dependency cycles introduced by a publish
+//!
+//! Cycles through dev- or build-dependencies break tools which build the
+//! dependency graph of a workspace, like `cargo publish` of the crates of the
+//! cycle. They are recorded in `dependency_cycles`, where admins can list
+//! them with the `dependency-cycles` binary and owners see the ones their
+//! publishes introduced.
+
+use swirl::PerformError;
+
+use crate::background_jobs::Environment;
+use crate::models::DependencyCycle;
+
+#[swirl::background_job]
+pub fn detect_dependency_cycles(env: &Environment, version_id: i32) -> Result<(), PerformError> {
+    let _heartbeat = env.heartbeat("detect_dependency_cycles")?;
+    let conn = env.connection()?;
+    let recorded = DependencyCycle::detect(&conn, version_id)?;
+    println!("dependency_cycles.recorded={}", recorded);
+    Ok(())
+}
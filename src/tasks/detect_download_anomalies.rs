@@ -0,0 +1,178 @@
+//! Detects crates which are suddenly downloaded far more often than usual
+//!
+//! The downloads of every crate which was downloaded at least
+//! `DOWNLOAD_ANOMALY_MIN_DOWNLOADS` times on a day (1,000 by default) are
+//! compared with its daily downloads during the four weeks before. Days
+//! scoring at least `DOWNLOAD_ANOMALY_MIN_SCORE` (10 by default) are recorded
+//! as `DownloadAnomaly`, which admins list with `crates-io-admin
+//! download-anomalies`, and owners see at
+//! `GET /crates/:crate_id/download_anomalies` if
+//! `SHOW_DOWNLOAD_ANOMALIES_TO_OWNERS` is set.
+//!
+//! Crates which are younger than a week have no meaningful baseline yet, and
+//! are skipped.
+
+use chrono::{Duration, NaiveDate, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Date, Double, Integer};
+use swirl::PerformError;
+
+use crate::background_jobs::Environment;
+use crate::models::DownloadAnomaly;
+
+/// The number of days before a day its downloads are compared with.
+const HISTORY_DAYS: i32 = 28;
+
+/// The minimum number of days a crate has to have existed before a day.
+const MIN_HISTORY_DAYS: i64 = 7;
+
+#[derive(Debug, QueryableByName)]
+struct Spike {
+    #[sql_type = "Integer"]
+    crate_id: i32,
+    #[sql_type = "BigInt"]
+    downloads: i64,
+    #[sql_type = "Double"]
+    mean: f64,
+    #[sql_type = "Double"]
+    stddev: f64,
+    #[sql_type = "BigInt"]
+    days: i64,
+}
+
+/// Checks today and yesterday, since downloads are still counted for the day
+/// before. Meant to be run hourly via `enqueue-job detect_download_anomalies`.
+#[swirl::background_job]
+pub fn detect_download_anomalies(env: &Environment) -> Result<(), PerformError> {
+    let _heartbeat = env.heartbeat("detect_download_anomalies")?;
+    let min_downloads = numeric_var("DOWNLOAD_ANOMALY_MIN_DOWNLOADS", 1_000)?;
+    let min_score = numeric_var("DOWNLOAD_ANOMALY_MIN_SCORE", 10)? as f64;
+
+    let conn = env.connection()?;
+    let today = Utc::today().naive_utc();
+    for &date in &[today - Duration::days(1), today] {
+        let detected = detect(&conn, date, min_downloads, min_score)?;
+        println!(
+            "download_anomalies.date={} download_anomalies.detected={}",
+            date, detected
+        );
+    }
+    Ok(())
+}
+
+fn numeric_var(var: &str, default: i64) -> Result<i64, PerformError> {
+    match dotenv::var(var) {
+        Ok(value) => Ok(value
+            .parse()
+            .map_err(|_| format!("{} must be a number", var))?),
+        Err(_) => Ok(default),
+    }
+}
+
+/// Records the anomalies of a day, and returns how many were found.
+fn detect(
+    conn: &PgConnection,
+    date: NaiveDate,
+    min_downloads: i64,
+    min_score: f64,
+) -> QueryResult<usize> {
+    let spikes = diesel::sql_query(include_str!("detect_download_anomalies.sql"))
+        .bind::<Date, _>(date)
+        .bind::<Double, _>(min_downloads as f64)
+        .bind::<Integer, _>(HISTORY_DAYS)
+        .load::<Spike>(conn)?;
+
+    let mut detected = 0;
+    for spike in spikes {
+        if spike.days < MIN_HISTORY_DAYS {
+            continue;
+        }
+        let score = score(spike.downloads as f64, spike.mean, spike.stddev);
+        if score >= min_score {
+            DownloadAnomaly::record(
+                conn,
+                spike.crate_id,
+                date,
+                spike.downloads,
+                spike.mean,
+                score,
+            )?;
+            detected += 1;
+        }
+    }
+    Ok(detected)
+}
+
+/// How many deviations `downloads` is above `mean`. Downloads are roughly
+/// Poisson distributed, so the deviation is at least the square root of the
+/// mean, which keeps crates with very steady downloads from scoring high
+/// on small changes.
+fn score(downloads: f64, mean: f64, stddev: f64) -> f64 {
+    (downloads - mean) / stddev.max(mean.sqrt()).max(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{crates, version_downloads};
+    use crate::test_util::{new_crate, new_user, new_version, pg_connection};
+    use std::collections::HashMap;
+
+    #[test]
+    fn scores_are_relative_to_the_usual_deviation() {
+        assert_eq!(score(100.0, 100.0, 5.0), 0.0);
+        assert_eq!(score(200.0, 100.0, 20.0), 5.0);
+        // Steady downloads still deviate by the square root of their mean
+        assert_eq!(score(200.0, 100.0, 0.0), 10.0);
+        assert_eq!(score(50.0, 0.0, 0.0), 50.0);
+    }
+
+    #[test]
+    fn spikes_are_recorded() {
+        let conn = pg_connection();
+        let user = new_user(&conn, 2, "login");
+        let today = Utc::today().naive_utc();
+        let mut version_ids = HashMap::new();
+        for &name in &["steady", "spiking", "new"] {
+            let krate = new_crate(&conn, name, user.id);
+            let age = if name == "new" { 2 } else { 60 };
+            diesel::update(&krate)
+                .set(crates::created_at.eq((today - Duration::days(age)).and_hms(0, 0, 0)))
+                .execute(&conn)
+                .unwrap();
+            let version = new_version(&conn, krate.id, "1.0.0", user.id);
+            version_ids.insert(name, version.id);
+        }
+
+        let mut rows = Vec::new();
+        for days_ago in 1..=HISTORY_DAYS as i64 {
+            let date = today - Duration::days(days_ago);
+            rows.push((version_ids["steady"], date, 2_000));
+            rows.push((version_ids["spiking"], date, 100 + days_ago as i32 % 3));
+        }
+        rows.push((version_ids["steady"], today, 2_100));
+        rows.push((version_ids["spiking"], today, 5_000));
+        rows.push((version_ids["new"], today, 5_000));
+        for (version_id, date, downloads) in rows {
+            diesel::insert_into(version_downloads::table)
+                .values((
+                    version_downloads::version_id.eq(version_id),
+                    version_downloads::date.eq(date),
+                    version_downloads::downloads.eq(downloads),
+                ))
+                .execute(&conn)
+                .unwrap();
+        }
+
+        assert_eq!(detect(&conn, today, 1_000, 10.0).unwrap(), 1);
+        let spiking = crates::table
+            .filter(crates::name.eq("spiking"))
+            .select(crates::id)
+            .first(&conn)
+            .unwrap();
+        let anomalies = DownloadAnomaly::by_crate(&conn, spiking).unwrap();
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].downloads, 5_000);
+        assert!(anomalies[0].baseline > 100.0 && anomalies[0].baseline < 103.0);
+    }
+}
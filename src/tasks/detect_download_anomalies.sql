@@ -0,0 +1,26 @@
+WITH daily AS (
+  SELECT versions.crate_id, version_downloads.date,
+         SUM(version_downloads.downloads)::float8 AS downloads
+  FROM version_downloads
+  INNER JOIN versions ON versions.id = version_downloads.version_id
+  WHERE version_downloads.date BETWEEN $1::date - $3::integer AND $1::date
+  GROUP BY versions.crate_id, version_downloads.date
+), spikes AS (
+  SELECT crate_id, downloads FROM daily WHERE date = $1::date AND downloads >= $2
+), history AS (
+  -- Days without downloads count as zero, unless the crate didn't exist yet
+  SELECT spikes.crate_id, COALESCE(daily.downloads, 0) AS downloads
+  FROM spikes
+  INNER JOIN crates ON crates.id = spikes.crate_id
+  CROSS JOIN generate_series($1::date - $3::integer, $1::date - 1, INTERVAL '1 day') AS day
+  LEFT JOIN daily ON daily.crate_id = spikes.crate_id AND daily.date = day::date
+  WHERE day::date >= crates.created_at::date
+)
+SELECT spikes.crate_id,
+       spikes.downloads::bigint AS downloads,
+       COALESCE(AVG(history.downloads), 0) AS mean,
+       COALESCE(STDDEV_POP(history.downloads), 0) AS stddev,
+       COUNT(history.crate_id) AS days
+FROM spikes
+LEFT JOIN history ON history.crate_id = spikes.crate_id
+GROUP BY spikes.crate_id, spikes.downloads
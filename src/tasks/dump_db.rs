@@ -15,6 +15,7 @@ pub fn dump_db(
     database_url: String,
     target_name: String,
 ) -> Result<(), PerformError> {
+    let _heartbeat = env.heartbeat("dump_db")?;
     let directory = DumpDirectory::create()?;
 
     println!("Begin exporting database");
@@ -0,0 +1,323 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::dsl::exists;
+use diesel::prelude::*;
+use semver::VersionReq;
+use swirl::PerformError;
+
+use crate::{
+    background_jobs::Environment,
+    email,
+    models::{DependencyKind, OwnerKind, User},
+    schema::{
+        crate_owners, crates, default_versions, dependencies, dependency_update_notifications,
+        dependency_update_subscriptions, users, versions,
+    },
+};
+
+/// Releases published this many hours before a run are checked again, so
+/// that a late run doesn't miss any. Each release is only reported once per
+/// crate and owner.
+const LOOKBACK_HOURS: i64 = 48;
+
+/// Sent notifications are kept this long, which has to be longer than
+/// `LOOKBACK_HOURS` to prevent duplicates.
+const SENT_RETENTION_DAYS: i64 = 7;
+
+/// Emails the owners who subscribed to dependency updates of a crate when a
+/// dependency of its default version has a new release which is incompatible
+/// with the version requirement.
+///
+/// A release is incompatible if it isn't semver compatible with the newest
+/// older release the requirement matches, so that e.g. `=1.2.3` isn't
+/// reported for `1.3.0`. Requirements which never matched a release aren't
+/// reported.
+///
+/// This is meant to be run daily via `enqueue-job notify_dependency_updates`,
+/// so that owners receive at most one email per day.
+#[swirl::background_job]
+pub fn notify_dependency_updates(env: &Environment) -> Result<(), PerformError> {
+    let _heartbeat = env.heartbeat("notify_dependency_updates")?;
+    let conn = env.connection()?;
+    let now = Utc::now().naive_utc();
+
+    let queued = queue_notifications(&conn, now - Duration::hours(LOOKBACK_HOURS))?;
+    let emails = send_notifications(&conn, now)?;
+    diesel::delete(dependency_update_notifications::table.filter(
+        dependency_update_notifications::sent_at.lt(now - Duration::days(SENT_RETENTION_DAYS)),
+    ))
+    .execute(&*conn)?;
+
+    println!(
+        "dependency_updates.queued={} dependency_updates.emails={}",
+        queued, emails
+    );
+    Ok(())
+}
+
+/// Queues a notification for every subscription affected by a release
+/// published after `since`, and returns how many were new.
+fn queue_notifications(conn: &PgConnection, since: NaiveDateTime) -> QueryResult<usize> {
+    // Only releases which became the default version of their crate count,
+    // so that e.g. backports to older release lines aren't reported.
+    let releases = versions::table
+        .inner_join(default_versions::table.on(default_versions::version_id.eq(versions::id)))
+        .filter(versions::created_at.gt(since))
+        .select((versions::crate_id, versions::id, versions::num))
+        .load::<(i32, i32, String)>(conn)?
+        .into_iter()
+        .filter_map(|(crate_id, version_id, num)| {
+            let num = semver::Version::parse(&num).ok()?;
+            if num.is_prerelease() {
+                return None;
+            }
+            Some((crate_id, (version_id, num)))
+        })
+        .collect::<HashMap<_, _>>();
+    if releases.is_empty() {
+        return Ok(0);
+    }
+
+    let released_crate_ids = releases.keys().cloned().collect::<Vec<_>>();
+    let mut published = HashMap::<i32, Vec<semver::Version>>::new();
+    let all_versions = versions::table
+        .filter(versions::crate_id.eq_any(&released_crate_ids))
+        .select((versions::crate_id, versions::num))
+        .load::<(i32, String)>(conn)?;
+    for (crate_id, num) in all_versions {
+        if let Ok(num) = semver::Version::parse(&num) {
+            if !num.is_prerelease() {
+                published.entry(crate_id).or_default().push(num);
+            }
+        }
+    }
+
+    let requirements = dependency_update_subscriptions::table
+        .inner_join(
+            default_versions::table
+                .on(default_versions::crate_id.eq(dependency_update_subscriptions::crate_id)),
+        )
+        .inner_join(
+            dependencies::table.on(dependencies::version_id.eq(default_versions::version_id)),
+        )
+        .filter(dependencies::crate_id.eq_any(released_crate_ids))
+        .filter(dependencies::kind.ne(DependencyKind::Dev as i32))
+        .filter(exists(
+            crate_owners::table
+                .filter(crate_owners::crate_id.eq(dependency_update_subscriptions::crate_id))
+                .filter(crate_owners::owner_id.eq(dependency_update_subscriptions::user_id))
+                .filter(crate_owners::owner_kind.eq(OwnerKind::User as i32))
+                .filter(crate_owners::deleted.eq(false)),
+        ))
+        .select((
+            dependency_update_subscriptions::user_id,
+            dependency_update_subscriptions::crate_id,
+            dependencies::crate_id,
+            dependencies::req,
+        ))
+        .load::<(i32, i32, i32, String)>(conn)?;
+
+    let notifications = requirements
+        .into_iter()
+        .filter_map(|(user_id, crate_id, dependency_id, requirement)| {
+            let (version_id, num) = &releases[&dependency_id];
+            // Requirements we can't parse aren't reported, as we can't tell
+            // whether they match.
+            let req = VersionReq::parse(&requirement).ok()?;
+            let matched = published
+                .get(&dependency_id)?
+                .iter()
+                .filter(|v| *v < num && req.matches(v))
+                .max()?;
+            let compatible = VersionReq::parse(&format!("^{}", matched)).ok()?;
+            if compatible.matches(num) {
+                return None;
+            }
+            Some((
+                dependency_update_notifications::user_id.eq(user_id),
+                dependency_update_notifications::crate_id.eq(crate_id),
+                dependency_update_notifications::dependency_version_id.eq(*version_id),
+                dependency_update_notifications::requirement.eq(requirement),
+            ))
+        })
+        .collect::<Vec<_>>();
+
+    diesel::insert_into(dependency_update_notifications::table)
+        .values(&notifications)
+        .on_conflict_do_nothing()
+        .execute(conn)
+}
+
+/// Sends one email per user with all their unsent notifications, and
+/// returns the number of emails sent.
+///
+/// Notifications of users without a verified email address are dropped.
+/// If sending an email fails, the notifications are sent with the next run.
+fn send_notifications(conn: &PgConnection, now: NaiveDateTime) -> QueryResult<usize> {
+    let pending = dependency_update_notifications::table
+        .inner_join(crates::table)
+        .inner_join(versions::table)
+        .filter(dependency_update_notifications::sent_at.is_null())
+        .order((dependency_update_notifications::user_id, crates::name))
+        .select((
+            dependency_update_notifications::id,
+            dependency_update_notifications::user_id,
+            crates::name,
+            versions::crate_id,
+            versions::num,
+            dependency_update_notifications::requirement,
+        ))
+        .load::<(i32, i32, String, i32, String, String)>(conn)?;
+
+    let dependency_ids = pending.iter().map(|n| n.3).collect::<Vec<_>>();
+    let dependency_names = crates::table
+        .filter(crates::id.eq_any(dependency_ids))
+        .select((crates::id, crates::name))
+        .load::<(i32, String)>(conn)?
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+
+    let mut by_user = BTreeMap::<i32, Vec<_>>::new();
+    for (id, user_id, crate_name, dependency_id, num, requirement) in pending {
+        let update = format!(
+            "- {} {} was released, but {} requires `{}`",
+            dependency_names[&dependency_id], num, crate_name, requirement
+        );
+        by_user.entry(user_id).or_default().push((id, update));
+    }
+
+    let mut emails = 0;
+    for (user_id, notifications) in by_user {
+        let user = users::table.find(user_id).first::<User>(conn)?;
+        if let Some(email) = user.verified_email(conn)? {
+            let updates = notifications
+                .iter()
+                .map(|(_, update)| update.as_str())
+                .collect::<Vec<_>>();
+            if let Err(e) = email::send_dependency_updates_email(&email, &user.gh_login, &updates) {
+                eprintln!(
+                    "Sending dependency updates to user {} failed: {}",
+                    user_id, e
+                );
+                continue;
+            }
+            emails += 1;
+        }
+
+        let ids = notifications.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+        diesel::update(
+            dependency_update_notifications::table
+                .filter(dependency_update_notifications::id.eq_any(ids)),
+        )
+        .set(dependency_update_notifications::sent_at.eq(now))
+        .execute(conn)?;
+    }
+    Ok(emails)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::default_versions::update_default_version;
+    use crate::test_util::{new_crate, new_user, new_version, pg_connection};
+
+    fn release(conn: &PgConnection, crate_id: i32, num: &str, user_id: i32) -> i32 {
+        let version = new_version(conn, crate_id, num, user_id);
+        update_default_version(crate_id, conn).unwrap();
+        version.id
+    }
+
+    fn add_dependency(conn: &PgConnection, version_id: i32, crate_id: i32, req: &str) {
+        diesel::insert_into(dependencies::table)
+            .values((
+                dependencies::version_id.eq(version_id),
+                dependencies::crate_id.eq(crate_id),
+                dependencies::req.eq(req),
+                dependencies::optional.eq(false),
+                dependencies::default_features.eq(true),
+                dependencies::features.eq(Vec::<String>::new()),
+            ))
+            .execute(conn)
+            .unwrap();
+    }
+
+    fn subscribe(conn: &PgConnection, crate_id: i32, user_id: i32) {
+        diesel::insert_into(dependency_update_subscriptions::table)
+            .values((
+                dependency_update_subscriptions::crate_id.eq(crate_id),
+                dependency_update_subscriptions::user_id.eq(user_id),
+            ))
+            .execute(conn)
+            .unwrap();
+    }
+
+    fn unsent(conn: &PgConnection) -> Vec<(i32, String)> {
+        dependency_update_notifications::table
+            .filter(dependency_update_notifications::sent_at.is_null())
+            .select((
+                dependency_update_notifications::dependency_version_id,
+                dependency_update_notifications::requirement,
+            ))
+            .load(conn)
+            .unwrap()
+    }
+
+    #[test]
+    fn incompatible_releases_are_reported_once() {
+        let conn = pg_connection();
+        let user = new_user(&conn, 2, "login");
+        let since = Utc::now().naive_utc() - Duration::hours(1);
+
+        let dependency = new_crate(&conn, "dependency", user.id).id;
+        release(&conn, dependency, "1.0.0", user.id);
+        let dependent = new_crate(&conn, "dependent", user.id).id;
+        let dependent_version = release(&conn, dependent, "0.1.0", user.id);
+        add_dependency(&conn, dependent_version, dependency, "^1.0");
+        let unsubscribed = new_crate(&conn, "unsubscribed", user.id).id;
+        let unsubscribed_version = release(&conn, unsubscribed, "0.1.0", user.id);
+        add_dependency(&conn, unsubscribed_version, dependency, "^1.0");
+        subscribe(&conn, dependent, user.id);
+
+        // Compatible releases and prereleases aren't reported
+        release(&conn, dependency, "1.1.0", user.id);
+        release(&conn, dependency, "2.0.0-beta.1", user.id);
+        assert_eq!(queue_notifications(&conn, since).unwrap(), 0);
+
+        let major = release(&conn, dependency, "2.0.0", user.id);
+        assert_eq!(queue_notifications(&conn, since).unwrap(), 1);
+        assert_eq!(unsent(&conn), vec![(major, "^1.0".to_string())]);
+
+        // The user has no verified email address
+        assert_eq!(
+            send_notifications(&conn, Utc::now().naive_utc()).unwrap(),
+            0
+        );
+        assert!(unsent(&conn).is_empty());
+        assert_eq!(queue_notifications(&conn, since).unwrap(), 0);
+    }
+
+    #[test]
+    fn pinned_and_unsatisfiable_requirements() {
+        let conn = pg_connection();
+        let user = new_user(&conn, 2, "login");
+        let since = Utc::now().naive_utc() - Duration::hours(1);
+
+        let dependency = new_crate(&conn, "dependency", user.id).id;
+        release(&conn, dependency, "1.0.0", user.id);
+        for &(name, req) in &[("pinned", "=1.0.0"), ("unsatisfiable", "^5.0")] {
+            let dependent = new_crate(&conn, name, user.id).id;
+            let dependent_version = release(&conn, dependent, "0.1.0", user.id);
+            add_dependency(&conn, dependent_version, dependency, req);
+            subscribe(&conn, dependent, user.id);
+        }
+
+        // Compatible with the pinned version, even though it doesn't match
+        release(&conn, dependency, "1.1.0", user.id);
+        assert_eq!(queue_notifications(&conn, since).unwrap(), 0);
+
+        let major = release(&conn, dependency, "2.0.0", user.id);
+        assert_eq!(queue_notifications(&conn, since).unwrap(), 1);
+        assert_eq!(unsent(&conn), vec![(major, "=1.0.0".to_string())]);
+    }
+}
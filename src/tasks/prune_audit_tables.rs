@@ -0,0 +1,167 @@
+//! Removes old rows from audit and event tables
+//!
+//! The retention period of each table is configured in days through
+//! `AUDIT_RETENTION_DAYS`, e.g. `version_owner_actions=730,yank_events=365`.
+//! Tables without a retention period are kept forever.
+//!
+//! Rows are deleted in small batches, so that no single transaction holds
+//! locks for long and autovacuum and the replicas can keep up.
+
+use std::thread;
+use std::time::Duration;
+
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Timestamp};
+use swirl::PerformError;
+
+use crate::background_jobs::Environment;
+
+/// How many rows are deleted at once, unless `PRUNE_BATCH_SIZE` is set.
+const DEFAULT_BATCH_SIZE: i64 = 1000;
+
+/// The pause between two batches.
+const BATCH_PAUSE: Duration = Duration::from_millis(100);
+
+#[derive(Debug, PartialEq)]
+struct PrunableTable {
+    name: &'static str,
+    /// The column holding the time at which a row was recorded.
+    recorded_at: &'static str,
+}
+
+const PRUNABLE_TABLES: &[PrunableTable] = &[
+    PrunableTable {
+        name: "version_owner_actions",
+        recorded_at: "time",
+    },
+    PrunableTable {
+        name: "yank_events",
+        recorded_at: "created_at",
+    },
+    PrunableTable {
+        name: "partner_audit_log",
+        recorded_at: "created_at",
+    },
+];
+
+#[swirl::background_job]
+pub fn prune_audit_tables(env: &Environment) -> Result<(), PerformError> {
+    let _heartbeat = env.heartbeat("prune_audit_tables")?;
+    let retention = parse_retention(&dotenv::var("AUDIT_RETENTION_DAYS").unwrap_or_default())?;
+    let batch_size = match dotenv::var("PRUNE_BATCH_SIZE") {
+        Ok(value) => value
+            .parse::<i64>()
+            .ok()
+            .filter(|&size| size > 0)
+            .ok_or_else(|| format!("invalid `PRUNE_BATCH_SIZE`: `{}`", value))?,
+        Err(_) => DEFAULT_BATCH_SIZE,
+    };
+
+    let conn = env.connection()?;
+    for (table, days) in retention {
+        let cutoff = Utc::now().naive_utc() - chrono::Duration::days(days.into());
+        let pruned = prune(&conn, table, cutoff, batch_size)?;
+        println!(
+            "Pruned rows older than {} days from `{}` count#pruned_rows.{}={}",
+            days, table.name, table.name, pruned
+        );
+    }
+    Ok(())
+}
+
+fn parse_retention(value: &str) -> Result<Vec<(&'static PrunableTable, u32)>, PerformError> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| -> Result<_, PerformError> {
+            let mut parts = entry.splitn(2, '=');
+            let name = parts.next().unwrap_or_default().trim();
+            let table = PRUNABLE_TABLES
+                .iter()
+                .find(|table| table.name == name)
+                .ok_or_else(|| format!("`{}` can't be pruned", name))?;
+            let days = parts
+                .next()
+                .and_then(|days| days.trim().parse::<u32>().ok())
+                .filter(|&days| days > 0)
+                .ok_or_else(|| format!("invalid retention period of `{}`", name))?;
+            Ok((table, days))
+        })
+        .collect()
+}
+
+/// Deletes all rows recorded before `cutoff`, and returns how many there were.
+fn prune(
+    conn: &PgConnection,
+    table: &PrunableTable,
+    cutoff: NaiveDateTime,
+    batch_size: i64,
+) -> QueryResult<usize> {
+    let query = format!(
+        "DELETE FROM {table} WHERE id IN \
+         (SELECT id FROM {table} WHERE {recorded_at} < $1 ORDER BY id LIMIT $2)",
+        table = table.name,
+        recorded_at = table.recorded_at,
+    );
+
+    let mut pruned = 0;
+    loop {
+        let deleted = diesel::sql_query(&*query)
+            .bind::<Timestamp, _>(cutoff)
+            .bind::<BigInt, _>(batch_size)
+            .execute(conn)?;
+        pruned += deleted;
+        if (deleted as i64) < batch_size {
+            return Ok(pruned);
+        }
+        thread::sleep(BATCH_PAUSE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::pg_connection;
+    use diesel::connection::SimpleConnection;
+    use diesel::dsl::sql;
+
+    #[test]
+    fn retention_periods_are_parsed() {
+        assert_eq!(parse_retention("").unwrap(), vec![]);
+        assert_eq!(
+            parse_retention("version_owner_actions=730, yank_events = 365").unwrap(),
+            vec![(&PRUNABLE_TABLES[0], 730), (&PRUNABLE_TABLES[1], 365)]
+        );
+        assert!(parse_retention("crates=30").is_err());
+        assert!(parse_retention("yank_events").is_err());
+        assert!(parse_retention("yank_events=0").is_err());
+        assert!(parse_retention("yank_events=forever").is_err());
+    }
+
+    #[test]
+    fn old_rows_are_pruned_in_batches() {
+        let conn = pg_connection();
+        conn.batch_execute(
+            "CREATE TEMPORARY TABLE pruned_events (id SERIAL PRIMARY KEY, created_at TIMESTAMP NOT NULL);
+             INSERT INTO pruned_events (created_at)
+                SELECT '2020-01-01'::timestamp - n * interval '1 day' FROM generate_series(0, 4) n;
+             INSERT INTO pruned_events (created_at) VALUES ('2020-01-10');",
+        )
+        .unwrap();
+
+        let table = PrunableTable {
+            name: "pruned_events",
+            recorded_at: "created_at",
+        };
+        let cutoff = chrono::NaiveDate::from_ymd(2020, 1, 5).and_hms(0, 0, 0);
+        assert_eq!(prune(&conn, &table, cutoff, 2).unwrap(), 5);
+        assert_eq!(prune(&conn, &table, cutoff, 2).unwrap(), 0);
+
+        let remaining = diesel::select(sql::<BigInt>("(SELECT COUNT(*) FROM pruned_events)"))
+            .get_result::<i64>(&conn)
+            .unwrap();
+        assert_eq!(remaining, 1);
+    }
+}
@@ -0,0 +1,20 @@
+use crate::{background_jobs::Environment, models::PublishIdempotencyKey};
+
+use swirl::PerformError;
+
+/// Removes the `Idempotency-Key`s of publishes which can't be retried
+/// anymore.
+///
+/// This is meant to be run periodically (e.g. once a day) via
+/// `enqueue-job prune_idempotency_keys`.
+#[swirl::background_job]
+pub fn prune_idempotency_keys(env: &Environment) -> Result<(), PerformError> {
+    let _heartbeat = env.heartbeat("prune_idempotency_keys")?;
+    let conn = env.connection()?;
+    let pruned = PublishIdempotencyKey::prune_expired(&conn)?;
+    println!(
+        "Pruned expired idempotency keys count#pruned_idempotency_keys={}",
+        pruned
+    );
+    Ok(())
+}
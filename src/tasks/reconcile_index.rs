@@ -0,0 +1,612 @@
+//! Rebuilds the index files from the database and repairs the ones which
+//! drifted
+//!
+//! Index files are only appended to by publishes and changed in place by
+//! yanks, so a push which got lost or a manual edit of the index repository
+//! goes unnoticed until someone can't resolve a version. Every crate's index
+//! file is rebuilt from the database and compared with the one in the index
+//! byte for byte. A file which differs is replaced, and the versions which
+//! were missing, differed or aren't in the database are logged.
+//!
+//! The `links` of versions and the names of renamed dependencies weren't
+//! recorded in the database before this job existed, so they are taken from
+//! the current entry where the database doesn't know them. The same goes for
+//! the `rust_version`, which like the `yanked_reason` is only written with
+//! `INDEX_V3_FIELDS`.
+//!
+//! `sync_index` does the same for a single crate, for whoever is on call. The
+//! index is served by GitHub, whose CDN can't be purged, so cargo only sees a
+//! replaced file once its cached copy expired, within a few minutes.
+//!
+//! Versions whose index update is still queued or held for a review are left
+//! as they are, their jobs will add them. Versions published before their
+//! index updates were tracked in `index_syncs` are only added back if they
+//! aren't yanked, since the versions rejected after a review are yanked
+//! without ever having been in the index.
+
+use std::collections::HashMap;
+
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use swirl::PerformError;
+
+use crate::background_jobs::Environment;
+use crate::git::{self, replace_index_file};
+use crate::models::publish_job::INDEXED;
+use crate::models::{CompressionFormat, Crate, DependencyKind};
+use crate::schema::{
+    crates, dependencies, index_syncs, publish_jobs, version_checksums, version_scan_holds,
+    versions,
+};
+
+/// The number of crates loaded from the database at a time. The index is
+/// locked while a batch is checked.
+const BATCH_SIZE: i64 = 1000;
+
+/// At most this many files are replaced per run, each with its own commit,
+/// so that a difference between all files, like a change in how entries are
+/// serialized, doesn't flood the index with commits before anyone looked at
+/// it. The other files are only logged.
+const MAX_REPAIRS: usize = 100;
+
+/// An index file which didn't match the database.
+#[derive(Debug, PartialEq)]
+struct Drift {
+    crate_name: String,
+    rebuilt: RebuiltIndexFile,
+    repaired: bool,
+}
+
+/// The index file of a crate as the database describes it.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct RebuiltIndexFile {
+    pub contents: String,
+    /// The versions which weren't in the index file.
+    pub missing: Vec<String>,
+    /// The versions whose entry was different.
+    pub changed: Vec<String>,
+    /// The versions of entries which aren't in the database or are in the
+    /// file twice, and lines which aren't entries of the crate.
+    pub unexpected: Vec<String>,
+}
+
+/// Meant to be run once a night via `enqueue-job reconcile_index`.
+#[swirl::background_job]
+pub fn reconcile_index(env: &Environment) -> Result<(), PerformError> {
+    let _heartbeat = env.heartbeat("reconcile_index")?;
+    let conn = env.connection()?;
+    let mut drifts = Vec::<Drift>::new();
+    let mut checked = 0;
+    let mut last_id = 0;
+
+    loop {
+        let batch = crates::table
+            .select((crates::id, crates::name))
+            .filter(crates::id.gt(last_id))
+            .order(crates::id)
+            .limit(BATCH_SIZE)
+            .load::<(i32, String)>(&*conn)?;
+        let (crate_id, _) = match batch.last() {
+            Some(last) => last,
+            None => break,
+        };
+        last_id = *crate_id;
+
+        let repo = env.lock_index()?;
+        for (crate_id, crate_name) in &batch {
+            let current = repo.read_index_file(crate_name)?;
+            let rebuilt = rebuild_index_file(
+                &conn,
+                *crate_id,
+                crate_name,
+                &current,
+                repo.writes_v3_fields(),
+            )?;
+            if rebuilt.contents == current {
+                continue;
+            }
+            let repaired = drifts.iter().filter(|drift| drift.repaired).count() < MAX_REPAIRS;
+            if repaired {
+                let message = format!("Reconciling crate `{}` with the database", crate_name);
+                replace_index_file(
+                    &repo,
+                    &conn,
+                    *crate_id,
+                    crate_name,
+                    &rebuilt.contents,
+                    &message,
+                )?;
+            }
+            drifts.push(Drift {
+                crate_name: crate_name.clone(),
+                rebuilt,
+                repaired,
+            });
+        }
+        drop(repo);
+
+        checked += batch.len();
+        println!("Reconciled the index files of {} crates", checked);
+    }
+
+    report(&drifts);
+    Ok(())
+}
+
+/// Rebuilds the index file of one crate from the database, and replaces it
+/// if it differs. Enqueued with `enqueue-job sync_index <crate>`.
+#[swirl::background_job]
+pub fn sync_index(env: &Environment, crate_name: String) -> Result<(), PerformError> {
+    let _heartbeat = env.heartbeat("sync_index")?;
+    let conn = env.connection()?;
+    let (crate_id, crate_name) = Crate::by_name(&crate_name)
+        .select((crates::id, crates::name))
+        .first::<(i32, String)>(&*conn)
+        .optional()?
+        .ok_or_else(|| format!("the crate `{}` doesn't exist", crate_name))?;
+
+    let repo = env.lock_index()?;
+    let current = repo.read_index_file(&crate_name)?;
+    let rebuilt = rebuild_index_file(
+        &conn,
+        crate_id,
+        &crate_name,
+        &current,
+        repo.writes_v3_fields(),
+    )?;
+    if rebuilt.contents == current {
+        println!("The index file of `{}` matches the database", crate_name);
+        return Ok(());
+    }
+    let message = format!("Syncing crate `{}` with the database", crate_name);
+    replace_index_file(
+        &repo,
+        &conn,
+        crate_id,
+        &crate_name,
+        &rebuilt.contents,
+        &message,
+    )?;
+    log_drift(&Drift {
+        crate_name,
+        rebuilt,
+        repaired: true,
+    });
+    Ok(())
+}
+
+fn log_drift(drift: &Drift) {
+    println!(
+        "{} the index file of `{}`: missing={:?} changed={:?} unexpected={:?}",
+        if drift.repaired {
+            "Repaired"
+        } else {
+            "Not repaired"
+        },
+        drift.crate_name,
+        drift.rebuilt.missing,
+        drift.rebuilt.changed,
+        drift.rebuilt.unexpected,
+    );
+}
+
+fn report(drifts: &[Drift]) {
+    for drift in drifts {
+        log_drift(drift);
+    }
+    let repaired = drifts.iter().filter(|drift| drift.repaired).count();
+    println!(
+        "count#index_drift.repaired={} count#index_drift.unrepaired={}",
+        repaired,
+        drifts.len() - repaired
+    );
+}
+
+type VersionRow = (
+    i32,
+    String,
+    serde_json::Value,
+    bool,
+    Option<String>,
+    CompressionFormat,
+    Option<String>,
+    Option<String>,
+    Option<i32>,
+    Option<NaiveDateTime>,
+);
+
+type DependencyRow = (
+    i32,
+    String,
+    String,
+    Vec<String>,
+    bool,
+    bool,
+    Option<String>,
+    DependencyKind,
+    Option<String>,
+);
+
+/// Rebuilds the index file of a crate from the database, keeping the order
+/// of the entries in the `current` file and appending the missing ones in
+/// the order they were published. `v3_fields` is whether the index is
+/// written with `INDEX_V3_FIELDS`.
+pub(crate) fn rebuild_index_file(
+    conn: &PgConnection,
+    crate_id: i32,
+    crate_name: &str,
+    current: &str,
+    v3_fields: bool,
+) -> Result<RebuiltIndexFile, PerformError> {
+    let versions = versions::table
+        .left_join(version_checksums::table)
+        .left_join(index_syncs::table)
+        .filter(versions::crate_id.eq(crate_id))
+        .select((
+            versions::id,
+            versions::num,
+            versions::features,
+            versions::yanked,
+            versions::links,
+            versions::compression,
+            versions::rust_version,
+            version_checksums::checksum.nullable(),
+            index_syncs::version_id.nullable(),
+            index_syncs::indexed_at.nullable(),
+        ))
+        .order(versions::id)
+        .load::<VersionRow>(conn)?;
+    let version_ids = versions.iter().map(|v| v.0).collect::<Vec<_>>();
+    let mut yank_reasons = git::yank_reasons(conn, &version_ids)?;
+
+    let held = version_scan_holds::table.select(version_scan_holds::version_id);
+    let queued = publish_jobs::table
+        .filter(publish_jobs::state.ne(INDEXED))
+        .select(publish_jobs::version_id);
+    let pending = versions::table
+        .filter(versions::id.eq_any(&version_ids))
+        .filter(versions::id.eq_any(held).or(versions::id.eq_any(queued)))
+        .select(versions::id)
+        .load::<i32>(conn)?;
+
+    let mut deps = HashMap::<i32, Vec<DependencyRow>>::new();
+    for dep in dependencies::table
+        .inner_join(crates::table)
+        .filter(dependencies::version_id.eq_any(&version_ids))
+        .select((
+            dependencies::version_id,
+            crates::name,
+            dependencies::req,
+            dependencies::features,
+            dependencies::optional,
+            dependencies::default_features,
+            dependencies::target,
+            dependencies::kind,
+            dependencies::explicit_name,
+        ))
+        .order(dependencies::id)
+        .load::<DependencyRow>(conn)?
+    {
+        deps.entry(dep.0).or_default().push(dep);
+    }
+
+    let mut rebuilt = RebuiltIndexFile::default();
+    let mut current_entries = Vec::new();
+    for line in current.lines().filter(|line| !line.is_empty()) {
+        match serde_json::from_str::<git::Crate>(line) {
+            Ok(entry) if entry.name == crate_name => current_entries.push((entry, line)),
+            _ => rebuilt.unexpected.push(line.into()),
+        }
+    }
+
+    // The rebuilt entries of the versions in the current file, or `None` to
+    // keep the current entry.
+    let mut rebuilt_entries = HashMap::<String, Option<git::Crate>>::new();
+    let mut appended = Vec::new();
+    for (
+        version_id,
+        num,
+        features,
+        yanked,
+        links,
+        compression,
+        rust_version,
+        checksum,
+        sync,
+        indexed_at,
+    ) in versions
+    {
+        let current = current_entries
+            .iter()
+            .find(|(entry, _)| entry.vers == num)
+            .map(|(entry, _)| entry);
+        let tracked = sync.is_some();
+        if pending.contains(&version_id) || (tracked && indexed_at.is_none()) {
+            rebuilt_entries.insert(num, None);
+            continue;
+        }
+        if current.is_none() && yanked && !tracked {
+            continue;
+        }
+        let cksum = match checksum.or_else(|| current.map(|entry| entry.cksum.clone())) {
+            Some(cksum) => cksum,
+            None => continue,
+        };
+        let entry = git::Crate {
+            name: crate_name.into(),
+            vers: num.clone(),
+            deps: rebuild_dependencies(deps.remove(&version_id).unwrap_or_default(), current),
+            cksum,
+            features: serde_json::from_value(features)?,
+            yanked: Some(yanked),
+            links: links.or_else(|| current.and_then(|entry| entry.links.clone())),
+            compression: Some(compression).filter(|&c| c != CompressionFormat::Gzip),
+            rust_version: rust_version
+                .or_else(|| current.and_then(|entry| entry.rust_version.clone())),
+            yanked_reason: yank_reasons.remove(&version_id).filter(|_| yanked),
+        };
+        let entry = if v3_fields {
+            entry
+        } else {
+            entry.without_v3_fields()
+        };
+        if current.is_some() {
+            rebuilt_entries.insert(num, Some(entry));
+        } else {
+            rebuilt.missing.push(num);
+            appended.push(serde_json::to_string(&entry)?);
+        }
+    }
+
+    for (entry, line) in current_entries {
+        let line = match rebuilt_entries.remove(&entry.vers) {
+            // Compared as JSON, so that entries which only differ in the
+            // order of their fields or features are left alone
+            Some(Some(rebuilt_entry)) => {
+                if serde_json::to_value(&rebuilt_entry)? == serde_json::to_value(&entry)? {
+                    line.to_string()
+                } else {
+                    rebuilt.changed.push(entry.vers);
+                    serde_json::to_string(&rebuilt_entry)?
+                }
+            }
+            Some(None) => line.to_string(),
+            // Versions which aren't in the database, or which are in the
+            // file twice
+            None => {
+                rebuilt.unexpected.push(entry.vers);
+                continue;
+            }
+        };
+        rebuilt.contents.push_str(&line);
+        rebuilt.contents.push('\n');
+    }
+    for line in appended {
+        rebuilt.contents.push_str(&line);
+        rebuilt.contents.push('\n');
+    }
+    Ok(rebuilt)
+}
+
+/// Where the database doesn't know whether a dependency was renamed, the
+/// dependency at the same position of the current entry is taken as the
+/// source of the name, if it's a dependency on the same crate.
+fn rebuild_dependencies(
+    deps: Vec<DependencyRow>,
+    current: Option<&git::Crate>,
+) -> Vec<git::Dependency> {
+    deps.into_iter()
+        .enumerate()
+        .map(|(i, dep)| {
+            let (_, krate, req, features, optional, default_features, target, kind, explicit) = dep;
+            let explicit = explicit.or_else(|| {
+                current
+                    .and_then(|entry| entry.deps.get(i))
+                    .filter(|dep| dep.package.as_deref() == Some(krate.as_str()))
+                    .map(|dep| dep.name.clone())
+            });
+            let (name, package) = match explicit {
+                Some(explicit) => (explicit, Some(krate)),
+                None => (krate, None),
+            };
+            git::Dependency {
+                name,
+                req,
+                features,
+                optional,
+                default_features,
+                target,
+                kind: Some(kind),
+                package,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{IndexSync, Version};
+    use crate::test_util::{new_crate, new_user, new_version, pg_connection};
+
+    const CKSUM: &str = "c0ffee00c0ffee00c0ffee00c0ffee00c0ffee00c0ffee00c0ffee00c0ffee00";
+
+    /// Creates a crate and its versions with the checksum `CKSUM`, and
+    /// returns the crate ID and the version IDs.
+    fn crate_with_versions(conn: &PgConnection, name: &str, nums: &[&str]) -> (i32, Vec<i32>) {
+        let user = new_user(conn, 2, "login");
+        let krate = new_crate(conn, name, user.id);
+        let version_ids = nums
+            .iter()
+            .map(|num| {
+                let version = new_version(conn, krate.id, num, user.id);
+                Version::record_checksum(version.id, CKSUM, conn).unwrap();
+                version.id
+            })
+            .collect();
+        (krate.id, version_ids)
+    }
+
+    fn entry(num: &str, yanked: bool) -> String {
+        format!(
+            r#"{{"name":"foo","vers":"{}","deps":[],"cksum":"{}","features":{{}},"yanked":{},"links":null}}"#,
+            num, CKSUM, yanked
+        )
+    }
+
+    fn file(entries: &[String]) -> String {
+        entries.iter().map(|entry| format!("{}\n", entry)).collect()
+    }
+
+    #[test]
+    fn matching_files_are_left_alone() {
+        let conn = pg_connection();
+        let (crate_id, _) = crate_with_versions(&conn, "foo", &["1.0.0", "1.1.0"]);
+        let current = file(&[entry("1.0.0", false), entry("1.1.0", false)]);
+
+        let rebuilt = rebuild_index_file(&conn, crate_id, "foo", &current, false).unwrap();
+        assert_eq!(
+            rebuilt,
+            RebuiltIndexFile {
+                contents: current,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn differently_ordered_entries_are_left_alone() {
+        let conn = pg_connection();
+        let (crate_id, version_ids) = crate_with_versions(&conn, "foo", &["1.0.0"]);
+        diesel::update(versions::table.find(version_ids[0]))
+            .set(versions::features.eq(json!({ "default": ["std"], "std": [] })))
+            .execute(&conn)
+            .unwrap();
+        let current = format!(
+            r#"{{"vers":"1.0.0","name":"foo","features":{{"std":[],"default":["std"]}},"deps":[],"cksum":"{}","links":null,"yanked":false}}
+"#,
+            CKSUM
+        );
+
+        let rebuilt = rebuild_index_file(&conn, crate_id, "foo", &current, false).unwrap();
+        assert_eq!(
+            rebuilt,
+            RebuiltIndexFile {
+                contents: current,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn drifted_files_are_rebuilt() {
+        let conn = pg_connection();
+        let (crate_id, version_ids) =
+            crate_with_versions(&conn, "foo", &["1.0.0", "1.1.0", "1.2.0"]);
+        diesel::update(versions::table.find(version_ids[0]))
+            .set(versions::yanked.eq(true))
+            .execute(&conn)
+            .unwrap();
+        let current = file(&[
+            entry("1.1.0", false),
+            entry("1.0.0", false),
+            entry("0.9.0", false),
+        ]);
+
+        let rebuilt = rebuild_index_file(&conn, crate_id, "foo", &current, false).unwrap();
+        assert_eq!(
+            rebuilt,
+            RebuiltIndexFile {
+                contents: file(&[
+                    entry("1.1.0", false),
+                    entry("1.0.0", true),
+                    entry("1.2.0", false)
+                ]),
+                missing: vec!["1.2.0".into()],
+                changed: vec!["1.0.0".into()],
+                unexpected: vec!["0.9.0".into()],
+            }
+        );
+    }
+
+    #[test]
+    fn pending_versions_are_left_to_their_jobs() {
+        let conn = pg_connection();
+        let (crate_id, version_ids) = crate_with_versions(&conn, "foo", &["1.0.0", "1.1.0"]);
+        IndexSync::queued(&conn, version_ids[1]).unwrap();
+        let current = file(&[entry("1.0.0", false)]);
+
+        let rebuilt = rebuild_index_file(&conn, crate_id, "foo", &current, false).unwrap();
+        assert_eq!(rebuilt.contents, current);
+        assert!(rebuilt.missing.is_empty());
+    }
+
+    #[test]
+    fn unrecorded_renames_are_kept() {
+        let conn = pg_connection();
+        let (dep_id, _) = crate_with_versions(&conn, "bar", &["1.0.0"]);
+        let (crate_id, version_ids) = crate_with_versions(&conn, "foo", &["1.0.0"]);
+        diesel::insert_into(dependencies::table)
+            .values((
+                dependencies::version_id.eq(version_ids[0]),
+                dependencies::crate_id.eq(dep_id),
+                dependencies::req.eq("^1"),
+                dependencies::optional.eq(false),
+                dependencies::default_features.eq(true),
+                dependencies::features.eq(Vec::<String>::new()),
+                dependencies::kind.eq(0),
+            ))
+            .execute(&conn)
+            .unwrap();
+        let current = format!(
+            "{}\n",
+            entry("1.0.0", false).replace(
+                r#""deps":[]"#,
+                r#""deps":[{"name":"baz","req":"^1","features":[],"optional":false,"default_features":true,"target":null,"kind":"normal","package":"bar"}]"#
+            )
+        );
+
+        let rebuilt = rebuild_index_file(&conn, crate_id, "foo", &current, false).unwrap();
+        assert_eq!(rebuilt.contents, current);
+    }
+
+    #[test]
+    fn v3_fields_are_only_written_when_enabled() {
+        use crate::models::{insert_version_owner_action, VersionAction};
+        use crate::schema::users;
+
+        let conn = pg_connection();
+        let (crate_id, version_ids) = crate_with_versions(&conn, "foo", &["1.0.0", "1.1.0"]);
+        let user_id = users::table.select(users::id).first(&conn).unwrap();
+        diesel::update(versions::table.find(version_ids[0]))
+            .set(versions::yanked.eq(true))
+            .execute(&conn)
+            .unwrap();
+        insert_version_owner_action(
+            &conn,
+            version_ids[0],
+            user_id,
+            None,
+            VersionAction::Yank,
+            Some("security issue"),
+        )
+        .unwrap();
+        Version::record_rust_version(version_ids[1], Some("1.42"), &conn).unwrap();
+        let current = file(&[entry("1.0.0", true), entry("1.1.0", false)]);
+
+        let rebuilt = rebuild_index_file(&conn, crate_id, "foo", &current, false).unwrap();
+        assert_eq!(rebuilt.contents, current);
+
+        let with =
+            |entry: String, field: &str| format!("{},{}}}", entry.trim_end_matches('}'), field);
+        let rebuilt = rebuild_index_file(&conn, crate_id, "foo", &current, true).unwrap();
+        assert_eq!(
+            rebuilt.contents,
+            file(&[
+                with(entry("1.0.0", true), r#""yanked_reason":"security issue""#),
+                with(entry("1.1.0", false), r#""rust_version":"1.42""#),
+            ])
+        );
+        assert_eq!(rebuilt.changed, vec!["1.0.0", "1.1.0"]);
+    }
+}
@@ -0,0 +1,324 @@
+//! Refreshes the logins, names and avatars of users from GitHub
+//!
+//! Profiles are otherwise only updated when their users log in, so the ones
+//! of users who rarely log in go stale. Each run refreshes the profiles which
+//! weren't refreshed for `RECHECK_AFTER_DAYS`, the ones of users who
+//! published most recently first.
+//!
+//! Requests carry the ETag of the previous response, and GitHub doesn't
+//! count requests answered with `304 Not Modified` against the rate limit,
+//! so unchanged profiles are almost free. The rate limit is shared with the
+//! other users of `GITHUB_API_TOKEN`, so a run stops once fewer than
+//! `RATE_LIMIT_RESERVE` requests are left, and as soon as GitHub asks to back
+//! off.
+
+use std::thread;
+use std::time::Duration as StdDuration;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::dsl::now;
+use diesel::pg::upsert::excluded;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Integer, Nullable, Text, Timestamp};
+use reqwest::blocking::Client;
+use reqwest::{header, StatusCode};
+use swirl::PerformError;
+
+use crate::background_jobs::Environment;
+use crate::schema::{user_profile_syncs, users};
+
+/// The maximum number of profiles looked up per run.
+const MAX_LOOKUPS_PER_RUN: i64 = 1000;
+
+/// The pause between two API requests.
+const REQUEST_INTERVAL: StdDuration = StdDuration::from_millis(250);
+
+/// Profiles are looked up again once their last lookup is this old.
+const RECHECK_AFTER_DAYS: i64 = 30;
+
+/// The number of requests of the hourly rate limit left for the other jobs
+/// using the same token.
+const RATE_LIMIT_RESERVE: u32 = 1000;
+
+/// This is meant to be run periodically (e.g. hourly) via
+/// `enqueue-job refresh_user_profiles`, and does nothing unless
+/// `GITHUB_API_TOKEN` is set, since the rate limit without a token is too
+/// low to be worth it.
+#[swirl::background_job]
+pub fn refresh_user_profiles(env: &Environment) -> Result<(), PerformError> {
+    let _heartbeat = env.heartbeat("refresh_user_profiles")?;
+    let token = match dotenv::var("GITHUB_API_TOKEN") {
+        Ok(token) => token,
+        Err(_) => {
+            println!("GITHUB_API_TOKEN is not set, not refreshing user profiles");
+            return Ok(());
+        }
+    };
+    let conn = env.connection()?;
+    let client = ApiClient {
+        http_client: env.http_client(),
+        token,
+    };
+
+    let refreshed = refresh(&conn, |gh_id, etag| {
+        let lookup = client.profile(gh_id, etag);
+        thread::sleep(REQUEST_INTERVAL);
+        lookup
+    })?;
+    println!("count#user_profiles.refreshed={}", refreshed);
+    Ok(())
+}
+
+/// The parts of a GitHub user we keep.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+struct Profile {
+    login: String,
+    name: Option<String>,
+    avatar_url: Option<String>,
+}
+
+/// The result of looking up the profile of a user.
+#[derive(Clone, Debug, PartialEq)]
+enum Lookup {
+    Changed {
+        profile: Profile,
+        etag: Option<String>,
+    },
+    Unchanged,
+    /// The account was deleted.
+    Missing,
+    /// No further lookups should be made, until the given time if known.
+    RateLimited(Option<NaiveDateTime>),
+    Failed(String),
+}
+
+/// A lookup, and how many requests the rate limit has left after it.
+type Response = (Lookup, Option<u32>);
+
+struct ApiClient<'a> {
+    http_client: &'a Client,
+    token: String,
+}
+
+impl ApiClient<'_> {
+    fn profile(&self, gh_id: i32, etag: Option<&str>) -> Response {
+        let url = format!("https://api.github.com/user/{}", gh_id);
+        let mut request = self
+            .http_client
+            .get(&url)
+            .header(header::ACCEPT, "application/vnd.github.v3+json")
+            .header(header::AUTHORIZATION, format!("token {}", self.token))
+            .header(header::USER_AGENT, "crates.io (https://crates.io)");
+        if let Some(etag) = etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        let response = match request.send() {
+            Ok(response) => response,
+            Err(error) => return (Lookup::Failed(error.to_string()), None),
+        };
+
+        let headers = response.headers();
+        let number = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<i64>().ok())
+        };
+        let remaining = number("x-ratelimit-remaining").map(|remaining| remaining as u32);
+        let reset = number("x-ratelimit-reset")
+            .map(|reset| NaiveDateTime::from_timestamp(reset, 0))
+            .or_else(|| {
+                number("retry-after")
+                    .map(|seconds| Utc::now().naive_utc() + Duration::seconds(seconds))
+            });
+        let etag = headers
+            .get(header::ETAG)
+            .and_then(|value| value.to_str().ok())
+            .map(String::from);
+
+        let status = response.status();
+        let lookup = if status == StatusCode::TOO_MANY_REQUESTS
+            || (status == StatusCode::FORBIDDEN
+                && (remaining == Some(0) || headers.contains_key("retry-after")))
+        {
+            Lookup::RateLimited(reset)
+        } else if status == StatusCode::NOT_MODIFIED {
+            Lookup::Unchanged
+        } else if status == StatusCode::NOT_FOUND {
+            Lookup::Missing
+        } else if !status.is_success() {
+            Lookup::Failed(format!("unexpected status {}", status))
+        } else {
+            match response.json::<Profile>() {
+                Ok(profile) => Lookup::Changed { profile, etag },
+                Err(error) => Lookup::Failed(error.to_string()),
+            }
+        };
+        (lookup, remaining)
+    }
+}
+
+#[derive(Debug, QueryableByName)]
+struct Candidate {
+    #[sql_type = "Integer"]
+    id: i32,
+    #[sql_type = "Integer"]
+    gh_id: i32,
+    #[sql_type = "Nullable<Text>"]
+    etag: Option<String>,
+}
+
+fn refresh<F>(conn: &PgConnection, mut lookup: F) -> QueryResult<usize>
+where
+    F: FnMut(i32, Option<&str>) -> Response,
+{
+    let recheck_before = Utc::now().naive_utc() - Duration::days(RECHECK_AFTER_DAYS);
+    let candidates = diesel::sql_query(include_str!("refresh_user_profiles.sql"))
+        .bind::<Timestamp, _>(recheck_before)
+        .bind::<BigInt, _>(MAX_LOOKUPS_PER_RUN)
+        .load::<Candidate>(conn)?;
+
+    let mut refreshed = 0;
+    for candidate in candidates {
+        let (result, remaining) = lookup(candidate.gh_id, candidate.etag.as_deref());
+        let etag = match result {
+            Lookup::Changed { profile, etag } => {
+                diesel::update(users::table.find(candidate.id))
+                    .set((
+                        users::gh_login.eq(&profile.login),
+                        users::name.eq(&profile.name),
+                        users::gh_avatar.eq(&profile.avatar_url),
+                    ))
+                    .execute(conn)?;
+                etag
+            }
+            Lookup::Unchanged => candidate.etag,
+            // Deleted accounts keep their last known profile
+            Lookup::Missing => None,
+            Lookup::RateLimited(reset) => {
+                match reset {
+                    Some(reset) => println!("user_profiles.rate_limited_until={}", reset),
+                    None => println!("user_profiles.rate_limited=true"),
+                }
+                break;
+            }
+            Lookup::Failed(error) => {
+                // Moved to the back of the queue, so that one broken profile
+                // can't block all others
+                eprintln!(
+                    "Refreshing the profile of user {} failed: {}",
+                    candidate.id, error
+                );
+                candidate.etag
+            }
+        };
+        diesel::insert_into(user_profile_syncs::table)
+            .values((
+                user_profile_syncs::user_id.eq(candidate.id),
+                user_profile_syncs::etag.eq(&etag),
+            ))
+            .on_conflict(user_profile_syncs::user_id)
+            .do_update()
+            .set((
+                user_profile_syncs::etag.eq(excluded(user_profile_syncs::etag)),
+                user_profile_syncs::checked_at.eq(now),
+            ))
+            .execute(conn)?;
+        refreshed += 1;
+
+        if remaining.map_or(false, |remaining| remaining <= RATE_LIMIT_RESERVE) {
+            println!("user_profiles.rate_limit_reserve_reached=true");
+            break;
+        }
+    }
+
+    Ok(refreshed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{new_user, pg_connection};
+    use diesel::dsl::IntervalDsl;
+
+    fn profile(login: &str) -> Profile {
+        Profile {
+            login: login.into(),
+            name: Some("Name".into()),
+            avatar_url: Some(format!("https://avatars.example.com/{}", login)),
+        }
+    }
+
+    #[test]
+    fn profiles_are_refreshed_conditionally() {
+        let conn = pg_connection();
+        let renamed = new_user(&conn, 1, "old_login").id;
+        let unchanged = new_user(&conn, 2, "unchanged").id;
+
+        let mut lookups = Vec::new();
+        let refreshed = refresh(&conn, |gh_id, etag| {
+            lookups.push((gh_id, etag.map(String::from)));
+            let lookup = Lookup::Changed {
+                profile: profile(if gh_id == 1 { "new_login" } else { "unchanged" }),
+                etag: Some(format!("\"etag-{}\"", gh_id)),
+            };
+            (lookup, Some(4000))
+        })
+        .unwrap();
+        assert_eq!(refreshed, 2);
+        assert_eq!(lookups, vec![(1, None), (2, None)]);
+
+        let user = users::table
+            .find(renamed)
+            .select((users::gh_login, users::name, users::gh_avatar))
+            .first::<(String, Option<String>, Option<String>)>(&conn)
+            .unwrap();
+        assert_eq!(
+            user,
+            (
+                "new_login".into(),
+                Some("Name".into()),
+                Some("https://avatars.example.com/new_login".into())
+            )
+        );
+
+        // Recently refreshed profiles aren't looked up again, and the next
+        // lookup carries the ETag
+        let refreshed = refresh(&conn, |_, _| panic!("unexpected lookup")).unwrap();
+        assert_eq!(refreshed, 0);
+        diesel::update(user_profile_syncs::table)
+            .set(user_profile_syncs::checked_at.eq(now - 31.days()))
+            .execute(&conn)
+            .unwrap();
+        let mut etags = Vec::new();
+        refresh(&conn, |_, etag| {
+            etags.push(etag.map(String::from));
+            (Lookup::Unchanged, Some(4000))
+        })
+        .unwrap();
+        assert_eq!(
+            etags,
+            vec![Some("\"etag-1\"".into()), Some("\"etag-2\"".into())]
+        );
+        let etag = user_profile_syncs::table
+            .find(unchanged)
+            .select(user_profile_syncs::etag)
+            .first::<Option<String>>(&conn)
+            .unwrap();
+        assert_eq!(etag.as_deref(), Some("\"etag-2\""));
+    }
+
+    #[test]
+    fn refresh_stops_at_the_rate_limit_reserve() {
+        let conn = pg_connection();
+        for gh_id in 1..=3 {
+            new_user(&conn, gh_id, &format!("user{}", gh_id));
+        }
+
+        let refreshed =
+            refresh(&conn, |_, _| (Lookup::Unchanged, Some(RATE_LIMIT_RESERVE))).unwrap();
+        assert_eq!(refreshed, 1);
+        let refreshed = refresh(&conn, |_, _| (Lookup::RateLimited(None), Some(0))).unwrap();
+        assert_eq!(refreshed, 0);
+    }
+}
@@ -0,0 +1,15 @@
+-- The users whose profiles are due for a refresh, the ones who published
+-- most recently first, followed by the ones who never published
+SELECT users.id, users.gh_id, user_profile_syncs.etag
+FROM users
+LEFT JOIN user_profile_syncs ON user_profile_syncs.user_id = users.id
+LEFT JOIN (
+  SELECT published_by, MAX(created_at) AS last_published_at
+  FROM versions
+  WHERE published_by IS NOT NULL
+  GROUP BY published_by
+) AS activity ON activity.published_by = users.id
+WHERE users.gh_id > 0
+  AND (user_profile_syncs.checked_at IS NULL OR user_profile_syncs.checked_at < $1)
+ORDER BY activity.last_published_at DESC NULLS LAST, users.id
+LIMIT $2
@@ -0,0 +1,30 @@
+//! Signs the head of the transparency log
+//!
+//! The PEM encoded private key is taken from `TRANSPARENCY_LOG_KEY`. Rotating
+//! the key doesn't invalidate earlier tree heads, since every head is served
+//! with the public key it was signed with.
+
+use chrono::Utc;
+use openssl::pkey::PKey;
+use swirl::PerformError;
+
+use crate::background_jobs::Environment;
+use crate::models::SignedTreeHead;
+
+/// Meant to be run hourly via `enqueue-job sign_transparency_log`.
+#[swirl::background_job]
+pub fn sign_transparency_log(env: &Environment) -> Result<(), PerformError> {
+    let _heartbeat = env.heartbeat("sign_transparency_log")?;
+    let key = dotenv::var("TRANSPARENCY_LOG_KEY")
+        .map_err(|_| "TRANSPARENCY_LOG_KEY must be set to sign the transparency log")?;
+    let key = PKey::private_key_from_pem(key.as_bytes())?;
+
+    let conn = env.connection()?;
+    let head = SignedTreeHead::sign(&conn, &key, Utc::now().naive_utc())
+        .map_err(|e| format!("Signing the tree head failed: {}", e))?;
+    println!(
+        "transparency_log.tree_size={} transparency_log.root_hash={}",
+        head.tree_size, head.root_hash
+    );
+    Ok(())
+}
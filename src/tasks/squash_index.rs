@@ -0,0 +1,82 @@
+//! Squashes the history of the index once it grew too long
+//!
+//! Every publish and yank adds a commit to the index, and cloning or
+//! fetching its full history gets slower for everyone using the git
+//! protocol. Once `HEAD` has more than `INDEX_SQUASH_THRESHOLD` commits, the
+//! history is pushed to a `snapshot-YYYY-MM-DD` branch, and `master` is
+//! force-pushed as a single commit of the current tree. Cargo copes with the
+//! force-push by fetching the new commit, so only the snapshot branch keeps
+//! the previous history.
+//!
+//! The index is locked during the squash, so no index update can be pushed
+//! in between and get lost.
+
+use chrono::Utc;
+use diesel::prelude::*;
+use swirl::PerformError;
+
+use crate::background_jobs::Environment;
+use crate::git::Repository;
+use crate::models::IndexSquash;
+
+/// The number of commits the index can have, unless `INDEX_SQUASH_THRESHOLD`
+/// is set.
+const DEFAULT_THRESHOLD: usize = 50_000;
+
+#[swirl::background_job]
+pub fn squash_index(env: &Environment) -> Result<(), PerformError> {
+    let _heartbeat = env.heartbeat("squash_index")?;
+    let threshold = match dotenv::var("INDEX_SQUASH_THRESHOLD") {
+        Ok(value) => value
+            .parse::<usize>()
+            .ok()
+            .filter(|&threshold| threshold > 0)
+            .ok_or_else(|| format!("invalid `INDEX_SQUASH_THRESHOLD`: `{}`", value))?,
+        Err(_) => DEFAULT_THRESHOLD,
+    };
+    let conn = env.connection()?;
+    let repo = env.lock_index()?;
+    repo.reset_head()?;
+    squash_index_history(&repo, &conn, threshold)?;
+    Ok(())
+}
+
+/// Squashes the history of the index if it has more than `threshold`
+/// commits, and records the squash.
+pub fn squash_index_history(
+    repo: &Repository,
+    conn: &PgConnection,
+    threshold: usize,
+) -> Result<Option<IndexSquash>, PerformError> {
+    let commit_count = repo.count_commits(threshold + 1)?;
+    if commit_count <= threshold {
+        println!(
+            "The index has {} commits, squashing at more than {}",
+            commit_count, threshold
+        );
+        return Ok(None);
+    }
+    // Only counted exactly for the record, now that it's needed
+    let commit_count = repo.count_commits(usize::max_value())?;
+
+    let snapshot_branch = format!("snapshot-{}", Utc::now().format("%Y-%m-%d"));
+    let message = format!(
+        "Collapse index into one commit\n\n\
+         The previous history of {} commits is on the `{}` branch.",
+        commit_count, snapshot_branch
+    );
+    let (previous_head, squashed_commit) = repo.squash(&snapshot_branch, &message)?;
+    println!(
+        "count#index_squash.commits={} Squashed the index into {}, the previous HEAD {} is on `{}`",
+        commit_count, squashed_commit, previous_head, snapshot_branch
+    );
+
+    let squash = IndexSquash::record(
+        conn,
+        &previous_head,
+        &squashed_commit,
+        &snapshot_branch,
+        commit_count as i32,
+    )?;
+    Ok(Some(squash))
+}
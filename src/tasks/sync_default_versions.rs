@@ -0,0 +1,161 @@
+use crate::{
+    background_jobs::Environment,
+    models::default_versions::{update_default_version, verify_default_version},
+    schema::crates,
+};
+
+use diesel::prelude::*;
+use swirl::PerformError;
+
+/// The number of crates loaded from the database at a time.
+const BATCH_SIZE: i64 = 1000;
+
+/// A `default_versions` row that did not match the expected default version.
+#[derive(Debug, PartialEq)]
+struct Repair {
+    crate_id: i32,
+    crate_name: String,
+    previous_version_id: Option<i32>,
+    new_version_id: i32,
+}
+
+/// Recomputes the default version of every crate and repairs any drift.
+///
+/// This is meant to be run periodically (e.g. once a day) via
+/// `enqueue-job sync_default_versions`.
+#[swirl::background_job]
+pub fn sync_default_versions(env: &Environment) -> Result<(), PerformError> {
+    let _heartbeat = env.heartbeat("sync_default_versions")?;
+    let conn = env.connection()?;
+    let repairs = sync(&conn)?;
+    report(&repairs);
+    Ok(())
+}
+
+fn sync(conn: &PgConnection) -> QueryResult<Vec<Repair>> {
+    let mut repairs = Vec::new();
+    let mut checked = 0;
+    let mut last_id = 0;
+
+    loop {
+        let batch = crates::table
+            .select((crates::id, crates::name))
+            .filter(crates::id.gt(last_id))
+            .order(crates::id)
+            .limit(BATCH_SIZE)
+            .load::<(i32, String)>(conn)?;
+
+        let (crate_id, _) = match batch.last() {
+            Some(last) => last,
+            None => break,
+        };
+        last_id = *crate_id;
+
+        conn.transaction::<_, diesel::result::Error, _>(|| {
+            for (crate_id, crate_name) in &batch {
+                let (previous_version_id, new_version_id) =
+                    match verify_default_version(*crate_id, conn) {
+                        Ok(Some(mismatch)) => mismatch,
+                        Ok(None) => continue,
+                        // Crates without any versions have no default version
+                        Err(diesel::result::Error::NotFound) => continue,
+                        Err(error) => return Err(error),
+                    };
+
+                update_default_version(*crate_id, conn)?;
+                repairs.push(Repair {
+                    crate_id: *crate_id,
+                    crate_name: crate_name.clone(),
+                    previous_version_id,
+                    new_version_id,
+                });
+            }
+            Ok(())
+        })?;
+
+        checked += batch.len();
+        println!("Checked default versions of {} crates", checked);
+    }
+
+    Ok(repairs)
+}
+
+fn report(repairs: &[Repair]) {
+    for repair in repairs {
+        let previous = repair
+            .previous_version_id
+            .map(|id| id.to_string())
+            .unwrap_or_else(|| "none".into());
+        println!(
+            "Repaired default version of `{}` (crate_id={}): {} -> {}",
+            repair.crate_name, repair.crate_id, previous, repair.new_version_id
+        );
+    }
+    println!("default_versions.repaired_rows={}", repairs.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Crate, Version};
+    use crate::schema::{default_versions, versions};
+    use crate::test_util::{new_crate, new_user, new_version, pg_connection};
+
+    fn crate_with_versions(conn: &PgConnection, nums: &[&str]) -> (Crate, Vec<Version>) {
+        let user = new_user(conn, 2, "login");
+        let krate = new_crate(conn, "foo", user.id);
+        let versions = nums
+            .iter()
+            .map(|num| new_version(conn, krate.id, num, user.id))
+            .collect();
+        (krate, versions)
+    }
+
+    fn default_version_id(conn: &PgConnection, crate_id: i32) -> Option<i32> {
+        default_versions::table
+            .select(default_versions::version_id)
+            .filter(default_versions::crate_id.eq(crate_id))
+            .first(conn)
+            .optional()
+            .unwrap()
+    }
+
+    #[test]
+    fn missing_rows_are_created() {
+        let conn = pg_connection();
+        let (krate, versions) = crate_with_versions(&conn, &["1.0.0", "1.1.0"]);
+
+        let repairs = sync(&conn).unwrap();
+        assert_eq!(repairs.len(), 1);
+        assert_eq!(repairs[0].previous_version_id, None);
+        assert_eq!(default_version_id(&conn, krate.id), Some(versions[1].id));
+    }
+
+    #[test]
+    fn drifted_rows_are_repaired() {
+        let conn = pg_connection();
+        let (krate, versions) = crate_with_versions(&conn, &["1.0.0", "1.1.0"]);
+        update_default_version(krate.id, &conn).unwrap();
+
+        // Simulate a yank that did not update the denormalized table
+        diesel::update(versions::table.find(versions[1].id))
+            .set(versions::yanked.eq(true))
+            .execute(&conn)
+            .unwrap();
+
+        let repairs = sync(&conn).unwrap();
+        assert_eq!(
+            repairs,
+            vec![Repair {
+                crate_id: krate.id,
+                crate_name: krate.name.clone(),
+                previous_version_id: Some(versions[1].id),
+                new_version_id: versions[0].id,
+            }]
+        );
+        assert_eq!(default_version_id(&conn, krate.id), Some(versions[0].id));
+
+        // A second run doesn't find anything to repair
+        assert!(sync(&conn).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,396 @@
+use crate::{background_jobs::Environment, schema::crates};
+
+use std::thread;
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use diesel::dsl::{now, sql};
+use diesel::prelude::*;
+use diesel::sql_types::Bool;
+use reqwest::blocking::Client;
+use reqwest::{header, StatusCode};
+use swirl::PerformError;
+use url::percent_encoding::{utf8_percent_encode, PATH_SEGMENT_ENCODE_SET};
+use url::Url;
+
+/// The maximum number of repositories looked up per run. Together with
+/// `REQUEST_INTERVAL` this keeps a run well within the API rate limits of
+/// the hosting providers.
+const MAX_LOOKUPS_PER_RUN: i64 = 500;
+
+/// The pause between two API requests.
+const REQUEST_INTERVAL: StdDuration = StdDuration::from_millis(500);
+
+/// Repositories are looked up again once their last lookup is this old.
+const RECHECK_AFTER_DAYS: i64 = 7;
+
+/// Records when the last commit was pushed to the repositories of crates
+/// hosted on GitHub or GitLab.
+///
+/// This is meant to be run periodically (e.g. hourly) via
+/// `enqueue-job sync_repository_activity`. Each run looks up the repositories
+/// which were never or least recently looked up. `GITHUB_API_TOKEN` and
+/// `GITLAB_API_TOKEN` can be set to raise the rate limits of the APIs.
+#[swirl::background_job]
+pub fn sync_repository_activity(env: &Environment) -> Result<(), PerformError> {
+    let _heartbeat = env.heartbeat("sync_repository_activity")?;
+    let conn = env.connection()?;
+    let client = ApiClient {
+        http_client: env.http_client(),
+        github_token: dotenv::var("GITHUB_API_TOKEN").ok(),
+        gitlab_token: dotenv::var("GITLAB_API_TOKEN").ok(),
+    };
+
+    let synced = sync(&conn, |repository| {
+        let lookup = client.last_commit(repository);
+        thread::sleep(REQUEST_INTERVAL);
+        lookup
+    })?;
+    println!("repository_activity.synced_crates={}", synced);
+    Ok(())
+}
+
+/// A repository on one of the supported hosting providers.
+#[derive(Clone, Debug, PartialEq)]
+enum Repository {
+    GitHub { owner: String, name: String },
+    GitLab { path: String },
+}
+
+impl Repository {
+    fn parse(url: &str) -> Option<Self> {
+        let url = Url::parse(url).ok()?;
+        let mut segments = url
+            .path_segments()?
+            .filter(|segment| !segment.is_empty())
+            .map(|segment| segment.trim_end_matches(".git"));
+
+        match url.host_str()? {
+            "github.com" | "www.github.com" => {
+                let owner = segments.next()?.to_string();
+                let name = segments.next()?.to_string();
+                Some(Repository::GitHub { owner, name })
+            }
+            "gitlab.com" | "www.gitlab.com" => {
+                // GitLab projects can be nested in groups, and the pages of a
+                // project are separated from its path by a `-` segment.
+                let path = segments
+                    .take_while(|&segment| segment != "-")
+                    .collect::<Vec<_>>();
+                if path.len() < 2 {
+                    return None;
+                }
+                Some(Repository::GitLab {
+                    path: path.join("/"),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The result of looking up the last commit of a repository.
+#[derive(Clone, Debug, PartialEq)]
+enum Lookup {
+    Found(NaiveDateTime),
+    /// The repository doesn't exist (anymore) or has no commits.
+    Missing,
+    /// The rate limit of the API was hit, no further lookups should be made.
+    RateLimited,
+    Failed(String),
+}
+
+struct ApiClient<'a> {
+    http_client: &'a Client,
+    github_token: Option<String>,
+    gitlab_token: Option<String>,
+}
+
+impl ApiClient<'_> {
+    fn last_commit(&self, repository: &Repository) -> Lookup {
+        #[derive(Deserialize)]
+        struct GitHubCommit {
+            commit: GitHubCommitDetails,
+        }
+        #[derive(Deserialize)]
+        struct GitHubCommitDetails {
+            committer: GitHubSignature,
+        }
+        #[derive(Deserialize)]
+        struct GitHubSignature {
+            date: DateTime<Utc>,
+        }
+        #[derive(Deserialize)]
+        struct GitLabCommit {
+            committed_date: DateTime<Utc>,
+        }
+
+        let request = match repository {
+            Repository::GitHub { owner, name } => {
+                let url = format!(
+                    "https://api.github.com/repos/{}/{}/commits?per_page=1",
+                    owner, name
+                );
+                let request = self
+                    .http_client
+                    .get(&url)
+                    .header(header::ACCEPT, "application/vnd.github.v3+json");
+                match &self.github_token {
+                    Some(token) => {
+                        request.header(header::AUTHORIZATION, format!("token {}", token))
+                    }
+                    None => request,
+                }
+            }
+            Repository::GitLab { path } => {
+                let url = format!(
+                    "https://gitlab.com/api/v4/projects/{}/repository/commits?per_page=1",
+                    utf8_percent_encode(path, PATH_SEGMENT_ENCODE_SET)
+                );
+                let request = self.http_client.get(&url);
+                match &self.gitlab_token {
+                    Some(token) => request.header("PRIVATE-TOKEN", token.as_str()),
+                    None => request,
+                }
+            }
+        };
+
+        let response = match request
+            .header(header::USER_AGENT, "crates.io (https://crates.io)")
+            .send()
+        {
+            Ok(response) => response,
+            Err(error) => return Lookup::Failed(error.to_string()),
+        };
+
+        let status = response.status();
+        let rate_limit_exhausted = response
+            .headers()
+            .get("x-ratelimit-remaining")
+            .map_or(false, |remaining| remaining == "0");
+        if status == StatusCode::TOO_MANY_REQUESTS
+            || (status == StatusCode::FORBIDDEN && rate_limit_exhausted)
+        {
+            return Lookup::RateLimited;
+        }
+        // GitHub answers with `409 Conflict` for empty repositories
+        if status == StatusCode::NOT_FOUND || status == StatusCode::CONFLICT {
+            return Lookup::Missing;
+        }
+        if !status.is_success() {
+            return Lookup::Failed(format!("unexpected status {}", status));
+        }
+
+        let date = match repository {
+            Repository::GitHub { .. } => response
+                .json::<Vec<GitHubCommit>>()
+                .map(|commits| commits.into_iter().next().map(|c| c.commit.committer.date)),
+            Repository::GitLab { .. } => response
+                .json::<Vec<GitLabCommit>>()
+                .map(|commits| commits.into_iter().next().map(|c| c.committed_date)),
+        };
+        match date {
+            Ok(Some(date)) => Lookup::Found(date.naive_utc()),
+            Ok(None) => Lookup::Missing,
+            Err(error) => Lookup::Failed(error.to_string()),
+        }
+    }
+}
+
+fn sync<F>(conn: &PgConnection, mut last_commit: F) -> QueryResult<usize>
+where
+    F: FnMut(&Repository) -> Lookup,
+{
+    let recheck_before = Utc::now().naive_utc() - Duration::days(RECHECK_AFTER_DAYS);
+    let candidates = crates::table
+        .select((crates::id, crates::repository))
+        .filter(sql::<Bool>(
+            "crates.repository ~* '^https?://(www\\.)?(github|gitlab)\\.com/'",
+        ))
+        .filter(
+            crates::repository_checked_at
+                .is_null()
+                .or(crates::repository_checked_at.lt(recheck_before)),
+        )
+        .order((
+            crates::repository_checked_at.asc().nulls_first(),
+            crates::id,
+        ))
+        .limit(MAX_LOOKUPS_PER_RUN)
+        .load::<(i32, Option<String>)>(conn)?;
+
+    let mut synced = 0;
+    for (crate_id, url) in candidates {
+        let target = crates::table.find(crate_id);
+        let repository = match url.as_ref().and_then(|url| Repository::parse(url)) {
+            Some(repository) => repository,
+            None => {
+                diesel::update(target)
+                    .set(crates::repository_checked_at.eq(now))
+                    .execute(conn)?;
+                continue;
+            }
+        };
+
+        match last_commit(&repository) {
+            Lookup::Found(date) => {
+                diesel::update(target)
+                    .set((
+                        crates::repository_last_commit_at.eq(date),
+                        crates::repository_checked_at.eq(now),
+                    ))
+                    .execute(conn)?;
+            }
+            Lookup::Missing => {
+                diesel::update(target)
+                    .set((
+                        crates::repository_last_commit_at.eq(None::<NaiveDateTime>),
+                        crates::repository_checked_at.eq(now),
+                    ))
+                    .execute(conn)?;
+            }
+            Lookup::RateLimited => {
+                println!("repository_activity.rate_limited=true");
+                break;
+            }
+            Lookup::Failed(error) => {
+                // Keep the previously known activity, but move the crate to
+                // the back of the queue so that one broken repository can't
+                // block all others.
+                eprintln!(
+                    "Looking up the activity of {:?} failed: {}",
+                    repository, error
+                );
+                diesel::update(target)
+                    .set(crates::repository_checked_at.eq(now))
+                    .execute(conn)?;
+            }
+        }
+        synced += 1;
+    }
+
+    Ok(synced)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{new_crate, new_user, pg_connection};
+    use chrono::NaiveDate;
+
+    fn crate_with_repository(conn: &PgConnection, name: &str, repository: &str) -> i32 {
+        let user = new_user(conn, 2, "login");
+        let krate = new_crate(conn, name, user.id);
+        diesel::update(&krate)
+            .set(crates::repository.eq(repository))
+            .execute(conn)
+            .unwrap();
+        krate.id
+    }
+
+    fn github(owner: &str, name: &str) -> Option<Repository> {
+        Some(Repository::GitHub {
+            owner: owner.into(),
+            name: name.into(),
+        })
+    }
+
+    fn gitlab(path: &str) -> Option<Repository> {
+        Some(Repository::GitLab { path: path.into() })
+    }
+
+    #[test]
+    fn parse_repository_urls() {
+        let parse = Repository::parse;
+        assert_eq!(
+            parse("https://github.com/rust-lang/cargo"),
+            github("rust-lang", "cargo")
+        );
+        assert_eq!(
+            parse("https://github.com/rust-lang/cargo/"),
+            github("rust-lang", "cargo")
+        );
+        assert_eq!(
+            parse("https://github.com/rust-lang/cargo.git"),
+            github("rust-lang", "cargo")
+        );
+        assert_eq!(
+            parse("https://www.github.com/rust-lang/cargo/tree/master/crates"),
+            github("rust-lang", "cargo")
+        );
+        assert_eq!(
+            parse("https://gitlab.com/group/project"),
+            gitlab("group/project")
+        );
+        assert_eq!(
+            parse("https://gitlab.com/group/subgroup/project/-/tree/master"),
+            gitlab("group/subgroup/project")
+        );
+        assert_eq!(parse("https://github.com/rust-lang"), None);
+        assert_eq!(parse("https://gitlab.com/project"), None);
+        assert_eq!(parse("https://example.com/rust-lang/cargo"), None);
+        assert_eq!(parse("not a url"), None);
+    }
+
+    #[test]
+    fn activity_is_recorded() {
+        let conn = pg_connection();
+        let active = crate_with_repository(&conn, "active", "https://github.com/foo/active");
+        let gone = crate_with_repository(&conn, "gone", "https://gitlab.com/foo/gone");
+        let elsewhere =
+            crate_with_repository(&conn, "elsewhere", "https://example.com/foo/elsewhere");
+        let last_commit = NaiveDate::from_ymd(2020, 1, 1).and_hms(12, 0, 0);
+
+        let mut lookups = Vec::new();
+        let synced = sync(&conn, |repository| {
+            lookups.push(repository.clone());
+            match repository {
+                Repository::GitHub { .. } => Lookup::Found(last_commit),
+                Repository::GitLab { .. } => Lookup::Missing,
+            }
+        })
+        .unwrap();
+        assert_eq!(synced, 2);
+        assert_eq!(
+            lookups,
+            vec![
+                github("foo", "active").unwrap(),
+                gitlab("foo/gone").unwrap()
+            ]
+        );
+
+        let activity = |id| {
+            crates::table
+                .find(id)
+                .select((
+                    crates::repository_last_commit_at,
+                    crates::repository_checked_at.is_not_null(),
+                ))
+                .first::<(Option<NaiveDateTime>, bool)>(&conn)
+                .unwrap()
+        };
+        assert_eq!(activity(active), (Some(last_commit), true));
+        assert_eq!(activity(gone), (None, true));
+        assert_eq!(activity(elsewhere), (None, false));
+
+        // Recently checked repositories aren't looked up again
+        let synced = sync(&conn, |_| panic!("unexpected lookup")).unwrap();
+        assert_eq!(synced, 0);
+    }
+
+    #[test]
+    fn sync_stops_when_rate_limited() {
+        let conn = pg_connection();
+        for name in &["one", "two"] {
+            crate_with_repository(&conn, name, "https://github.com/foo/bar");
+        }
+
+        let mut lookups = 0;
+        let synced = sync(&conn, |_| {
+            lookups += 1;
+            Lookup::RateLimited
+        })
+        .unwrap();
+        assert_eq!((synced, lookups), (0, 1));
+    }
+}
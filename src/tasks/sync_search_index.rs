@@ -0,0 +1,123 @@
+use crate::{
+    background_jobs::Environment,
+    schema::{crates, search_index_cursors},
+    search_backend::SearchBackend,
+};
+
+use chrono::{Duration, NaiveDateTime};
+use diesel::prelude::*;
+use swirl::PerformError;
+
+/// The number of crates sent to the search backend at a time.
+const BATCH_SIZE: usize = 500;
+
+/// Crates updated this many minutes before the end of the previous sync are
+/// indexed again, so that transactions committing late aren't missed.
+const OVERLAP_MINUTES: i64 = 5;
+
+/// Sends all crates updated since the previous run to the search backend.
+///
+/// This is meant to be run periodically (e.g. every few minutes) via
+/// `enqueue-job sync_search_index`. The first run indexes all crates.
+#[swirl::background_job]
+pub fn sync_search_index(env: &Environment) -> Result<(), PerformError> {
+    let _heartbeat = env.heartbeat("sync_search_index")?;
+    let conn = env.connection()?;
+    let synced = sync(&conn, env.search_backend())?;
+    println!("search_index.synced_crates={}", synced);
+    Ok(())
+}
+
+/// Removes a deleted crate from the index of the search backend, which
+/// `sync_search_index` can't notice.
+#[swirl::background_job]
+pub fn remove_from_search_index(env: &Environment, crate_id: i32) -> Result<(), PerformError> {
+    let _heartbeat = env.heartbeat("remove_from_search_index")?;
+    env.search_backend().remove_crates(&[crate_id])
+}
+
+fn sync(conn: &PgConnection, backend: &dyn SearchBackend) -> Result<usize, PerformError> {
+    let synced_until = search_index_cursors::table
+        .find(backend.name())
+        .select(search_index_cursors::synced_until)
+        .first::<NaiveDateTime>(conn)
+        .optional()?;
+
+    let mut query = crates::table
+        .select((crates::id, crates::updated_at))
+        .order(crates::updated_at)
+        .into_boxed();
+    if let Some(synced_until) = synced_until {
+        let since = synced_until - Duration::minutes(OVERLAP_MINUTES);
+        query = query.filter(crates::updated_at.gt(since));
+    }
+    let updated = query.load::<(i32, NaiveDateTime)>(conn)?;
+
+    for batch in updated.chunks(BATCH_SIZE) {
+        let crate_ids = batch.iter().map(|&(id, _)| id).collect::<Vec<_>>();
+        backend.index_crates(conn, &crate_ids)?;
+
+        let (_, updated_at) = batch[batch.len() - 1];
+        diesel::insert_into(search_index_cursors::table)
+            .values((
+                search_index_cursors::backend.eq(backend.name()),
+                search_index_cursors::synced_until.eq(updated_at),
+            ))
+            .on_conflict(search_index_cursors::backend)
+            .do_update()
+            .set(search_index_cursors::synced_until.eq(updated_at))
+            .execute(conn)?;
+    }
+
+    Ok(updated.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        search_backend::TextSearch,
+        test_util::{new_crate, new_user, pg_connection},
+        util::errors::AppResult,
+    };
+    use diesel::dsl::{now, IntervalDsl};
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<i32>>);
+
+    impl SearchBackend for Recorder {
+        fn name(&self) -> &'static str {
+            "recorder"
+        }
+
+        fn text_search(&self, _: &PgConnection, _: &str) -> AppResult<TextSearch> {
+            Ok(TextSearch::Ranked(Vec::new()))
+        }
+
+        fn index_crates(&self, _: &PgConnection, crate_ids: &[i32]) -> Result<(), PerformError> {
+            self.0.lock().unwrap().extend_from_slice(crate_ids);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn only_updated_crates_are_indexed() {
+        let conn = pg_connection();
+        let user = new_user(&conn, 2, "login");
+        let old = new_crate(&conn, "old", user.id).id;
+        let new = new_crate(&conn, "new", user.id).id;
+        diesel::update(crates::table.find(old))
+            .set(crates::updated_at.eq(now - 1.day()))
+            .execute(&conn)
+            .unwrap();
+
+        let recorder = Recorder::default();
+        assert_eq!(sync(&conn, &recorder).unwrap(), 2);
+        assert_eq!(*recorder.0.lock().unwrap(), vec![old, new]);
+
+        let recorder = Recorder::default();
+        assert_eq!(sync(&conn, &recorder).unwrap(), 1);
+        assert_eq!(*recorder.0.lock().unwrap(), vec![new]);
+    }
+}
@@ -9,6 +9,7 @@ use swirl::PerformError;
 
 #[swirl::background_job]
 pub fn update_downloads(env: &Environment) -> Result<(), PerformError> {
+    let _heartbeat = env.heartbeat("update_downloads")?;
     let conn = env.connection()?;
     update(&conn)?;
     Ok(())
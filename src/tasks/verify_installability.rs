@@ -0,0 +1,151 @@
+//! Verifies that recently published versions can be installed
+//!
+//! A few minutes after a version was published, its index entry is fetched
+//! from `PUBLIC_INDEX_URL`, the root of the index as cargo reads it through
+//! the CDN, e.g. `https://raw.githubusercontent.com/rust-lang/crates.io-index/master`,
+//! and its crate file is downloaded from its public download URL. Both have
+//! to match the checksum recorded at publish time, otherwise the check fails
+//! and is repeated on the next run. The `monitor` binary pages whoever is on
+//! call while checks are failing.
+//!
+//! `INSTALL_CHECK_DELAY` is the number of minutes the CDN gets to pick up a
+//! new version before it's checked, 5 by default. The first passing check is
+//! recorded as the time the version became visible in the `index_syncs`
+//! table.
+
+use reqwest::blocking::Client;
+use serde_json::Value;
+use swirl::PerformError;
+
+use crate::background_jobs::Environment;
+use crate::git::relative_index_path;
+use crate::models::{IndexSync, Version, VersionInstallCheck};
+
+const DEFAULT_DELAY_MINUTES: i32 = 5;
+
+/// Meant to be run every few minutes via `enqueue-job verify_installability`.
+#[swirl::background_job]
+pub fn verify_installability(env: &Environment) -> Result<(), PerformError> {
+    let _heartbeat = env.heartbeat("verify_installability")?;
+    let index_url = dotenv::var("PUBLIC_INDEX_URL")
+        .map_err(|_| "PUBLIC_INDEX_URL must be set to verify that versions can be installed")?;
+    let delay = dotenv::var("INSTALL_CHECK_DELAY")
+        .map(|s| {
+            s.parse()
+                .expect("INSTALL_CHECK_DELAY must be a number of minutes")
+        })
+        .unwrap_or(DEFAULT_DELAY_MINUTES);
+
+    let conn = env.connection()?;
+    for (version_id, name, num) in VersionInstallCheck::due(&conn, delay)? {
+        let checksum = match Version::checksum(version_id, &conn)? {
+            Some(checksum) => checksum,
+            None => continue,
+        };
+        let download_url = env.uploader.crate_location(&name, &num);
+        let result = check_index(env.http_client(), &index_url, &name, &num, &checksum)
+            .and_then(|_| check_download(env.http_client(), &download_url, &checksum));
+        println!(
+            "install_check.crate={} install_check.version={} install_check.error={:?}",
+            name,
+            num,
+            result.as_ref().err()
+        );
+        IndexSync::record_visible(&conn, version_id, result.is_ok())?;
+        VersionInstallCheck::record(&conn, version_id, result)?;
+    }
+    Ok(())
+}
+
+fn check_index(
+    client: &Client,
+    index_url: &str,
+    name: &str,
+    num: &str,
+    checksum: &str,
+) -> Result<(), String> {
+    let url = format!(
+        "{}/{}",
+        index_url.trim_end_matches('/'),
+        relative_index_path(name)
+    );
+    let index_file = client
+        .get(&url)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.text())
+        .map_err(|e| format!("fetching the index file failed: {}", e))?;
+    check_index_entry(&index_file, num, checksum)
+}
+
+fn check_download(client: &Client, url: &str, checksum: &str) -> Result<(), String> {
+    let body = client
+        .get(url)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.bytes())
+        .map_err(|e| format!("downloading the crate file failed: {}", e))?;
+    check_crate_file(&body, checksum)
+}
+
+/// Checks that an index file has an entry for the version with the expected
+/// checksum.
+fn check_index_entry(index_file: &str, num: &str, checksum: &str) -> Result<(), String> {
+    let entry = index_file
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .find(|entry| entry["vers"].as_str() == Some(num))
+        .ok_or_else(|| format!("the index file has no entry for version {}", num))?;
+    match entry["cksum"].as_str() {
+        Some(cksum) if cksum == checksum => Ok(()),
+        cksum => Err(format!(
+            "the index entry has the checksum {}, expected {}",
+            cksum.unwrap_or("null"),
+            checksum
+        )),
+    }
+}
+
+/// Checks that the downloaded crate file has the expected SHA-256 checksum.
+fn check_crate_file(body: &[u8], checksum: &str) -> Result<(), String> {
+    let actual = hex::encode(openssl::sha::sha256(body));
+    if actual == checksum {
+        Ok(())
+    } else {
+        Err(format!(
+            "the downloaded crate file has the checksum {}, expected {}",
+            actual, checksum
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_entries_must_have_the_recorded_checksum() {
+        let index_file = concat!(
+            r#"{"name":"foo","vers":"1.0.0","deps":[],"cksum":"aaa","features":{},"yanked":false}"#,
+            "\n",
+            r#"{"name":"foo","vers":"1.1.0","deps":[],"cksum":"bbb","features":{},"yanked":false}"#,
+            "\n",
+        );
+        assert_eq!(check_index_entry(index_file, "1.1.0", "bbb"), Ok(()));
+        assert_eq!(
+            check_index_entry(index_file, "1.1.0", "ccc"),
+            Err("the index entry has the checksum bbb, expected ccc".into())
+        );
+        assert_eq!(
+            check_index_entry(index_file, "2.0.0", "bbb"),
+            Err("the index file has no entry for version 2.0.0".into())
+        );
+    }
+
+    #[test]
+    fn crate_files_must_have_the_recorded_checksum() {
+        let checksum = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        assert_eq!(check_crate_file(b"hello", checksum), Ok(()));
+        assert!(check_crate_file(b"hello!", checksum).is_err());
+    }
+}
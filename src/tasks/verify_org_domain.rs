@@ -0,0 +1,115 @@
+//! Verifies that a GitHub organization controls a domain
+//!
+//! The TXT records of the challenge subdomain are looked up over DNS over
+//! HTTPS, using the JSON API which Cloudflare and Google both offer, so that
+//! the worker doesn't need a resolver of its own. `DNS_OVER_HTTPS_URL`
+//! selects the resolver, Cloudflare's by default.
+
+use reqwest::blocking::Client;
+use reqwest::header;
+use serde_json::Value;
+use swirl::PerformError;
+
+use crate::background_jobs::{EnqueueVersioned, Environment};
+use crate::crate_page;
+use crate::models::OrgDomain;
+use crate::schema::org_domains;
+
+use diesel::prelude::*;
+
+const DEFAULT_RESOLVER: &str = "https://cloudflare-dns.com/dns-query";
+
+/// The DNS type of TXT records.
+const TXT: u64 = 16;
+
+#[swirl::background_job]
+pub fn verify_org_domain(env: &Environment, domain_id: i32) -> Result<(), PerformError> {
+    let _heartbeat = env.heartbeat("verify_org_domain")?;
+    let conn = env.connection()?;
+    let domain = org_domains::table
+        .find(domain_id)
+        .first::<OrgDomain>(&*conn)
+        .optional()?;
+    let domain = match domain {
+        Some(domain) => domain,
+        None => return Ok(()),
+    };
+
+    let resolver = dotenv::var("DNS_OVER_HTTPS_URL").unwrap_or_else(|_| DEFAULT_RESOLVER.into());
+    let verified = domain.check(&conn, |name| {
+        txt_records(env.http_client(), &resolver, name).map_err(|e| e.to_string())
+    })?;
+    println!(
+        "org_domain.domain={} org_domain.verified={:?}",
+        domain.domain, verified
+    );
+
+    // Crate pages show the verified domain of the organization
+    if verified.is_some() && verified != Some(domain.verified_at.is_some()) {
+        for name in OrgDomain::crates_of_org(&conn, &domain.org)? {
+            crate_page::render_crate_page(name).enqueue_versioned(&conn)?;
+        }
+    }
+    Ok(())
+}
+
+fn txt_records(client: &Client, resolver: &str, name: &str) -> Result<Vec<String>, PerformError> {
+    let response = client
+        .get(resolver)
+        .query(&[("name", name), ("type", "TXT")])
+        .header(header::ACCEPT, "application/dns-json")
+        .send()?
+        .error_for_status()?
+        .json::<Value>()?;
+    Ok(parse_txt_answers(&response))
+}
+
+/// Extracts the TXT records from a DNS over HTTPS JSON response. Records
+/// longer than 255 bytes are split into several quoted strings, which are
+/// joined again.
+fn parse_txt_answers(response: &Value) -> Vec<String> {
+    let answers = match response["Answer"].as_array() {
+        Some(answers) => answers,
+        None => return Vec::new(),
+    };
+    answers
+        .iter()
+        .filter(|answer| answer["type"].as_u64() == Some(TXT))
+        .filter_map(|answer| answer["data"].as_str())
+        .map(|data| {
+            let data = data.trim();
+            if data.starts_with('"') {
+                data.split('"').skip(1).step_by(2).collect()
+            } else {
+                data.to_string()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn txt_answers_are_unquoted_and_joined() {
+        let response = json!({
+            "Status": 0,
+            "Answer": [
+                { "name": "_crates-io-challenge.example.com", "type": 16, "data": "\"crates-io-verification=abc\"" },
+                { "name": "_crates-io-challenge.example.com", "type": 16, "data": "\"first part \" \"second part\"" },
+                { "name": "_crates-io-challenge.example.com", "type": 16, "data": "unquoted" },
+                { "name": "example.com", "type": 5, "data": "example.net." }
+            ]
+        });
+        assert_eq!(
+            parse_txt_answers(&response),
+            vec![
+                "crates-io-verification=abc",
+                "first part second part",
+                "unquoted"
+            ]
+        );
+        assert!(parse_txt_answers(&json!({ "Status": 3 })).is_empty());
+    }
+}
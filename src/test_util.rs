@@ -1,7 +1,11 @@
 #![cfg(test)]
 
+use std::collections::HashMap;
+
 use diesel::prelude::*;
 
+use crate::models::{Crate, NewCrate, NewUser, NewVersion, User, Version};
+
 pub fn pg_connection_no_transaction() -> PgConnection {
     let database_url =
         dotenv::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL must be set to run tests");
@@ -13,3 +17,29 @@ pub fn pg_connection() -> PgConnection {
     conn.begin_test_transaction().unwrap();
     conn
 }
+
+/// Creates a user with the given GitHub ID and login.
+pub fn new_user(conn: &PgConnection, gh_id: i32, login: &str) -> User {
+    NewUser::new(gh_id, login, None, None, "access_token")
+        .create_or_update(None, conn)
+        .unwrap()
+}
+
+/// Creates a crate owned by the given user.
+pub fn new_crate(conn: &PgConnection, name: &str, user_id: i32) -> Crate {
+    NewCrate {
+        name,
+        ..Default::default()
+    }
+    .create_or_update(conn, user_id, None)
+    .unwrap()
+}
+
+/// Publishes a version of a crate, without updating its default version.
+pub fn new_version(conn: &PgConnection, crate_id: i32, num: &str, user_id: i32) -> Version {
+    let num = semver::Version::parse(num).unwrap();
+    NewVersion::new(crate_id, &num, &HashMap::new(), None, None, 0, user_id)
+        .unwrap()
+        .save(conn, &[], "someone@example.com")
+        .unwrap()
+}
@@ -0,0 +1,206 @@
+use cargo_registry::config::{ConfigSource, ConfigVariable};
+use cargo_registry::models::{Crate, CrateUploadLimit};
+use cargo_registry::schema::users;
+use cargo_registry::views::EncodableCrateUploadLimit;
+use diesel::prelude::*;
+
+use crate::builders::{CrateBuilder, PublishBuilder};
+use crate::util::{MockCookieUser, RequestHelper, TestApp};
+use crate::OkBool;
+
+const ADMIN_GH_ID: i32 = 424_242;
+const URL: &str = "/api/private/admin/crates/foo_limited/upload-limit";
+
+#[derive(Deserialize)]
+struct UploadLimitResponse {
+    upload_limit: EncodableCrateUploadLimit,
+}
+
+/// An app where the user is an admin and owns `foo_limited`.
+fn setup() -> (TestApp, MockCookieUser) {
+    let (app, _, user) = TestApp::init()
+        .with_config(|config| config.admin_github_ids = vec![ADMIN_GH_ID])
+        .with_user();
+    app.db(|conn| {
+        let user = user.as_model();
+        diesel::update(users::table.find(user.id))
+            .set(users::gh_id.eq(ADMIN_GH_ID))
+            .execute(conn)
+            .unwrap();
+        CrateBuilder::new("foo_limited", user.id).expect_build(conn);
+    });
+    (app, user)
+}
+
+fn active_limit(app: &TestApp) -> Option<i32> {
+    app.db(|conn| {
+        let krate = Crate::by_name("foo_limited").first::<Crate>(conn).unwrap();
+        CrateUploadLimit::active_for(conn, krate.id).unwrap()
+    })
+}
+
+#[test]
+fn only_admins_can_set_upload_limits() {
+    let (app, anon, user) = TestApp::init().with_user();
+    app.db(|conn| {
+        CrateBuilder::new("foo_limited", user.as_model().id).expect_build(conn);
+    });
+    let body = json!({ "max_upload_size": 100, "reason": "testing" }).to_string();
+
+    anon.put::<()>(URL, body.as_bytes()).assert_forbidden();
+    user.put::<()>(URL, body.as_bytes()).assert_forbidden();
+    user.delete::<()>(URL).assert_forbidden();
+}
+
+#[test]
+fn upload_limits_apply_until_they_are_removed() {
+    let (app, admin) = setup();
+
+    let body = json!({ "max_upload_size": 100, "reason": "support request" }).to_string();
+    let json: UploadLimitResponse = admin.put(URL, body.as_bytes()).good();
+    assert_eq!(json.upload_limit.krate, "foo_limited");
+    assert_eq!(json.upload_limit.max_upload_size, 100);
+    assert_eq!(json.upload_limit.reason, "support request");
+    assert_eq!(json.upload_limit.expires_at, None);
+
+    let crate_to_publish = PublishBuilder::new("foo_limited").version("1.1.0");
+    let json = admin.enqueue_publish(crate_to_publish).bad_with_status(200);
+    assert!(
+        json.errors[0].detail.contains("max upload size is: 100"),
+        "{:?}",
+        json.errors
+    );
+
+    let json: OkBool = admin.delete(URL).good();
+    assert!(json.ok);
+    admin.delete::<()>(URL).assert_not_found();
+    assert_eq!(active_limit(&app), None);
+}
+
+#[test]
+fn expired_upload_limits_no_longer_apply() {
+    let (app, admin) = setup();
+
+    let expires_at = chrono::NaiveDate::from_ymd(2020, 1, 1).and_hms(0, 0, 0);
+    let body = json!({
+        "max_upload_size": 100,
+        "reason": "support request",
+        "expires_at": expires_at,
+    })
+    .to_string();
+    let json: UploadLimitResponse = admin.put(URL, body.as_bytes()).good();
+    assert_eq!(json.upload_limit.expires_at, Some(expires_at));
+    assert_eq!(active_limit(&app), None);
+
+    let tomorrow = chrono::Utc::today().naive_utc().succ();
+    let expires_at = tomorrow.and_hms(0, 0, 0);
+    let body = json!({
+        "max_upload_size": 100,
+        "reason": "support request",
+        "expires_at": expires_at,
+    })
+    .to_string();
+    admin
+        .put::<UploadLimitResponse>(URL, body.as_bytes())
+        .good();
+    assert_eq!(active_limit(&app), Some(100));
+}
+
+#[test]
+fn upload_limits_need_a_size_and_a_reason() {
+    let (_, admin) = setup();
+
+    for body in &[
+        json!({ "max_upload_size": 0, "reason": "testing" }),
+        json!({ "max_upload_size": 100, "reason": " " }),
+        json!({ "max_upload_size": 100 }),
+    ] {
+        admin
+            .put::<()>(URL, body.to_string().as_bytes())
+            .assert_status(400);
+    }
+}
+
+#[test]
+fn admins_see_the_effective_config_without_secrets() {
+    let (app, anon, user) = TestApp::init()
+        .with_config(|config| {
+            config.admin_github_ids = vec![ADMIN_GH_ID];
+            config.layers.profile = Some("staging".into());
+            for (name, value, source) in &[
+                ("MAX_FILE_COUNT", "100", ConfigSource::Database),
+                ("SESSION_KEY", "a very secret key", ConfigSource::Profile),
+            ] {
+                let variable = ConfigVariable {
+                    value: value.to_string(),
+                    source: *source,
+                };
+                config.layers.variables.insert(name.to_string(), variable);
+            }
+        })
+        .with_user();
+    let url = "/api/private/admin/config";
+    anon.get::<()>(url).assert_forbidden();
+    user.get::<()>(url).assert_forbidden();
+
+    app.db(|conn| {
+        diesel::update(users::table.find(user.as_model().id))
+            .set(users::gh_id.eq(ADMIN_GH_ID))
+            .execute(conn)
+            .unwrap();
+    });
+    let json = user.get::<serde_json::Value>(url).good();
+    assert_eq!(
+        json,
+        json!({
+            "profile": "staging",
+            "variables": [
+                { "name": "MAX_FILE_COUNT", "value": "100", "source": "database" },
+                { "name": "SESSION_KEY", "value": "[redacted]", "source": "profile" },
+            ],
+        })
+    );
+}
+
+#[test]
+fn admins_replace_the_index_config() {
+    let (app, admin) = setup();
+    let url = "/api/private/admin/index-config";
+    let body = json!({
+        "dl_endpoints": [
+            { "url": "https://mirror.example.com/crates", "priority": 5 },
+            { "url": "https://backup.example.com/crates", "priority": 1 },
+        ],
+        "checksum_url": "https://mirror.example.com/{crate}/{version}.sha256",
+    })
+    .to_string();
+    let json: serde_json::Value = admin.put(url, body.as_bytes()).good();
+    assert_eq!(
+        json["dl-endpoints"],
+        json!([
+            { "url": "https://mirror.example.com/crates", "priority": 5 },
+            { "url": "https://backup.example.com/crates", "priority": 1 },
+        ])
+    );
+    assert_eq!(
+        json["checksum-url"],
+        "https://mirror.example.com/{crate}/{version}.sha256"
+    );
+
+    let body = json!({ "dl_endpoints": [], "checksum_url": null }).to_string();
+    let json: serde_json::Value = admin.put(url, body.as_bytes()).good();
+    assert!(json.get("dl-endpoints").is_none());
+    assert!(json.get("checksum-url").is_none());
+    let json: serde_json::Value = admin.get("/api/v1/index-config").good();
+    assert!(json.get("dl-endpoints").is_none());
+
+    let body = json!({
+        "dl_endpoints": [{ "url": "ftp://mirror.example.com", "priority": 0 }],
+        "checksum_url": null,
+    })
+    .to_string();
+    admin.put::<()>(url, body.as_bytes()).assert_status(400);
+
+    let user = app.db_new_user("not-an-admin");
+    user.put::<()>(url, body.as_bytes()).assert_forbidden();
+}
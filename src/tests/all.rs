@@ -11,11 +11,19 @@ extern crate serde_json;
 
 use crate::util::{Bad, RequestHelper, TestApp};
 use cargo_registry::{
-    models::{Crate, CrateOwner, Dependency, NewCategory, NewTeam, NewUser, Team, User, Version},
+    models::{
+        dependency::DependencyCheck, Crate, CrateOwner, Dependency, NewCategory, NewTeam, NewUser,
+        Team, User, Version,
+    },
+    publish_rate_limit::RateLimiterConfig,
+    response_cache::ResponseCacheConfig,
     schema::crate_owners,
+    search_backend::SearchConfig,
+    storage::S3Storage,
     views::{
-        EncodableCategory, EncodableCategoryWithSubcategories, EncodableCrate, EncodableKeyword,
-        EncodableOwner, EncodableVersion, GoodCrate,
+        EncodableCategory, EncodableCategoryWithSubcategories, EncodableCrate,
+        EncodableCrateQuality, EncodableDependencyFreshness, EncodableKeyword, EncodableOwner,
+        EncodableSearchFacets, EncodableVersion, GoodCrate,
     },
     App, Config, Env, Replica, Uploader,
 };
@@ -25,6 +33,7 @@ use std::{
         atomic::{AtomicUsize, Ordering},
         Arc,
     },
+    time::Duration,
 };
 
 use conduit_test::MockRequest;
@@ -40,6 +49,7 @@ macro_rules! t {
     };
 }
 
+mod admin;
 mod authentication;
 mod badge;
 mod builders;
@@ -47,15 +57,29 @@ mod categories;
 mod category;
 mod dump_db;
 mod git;
+mod index_config;
+mod index_entries;
+mod index_signing;
+mod index_squash;
+mod job_payloads;
 mod keyword;
 mod krate;
+mod metrics;
+mod org_domain;
 mod owners;
+mod partner;
 mod read_only_mode;
 mod record;
 mod schema_details;
 mod server;
+mod service_consumer;
+mod signature;
+mod sitemap;
+mod stats;
+mod storage;
 mod team;
 mod token;
+mod transparency_log;
 mod user;
 mod util;
 mod version;
@@ -70,6 +94,7 @@ struct CrateMeta {
     total: i32,
     next_page: Option<String>,
     prev_page: Option<String>,
+    facets: Option<EncodableSearchFacets>,
 }
 #[derive(Deserialize)]
 pub struct CrateResponse {
@@ -77,6 +102,8 @@ pub struct CrateResponse {
     krate: EncodableCrate,
     versions: Vec<EncodableVersion>,
     keywords: Vec<EncodableKeyword>,
+    quality: Option<EncodableCrateQuality>,
+    dependency_freshness: Option<EncodableDependencyFreshness>,
 }
 #[derive(Deserialize)]
 pub struct VersionResponse {
@@ -113,7 +140,7 @@ fn app() -> (Arc<App>, conduit_middleware::MiddlewareBuilder) {
 }
 
 fn simple_config() -> Config {
-    let uploader = Uploader::S3 {
+    let uploader = Uploader::new(Arc::new(S3Storage {
         bucket: s3::Bucket::new(
             String::from("alexcrichton-test"),
             None,
@@ -124,7 +151,7 @@ fn simple_config() -> Config {
             "http",
         ),
         cdn: None,
-    };
+    }));
 
     Config {
         uploader,
@@ -137,12 +164,34 @@ fn simple_config() -> Config {
         env: Env::Test,
         max_upload_size: 3000,
         max_unpack_size: 2000,
+        max_compression_ratio: 50,
+        max_file_compression_ratio: 200,
+        max_file_count: 50_000,
+        max_path_length: 512,
         mirror: Replica::Primary,
         // When testing we route all API traffic over HTTP so we can
         // sniff/record it, but everywhere else we use https
         api_protocol: String::from("http"),
         publish_rate_limit: Default::default(),
+        publish_rate_limiter: RateLimiterConfig::Database,
         blocked_traffic: Default::default(),
+        og_image_renderer: None,
+        search: SearchConfig::Postgres,
+        yank_cooldown: Duration::from_secs(0),
+        cors: Default::default(),
+        // Responses are always rendered again, so that tests see their changes
+        response_cache: ResponseCacheConfig {
+            fresh_for: Duration::from_secs(0),
+            stale_for: Duration::from_secs(0),
+        },
+        dependency_check: DependencyCheck::Off,
+        accept_zstd_crates: false,
+        hold_flagged_versions: false,
+        show_download_anomalies_to_owners: false,
+        admin_github_ids: Vec::new(),
+        metrics_authorization_token: None,
+        sigstore_trust_root: None,
+        layers: Default::default(),
     }
 }
 
@@ -9,7 +9,7 @@ type ResponseResult = Result<Response, Box<dyn std::error::Error + Send>>;
 
 static URL: &str = "/api/v1/me/updates";
 static MUST_LOGIN: &[u8] =
-    b"{\"errors\":[{\"detail\":\"must be logged in to perform that action\"}]}";
+    b"{\"errors\":[{\"detail\":\"must be logged in to perform that action\",\"code\":\"unauthorized\"}]}";
 static INTERNAL_ERROR_NO_USER: &str =
     "user_id from cookie or token not found in database caused by NotFound";
 
@@ -1,7 +1,10 @@
 //! Structs using the builder pattern that make it easier to create records in tests.
 
 use cargo_registry::{
-    models::{Crate, Keyword, NewCrate, NewVersion, Version},
+    models::{
+        default_versions::update_default_version, Crate, DependencyKind, Keyword, NewCrate,
+        NewVersion, Version,
+    },
     schema::{crates, dependencies, version_downloads, versions},
     util::errors::AppResult,
     views::krate_publish as u,
@@ -56,6 +59,13 @@ impl<'a> VersionBuilder<'a> {
         self
     }
 
+    /// Adds a feature to this version, which enables the given features.
+    pub fn feature(mut self, name: &str, enables: &[&str]) -> Self {
+        let enables = enables.iter().map(|feature| feature.to_string()).collect();
+        self.features.insert(name.to_string(), enables);
+        self
+    }
+
     /// Sets the version's `yanked` value.
     pub fn yanked(self, yanked: bool) -> Self {
         Self { yanked, ..self }
@@ -97,6 +107,8 @@ impl<'a> VersionBuilder<'a> {
                 .get_result(connection)?;
         }
 
+        update_default_version(crate_id, connection)?;
+
         let new_deps = self
             .dependencies
             .into_iter()
@@ -440,6 +452,12 @@ impl PublishBuilder {
         self
     }
 
+    /// Set the license expression of this crate.
+    pub fn license(mut self, license: &str) -> Self {
+        self.license = Some(license.into());
+        self
+    }
+
     /// Remove the license from this crate. Publish will fail unless license or license file is set.
     pub fn unset_license(mut self) -> Self {
         self.license = None;
@@ -491,6 +509,7 @@ impl PublishBuilder {
             repository: None,
             badges: Some(self.badges),
             links: None,
+            rust_version: None,
         };
 
         let json = serde_json::to_string(&new_crate).unwrap();
@@ -525,6 +544,7 @@ pub struct DependencyBuilder {
     registry: Option<String>,
     explicit_name_in_toml: Option<u::EncodableCrateName>,
     version_req: u::EncodableCrateVersionReq,
+    kind: Option<DependencyKind>,
 }
 
 impl DependencyBuilder {
@@ -535,6 +555,7 @@ impl DependencyBuilder {
             registry: None,
             explicit_name_in_toml: None,
             version_req: u::EncodableCrateVersionReq(semver::VersionReq::parse(">= 0").unwrap()),
+            kind: None,
         }
     }
 
@@ -563,6 +584,12 @@ impl DependencyBuilder {
         self
     }
 
+    /// Set the kind of this dependency, e.g. a dev-dependency.
+    pub fn kind(mut self, kind: DependencyKind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
     /// Consume this builder to create a `u::CrateDependency`. If the dependent crate doesn't
     /// already exist, publishing a crate with this dependency will fail.
     fn build(self) -> u::EncodableCrateDependency {
@@ -573,7 +600,7 @@ impl DependencyBuilder {
             features: Vec::new(),
             version_req: self.version_req,
             target: None,
-            kind: None,
+            kind: self.kind,
             explicit_name_in_toml: self.explicit_name_in_toml,
             registry: self.registry,
         }
@@ -0,0 +1,47 @@
+use cargo_registry::models::index_config::DownloadEndpoint;
+use cargo_registry::models::{IndexConfig, IndexSettings};
+
+use crate::util::{RequestHelper, TestApp};
+
+#[test]
+fn index_config_follows_the_server_settings() {
+    let (app, anon) = TestApp::init().empty();
+    app.db(|conn| {
+        IndexConfig::add_download_endpoint(conn, "https://mirror.example.com/crates", 5).unwrap();
+    });
+
+    let json: serde_json::Value = anon.get("/api/v1/index-config").good();
+    let api = json["api"].as_str().unwrap();
+    assert_eq!(json["dl"], format!("{}/api/v1/crates", api));
+    assert_eq!(
+        json["dl-endpoints"][0]["url"],
+        "https://mirror.example.com/crates"
+    );
+    assert!(json.get("auth-required").is_none());
+
+    let settings = IndexSettings {
+        dl: "https://static.example.com/api/v1/crates".into(),
+        api: "https://example.com".into(),
+        auth_required: true,
+        storage_endpoint: Some(DownloadEndpoint {
+            url: "https://static.example.com/crates/{crate}/{crate}-{version}.crate".into(),
+            priority: 10,
+        }),
+    };
+    let config = app.db(|conn| IndexConfig::load(conn, &settings).unwrap());
+    assert_eq!(config.dl, settings.dl);
+    assert_eq!(config.api, settings.api);
+    assert!(config.auth_required);
+    let urls = config
+        .dl_endpoints
+        .iter()
+        .map(|endpoint| endpoint.url.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        urls,
+        vec![
+            "https://static.example.com/crates/{crate}/{crate}-{version}.crate",
+            "https://mirror.example.com/crates",
+        ]
+    );
+}
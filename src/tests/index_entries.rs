@@ -0,0 +1,59 @@
+//! Index entries are serialized again whenever a version is yanked or
+//! unyanked, so a change to their serialization would rewrite every file of
+//! the index which is touched afterwards. Every file in `index-entries` is a
+//! corpus of entries as they are in the index, which have to be reproduced
+//! byte for byte.
+//!
+//! Features are written in the order of their names. Entries which were
+//! written with another order are sorted when they're serialized again.
+
+use std::fs;
+use std::path::Path;
+
+use cargo_registry::git;
+
+#[test]
+fn index_entries_are_serialized_as_they_were() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/tests/index-entries");
+    let mut checked = 0;
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        let contents = fs::read_to_string(&path).unwrap();
+        for (i, line) in contents.lines().enumerate() {
+            let krate = serde_json::from_str::<git::Crate>(line).unwrap_or_else(|error| {
+                panic!("{}:{} can't be parsed: {}", path.display(), i + 1, error)
+            });
+            assert_eq!(
+                serde_json::to_string(&krate).unwrap(),
+                line,
+                "{}:{} is serialized differently",
+                path.display(),
+                i + 1
+            );
+            checked += 1;
+        }
+    }
+    assert!(checked > 0, "no index entries were checked");
+}
+
+#[test]
+fn features_are_serialized_in_order() {
+    let line = r#"{"name":"foo","vers":"1.0.0","deps":[],"cksum":"0","features":{"std":[],"alloc":[],"default":["std"]},"yanked":false,"links":null}"#;
+    let krate = serde_json::from_str::<git::Crate>(line).unwrap();
+    let serialized = serde_json::to_string(&krate).unwrap();
+    assert!(
+        serialized.contains(r#""features":{"alloc":[],"default":["std"],"std":[]}"#),
+        "{}",
+        serialized
+    );
+}
+
+#[test]
+fn v3_fields_can_be_left_out() {
+    let line = r#"{"name":"foo","vers":"1.0.0","deps":[],"cksum":"0","features":{},"yanked":true,"links":null,"rust_version":"1.42","yanked_reason":"security issue"}"#;
+    let krate = serde_json::from_str::<git::Crate>(line).unwrap();
+    assert_eq!(
+        serde_json::to_string(&krate.without_v3_fields()).unwrap(),
+        r#"{"name":"foo","vers":"1.0.0","deps":[],"cksum":"0","features":{},"yanked":true,"links":null}"#
+    );
+}
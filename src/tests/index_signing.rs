@@ -0,0 +1,52 @@
+use cargo_registry::git::{replace_index_file, Credentials, Repository, RepositoryConfig};
+use cargo_registry::index_signing::SigningKey;
+use cargo_registry::models::IndexSigningKey;
+use openssl::pkey::PKey;
+use url::Url;
+
+use crate::builders::CrateBuilder;
+use crate::util::{RequestHelper, TestApp};
+
+fn signing_key() -> SigningKey {
+    let key = PKey::generate_ed25519().unwrap();
+    SigningKey::from_pem(&key.private_key_to_pem_pkcs8().unwrap()).unwrap()
+}
+
+#[test]
+fn index_commits_are_signed_with_the_configured_key() {
+    let (app, anon, user) = TestApp::init().with_git_index().with_user();
+    let key = signing_key();
+    let repo = Repository::open(&RepositoryConfig {
+        index_location: Url::from_file_path(&crate::git::bare()).unwrap(),
+        credentials: Credentials::Missing,
+        signing_key: Some(key.clone()),
+        v3_fields: false,
+        clone_depth: None,
+        sparse_checkout: false,
+    })
+    .unwrap();
+
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo_signed", user.as_model().id).expect_build(conn);
+        IndexSigningKey::record(conn, &key).unwrap();
+        replace_index_file(&repo, conn, krate.id, "foo_signed", "", "Signed commit").unwrap();
+    });
+
+    let upstream = app.upstream_repository();
+    let head = upstream.head().unwrap().target().unwrap();
+    let (signature, _) = upstream.extract_signature(&head, None).unwrap();
+    let signature = signature.as_str().unwrap();
+    assert!(signature.starts_with("-----BEGIN SSH SIGNATURE-----\n"));
+
+    #[derive(Deserialize)]
+    struct Keys {
+        allowed_signers: String,
+    }
+    let json: Keys = anon.get("/api/v1/index-signing-keys").good();
+    assert!(json
+        .allowed_signers
+        .starts_with("bors@rust-lang.org namespaces=\"git\""));
+    assert!(json
+        .allowed_signers
+        .ends_with(&format!(" {}\n", key.public_key())));
+}
@@ -0,0 +1,85 @@
+use cargo_registry::git::{Credentials, Repository, RepositoryConfig};
+use cargo_registry::tasks::squash_index_history;
+use url::Url;
+
+use crate::builders::PublishBuilder;
+use crate::util::{RequestHelper, TestApp};
+
+#[test]
+fn index_history_is_squashed_above_the_threshold() {
+    let (app, _, _, token) = TestApp::full().with_token();
+    token
+        .enqueue_publish(PublishBuilder::new("foo_squash"))
+        .good();
+    token
+        .enqueue_publish(PublishBuilder::new("bar_squash"))
+        .good();
+    app.run_pending_background_jobs();
+
+    let repo = Repository::open(&RepositoryConfig {
+        index_location: Url::from_file_path(&crate::git::bare()).unwrap(),
+        credentials: Credentials::Missing,
+        signing_key: None,
+        v3_fields: false,
+        clone_depth: None,
+        sparse_checkout: false,
+    })
+    .unwrap();
+    let squash = app.db(|conn| {
+        assert_eq!(squash_index_history(&repo, conn, 1000).unwrap(), None);
+        squash_index_history(&repo, conn, 2).unwrap().unwrap()
+    });
+    // The initial commit and one for each publish
+    assert!(squash.commit_count >= 3);
+
+    let upstream = app.upstream_repository();
+    let head = upstream.head().unwrap().peel_to_commit().unwrap();
+    assert_eq!(head.id().to_string(), squash.squashed_commit);
+    assert_eq!(head.parent_count(), 0);
+    let snapshot = upstream
+        .find_branch(&squash.snapshot_branch, git2::BranchType::Local)
+        .unwrap();
+    assert_eq!(
+        snapshot.get().target().unwrap().to_string(),
+        squash.previous_head
+    );
+    assert_eq!(app.crates_from_index_head("fo/o_/foo_squash").len(), 1);
+    assert_eq!(app.crates_from_index_head("ba/r_/bar_squash").len(), 1);
+}
+
+#[test]
+fn shallow_sparse_clones_fetch_the_history_to_squash() {
+    let (app, _, _, token) = TestApp::full().with_token();
+    token
+        .enqueue_publish(PublishBuilder::new("foo_shallow"))
+        .good();
+    token
+        .enqueue_publish(PublishBuilder::new("bar_shallow"))
+        .good();
+    app.run_pending_background_jobs();
+
+    let repo = Repository::open(&RepositoryConfig {
+        index_location: Url::from_file_path(&crate::git::bare()).unwrap(),
+        credentials: Credentials::Missing,
+        signing_key: None,
+        v3_fields: false,
+        clone_depth: Some(1),
+        sparse_checkout: true,
+    })
+    .unwrap();
+    // Index files are checked out as they're read
+    let entry = repo.read_index_file("foo_shallow").unwrap();
+    assert!(entry.contains("\"name\":\"foo_shallow\""));
+
+    let squash = app.db(|conn| squash_index_history(&repo, conn, 2).unwrap().unwrap());
+    assert!(squash.commit_count >= 3);
+    let upstream = app.upstream_repository();
+    let snapshot = upstream
+        .find_branch(&squash.snapshot_branch, git2::BranchType::Local)
+        .unwrap();
+    assert_eq!(
+        snapshot.get().target().unwrap().to_string(),
+        squash.previous_head
+    );
+    assert_eq!(app.crates_from_index_head("ba/r_/bar_shallow").len(), 1);
+}
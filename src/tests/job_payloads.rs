@@ -0,0 +1,131 @@
+//! Jobs enqueued by older deploys must still run after a deploy, so every
+//! payload version of every job type has a fixture in `job-payloads`.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use cargo_registry::background_jobs::payload;
+use cargo_registry::{crate_page, git, og_image, render, sbom, sitemap, tasks};
+use swirl::Job;
+
+#[derive(Deserialize)]
+struct Fixture {
+    job_type: String,
+    payload_version: i32,
+    data: serde_json::Value,
+}
+
+/// Deserializes the fixture as the type of `_job`, if it is of the same type.
+fn deserializes_as<J: Job>(_job: J, fixture: &Fixture) -> bool {
+    if fixture.job_type != J::JOB_TYPE {
+        return false;
+    }
+
+    let data = payload::migrate(
+        &fixture.job_type,
+        fixture.payload_version,
+        fixture.data.clone(),
+    )
+    .unwrap();
+    if let Err(error) = serde_json::from_value::<J>(data) {
+        panic!(
+            "version {} of `{}` can't be deserialized: {}",
+            fixture.payload_version, fixture.job_type, error
+        );
+    }
+    true
+}
+
+fn deserializes(fixture: &Fixture) -> bool {
+    let krate = || git::Crate {
+        name: String::new(),
+        vers: String::new(),
+        deps: Vec::new(),
+        cksum: String::new(),
+        features: Default::default(),
+        yanked: None,
+        links: None,
+        compression: None,
+        rust_version: None,
+        yanked_reason: None,
+    };
+    let version: cargo_registry::models::Version = serde_json::from_value(json!({
+        "id": 1,
+        "crate_id": 1,
+        "num": "1.0.0",
+        "updated_at": "2020-01-01T00:00:00",
+        "created_at": "2020-01-01T00:00:00",
+        "downloads": 0,
+        "features": {},
+        "yanked": false,
+    }))
+    .unwrap();
+
+    deserializes_as(git::add_crate(krate()), fixture)
+        || deserializes_as(git::add_queued_crate(0, krate()), fixture)
+        || deserializes_as(git::yank(String::new(), version, true), fixture)
+        || deserializes_as(git::yank_bulk(String::new(), vec![], true), fixture)
+        || deserializes_as(
+            render::render_and_upload_readme(0, String::new(), String::new(), None, None),
+            fixture,
+        )
+        || deserializes_as(og_image::render_og_image(String::new()), fixture)
+        || deserializes_as(og_image::refresh_og_images(), fixture)
+        || deserializes_as(sbom::generate_sbom(0), fixture)
+        || deserializes_as(crate_page::render_crate_page(String::new()), fixture)
+        || deserializes_as(tasks::dump_db(String::new(), String::new()), fixture)
+        || deserializes_as(tasks::update_downloads(), fixture)
+        || deserializes_as(tasks::sync_default_versions(), fixture)
+        || deserializes_as(tasks::sync_search_index(), fixture)
+        || deserializes_as(tasks::remove_from_search_index(0), fixture)
+        || deserializes_as(tasks::compute_crate_quality(), fixture)
+        || deserializes_as(tasks::compute_release_stats(), fixture)
+        || deserializes_as(tasks::compute_ecosystem_stats(), fixture)
+        || deserializes_as(tasks::detect_dependency_cycles(0), fixture)
+        || deserializes_as(tasks::detect_download_anomalies(), fixture)
+        || deserializes_as(tasks::sync_repository_activity(), fixture)
+        || deserializes_as(tasks::prune_audit_tables(), fixture)
+        || deserializes_as(tasks::prune_idempotency_keys(), fixture)
+        || deserializes_as(tasks::sign_transparency_log(), fixture)
+        || deserializes_as(sitemap::generate_sitemaps(), fixture)
+        || deserializes_as(tasks::notify_dependency_updates(), fixture)
+        || deserializes_as(git::update_index_config(), fixture)
+        || deserializes_as(tasks::check_squatting_reports(), fixture)
+        || deserializes_as(tasks::verify_org_domain(0), fixture)
+        || deserializes_as(tasks::verify_installability(), fixture)
+        || deserializes_as(tasks::reconcile_index(), fixture)
+        || deserializes_as(tasks::sync_index(String::new()), fixture)
+        || deserializes_as(tasks::squash_index(), fixture)
+        || deserializes_as(tasks::refresh_user_profiles(), fixture)
+        || deserializes_as(tasks::deliver_webhooks(), fixture)
+        || deserializes_as(tasks::compute_dependency_freshness(), fixture)
+}
+
+#[test]
+fn old_payloads_still_deserialize() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/tests/job-payloads");
+    let mut versions = HashSet::new();
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        let fixture: Fixture = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert!(
+            deserializes(&fixture),
+            "unknown job type `{}` in {}",
+            fixture.job_type,
+            path.display()
+        );
+        versions.insert((fixture.job_type, fixture.payload_version));
+    }
+
+    for (job_type, _) in versions.clone() {
+        for version in 1..=payload::current_version(&job_type) {
+            assert!(
+                versions.contains(&(job_type.clone(), version)),
+                "missing a fixture of version {} of `{}`",
+                version,
+                job_type
+            );
+        }
+    }
+}
@@ -1,19 +1,35 @@
 use crate::{
     builders::{CrateBuilder, DependencyBuilder, PublishBuilder, VersionBuilder},
-    new_category, new_dependency, new_user, CrateMeta, CrateResponse, GoodCrate, OkBool,
-    RequestHelper, TestApp,
+    new_category, new_dependency, new_user,
+    util::MockCookieUser,
+    CrateMeta, CrateResponse, GoodCrate, OkBool, RequestHelper, TestApp,
 };
 use cargo_registry::{
-    models::{krate::MAX_NAME_LENGTH, Category, Crate},
-    schema::{api_tokens, crates, emails, metadata, versions, versions_published_by},
+    background_jobs::EnqueueVersioned,
+    git,
+    models::{
+        funding::collect_funding_links, krate::MAX_NAME_LENGTH, Category, CompressionFormat, Crate,
+        CratePolicy, CrateSecurityPolicy, DownloadAnomaly, FundingLink, NewReleaseStats,
+        PolicyFile, PublishIdempotencyKey, ScanHold, VersionAnalysis, VersionScanResult,
+    },
+    schema::{
+        api_tokens, crates, emails, metadata, publish_idempotency_keys, versions,
+        versions_published_by,
+    },
+    storage::MemoryStorage,
+    tarball, uploaders,
     views::{
-        EncodableCategory, EncodableCrate, EncodableDependency, EncodableKeyword, EncodableVersion,
-        EncodableVersionDownload,
+        EncodableCategory, EncodableCrate, EncodableCrateLinkEvent, EncodableCustomLinks,
+        EncodableDependency, EncodableDownloadAnomaly, EncodableFacetCount, EncodableKeyword,
+        EncodableMetadataChange, EncodableVersion, EncodableVersionDownload,
     },
+    Uploader,
 };
 use std::{
     collections::HashMap,
     io::{self, prelude::*},
+    path::Path,
+    sync::Arc,
     thread,
     time::Duration,
 };
@@ -242,6 +258,337 @@ fn exact_match_first_on_queries() {
     assert_eq!(json.crates[2].name, "foo_exact");
 }
 
+#[test]
+fn search_in_readme() {
+    use diesel::sql_types::{Integer, Text};
+
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let index_readme = |krate: &Crate, readme: &str| {
+            diesel::sql_query(
+                "INSERT INTO readme_renderings (version_id, textsearchable_index_col) \
+                 SELECT version_id, to_tsvector('english', $2) \
+                 FROM default_versions WHERE crate_id = $1",
+            )
+            .bind::<Integer, _>(krate.id)
+            .bind::<Text, _>(readme)
+            .execute(conn)
+            .unwrap();
+        };
+
+        let krate = CrateBuilder::new("foo_readme_search", user.id)
+            .description("An executor")
+            .version("1.0.0")
+            .expect_build(conn);
+        index_readme(&krate, "Runs asynchronous tasks to completion");
+
+        let krate = CrateBuilder::new("bar_readme_search", user.id)
+            .description("An asynchronous executor")
+            .version("1.0.0")
+            .expect_build(conn);
+        index_readme(&krate, "Runs asynchronous tasks to completion");
+
+        CrateBuilder::new("baz_readme_search", user.id)
+            .description("Something else entirely")
+            .version("1.0.0")
+            .expect_build(conn);
+    });
+
+    let json = anon.search("q=asynchronous");
+    assert_eq!(json.meta.total, 1);
+    assert_eq!(json.crates[0].name, "bar_readme_search");
+
+    let json = anon.search("q=asynchronous&search_in=readme");
+    assert_eq!(json.meta.total, 2);
+    assert_eq!(json.crates[0].name, "bar_readme_search");
+    assert_eq!(json.crates[1].name, "foo_readme_search");
+
+    let json = anon
+        .get_with_query::<()>("/api/v1/crates", "q=asynchronous&search_in=nope")
+        .bad_with_status(400);
+    assert_eq!(json.errors[0].detail, "invalid `search_in` value: `nope`");
+}
+
+#[test]
+fn search_with_synonyms() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let synonyms = r#""http client" = ["reqwest"]"#;
+        cargo_registry::boot::synonyms::sync_with_connection(synonyms, conn).unwrap();
+
+        CrateBuilder::new("reqwest_synonyms", user.id)
+            .keyword("reqwest")
+            .expect_build(conn);
+        CrateBuilder::new("http_client_synonyms", user.id)
+            .description("A simple http client")
+            .expect_build(conn);
+        CrateBuilder::new("other_synonyms", user.id)
+            .description("A simple http server")
+            .expect_build(conn);
+    });
+
+    let json = anon.search("q=http%20client");
+    assert_eq!(json.meta.total, 2);
+    assert_eq!(json.crates[0].name, "http_client_synonyms");
+    assert_eq!(json.crates[1].name, "reqwest_synonyms");
+
+    let json = anon.search("q=reqwest");
+    assert_eq!(json.meta.total, 1);
+    assert_eq!(json.crates[0].name, "reqwest_synonyms");
+}
+
+#[test]
+fn search_facets() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        new_category("Category 1", "cat1", "Category 1 crates")
+            .create_or_update(conn)
+            .unwrap();
+
+        let krate = CrateBuilder::new("foo_facets", user.id)
+            .version(VersionBuilder::new("1.0.0").license(Some("MIT")))
+            .keyword("kw1")
+            .keyword("kw2")
+            .expect_build(conn);
+        Category::update_crate(conn, &krate, &["cat1"]).unwrap();
+
+        CrateBuilder::new("bar_facets", user.id)
+            .version(VersionBuilder::new("1.0.0").license(Some("MIT")))
+            .keyword("kw1")
+            .expect_build(conn);
+
+        CrateBuilder::new("baz_facets", user.id)
+            .version(VersionBuilder::new("1.0.0").license(Some("Apache-2.0")))
+            .keyword("kw1")
+            .expect_build(conn);
+
+        CrateBuilder::new("other", user.id)
+            .version(VersionBuilder::new("1.0.0").license(Some("MIT")))
+            .keyword("kw3")
+            .expect_build(conn);
+    });
+
+    let counts = |counts: Option<Vec<EncodableFacetCount>>| {
+        counts
+            .unwrap()
+            .into_iter()
+            .map(|c| (c.value, c.count))
+            .collect::<Vec<_>>()
+    };
+
+    let json = anon.search("q=facets&facets=categories,keywords,licenses&per_page=1");
+    assert_eq!(json.crates.len(), 1);
+    assert_eq!(json.meta.total, 3);
+    let facets = json.meta.facets.unwrap();
+    assert_eq!(counts(facets.categories), vec![("cat1".into(), 1)]);
+    assert_eq!(
+        counts(facets.keywords),
+        vec![("kw1".into(), 3), ("kw2".into(), 1)]
+    );
+    assert_eq!(
+        counts(facets.licenses),
+        vec![("MIT".into(), 2), ("Apache-2.0".into(), 1)]
+    );
+
+    let facets = anon
+        .search("keyword=kw3&facets=licenses")
+        .meta
+        .facets
+        .unwrap();
+    assert_eq!(counts(facets.licenses), vec![("MIT".into(), 1)]);
+    assert!(facets.categories.is_none());
+    assert!(facets.keywords.is_none());
+
+    assert!(anon.search("q=facets").meta.facets.is_none());
+
+    let json = anon
+        .get_with_query::<()>("/api/v1/crates", "facets=owners")
+        .bad_with_status(400);
+    assert_eq!(json.errors[0].detail, "invalid `facets` value: `owners`");
+}
+
+#[test]
+fn sort_by_quality() {
+    use cargo_registry::schema::crate_quality;
+
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let low = CrateBuilder::new("low_quality", user.id).expect_build(conn);
+        let high = CrateBuilder::new("high_quality", user.id).expect_build(conn);
+        CrateBuilder::new("unknown_quality", user.id).expect_build(conn);
+
+        for (krate, score) in &[(low, 0.2_f32), (high, 0.9)] {
+            diesel::insert_into(crate_quality::table)
+                .values((
+                    crate_quality::crate_id.eq(krate.id),
+                    crate_quality::has_documentation.eq(true),
+                    crate_quality::score.eq(score),
+                ))
+                .execute(conn)
+                .unwrap();
+        }
+    });
+
+    let json = anon.search("sort=quality");
+    assert_eq!(json.meta.total, 3);
+    assert_eq!(json.crates[0].name, "high_quality");
+    assert_eq!(json.crates[1].name, "low_quality");
+    assert_eq!(json.crates[2].name, "unknown_quality");
+
+    let quality = anon.show_crate("high_quality").quality.unwrap();
+    assert!(quality.has_documentation);
+    assert_eq!(quality.has_tests, None);
+    assert!((quality.score - 0.9).abs() < 1e-6);
+    assert!(anon.show_crate("unknown_quality").quality.is_none());
+}
+
+#[test]
+fn sort_by_dependency_freshness() {
+    use cargo_registry::schema::crate_dependency_freshness;
+
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let stale = CrateBuilder::new("stale_deps", user.id)
+            .version("1.0.0")
+            .expect_build(conn);
+        let fresh = CrateBuilder::new("fresh_deps", user.id)
+            .version("1.0.0")
+            .expect_build(conn);
+        CrateBuilder::new("unknown_deps", user.id).expect_build(conn);
+
+        for (krate, score) in &[(stale, 0.25_f32), (fresh, 1.0)] {
+            let version_id = versions::table
+                .filter(versions::crate_id.eq(krate.id))
+                .select(versions::id)
+                .first::<i32>(conn)
+                .unwrap();
+            diesel::insert_into(crate_dependency_freshness::table)
+                .values((
+                    crate_dependency_freshness::crate_id.eq(krate.id),
+                    crate_dependency_freshness::version_id.eq(version_id),
+                    crate_dependency_freshness::dependencies.eq(4),
+                    crate_dependency_freshness::outdated_dependencies.eq(3),
+                    crate_dependency_freshness::releases_behind.eq(5),
+                    crate_dependency_freshness::score.eq(score),
+                ))
+                .execute(conn)
+                .unwrap();
+        }
+    });
+
+    let json = anon.search("sort=dependency-freshness");
+    assert_eq!(json.meta.total, 3);
+    assert_eq!(json.crates[0].name, "fresh_deps");
+    assert_eq!(json.crates[1].name, "stale_deps");
+    assert_eq!(json.crates[2].name, "unknown_deps");
+
+    let freshness = anon.show_crate("stale_deps").dependency_freshness.unwrap();
+    assert_eq!(freshness.dependencies, 4);
+    assert_eq!(freshness.outdated_dependencies, 3);
+    assert!((freshness.score - 0.25).abs() < 1e-6);
+    assert!(anon
+        .show_crate("unknown_deps")
+        .dependency_freshness
+        .is_none());
+}
+
+#[test]
+fn search_by_repository_activity() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let recent = CrateBuilder::new("recently_active", user.id).expect_build(conn);
+        let old = CrateBuilder::new("long_inactive", user.id).expect_build(conn);
+        CrateBuilder::new("unknown_activity", user.id).expect_build(conn);
+
+        for (krate, days) in &[(recent, 10), (old, 800)] {
+            let last_commit_at = (Utc::now() - chrono::Duration::days(*days)).naive_utc();
+            update(crates::table.find(krate.id))
+                .set(crates::repository_last_commit_at.eq(last_commit_at))
+                .execute(conn)
+                .unwrap();
+        }
+    });
+
+    let json = anon.search("active_within=1y");
+    assert_eq!(json.meta.total, 1);
+    assert_eq!(json.crates[0].name, "recently_active");
+    assert!(json.crates[0].repository_last_commit_at.is_some());
+
+    let json = anon.search("active_within=3y");
+    assert_eq!(json.meta.total, 2);
+
+    let json = anon.search("sort=recent-activity");
+    assert_eq!(json.meta.total, 3);
+    assert_eq!(json.crates[0].name, "recently_active");
+    assert_eq!(json.crates[1].name, "long_inactive");
+    assert_eq!(json.crates[2].name, "unknown_activity");
+
+    let krate = anon.show_crate("unknown_activity").krate;
+    assert!(krate.repository_last_commit_at.is_none());
+
+    let json = anon
+        .get_with_query::<()>("/api/v1/crates", "active_within=soon")
+        .bad_with_status(400);
+    assert_eq!(
+        json.errors[0].detail,
+        "invalid `active_within` value: `soon`, expected e.g. `30d`, `6m` or `1y`"
+    );
+}
+
+#[test]
+fn search_by_target() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let wasm = CrateBuilder::new("foo_wasm", user.id).expect_build(conn);
+        let embedded = CrateBuilder::new("foo_embedded", user.id).expect_build(conn);
+        CrateBuilder::new("foo_anywhere", user.id).expect_build(conn);
+
+        for (krate, targets) in &[
+            (wasm, vec!["wasm32-unknown-unknown", "wasm32-wasi"]),
+            (embedded, vec!["thumbv7em-none-eabihf"]),
+        ] {
+            update(versions::table.filter(versions::crate_id.eq(krate.id)))
+                .set(versions::targets.eq(targets))
+                .execute(conn)
+                .unwrap();
+        }
+    });
+
+    let json = anon.search("target=wasm32-wasi");
+    assert_eq!(json.meta.total, 1);
+    assert_eq!(json.crates[0].name, "foo_wasm");
+    let json = anon.search("target=x86_64-unknown-linux-gnu");
+    assert_eq!(json.meta.total, 0);
+
+    let versions = anon.show_crate("foo_wasm").versions;
+    assert_eq!(
+        versions[0].targets,
+        vec!["wasm32-unknown-unknown", "wasm32-wasi"]
+    );
+    assert!(anon.show_crate("foo_anywhere").versions[0]
+        .targets
+        .is_empty());
+
+    let json = anon
+        .get_with_query::<()>("/api/v1/crates", "target=wasm64")
+        .bad_with_status(400);
+    assert_eq!(json.errors[0].detail, "unknown target `wasm64`");
+}
+
 #[test]
 #[allow(clippy::cognitive_complexity)]
 fn index_sorting() {
@@ -654,11 +1001,14 @@ fn versions() {
             .version("0.5.0")
             .expect_build(conn);
         // Make version 1.0.0 mimic a version published before we started recording who published
-        // versions
+        // versions and how
         let none: Option<i32> = None;
         update(versions::table)
             .filter(versions::num.eq("1.0.0"))
-            .set(versions::published_by.eq(none))
+            .set((
+                versions::published_by.eq(none),
+                versions::publish_channel.eq(none),
+            ))
             .execute(conn)
             .unwrap();
     });
@@ -674,6 +1024,11 @@ fn versions() {
         json.versions[1].published_by.as_ref().unwrap().login,
         user.gh_login
     );
+    assert_eq!(json.versions[0].publish_channel, None);
+    assert_eq!(
+        json.versions[1].publish_channel.as_ref().unwrap(),
+        "registry"
+    );
 }
 
 #[test]
@@ -847,6 +1202,92 @@ fn new_krate_with_dependency() {
     token.enqueue_publish(crate_to_publish).good();
 }
 
+#[test]
+fn unmatched_dependencies_are_checked_if_enabled() {
+    use cargo_registry::models::dependency::DependencyCheck;
+
+    let check = |dependency_check| {
+        let (app, _, user, token) = TestApp::full()
+            .with_config(|config| config.dependency_check = dependency_check)
+            .with_token();
+        app.db(|conn| {
+            CrateBuilder::new("foo_avail", user.as_model().id)
+                .version("1.0.0")
+                .version(VersionBuilder::new("2.0.0").yanked(true))
+                .expect_build(conn);
+        });
+
+        let dependency = DependencyBuilder::new("foo_avail").version_req("^2.0.0");
+        let crate_to_publish = PublishBuilder::new("new_avail")
+            .version("1.0.0")
+            .dependency(dependency);
+        token.enqueue_publish(crate_to_publish)
+    };
+
+    let json = check(DependencyCheck::Off).good();
+    assert!(json.warnings.unmatched_dependencies.is_empty());
+
+    let json = check(DependencyCheck::Warn).good();
+    assert_eq!(json.warnings.unmatched_dependencies, ["foo_avail ^2.0.0"]);
+    assert_eq!(json.warnings.other.len(), 1);
+
+    let json = check(DependencyCheck::Deny).bad_with_status(200);
+    assert_eq!(
+        json.errors[0].detail,
+        "no version which isn't yanked matches the dependencies: foo_avail ^2.0.0"
+    );
+}
+
+#[test]
+fn dependency_cycles_are_reported_to_owners() {
+    use cargo_registry::models::DependencyKind;
+    use cargo_registry::views::EncodableDependencyCycle;
+
+    #[derive(Deserialize)]
+    struct Cycles {
+        dependency_cycles: Vec<EncodableDependencyCycle>,
+    }
+
+    let (app, anon, user, token) = TestApp::full().with_token();
+    let another_user = app.db_new_user("bar");
+
+    app.db(|conn| {
+        let cyc_a = CrateBuilder::new("cyc_a", user.as_model().id).expect_build(conn);
+        CrateBuilder::new("cyc_b", another_user.as_model().id)
+            .version(VersionBuilder::new("1.0.0").dependency(&cyc_a, None))
+            .expect_build(conn);
+    });
+
+    let dependency = DependencyBuilder::new("cyc_b").kind(DependencyKind::Dev);
+    let crate_to_publish = PublishBuilder::new("cyc_a")
+        .version("1.1.0")
+        .dependency(dependency);
+    token.enqueue_publish(crate_to_publish).good();
+    app.run_pending_background_jobs();
+
+    let url = "/api/v1/crates/cyc_a/dependency_cycles";
+    let json: Cycles = user.get(url).good();
+    assert_eq!(json.dependency_cycles.len(), 1);
+    let cycle = &json.dependency_cycles[0];
+    assert_eq!(cycle.version, "1.1.0");
+    assert_eq!(cycle.crates, ["cyc_a", "cyc_b"]);
+    assert_eq!(cycle.kinds, ["dev", "normal"]);
+
+    // Publishing again doesn't report the cycle twice
+    let dependency = DependencyBuilder::new("cyc_b").kind(DependencyKind::Dev);
+    let crate_to_publish = PublishBuilder::new("cyc_a")
+        .version("1.2.0")
+        .dependency(dependency);
+    token.enqueue_publish(crate_to_publish).good();
+    app.run_pending_background_jobs();
+    let json: Cycles = user.get(url).good();
+    assert_eq!(json.dependency_cycles.len(), 1);
+
+    let json = another_user.get::<()>(url).bad_with_status(200);
+    assert!(json.errors[0].detail.contains("only owners of a crate"));
+    anon.get::<()>(url).assert_forbidden();
+}
+
 #[test]
 fn reject_new_krate_with_non_exact_dependency() {
     let (app, _, user, token) = TestApp::init().with_token();
@@ -961,6 +1402,57 @@ fn new_krate_wrong_user() {
     );
 }
 
+fn compression_bomb_app() -> (TestApp, MockCookieUser) {
+    let (app, _, user) = TestApp::init()
+        .with_config(|config| {
+            config.max_upload_size = 100_000;
+            config.max_unpack_size = 16 * 1024 * 1024;
+        })
+        .with_user();
+    (app, user)
+}
+
+#[test]
+fn new_krate_file_compression_bomb() {
+    let (_, user) = compression_bomb_app();
+
+    let zeros = vec![0; 2 * 1024 * 1024];
+    let files = [("foo_bomb-1.0.0/big", &zeros[..])];
+    let builder = PublishBuilder::new("foo_bomb").files(&files);
+
+    let json = user.enqueue_publish(builder).bad_with_status(400);
+    assert!(
+        json.errors[0]
+            .detail
+            .starts_with("`big` in the uploaded tarball expands"),
+        "{:?}",
+        json.errors
+    );
+}
+
+#[test]
+fn new_krate_total_compression_bomb() {
+    let (_, user) = compression_bomb_app();
+
+    // Each file is too small to be checked on its own
+    let zeros = vec![0; 512 * 1024];
+    let files = [
+        ("foo_bomb-1.0.0/a", &zeros[..]),
+        ("foo_bomb-1.0.0/b", &zeros[..]),
+        ("foo_bomb-1.0.0/c", &zeros[..]),
+    ];
+    let builder = PublishBuilder::new("foo_bomb").files(&files);
+
+    let json = user.enqueue_publish(builder).bad_with_status(400);
+    assert!(
+        json.errors[0]
+            .detail
+            .starts_with("the uploaded tarball expands"),
+        "{:?}",
+        json.errors
+    );
+}
+
 // TODO: Move this test to the main crate
 #[test]
 fn valid_feature_names() {
@@ -1022,31 +1514,155 @@ fn new_krate_wrong_files() {
     );
 }
 
+/// A tarball with a single entry, whose path is written as is, since the
+/// `tar` crate refuses to create entries with malicious paths.
+fn tarball_with_raw_path(path: &[u8], entry_type: tar::EntryType) -> Vec<u8> {
+    let mut tarball = Vec::new();
+    {
+        let mut ar = tar::Builder::new(GzEncoder::new(&mut tarball, Compression::default()));
+        let mut header = tar::Header::new_old();
+        header.as_old_mut().name[..path.len()].copy_from_slice(path);
+        header.set_entry_type(entry_type);
+        header.set_size(0);
+        header.set_cksum();
+        t!(ar.append(&header, &[][..]));
+        t!(ar.finish());
+    }
+    tarball
+}
+
 #[test]
-fn new_krate_gzip_bomb() {
-    let (_, _, _, token) = TestApp::init().with_token();
+fn new_krate_path_traversal() {
+    let (_, _, user) = TestApp::init().with_user();
 
-    let len = 512 * 1024;
-    let mut body = io::repeat(0).take(len);
+    let cases: &[(&[u8], &str)] = &[
+        (
+            b"/foo_escape-1.0.0/a",
+            "invalid tarball uploaded: `/foo_escape-1.0.0/a` is an absolute path",
+        ),
+        (
+            b"foo_escape-1.0.0/../../a",
+            "invalid tarball uploaded: `foo_escape-1.0.0/../../a` refers to a parent directory",
+        ),
+    ];
+    for &(path, expected) in cases {
+        let tarball = tarball_with_raw_path(path, tar::EntryType::Regular);
+        let builder = PublishBuilder::new("foo_escape").tarball(tarball);
+
+        let json = user.enqueue_publish(builder).bad_with_status(200);
+        assert_eq!(json.errors[0].detail, expected);
+    }
+}
 
-    let crate_to_publish = PublishBuilder::new("foo")
-        .version("1.1.0")
-        .files_with_io(&mut [("foo-1.1.0/a", &mut body, len)]);
+#[test]
+fn new_krate_tarball_with_symlinks() {
+    let (_, _, user) = TestApp::init().with_user();
 
-    let json = token.enqueue_publish(crate_to_publish).bad_with_status(200);
+    let mut tarball = Vec::new();
+    {
+        let mut ar = tar::Builder::new(GzEncoder::new(&mut tarball, Compression::default()));
+        let mut header = tar::Header::new_gnu();
+        t!(header.set_path("foo_link-1.0.0/passwd"));
+        header.set_size(0);
+        header.set_entry_type(tar::EntryType::symlink());
+        t!(header.set_link_name("/etc/passwd"));
+        header.set_cksum();
+        t!(ar.append(&header, &[][..]));
+        t!(ar.finish());
+    }
+    let builder = PublishBuilder::new("foo_link").tarball(tarball);
 
-    assert!(
-        json.errors[0]
-            .detail
-            .contains("too large when decompressed"),
-        "{:?}",
-        json.errors
+    let json = user.enqueue_publish(builder).bad_with_status(200);
+    assert_eq!(
+        json.errors[0].detail,
+        "invalid tarball uploaded: `foo_link-1.0.0/passwd` is a link"
     );
 }
 
 #[test]
-fn new_krate_duplicate_version() {
-    let (app, _, user, token) = TestApp::init().with_token();
+fn new_krate_with_too_many_files() {
+    let (_, _, user) = TestApp::init()
+        .with_config(|config| config.max_file_count = 2)
+        .with_user();
+
+    let files = [
+        ("foo_many_files-1.0.0/a", &b""[..]),
+        ("foo_many_files-1.0.0/b", &b""[..]),
+        ("foo_many_files-1.0.0/c", &b""[..]),
+    ];
+    let builder = PublishBuilder::new("foo_many_files").files(&files);
+
+    let json = user.enqueue_publish(builder).bad_with_status(400);
+    assert_eq!(
+        json.errors[0].detail,
+        "the uploaded tarball contains more than the maximum of 2 files"
+    );
+}
+
+#[test]
+fn new_krate_with_too_long_path() {
+    let (_, _, user) = TestApp::init()
+        .with_config(|config| config.max_path_length = 10)
+        .with_user();
+
+    let files = [
+        ("foo_long_path-1.0.0/src/lib.rs", &b""[..]),
+        ("foo_long_path-1.0.0/src/very/deeply/nested.rs", &b""[..]),
+    ];
+    let builder = PublishBuilder::new("foo_long_path").files(&files);
+
+    let json = user.enqueue_publish(builder).bad_with_status(400);
+    assert_eq!(
+        json.errors[0].detail,
+        "the path `src/very/deeply/nested.rs` in the uploaded tarball is longer than the \
+         maximum of 10 bytes"
+    );
+}
+
+#[test]
+fn new_krate_with_paths_differing_by_case() {
+    let (_, _, user) = TestApp::init().with_user();
+
+    let files = [
+        ("foo_case-1.0.0/README.md", &b"readme"[..]),
+        ("foo_case-1.0.0/src/lib.rs", &b""[..]),
+        ("foo_case-1.0.0/readme.md", &b"other readme"[..]),
+    ];
+    let builder = PublishBuilder::new("foo_case").files(&files);
+
+    let json = user.enqueue_publish(builder).bad_with_status(200);
+    assert_eq!(
+        json.errors[0].detail,
+        "invalid tarball uploaded: `foo_case-1.0.0/README.md` and `foo_case-1.0.0/readme.md` \
+         only differ by case, so one would overwrite the other on case-insensitive file systems"
+    );
+}
+
+#[test]
+fn new_krate_gzip_bomb() {
+    let (_, _, _, token) = TestApp::init().with_token();
+
+    let len = 512 * 1024;
+    let mut body = io::repeat(0).take(len);
+
+    let crate_to_publish = PublishBuilder::new("foo")
+        .version("1.1.0")
+        .files_with_io(&mut [("foo-1.1.0/a", &mut body, len)]);
+
+    let json = token.enqueue_publish(crate_to_publish).bad_with_status(200);
+
+    assert!(
+        json.errors[0]
+            .detail
+            .contains("too large when decompressed"),
+        "{:?}",
+        json.errors
+    );
+}
+
+#[test]
+fn new_krate_duplicate_version() {
+    let (app, _, user, token) = TestApp::init().with_token();
 
     app.db(|conn| {
         // Insert a crate directly into the database and then we'll try to publish the same version
@@ -1144,6 +1760,239 @@ fn new_krate_git_upload() {
     );
 }
 
+#[test]
+fn new_krate_checksum_covers_the_whole_crate_file() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    let mut tarball = Vec::new();
+    {
+        let mut ar = tar::Builder::new(GzEncoder::new(&mut tarball, Compression::default()));
+        let mut header = tar::Header::new_gnu();
+        t!(header.set_path("fstream-1.0.0/src/lib.rs"));
+        header.set_size(0);
+        header.set_cksum();
+        t!(ar.append(&header, &[][..]));
+        t!(ar.into_inner().and_then(GzEncoder::finish));
+    }
+    // Data after the archive isn't read when verifying it, but is part of
+    // the crate file
+    tarball.extend_from_slice(&[0; 512]);
+    let expected = hex::encode(openssl::sha::sha256(&tarball));
+
+    let crate_to_publish = PublishBuilder::new("fstream").tarball(tarball);
+    token.enqueue_publish(crate_to_publish).good();
+    app.run_pending_background_jobs();
+
+    let crates = app.crates_from_index_head("fs/tr/fstream");
+    assert_eq!(crates[0].cksum, expected);
+}
+
+#[test]
+fn new_krate_records_analyses() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    let license: &[u8] = b"Permission is hereby granted, free of charge, to any person";
+    let lib: &[u8] = b"#![no_std]\n";
+    let blob: &[u8] = b"\0\x01\x02";
+    let files = [
+        ("foo_analyzed-1.0.0/LICENSE-MIT", license),
+        ("foo_analyzed-1.0.0/src/lib.rs", lib),
+        ("foo_analyzed-1.0.0/assets/blob.bin", blob),
+    ];
+    let crate_to_publish = PublishBuilder::new("foo_analyzed").files(&files);
+    token.enqueue_publish(crate_to_publish).good();
+    app.run_pending_background_jobs();
+
+    let analyses = app.db(|conn| {
+        let version_id = versions::table
+            .inner_join(crates::table)
+            .filter(crates::name.eq("foo_analyzed"))
+            .select(versions::id)
+            .first(conn)
+            .unwrap();
+        VersionAnalysis::by_version(conn, version_id).unwrap()
+    });
+    assert_eq!(
+        analyses.keys().collect::<Vec<_>>(),
+        vec![
+            "binary",
+            "executables",
+            "fingerprint",
+            "license",
+            "no_std",
+            "size"
+        ]
+    );
+    assert_eq!(analyses["size"]["files"], 3);
+    assert_eq!(
+        analyses["binary"]["binary_files"],
+        json!(["assets/blob.bin"])
+    );
+    assert_eq!(analyses["no_std"]["no_std"], true);
+    assert_eq!(analyses["license"]["license_files"][0]["license"], "MIT");
+    assert_eq!(analyses["executables"]["findings"], json!([]));
+}
+
+#[test]
+fn dry_run_publish_checks_without_publishing() {
+    use conduit::Method;
+
+    let (storage, app, token) = crate::storage::memory_storage_app();
+    let dry_run = |crate_to_publish: PublishBuilder| {
+        let mut request = token.request_builder(Method::Put, "/api/v1/crates/new");
+        request.with_query("dry_run=true");
+        request.with_body(&crate_to_publish.body());
+        token.run::<GoodCrate>(request)
+    };
+
+    let crate_to_publish = PublishBuilder::new("foo_dry_run").unset_description();
+    let json = dry_run(crate_to_publish).good();
+    assert_eq!(json.krate.name, "foo_dry_run");
+    assert!(json
+        .warnings
+        .lints
+        .iter()
+        .any(|lint| lint.lint == "missing_description"));
+
+    assert!(storage.paths().is_empty());
+    app.run_pending_background_jobs();
+    token
+        .get::<()>("/api/v1/crates/foo_dry_run")
+        .assert_not_found();
+
+    // Errors are reported like for a real publish
+    let other = app.db_new_user("other");
+    app.db(|conn| {
+        CrateBuilder::new("foo_dry_run_owned", other.as_model().id).expect_build(conn);
+    });
+    let json =
+        dry_run(PublishBuilder::new("foo_dry_run_owned").version("2.0.0")).bad_with_status(200);
+    assert!(
+        json.errors[0].detail.contains("don't seem to be an owner"),
+        "{:?}",
+        json.errors
+    );
+}
+
+#[test]
+fn queued_publish_reports_its_status() {
+    use conduit::Method;
+
+    let storage = Arc::new(MemoryStorage::default());
+    let (app, anon, _, token) = TestApp::init()
+        .with_git_index()
+        .with_job_runner()
+        .with_config(|config| config.uploader = Uploader::new(storage.clone()))
+        .with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo_queued");
+    let mut request = token.request_builder(Method::Put, "/api/v1/crates/new");
+    request.with_query("queued=true");
+    request.with_body(&crate_to_publish.body());
+    let json: GoodCrate = token.run(request).good();
+    assert_eq!(json.krate.name, "foo_queued");
+    assert_eq!(
+        storage.paths(),
+        vec!["staging/foo_queued/foo_queued-1.0.0.crate"]
+    );
+
+    let url = "/api/v1/crates/foo_queued/1.0.0/publish-status";
+    let json: serde_json::Value = token.get(url).good();
+    assert_eq!(json["publish_status"]["state"], "queued");
+    assert_eq!(json["publish_status"]["error"], serde_json::Value::Null);
+
+    // Only the publisher can see the status
+    anon.get::<()>(url).assert_forbidden();
+    let other = app.db_new_user("other");
+    other.get::<()>(url).assert_not_found();
+
+    app.run_pending_background_jobs();
+    let json: serde_json::Value = token.get(url).good();
+    assert_eq!(json["publish_status"]["state"], "indexed");
+    let crates = app.crates_from_index_head("fo/o_/foo_queued");
+    assert_eq!(crates[0].vers, "1.0.0");
+    let paths = storage.paths();
+    assert!(paths.contains(&"crates/foo_queued/foo_queued-1.0.0.crate".to_string()));
+    assert!(!paths.iter().any(|path| path.starts_with("staging/")));
+
+    // Versions published without `?queued=true` have no status
+    token
+        .enqueue_publish(PublishBuilder::new("foo_sync"))
+        .good();
+    app.run_pending_background_jobs();
+    token
+        .get::<()>("/api/v1/crates/foo_sync/1.0.0/publish-status")
+        .assert_not_found();
+}
+
+#[test]
+fn publish_retry_with_idempotency_key() {
+    use conduit::Method;
+
+    let (app, _, user, token) = TestApp::init().with_token();
+    let user = user.as_model();
+
+    let tarball = b"the crate file".to_vec();
+    let checksum = hex::encode(openssl::sha::sha256(&tarball));
+    let response = json!({ "crate": { "name": "foo_retried" }, "warnings": {} });
+    app.db(|conn| {
+        PublishIdempotencyKey::claim(conn, user.id, "retry-1", "foo_retried", "1.0.0").unwrap();
+        PublishIdempotencyKey::record(conn, user.id, "retry-1", &checksum, &response).unwrap();
+    });
+
+    let publish = |tarball: Vec<u8>| {
+        let crate_to_publish = PublishBuilder::new("foo_retried").tarball(tarball);
+        let mut request = token.request_builder(Method::Put, "/api/v1/crates/new");
+        request.header("Idempotency-Key", "retry-1");
+        request.with_body(&crate_to_publish.body());
+        token.run::<serde_json::Value>(request)
+    };
+
+    assert_eq!(publish(tarball).good(), response);
+
+    let json = publish(b"another crate file".to_vec()).bad_with_status(200);
+    assert!(
+        json.errors[0].detail.contains("already used to publish"),
+        "{:?}",
+        json.errors
+    );
+}
+
+#[test]
+fn publish_claims_its_idempotency_key() {
+    use conduit::Method;
+
+    let (_storage, app, token) = crate::storage::memory_storage_app();
+    let user_id = token.as_model().user_id;
+
+    let body = PublishBuilder::new("foo_claimed").body();
+    let publish = || {
+        let mut request = token.request_builder(Method::Put, "/api/v1/crates/new");
+        request.header("Idempotency-Key", "claim-1");
+        request.with_body(&body);
+        token.run::<serde_json::Value>(request).good()
+    };
+
+    let json = publish();
+    assert_eq!(json["crate"]["name"], "foo_claimed");
+    assert_eq!(publish(), json);
+
+    app.db(|conn| {
+        let key = PublishIdempotencyKey::find(conn, user_id, "claim-1")
+            .unwrap()
+            .unwrap();
+        assert_eq!(key.crate_name, "foo_claimed");
+        assert_eq!(key.response, Some(json.clone()));
+        assert_eq!(PublishIdempotencyKey::prune_expired(conn).unwrap(), 0);
+
+        update(publish_idempotency_keys::table)
+            .set(publish_idempotency_keys::created_at.eq(now - 2.days()))
+            .execute(conn)
+            .unwrap();
+        assert_eq!(PublishIdempotencyKey::prune_expired(conn).unwrap(), 1);
+    });
+}
+
 #[test]
 fn new_krate_git_upload_appends() {
     let (app, _, _, token) = TestApp::full().with_token();
@@ -1273,6 +2122,108 @@ fn new_krate_records_verified_email() {
     });
 }
 
+#[test]
+fn index_checksums() {
+    let (app, anon, user) = TestApp::init()
+        .with_git_index()
+        .with_job_runner()
+        .with_user();
+
+    let index_entry = git::Crate {
+        name: "foo_checksum".into(),
+        vers: "1.0.0".into(),
+        deps: Vec::new(),
+        cksum: "0".repeat(64),
+        features: HashMap::new(),
+        yanked: Some(false),
+        links: None,
+        compression: None,
+        rust_version: None,
+        yanked_reason: None,
+    };
+    let index_file = serde_json::to_string(&index_entry).unwrap() + "\n";
+    app.db(|conn| {
+        CrateBuilder::new("foo_checksum", user.as_model().id).expect_build(conn);
+        git::add_crate(index_entry).enqueue_versioned(conn).unwrap();
+    });
+    app.run_pending_background_jobs();
+
+    let json: serde_json::Value = anon
+        .get("/api/v1/index-checksums?crates=foo_checksum,missing")
+        .good();
+    assert_eq!(
+        json,
+        json!({
+            "checksums": {
+                "foo_checksum": hex::encode(openssl::sha::sha256(index_file.as_bytes())),
+                "missing": null,
+            }
+        })
+    );
+
+    let too_many = (0..101).map(|i| i.to_string()).collect::<Vec<_>>();
+    let url = format!("/api/v1/index-checksums?crates={}", too_many.join(","));
+    anon.get::<()>(&url).assert_status(400);
+}
+
+#[test]
+fn index_v3_fields_are_written_when_enabled() {
+    use cargo_registry::models::{insert_version_owner_action, Version, VersionAction};
+
+    let (app, _, user) = TestApp::init()
+        .with_git_index()
+        .with_job_runner()
+        .with_index_v3_fields()
+        .with_user();
+    let user = user.as_model();
+
+    let index_entry = git::Crate {
+        name: "foo_v3".into(),
+        vers: "1.0.0".into(),
+        deps: Vec::new(),
+        cksum: "0".repeat(64),
+        features: HashMap::new(),
+        yanked: Some(false),
+        links: None,
+        compression: None,
+        rust_version: Some("1.42".into()),
+        yanked_reason: None,
+    };
+    let version = app.db(|conn| {
+        let krate = CrateBuilder::new("foo_v3", user.id).expect_build(conn);
+        git::add_crate(index_entry).enqueue_versioned(conn).unwrap();
+        Version::belonging_to(&krate)
+            .first::<Version>(conn)
+            .unwrap()
+    });
+    app.run_pending_background_jobs();
+
+    let crates = app.crates_from_index_head("3/f/foo_v3");
+    assert_eq!(crates[0].rust_version.as_deref(), Some("1.42"));
+    assert_eq!(crates[0].yanked_reason, None);
+
+    app.db(|conn| {
+        insert_version_owner_action(
+            conn,
+            version.id,
+            user.id,
+            None,
+            VersionAction::Yank,
+            Some("security issue"),
+        )
+        .unwrap();
+        git::yank("foo_v3".into(), version, true)
+            .enqueue_versioned(conn)
+            .unwrap();
+    });
+    app.run_pending_background_jobs();
+
+    let crates = app.crates_from_index_head("3/f/foo_v3");
+    assert_eq!(crates[0].yanked, Some(true));
+    assert_eq!(crates[0].yanked_reason.as_deref(), Some("security issue"));
+    assert_eq!(crates[0].rust_version.as_deref(), Some("1.42"));
+}
+
 #[test]
 fn summary_doesnt_die() {
     let (_, anon) = TestApp::init().empty();
@@ -1426,6 +2377,88 @@ fn download() {
     assert_dl_count("FOO_DOWNLOAD", Some(&query), 2);
 }
 
+#[test]
+fn downloads_per_version() {
+    use cargo_registry::schema::version_downloads;
+    use chrono::Duration;
+
+    #[derive(Deserialize)]
+    struct Comparison {
+        dates: Vec<String>,
+        version_series: Vec<Series>,
+    }
+    #[derive(Deserialize)]
+    struct Series {
+        version: String,
+        downloads: Vec<i32>,
+    }
+
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo_compare", user.id)
+            .version(VersionBuilder::new("1.0.0"))
+            .version(VersionBuilder::new("2.0.0"))
+            .version(VersionBuilder::new("3.0.0"))
+            .expect_build(conn);
+        let today = Utc::today().naive_utc();
+        let version_id = |num: &str| {
+            versions::table
+                .filter(versions::crate_id.eq(krate.id))
+                .filter(versions::num.eq(num))
+                .select(versions::id)
+                .first::<i32>(conn)
+                .unwrap()
+        };
+        let rows = vec![
+            (version_id("1.0.0"), today - Duration::days(2), 5),
+            (version_id("1.0.0"), today, 3),
+            (version_id("2.0.0"), today, 7),
+            (version_id("3.0.0"), today, 11),
+            (version_id("1.0.0"), today - Duration::days(200), 13),
+        ]
+        .into_iter()
+        .map(|(version_id, date, downloads)| {
+            (
+                version_downloads::version_id.eq(version_id),
+                version_downloads::date.eq(date),
+                version_downloads::downloads.eq(downloads),
+            )
+        })
+        .collect::<Vec<_>>();
+        diesel::insert_into(version_downloads::table)
+            .values(&rows)
+            .execute(conn)
+            .unwrap();
+    });
+
+    let url = "/api/v1/crates/foo_compare/downloads";
+    let json: Comparison = anon
+        .get_with_query(url, "per_version=true&versions=2.0.0,1.0.0")
+        .good();
+    assert_eq!(json.dates.len(), 90);
+    assert_eq!(
+        json.dates.last().unwrap(),
+        &Utc::today().naive_utc().to_string()
+    );
+    let series = json
+        .version_series
+        .iter()
+        .map(|s| (s.version.as_str(), &s.downloads[87..]))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        series,
+        vec![("2.0.0", &[0, 0, 7][..]), ("1.0.0", &[5, 0, 3][..])]
+    );
+    assert_eq!(json.version_series[1].downloads.iter().sum::<i32>(), 8);
+
+    anon.get_with_query::<()>(url, "per_version=true")
+        .assert_status(400);
+    anon.get_with_query::<()>(url, "per_version=true&versions=1.0.0,9.9.9")
+        .assert_status(400);
+}
+
 #[test]
 fn download_nonexistent_version_of_existing_crate_404s() {
     let (app, anon, user) = TestApp::init().with_user();
@@ -1457,80 +2490,621 @@ fn download_noncanonical_crate_name() {
 }
 
 #[test]
-fn dependencies() {
-    let (app, anon, user) = TestApp::init().with_user();
-    let user = user.as_model();
+fn shields_badges() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_badges", user.id)
+            .downloads(1_450)
+            .version(VersionBuilder::new("1.0.0").license(Some("MIT")))
+            .version(VersionBuilder::new("2.0.0-beta.1"))
+            .expect_build(conn);
+    });
+
+    let badge = |kind: &str| {
+        let url = format!("/api/v1/crates/foo_badges/badges/{}", kind);
+        anon.get::<serde_json::Value>(&url).good()
+    };
+
+    assert_eq!(
+        badge("downloads"),
+        json!({ "schemaVersion": 1, "label": "downloads", "message": "1.4k", "color": "blue" })
+    );
+    assert_eq!(
+        badge("version"),
+        json!({ "schemaVersion": 1, "label": "crates.io", "message": "v1.0.0", "color": "green" })
+    );
+    assert_eq!(
+        badge("license"),
+        json!({ "schemaVersion": 1, "label": "license", "message": "MIT", "color": "blue" })
+    );
+    assert_eq!(
+        badge("msrv"),
+        json!({ "schemaVersion": 1, "label": "msrv", "message": "unknown", "color": "lightgrey" })
+    );
+}
+
+#[test]
+fn shields_msrv_badge() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_msrv", user.id)
+            .version("1.0.0")
+            .expect_build(conn);
+        update(versions::table)
+            .set(versions::rust_version.eq("1.42"))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let json: serde_json::Value = anon.get("/api/v1/crates/foo_msrv/badges/msrv").good();
+    assert_eq!(
+        json,
+        json!({ "schemaVersion": 1, "label": "msrv", "message": "1.42", "color": "blue" })
+    );
+}
+
+#[test]
+fn shields_badges_for_missing_crate() {
+    let (_, anon) = TestApp::init().empty();
+
+    let json = anon
+        .get::<serde_json::Value>("/api/v1/crates/missing/badges/version")
+        .good();
+    assert_eq!(json["isError"], json!(true));
+    assert_eq!(json["message"], json!("crate not found"));
+}
+
+#[test]
+fn og_image_redirects_to_uploaded_image() {
+    let (_, anon) = TestApp::init().empty();
+
+    anon.get::<()>("/api/v1/crates/foo_og/og_image")
+        .assert_redirect_ends_with("/og-images/foo_og.png");
+}
+
+#[test]
+fn new_krate_with_invalid_policy_file() {
+    let (_, _, user) = TestApp::init().with_user();
+
+    let policy: &[u8] = b"[deprecation]\nreplaced_by = \"bar\"\n";
+    let files = [("foo_policy-1.0.0/.crates-io.toml", policy)];
+    let builder = PublishBuilder::new("foo_policy").files(&files);
+
+    let json = user.enqueue_publish(builder).bad_with_status(200);
+    assert!(
+        json.errors[0]
+            .detail
+            .contains("invalid `.crates-io.toml`: unknown field `replaced_by`"),
+        "{:?}",
+        json.errors
+    );
+}
+
+#[test]
+fn crate_policy() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo_policy", user.id).expect_build(conn);
+        let version = VersionBuilder::new("1.0.0").expect_build(krate.id, user.id, conn);
+        let policy = PolicyFile::parse("[deprecation]\nreplaced-by = \"bar\"\n").unwrap();
+        CratePolicy::update_crate(conn, krate.id, version.id, Some(policy)).unwrap();
+
+        CrateBuilder::new("foo_no_policy", user.id)
+            .version("1.0.0")
+            .expect_build(conn);
+    });
+
+    let json: serde_json::Value = anon.get("/api/v1/crates/foo_policy/policy").good();
+    assert_eq!(
+        json["policy"],
+        json!({
+            "version": "1.0.0",
+            "deprecation": { "replaced_by": "bar", "message": null },
+        })
+    );
+
+    let json: serde_json::Value = anon.get("/api/v1/crates/foo_no_policy/policy").good();
+    assert_eq!(json["policy"], serde_json::Value::Null);
+}
+
+#[test]
+fn release_stats() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo_stats", user.id)
+            .version("1.0.0")
+            .version(VersionBuilder::new("1.1.0").yanked(true))
+            .expect_build(conn);
+        let today = Utc::now().naive_utc();
+        let versions = [(false, today), (true, today)];
+        NewReleaseStats::compute(krate.id, &versions, today)
+            .save(conn)
+            .unwrap();
+
+        CrateBuilder::new("foo_no_stats", user.id)
+            .version("1.0.0")
+            .expect_build(conn);
+    });
+
+    let json: serde_json::Value = anon.get("/api/v1/crates/foo_stats/release_stats").good();
+    assert_eq!(json["release_stats"]["releases"], 2);
+    assert_eq!(json["release_stats"]["avg_days_between_releases"], 0.0);
+    assert_eq!(json["release_stats"]["releases_per_year"], 2.0);
+    assert_eq!(json["release_stats"]["yank_rate"], 0.5);
+
+    let json: serde_json::Value = anon.get("/api/v1/crates/foo_no_stats/release_stats").good();
+    assert_eq!(json["release_stats"], serde_json::Value::Null);
+}
+
+#[test]
+fn full_crate_page() {
+    let (app, anon, user) = TestApp::full().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_full", user.id)
+            .version("1.0.0")
+            .keyword("kw1")
+            .expect_build(conn);
+    });
+
+    let json: serde_json::Value = anon.get("/api/v1/crates/foo_full/full").good();
+    assert_eq!(json["crate"]["name"], "foo_full");
+    assert_eq!(json["versions"][0]["num"], "1.0.0");
+    assert_eq!(json["keywords"][0]["keyword"], "kw1");
+    assert_eq!(json["owners"][0]["login"], "foo");
+    assert!(json["downloads"]["version_downloads"].is_array());
+
+    // The payload is only stored by the job
+    let stored = app.db(|conn| {
+        cargo_registry::schema::crate_pages::table
+            .count()
+            .get_result::<i64>(conn)
+            .unwrap()
+    });
+    assert_eq!(stored, 0);
+    app.run_pending_background_jobs();
+
+    // The stored payload is served until it's rendered again
+    app.db(|conn| {
+        update(crates::table)
+            .set(crates::description.eq("changed"))
+            .execute(conn)
+            .unwrap();
+    });
+    let cached: serde_json::Value = anon.get("/api/v1/crates/foo_full/full").good();
+    assert_eq!(cached, json);
+}
+
+#[test]
+fn crate_funding() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo_funding", user.id).expect_build(conn);
+        let manifest = "[package]\nname = \"foo_funding\"\n\n[package.metadata.funding]\ngithub = \"octocat\"\n";
+        let links = collect_funding_links(Some(manifest), Some("ko_fi: octocat\n"));
+        FundingLink::update_crate(conn, krate.id, &links).unwrap();
+    });
+
+    let json: serde_json::Value = anon.get("/api/v1/crates/foo_funding/funding").good();
+    assert_eq!(
+        json["funding"],
+        json!([
+            { "platform": "github", "url": "https://github.com/sponsors/octocat" },
+            { "platform": "ko_fi", "url": "https://ko-fi.com/octocat" },
+        ])
+    );
+}
+
+#[test]
+fn security_policy_url() {
+    let (app, anon, user) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo_security", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+        let version_id = versions::table
+            .filter(versions::crate_id.eq(krate.id))
+            .select(versions::id)
+            .first(conn)
+            .unwrap();
+        let policy = (
+            "SECURITY.md".to_string(),
+            "https://github.com/foo/bar/blob/HEAD/SECURITY.md".to_string(),
+        );
+        CrateSecurityPolicy::update_crate(conn, krate.id, version_id, Some(policy)).unwrap();
+    });
+
+    let json = anon.show_crate("foo_security");
+    assert_eq!(
+        json.krate.security_policy_url.as_deref(),
+        Some("https://github.com/foo/bar/blob/HEAD/SECURITY.md")
+    );
+
+    // A link set by the owners takes precedence
+    let body = br#"{"security_policy": "https://example.com/security"}"#;
+    user.put::<()>("/api/v1/crates/foo_security/custom_links", body)
+        .assert_status(200);
+    let json = anon.show_crate("foo_security");
+    assert_eq!(
+        json.krate.security_policy_url.as_deref(),
+        Some("https://example.com/security")
+    );
+}
+
+#[test]
+fn dependencies() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let c1 = CrateBuilder::new("foo_deps", user.id).expect_build(conn);
+        let v = VersionBuilder::new("1.0.0").expect_build(c1.id, user.id, conn);
+        let c2 = CrateBuilder::new("bar_deps", user.id).expect_build(conn);
+        new_dependency(conn, &v, &c2);
+    });
+
+    let deps: Deps = anon
+        .get("/api/v1/crates/foo_deps/1.0.0/dependencies")
+        .good();
+    assert_eq!(deps.dependencies[0].crate_id, "bar_deps");
+
+    anon.get::<()>("/api/v1/crates/foo_deps/1.0.2/dependencies")
+        .bad_with_status(200);
+}
+
+#[test]
+fn diesel_not_found_results_in_404() {
+    let (_, _, user) = TestApp::init().with_user();
+
+    user.get("/api/v1/crates/foo_following/following")
+        .assert_not_found();
+}
+
+#[test]
+fn following() {
+    // TODO: Test anon requests as well?
+    let (app, _, user) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_following", user.as_model().id).expect_build(conn);
+    });
+
+    let is_following = || -> bool {
+        #[derive(Deserialize)]
+        struct F {
+            following: bool,
+        }
+
+        user.get::<F>("/api/v1/crates/foo_following/following")
+            .good()
+            .following
+    };
+
+    let follow = || {
+        assert!(
+            user.put::<OkBool>("/api/v1/crates/foo_following/follow", b"")
+                .good()
+                .ok
+        );
+    };
+
+    let unfollow = || {
+        assert!(
+            user.delete::<OkBool>("api/v1/crates/foo_following/follow")
+                .good()
+                .ok
+        );
+    };
+
+    assert!(!is_following());
+    follow();
+    follow();
+    assert!(is_following());
+    assert_eq!(user.search("following=1").crates.len(), 1);
+
+    unfollow();
+    unfollow();
+    assert!(!is_following());
+    assert_eq!(user.search("following=1").crates.len(), 0);
+}
+
+#[test]
+fn dependency_update_subscriptions() {
+    let (app, _, user) = TestApp::init().with_user();
+    let other = app.db_new_user("other");
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_dep_updates", user.as_model().id).expect_build(conn);
+    });
+
+    let is_subscribed = || -> bool {
+        #[derive(Deserialize)]
+        struct S {
+            subscribed: bool,
+        }
+
+        user.get::<S>("/api/v1/crates/foo_dep_updates/dependency_updates")
+            .good()
+            .subscribed
+    };
+
+    assert!(!is_subscribed());
+    assert!(
+        user.put::<OkBool>("/api/v1/crates/foo_dep_updates/dependency_updates", b"")
+            .good()
+            .ok
+    );
+    assert!(is_subscribed());
+    assert!(
+        user.delete::<OkBool>("/api/v1/crates/foo_dep_updates/dependency_updates")
+            .good()
+            .ok
+    );
+    assert!(!is_subscribed());
+
+    let json = other
+        .put::<OkBool>("/api/v1/crates/foo_dep_updates/dependency_updates", b"")
+        .bad_with_status(200);
+    assert!(json.errors[0].detail.contains("only owners of a crate"));
+}
+
+#[test]
+fn squatting_reports() {
+    use cargo_registry::models::SquattingReport;
+    use cargo_registry::schema::squatting_reports;
+
+    let (app, _, user) = TestApp::init().with_user();
+    let reporter = app.db_new_user("reporter");
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_squatted", user.as_model().id).expect_build(conn);
+        CrateBuilder::new("foo_used", user.as_model().id)
+            .downloads(10_000)
+            .expect_build(conn);
+        diesel::update(crates::table)
+            .set(crates::updated_at.eq(Utc::now().naive_utc() - chrono::Duration::days(400)))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let body = br#"{"reason": "empty crate"}"#;
+    reporter
+        .put::<OkBool>("/api/v1/crates/foo_squatted/squatting_report", body)
+        .good();
+    reporter
+        .put::<OkBool>("/api/v1/crates/foo_used/squatting_report", body)
+        .good();
+
+    let json = reporter
+        .put::<()>("/api/v1/crates/foo_squatted/squatting_report", body)
+        .bad_with_status(200);
+    assert_eq!(
+        json.errors[0].detail,
+        "you have already reported this crate"
+    );
+    let json = user
+        .put::<()>("/api/v1/crates/foo_squatted/squatting_report", body)
+        .bad_with_status(200);
+    assert_eq!(json.errors[0].detail, "you can't report your own crate");
+    let json = reporter
+        .put::<()>(
+            "/api/v1/crates/foo_used/squatting_report",
+            br#"{"reason": " "}"#,
+        )
+        .bad_with_status(200);
+    assert_eq!(
+        json.errors[0].detail,
+        "a reason is required to report a crate"
+    );
+
+    app.db(|conn| {
+        let checked = SquattingReport::check_pending(conn, Utc::now().naive_utc()).unwrap();
+        assert_eq!(checked, 2);
+        let candidates = SquattingReport::candidates(conn).unwrap();
+        assert_eq!(
+            candidates,
+            vec![("foo_squatted".to_string(), vec!["empty crate".to_string()])]
+        );
+
+        let krate = Crate::by_name("foo_squatted").first::<Crate>(conn).unwrap();
+        let now = Utc::now().naive_utc();
+        assert_eq!(
+            SquattingReport::resolve(conn, krate.id, "released", now).unwrap(),
+            1
+        );
+        assert!(SquattingReport::candidates(conn).unwrap().is_empty());
+        let statuses = squatting_reports::table
+            .select(squatting_reports::status)
+            .order(squatting_reports::id)
+            .load::<String>(conn)
+            .unwrap();
+        assert_eq!(statuses, vec!["resolved", "dismissed"]);
+        let outcomes = SquattingReport::outcomes(conn).unwrap();
+        assert_eq!(outcomes.len(), 1);
+        assert!(outcomes[0].0.is_candidate());
+    });
+}
+
+#[test]
+fn metadata_history() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let other = app.db_new_user("other");
+
+    #[derive(Deserialize)]
+    struct History {
+        history: Vec<EncodableMetadataChange>,
+    }
+
+    let crate_to_publish = PublishBuilder::new("foo_history").keyword("a");
+    user.enqueue_publish(crate_to_publish).good();
+    let crate_to_publish = PublishBuilder::new("foo_history")
+        .version("1.1.0")
+        .description("new description")
+        .keyword("a")
+        .keyword("b");
+    user.enqueue_publish(crate_to_publish).good();
+    let body = br#"{"chat": "https://discord.gg/foo"}"#;
+    user.put::<()>("/api/v1/crates/foo_history/custom_links", body)
+        .assert_status(200);
+
+    let url = "/api/v1/crates/foo_history/history";
+    let json = user.get::<History>(url).good();
+    let changes = json
+        .history
+        .iter()
+        .map(|change| {
+            (
+                change.field.as_str(),
+                change.old_value.clone(),
+                change.new_value.clone(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        changes,
+        vec![
+            (
+                "description",
+                json!("description"),
+                json!("new description")
+            ),
+            ("keywords", json!(["a"]), json!(["a", "b"])),
+            (
+                "links",
+                json!({"funding": null, "chat": null, "security_policy": null}),
+                json!({"funding": null, "chat": "https://discord.gg/foo", "security_policy": null}),
+            ),
+        ]
+    );
+    assert!(json.history.iter().all(|change| change.user.login == "foo"));
+
+    anon.get::<()>(url).assert_forbidden();
+    let json = other.get::<()>(url).bad_with_status(200);
+    assert_eq!(
+        json.errors[0].detail,
+        "only owners of a crate can see its history"
+    );
+}
+
+#[test]
+fn download_anomalies_are_only_shown_to_owners() {
+    let (app, anon, user) = TestApp::init()
+        .with_config(|config| config.show_download_anomalies_to_owners = true)
+        .with_user();
+    let other = app.db_new_user("other");
 
     app.db(|conn| {
-        let c1 = CrateBuilder::new("foo_deps", user.id).expect_build(conn);
-        let v = VersionBuilder::new("1.0.0").expect_build(c1.id, user.id, conn);
-        let c2 = CrateBuilder::new("bar_deps", user.id).expect_build(conn);
-        new_dependency(conn, &v, &c2);
+        let krate = CrateBuilder::new("foo_anomalies", user.as_model().id).expect_build(conn);
+        let date = Utc::today().naive_utc();
+        DownloadAnomaly::record(conn, krate.id, date, 50_000, 120.0, 450.5).unwrap();
     });
 
-    let deps: Deps = anon
-        .get("/api/v1/crates/foo_deps/1.0.0/dependencies")
-        .good();
-    assert_eq!(deps.dependencies[0].crate_id, "bar_deps");
+    #[derive(Deserialize)]
+    struct Anomalies {
+        anomalies: Vec<EncodableDownloadAnomaly>,
+    }
 
-    anon.get::<()>("/api/v1/crates/foo_deps/1.0.2/dependencies")
-        .bad_with_status(200);
+    let url = "/api/v1/crates/foo_anomalies/download_anomalies";
+    let json = user.get::<Anomalies>(url).good();
+    assert_eq!(json.anomalies.len(), 1);
+    assert_eq!(json.anomalies[0].downloads, 50_000);
+    assert_eq!(json.anomalies[0].score, 450.5);
+
+    anon.get::<()>(url).assert_forbidden();
+    let json = other.get::<()>(url).bad_with_status(200);
+    assert_eq!(
+        json.errors[0].detail,
+        "only owners of a crate can see its download anomalies"
+    );
 }
 
 #[test]
-fn diesel_not_found_results_in_404() {
-    let (_, _, user) = TestApp::init().with_user();
+fn download_anomalies_are_hidden_unless_enabled() {
+    let (app, _, user) = TestApp::init().with_user();
+    app.db(|conn| {
+        CrateBuilder::new("foo_anomalies", user.as_model().id).expect_build(conn);
+    });
 
-    user.get("/api/v1/crates/foo_following/following")
+    user.get::<()>("/api/v1/crates/foo_anomalies/download_anomalies")
         .assert_not_found();
 }
 
 #[test]
-fn following() {
-    // TODO: Test anon requests as well?
-    let (app, _, user) = TestApp::init().with_user();
+fn custom_links() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let other = app.db_new_user("other");
 
     app.db(|conn| {
-        CrateBuilder::new("foo_following", user.as_model().id).expect_build(conn);
+        CrateBuilder::new("foo_links", user.as_model().id).expect_build(conn);
     });
 
-    let is_following = || -> bool {
-        #[derive(Deserialize)]
-        struct F {
-            following: bool,
-        }
-
-        user.get::<F>("/api/v1/crates/foo_following/following")
-            .good()
-            .following
-    };
+    #[derive(Deserialize)]
+    struct Links {
+        custom_links: EncodableCustomLinks,
+    }
+    #[derive(Deserialize)]
+    struct History {
+        custom_link_events: Vec<EncodableCrateLinkEvent>,
+    }
 
-    let follow = || {
-        assert!(
-            user.put::<OkBool>("/api/v1/crates/foo_following/follow", b"")
-                .good()
-                .ok
-        );
-    };
+    let url = "/api/v1/crates/foo_links/custom_links";
+    let body =
+        br#"{"funding": "https://github.com/sponsors/foo", "chat": "https://discord.gg/foo"}"#;
+    let json = user.put::<Links>(url, body).good();
+    assert_eq!(
+        json.custom_links.funding.as_deref(),
+        Some("https://github.com/sponsors/foo")
+    );
+    assert_eq!(
+        anon.show_crate("foo_links").krate.custom_links,
+        Some(json.custom_links)
+    );
 
-    let unfollow = || {
-        assert!(
-            user.delete::<OkBool>("api/v1/crates/foo_following/follow")
-                .good()
-                .ok
-        );
-    };
+    // Unchanged links aren't recorded again
+    let body = br#"{"funding": "https://github.com/sponsors/foo", "security_policy": "https://example.com/SECURITY.md"}"#;
+    user.put::<Links>(url, body).good();
+    let json = anon
+        .get::<History>("/api/v1/crates/foo_links/custom_links/history")
+        .good();
+    let events = json
+        .custom_link_events
+        .iter()
+        .map(|event| (event.kind.as_str(), event.url.as_deref()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        events,
+        vec![
+            ("funding", Some("https://github.com/sponsors/foo")),
+            ("chat", Some("https://discord.gg/foo")),
+            ("chat", None),
+            ("security_policy", Some("https://example.com/SECURITY.md")),
+        ]
+    );
+    assert_eq!(json.custom_link_events[0].user.login, "foo");
 
-    assert!(!is_following());
-    follow();
-    follow();
-    assert!(is_following());
-    assert_eq!(user.search("following=1").crates.len(), 1);
+    let json = user
+        .put::<Links>(url, br#"{"chat": "https://example.com/chat"}"#)
+        .bad_with_status(200);
+    assert!(json.errors[0]
+        .detail
+        .contains("the chat link must point to"));
 
-    unfollow();
-    unfollow();
-    assert!(!is_following());
-    assert_eq!(user.search("following=1").crates.len(), 0);
+    let json = other
+        .put::<Links>(url, br#"{"funding": "https://example.com"}"#)
+        .bad_with_status(200);
+    assert!(json.errors[0].detail.contains("only owners of a crate"));
 }
 
 #[test]
@@ -1571,6 +3145,36 @@ fn yank_works_as_intended() {
     assert!(!json.version.yanked);
 }
 
+#[test]
+fn yanks_are_not_served_from_the_response_cache() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_cached", user.id)
+            .version("1.0.0")
+            .expect_build(conn);
+    });
+
+    let json = anon.show_crate("foo_cached");
+    assert!(!json.versions[0].yanked);
+
+    // Yanking doesn't touch the crate. Timestamps don't advance within the
+    // test transaction, so move the one of the version explicitly.
+    app.db(|conn| {
+        update(versions::table)
+            .set((
+                versions::yanked.eq(true),
+                versions::updated_at.eq(Utc::now().naive_utc() + chrono::Duration::hours(1)),
+            ))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let json = anon.show_crate("foo_cached");
+    assert!(json.versions[0].yanked);
+}
+
 #[test]
 fn yank_by_a_non_owner_fails() {
     let (app, _, _, token) = TestApp::full().with_token();
@@ -1590,6 +3194,196 @@ fn yank_by_a_non_owner_fails() {
     );
 }
 
+#[test]
+fn yanks_are_listed_with_reasons() {
+    use cargo_registry::views::EncodableYank;
+    use conduit::Method;
+
+    #[derive(Deserialize)]
+    struct YanksResponse {
+        yanks: Vec<EncodableYank>,
+        meta: CrateMeta,
+    }
+
+    let (app, anon, _, token) = TestApp::full().with_token();
+    token
+        .enqueue_publish(PublishBuilder::new("fyk_feed"))
+        .good();
+
+    let mut request = token.request_builder(Method::Delete, "/api/v1/crates/fyk_feed/1.0.0/yank");
+    request.with_query("reason=see%20%3Cb%20onclick%3Dx%3E**RUSTSEC-2020-0001**%3C/b%3E");
+    token.run::<OkBool>(request).good();
+    app.run_pending_background_jobs();
+    token.unyank("fyk_feed", "1.0.0").good();
+
+    let json: YanksResponse = anon
+        .get_with_query("/api/v1/yanks", "since=2000-01-01T00:00:00Z")
+        .good();
+    assert_eq!(json.meta.total, 2);
+    assert_eq!(json.yanks[0].krate, "fyk_feed");
+    assert_eq!(json.yanks[0].num, "1.0.0");
+    assert!(json.yanks[0].yanked);
+    assert_eq!(
+        json.yanks[0].reason.as_deref(),
+        Some("see <b onclick=x>**RUSTSEC-2020-0001**</b>")
+    );
+    assert_eq!(
+        json.yanks[0].reason_html.as_deref(),
+        Some("see <b><strong>RUSTSEC-2020-0001</strong></b>")
+    );
+    assert!(!json.yanks[1].yanked);
+    assert_eq!(json.yanks[1].reason, None);
+
+    let json: YanksResponse = anon
+        .get_with_query("/api/v1/yanks", "since=2100-01-01T00:00:00Z")
+        .good();
+    assert_eq!(json.meta.total, 0);
+
+    let json = anon
+        .get_with_query::<()>("/api/v1/yanks", "since=yesterday")
+        .bad_with_status(400);
+    assert_eq!(
+        json.errors[0].detail,
+        "invalid `since` value: `yesterday`, expected an RFC 3339 timestamp"
+    );
+    anon.get::<()>("/api/v1/yanks").bad_with_status(400);
+}
+
+#[test]
+fn deleted_crates_are_listed_with_reasons() {
+    use cargo_registry::models::DeletedCrate;
+    use cargo_registry::views::EncodableDeletedCrate;
+
+    #[derive(Deserialize)]
+    struct DeletedCratesResponse {
+        deleted_crates: Vec<EncodableDeletedCrate>,
+        meta: CrateMeta,
+    }
+
+    let (app, anon) = TestApp::init().empty();
+    app.db(|conn| {
+        DeletedCrate::record(conn, "foo_malware", None, "malware").unwrap();
+        DeletedCrate::record(conn, "foo_legal", Some("1.0.0"), "legal").unwrap();
+    });
+
+    let json: DeletedCratesResponse = anon.get("/api/v1/deleted_crates").good();
+    assert_eq!(json.meta.total, 2);
+    assert_eq!(json.deleted_crates[0].krate, "foo_malware");
+    assert_eq!(json.deleted_crates[0].version, None);
+    assert_eq!(json.deleted_crates[0].reason, "malware");
+    assert_eq!(json.deleted_crates[1].version.as_deref(), Some("1.0.0"));
+
+    let json: DeletedCratesResponse = anon
+        .get_with_query("/api/v1/deleted_crates", "since=2100-01-01T00:00:00Z")
+        .good();
+    assert_eq!(json.meta.total, 0);
+    anon.get_with_query::<()>("/api/v1/deleted_crates", "since=yesterday")
+        .bad_with_status(400);
+}
+
+#[test]
+fn yank_flapping_is_rate_limited() {
+    use cargo_registry::views::EncodableYankEvent;
+
+    #[derive(Deserialize)]
+    struct YankHistory {
+        yank_events: Vec<EncodableYankEvent>,
+    }
+
+    let (app, anon, user, token) = TestApp::full()
+        .with_config(|config| config.yank_cooldown = Duration::from_secs(60))
+        .with_token();
+    token
+        .enqueue_publish(PublishBuilder::new("fyk_flap"))
+        .good();
+
+    token.yank("fyk_flap", "1.0.0").good();
+    token.unyank("fyk_flap", "1.0.0").bad_with_status(429);
+    // Repeating the previous transition isn't flapping
+    token.yank("fyk_flap", "1.0.0").good();
+
+    let crates = app.crates_from_index_head("fy/k_/fyk_flap");
+    assert!(crates[0].yanked.unwrap());
+
+    let history: YankHistory = anon
+        .get("/api/v1/crates/fyk_flap/1.0.0/yank_history")
+        .good();
+    assert_eq!(history.yank_events.len(), 1);
+    assert!(history.yank_events[0].yanked);
+    assert_eq!(history.yank_events[0].user.login, user.as_model().gh_login);
+}
+
+#[test]
+fn yank_bulk() {
+    use cargo_registry::views::EncodableBulkYankResult;
+
+    #[derive(Deserialize)]
+    struct BulkYank {
+        results: Vec<EncodableBulkYankResult>,
+    }
+
+    let (app, anon, _, token) = TestApp::full().with_token();
+    for version in &["1.0.0", "1.1.0", "2.0.0"] {
+        token
+            .enqueue_publish(PublishBuilder::new("fyk_bulk").version(version))
+            .good();
+    }
+    app.run_pending_background_jobs();
+
+    let url = "/api/v1/crates/fyk_bulk/yank_bulk";
+    let json: BulkYank = token
+        .post(url, br#"{"yanked": true, "range": "<2.0.0"}"#)
+        .good();
+    app.run_pending_background_jobs();
+    let results = json
+        .results
+        .iter()
+        .map(|r| (r.num.as_str(), r.status.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(results, [("1.0.0", "yanked"), ("1.1.0", "yanked")]);
+
+    let crates = app.crates_from_index_head("fy/k_/fyk_bulk");
+    let yanked = crates
+        .iter()
+        .map(|c| (c.vers.as_str(), c.yanked.unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(yanked, [("1.0.0", true), ("1.1.0", true), ("2.0.0", false)]);
+    assert!(anon.show_version("fyk_bulk", "1.1.0").version.yanked);
+
+    let json: BulkYank = token
+        .post(
+            url,
+            br#"{"yanked": false, "versions": ["1.0.0", "2.0.0", "1.0.0", "3.0.0"]}"#,
+        )
+        .good();
+    app.run_pending_background_jobs();
+    let results = json
+        .results
+        .iter()
+        .map(|r| (r.num.as_str(), r.status.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        results,
+        [
+            ("1.0.0", "unyanked"),
+            ("2.0.0", "unchanged"),
+            ("3.0.0", "not_found")
+        ]
+    );
+    assert!(!anon.show_version("fyk_bulk", "1.0.0").version.yanked);
+    assert!(anon.show_version("fyk_bulk", "1.1.0").version.yanked);
+
+    token
+        .post::<()>(
+            url,
+            br#"{"yanked": true, "range": "<2.0.0", "versions": ["1.0.0"]}"#,
+        )
+        .bad_with_status(400);
+    token
+        .post::<()>(url, br#"{"yanked": true, "range": "not a range"}"#)
+        .bad_with_status(400);
+}
+
 #[test]
 fn yank_max_version() {
     let (_, anon, _, token) = TestApp::full().with_token();
@@ -2080,6 +3874,123 @@ fn reverse_dependencies_query_supports_u64_version_number_parts() {
     assert_eq!(deps.versions[0].num, large_but_valid_version_number);
 }
 
+#[test]
+fn new_krate_normalizes_its_license() {
+    let (app, _, _, token) = TestApp::init().with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo_spdx").license("MIT/Apache-2.0");
+    token.enqueue_publish(crate_to_publish).good();
+
+    let (license, expression, ids) = app.db(|conn| {
+        versions::table
+            .inner_join(crates::table)
+            .filter(crates::name.eq("foo_spdx"))
+            .select((
+                versions::license,
+                versions::license_expression,
+                versions::license_ids,
+            ))
+            .first::<(Option<String>, Option<String>, Option<Vec<String>>)>(conn)
+            .unwrap()
+    });
+    assert_eq!(license.as_deref(), Some("MIT/Apache-2.0"));
+    assert_eq!(expression.as_deref(), Some("MIT OR Apache-2.0"));
+    assert_eq!(ids, Some(vec!["Apache-2.0".into(), "MIT".into()]));
+
+    let crate_to_publish = PublishBuilder::new("foo_spdx_invalid").license("(MIT OR Apache-2.0");
+    let json = token.enqueue_publish(crate_to_publish).bad_with_status(200);
+    assert!(
+        json.errors[0].detail.contains("http://spdx.org/licenses/"),
+        "{:?}",
+        json.errors
+    );
+}
+
+#[test]
+fn new_krate_warns_about_undeclared_license_files() {
+    let (_, app, token) = crate::storage::memory_storage_app();
+
+    let license: &[u8] = b"Permission is hereby granted, free of charge, to any person";
+    let crate_to_publish = PublishBuilder::new("foo_license_files")
+        .license("Apache-2.0")
+        .files(&[("foo_license_files-1.0.0/LICENSE", license)]);
+    let json = token.enqueue_publish(crate_to_publish).good();
+
+    let lints = json
+        .warnings
+        .lints
+        .iter()
+        .filter(|lint| lint.lint == "license_mismatch")
+        .collect::<Vec<_>>();
+    assert_eq!(lints.len(), 1);
+    assert_eq!(
+        lints[0].message,
+        "`LICENSE` looks like the MIT license, which the `license` field doesn't mention"
+    );
+
+    let analyses = app.db(|conn| {
+        let version_id = versions::table
+            .inner_join(crates::table)
+            .filter(crates::name.eq("foo_license_files"))
+            .select(versions::id)
+            .first(conn)
+            .unwrap();
+        VersionAnalysis::by_version(conn, version_id).unwrap()
+    });
+    assert_eq!(analyses["license"]["mismatched_files"], json!(["LICENSE"]));
+}
+
+#[test]
+fn new_krate_warns_about_locked_versions_which_cant_be_installed() {
+    let (app, _, user, token) = TestApp::init().with_token();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_locked_dep", user.as_model().id)
+            .version("1.0.0")
+            .version(VersionBuilder::new("1.1.0").yanked(true))
+            .expect_build(conn);
+    });
+
+    let lockfile: &[u8] = br#"
+[[package]]
+name = "foo_lockfile"
+version = "1.0.0"
+
+[[package]]
+name = "foo_locked_dep"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "foo_locked_dep"
+version = "1.1.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "foo_locked_dep"
+version = "2.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+    let crate_to_publish =
+        PublishBuilder::new("foo_lockfile").files(&[("foo_lockfile-1.0.0/Cargo.lock", lockfile)]);
+    let json = token.enqueue_publish(crate_to_publish).good();
+
+    let messages = json
+        .warnings
+        .lints
+        .iter()
+        .filter(|lint| lint.lint.ends_with("_locked_version"))
+        .map(|lint| lint.message.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        [
+            "`Cargo.lock` pins foo_locked_dep 1.1.0, which has been yanked",
+            "`Cargo.lock` pins foo_locked_dep 2.0.0, which doesn't exist on crates.io",
+        ]
+    );
+}
+
 #[test]
 fn author_license_and_description_required() {
     let (_, _, _, token) = TestApp::init().with_token();
@@ -2350,6 +4261,159 @@ fn new_krate_tarball_with_hard_links() {
     );
 }
 
+fn zstd_tarball(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut tarball = Vec::new();
+    {
+        let encoder = t!(zstd::Encoder::new(&mut tarball, 0));
+        let mut ar = tar::Builder::new(encoder);
+        for &(name, data) in files {
+            let mut header = tar::Header::new_gnu();
+            t!(header.set_path(name));
+            header.set_size(data.len() as u64);
+            header.set_cksum();
+            t!(ar.append(&header, data));
+        }
+        t!(ar.into_inner().and_then(zstd::Encoder::finish));
+    }
+    tarball
+}
+
+#[test]
+fn new_krate_zstd_tarball_rejected_by_default() {
+    let (app, _, _, token) = TestApp::init().with_token();
+
+    let tarball = zstd_tarball(&[("foo_zstd-1.0.0/src/lib.rs", b"")]);
+    let crate_to_publish = PublishBuilder::new("foo_zstd").tarball(tarball);
+    let json = token.enqueue_publish(crate_to_publish).bad_with_status(200);
+    assert_eq!(
+        json.errors[0].detail,
+        "zstd compressed crate files aren't accepted by this registry, \
+         please compress the crate file with gzip"
+    );
+
+    let count = app.db(|conn| versions::table.count().get_result::<i64>(conn).unwrap());
+    assert_eq!(count, 0);
+}
+
+#[test]
+fn zstd_tarballs_can_be_inspected() {
+    let (app, _, user) = TestApp::init().with_user();
+    let krate = app.db(|conn| CrateBuilder::new("foo_zstd", user.as_model().id).expect_build(conn));
+    let vers = semver::Version::parse("1.0.0").unwrap();
+
+    let crate_file = zstd_tarball(&[
+        ("foo_zstd-1.0.0/src/lib.rs", b""),
+        ("foo_zstd-1.0.0/tests/it.rs", b""),
+    ]);
+    assert_eq!(
+        uploaders::detect_compression(&crate_file),
+        CompressionFormat::Zstd
+    );
+    let contents = uploaders::inspect_crate_file(
+        &krate,
+        &vers,
+        &crate_file,
+        u64::max_value(),
+        &mut tarball::default_analyzers(),
+    )
+    .unwrap();
+    assert_eq!(contents.compression, CompressionFormat::Zstd);
+    assert!(contents.has_tests);
+}
+
+#[test]
+fn flagged_versions_are_held_back_from_the_index() {
+    let storage = Arc::new(MemoryStorage::default());
+    let (app, _, _, token) = TestApp::init()
+        .with_git_index()
+        .with_job_runner()
+        .with_config(|config| {
+            config.uploader = Uploader::new(storage);
+            config.hold_flagged_versions = true;
+        })
+        .with_token();
+
+    let lib = b"pub fn foo() {}\n";
+    let elf = b"\x7fELF\x02\x01\x01\0";
+    let crate_to_publish = PublishBuilder::new("foo_held").files(&[
+        ("foo_held-1.0.0/src/lib.rs", &lib[..]),
+        ("foo_held-1.0.0/bin/tool", &elf[..]),
+    ]);
+    let json = token.enqueue_publish(crate_to_publish).good();
+    assert_eq!(json.warnings.other.len(), 1);
+    assert!(json.warnings.other[0].contains("once it has been reviewed"));
+    let json = token
+        .enqueue_publish(PublishBuilder::new("foo_clean"))
+        .good();
+    assert!(json.warnings.other.is_empty());
+    app.run_pending_background_jobs();
+
+    let tree = app
+        .upstream_repository()
+        .head()
+        .unwrap()
+        .peel_to_tree()
+        .unwrap();
+    assert!(tree.get_path(Path::new("fo/o_/foo_held")).is_err());
+    assert_eq!(app.crates_from_index_head("fo/o_/foo_clean").len(), 1);
+
+    app.db(|conn| {
+        let version_id = versions::table
+            .inner_join(crates::table)
+            .filter(crates::name.eq("foo_held"))
+            .select(versions::id)
+            .first::<i32>(conn)
+            .unwrap();
+        let findings = VersionScanResult::by_version(conn, version_id).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].path, "bin/tool");
+        assert_eq!(findings[0].kind, "elf");
+
+        // What `crates-io-admin scan-review foo_held 1.0.0 release` does
+        let hold = ScanHold::release(conn, version_id).unwrap().unwrap();
+        assert_eq!(hold.publish_job_id, None);
+        let entry = serde_json::from_value::<git::Crate>(hold.index_entry).unwrap();
+        git::add_crate(entry).enqueue_versioned(conn).unwrap();
+        assert_eq!(ScanHold::release(conn, version_id).unwrap(), None);
+    });
+    app.run_pending_background_jobs();
+
+    let crates = app.crates_from_index_head("fo/o_/foo_held");
+    assert_eq!(crates[0].vers, "1.0.0");
+}
+
+#[test]
+fn new_krate_records_its_files() {
+    let (_, _, token) = crate::storage::memory_storage_app();
+
+    let lib = b"pub fn foo() {}\n";
+    let readme = b"# foo_files\n";
+    let crate_to_publish = PublishBuilder::new("foo_files").files(&[
+        ("foo_files-1.0.0/src/lib.rs", &lib[..]),
+        ("foo_files-1.0.0/README.md", &readme[..]),
+    ]);
+    token.enqueue_publish(crate_to_publish).good();
+
+    let json: serde_json::Value = token.get("/api/v1/crates/foo_files/1.0.0/files").good();
+    assert_eq!(
+        json,
+        json!({
+            "files": [
+                {
+                    "path": "README.md",
+                    "size": readme.len(),
+                    "sha256": hex::encode(openssl::sha::sha256(readme))
+                },
+                {
+                    "path": "src/lib.rs",
+                    "size": lib.len(),
+                    "sha256": hex::encode(openssl::sha::sha256(lib))
+                }
+            ]
+        })
+    );
+}
+
 #[test]
 fn publish_new_crate_rate_limited() {
     let (app, anon, _, token) = TestApp::full()
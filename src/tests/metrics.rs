@@ -0,0 +1,84 @@
+use cargo_registry::models::{IndexSync, Version};
+use cargo_registry::schema::{index_syncs, versions};
+use conduit::Method;
+use diesel::dsl::{now, IntervalDsl};
+use diesel::prelude::*;
+
+use crate::builders::CrateBuilder;
+use crate::util::{MockAnonymousUser, RequestHelper, Response, TestApp};
+
+const TOKEN: &str = "scraper-token";
+
+fn scrape(anon: &MockAnonymousUser, authorization: &str) -> Response<()> {
+    let mut req = anon.request_builder(Method::Get, "/api/private/metrics/index-sync");
+    req.header("Authorization", authorization);
+    anon.run(req)
+}
+
+#[test]
+fn metrics_are_only_served_with_the_configured_token() {
+    let (_, anon) = TestApp::init().empty();
+    scrape(&anon, &format!("Bearer {}", TOKEN)).assert_not_found();
+
+    let (_, anon) = TestApp::init()
+        .with_config(|config| config.metrics_authorization_token = Some(TOKEN.into()))
+        .empty();
+    scrape(&anon, "Bearer wrong-token").assert_forbidden();
+    scrape(&anon, "").assert_forbidden();
+    scrape(&anon, &format!("Bearer {}", TOKEN)).assert_status(200);
+}
+
+#[test]
+fn index_sync_durations_and_lag_are_reported() {
+    let (app, anon, user) = TestApp::init()
+        .with_config(|config| config.metrics_authorization_token = Some(TOKEN.into()))
+        .with_user();
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo_synced", user.as_model().id)
+            .version("1.0.0")
+            .version("1.1.0")
+            .expect_build(conn);
+        let version_ids = Version::belonging_to(&krate)
+            .select(versions::id)
+            .order(versions::id)
+            .load::<i32>(conn)
+            .unwrap();
+        for &version_id in &version_ids {
+            IndexSync::queued(conn, version_id).unwrap();
+        }
+        // The first version took 3 seconds to be indexed, the second one is
+        // still waiting, and failed once.
+        diesel::update(index_syncs::table.find(version_ids[0]))
+            .set(index_syncs::queued_at.eq(now - 3.seconds()))
+            .execute(conn)
+            .unwrap();
+        IndexSync::record_indexed(conn, "foo_synced", "1.0.0", true).unwrap();
+        IndexSync::record_indexed(conn, "foo_synced", "1.1.0", false).unwrap();
+        diesel::update(index_syncs::table.find(version_ids[1]))
+            .set(index_syncs::queued_at.eq(now - 1.minute()))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let resp = scrape(&anon, &format!("Bearer {}", TOKEN));
+    resp.assert_status(200);
+    assert_eq!(
+        resp.header("Content-Type"),
+        Some("text/plain; version=0.0.4; charset=utf-8")
+    );
+    let text = resp.into_text();
+    assert!(text.contains("crates_io_index_sync_index_duration_seconds_bucket{le=\"1\"} 0\n"));
+    assert!(text.contains("crates_io_index_sync_index_duration_seconds_bucket{le=\"5\"} 1\n"));
+    assert!(text.contains("crates_io_index_sync_index_duration_seconds_count 1\n"));
+    assert!(text.contains("crates_io_index_sync_visibility_duration_seconds_count 0\n"));
+    assert!(text.contains("crates_io_index_sync_failures_total{stage=\"index\"} 1\n"));
+    assert!(text.contains("crates_io_index_sync_failures_total{stage=\"visibility\"} 0\n"));
+
+    let lag = text
+        .lines()
+        .find(|line| line.starts_with("crates_io_index_sync_index_lag_seconds "))
+        .and_then(|line| line.split(' ').nth(1))
+        .and_then(|value| value.parse::<f64>().ok())
+        .unwrap();
+    assert!(lag >= 60.0, "unexpected lag {}", lag);
+}
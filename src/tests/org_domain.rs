@@ -0,0 +1,108 @@
+use crate::{
+    add_team_to_crate, builders::CrateBuilder, new_team, CrateResponse, OwnerTeamsResponse,
+    RequestHelper, TestApp,
+};
+use cargo_registry::models::OrgDomain;
+
+use serde_json::Value;
+
+#[test]
+fn domains_can_only_be_verified_for_orgs_owning_crates() {
+    let (_, _, _, token) = TestApp::init().with_token();
+
+    let body = br#"{ "domain": "example.com" }"#;
+    let json = token
+        .put::<()>("/api/v1/orgs/crates-test-org/domains", body)
+        .bad_with_status(200);
+    assert_eq!(
+        json.errors[0].detail,
+        "no team of `crates-test-org` owns crates, so its domains can't be verified"
+    );
+}
+
+#[test]
+fn invalid_domains_are_rejected() {
+    let (_, _, _, token) = TestApp::init().with_token();
+
+    let body = br#"{ "domain": "https://example.com/" }"#;
+    let json = token
+        .put::<()>("/api/v1/orgs/crates-test-org/domains", body)
+        .bad_with_status(400);
+    assert_eq!(
+        json.errors[0].detail,
+        "`https://example.com/` is not a valid domain"
+    );
+}
+
+#[test]
+fn domains_are_verified_by_their_txt_record() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    let domain = app.db(|conn| {
+        let domain = OrgDomain::request(conn, "crates-test-org", "example.com", user.id).unwrap();
+        let expected = domain.challenge_record_value();
+
+        let lookup_failed = domain.check(conn, |_| Err("timeout".into())).unwrap();
+        assert_eq!(lookup_failed, None);
+        let wrong_record = domain
+            .check(conn, |_| Ok(vec!["crates-io-verification=wrong".into()]))
+            .unwrap();
+        assert_eq!(wrong_record, Some(false));
+        let verified = domain
+            .check(conn, |name| {
+                assert_eq!(name, "_crates-io-challenge.example.com");
+                Ok(vec!["v=spf1 -all".into(), expected.clone()])
+            })
+            .unwrap();
+        assert_eq!(verified, Some(true));
+
+        // Requesting the domain again keeps its challenge
+        let again = OrgDomain::request(conn, "crates-test-org", "example.com", user.id).unwrap();
+        assert_eq!(again.challenge, domain.challenge);
+        assert!(again.verified_at.is_some());
+        domain
+    });
+
+    let json: Value = anon.get("/api/v1/orgs/Crates-Test-Org/domains").good();
+    let domains = json["domains"].as_array().unwrap();
+    assert_eq!(domains.len(), 1);
+    assert_eq!(domains[0]["domain"], "example.com");
+    assert_eq!(
+        domains[0]["txt_record_value"],
+        domain.challenge_record_value()
+    );
+    assert!(domains[0]["verified_at"].is_string());
+}
+
+#[test]
+fn crates_of_verified_orgs_show_their_domain() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo_verified", user.id).expect_build(conn);
+        let team = new_team("github:crates-test-org:core")
+            .create_or_update(conn)
+            .unwrap();
+        add_team_to_crate(&team, &krate, user, conn).unwrap();
+        CrateBuilder::new("foo_unverified", user.id).expect_build(conn);
+
+        let domain = OrgDomain::request(conn, "crates-test-org", "example.com", user.id).unwrap();
+        let record = domain.challenge_record_value();
+        domain.check(conn, |_| Ok(vec![record])).unwrap();
+        // Pending domains aren't shown
+        OrgDomain::request(conn, "crates-test-org", "example.org", user.id).unwrap();
+    });
+
+    let json: CrateResponse = anon.get("/api/v1/crates/foo_verified").good();
+    assert_eq!(
+        json.krate.verified_publisher.as_deref(),
+        Some("example.com")
+    );
+    let json: OwnerTeamsResponse = anon.get("/api/v1/crates/foo_verified/owner_team").good();
+    assert_eq!(json.teams[0].verified_domains, vec!["example.com"]);
+
+    let json: CrateResponse = anon.get("/api/v1/crates/foo_unverified").good();
+    assert_eq!(json.krate.verified_publisher, None);
+}
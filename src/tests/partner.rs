@@ -0,0 +1,160 @@
+use cargo_registry::models::{
+    security_partner::{self, CONFIRMED, DISMISSED},
+    SecurityPartner, Version, VersionVerdict,
+};
+use cargo_registry::schema::versions;
+use cargo_registry::views::EncodableVerdict;
+use conduit::Method;
+use diesel::prelude::*;
+
+use crate::builders::CrateBuilder;
+use crate::util::{MockAnonymousUser, RequestHelper, Response, TestApp};
+
+const CHECKSUM: &str = "c0ffee00c0ffee00c0ffee00c0ffee00c0ffee00c0ffee00c0ffee00c0ffee00";
+
+#[derive(Deserialize)]
+struct VerdictResponse {
+    verdict: EncodableVerdict,
+}
+
+fn submit(
+    anon: &MockAnonymousUser,
+    api_key: &str,
+    version: &str,
+    checksum: &str,
+    verdict: &str,
+) -> Response<VerdictResponse> {
+    let body = json!({
+        "crate": "foo_scanned",
+        "version": version,
+        "checksum": checksum,
+        "verdict": verdict,
+        "details": "found by a test"
+    });
+    let mut req = anon.request_builder(Method::Post, "/api/v1/partners/verdicts");
+    req.header("X-Partner-Key", api_key);
+    req.with_body(body.to_string().as_bytes());
+    anon.run(req)
+}
+
+fn setup(
+    verdicts_per_day: i32,
+    auto_quarantine: bool,
+) -> (TestApp, MockAnonymousUser, SecurityPartner) {
+    let (app, anon, user) = TestApp::init().with_user();
+    let partner = app.db(|conn| {
+        let krate = CrateBuilder::new("foo_scanned", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+        let version_id = Version::belonging_to(&krate)
+            .select(versions::id)
+            .first::<i32>(conn)
+            .unwrap();
+        Version::record_checksum(version_id, CHECKSUM, conn).unwrap();
+        SecurityPartner::create(
+            conn,
+            "scanner",
+            "scanner@example.com",
+            verdicts_per_day,
+            auto_quarantine,
+        )
+        .unwrap()
+    });
+    (app, anon, partner)
+}
+
+#[test]
+fn verdicts_are_pinned_to_the_checksum() {
+    let (app, anon, partner) = setup(100, false);
+
+    submit(&anon, "not-a-key", "1.0.0", CHECKSUM, "suspicious").assert_forbidden();
+    submit(&anon, &partner.api_key, "2.0.0", CHECKSUM, "suspicious").assert_not_found();
+    submit(
+        &anon,
+        &partner.api_key,
+        "1.0.0",
+        &"0".repeat(64),
+        "suspicious",
+    )
+    .assert_status(400);
+    submit(&anon, &partner.api_key, "1.0.0", CHECKSUM, "dubious").assert_status(400);
+
+    let json = submit(&anon, &partner.api_key, "1.0.0", CHECKSUM, "suspicious").good();
+    assert_eq!(json.verdict.status, "pending");
+    assert!(!json.verdict.quarantined);
+
+    let json = submit(&anon, &partner.api_key, "1.0.0", CHECKSUM, "clean").good();
+    assert_eq!(json.verdict.status, "recorded");
+
+    app.db(|conn| {
+        let queue = VersionVerdict::pending(conn).unwrap();
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue[0].1, "scanner");
+        assert_eq!(queue[0].2, "foo_scanned");
+
+        let actions = partner
+            .audit_log(conn, 10)
+            .unwrap()
+            .into_iter()
+            .map(|(action, _, _)| action)
+            .collect::<Vec<_>>();
+        assert_eq!(actions.len(), 5);
+        assert!(actions.contains(&"unknown_version".to_string()));
+        assert!(actions.contains(&"checksum_mismatch".to_string()));
+        assert!(actions.contains(&"invalid_request".to_string()));
+    });
+}
+
+#[test]
+fn malicious_verdicts_can_quarantine_versions() {
+    let (app, anon, partner) = setup(100, true);
+    let url = "/api/v1/crates/foo_scanned/1.0.0/download";
+
+    let json = submit(&anon, &partner.api_key, "1.0.0", CHECKSUM, "malicious").good();
+    assert!(json.verdict.quarantined);
+    anon.get::<()>(url).assert_status(400);
+
+    app.db(|conn| {
+        let now = chrono::Utc::now().naive_utc();
+        let verdict = VersionVerdict::review(conn, json.verdict.id, DISMISSED, now).unwrap();
+        assert!(verdict.is_some());
+        assert!(
+            VersionVerdict::review(conn, json.verdict.id, CONFIRMED, now)
+                .unwrap()
+                .is_none()
+        );
+    });
+    anon.get::<()>(url)
+        .assert_redirect_ends_with("/crates/foo_scanned/foo_scanned-1.0.0.crate");
+}
+
+#[test]
+fn quarantines_invalidate_cached_downloads() {
+    let (app, anon, partner) = setup(100, true);
+    let url = "/api/v1/crates/foo_scanned/1.0.0/download";
+    anon.get::<()>(url).assert_status(302);
+
+    // Quarantining the version directly doesn't invalidate the cache.
+    app.db(|conn| {
+        let version_id = versions::table
+            .select(versions::id)
+            .first::<i32>(conn)
+            .unwrap();
+        security_partner::quarantine(conn, version_id, None).unwrap();
+    });
+    anon.get::<()>(url).assert_status(302);
+
+    let json = submit(&anon, &partner.api_key, "1.0.0", CHECKSUM, "malicious").good();
+    assert!(json.verdict.quarantined);
+    anon.get::<()>(url).assert_status(400);
+}
+
+#[test]
+fn partners_have_a_daily_quota() {
+    let (_, anon, partner) = setup(1, false);
+
+    submit(&anon, &partner.api_key, "1.0.0", CHECKSUM, "clean").good();
+    let resp = submit(&anon, &partner.api_key, "1.0.0", CHECKSUM, "clean");
+    resp.assert_status(429);
+    assert!(resp.header("Retry-After").is_some());
+}
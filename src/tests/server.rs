@@ -1,3 +1,4 @@
+use cargo_registry::middleware::cors::{AllowedOrigins, CorsConfig, CorsPolicy};
 use conduit::Method;
 
 use crate::builders::*;
@@ -71,3 +72,93 @@ fn block_traffic_via_arbitrary_header_and_value() {
     let resp = anon.run::<()>(req);
     resp.assert_status(302);
 }
+
+fn cors_config() -> CorsConfig {
+    CorsConfig {
+        public: Some(CorsPolicy {
+            origins: AllowedOrigins::Any,
+            methods: vec!["GET".into(), "HEAD".into()],
+            max_age: 600,
+        }),
+        private: Some(CorsPolicy {
+            origins: AllowedOrigins::List(vec!["https://dashboard.example.com".into()]),
+            methods: vec!["GET".into(), "PUT".into(), "DELETE".into()],
+            max_age: 60,
+        }),
+    }
+}
+
+#[test]
+fn cors_public_endpoints_allow_any_origin() {
+    let (_app, anon) = TestApp::init()
+        .with_config(|config| config.cors = cors_config())
+        .empty();
+
+    let mut req = anon.request_builder(Method::Get, "/api/v1/crates");
+    req.header("Origin", "https://stats.example.org");
+    let resp = anon.run::<()>(req);
+    resp.assert_status(200);
+    assert_eq!(resp.header("Access-Control-Allow-Origin"), Some("*"));
+    assert_eq!(resp.header("Access-Control-Allow-Credentials"), None);
+
+    let mut req = anon.request_builder(Method::Options, "/api/v1/crates");
+    req.header("Origin", "https://stats.example.org");
+    req.header("Access-Control-Request-Method", "GET");
+    let resp = anon.run::<()>(req);
+    resp.assert_status(204);
+    assert_eq!(resp.header("Access-Control-Allow-Origin"), Some("*"));
+    assert_eq!(
+        resp.header("Access-Control-Allow-Methods"),
+        Some("GET, HEAD")
+    );
+    assert_eq!(resp.header("Access-Control-Max-Age"), Some("600"));
+
+    // Requests without an `Origin` are left alone
+    let resp = anon.get::<()>("/api/v1/crates");
+    assert_eq!(resp.header("Access-Control-Allow-Origin"), None);
+}
+
+#[test]
+fn cors_private_endpoints_require_a_listed_origin() {
+    let (_app, anon, user) = TestApp::init()
+        .with_config(|config| config.cors = cors_config())
+        .with_user();
+
+    let mut req = user.request_builder(Method::Get, "/api/v1/me");
+    req.header("Origin", "https://dashboard.example.com");
+    let resp = user.run::<()>(req);
+    resp.assert_status(200);
+    assert_eq!(
+        resp.header("Access-Control-Allow-Origin"),
+        Some("https://dashboard.example.com")
+    );
+    assert_eq!(
+        resp.header("Access-Control-Allow-Credentials"),
+        Some("true")
+    );
+    assert_eq!(resp.header("Vary"), Some("Origin"));
+
+    let mut req = anon.request_builder(Method::Options, "/api/v1/crates/foo/1.0.0/yank");
+    req.header("Origin", "https://dashboard.example.com");
+    req.header("Access-Control-Request-Method", "DELETE");
+    let resp = anon.run::<()>(req);
+    resp.assert_status(204);
+    assert_eq!(resp.header("Access-Control-Max-Age"), Some("60"));
+    assert_eq!(
+        resp.header("Access-Control-Allow-Credentials"),
+        Some("true")
+    );
+
+    // The public policy doesn't apply to private endpoints
+    let mut req = user.request_builder(Method::Get, "/api/v1/me");
+    req.header("Origin", "https://stats.example.org");
+    let resp = user.run::<()>(req);
+    resp.assert_status(200);
+    assert_eq!(resp.header("Access-Control-Allow-Origin"), None);
+
+    let mut req = anon.request_builder(Method::Options, "/api/v1/crates/foo/1.0.0/yank");
+    req.header("Origin", "https://stats.example.org");
+    req.header("Access-Control-Request-Method", "DELETE");
+    let resp = anon.run::<()>(req);
+    assert_eq!(resp.header("Access-Control-Allow-Origin"), None);
+}
@@ -0,0 +1,55 @@
+use cargo_registry::models::ServiceConsumer;
+use conduit::Method;
+
+use crate::util::{RequestHelper, TestApp};
+
+#[test]
+fn service_consumers_have_an_hourly_quota() {
+    let (app, anon) = TestApp::init().empty();
+    let consumer =
+        app.db(|conn| ServiceConsumer::create(conn, "scanner", "scanner@example.com", 2).unwrap());
+
+    for remaining in &["1", "0"] {
+        let mut req = anon.request_builder(Method::Get, "/api/v1/crates");
+        req.header("X-Service-Key", &consumer.api_key);
+        let resp = anon.run::<()>(req);
+        resp.assert_status(200);
+        assert_eq!(resp.header("X-RateLimit-Limit"), Some("2"));
+        assert_eq!(resp.header("X-RateLimit-Remaining"), Some(*remaining));
+    }
+
+    let mut req = anon.request_builder(Method::Get, "/api/v1/crates");
+    req.header("X-Service-Key", &consumer.api_key);
+    let resp = anon.run::<()>(req);
+    resp.assert_status(429);
+    assert!(resp.header("Retry-After").is_some());
+
+    // Anonymous requests aren't affected by the quota of a consumer
+    anon.get::<()>("/api/v1/crates").assert_status(200);
+
+    let usage = app.db(|conn| {
+        let since = chrono::Utc::now().naive_utc() - chrono::Duration::hours(1);
+        consumer.usage_since(conn, since).unwrap()
+    });
+    assert_eq!(usage.iter().map(|(_, requests)| requests).sum::<i32>(), 3);
+}
+
+#[test]
+fn service_keys_are_read_only() {
+    let (app, anon) = TestApp::init().empty();
+    let consumer =
+        app.db(|conn| ServiceConsumer::create(conn, "mirror", "mirror@example.com", 100).unwrap());
+
+    let mut req = anon.request_builder(Method::Delete, "/api/v1/crates/foo/1.0.0/yank");
+    req.header("X-Service-Key", &consumer.api_key);
+    anon.run::<()>(req).assert_status(400);
+}
+
+#[test]
+fn unknown_service_keys_are_rejected() {
+    let (_, anon) = TestApp::init().empty();
+
+    let mut req = anon.request_builder(Method::Get, "/api/v1/crates");
+    req.header("X-Service-Key", "not-a-key");
+    anon.run::<()>(req).assert_status(403);
+}
@@ -0,0 +1,257 @@
+use cargo_registry::models::Version;
+use cargo_registry::sigstore::TrustRoot;
+use openssl::asn1::Asn1Time;
+use openssl::ec::{EcGroup, EcKey};
+use openssl::ecdsa::EcdsaSig;
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::{PKey, Private};
+use openssl::sign::Signer;
+use openssl::x509::extension::{BasicConstraints, SubjectAlternativeName};
+use openssl::x509::{X509Builder, X509NameBuilder, X509};
+use serde_json::Value;
+
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{MockAnonymousUser, MockCookieUser, RequestHelper, TestApp};
+use crate::OkBool;
+
+const URL: &str = "/api/v1/crates/foo_signed/1.0.0/signature";
+
+fn ec_key() -> PKey<Private> {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+    PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+}
+
+/// A Fulcio CA and a Rekor log, which sign and log the bundles of the tests.
+struct Sigstore {
+    ca_key: PKey<Private>,
+    ca: X509,
+    log_key: PKey<Private>,
+}
+
+impl Sigstore {
+    fn new() -> Self {
+        let ca_key = ec_key();
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", "fulcio.test").unwrap();
+        let name = name.build();
+        let mut builder = X509Builder::new().unwrap();
+        builder.set_pubkey(&ca_key).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_issuer_name(&name).unwrap();
+        builder
+            .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        let constraints = BasicConstraints::new().critical().ca().build().unwrap();
+        builder.append_extension(constraints).unwrap();
+        builder.sign(&ca_key, MessageDigest::sha256()).unwrap();
+
+        Sigstore {
+            ca_key,
+            ca: builder.build(),
+            log_key: ec_key(),
+        }
+    }
+
+    fn trust_root(&self) -> TrustRoot {
+        let log_key = PKey::public_key_from_der(&self.log_key.public_key_to_der().unwrap());
+        TrustRoot::new(vec![self.ca.clone()], vec![log_key.unwrap()])
+    }
+
+    /// A sigstore bundle signing the given checksum with a fresh certificate.
+    fn bundle(&self, checksum: &str) -> String {
+        let key = ec_key();
+        let mut builder = X509Builder::new().unwrap();
+        builder.set_pubkey(&key).unwrap();
+        builder.set_issuer_name(self.ca.subject_name()).unwrap();
+        builder
+            .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        let san = SubjectAlternativeName::new()
+            .uri("https://github.com/foo/foo/.github/workflows/release.yml@refs/heads/master")
+            .build(&builder.x509v3_context(Some(&self.ca), None))
+            .unwrap();
+        builder.append_extension(san).unwrap();
+        builder.sign(&self.ca_key, MessageDigest::sha256()).unwrap();
+        let certificate = builder.build();
+
+        let digest = hex::decode(checksum).unwrap();
+        let signature = EcdsaSig::sign(&digest, &key.ec_key().unwrap())
+            .unwrap()
+            .to_der()
+            .unwrap();
+
+        let body = json!({
+            "apiVersion": "0.0.1",
+            "kind": "hashedrekord",
+            "spec": {
+                "data": { "hash": { "algorithm": "sha256", "value": checksum } },
+                "signature": {
+                    "content": base64::encode(&signature),
+                    "publicKey": { "content": base64::encode(&certificate.to_pem().unwrap()) }
+                }
+            }
+        });
+        let body = base64::encode(&body.to_string());
+        let key_id = openssl::sha::sha256(&self.log_key.public_key_to_der().unwrap());
+        let integrated_time = chrono::Utc::now().timestamp();
+        // The canonical JSON Rekor signs as the signed entry timestamp
+        let signed_entry = json!({
+            "body": body,
+            "integratedTime": integrated_time,
+            "logID": hex::encode(&key_id),
+            "logIndex": 42
+        });
+        let mut signer = Signer::new(MessageDigest::sha256(), &self.log_key).unwrap();
+        signer.update(signed_entry.to_string().as_bytes()).unwrap();
+        let timestamp = signer.sign_to_vec().unwrap();
+
+        json!({
+            "mediaType": "application/vnd.dev.sigstore.bundle+json;version=0.1",
+            "verificationMaterial": {
+                "x509CertificateChain": {
+                    "certificates": [
+                        { "rawBytes": base64::encode(&certificate.to_der().unwrap()) },
+                        { "rawBytes": base64::encode(&self.ca.to_der().unwrap()) }
+                    ]
+                },
+                "tlogEntries": [{
+                    "logIndex": "42",
+                    "logId": { "keyId": base64::encode(&key_id) },
+                    "integratedTime": integrated_time.to_string(),
+                    "inclusionPromise": { "signedEntryTimestamp": base64::encode(&timestamp) },
+                    "canonicalizedBody": body
+                }]
+            },
+            "messageSignature": {
+                "messageDigest": {
+                    "algorithm": "SHA2_256",
+                    "digest": base64::encode(&digest)
+                },
+                "signature": base64::encode(&signature)
+            }
+        })
+        .to_string()
+    }
+}
+
+/// An app trusting the sigstore, with `foo_signed` 1.0.0 published by the
+/// user.
+fn signing_app(sigstore: &Sigstore) -> (TestApp, MockAnonymousUser, MockCookieUser) {
+    let trust_root = sigstore.trust_root();
+    let (app, anon, user) = TestApp::init()
+        .with_config(|config| config.sigstore_trust_root = Some(trust_root))
+        .with_user();
+    app.db(|conn| {
+        let user = user.as_model();
+        let krate = CrateBuilder::new("foo_signed", user.id).expect_build(conn);
+        let version = VersionBuilder::new("1.0.0").expect_build(krate.id, user.id, conn);
+        Version::record_checksum(version.id, &"a".repeat(64), conn).unwrap();
+    });
+    (app, anon, user)
+}
+
+#[test]
+fn owners_can_sign_versions_once() {
+    let sigstore = Sigstore::new();
+    let (_app, anon, user) = signing_app(&sigstore);
+
+    anon.get::<()>(URL).assert_not_found();
+
+    let bundle = sigstore.bundle(&"a".repeat(64));
+    assert!(user.put::<OkBool>(URL, bundle.as_bytes()).good().ok);
+    let served = anon.get::<Value>(URL).good();
+    assert_eq!(served, serde_json::from_str::<Value>(&bundle).unwrap());
+
+    let json = user.put::<()>(URL, bundle.as_bytes()).bad_with_status(400);
+    assert_eq!(json.errors[0].detail, "foo_signed 1.0.0 is already signed");
+}
+
+#[test]
+fn owners_can_sign_versions_published_by_others() {
+    let sigstore = Sigstore::new();
+    let trust_root = sigstore.trust_root();
+    let (app, _, user) = TestApp::init()
+        .with_config(|config| config.sigstore_trust_root = Some(trust_root))
+        .with_user();
+    let publisher = app.db_new_user("publisher");
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo_signed", user.as_model().id).expect_build(conn);
+        let version =
+            VersionBuilder::new("1.0.0").expect_build(krate.id, publisher.as_model().id, conn);
+        Version::record_checksum(version.id, &"a".repeat(64), conn).unwrap();
+    });
+
+    let bundle = sigstore.bundle(&"a".repeat(64));
+    assert!(user.put::<OkBool>(URL, bundle.as_bytes()).good().ok);
+}
+
+#[test]
+fn bundles_have_to_sign_the_crate_file() {
+    let sigstore = Sigstore::new();
+    let (_app, anon, user) = signing_app(&sigstore);
+
+    let json = user
+        .put::<()>(URL, sigstore.bundle(&"b".repeat(64)).as_bytes())
+        .bad_with_status(400);
+    assert_eq!(
+        json.errors[0].detail,
+        "invalid sigstore bundle: the message digest doesn't match the crate file"
+    );
+    anon.get::<()>(URL).assert_not_found();
+}
+
+#[test]
+fn bundles_have_to_be_signed_by_the_trusted_sigstore() {
+    let sigstore = Sigstore::new();
+    let (_app, anon, user) = signing_app(&sigstore);
+
+    let json = user
+        .put::<()>(URL, Sigstore::new().bundle(&"a".repeat(64)).as_bytes())
+        .bad_with_status(400);
+    assert_eq!(
+        json.errors[0].detail,
+        "invalid sigstore bundle: the certificate wasn't issued by Fulcio"
+    );
+    anon.get::<()>(URL).assert_not_found();
+}
+
+#[test]
+fn bundles_arent_accepted_without_a_trust_root() {
+    let (app, _, user) = TestApp::init().with_user();
+    app.db(|conn| {
+        let user = user.as_model();
+        let krate = CrateBuilder::new("foo_signed", user.id).expect_build(conn);
+        let version = VersionBuilder::new("1.0.0").expect_build(krate.id, user.id, conn);
+        Version::record_checksum(version.id, &"a".repeat(64), conn).unwrap();
+    });
+
+    let json = user
+        .put::<()>(URL, Sigstore::new().bundle(&"a".repeat(64)).as_bytes())
+        .bad_with_status(400);
+    assert_eq!(
+        json.errors[0].detail,
+        "this registry doesn't accept sigstore bundles"
+    );
+}
+
+#[test]
+fn only_owners_can_sign_versions() {
+    let sigstore = Sigstore::new();
+    let (app, _, _) = signing_app(&sigstore);
+
+    let other = app.db_new_user("other");
+    let json = other
+        .put::<()>(URL, sigstore.bundle(&"a".repeat(64)).as_bytes())
+        .bad_with_status(200);
+    assert_eq!(
+        json.errors[0].detail,
+        "must already be an owner to sign a version"
+    );
+}
@@ -0,0 +1,35 @@
+use cargo_registry::schema::sitemaps;
+use diesel::prelude::*;
+
+use crate::util::{RequestHelper, TestApp};
+
+#[test]
+fn index_lists_generated_sitemaps() {
+    let (app, anon) = TestApp::init().empty();
+    app.db(|conn| {
+        diesel::insert_into(sitemaps::table)
+            .values(&vec![
+                (
+                    sitemaps::file_name.eq("crates-1.xml"),
+                    sitemaps::url_count.eq(2),
+                ),
+                (
+                    sitemaps::file_name.eq("keywords-1.xml"),
+                    sitemaps::url_count.eq(1),
+                ),
+            ])
+            .execute(conn)
+            .unwrap();
+    });
+
+    let resp = anon.get::<()>("/sitemap.xml");
+    resp.assert_status(200);
+    assert_eq!(
+        resp.header("Content-Type"),
+        Some("application/xml; charset=utf-8")
+    );
+    let xml = resp.into_text();
+    assert!(xml.starts_with("<?xml"));
+    assert!(xml.contains("/sitemaps/crates-1.xml</loc>"));
+    assert!(xml.contains("/sitemaps/keywords-1.xml</loc>"));
+}
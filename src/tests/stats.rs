@@ -0,0 +1,63 @@
+use crate::{RequestHelper, TestApp};
+use cargo_registry::schema::ecosystem_stats;
+
+use chrono::NaiveDate;
+use diesel::prelude::*;
+use serde_json::Value;
+
+#[test]
+fn ecosystem_stats_are_served_oldest_first() {
+    let (app, anon) = TestApp::init().empty();
+    app.db(|conn| {
+        let day = |day: u32, total_crates: i32, total_users: Option<i32>| {
+            (
+                ecosystem_stats::date.eq(NaiveDate::from_ymd(2020, 3, day)),
+                ecosystem_stats::total_crates.eq(total_crates),
+                ecosystem_stats::new_crates.eq(1),
+                ecosystem_stats::new_versions.eq(3),
+                ecosystem_stats::downloads.eq(1000),
+                ecosystem_stats::total_users.eq(total_users),
+            )
+        };
+        diesel::insert_into(ecosystem_stats::table)
+            .values(&vec![
+                day(3, 12, Some(7)),
+                day(1, 10, None),
+                day(2, 11, Some(5)),
+            ])
+            .execute(conn)
+            .unwrap();
+    });
+
+    let resp = anon.get::<Value>("/api/v1/stats/ecosystem");
+    assert_eq!(resp.header("Cache-Control"), Some("public, max-age=21600"));
+    let json = resp.good();
+    assert_eq!(
+        json["stats"][0],
+        json!({
+            "date": "2020-03-01",
+            "total_crates": 10,
+            "new_crates": 1,
+            "new_versions": 3,
+            "new_users": null,
+            "downloads": 1000,
+        })
+    );
+    assert_eq!(json["stats"][2]["new_users"], 2);
+
+    let json: Value = anon
+        .get_with_query("/api/v1/stats/ecosystem", "since=2020-03-02")
+        .good();
+    let dates = json["stats"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|stats| stats["date"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(dates, vec!["2020-03-02", "2020-03-03"]);
+
+    let json = anon
+        .get_with_query::<()>("/api/v1/stats/ecosystem", "since=yesterday")
+        .bad_with_status(400);
+    assert_eq!(json.errors[0].detail, "`yesterday` is not a date");
+}
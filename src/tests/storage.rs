@@ -0,0 +1,68 @@
+use cargo_registry::{storage::MemoryStorage, views::GoodCrate, Uploader};
+use std::sync::Arc;
+
+use crate::builders::PublishBuilder;
+use crate::util::{MockTokenUser, RequestHelper, TestApp};
+
+/// An app which stores uploads in memory, so that crates can be published
+/// without recorded S3 requests.
+pub fn memory_storage_app() -> (Arc<MemoryStorage>, TestApp, MockTokenUser) {
+    let storage = Arc::new(MemoryStorage::default());
+    let uploader = Uploader::new(storage.clone());
+    let (app, _, _, token) = TestApp::init()
+        .with_git_index()
+        .with_job_runner()
+        .with_config(|config| config.uploader = uploader)
+        .with_token();
+    (storage, app, token)
+}
+
+#[test]
+fn publishing_stores_the_crate_file() {
+    let (storage, _app, token) = memory_storage_app();
+
+    let crate_to_publish = PublishBuilder::new("foo_stored").version("1.0.0");
+    let _: GoodCrate = token.enqueue_publish(crate_to_publish).good();
+
+    let file = storage
+        .get("crates/foo_stored/foo_stored-1.0.0.crate")
+        .unwrap();
+    assert_eq!(file.content_type, "application/x-tar");
+    assert_eq!(
+        file.headers["cache-control"],
+        "public,max-age=31536000,immutable"
+    );
+    // gzip magic bytes
+    assert_eq!(&file.content[..2], &[0x1f, 0x8b]);
+}
+
+#[test]
+fn readmes_are_rendered_into_the_storage() {
+    let (storage, app, token) = memory_storage_app();
+
+    let crate_to_publish = PublishBuilder::new("foo_readme_stored")
+        .version("1.0.0")
+        .readme("hello *world*");
+    let _: GoodCrate = token.enqueue_publish(crate_to_publish).good();
+    app.run_pending_background_jobs();
+
+    let file = storage
+        .get("readmes/foo_readme_stored/foo_readme_stored-1.0.0.html")
+        .unwrap();
+    assert_eq!(file.content_type, "text/html");
+    let html = String::from_utf8(file.content).unwrap();
+    assert!(html.contains("<em>world</em>"), "{}", html);
+}
+
+#[test]
+fn downloads_redirect_to_the_storage() {
+    let (_storage, _app, token) = memory_storage_app();
+
+    let crate_to_publish = PublishBuilder::new("foo_dl_stored").version("1.0.0");
+    let _: GoodCrate = token.enqueue_publish(crate_to_publish).good();
+
+    token
+        .get::<()>("/api/v1/crates/foo_dl_stored/1.0.0/download")
+        .assert_status(302)
+        .assert_redirect_ends_with("memory://crates/foo_dl_stored/foo_dl_stored-1.0.0.crate");
+}
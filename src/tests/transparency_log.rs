@@ -0,0 +1,133 @@
+use cargo_registry::models::{SignedTreeHead, TransparencyLogEntry};
+use cargo_registry::schema::transparency_log_entries;
+use cargo_registry::transparency_log;
+use cargo_registry::views::{EncodableLogEntry, EncodableTreeHead};
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use openssl::ec::{EcGroup, EcKey};
+use openssl::nid::Nid;
+use openssl::pkey::{PKey, Private};
+
+use crate::util::{RequestHelper, TestApp};
+
+#[derive(Deserialize)]
+struct TreeHead {
+    tree_head: EncodableTreeHead,
+}
+
+#[derive(Deserialize)]
+struct Entries {
+    entries: Vec<EncodableLogEntry>,
+}
+
+fn key() -> PKey<Private> {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+    PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+}
+
+fn now() -> NaiveDateTime {
+    Utc::now().naive_utc()
+}
+
+#[test]
+fn tree_heads_cover_the_published_entries() {
+    let (app, anon) = TestApp::init().empty();
+    let key = key();
+
+    anon.get::<()>("/api/v1/transparency-log/tree-head")
+        .assert_not_found();
+
+    app.db(|conn| {
+        TransparencyLogEntry::append(conn, "foo", "1.0.0", &"a".repeat(64)).unwrap();
+        TransparencyLogEntry::append(conn, "bar", "0.1.0", &"b".repeat(64)).unwrap();
+        SignedTreeHead::sign(conn, &key, now()).unwrap();
+        TransparencyLogEntry::append(conn, "foo", "1.0.1", &"c".repeat(64)).unwrap();
+    });
+
+    let head = anon
+        .get::<TreeHead>("/api/v1/transparency-log/tree-head")
+        .good()
+        .tree_head;
+    assert_eq!(head.tree_size, 2);
+
+    // Entries which aren't part of a tree head yet aren't served.
+    let entries = anon
+        .get::<Entries>("/api/v1/transparency-log/entries")
+        .good()
+        .entries;
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].leaf_index, 0);
+    assert_eq!(entries[0].krate, "foo");
+    assert_eq!(entries[1].krate, "bar");
+
+    app.db(|conn| {
+        let head = SignedTreeHead::sign(conn, &key, now()).unwrap();
+        assert_eq!(head.tree_size, 3);
+    });
+
+    let head = anon
+        .get::<TreeHead>("/api/v1/transparency-log/tree-head")
+        .good()
+        .tree_head;
+    let entries = anon
+        .get::<Entries>("/api/v1/transparency-log/entries?start=0&limit=10")
+        .good()
+        .entries;
+    assert_eq!(entries.len(), 3);
+
+    // Auditors can recompute the root hash from the entries.
+    let leaves = entries
+        .iter()
+        .map(|entry| {
+            let data = format!(
+                "{}\n{}\n{}\n{}",
+                entry.krate, entry.num, entry.checksum, entry.published_at
+            );
+            transparency_log::leaf_hash(data.as_bytes())
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        hex::encode(transparency_log::root_hash(&leaves)),
+        head.root_hash
+    );
+
+    let message = format!(
+        "crates.io transparency log\n{}\n{}\n{}\n",
+        head.tree_size, head.root_hash, head.timestamp
+    );
+    let public_key = PKey::public_key_from_pem(head.public_key.as_bytes()).unwrap();
+    let signature = hex::decode(&head.signature).unwrap();
+    assert!(transparency_log::verify(&public_key, &message, &signature).unwrap());
+
+    let entries = anon
+        .get::<Entries>("/api/v1/transparency-log/entries?start=2")
+        .good()
+        .entries;
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].num, "1.0.1");
+
+    anon.get::<()>("/api/v1/transparency-log/entries?limit=1001")
+        .assert_status(400);
+}
+
+#[test]
+fn sequenced_entries_cannot_be_changed() {
+    let (app, _) = TestApp::init().empty();
+
+    app.db(|conn| {
+        TransparencyLogEntry::append(conn, "foo", "1.0.0", &"a".repeat(64)).unwrap();
+        SignedTreeHead::sign(conn, &key(), now()).unwrap();
+
+        let changed = conn.transaction::<_, diesel::result::Error, _>(|| {
+            diesel::update(transparency_log_entries::table)
+                .set(transparency_log_entries::checksum.eq("b".repeat(64)))
+                .execute(conn)
+        });
+        assert!(changed.is_err());
+
+        let deleted = conn.transaction::<_, diesel::result::Error, _>(|| {
+            diesel::delete(transparency_log_entries::table).execute(conn)
+        });
+        assert!(deleted.is_err());
+    });
+}
@@ -6,10 +6,13 @@ use crate::{
 };
 use cargo_registry::{
     models::{Email, NewUser, User},
-    schema::crate_owners,
-    views::{EncodablePrivateUser, EncodablePublicUser, EncodableVersion, OwnedCrate},
+    schema::{crate_owners, version_downloads, versions},
+    views::{
+        EncodableDashboard, EncodablePrivateUser, EncodablePublicUser, EncodableVersion, OwnedCrate,
+    },
 };
 
+use chrono::{Duration, Utc};
 use diesel::prelude::*;
 
 #[derive(Deserialize)]
@@ -139,6 +142,62 @@ fn me() {
     assert_eq!(updated_json.owned_crates.len(), 1);
 }
 
+#[test]
+fn dashboard() {
+    let url = "/api/v1/me/dashboard";
+    let (app, anon, owner, token) = TestApp::init().with_token();
+    anon.get::<()>(url).assert_forbidden();
+
+    let user = app.db_new_user("dashboard_user");
+    app.db(|conn| {
+        let user_id = user.as_model().id;
+        let krate = CrateBuilder::new("foo_dashboard", user_id)
+            .version("1.0.0")
+            .expect_build(conn);
+        let version_id = versions::table
+            .filter(versions::crate_id.eq(krate.id))
+            .select(versions::id)
+            .first::<i32>(conn)
+            .unwrap();
+        let today = Utc::now().naive_utc().date();
+        let rows = vec![(today, 10), (today - Duration::days(10), 4)]
+            .into_iter()
+            .map(|(date, downloads)| {
+                (
+                    version_downloads::version_id.eq(version_id),
+                    version_downloads::date.eq(date),
+                    version_downloads::downloads.eq(downloads),
+                )
+            })
+            .collect::<Vec<_>>();
+        diesel::insert_into(version_downloads::table)
+            .values(&rows)
+            .execute(conn)
+            .unwrap();
+
+        CrateBuilder::new("foo_dashboard_yanked", user_id)
+            .version(VersionBuilder::new("1.0.0").yanked(true))
+            .expect_build(conn);
+        CrateBuilder::new("foo_dashboard_invited", owner.as_model().id).expect_build(conn);
+    });
+    token.add_user_owner("foo_dashboard_invited", user.as_model());
+
+    let json: EncodableDashboard = user.get(url).good();
+    assert_eq!(json.crates.len(), 2);
+    assert_eq!(json.crates[0].name, "foo_dashboard");
+    assert_eq!(json.crates[0].latest_version.as_deref(), Some("1.0.0"));
+    assert_eq!(json.crates[0].weekly_downloads, 10);
+    assert_eq!(json.crates[0].weekly_downloads_delta, 6);
+    assert!(json.crates[0].advisories.is_empty());
+    assert_eq!(json.crates[1].advisories, vec!["latest_version_yanked"]);
+    assert_eq!(json.crate_owner_invitations.len(), 1);
+    assert_eq!(
+        json.crate_owner_invitations[0].crate_name,
+        "foo_dashboard_invited"
+    );
+    assert!(json.action_items.is_empty());
+}
+
 #[test]
 fn show() {
     let (app, anon, _) = TestApp::init().with_user();
@@ -299,6 +358,64 @@ fn following() {
         .bad_with_status(400);
 }
 
+#[test]
+fn user_publishes() {
+    #[derive(Deserialize)]
+    struct R {
+        versions: Vec<EncodableVersion>,
+        meta: Meta,
+    }
+    #[derive(Deserialize)]
+    struct Meta {
+        total: i64,
+        more: bool,
+    }
+
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    let other = app.db_new_user("other").as_model().clone();
+
+    app.db(|conn| {
+        // Versions published to crates the user doesn't own are listed too
+        let krate = CrateBuilder::new("foo_published", other.id).expect_build(conn);
+        VersionBuilder::new("1.0.0").expect_build(krate.id, user.id, conn);
+        VersionBuilder::new("1.1.0").expect_build(krate.id, other.id, conn);
+        let old = VersionBuilder::new("0.1.0").expect_build(krate.id, user.id, conn);
+        diesel::update(&old)
+            .set(versions::created_at.eq(chrono::NaiveDate::from_ymd(2019, 1, 1).and_hms(0, 0, 0)))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let url = format!("/api/v1/users/{}/publishes", user.id);
+    let r: R = anon.get(&url).good();
+    assert_eq!(r.meta.total, 2);
+    assert!(!r.meta.more);
+    let nums = r
+        .versions
+        .iter()
+        .map(|v| v.num.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(nums, vec!["1.0.0", "0.1.0"]);
+    assert!(r
+        .versions
+        .iter()
+        .all(|v| v.published_by.as_ref().unwrap().login == user.gh_login));
+
+    let r: R = anon.get_with_query(&url, "since=2020-01-01").good();
+    assert_eq!(r.meta.total, 1);
+    assert_eq!(r.versions[0].num, "1.0.0");
+
+    let r: R = anon.get_with_query(&url, "per_page=1").good();
+    assert_eq!(r.versions.len(), 1);
+    assert!(r.meta.more);
+
+    anon.get_with_query::<()>(&url, "since=yesterday")
+        .bad_with_status(400);
+    anon.get::<()>("/api/v1/users/0/publishes")
+        .assert_not_found();
+}
+
 #[test]
 fn user_total_downloads() {
     use diesel::update;
@@ -745,3 +862,90 @@ fn test_update_email_notifications_not_owned() {
     // There should be no change to the `email_notifications` value for a crate not belonging to me
     assert!(email_notifications);
 }
+
+#[test]
+fn merging_users_moves_crates_and_revokes_tokens() {
+    use cargo_registry::models::{ApiToken, MergeReport, UserMerge};
+    use cargo_registry::schema::{api_tokens, emails, follows};
+
+    let (app, _) = TestApp::init().empty();
+    let old_account = app.db_new_user("old_login");
+    old_account.db_new_token("laptop");
+    let from = old_account.as_model().clone();
+    let into = app.db(|conn| new_user("new_login").create_or_update(None, conn).unwrap());
+
+    let (only_old, shared) = app.db(|conn| {
+        let only_old = CrateBuilder::new("only_old", from.id).expect_build(conn);
+        let shared = CrateBuilder::new("shared", from.id).expect_build(conn);
+        diesel::insert_into(crate_owners::table)
+            .values((
+                crate_owners::crate_id.eq(shared.id),
+                crate_owners::owner_id.eq(into.id),
+                crate_owners::owner_kind.eq(0),
+            ))
+            .execute(conn)
+            .unwrap();
+        let rows = vec![
+            (
+                follows::user_id.eq(from.id),
+                follows::crate_id.eq(only_old.id),
+            ),
+            (
+                follows::user_id.eq(from.id),
+                follows::crate_id.eq(shared.id),
+            ),
+            (
+                follows::user_id.eq(into.id),
+                follows::crate_id.eq(shared.id),
+            ),
+        ];
+        diesel::insert_into(follows::table)
+            .values(&rows)
+            .execute(conn)
+            .unwrap();
+        (only_old, shared)
+    });
+
+    let (merge, report) = app.db(|conn| {
+        conn.transaction(|| UserMerge::merge(conn, &from, &into))
+            .unwrap()
+    });
+    assert_eq!(report.moved_ownerships, vec![only_old.id]);
+    assert_eq!(report.removed_ownerships, vec![shared.id]);
+    assert_eq!(report.moved_follows, vec![only_old.id]);
+    assert_eq!(report.removed_follows, vec![shared.id]);
+    assert_eq!(report.revoked_tokens.len(), 1);
+    assert!(report.moved_email.is_some());
+    assert_eq!(
+        serde_json::from_value::<MergeReport>(merge.report).unwrap(),
+        report
+    );
+
+    app.db(|conn| {
+        for krate in &[&only_old, &shared] {
+            let owners = crate_owners::table
+                .filter(crate_owners::crate_id.eq(krate.id))
+                .filter(crate_owners::deleted.eq(false))
+                .select(crate_owners::owner_id)
+                .load::<i32>(conn)
+                .unwrap();
+            assert_eq!(owners, vec![into.id]);
+        }
+        let followed = follows::table
+            .filter(follows::user_id.eq(into.id))
+            .count()
+            .get_result::<i64>(conn)
+            .unwrap();
+        assert_eq!(followed, 2);
+        let tokens = api_tokens::table
+            .filter(api_tokens::user_id.eq(from.id))
+            .load::<ApiToken>(conn)
+            .unwrap();
+        assert!(tokens.iter().all(|token| token.revoked));
+        let email_owner = emails::table
+            .select(emails::user_id)
+            .first::<i32>(conn)
+            .unwrap();
+        assert_eq!(email_owner, into.id);
+    });
+}
@@ -103,6 +103,7 @@ impl TestApp {
             bomb: None,
             index: None,
             build_job_runner: false,
+            index_v3_fields: false,
         }
     }
 
@@ -210,6 +211,7 @@ pub struct TestAppBuilder {
     bomb: Option<record::Bomb>,
     index: Option<UpstreamRepository>,
     build_job_runner: bool,
+    index_v3_fields: bool,
 }
 
 impl TestAppBuilder {
@@ -224,6 +226,10 @@ impl TestAppBuilder {
             let repository_config = RepositoryConfig {
                 index_location: Url::from_file_path(&git::bare()).unwrap(),
                 credentials: Credentials::Missing,
+                signing_key: None,
+                v3_fields: self.index_v3_fields,
+                clone_depth: None,
+                sparse_checkout: false,
             };
             let index = WorkerRepository::open(&repository_config).expect("Could not clone index");
             let environment = Environment::new(
@@ -231,6 +237,8 @@ impl TestAppBuilder {
                 connection_pool.clone(),
                 app.config.uploader.clone(),
                 app.http_client().clone(),
+                app.search_backend.clone(),
+                app.config.og_image_renderer.clone(),
             );
 
             Some(
@@ -308,6 +316,12 @@ impl TestAppBuilder {
         self.build_job_runner = true;
         self
     }
+
+    /// Writes index entries with `rust_version` and `yanked_reason`
+    pub fn with_index_v3_fields(mut self) -> Self {
+        self.index_v3_fields = true;
+        self
+    }
 }
 
 /// A collection of helper methods for the 3 authentication types
@@ -354,6 +368,16 @@ pub trait RequestHelper {
         self.run(request)
     }
 
+    /// Issue a POST request
+    fn post<T>(&self, path: &str, body: &[u8]) -> Response<T>
+    where
+        for<'de> T: serde::Deserialize<'de>,
+    {
+        let mut request = self.request_builder(Method::Post, path);
+        request.with_body(body);
+        self.run(request)
+    }
+
     /// Issue a DELETE request
     fn delete<T>(&self, path: &str) -> Response<T>
     where
@@ -619,6 +643,19 @@ where
         assert!(self.response.headers["Location"][0].ends_with(target));
         self
     }
+
+    /// The first value of the given response header
+    pub fn header(&self, name: &str) -> Option<&str> {
+        let values = self.response.headers.get(name)?;
+        values.first().map(String::as_str)
+    }
+
+    /// The body of a response which isn't JSON
+    pub fn into_text(mut self) -> String {
+        let mut body = Vec::new();
+        self.response.body.write_body(&mut body).unwrap();
+        String::from_utf8(body).unwrap()
+    }
 }
 
 impl Response<()> {
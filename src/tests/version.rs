@@ -2,8 +2,17 @@ use crate::{
     builders::{CrateBuilder, PublishBuilder, VersionBuilder},
     RequestHelper, TestApp, VersionResponse,
 };
-use cargo_registry::{models::Version, schema::versions, views::EncodableVersion};
+use cargo_registry::{
+    models::{
+        feature_docs::parse_feature_docs, CompressionFormat, DeprecatedEndpointUsage,
+        ServiceConsumer, Version, VersionFeatureDoc, VersionInstallCheck,
+    },
+    sbom,
+    schema::{readme_renderings, versions},
+    views::EncodableVersion,
+};
 
+use conduit::Method;
 use diesel::prelude::*;
 use serde_json::Value;
 
@@ -64,6 +73,78 @@ fn show_by_id() {
     assert_eq!(json.version.crate_size, Some(1234));
 }
 
+#[test]
+fn versions_advertise_the_compression_of_their_crate_file() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    let v = app.db(|conn| {
+        let krate = CrateBuilder::new("foo_compressed", user.id).expect_build(conn);
+        VersionBuilder::new("1.0.0").expect_build(krate.id, user.id, conn)
+    });
+    let url = "/api/v1/crates/foo_compressed/1.0.0";
+    let json: Value = anon.get(url).good();
+    assert_eq!(json["version"]["compression"], "gzip");
+
+    app.db(|conn| Version::record_compression(v.id, CompressionFormat::Zstd, conn).unwrap());
+    let json: VersionResponse = anon.get(url).good();
+    assert_eq!(json.version.compression, CompressionFormat::Zstd);
+}
+
+#[test]
+fn deprecated_endpoints_announce_their_deprecation() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    let (v, consumer) = app.db(|conn| {
+        let krate = CrateBuilder::new("foo_deprecated", user.id).expect_build(conn);
+        let v = VersionBuilder::new("1.0.0").expect_build(krate.id, user.id, conn);
+        let consumer = ServiceConsumer::create(conn, "mirror", "mirror@example.com", 100).unwrap();
+        (v, consumer)
+    });
+
+    let url = format!("/api/v1/versions/{}", v.id);
+    let resp = anon.get::<Value>(&url);
+    assert_eq!(
+        resp.header("Deprecation"),
+        Some("Wed, 25 Mar 2020 00:00:00 GMT")
+    );
+    assert_eq!(resp.header("Sunset"), None);
+    let json = resp.good();
+    assert_eq!(json["version"]["id"], v.id);
+    let deprecation = &json["meta"]["deprecations"][0];
+    assert_eq!(deprecation["path"], "/api/v1/versions/:version_id");
+    assert_eq!(deprecation["deprecated_on"], "2020-03-25");
+    assert!(deprecation["message"]
+        .as_str()
+        .unwrap()
+        .contains("/api/v1/crates"));
+
+    let mut req = anon.request_builder(Method::Get, &url);
+    req.header("X-Service-Key", &consumer.api_key);
+    anon.run::<()>(req).assert_status(200);
+    anon.get::<()>(&url).assert_status(200);
+
+    let resp = anon.get::<Value>("/api/v1/crates/foo_deprecated/1.0.0");
+    assert_eq!(resp.header("Deprecation"), None);
+    assert!(resp.good()["meta"].get("deprecations").is_none());
+
+    let usage = app.db(|conn| {
+        let today = chrono::Utc::today().naive_utc();
+        DeprecatedEndpointUsage::since(conn, today).unwrap()
+    });
+    let usage = usage
+        .iter()
+        .map(|u| (u.endpoint.as_str(), u.consumer.as_str(), u.requests))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        usage,
+        vec![
+            ("/api/v1/versions/:version_id", "conduit-test", 2),
+            ("/api/v1/versions/:version_id", "mirror", 1),
+        ]
+    );
+}
+
 #[test]
 fn show_by_crate_name_and_semver_with_published_by() {
     let (app, anon, user) = TestApp::init().with_user();
@@ -135,6 +216,76 @@ fn record_rerendered_readme_time() {
     });
 }
 
+#[test]
+fn readme_toc() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    let url = "/api/v1/crates/foo_toc/1.0.0/readme_toc";
+
+    let version = app.db(|conn| {
+        let c = CrateBuilder::new("foo_toc", user.id).expect_build(conn);
+        VersionBuilder::new("1.0.0").expect_build(c.id, user.id, conn)
+    });
+    anon.get::<()>(url).assert_not_found();
+
+    app.db(|conn| {
+        Version::record_readme_rendering(version.id, conn).unwrap();
+    });
+    anon.get::<()>(url).assert_not_found();
+
+    app.db(|conn| {
+        diesel::update(readme_renderings::table.find(version.id))
+            .set(readme_renderings::toc.eq(json!([
+                { "level": 1, "slug": "foo", "title": "Foo" },
+                { "level": 2, "slug": "usage", "title": "Usage" },
+            ])))
+            .execute(conn)
+            .unwrap();
+    });
+    let json: Value = anon.get(url).good();
+    assert_eq!(json["toc"][1]["slug"], "usage");
+    assert_eq!(json["toc"][1]["level"], 2);
+}
+
+#[test]
+fn install_checks_repeat_until_they_pass() {
+    use diesel::dsl::{now, IntervalDsl};
+
+    let (app, _, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let c = CrateBuilder::new("foo_install_check", user.id).expect_build(conn);
+        let published = VersionBuilder::new("1.0.0").expect_build(c.id, user.id, conn);
+        let yanked = VersionBuilder::new("1.1.0")
+            .yanked(true)
+            .expect_build(c.id, user.id, conn);
+        diesel::update(versions::table.filter(versions::id.eq_any(vec![published.id, yanked.id])))
+            .set(versions::created_at.eq(now - 10.minutes()))
+            .execute(conn)
+            .unwrap();
+        // Too recent to be picked up by the CDN
+        VersionBuilder::new("1.2.0").expect_build(c.id, user.id, conn);
+
+        let due = || VersionInstallCheck::due(conn, 5).unwrap();
+        assert_eq!(
+            due(),
+            vec![(published.id, "foo_install_check".into(), "1.0.0".into())]
+        );
+
+        VersionInstallCheck::record(conn, published.id, Err("404 Not Found".into())).unwrap();
+        assert_eq!(due().len(), 1);
+        let failing = VersionInstallCheck::failing(conn).unwrap();
+        assert_eq!(failing.len(), 1);
+        assert_eq!(failing[0].0.error.as_deref(), Some("404 Not Found"));
+
+        VersionInstallCheck::record(conn, published.id, Ok(())).unwrap();
+        assert_eq!(due(), vec![]);
+        let failing = VersionInstallCheck::failing(conn).unwrap();
+        assert_eq!(failing, vec![]);
+    });
+}
+
 #[test]
 fn version_size() {
     let (_, _, user) = TestApp::full().with_user();
@@ -165,3 +316,120 @@ fn version_size() {
         .expect("Could not find v2.0.0");
     assert_eq!(version2.crate_size, Some(91));
 }
+
+#[test]
+fn features_with_descriptions() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let version = VersionBuilder::new("1.0.0")
+            .feature("default", &["std"])
+            .feature("std", &[])
+            .feature("undocumented", &[]);
+        let krate = CrateBuilder::new("foo_features", user.id)
+            .version(version)
+            .expect_build(conn);
+        let version = Version::belonging_to(&krate)
+            .first::<Version>(conn)
+            .unwrap();
+
+        let manifest = "[features]\n\
+            ## Enabled by default.\ndefault = [\"std\"]\n\
+            ## Uses the standard library.\nstd = []\n\
+            ## Not a feature of the published version.\nunknown = []\n";
+        let features = serde_json::from_value(version.features.clone()).unwrap();
+        VersionFeatureDoc::save(conn, version.id, &features, parse_feature_docs(manifest)).unwrap();
+    });
+
+    let json: Value = anon
+        .get("/api/v1/crates/foo_features/1.0.0/features")
+        .good();
+    assert_eq!(
+        json,
+        json!({
+            "features": [
+                {
+                    "name": "default",
+                    "enables": ["std"],
+                    "description": "Enabled by default.",
+                },
+                {
+                    "name": "std",
+                    "enables": [],
+                    "description": "Uses the standard library.",
+                },
+                {
+                    "name": "undocumented",
+                    "enables": [],
+                    "description": null,
+                },
+            ]
+        })
+    );
+}
+
+#[test]
+fn files_of_versions_published_before_they_were_recorded() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    app.db(|conn| {
+        CrateBuilder::new("foo_unlisted", user.id)
+            .version("1.0.0")
+            .expect_build(conn);
+    });
+
+    let json: Value = anon.get("/api/v1/crates/foo_unlisted/1.0.0/files").good();
+    assert_eq!(json, json!({ "files": [] }));
+}
+
+#[test]
+fn sbom_redirects_to_uploaded_document() {
+    let (_, anon) = TestApp::init().empty();
+
+    anon.get::<()>("/api/v1/crates/foo_sbom/1.0.0/sbom")
+        .assert_redirect_ends_with("/crates/foo_sbom/foo_sbom-1.0.0.cdx.json");
+}
+
+#[test]
+fn sbom_lists_declared_dependencies() {
+    let (app, _, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let dependency = CrateBuilder::new("bar_sbom", user.id).expect_build(conn);
+        let krate = CrateBuilder::new("foo_sbom", user.id)
+            .description("A crate with an SBOM")
+            .homepage("https://example.com")
+            .expect_build(conn);
+        let version = VersionBuilder::new("1.2.3")
+            .license(Some("MIT"))
+            .dependency(&dependency, Some("cfg(unix)"))
+            .expect_build(krate.id, user.id, conn);
+        Version::record_checksum(version.id, &"a".repeat(64), conn).unwrap();
+
+        let checksum = Version::checksum(version.id, conn).unwrap();
+        let dependencies = version.dependencies(conn).unwrap();
+        let sbom = sbom::build_sbom(&krate, &version, checksum.as_deref(), &dependencies);
+
+        let component = &sbom["metadata"]["component"];
+        assert_eq!(component["purl"], "pkg:cargo/foo_sbom@1.2.3");
+        assert_eq!(component["licenses"][0]["expression"], "MIT");
+        assert_eq!(component["hashes"][0]["content"], "a".repeat(64));
+        assert_eq!(
+            component["externalReferences"],
+            json!([{ "type": "website", "url": "https://example.com" }])
+        );
+
+        assert_eq!(sbom["components"][0]["name"], "bar_sbom");
+        assert_eq!(sbom["components"][0]["scope"], "required");
+        assert_eq!(
+            sbom["components"][0]["properties"][1],
+            json!({ "name": "cargo:target", "value": "cfg(unix)" })
+        );
+        assert_eq!(
+            sbom["dependencies"][0]["dependsOn"],
+            json!(["pkg:cargo/bar_sbom"])
+        );
+    });
+}
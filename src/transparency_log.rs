@@ -0,0 +1,161 @@
+//! The hashing and signing of the transparency log
+//!
+//! Every published version is appended to the log as a leaf of
+//! `<crate>\n<version>\n<checksum>\n<published at>`, where the checksum is the
+//! hex encoded SHA-256 checksum of the crate file and the time is formatted
+//! as `2020-03-13T14:10:52Z`. The leaves form a Merkle tree, hashed like the
+//! Certificate Transparency logs of RFC 6962, and `sign_transparency_log`
+//! periodically signs the root hash of the whole tree as a tree head.
+//!
+//! Auditors can rebuild the tree from the entries served by the API and
+//! compare its root with the signed tree heads. Since the tree is only ever
+//! appended to, every earlier tree head must match a prefix of the entries,
+//! so a crate file can't be changed without the signatures giving it away.
+
+use chrono::NaiveDateTime;
+use openssl::error::ErrorStack;
+use openssl::hash::MessageDigest;
+use openssl::pkey::{HasPublic, PKey, PKeyRef, Private};
+use openssl::sha::{sha256, Sha256};
+use openssl::sign::{Signer, Verifier};
+
+pub type Hash = [u8; 32];
+
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%SZ";
+
+pub fn format_timestamp(time: NaiveDateTime) -> String {
+    time.format(TIMESTAMP_FORMAT).to_string()
+}
+
+pub fn leaf_data(
+    crate_name: &str,
+    num: &str,
+    checksum: &str,
+    published_at: NaiveDateTime,
+) -> String {
+    format!(
+        "{}\n{}\n{}\n{}",
+        crate_name,
+        num,
+        checksum,
+        format_timestamp(published_at)
+    )
+}
+
+pub fn leaf_hash(data: &[u8]) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update(&[0]);
+    hasher.update(data);
+    hasher.finish()
+}
+
+fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update(&[1]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finish()
+}
+
+/// The root hash of the tree of the given leaf hashes.
+pub fn root_hash(leaves: &[Hash]) -> Hash {
+    match leaves.len() {
+        0 => sha256(b""),
+        1 => leaves[0],
+        n => {
+            // The left subtree is the largest complete tree with fewer than
+            // `n` leaves.
+            let split = n.next_power_of_two() / 2;
+            node_hash(&root_hash(&leaves[..split]), &root_hash(&leaves[split..]))
+        }
+    }
+}
+
+/// The message which is signed for a tree head.
+pub fn tree_head_message(tree_size: i32, root_hash: &str, signed_at: NaiveDateTime) -> String {
+    format!(
+        "crates.io transparency log\n{}\n{}\n{}\n",
+        tree_size,
+        root_hash,
+        format_timestamp(signed_at)
+    )
+}
+
+/// Signs the message with a SHA-256 digest, which works for both RSA and
+/// ECDSA keys.
+pub fn sign(key: &PKey<Private>, message: &str) -> Result<Vec<u8>, ErrorStack> {
+    let mut signer = Signer::new(MessageDigest::sha256(), key)?;
+    signer.update(message.as_bytes())?;
+    signer.sign_to_vec()
+}
+
+pub fn verify<T: HasPublic>(
+    key: &PKeyRef<T>,
+    message: &str,
+    signature: &[u8],
+) -> Result<bool, ErrorStack> {
+    let mut verifier = Verifier::new(MessageDigest::sha256(), key)?;
+    verifier.update(message.as_bytes())?;
+    verifier.verify(signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::nid::Nid;
+
+    #[test]
+    fn hashes_match_rfc_6962() {
+        assert_eq!(
+            hex::encode(root_hash(&[])),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hex::encode(leaf_hash(b"")),
+            "6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d"
+        );
+    }
+
+    #[test]
+    fn trees_are_split_at_powers_of_two() {
+        let leaves = (0..5u8).map(|i| leaf_hash(&[i])).collect::<Vec<_>>();
+
+        assert_eq!(root_hash(&leaves[..1]), leaves[0]);
+        let left = node_hash(
+            &node_hash(&leaves[0], &leaves[1]),
+            &node_hash(&leaves[2], &leaves[3]),
+        );
+        assert_eq!(root_hash(&leaves[..4]), left);
+        assert_eq!(root_hash(&leaves), node_hash(&left, &leaves[4]));
+        assert_eq!(
+            root_hash(&leaves[..3]),
+            node_hash(&node_hash(&leaves[0], &leaves[1]), &leaves[2])
+        );
+    }
+
+    #[test]
+    fn leaves_have_second_precision() {
+        let published_at = NaiveDate::from_ymd(2020, 3, 13).and_hms_micro(14, 10, 52, 123);
+        assert_eq!(
+            leaf_data("foo", "1.0.0", "abc", published_at),
+            "foo\n1.0.0\nabc\n2020-03-13T14:10:52Z"
+        );
+    }
+
+    #[test]
+    fn tree_heads_can_be_verified() {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+        let public_key = PKey::public_key_from_pem(&key.public_key_to_pem().unwrap()).unwrap();
+        let signed_at = NaiveDate::from_ymd(2020, 3, 13).and_hms(14, 10, 52);
+
+        let message = tree_head_message(5, "00ff", signed_at);
+        let signature = sign(&key, &message).unwrap();
+        assert!(verify(&public_key, &message, &signature).unwrap());
+
+        let message = tree_head_message(6, "00ff", signed_at);
+        assert!(!verify(&public_key, &message, &signature).unwrap());
+    }
+}
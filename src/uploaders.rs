@@ -1,78 +1,138 @@
 use conduit::Request;
-use flate2::read::GzDecoder;
+use flate2::bufread::GzDecoder;
 use openssl::error::ErrorStack;
 use openssl::hash::{Hasher, MessageDigest};
 use reqwest::{blocking::Client, header};
+use serde_json::Value;
 
-use crate::util::errors::{cargo_err, internal, AppResult, ChainError};
+use crate::util::errors::{
+    cargo_err, internal, AppResult, ChainError, CompressionBomb, InvalidTarballEntry,
+    TarballLimitExceeded,
+};
 use crate::util::{Error, LimitErrorReader, Maximums};
 
-use std::env;
-use std::fs::{self, File};
-use std::io::{Cursor, Read};
+use std::cell::Cell;
+use std::cmp;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Cursor, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path};
+use std::rc::Rc;
 use std::sync::Arc;
 
 use crate::middleware::app::RequestApp;
-use crate::models::Crate;
+use crate::models::crate_policy::{PolicyFile, POLICY_FILE};
+use crate::models::feature_docs::{parse_feature_docs, ORIGINAL_MANIFEST_FILE};
+use crate::models::funding::{collect_funding_links, FundingLink, FUNDING_FILE};
+use crate::models::targets::declared_targets;
+use crate::models::{CompressionFormat, Crate, VersionFile};
+use crate::storage::Storage;
+use crate::tarball::{default_analyzers, Analyzer};
 
 const CACHE_CONTROL_IMMUTABLE: &str = "public,max-age=31536000,immutable";
 const CACHE_CONTROL_README: &str = "public,max-age=604800";
+const CACHE_CONTROL_OG_IMAGE: &str = "public,max-age=86400";
+const CACHE_CONTROL_SITEMAP: &str = "public,max-age=86400";
+const CACHE_CONTROL_SBOM: &str = "public,max-age=86400";
 
-#[derive(Clone, Debug)]
-pub enum Uploader {
-    /// For production usage, uploads and redirects to s3.
-    /// For test usage with `TestApp::with_proxy()`, the recording proxy is used.
-    S3 {
-        bucket: s3::Bucket,
-        cdn: Option<String>,
-    },
+/// The result of uploading a crate file.
+#[derive(Debug)]
+pub struct UploadedCrate {
+    /// The SHA-256 checksum of the crate file.
+    pub checksum: Vec<u8>,
+    /// The crate's policy file, if it contains one.
+    pub policy_file: Option<PolicyFile>,
+    /// Whether the crate contains a `tests` directory.
+    pub has_tests: bool,
+    /// The descriptions of features in the original manifest.
+    pub feature_docs: BTreeMap<String, String>,
+    /// The funding links in the manifest and the funding file.
+    pub funding_links: Vec<FundingLink>,
+    /// The targets declared in the manifest, see the `targets` module.
+    pub targets: Vec<String>,
+    /// The reports of the analyzers, by their name.
+    pub analyses: BTreeMap<String, Value>,
+    /// The normalized `Cargo.toml`, unless it couldn't be parsed.
+    pub manifest: Option<toml::Value>,
+    /// The `Cargo.lock`, if the crate file contains one which can be parsed.
+    pub lockfile: Option<toml::Value>,
+    /// How the crate file is compressed.
+    pub compression: CompressionFormat,
+    /// The regular files in the crate file, in the order of the archive.
+    pub files: Vec<VersionFile>,
+    /// The commit the crate was packaged from, see `vcs_commit`.
+    pub vcs_commit: Option<String>,
+    /// The directory of the package in its repository, see `vcs_path`.
+    pub vcs_path: Option<String>,
+}
 
-    /// For development usage only: "uploads" crate files to `dist` and serves them
-    /// from there as well to enable local publishing and download
-    Local,
+/// What `verify_tarball` found out about the contents of a crate file.
+#[derive(Debug)]
+pub struct TarballContents {
+    pub policy_file: Option<PolicyFile>,
+    pub has_tests: bool,
+    pub feature_docs: BTreeMap<String, String>,
+    pub funding_links: Vec<FundingLink>,
+    pub targets: Vec<String>,
+    pub analyses: BTreeMap<String, Value>,
+    pub manifest: Option<toml::Value>,
+    pub lockfile: Option<toml::Value>,
+    pub compression: CompressionFormat,
+    pub files: Vec<VersionFile>,
+    pub vcs_commit: Option<String>,
+    pub vcs_path: Option<String>,
+}
+
+/// Uploads files to, and locates them in, the configured `Storage`.
+#[derive(Clone, Debug)]
+pub struct Uploader {
+    storage: Arc<dyn Storage>,
 }
 
 impl Uploader {
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Uploader { storage }
+    }
+
+    /// Returns the storage backend the files are uploaded to.
+    pub fn storage(&self) -> &dyn Storage {
+        &*self.storage
+    }
+
     /// Returns the URL of an uploaded crate's version archive.
     ///
     /// The function doesn't check for the existence of the file.
     pub fn crate_location(&self, crate_name: &str, version: &str) -> String {
-        match *self {
-            Uploader::S3 {
-                ref bucket,
-                ref cdn,
-                ..
-            } => {
-                let host = match *cdn {
-                    Some(ref s) => s.clone(),
-                    None => bucket.host(),
-                };
-                let path = Uploader::crate_path(crate_name, version);
-                format!("https://{}/{}", host, path)
-            }
-            Uploader::Local => format!("/{}", Uploader::crate_path(crate_name, version)),
-        }
+        self.storage.url(&Uploader::crate_path(crate_name, version))
     }
 
     /// Returns the URL of an uploaded crate's version readme.
     ///
     /// The function doesn't check for the existence of the file.
     pub fn readme_location(&self, crate_name: &str, version: &str) -> String {
-        match *self {
-            Uploader::S3 {
-                ref bucket,
-                ref cdn,
-                ..
-            } => {
-                let host = match *cdn {
-                    Some(ref s) => s.clone(),
-                    None => bucket.host(),
-                };
-                let path = Uploader::readme_path(crate_name, version);
-                format!("https://{}/{}", host, path)
-            }
-            Uploader::Local => format!("/{}", Uploader::readme_path(crate_name, version)),
-        }
+        self.storage
+            .url(&Uploader::readme_path(crate_name, version))
+    }
+
+    /// Returns the URL of the SBOM of an uploaded crate's version.
+    ///
+    /// The function doesn't check for the existence of the file.
+    pub fn sbom_location(&self, crate_name: &str, version: &str) -> String {
+        self.storage.url(&Uploader::sbom_path(crate_name, version))
+    }
+
+    /// Returns the URL of a crate's Open Graph preview image.
+    ///
+    /// The function doesn't check for the existence of the file.
+    pub fn og_image_location(&self, crate_name: &str) -> String {
+        self.storage.url(&Uploader::og_image_path(crate_name))
+    }
+
+    /// Returns the URL of a generated sitemap file.
+    ///
+    /// The function doesn't check for the existence of the file.
+    pub fn sitemap_location(&self, file_name: &str) -> String {
+        self.storage.url(&Uploader::sitemap_path(file_name))
     }
 
     /// Returns the internal path of an uploaded crate's version archive.
@@ -81,74 +141,92 @@ impl Uploader {
         format!("crates/{}/{}-{}.crate", name, name, version)
     }
 
+    /// Returns the internal path a crate file of a queued publish is kept at
+    /// until it's uploaded, see `stage_crate_file`.
+    fn staged_crate_path(name: &str, version: &str) -> String {
+        format!("staging/{}/{}-{}.crate", name, name, version)
+    }
+
     /// Returns the internal path of an uploaded crate's version readme.
     fn readme_path(name: &str, version: &str) -> String {
         format!("readmes/{}/{}-{}.html", name, name, version)
     }
 
-    /// Uploads a file using the configured uploader (either `S3`, `Local`).
-    ///
-    /// It returns the path of the uploaded file.
-    ///
-    /// # Panics
+    /// Returns the internal path of the SBOM of an uploaded crate's version,
+    /// which is stored next to the crate file.
+    fn sbom_path(name: &str, version: &str) -> String {
+        format!("crates/{}/{}-{}.cdx.json", name, name, version)
+    }
+
+    /// Returns the internal path of a crate's Open Graph preview image.
+    fn og_image_path(name: &str) -> String {
+        format!("og-images/{}.png", name)
+    }
+
+    /// Returns the internal path of a generated sitemap file.
+    fn sitemap_path(file_name: &str) -> String {
+        format!("sitemaps/{}", file_name)
+    }
+
+    /// Uploads a file to the configured storage backend.
     ///
-    /// This function can panic on an `Self::Local` during development.
-    /// Production and tests use `Self::S3` which should not panic.
+    /// It returns the path of the uploaded file, if the backend knows it.
     pub fn upload<R: std::io::Read + Send + 'static>(
         &self,
         client: &Client,
         path: &str,
-        mut content: R,
+        content: R,
         content_length: u64,
         content_type: &str,
         extra_headers: header::HeaderMap,
     ) -> Result<Option<String>, Error> {
-        match *self {
-            Uploader::S3 { ref bucket, .. } => {
-                bucket.put(
-                    client,
-                    path,
-                    content,
-                    content_length,
-                    content_type,
-                    extra_headers,
-                )?;
-                Ok(Some(String::from(path)))
-            }
-            Uploader::Local => {
-                let filename = env::current_dir().unwrap().join("local_uploads").join(path);
-                let dir = filename.parent().unwrap();
-                fs::create_dir_all(dir)?;
-                let mut file = File::create(&filename)?;
-                std::io::copy(&mut content, &mut file)?;
-                Ok(filename.to_str().map(String::from))
-            }
-        }
+        self.storage.put(
+            client,
+            path,
+            Box::new(content),
+            content_length,
+            content_type,
+            extra_headers,
+        )
     }
 
-    /// Uploads a crate and returns the checksum of the uploaded crate file.
+    /// Uploads a crate from the body of a publish request, see `spool_crate`.
     pub fn upload_crate(
         &self,
         req: &mut dyn Request,
         krate: &Crate,
         maximums: Maximums,
         vers: &semver::Version,
-    ) -> AppResult<Vec<u8>> {
+    ) -> AppResult<UploadedCrate> {
         let app = Arc::clone(req.app());
-        let path = Uploader::crate_path(&krate.name, &vers.to_string());
-        let mut body = Vec::new();
-        LimitErrorReader::new(req.body(), maximums.max_upload_size).read_to_end(&mut body)?;
-        verify_tarball(krate, vers, &body, maximums.max_unpack_size)?;
-        let checksum = hash(&body)?;
-        let content_length = body.len() as u64;
-        let content = Cursor::new(body);
+        let (uploaded, file, content_length) = spool_crate(req, krate, maximums, vers)?;
+        self.store_crate_file(
+            app.http_client(),
+            &krate.name,
+            &vers.to_string(),
+            file,
+            content_length,
+        )?;
+        Ok(uploaded)
+    }
+
+    /// Uploads a crate file which was already verified.
+    pub fn store_crate_file<R: Read + Send + 'static>(
+        &self,
+        http_client: &Client,
+        crate_name: &str,
+        version: &str,
+        content: R,
+        content_length: u64,
+    ) -> AppResult<()> {
+        let path = Uploader::crate_path(crate_name, version);
         let mut extra_headers = header::HeaderMap::new();
         extra_headers.insert(
             header::CACHE_CONTROL,
             CACHE_CONTROL_IMMUTABLE.parse().unwrap(),
         );
         self.upload(
-            app.http_client(),
+            http_client,
             &path,
             content,
             content_length,
@@ -156,7 +234,32 @@ impl Uploader {
             extra_headers,
         )
         .map_err(|e| internal(&format_args!("failed to upload crate: {}", e)))?;
-        Ok(checksum)
+        Ok(())
+    }
+
+    /// Keeps a verified crate file of a publish with `?queued=true` in the
+    /// storage until the background job uploads it with `store_crate_file`.
+    ///
+    /// It returns the internal path of the staged file, which isn't served.
+    pub fn stage_crate_file<R: Read + Send + 'static>(
+        &self,
+        http_client: &Client,
+        crate_name: &str,
+        version: &str,
+        content: R,
+        content_length: u64,
+    ) -> AppResult<String> {
+        let path = Uploader::staged_crate_path(crate_name, version);
+        self.upload(
+            http_client,
+            &path,
+            content,
+            content_length,
+            "application/x-tar",
+            header::HeaderMap::new(),
+        )
+        .map_err(|e| internal(&format_args!("failed to stage crate: {}", e)))?;
+        Ok(path)
     }
 
     pub(crate) fn upload_readme(
@@ -181,49 +284,568 @@ impl Uploader {
         )?;
         Ok(())
     }
+
+    pub(crate) fn upload_sbom(
+        &self,
+        http_client: &Client,
+        crate_name: &str,
+        vers: &str,
+        sbom: String,
+    ) -> Result<(), Error> {
+        let path = Uploader::sbom_path(crate_name, vers);
+        let content_length = sbom.len() as u64;
+        let content = Cursor::new(sbom);
+        let mut extra_headers = header::HeaderMap::new();
+        extra_headers.insert(header::CACHE_CONTROL, CACHE_CONTROL_SBOM.parse().unwrap());
+        self.upload(
+            http_client,
+            &path,
+            content,
+            content_length,
+            "application/vnd.cyclonedx+json",
+            extra_headers,
+        )?;
+        Ok(())
+    }
+
+    pub(crate) fn upload_og_image(
+        &self,
+        http_client: &Client,
+        crate_name: &str,
+        png: Vec<u8>,
+    ) -> Result<(), Error> {
+        let path = Uploader::og_image_path(crate_name);
+        let content_length = png.len() as u64;
+        let content = Cursor::new(png);
+        let mut extra_headers = header::HeaderMap::new();
+        extra_headers.insert(
+            header::CACHE_CONTROL,
+            CACHE_CONTROL_OG_IMAGE.parse().unwrap(),
+        );
+        self.upload(
+            http_client,
+            &path,
+            content,
+            content_length,
+            "image/png",
+            extra_headers,
+        )?;
+        Ok(())
+    }
+
+    pub(crate) fn upload_sitemap(
+        &self,
+        http_client: &Client,
+        file_name: &str,
+        xml: String,
+    ) -> Result<(), Error> {
+        let path = Uploader::sitemap_path(file_name);
+        let content_length = xml.len() as u64;
+        let content = Cursor::new(xml);
+        let mut extra_headers = header::HeaderMap::new();
+        extra_headers.insert(
+            header::CACHE_CONTROL,
+            CACHE_CONTROL_SITEMAP.parse().unwrap(),
+        );
+        self.upload(
+            http_client,
+            &path,
+            content,
+            content_length,
+            "application/xml",
+            extra_headers,
+        )?;
+        Ok(())
+    }
 }
 
-fn verify_tarball(
+/// Reads the crate file from the body of a publish request.
+///
+/// The body is verified and checksummed while it's read, and spooled to a
+/// temporary file instead of memory, which is returned with its length.
+/// Oversized or malformed crate files are rejected as soon as the problem is
+/// read.
+pub fn spool_crate(
+    req: &mut dyn Request,
+    krate: &Crate,
+    maximums: Maximums,
+    vers: &semver::Version,
+) -> AppResult<(UploadedCrate, File, u64)> {
+    let accept_zstd = req.app().config.accept_zstd_crates;
+    let mut body = HashingTee {
+        inner: LimitErrorReader::new(req.body(), maximums.max_upload_size),
+        file: tempfile::tempfile()?,
+        hasher: Hasher::new(MessageDigest::sha256())?,
+    };
+    let contents = verify_tarball(
+        krate,
+        vers,
+        &mut body,
+        maximums,
+        accept_zstd,
+        &mut default_analyzers(),
+    )?;
+    // The archive can end before the body does, e.g. with trailing
+    // padding, which is part of the crate file and its checksum
+    io::copy(&mut body, &mut io::sink())?;
+
+    let HashingTee {
+        mut file,
+        mut hasher,
+        ..
+    } = body;
+    let checksum = hasher.finish()?.to_vec();
+    let content_length = file.seek(SeekFrom::End(0))?;
+    file.seek(SeekFrom::Start(0))?;
+    let uploaded = UploadedCrate {
+        checksum,
+        policy_file: contents.policy_file,
+        has_tests: contents.has_tests,
+        feature_docs: contents.feature_docs,
+        funding_links: contents.funding_links,
+        targets: contents.targets,
+        analyses: contents.analyses,
+        manifest: contents.manifest,
+        lockfile: contents.lockfile,
+        compression: contents.compression,
+        files: contents.files,
+        vcs_commit: contents.vcs_commit,
+        vcs_path: contents.vcs_path,
+    };
+    Ok((uploaded, file, content_length))
+}
+
+/// Verifies a crate file that has already been read into memory, e.g. when
+/// importing crates from another registry, without uploading it.
+pub fn verify_crate_file(
+    krate: &Crate,
+    vers: &semver::Version,
+    body: &[u8],
+    maximums: Maximums,
+    accept_zstd: bool,
+) -> AppResult<UploadedCrate> {
+    let contents = verify_tarball(
+        krate,
+        vers,
+        body,
+        maximums,
+        accept_zstd,
+        &mut default_analyzers(),
+    )?;
+    Ok(UploadedCrate {
+        checksum: hash(body)?,
+        policy_file: contents.policy_file,
+        has_tests: contents.has_tests,
+        feature_docs: contents.feature_docs,
+        funding_links: contents.funding_links,
+        targets: contents.targets,
+        analyses: contents.analyses,
+        manifest: contents.manifest,
+        lockfile: contents.lockfile,
+        compression: contents.compression,
+        files: contents.files,
+        vcs_commit: contents.vcs_commit,
+        vcs_path: contents.vcs_path,
+    })
+}
+
+/// Inspects a crate file which was uploaded before, e.g. to correct what was
+/// extracted from it by older code, running the given analyzers over it.
+pub fn inspect_crate_file(
     krate: &Crate,
     vers: &semver::Version,
     tarball: &[u8],
     max_unpack: u64,
+    analyzers: &mut [Box<dyn Analyzer>],
+) -> AppResult<TarballContents> {
+    // Crate files which were accepted before aren't rejected by limits that
+    // were introduced later, so the compression ratios aren't checked.
+    let maximums = Maximums::new(None, max_unpack, max_unpack);
+    verify_tarball(krate, vers, tarball, maximums, true, analyzers)
+}
+
+/// Copies everything read from `inner` to `file` and hashes it, so that a
+/// crate file can be verified, checksummed and spooled in a single pass.
+struct HashingTee<R> {
+    inner: R,
+    file: File,
+    hasher: Hasher,
+}
+
+impl<R: Read> Read for HashingTee<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n])?;
+        self.file.write_all(&buf[..n])?;
+        Ok(n)
+    }
+}
+
+/// Counts the bytes which a decoder consumed from it, so that the
+/// verification can compare the compressed size of files with their size.
+/// Bytes which are only buffered aren't counted.
+struct CountingReader<R> {
+    inner: BufReader<R>,
+    count: Rc<Cell<u64>>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count.set(self.count.get() + n as u64);
+        Ok(n)
+    }
+}
+
+impl<R: Read> BufRead for CountingReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.inner.consume(amount);
+        self.count.set(self.count.get() + amount as u64);
+    }
+}
+
+/// Compression ratios are only checked once this many bytes were
+/// decompressed, since the compressed size of small files isn't accurate,
+/// and they can't do much harm anyway.
+const MIN_RATIO_CHECKED_SIZE: u64 = 1024 * 1024;
+
+fn check_compression_ratio(
+    path: Option<&Path>,
+    decompressed: u64,
+    compressed: u64,
+    max_ratio: u64,
 ) -> AppResult<()> {
-    // All our data is currently encoded with gzip
-    let decoder = GzDecoder::new(tarball);
+    if decompressed < MIN_RATIO_CHECKED_SIZE {
+        return Ok(());
+    }
+    let ratio = decompressed / cmp::max(compressed, 1);
+    if ratio > max_ratio {
+        return Err(Box::new(CompressionBomb {
+            path: path.map(|path| path.display().to_string()),
+            ratio,
+            max_ratio,
+        }));
+    }
+    Ok(())
+}
+
+/// Checks an entry against the maximum number of entries and path length,
+/// where `previous_entries` is the number of entries before it.
+fn check_entry_limits(relative: &Path, previous_entries: u64, maximums: Maximums) -> AppResult<()> {
+    if previous_entries >= maximums.max_file_count {
+        return Err(Box::new(TarballLimitExceeded::TooManyFiles {
+            max: maximums.max_file_count,
+        }));
+    }
+    let path = relative.to_string_lossy();
+    if path.len() as u64 > maximums.max_path_length {
+        return Err(Box::new(TarballLimitExceeded::PathTooLong {
+            path: path.into_owned(),
+            max: maximums.max_path_length,
+        }));
+    }
+    Ok(())
+}
+
+/// Checks that the path of an entry doesn't only differ by case from the path
+/// of an earlier entry. `lowercase_paths` maps the lowercased paths of the
+/// earlier entries to their paths.
+fn check_case_collision(
+    path: &Path,
+    lowercase_paths: &mut HashMap<String, String>,
+) -> AppResult<()> {
+    let path = path.to_string_lossy().trim_end_matches('/').to_string();
+    match lowercase_paths.get(&path.to_lowercase()) {
+        // The same path can appear more than once in an archive, the last
+        // entry wins everywhere
+        Some(earlier) if *earlier != path => Err(Box::new(InvalidTarballEntry::CaseCollision(
+            earlier.clone(),
+            path,
+        ))),
+        Some(_) => Ok(()),
+        None => {
+            lowercase_paths.insert(path.to_lowercase(), path);
+            Ok(())
+        }
+    }
+}
+
+/// Verifies that unpacking the entry can't write outside of the `$name-$vers/`
+/// directory of the package.
+fn check_entry<R: Read>(entry: &tar::Entry<'_, R>, prefix: &str) -> AppResult<()> {
+    let path = entry.path()?;
+    let display = || path.display().to_string();
+
+    // Cargo's own unpacking skips these, but other tools reading crate files
+    // may not.
+    if path.has_root() {
+        return Err(Box::new(InvalidTarballEntry::AbsolutePath(display())));
+    }
+    if path.components().any(|c| c == Component::ParentDir) {
+        return Err(Box::new(InvalidTarballEntry::ParentDirectory(display())));
+    }
+
+    // Verify that all entries actually start with `$name-$vers/`.
+    // Historically Cargo didn't verify this on extraction so you could
+    // upload a tarball that contains both `foo-0.1.0/` source code as well
+    // as `bar-0.1.0/` source code, and this could overwrite other crates in
+    // the registry!
+    if !path.starts_with(prefix) {
+        return Err(Box::new(InvalidTarballEntry::OutsidePackage(display())));
+    }
+
+    // Historical versions of the `tar` crate which Cargo uses internally
+    // don't properly prevent hard links and symlinks from overwriting
+    // arbitrary files on the filesystem. As a bit of a hammer we reject any
+    // tarball with these sorts of links. Cargo doesn't currently ever
+    // generate a tarball with these file types so this should work for now.
+    let entry_type = entry.header().entry_type();
+    if entry_type.is_hard_link() || entry_type.is_symlink() {
+        return Err(Box::new(InvalidTarballEntry::Link(display())));
+    }
+    Ok(())
+}
+
+/// The magic number at the start of every zstd frame.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
 
-    // Don't let gzip decompression go into the weeeds, apply a fixed cap after
+/// Detects how a crate file is compressed from its first bytes. Anything
+/// that isn't zstd is treated as gzip, which has always been accepted, so
+/// that malformed crate files are still rejected by the gzip decoder.
+pub fn detect_compression(crate_file: &[u8]) -> CompressionFormat {
+    if crate_file.starts_with(&ZSTD_MAGIC) {
+        CompressionFormat::Zstd
+    } else {
+        CompressionFormat::Gzip
+    }
+}
+
+fn verify_tarball<'a, R: Read + 'a>(
+    krate: &Crate,
+    vers: &semver::Version,
+    mut tarball: R,
+    maximums: Maximums,
+    accept_zstd: bool,
+    analyzers: &mut [Box<dyn Analyzer>],
+) -> AppResult<TarballContents> {
+    let mut magic = Vec::with_capacity(ZSTD_MAGIC.len());
+    tarball
+        .by_ref()
+        .take(ZSTD_MAGIC.len() as u64)
+        .read_to_end(&mut magic)?;
+    let compression = detect_compression(&magic);
+    let compressed = Rc::new(Cell::new(0));
+    let tarball = CountingReader {
+        inner: BufReader::new(Cursor::new(magic).chain(tarball)),
+        count: Rc::clone(&compressed),
+    };
+
+    let decoder: Box<dyn Read + 'a> = match compression {
+        CompressionFormat::Gzip => Box::new(GzDecoder::new(tarball)),
+        CompressionFormat::Zstd if accept_zstd => Box::new(zstd::Decoder::with_buffer(tarball)?),
+        CompressionFormat::Zstd => {
+            return Err(cargo_err(
+                "zstd compressed crate files aren't accepted by this registry, \
+                 please compress the crate file with gzip",
+            ))
+        }
+    };
+
+    // Don't let decompression go into the weeeds, apply a fixed cap after
     // which point we say the decompressed source is "too large".
-    let decoder = LimitErrorReader::new(decoder, max_unpack);
+    let decoder = LimitErrorReader::new(decoder, maximums.max_unpack_size);
 
     // Use this I/O object now to take a peek inside
     let mut archive = tar::Archive::new(decoder);
     let prefix = format!("{}-{}", krate.name, vers);
+    let policy_path = Path::new(&prefix).join(POLICY_FILE);
+    let tests_path = Path::new(&prefix).join("tests");
+    let manifest_path = Path::new(&prefix).join(ORIGINAL_MANIFEST_FILE);
+    let normalized_manifest_path = Path::new(&prefix).join("Cargo.toml");
+    let funding_path = Path::new(&prefix).join(FUNDING_FILE);
+    let lockfile_path = Path::new(&prefix).join("Cargo.lock");
+    let vcs_info_path = Path::new(&prefix).join(VCS_INFO_FILE);
+    let mut policy_file = None;
+    let mut has_tests = false;
+    let mut feature_docs = BTreeMap::new();
+    let mut normalized_manifest = None;
+    let mut funding_file = None;
+    let mut lockfile = None;
+    let mut vcs_commit = None;
+    let mut vcs_path = None;
+    let mut decompressed = 0;
+    let mut entry_count = 0;
+    let mut lowercase_paths = HashMap::new();
+    let mut files = Vec::new();
     for entry in archive.entries()? {
-        let entry = entry.chain_error(|| {
+        let mut entry = entry.chain_error(|| {
+            cargo_err("uploaded tarball is malformed or too large when decompressed")
+        })?;
+        let compressed_before = compressed.get();
+
+        check_entry(&entry, &prefix)?;
+
+        // Entries can only be read in order, so everything which is needed
+        // from an entry is read at once: whole files for what's extracted
+        // below, and as much as the analyzers ask for otherwise.
+        let path = entry.path()?.into_owned();
+        let relative = path.strip_prefix(&prefix).unwrap_or(&path).to_path_buf();
+        check_entry_limits(&relative, entry_count, maximums)?;
+        check_case_collision(&path, &mut lowercase_paths)?;
+        entry_count += 1;
+        let size = entry.header().size()?;
+        let is_extracted = path == policy_path
+            || path == manifest_path
+            || path == normalized_manifest_path
+            || path == funding_path
+            || path == lockfile_path
+            || path == vcs_info_path;
+        let wanted = analyzers
+            .iter()
+            .map(|analyzer| analyzer.wants_contents(&relative))
+            .max()
+            .unwrap_or(0);
+        let mut contents = Vec::new();
+        let read = if is_extracted {
+            entry.read_to_end(&mut contents)
+        } else {
+            entry
+                .by_ref()
+                .take(wanted as u64)
+                .read_to_end(&mut contents)
+        };
+        read.chain_error(|| {
+            cargo_err("uploaded tarball is malformed or too large when decompressed")
+        })?;
+
+        // The rest of the file is read as well to know how far it expands,
+        // and to checksum it for the file listing. The archive would skip it
+        // by reading it anyway.
+        let mut hasher = Hasher::new(MessageDigest::sha256())?;
+        hasher.update(&contents)?;
+        io::copy(&mut entry, &mut hasher).chain_error(|| {
             cargo_err("uploaded tarball is malformed or too large when decompressed")
         })?;
+        if entry.header().entry_type().is_file() {
+            files.push(VersionFile {
+                path: relative.to_string_lossy().into_owned(),
+                size: size as i64,
+                sha256: hex::encode(hasher.finish()?),
+            });
+        }
+        check_compression_ratio(
+            Some(relative.as_path()),
+            size,
+            compressed.get() - compressed_before,
+            maximums.max_file_compression_ratio,
+        )?;
+        decompressed += size;
+        check_compression_ratio(
+            None,
+            decompressed,
+            compressed.get(),
+            maximums.max_compression_ratio,
+        )?;
 
-        // Verify that all entries actually start with `$name-$vers/`.
-        // Historically Cargo didn't verify this on extraction so you could
-        // upload a tarball that contains both `foo-0.1.0/` source code as well
-        // as `bar-0.1.0/` source code, and this could overwrite other crates in
-        // the registry!
-        if !entry.path()?.starts_with(&prefix) {
-            return Err(cargo_err("invalid tarball uploaded"));
+        for analyzer in analyzers.iter_mut() {
+            analyzer.entry(&relative, size, &contents);
         }
 
-        // Historical versions of the `tar` crate which Cargo uses internally
-        // don't properly prevent hard links and symlinks from overwriting
-        // arbitrary files on the filesystem. As a bit of a hammer we reject any
-        // tarball with these sorts of links. Cargo doesn't currently ever
-        // generate a tarball with these file types so this should work for now.
-        let entry_type = entry.header().entry_type();
-        if entry_type.is_hard_link() || entry_type.is_symlink() {
-            return Err(cargo_err("invalid tarball uploaded"));
+        if path == policy_path {
+            let contents = std::str::from_utf8(&contents)
+                .map_err(|_| cargo_err(&format_args!("`{}` is not valid UTF-8", POLICY_FILE)))?;
+            policy_file = Some(PolicyFile::parse(contents)?);
+        }
+
+        // Feature descriptions are optional, so a manifest which isn't valid
+        // UTF-8 doesn't prevent the crate from being published.
+        if path == manifest_path {
+            if let Ok(contents) = std::str::from_utf8(&contents) {
+                feature_docs = parse_feature_docs(contents);
+            }
+        }
+
+        // Like feature descriptions, funding links are only collected on a
+        // best effort basis.
+        if path == normalized_manifest_path {
+            normalized_manifest = String::from_utf8(contents).ok();
+        } else if path == funding_path {
+            funding_file = String::from_utf8(contents).ok();
+        } else if path == lockfile_path {
+            lockfile = String::from_utf8(contents)
+                .ok()
+                .and_then(|lockfile| lockfile.parse().ok());
+        } else if path == vcs_info_path {
+            vcs_commit = self::vcs_commit(&contents);
+            vcs_path = self::vcs_path(&contents);
+        }
+
+        if path.starts_with(&tests_path) {
+            has_tests = true;
         }
     }
-    Ok(())
+    let funding_links =
+        collect_funding_links(normalized_manifest.as_deref(), funding_file.as_deref());
+    let manifest = normalized_manifest
+        .as_deref()
+        .and_then(|manifest| manifest.parse().ok());
+    let targets = match &manifest {
+        Some(manifest) => declared_targets(manifest)?,
+        None => Vec::new(),
+    };
+    let analyses = analyzers
+        .iter_mut()
+        .map(|analyzer| (analyzer.name().to_string(), analyzer.finish()))
+        .collect();
+    Ok(TarballContents {
+        policy_file,
+        has_tests,
+        feature_docs,
+        funding_links,
+        targets,
+        analyses,
+        manifest,
+        lockfile,
+        compression,
+        files,
+        vcs_commit,
+        vcs_path,
+    })
+}
+
+/// The file in which `cargo package` records the commit it packaged.
+pub const VCS_INFO_FILE: &str = ".cargo_vcs_info.json";
+
+/// The commit recorded in the contents of a `.cargo_vcs_info.json` file,
+/// like `{"git":{"sha1":"..."}}`, if it's a full hexadecimal commit hash.
+pub fn vcs_commit(vcs_info: &[u8]) -> Option<String> {
+    let vcs_info = serde_json::from_slice::<Value>(vcs_info).ok()?;
+    let sha1 = vcs_info["git"]["sha1"].as_str()?;
+    if sha1.len() == 40 && sha1.chars().all(|c| c.is_ascii_hexdigit()) {
+        Some(sha1.to_lowercase())
+    } else {
+        None
+    }
+}
+
+/// The directory of the package in its repository, recorded as `path_in_vcs`
+/// by newer versions of cargo, with an empty string for the root. Anything
+/// but a relative path without `..` is ignored.
+pub fn vcs_path(vcs_info: &[u8]) -> Option<String> {
+    let vcs_info = serde_json::from_slice::<Value>(vcs_info).ok()?;
+    let path = vcs_info["path_in_vcs"].as_str()?;
+    let segments = Path::new(path)
+        .components()
+        .map(|component| match component {
+            Component::Normal(segment) => segment.to_str(),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(segments.join("/"))
 }
 
 fn hash(data: &[u8]) -> Result<Vec<u8>, ErrorStack> {
@@ -231,3 +853,34 @@ fn hash(data: &[u8]) -> Result<Vec<u8>, ErrorStack> {
     hasher.update(data)?;
     Ok(hasher.finish()?.to_vec())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vcs_commit_is_read_from_the_vcs_info() {
+        let vcs_info =
+            br#"{"git":{"sha1":"0123456789ABCDEF0123456789abcdef01234567"},"path_in_vcs":""}"#;
+        assert_eq!(
+            vcs_commit(vcs_info).as_deref(),
+            Some("0123456789abcdef0123456789abcdef01234567")
+        );
+        assert_eq!(vcs_commit(br#"{"git":{"sha1":"main"}}"#), None);
+        assert_eq!(vcs_commit(br#"{"hg":{}}"#), None);
+        assert_eq!(vcs_commit(b"not json"), None);
+    }
+
+    #[test]
+    fn vcs_path_is_read_from_the_vcs_info() {
+        let vcs_path = |path: &str| {
+            let vcs_info = serde_json::json!({ "git": {}, "path_in_vcs": path });
+            self::vcs_path(vcs_info.to_string().as_bytes())
+        };
+        assert_eq!(vcs_path("").as_deref(), Some(""));
+        assert_eq!(vcs_path("crates/foo/").as_deref(), Some("crates/foo"));
+        assert_eq!(vcs_path("../foo"), None);
+        assert_eq!(vcs_path("/foo"), None);
+        assert_eq!(super::vcs_path(br#"{"git":{}}"#), None);
+    }
+}
@@ -7,14 +7,18 @@ use serde::Serialize;
 
 pub use self::errors::concrete::Error;
 pub use self::io_util::{read_fill, read_le_u32, LimitErrorReader};
+pub use self::lru_cache::LruCache;
 pub use self::request_helpers::*;
 pub use self::request_proxy::RequestProxy;
+pub use self::ttl_cache::TtlCache;
 
 pub mod errors;
 mod io_util;
+mod lru_cache;
 mod request_helpers;
 mod request_proxy;
 pub mod rfc3339;
+mod ttl_cache;
 
 /// Serialize a value to JSON and build a status 200 Response
 ///
@@ -24,7 +28,11 @@ pub mod rfc3339;
 ///
 /// This function will panic if serialization fails.
 pub fn json_response<T: Serialize>(t: &T) -> Response {
-    let json = serde_json::to_string(t).unwrap();
+    raw_json_response(serde_json::to_string(t).unwrap())
+}
+
+/// Builds a response from an already serialized JSON document.
+pub fn raw_json_response(json: String) -> Response {
     let mut headers = HashMap::new();
     headers.insert(
         "Content-Type".to_string(),
@@ -42,9 +50,21 @@ pub fn json_response<T: Serialize>(t: &T) -> Response {
 pub struct Maximums {
     pub max_upload_size: u64,
     pub max_unpack_size: u64,
+    /// How many times larger than the crate file its contents may be.
+    pub max_compression_ratio: u64,
+    /// How many times larger than its compressed data a single file may be.
+    pub max_file_compression_ratio: u64,
+    /// How many entries, including directories, the crate file may contain.
+    pub max_file_count: u64,
+    /// How long the path of an entry may be in bytes, not counting the
+    /// `$name-$vers/` directory.
+    pub max_path_length: u64,
 }
 
 impl Maximums {
+    /// The size limits of a crate file, which doesn't limit the compression
+    /// ratios or the entries unless `with_compression_ratios` and
+    /// `with_entry_limits` are used.
     pub fn new(
         krate_max_upload: Option<i32>,
         app_max_upload: u64,
@@ -55,6 +75,26 @@ impl Maximums {
         Maximums {
             max_upload_size,
             max_unpack_size,
+            max_compression_ratio: u64::max_value(),
+            max_file_compression_ratio: u64::max_value(),
+            max_file_count: u64::max_value(),
+            max_path_length: u64::max_value(),
+        }
+    }
+
+    pub fn with_compression_ratios(self, total: u64, per_file: u64) -> Maximums {
+        Maximums {
+            max_compression_ratio: total,
+            max_file_compression_ratio: per_file,
+            ..self
+        }
+    }
+
+    pub fn with_entry_limits(self, file_count: u64, path_length: u64) -> Maximums {
+        Maximums {
+            max_file_count: file_count,
+            max_path_length: path_length,
+            ..self
         }
     }
 }
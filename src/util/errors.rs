@@ -20,6 +20,7 @@ use chrono::NaiveDateTime;
 use conduit::Response;
 use diesel::result::Error as DieselError;
 
+use crate::i18n::{Locale, Message};
 use crate::util::json_response;
 
 pub(super) mod concrete;
@@ -52,6 +53,8 @@ pub fn server_error<S: ToString + ?Sized>(error: &S) -> Box<dyn AppError> {
 #[derive(Serialize)]
 struct StringError<'a> {
     detail: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<&'a str>,
 }
 #[derive(Serialize)]
 struct Bad<'a> {
@@ -61,12 +64,37 @@ struct Bad<'a> {
 /// Generates a response with the provided status and description as JSON
 fn json_error(detail: &str, status: (u32, &'static str)) -> Response {
     let mut response = json_response(&Bad {
-        errors: vec![StringError { detail }],
+        errors: vec![StringError { detail, code: None }],
     });
     response.status = status;
     response
 }
 
+/// Generates a response with the provided status and a message from the
+/// catalog, rendered in the requested locale
+fn localized_json_error(
+    message: &Message,
+    locale: Locale,
+    status: (u32, &'static str),
+) -> Response {
+    let detail = message.render(locale);
+    let mut response = json_response(&Bad {
+        errors: vec![StringError {
+            detail: &detail,
+            code: Some(message.code()),
+        }],
+    });
+    response.status = status;
+    response
+        .headers
+        .insert("Content-Language".into(), vec![locale.tag().into()]);
+    // The message depends on the `Accept-Language` header of the request
+    response
+        .headers
+        .insert("Vary".into(), vec!["Accept-Language".into()]);
+    response
+}
+
 // =============================================================================
 // AppError trait
 
@@ -77,6 +105,14 @@ pub trait AppError: Send + fmt::Display + fmt::Debug + 'static {
     /// where it is eventually logged and turned into a status 500 response.
     fn response(&self) -> Option<Response>;
 
+    /// Generate an HTTP response for the error in the given locale
+    ///
+    /// Errors that are not part of the message catalog ignore the locale and
+    /// fall back to `response()`.
+    fn localized_response(&self, _locale: Locale) -> Option<Response> {
+        self.response()
+    }
+
     /// The cause of an error response
     ///
     /// If present, an error provided to the `LogRequests` middleware.
@@ -119,6 +155,10 @@ impl AppError for Box<dyn AppError> {
         (**self).response()
     }
 
+    fn localized_response(&self, locale: Locale) -> Option<Response> {
+        (**self).localized_response(locale)
+    }
+
     fn cause(&self) -> Option<&dyn AppError> {
         (**self).cause()
     }
@@ -175,6 +215,10 @@ impl<E: AppError> AppError for ChainedError<E> {
         self.error.response()
     }
 
+    fn localized_response(&self, locale: Locale) -> Option<Response> {
+        self.error.localized_response(locale)
+    }
+
     fn cause(&self) -> Option<&dyn AppError> {
         Some(&*self.cause)
     }
@@ -228,7 +272,12 @@ pub struct NotFound;
 
 impl AppError for NotFound {
     fn response(&self) -> Option<Response> {
-        Some(json_error("Not Found", (404, "Not Found")))
+        self.localized_response(Locale::default())
+    }
+
+    fn localized_response(&self, locale: Locale) -> Option<Response> {
+        let message = Message::NotFound;
+        Some(localized_json_error(&message, locale, (404, "Not Found")))
     }
 }
 
@@ -243,8 +292,12 @@ pub struct Unauthorized;
 
 impl AppError for Unauthorized {
     fn response(&self) -> Option<Response> {
-        let detail = "must be logged in to perform that action";
-        Some(json_error(detail, (403, "Forbidden")))
+        self.localized_response(Locale::default())
+    }
+
+    fn localized_response(&self, locale: Locale) -> Option<Response> {
+        let message = Message::Unauthorized;
+        Some(localized_json_error(&message, locale, (403, "Forbidden")))
     }
 }
 
@@ -280,9 +333,13 @@ pub struct ReadOnlyMode;
 
 impl AppError for ReadOnlyMode {
     fn response(&self) -> Option<Response> {
-        let detail = "Crates.io is currently in read-only mode for maintenance. \
-                      Please try again later.";
-        Some(json_error(detail, (503, "Service Unavailable")))
+        self.localized_response(Locale::default())
+    }
+
+    fn localized_response(&self, locale: Locale) -> Option<Response> {
+        let message = Message::ReadOnlyMode;
+        let status = (503, "Service Unavailable");
+        Some(localized_json_error(&message, locale, status))
     }
 }
 
@@ -299,19 +356,21 @@ pub struct TooManyRequests {
 
 impl AppError for TooManyRequests {
     fn response(&self) -> Option<Response> {
+        self.localized_response(Locale::default())
+    }
+
+    fn localized_response(&self, locale: Locale) -> Option<Response> {
         const HTTP_DATE_FORMAT: &str = "%a, %d %b %Y %H:%M:%S GMT";
-        let retry_after = self.retry_after.format(HTTP_DATE_FORMAT);
-
-        let detail = format!(
-            "You have published too many crates in a \
-             short period of time. Please try again after {} or email \
-             help@crates.io to have your limit increased.",
-            retry_after
-        );
-        let mut response = json_error(&detail, (429, "TOO MANY REQUESTS"));
+        let retry_after = self.retry_after.format(HTTP_DATE_FORMAT).to_string();
+
+        let message = Message::TooManyRequests {
+            retry_after: retry_after.clone(),
+        };
+        let status = (429, "TOO MANY REQUESTS");
+        let mut response = localized_json_error(&message, locale, status);
         response
             .headers
-            .insert("Retry-After".into(), vec![retry_after.to_string()]);
+            .insert("Retry-After".into(), vec![retry_after]);
         Some(response)
     }
 }
@@ -322,6 +381,117 @@ impl fmt::Display for TooManyRequests {
     }
 }
 
+/// A crate file which expands much more than crates do when decompressed,
+/// either as a whole or in a single file.
+#[derive(Debug, Clone)]
+pub struct CompressionBomb {
+    /// The file which expands too much, unless it's the whole crate file.
+    pub path: Option<String>,
+    pub ratio: u64,
+    pub max_ratio: u64,
+}
+
+impl AppError for CompressionBomb {
+    fn response(&self) -> Option<Response> {
+        Some(json_error(&self.to_string(), (400, "Bad Request")))
+    }
+}
+
+impl fmt::Display for CompressionBomb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "`{}` in the uploaded tarball", path)?,
+            None => "the uploaded tarball".fmt(f)?,
+        }
+        write!(
+            f,
+            " expands {}x when decompressed, more than the maximum of {}x",
+            self.ratio, self.max_ratio
+        )
+    }
+}
+
+/// A crate file with more entries or longer paths than the registry accepts,
+/// see `Config::max_file_count` and `Config::max_path_length`.
+#[derive(Debug, Clone)]
+pub enum TarballLimitExceeded {
+    TooManyFiles {
+        max: u64,
+    },
+    /// A path, relative to the `$name-$vers/` directory, which is too long.
+    PathTooLong {
+        path: String,
+        max: u64,
+    },
+}
+
+impl AppError for TarballLimitExceeded {
+    fn response(&self) -> Option<Response> {
+        Some(json_error(&self.to_string(), (400, "Bad Request")))
+    }
+}
+
+impl fmt::Display for TarballLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TarballLimitExceeded::TooManyFiles { max } => write!(
+                f,
+                "the uploaded tarball contains more than the maximum of {} files",
+                max
+            ),
+            TarballLimitExceeded::PathTooLong { path, max } => write!(
+                f,
+                "the path `{}` in the uploaded tarball is longer than the maximum of {} bytes",
+                path, max
+            ),
+        }
+    }
+}
+
+/// An entry of a crate file which could write outside of the directory of
+/// the package when the crate file is unpacked.
+#[derive(Debug, Clone)]
+pub enum InvalidTarballEntry {
+    AbsolutePath(String),
+    ParentDirectory(String),
+    /// An entry which isn't in the `$name-$vers/` directory.
+    OutsidePackage(String),
+    /// A symlink or hard link, which could point anywhere.
+    Link(String),
+    /// Two paths which only differ by case, which overwrite each other on
+    /// case-insensitive file systems, like the defaults of Windows and macOS.
+    CaseCollision(String, String),
+}
+
+impl AppError for InvalidTarballEntry {
+    fn response(&self) -> Option<Response> {
+        // Status 200, like all errors of the publish endpoint
+        Some(json_error(&self.to_string(), (200, "OK")))
+    }
+}
+
+impl fmt::Display for InvalidTarballEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        "invalid tarball uploaded: ".fmt(f)?;
+        match self {
+            InvalidTarballEntry::AbsolutePath(path) => write!(f, "`{}` is an absolute path", path),
+            InvalidTarballEntry::ParentDirectory(path) => {
+                write!(f, "`{}` refers to a parent directory", path)
+            }
+            InvalidTarballEntry::OutsidePackage(path) => {
+                write!(f, "`{}` is outside of the package directory", path)
+            }
+            InvalidTarballEntry::Link(path) => write!(f, "`{}` is a link", path),
+            InvalidTarballEntry::CaseCollision(first, second) => write!(
+                f,
+                "`{}` and `{}` only differ by case, so one would overwrite the other on \
+                 case-insensitive file systems",
+                first, second
+            ),
+        }
+    }
+}
+
 #[test]
 fn chain_error_internal() {
     assert_eq!(
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::mem;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+
+/// An in-process cache which keeps the recently used entries.
+///
+/// Instead of tracking the order of all entries, the cache keeps two
+/// generations of at most `capacity` entries each. Hits in the previous
+/// generation move the entry into the current one, and once the current
+/// generation is full it replaces the previous one. Entries which weren't
+/// used during a whole generation are dropped that way, and every operation
+/// takes constant time.
+///
+/// Every entry also expires after the duration it was inserted with.
+#[derive(Debug)]
+pub struct LruCache<V> {
+    capacity: usize,
+    generations: Mutex<Generations<V>>,
+}
+
+#[derive(Debug)]
+struct Generations<V> {
+    current: HashMap<String, (Instant, V)>,
+    previous: HashMap<String, (Instant, V)>,
+}
+
+impl<V> Generations<V> {
+    fn insert(&mut self, capacity: usize, key: String, entry: (Instant, V)) {
+        if self.current.len() >= capacity && !self.current.contains_key(&key) {
+            self.previous = mem::replace(&mut self.current, HashMap::new());
+        }
+        self.previous.remove(&key);
+        self.current.insert(key, entry);
+    }
+}
+
+impl<V: Clone> LruCache<V> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            generations: Mutex::new(Generations {
+                current: HashMap::new(),
+                previous: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Returns the value cached for `key`, unless it has expired.
+    pub fn get(&self, key: &str) -> Option<V> {
+        let mut generations = self.generations.lock();
+        let now = Instant::now();
+        if let Some((expires_at, value)) = generations.current.get(key) {
+            return if *expires_at > now {
+                Some(value.clone())
+            } else {
+                None
+            };
+        }
+
+        let (expires_at, value) = generations.previous.remove(key)?;
+        if expires_at <= now {
+            return None;
+        }
+        generations.insert(self.capacity, key.to_string(), (expires_at, value.clone()));
+        Some(value)
+    }
+
+    pub fn insert(&self, key: String, value: V, ttl: Duration) {
+        let expires_at = Instant::now() + ttl;
+        self.generations
+            .lock()
+            .insert(self.capacity, key, (expires_at, value));
+    }
+
+    /// Removes all entries whose key starts with `prefix`.
+    pub fn remove_prefix(&self, prefix: &str) {
+        let mut generations = self.generations.lock();
+        generations
+            .current
+            .retain(|key, _| !key.starts_with(prefix));
+        generations
+            .previous
+            .retain(|key, _| !key.starts_with(prefix));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LruCache;
+    use std::time::Duration;
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn entries_expire() {
+        let cache = LruCache::new(10);
+        cache.insert("a".into(), 1, Duration::from_secs(0));
+        cache.insert("b".into(), 2, TTL);
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(cache.get("b"), Some(2));
+        assert_eq!(cache.get("c"), None);
+    }
+
+    #[test]
+    fn unused_entries_are_dropped() {
+        let cache = LruCache::new(2);
+        cache.insert("a".into(), 1, TTL);
+        cache.insert("b".into(), 2, TTL);
+
+        // Starts a new generation, "a" is moved into it when it's used.
+        cache.insert("c".into(), 3, TTL);
+        assert_eq!(cache.get("a"), Some(1));
+
+        // Starts another generation, dropping "b".
+        cache.insert("d".into(), 4, TTL);
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get("a"), Some(1));
+        assert_eq!(cache.get("c"), Some(3));
+        assert_eq!(cache.get("d"), Some(4));
+    }
+
+    #[test]
+    fn entries_can_be_removed_by_prefix() {
+        let cache = LruCache::new(10);
+        cache.insert("foo/1.0.0".into(), 1, TTL);
+        cache.insert("foo_bar/1.0.0".into(), 2, TTL);
+        cache.remove_prefix("foo/");
+        assert_eq!(cache.get("foo/1.0.0"), None);
+        assert_eq!(cache.get("foo_bar/1.0.0"), Some(2));
+    }
+}
@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+
+/// A small in-process cache whose entries expire after a fixed duration.
+///
+/// The cache holds at most `capacity` entries. Inserting into a full cache
+/// first drops all expired entries, and if that doesn't free up any space the
+/// whole cache is cleared. This is good enough for caching the results of
+/// expensive queries for a short time, without the bookkeeping of an LRU.
+#[derive(Debug)]
+pub struct TtlCache<V> {
+    ttl: Duration,
+    capacity: usize,
+    entries: Mutex<HashMap<String, (Instant, V)>>,
+}
+
+impl<V: Clone> TtlCache<V> {
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            ttl,
+            capacity,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the value cached for `key`, unless it has expired.
+    pub fn get(&self, key: &str) -> Option<V> {
+        let entries = self.entries.lock();
+        match entries.get(key) {
+            Some((inserted_at, value)) if inserted_at.elapsed() < self.ttl => Some(value.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn insert(&self, key: String, value: V) {
+        let mut entries = self.entries.lock();
+        if entries.len() >= self.capacity && !entries.contains_key(&key) {
+            let ttl = self.ttl;
+            entries.retain(|_, (inserted_at, _)| inserted_at.elapsed() < ttl);
+            if entries.len() >= self.capacity {
+                entries.clear();
+            }
+        }
+        entries.insert(key, (Instant::now(), value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TtlCache;
+    use std::time::Duration;
+
+    #[test]
+    fn entries_expire() {
+        let cache = TtlCache::new(Duration::from_secs(0), 10);
+        cache.insert("a".into(), 1);
+        assert_eq!(cache.get("a"), None);
+
+        let cache = TtlCache::new(Duration::from_secs(60), 10);
+        cache.insert("a".into(), 1);
+        assert_eq!(cache.get("a"), Some(1));
+        assert_eq!(cache.get("b"), None);
+    }
+
+    #[test]
+    fn full_cache_is_cleared() {
+        let cache = TtlCache::new(Duration::from_secs(60), 2);
+        cache.insert("a".into(), 1);
+        cache.insert("b".into(), 2);
+        cache.insert("a".into(), 3);
+        assert_eq!(cache.get("a"), Some(3));
+        assert_eq!(cache.get("b"), Some(2));
+
+        cache.insert("c".into(), 4);
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get("c"), Some(4));
+    }
+}
@@ -1,7 +1,7 @@
-use chrono::NaiveDateTime;
+use chrono::{NaiveDate, NaiveDateTime};
 use std::collections::HashMap;
 
-use crate::models::DependencyKind;
+use crate::models::{CompressionFormat, DependencyKind};
 use crate::util::rfc3339;
 
 #[derive(PartialEq, Debug, Serialize, Deserialize)]
@@ -44,6 +44,113 @@ pub struct EncodableCrateOwnerInvitation {
     pub created_at: NaiveDateTime,
 }
 
+/// The serialization format for the `CratePolicy` model.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct EncodableCratePolicy {
+    /// The version that the policy was published with.
+    pub version: String,
+    pub deprecation: Option<EncodableDeprecation>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct EncodableDeprecation {
+    pub replaced_by: Option<String>,
+    pub message: Option<String>,
+}
+
+/// The serialization format for the `CrateQuality` model.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct EncodableCrateQuality {
+    /// The combined score between 0 and 1, used by `sort=quality`.
+    pub score: f32,
+    pub has_documentation: bool,
+    pub readme_length: i32,
+    pub has_tests: Option<bool>,
+    pub releases_last_year: i32,
+    pub yanked_ratio: f32,
+    #[serde(with = "rfc3339")]
+    pub computed_at: NaiveDateTime,
+}
+
+/// The serialization format for the `DependencyFreshness` model.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct EncodableDependencyFreshness {
+    /// Between 0 and 1, where 1 means that all dependencies are up to date,
+    /// used by `sort=dependency-freshness`.
+    pub score: f32,
+    pub dependencies: i32,
+    pub outdated_dependencies: i32,
+    /// The number of semver incompatible releases the dependencies are
+    /// behind in total.
+    pub releases_behind: i32,
+    #[serde(with = "rfc3339")]
+    pub computed_at: NaiveDateTime,
+}
+
+/// The serialization format for the `ReleaseStats` model. The average and
+/// longest gap between releases are in days, and `null` for crates with a
+/// single release.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct EncodableReleaseStats {
+    pub releases: i32,
+    pub avg_days_between_releases: Option<f32>,
+    pub longest_gap_days: Option<f32>,
+    pub releases_per_year: f32,
+    pub yank_rate: f32,
+    #[serde(with = "rfc3339")]
+    pub computed_at: NaiveDateTime,
+}
+
+/// The serialization format for a day of the `EcosystemStats` model.
+/// `new_users` is `null` for days whose users weren't counted.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct EncodableEcosystemStats {
+    pub date: NaiveDate,
+    pub total_crates: i32,
+    pub new_crates: i32,
+    pub new_versions: i32,
+    pub new_users: Option<i32>,
+    pub downloads: i64,
+}
+
+/// The serialization format for the `DownloadAnomaly` model.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct EncodableDownloadAnomaly {
+    pub date: NaiveDate,
+    pub downloads: i64,
+    pub baseline: f64,
+    pub score: f64,
+}
+
+/// The serialization format for the `CrateUploadLimit` model.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct EncodableCrateUploadLimit {
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub max_upload_size: i32,
+    pub reason: String,
+    pub expires_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+/// Aggregated counts for the results of a crate search, as requested via the
+/// `facets` query parameter.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct EncodableSearchFacets {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub categories: Option<Vec<EncodableFacetCount>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keywords: Option<Vec<EncodableFacetCount>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub licenses: Option<Vec<EncodableFacetCount>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EncodableFacetCount {
+    pub value: String,
+    pub count: i64,
+}
+
 #[derive(Deserialize, Serialize, Debug, Copy, Clone)]
 pub struct InvitationResponse {
     pub crate_id: i32,
@@ -102,6 +209,21 @@ pub struct EncodableCrate {
     pub homepage: Option<String>,
     pub documentation: Option<String>,
     pub repository: Option<String>,
+    /// When the last commit was pushed to the repository, as far as it's
+    /// known for repositories hosted on GitHub or GitLab.
+    #[serde(with = "rfc3339::option")]
+    pub repository_last_commit_at: Option<NaiveDateTime>,
+    /// Only included in the response of `GET /crates/:crate_id`.
+    pub custom_links: Option<EncodableCustomLinks>,
+    /// The verified domain of the organization owning the crate. Only
+    /// included in the response of `GET /crates/:crate_id`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verified_publisher: Option<String>,
+    /// Where to report vulnerabilities, the security policy link set by the
+    /// owners or else the `SECURITY.md` published with the crate. Only
+    /// included in the response of `GET /crates/:crate_id`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub security_policy_url: Option<String>,
     pub links: EncodableCrateLinks,
     pub exact_match: bool,
 }
@@ -116,6 +238,57 @@ pub struct EncodableCrateLinks {
     pub reverse_dependencies: String,
 }
 
+/// The links owners added to a crate in addition to the ones from its
+/// manifest.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct EncodableCustomLinks {
+    pub funding: Option<String>,
+    pub chat: Option<String>,
+    pub security_policy: Option<String>,
+}
+
+/// A change of one of the custom links of a crate, `url` is `None` if the
+/// link was removed.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableCrateLinkEvent {
+    pub kind: String,
+    pub url: Option<String>,
+    pub user: EncodablePublicUser,
+    #[serde(with = "rfc3339")]
+    pub time: NaiveDateTime,
+}
+
+/// A change of one field of the metadata of a crate, with the values before
+/// and after it.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableMetadataChange {
+    pub field: String,
+    pub old_value: serde_json::Value,
+    pub new_value: serde_json::Value,
+    pub user: EncodablePublicUser,
+    #[serde(with = "rfc3339")]
+    pub time: NaiveDateTime,
+}
+
+/// A dependency cycle introduced by publishing a version of a crate. `kinds`
+/// are the kinds of the dependency of each of the `crates` on the next one,
+/// and of the last one on the first.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableDependencyCycle {
+    pub version: String,
+    pub crates: Vec<String>,
+    pub kinds: Vec<String>,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct EncodableFundingLink {
+    pub platform: String,
+    pub url: String,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EncodableOwner {
     pub id: i32,
@@ -124,6 +297,23 @@ pub struct EncodableOwner {
     pub url: Option<String>,
     pub name: Option<String>,
     pub avatar: Option<String>,
+    /// The verified domains of the organization of a team.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub verified_domains: Vec<String>,
+}
+
+/// A domain a GitHub organization verified or asked to verify, with the TXT
+/// record proving its control over the domain.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableOrgDomain {
+    pub org: String,
+    pub domain: String,
+    pub txt_record_name: String,
+    pub txt_record_value: String,
+    #[serde(with = "rfc3339::option")]
+    pub checked_at: Option<NaiveDateTime>,
+    #[serde(with = "rfc3339::option")]
+    pub verified_at: Option<NaiveDateTime>,
 }
 
 #[derive(Serialize, Debug)]
@@ -163,6 +353,37 @@ pub struct EncodableMe {
     pub owned_crates: Vec<OwnedCrate>,
 }
 
+/// The summary of everything a user owns, served by `GET /me/dashboard`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableDashboard {
+    pub crates: Vec<EncodableDashboardCrate>,
+    pub crate_owner_invitations: Vec<EncodableCrateOwnerInvitation>,
+    pub action_items: Vec<EncodableActionItem>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableDashboardCrate {
+    pub id: i32,
+    pub name: String,
+    /// The default version, the one shown on the crate page.
+    pub latest_version: Option<String>,
+    /// Downloads in the last seven days, and the change from the seven days
+    /// before.
+    pub weekly_downloads: i64,
+    pub weekly_downloads_delta: i64,
+    /// `dependency_cycle`, `latest_version_yanked` or `squatting_report`.
+    pub advisories: Vec<String>,
+}
+
+/// Something the user needs to do, like `verify_email` or looking into a
+/// `failed_publish`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableActionItem {
+    pub kind: String,
+    pub crate_name: Option<String>,
+    pub detail: String,
+}
+
 /// The serialization format for the `User` model.
 /// Same as public user, except for addition of
 /// email field
@@ -218,9 +439,32 @@ pub struct EncodableVersion {
     pub links: EncodableVersionLinks,
     pub crate_size: Option<i32>,
     pub published_by: Option<EncodablePublicUser>,
+    pub publish_channel: Option<String>,
+    /// How the crate file is compressed, `gzip` or `zstd`.
+    pub compression: CompressionFormat,
+    /// The targets the crate declared to support in its manifest, empty if
+    /// it didn't declare any.
+    pub targets: Vec<String>,
     pub audit_actions: Vec<EncodableAuditAction>,
 }
 
+/// A feature of a version, with its description from the manifest.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct EncodableFeature {
+    pub name: String,
+    /// The features and optional dependencies the feature enables.
+    pub enables: Vec<String>,
+    pub description: Option<String>,
+}
+
+/// A file in the crate file of a version.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct EncodableVersionFile {
+    pub path: String,
+    pub size: i64,
+    pub sha256: String,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EncodableVersionLinks {
     pub dependencies: String,
@@ -228,6 +472,52 @@ pub struct EncodableVersionLinks {
     pub authors: String,
 }
 
+/// A version being yanked or unyanked, as listed by `GET /yanks`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableYank {
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub num: String,
+    pub yanked: bool,
+    pub reason: Option<String>,
+    /// The reason rendered from Markdown, see `render::message_to_html`.
+    pub reason_html: Option<String>,
+    #[serde(with = "rfc3339")]
+    pub time: NaiveDateTime,
+}
+
+/// A crate or version removed from the registry, as listed by
+/// `GET /deleted_crates`. `version` is `None` if the whole crate was deleted.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableDeletedCrate {
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub version: Option<String>,
+    pub reason: String,
+    #[serde(with = "rfc3339")]
+    pub deleted_at: NaiveDateTime,
+}
+
+/// What happened to one version of a `POST /crates/:crate_id/yank_bulk`
+/// request: `yanked`, `unyanked`, `unchanged`, `rate_limited` or
+/// `not_found`. Rate limited versions can be changed again at `retry_after`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableBulkYankResult {
+    pub num: String,
+    pub status: String,
+    #[serde(with = "rfc3339::option")]
+    pub retry_after: Option<NaiveDateTime>,
+}
+
+/// A transition of a version between yanked and unyanked.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableYankEvent {
+    pub yanked: bool,
+    pub user: EncodablePublicUser,
+    #[serde(with = "rfc3339")]
+    pub time: NaiveDateTime,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct GoodCrate {
     #[serde(rename = "crate")]
@@ -239,9 +529,88 @@ pub struct GoodCrate {
 pub struct PublishWarnings {
     pub invalid_categories: Vec<String>,
     pub invalid_badges: Vec<String>,
+    /// Dependencies which no version that isn't yanked matches, as
+    /// `name req`.
+    #[serde(default)]
+    pub unmatched_dependencies: Vec<String>,
+    /// Things in the metadata or manifest which are allowed, but probably
+    /// not intended, see `manifest_lints`.
+    #[serde(default)]
+    pub lints: Vec<EncodableLint>,
     pub other: Vec<String>,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableLint {
+    pub lint: String,
+    pub message: String,
+}
+
+/// The state of a publish with `?queued=true`: `queued`, `indexed` or `failed`.
+/// The error of the last failed attempt is kept until the publish succeeds.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodablePublishStatus {
+    pub state: String,
+    pub error: Option<EncodablePublishError>,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "rfc3339")]
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodablePublishError {
+    pub detail: String,
+}
+
+/// A verdict submitted through the partner API. `status` is `recorded` for
+/// clean verdicts, and `pending` until the verdict is reviewed otherwise.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableVerdict {
+    pub id: i32,
+    pub verdict: String,
+    pub status: String,
+    /// Whether downloads of the version are blocked.
+    pub quarantined: bool,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+}
+
+/// A signed root hash of the transparency log, see the `transparency_log`
+/// module for how it's computed. The signature is over the message of
+/// `transparency_log::tree_head_message`, with `timestamp` as the time.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableTreeHead {
+    pub tree_size: i32,
+    pub root_hash: String,
+    pub timestamp: String,
+    /// The hex encoded signature.
+    pub signature: String,
+    /// The PEM encoded key the signature can be verified with.
+    pub public_key: String,
+}
+
+/// A key commits to the index were signed with, in the OpenSSH format.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableIndexSigningKey {
+    pub fingerprint: String,
+    pub public_key: String,
+    #[serde(with = "rfc3339")]
+    pub first_used_at: NaiveDateTime,
+}
+
+/// An entry of the transparency log, with `published_at` formatted exactly
+/// as in its leaf.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableLogEntry {
+    pub leaf_index: i32,
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub num: String,
+    pub checksum: String,
+    pub published_at: String,
+}
+
 pub mod krate_publish;
 pub use self::krate_publish::{EncodableCrateDependency, EncodableCrateUpload};
 
@@ -322,6 +691,9 @@ mod tests {
             },
             crate_size: Some(1234),
             published_by: None,
+            publish_channel: Some("registry".to_string()),
+            compression: CompressionFormat::Gzip,
+            targets: vec!["wasm32-unknown-unknown".to_string()],
             audit_actions: vec![EncodableAuditAction {
                 action: "publish".to_string(),
                 user: EncodablePublicUser {
@@ -368,6 +740,10 @@ mod tests {
             homepage: None,
             documentation: None,
             repository: None,
+            repository_last_commit_at: None,
+            custom_links: None,
+            verified_publisher: None,
+            security_policy_url: None,
             links: EncodableCrateLinks {
                 version_downloads: "".to_string(),
                 versions: None,
@@ -34,6 +34,8 @@ pub struct EncodableCrateUpload {
     pub badges: Option<HashMap<String, HashMap<String, String>>>,
     #[serde(default)]
     pub links: Option<String>,
+    #[serde(default)]
+    pub rust_version: Option<EncodableRustVersion>,
 }
 
 #[derive(PartialEq, Eq, Hash, Serialize, Debug, Deref)]
@@ -42,6 +44,8 @@ pub struct EncodableCrateName(pub String);
 pub struct EncodableCrateVersion(pub semver::Version);
 #[derive(Debug, Deref)]
 pub struct EncodableCrateVersionReq(pub semver::VersionReq);
+#[derive(Serialize, Debug, Deref)]
+pub struct EncodableRustVersion(pub String);
 #[derive(Serialize, Debug, Deref, Default)]
 pub struct EncodableKeywordList(pub Vec<EncodableKeyword>);
 #[derive(Serialize, Debug, Deref)]
@@ -162,6 +166,24 @@ impl<'de> Deserialize<'de> for EncodableCrateVersionReq {
     }
 }
 
+impl<'de> Deserialize<'de> for EncodableRustVersion {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<EncodableRustVersion, D::Error> {
+        let s = String::deserialize(d)?;
+        let parts = s.split('.').collect::<Vec<_>>();
+        let valid = parts.len() <= 3
+            && parts
+                .iter()
+                .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()));
+        if valid {
+            Ok(EncodableRustVersion(s))
+        } else {
+            let value = de::Unexpected::Str(&s);
+            let expected = "a Rust version like `1.42` or `1.42.0`";
+            Err(de::Error::invalid_value(value, &expected))
+        }
+    }
+}
+
 impl<T: ?Sized> PartialEq<T> for EncodableCrateVersionReq
 where
     semver::VersionReq: PartialEq<T>,
@@ -240,3 +262,15 @@ fn feature_deserializes_for_valid_features() {
     assert!(json::from_str::<EncodableFeature>("\"a/a\"").is_ok());
     assert!(json::from_str::<EncodableFeature>("\"32-column-tables\"").is_ok());
 }
+
+#[test]
+fn rust_version_deserializes_for_valid_versions() {
+    use serde_json as json;
+
+    assert!(json::from_str::<EncodableRustVersion>("\"1.42\"").is_ok());
+    assert!(json::from_str::<EncodableRustVersion>("\"1.42.0\"").is_ok());
+    assert!(json::from_str::<EncodableRustVersion>("\"\"").is_err());
+    assert!(json::from_str::<EncodableRustVersion>("\"1.42.0.1\"").is_err());
+    assert!(json::from_str::<EncodableRustVersion>("\"1.42.0-nightly\"").is_err());
+    assert!(json::from_str::<EncodableRustVersion>("\"^1.42\"").is_err());
+}